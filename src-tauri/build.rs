@@ -1,3 +1,6 @@
 fn main() {
-    tauri_build::build()
+    // Only the desktop app is built with Tauri.
+    if std::env::var("CARGO_CFG_TARGET_ARCH").as_deref() != Ok("wasm32") {
+        tauri_build::build()
+    }
 }
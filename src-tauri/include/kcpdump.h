@@ -0,0 +1,31 @@
+/* C interface to the kcpdump-rs dissection engine. */
+#ifndef KCPDUMP_H
+#define KCPDUMP_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct KcpdumpCapture KcpdumpCapture;
+
+/* Opens a capture file. Returns NULL if the file cannot be read. */
+KcpdumpCapture *kcpdump_open(const char *path);
+
+/* Advances to the next packet: 1 if a packet was read, 0 at the end of the
+ * capture, -1 on a read error. */
+int kcpdump_next_packet(KcpdumpCapture *capture);
+
+/* Dissection tree of the current packet as a JSON array of
+ * {"protocol", "fields": [{"name", "value"}]} objects, or NULL if there is
+ * no current packet. Release it with kcpdump_string_free. */
+char *kcpdump_field_tree_json(const KcpdumpCapture *capture);
+
+void kcpdump_string_free(char *string);
+
+void kcpdump_close(KcpdumpCapture *capture);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* KCPDUMP_H */
@@ -0,0 +1,2132 @@
+//! Tauri Application
+//! The commands the frontend invokes and `run`, which starts the app. Not
+//! built for wasm32, where only the parsers are available.
+
+// Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
+use crate::arp::{ArpEntry, ArpTableBuilder};
+use crate::asn::{AsnDatabase, AsnReport, AutonomousSystem};
+use crate::bpf::CaptureFilter;
+use crate::can::{CanFrameRow, DbcDatabase};
+use crate::cap::{Capture, NetworkInterface, PcapPacket};
+use crate::database::DatabaseSession;
+use crate::detail::DetailNode;
+use crate::dhcp::{DhcpLease, DhcpTransaction};
+use crate::dissect::{DissectorRegistry, FieldInfo, PacketLayers};
+use crate::displayfilter::DisplayFilter;
+use crate::dns::{DnsTransaction, DnsTransactionBuilder};
+use crate::encrypteddns::EncryptedDnsUsage;
+use crate::error::KcpdumpError;
+use crate::expert::{ExpertItem, ExpertSummary};
+use crate::extract::ExtractedFile;
+use crate::filter::PacketFilter;
+use crate::flowgraph::FlowGraph;
+use crate::flows::{Conversation, ConversationLayer, FlowKey};
+use crate::geoip::{EndpointGeo, GeoEnricher, GeoIpDatabase, GeoMap};
+use crate::http::HttpTransaction;
+use crate::icmp::EchoPair;
+use crate::ids::IdsScan;
+use crate::inventory::Asset;
+use crate::iograph::{IoGraph, IoGraphSplit};
+use crate::live::{LiveCaptureOptions, LiveWindow};
+use crate::link::{LinkFrame, LinkTypeError};
+use crate::lorawan::{LoraWanFrameRow, SessionKeyConfig};
+use crate::merge::SplitMode;
+use crate::messagebus::MessageBusSession;
+use crate::multicast::MulticastReport;
+use crate::ndp::NeighborTable;
+use crate::oui::OuiDatabase;
+use crate::packet::{IPv4Packet, IPv6Packet, EtherType, IpProtocol, MacAddress, TcpOption, TcpSegment, UdpDatagram};
+use crate::packetlist::{FieldColumns, PacketListQuery, PacketPage};
+use crate::ptp::PtpOffsetSample;
+use crate::reassembly::StreamReassembler;
+use crate::recent::{RecentCapture, RecentCaptures, ViewState};
+use crate::resolve::{NameBatch, NameResolutionMode};
+use crate::sampling::Sampling;
+use crate::session::{CaptureId, CaptureInfo, LoadedCapture, Session, SessionSettings};
+use crate::snippet::{ByteRange, SnippetFormat};
+use crate::ssh::SshFingerprint;
+use crate::stats::{CaptureFileInfo, CaptureSummary};
+use tauri::Manager;
+use crate::text2pcap::HexImportOptions;
+use crate::timefmt::{TimeDisplayMode, TimeFormatter, Timestamp};
+use crate::timeline::ConversationTimeline;
+use crate::tls::TlsConnection;
+use crate::voip::{VoipCall, VoipCallDetail};
+use crate::watch::{AnalysisProfile, WatchId};
+use crate::wlan::WpaHandshake;
+use crate::wlaninventory::WlanInventory;
+use crate::zigbee::ZigbeeFrameRow;
+
+use crate::{
+    arp, asn, can, cap, database, dbexport, detail, dhcp, ecs, encrypteddns, expert, extract,
+    flowgraph, flows, geoip, http, icmp, ids, inventory, iograph, link, live, lorawan, merge,
+    messagebus, multicast, ndp, packetlist, parquet, ptp, report, sampling, snippet, ssh, stats,
+    tcpdump, text2pcap, timeline, tls, voip, watch, wlan, wlaninventory, zigbee,
+};
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct EthernetTuple {
+    eth_type: String,
+    source: String,
+    target: String,
+    source_vendor: Option<String>,  // 请求 resolve_vendors 时的 OUI 厂商
+    target_vendor: Option<String>,
+    vlan_ids: Vec<u16>,     // 由外到内的 VLAN ID，无标签时为空
+    timestamp: Timestamp,
+    time: String,   // 按会话时间显示模式格式化的时间
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct IpPacketTuple {
+    version: u8,        // 4 或 6
+    source_ip: String,
+    dest_ip: String,
+    source_name: Option<String>,    // 请求 resolve_names 时解析出的主机名
+    dest_name: Option<String>,
+    source_geo: Option<EndpointGeo>,    // 请求 geoip 时的位置与所属 AS
+    dest_geo: Option<EndpointGeo>,
+    protocol: u8,       // IPv6 为扩展头之后的 next header
+    ttl: u8,            // IPv6 为 hop limit
+    timestamp: Timestamp,
+    time: String,
+    total_length: u16,
+    traffic_class: u8,
+    flow_label: Option<u32>,
+    checksum_valid: Option<bool>,   // IPv4 首部及 TCP/UDP/ICMP 校验和均正确；无可校验内容时为空
+    vlan_ids: Vec<u16>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct TcpSegmentTuple {
+    source_ip: String,
+    dest_ip: String,
+    source_name: Option<String>,
+    dest_name: Option<String>,
+    source_port: u16,
+    dest_port: u16,
+    sequence_number: u32,
+    ack_number: u32,
+    flags: String,         // tcpdump 风格的标志位，例如 "S."
+    window_size: u16,
+    options: Vec<TcpOption>,
+    checksum_valid: Option<bool>,   // 含伪首部的校验和；分片等无法校验时为空
+    payload_length: usize,
+    vlan_ids: Vec<u16>,
+    timestamp: Timestamp,
+    time: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct UdpDatagramTuple {
+    source_ip: String,
+    dest_ip: String,
+    source_name: Option<String>,
+    dest_name: Option<String>,
+    source_port: u16,
+    dest_port: u16,
+    length: u16,
+    checksum: u16,
+    checksum_valid: Option<bool>,   // 未携带校验和（0）时为空
+    payload_length: usize,
+    vlan_ids: Vec<u16>,
+    timestamp: Timestamp,
+    time: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ArpPacketTuple {
+    number: usize,
+    hardware_type: u16,
+    protocol_type: u16,
+    operation: String,     // "request"、"reply" 等
+    sender_mac: String,
+    sender_ip: String,
+    target_mac: String,
+    target_ip: String,
+    gratuitous: bool,
+    vlan_ids: Vec<u16>,
+    timestamp: Timestamp,
+    time: String,
+}
+
+/// ARP packets of a capture together with the MAC/IP table learned from them.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ArpAnalysis {
+    packets: Vec<ArpPacketTuple>,
+    table: Vec<ArpEntry>,
+}
+
+/// Transport-layer details of one packet, tagged with its protocol.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[serde(tag = "protocol", rename_all = "camelCase")]
+enum TransportTuple {
+    Tcp(TcpSegmentTuple),
+    Udp(UdpDatagramTuple),
+}
+
+/// Lists the link-layer headers of the packets. With `resolve_vendors`, MAC
+/// addresses are annotated with their vendor from the loaded OUI database
+/// or the built-in table.
+#[tauri::command]
+async fn analyze_pcap(
+    file_path: String,
+    filter: Option<String>,
+    resolve_vendors: Option<bool>,
+    session: tauri::State<'_, Session>,
+    registry: tauri::State<'_, Arc<DissectorRegistry>>,
+) -> Result<Vec<EthernetTuple>, KcpdumpError> {
+    let mode = session.settings().time_display_mode;
+    let filter = compile_display_filter(filter.as_deref(), &registry)?;
+    let vendors = resolve_vendors
+        .unwrap_or_default()
+        .then(|| session.oui().unwrap_or_default());
+    collect_ethernet_tuples(&file_path, mode, filter.as_ref(), vendors.as_deref(), &registry).await
+}
+
+/// Lists the IPv4 and IPv6 packets, with IPv6 fields mapped onto their IPv4
+/// counterparts and `version` telling them apart. With `resolve_names`,
+/// addresses come with host names in the session's name resolution mode, and
+/// with `geoip`, with what the loaded GeoIP and ASN databases know of them.
+#[tauri::command]
+async fn analyze_ipv4_packets(
+    file_path: String,
+    filter: Option<String>,
+    resolve_names: Option<bool>,
+    geoip: Option<bool>,
+    session: tauri::State<'_, Session>,
+    registry: tauri::State<'_, Arc<DissectorRegistry>>,
+) -> Result<Vec<IpPacketTuple>, KcpdumpError> {
+    let mode = session.settings().time_display_mode;
+    let filter = compile_display_filter(filter.as_deref(), &registry)?;
+    let names = name_batch(resolve_names, &session);
+    let geo = geo_enricher(geoip, &session);
+    collect_ipv4_tuples(&file_path, mode, filter.as_ref(), names, geo, &registry).await
+}
+
+/// Lists the TCP segments carried over IPv4 with their ports, sequence
+/// numbers, flags, window and decoded options.
+#[tauri::command]
+async fn analyze_tcp_packets(
+    file_path: String,
+    filter: Option<String>,
+    resolve_names: Option<bool>,
+    session: tauri::State<'_, Session>,
+    registry: tauri::State<'_, Arc<DissectorRegistry>>,
+) -> Result<Vec<TcpSegmentTuple>, KcpdumpError> {
+    let mode = session.settings().time_display_mode;
+    let filter = compile_display_filter(filter.as_deref(), &registry)?;
+    let names = name_batch(resolve_names, &session);
+    collect_tcp_tuples(&file_path, mode, filter.as_ref(), names, &registry).await
+}
+
+/// Lists the TCP and UDP packets carried over IPv4 with their ports, so that
+/// every flow can be shown with its endpoints.
+#[tauri::command]
+async fn analyze_transport(
+    file_path: String,
+    filter: Option<String>,
+    resolve_names: Option<bool>,
+    session: tauri::State<'_, Session>,
+    registry: tauri::State<'_, Arc<DissectorRegistry>>,
+) -> Result<Vec<TransportTuple>, KcpdumpError> {
+    let mode = session.settings().time_display_mode;
+    let filter = compile_display_filter(filter.as_deref(), &registry)?;
+    let names = name_batch(resolve_names, &session);
+    collect_transport_tuples(&file_path, mode, filter.as_ref(), names, &registry).await
+}
+
+/// Lists the ARP packets of a capture and aggregates the IPv4 to MAC table
+/// they advertise; addresses that changed MAC hint at ARP spoofing.
+#[tauri::command]
+async fn analyze_arp(
+    file_path: String,
+    filter: Option<String>,
+    session: tauri::State<'_, Session>,
+    registry: tauri::State<'_, Arc<DissectorRegistry>>,
+) -> Result<ArpAnalysis, KcpdumpError> {
+    let mode = session.settings().time_display_mode;
+    let filter = compile_display_filter(filter.as_deref(), &registry)?;
+    collect_arp_analysis(&file_path, mode, filter.as_ref(), &registry).await
+}
+
+/// Pairs the DNS queries of a capture, over UDP or TCP port 53, with their
+/// responses, including the answers, TTLs and response code.
+#[tauri::command]
+async fn analyze_dns(
+    file_path: String,
+    filter: Option<String>,
+    registry: tauri::State<'_, Arc<DissectorRegistry>>,
+) -> Result<Vec<DnsTransaction>, KcpdumpError> {
+    let filter = compile_display_filter(filter.as_deref(), &registry)?;
+    collect_dns_transactions(&file_path, filter.as_ref(), &registry).await
+}
+
+/// Lists the HTTP/1.x transactions of the reassembled TCP streams with their
+/// method, URI, host, status, content type and body size.
+#[tauri::command]
+async fn analyze_http(
+    file_path: String,
+    filter: Option<String>,
+    registry: tauri::State<'_, Arc<DissectorRegistry>>,
+) -> Result<Vec<HttpTransaction>, KcpdumpError> {
+    let filter = compile_display_filter(filter.as_deref(), &registry)?;
+    collect_http_transactions(&file_path, filter.as_ref(), &registry).await
+}
+
+/// Saves the response body of one transaction to `path`. The id refers to the
+/// list returned by `analyze_http` for the same file and filter.
+#[tauri::command]
+async fn export_http_object(
+    file_path: String,
+    filter: Option<String>,
+    id: usize,
+    path: String,
+    registry: tauri::State<'_, Arc<DissectorRegistry>>,
+) -> Result<(), KcpdumpError> {
+    let filter = compile_display_filter(filter.as_deref(), &registry)?;
+    let transactions = collect_http_transactions(&file_path, filter.as_ref(), &registry).await?;
+    let transaction = transactions
+        .get(id)
+        .ok_or_else(|| KcpdumpError::InvalidInput(format!("No HTTP transaction {}", id)))?;
+    tokio::fs::write(&path, &transaction.body)
+        .await
+        .map_err(|e| KcpdumpError::io("Failed to write HTTP object", e))
+}
+
+/// Lists the TLS connections of a capture with their SNI, offered and
+/// selected versions and cipher suites, certificates and JA3/JA3S
+/// fingerprints.
+#[tauri::command]
+async fn analyze_tls(
+    file_path: String,
+    filter: Option<String>,
+    registry: tauri::State<'_, Arc<DissectorRegistry>>,
+) -> Result<Vec<TlsConnection>, KcpdumpError> {
+    let filter = compile_display_filter(filter.as_deref(), &registry)?;
+    collect_tls_connections(&file_path, filter.as_ref(), &registry).await
+}
+
+#[tauri::command]
+fn get_session_settings(session: tauri::State<'_, Session>) -> SessionSettings {
+    session.settings()
+}
+
+#[tauri::command]
+fn set_time_display_mode(mode: TimeDisplayMode, session: tauri::State<'_, Session>) {
+    session.set_time_display_mode(mode);
+}
+
+/// Selects where commands asked to resolve names look addresses up.
+#[tauri::command]
+fn set_name_resolution_mode(mode: NameResolutionMode, session: tauri::State<'_, Session>) {
+    session.set_name_resolution(mode);
+}
+
+/// Resolves names from the hosts file at `path` instead of the system one.
+/// Returns the number of addresses it names.
+#[tauri::command]
+fn load_hosts_file(
+    path: String,
+    session: tauri::State<'_, Session>,
+) -> Result<usize, KcpdumpError> {
+    session.resolver().load_hosts(&path)
+}
+
+#[tauri::command]
+fn list_filter_fields(registry: tauri::State<'_, Arc<DissectorRegistry>>) -> Vec<FieldInfo> {
+    registry.fields()
+}
+
+/// Renders the filtered packets as tcpdump-style lines. The text is returned
+/// for the clipboard and, if `output_path` is given, also written to that file.
+#[tauri::command]
+async fn export_tcpdump_text(
+    file_path: String,
+    output_path: Option<String>,
+    filter: Option<PacketFilter>,
+    session: tauri::State<'_, Session>,
+) -> Result<String, KcpdumpError> {
+    let mode = session.settings().time_display_mode;
+    let lines = tcpdump::export_lines(&file_path, &filter.unwrap_or_default(), mode).await?;
+    let mut text = lines.join("\n");
+    text.push('\n');
+    if let Some(output_path) = output_path {
+        tokio::fs::write(&output_path, &text)
+            .await
+            .map_err(|e| KcpdumpError::io("Failed to write file", e))?;
+    }
+    Ok(text)
+}
+
+/// Returns ladder diagram events for the filtered packets, optionally
+/// restricted to a single conversation.
+#[tauri::command]
+async fn get_flow_graph(
+    file_path: String,
+    filter: Option<PacketFilter>,
+    conversation: Option<FlowKey>,
+    session: tauri::State<'_, Session>,
+) -> Result<FlowGraph, KcpdumpError> {
+    let mode = session.settings().time_display_mode;
+    flowgraph::build_flow_graph(&file_path, &filter.unwrap_or_default(), conversation, mode)
+        .await
+        .map_err(KcpdumpError::from)
+}
+
+/// Loads a capture into the session workspace and records it as recently opened.
+/// Packet list rows are emitted as `capture-load` events with the percentage
+/// read while the file loads; afterwards pages come from `get_packets`. Only
+/// packets matching the optional `bpf` capture filter are loaded.
+#[tauri::command]
+async fn open_capture(
+    file_path: String,
+    bpf: Option<String>,
+    app: tauri::AppHandle,
+    session: tauri::State<'_, Session>,
+    recent: tauri::State<'_, RecentCaptures>,
+) -> Result<CaptureInfo, KcpdumpError> {
+    use tauri::Emitter;
+
+    let filter = bpf.as_deref().map(CaptureFilter::compile).transpose()?;
+    let id = session.next_capture_id();
+    let mode = session.settings().time_display_mode;
+    let capture = LoadedCapture::load_with_progress(id, &file_path, mode, filter, |event| {
+        let _ = app.emit("capture-load", event);
+    })
+    .await?;
+    recent
+        .touch(&file_path)
+        .map_err(|e| KcpdumpError::io("Failed to update recent captures", e))?;
+    Ok(session.insert_capture(capture))
+}
+
+/// Returns up to `count` packet list rows of an open capture, starting at
+/// row `offset`.
+#[tauri::command]
+async fn get_packets(
+    capture_id: CaptureId,
+    offset: usize,
+    count: usize,
+    session: tauri::State<'_, Session>,
+) -> Result<PacketPage, KcpdumpError> {
+    let capture = session
+        .capture(capture_id)
+        .ok_or(KcpdumpError::CaptureNotFound { id: capture_id })?;
+    let mode = session.settings().time_display_mode;
+    spawn_analysis(move || packetlist::page(&capture, offset, count, mode, None)).await
+}
+
+/// Opens a sampled subset of an open capture as a new capture, so that
+/// analyses can run on it.
+#[tauri::command]
+async fn sample_capture(
+    capture_id: CaptureId,
+    sampling: Sampling,
+    session: tauri::State<'_, Session>,
+) -> Result<CaptureInfo, KcpdumpError> {
+    let capture = session
+        .capture(capture_id)
+        .ok_or(KcpdumpError::CaptureNotFound { id: capture_id })?;
+    let id = session.next_capture_id();
+    let sampled =
+        spawn_analysis(move || sampling::sampled_capture(id, &capture, &sampling)).await?;
+    Ok(session.insert_capture(sampled))
+}
+
+#[tauri::command]
+fn close_capture(capture_id: CaptureId, session: tauri::State<'_, Session>) -> bool {
+    session.remove_capture(capture_id) || session.remove_live_capture(capture_id)
+}
+
+#[tauri::command]
+fn list_captures(session: tauri::State<'_, Session>) -> Vec<CaptureInfo> {
+    session
+        .captures()
+        .iter()
+        .map(|capture| capture.info())
+        .collect()
+}
+
+#[tauri::command]
+fn list_recent_captures(recent: tauri::State<'_, RecentCaptures>) -> Vec<RecentCapture> {
+    recent.list()
+}
+
+/// Stores the filter, column layout and bookmarks of a capture so they can be restored later.
+#[tauri::command]
+fn save_capture_state(
+    file_path: String,
+    state: ViewState,
+    recent: tauri::State<'_, RecentCaptures>,
+) -> Result<(), KcpdumpError> {
+    recent
+        .save_state(&file_path, state)
+        .map_err(|e| KcpdumpError::io("Failed to save capture state", e))
+}
+
+#[tauri::command]
+fn remove_recent_capture(
+    file_path: String,
+    recent: tauri::State<'_, RecentCaptures>,
+) -> Result<bool, KcpdumpError> {
+    recent
+        .remove(&file_path)
+        .map_err(|e| KcpdumpError::io("Failed to update recent captures", e))
+}
+
+/// Restored Capture
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct RestoredCapture {
+    capture: CaptureInfo,
+    state: ViewState,
+}
+
+/// Reopens a recent capture together with its saved view state.
+#[tauri::command]
+async fn restore_recent_capture(
+    file_path: String,
+    app: tauri::AppHandle,
+    session: tauri::State<'_, Session>,
+    recent: tauri::State<'_, RecentCaptures>,
+) -> Result<RestoredCapture, KcpdumpError> {
+    let state = recent
+        .get(&file_path)
+        .map(|entry| entry.state)
+        .unwrap_or_default();
+    let capture = open_capture(file_path, None, app, session, recent).await?;
+    Ok(RestoredCapture { capture, state })
+}
+
+/// Converts a pasted hex dump into a pcap file and opens it in the workspace.
+#[tauri::command]
+async fn import_hex_dump(
+    text: String,
+    output_path: String,
+    options: Option<HexImportOptions>,
+    app: tauri::AppHandle,
+    session: tauri::State<'_, Session>,
+    recent: tauri::State<'_, RecentCaptures>,
+) -> Result<CaptureInfo, KcpdumpError> {
+    text2pcap::import_hex_dump(&text, &output_path, &options.unwrap_or_default())?;
+    open_capture(output_path, None, app, session, recent).await
+}
+
+/// Exports the bytes of a packet, or of a range within it such as a single field,
+/// as a code snippet.
+#[tauri::command]
+async fn export_packet_bytes(
+    capture_id: CaptureId,
+    number: usize,
+    format: SnippetFormat,
+    range: Option<ByteRange>,
+    session: tauri::State<'_, Session>,
+) -> Result<String, KcpdumpError> {
+    let (packet, _) = session_packet(&session, capture_id, number).await?;
+    let bytes = match range {
+        Some(range) => range.slice(&packet.data)?,
+        None => &packet.data,
+    };
+    Ok(snippet::format_bytes(bytes, format, &format!("pkt{}", number)))
+}
+
+/// Packet Bytes
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PacketBytes {
+    data: Vec<u8>,
+    /// Offset, hex and ASCII columns of `data`, when requested.
+    hex_dump: Option<String>,
+}
+
+/// Returns the raw bytes of one packet for the bytes pane, optionally
+/// rendered as a hex dump whose offsets match those of `get_packet_detail`.
+#[tauri::command]
+async fn get_packet_bytes(
+    capture_id: CaptureId,
+    number: usize,
+    hex_dump: Option<bool>,
+    session: tauri::State<'_, Session>,
+) -> Result<PacketBytes, KcpdumpError> {
+    let (packet, _) = session_packet(&session, capture_id, number).await?;
+    Ok(PacketBytes {
+        hex_dump: hex_dump
+            .unwrap_or(false)
+            .then(|| snippet::hex_dump(&packet.data)),
+        data: packet.data,
+    })
+}
+
+/// Returns the decode tree of one packet for the packet details pane, with the
+/// byte range of every protocol and field for highlighting in the hex view.
+#[tauri::command]
+async fn get_packet_detail(
+    capture_id: CaptureId,
+    number: usize,
+    session: tauri::State<'_, Session>,
+    registry: tauri::State<'_, Arc<DissectorRegistry>>,
+) -> Result<Vec<DetailNode>, KcpdumpError> {
+    let (packet, link_type) = session_packet(&session, capture_id, number).await?;
+    let layers = PacketLayers::decode_link(number, link_type, &packet);
+    Ok(detail::packet_detail(&layers, &registry))
+}
+
+/// Returns a page of packet list rows for one capture, or for all open captures
+/// interleaved by time when no capture is selected, sorted by the requested column.
+#[tauri::command]
+async fn get_packet_list(
+    query: PacketListQuery,
+    session: tauri::State<'_, Session>,
+    registry: tauri::State<'_, Arc<DissectorRegistry>>,
+) -> Result<PacketPage, KcpdumpError> {
+    let mode = session.settings().time_display_mode;
+    let registry = Arc::clone(&registry);
+    if let Some(capture) = query.capture_id.and_then(|id| session.capture(id))
+        && query.is_capture_order()
+    {
+        let count = query.limit.unwrap_or(usize::MAX);
+        return spawn_analysis(move || {
+            let columns = FieldColumns::new(&registry, query.columns)?;
+            packetlist::page(&capture, query.offset, count, mode, Some(&columns))
+        })
+        .await;
+    }
+    let captures = session.select(query.capture_id)?;
+    spawn_analysis(move || {
+        let columns = FieldColumns::new(&registry, query.columns.clone())?;
+        let mut rows = packetlist::build_rows(&captures, &query.filter, mode, Some(&columns))?;
+        packetlist::sort_rows(&mut rows, query.sort);
+        Ok(packetlist::paginate(rows, query.offset, query.limit))
+    })
+    .await
+}
+
+/// Returns headline statistics for one capture, or for all open captures,
+/// to populate the dashboard when a file is opened.
+#[tauri::command]
+async fn capture_summary(
+    capture_id: Option<CaptureId>,
+    session: tauri::State<'_, Session>,
+) -> Result<CaptureSummary, KcpdumpError> {
+    let captures = session.select(capture_id)?;
+    spawn_analysis(move || stats::capture_summary(&captures)).await
+}
+
+/// Returns the file-level facts of a capture, such as its link type, packet
+/// count and time span, without loading or dissecting it.
+#[tauri::command]
+async fn capture_info(path: String) -> Result<CaptureFileInfo, KcpdumpError> {
+    stats::capture_file_info(&path).await
+}
+
+/// Returns aggregated expert information for one capture, or for all open captures.
+#[tauri::command]
+async fn get_expert_info(
+    capture_id: Option<CaptureId>,
+    session: tauri::State<'_, Session>,
+) -> Result<ExpertSummary, KcpdumpError> {
+    let captures = session.select(capture_id)?;
+    spawn_analysis(move || expert::summarize_captures(&captures)).await
+}
+
+/// Returns every expert finding of one capture with the frame number it was
+/// reported for, in capture order.
+#[tauri::command]
+async fn get_expert_events(
+    capture_id: CaptureId,
+    session: tauri::State<'_, Session>,
+) -> Result<Vec<ExpertItem>, KcpdumpError> {
+    let capture = session
+        .capture(capture_id)
+        .ok_or(KcpdumpError::CaptureNotFound { id: capture_id })?;
+    spawn_analysis(move || expert::capture_events(&capture)).await
+}
+
+/// Lists the conversations of one capture, or of all open captures, between
+/// MAC addresses, IP addresses or transport endpoints, with per-direction
+/// packet and byte counts. With `geoip`, IP addresses come with what the
+/// loaded GeoIP and ASN databases know of them.
+#[tauri::command]
+async fn get_conversations(
+    capture_id: Option<CaptureId>,
+    layer: ConversationLayer,
+    geoip: Option<bool>,
+    session: tauri::State<'_, Session>,
+) -> Result<Vec<Conversation>, KcpdumpError> {
+    let captures = session.select(capture_id)?;
+    let mut conversations = spawn_analysis(move || flows::conversations(&captures, layer)).await?;
+    if let Some(mut geo) = geo_enricher(geoip, &session) {
+        for conversation in &mut conversations {
+            conversation.geo_a = geo.lookup_str(&conversation.address_a);
+            conversation.geo_b = geo.lookup_str(&conversation.address_b);
+        }
+    }
+    Ok(conversations)
+}
+
+/// Lists the HTTP objects and carved files found in the TCP streams of one
+/// capture, or of all open captures.
+#[tauri::command]
+async fn extract_files(
+    capture_id: Option<CaptureId>,
+    session: tauri::State<'_, Session>,
+) -> Result<Vec<ExtractedFile>, KcpdumpError> {
+    let captures = session.select(capture_id)?;
+    spawn_analysis(move || extract::extract_files(&captures)).await
+}
+
+/// Saves the extracted files with the given ids into `directory` and returns
+/// the paths written. Ids refer to the list returned by `extract_files` for
+/// the same selection.
+#[tauri::command]
+async fn save_extracted_files(
+    capture_id: Option<CaptureId>,
+    ids: Vec<usize>,
+    directory: String,
+    session: tauri::State<'_, Session>,
+) -> Result<Vec<String>, KcpdumpError> {
+    let captures = session.select(capture_id)?;
+    spawn_analysis(move || {
+        let files = extract::extract_files(&captures)?;
+        extract::save_files(&files, &ids, std::path::Path::new(&directory))
+            .map_err(|e| KcpdumpError::io("Failed to save extracted files", e))
+    })
+    .await
+}
+
+/// Lists the SIP calls of one capture, or of all open captures, with their
+/// state and RTP stream quality.
+#[tauri::command]
+async fn voip_calls(
+    capture_id: Option<CaptureId>,
+    session: tauri::State<'_, Session>,
+) -> Result<Vec<VoipCall>, KcpdumpError> {
+    let captures = session.select(capture_id)?;
+    let mode = session.settings().time_display_mode;
+    let calls = spawn_analysis(move || voip::analyze_calls(&captures, mode)).await?;
+    Ok(calls.into_iter().map(|detail| detail.call).collect())
+}
+
+/// Returns the SIP ladder and RTP stream statistics of the call with `call_id`.
+#[tauri::command]
+async fn get_voip_call(
+    capture_id: Option<CaptureId>,
+    call_id: String,
+    session: tauri::State<'_, Session>,
+) -> Result<VoipCallDetail, KcpdumpError> {
+    let captures = session.select(capture_id)?;
+    let mode = session.settings().time_display_mode;
+    spawn_analysis(move || voip::analyze_calls(&captures, mode))
+        .await?
+        .into_iter()
+        .find(|detail| detail.call.call_id == call_id)
+        .ok_or_else(|| KcpdumpError::InvalidInput(format!("No call with id {}", call_id)))
+}
+
+/// Loads the MaxMind database used to locate addresses, replacing any previous
+/// one, or unloads it without a path.
+#[tauri::command]
+fn load_geoip_database(
+    path: Option<String>,
+    session: tauri::State<'_, Session>,
+) -> Result<(), KcpdumpError> {
+    session.set_geoip(path.as_deref().map(GeoIpDatabase::open).transpose()?);
+    Ok(())
+}
+
+/// Returns traffic volumes per country and per coordinate for the world map view.
+#[tauri::command]
+async fn get_geo_map(
+    capture_id: Option<CaptureId>,
+    session: tauri::State<'_, Session>,
+) -> Result<GeoMap, KcpdumpError> {
+    let database = session
+        .geoip()
+        .ok_or_else(|| KcpdumpError::Other("No GeoIP database loaded".to_string()))?;
+    let captures = session.select(capture_id)?;
+    spawn_analysis(move || geoip::geo_map(&captures, |address| database.lookup(address))).await
+}
+
+/// Loads a CSV of OUI assignments, such as the IEEE `oui.csv` export, whose
+/// vendors take precedence over the built-in table, replacing any previous one.
+#[tauri::command]
+fn load_oui_database(path: String, session: tauri::State<'_, Session>) -> Result<usize, KcpdumpError> {
+    let database = OuiDatabase::open(&path)?;
+    let entries = database.len();
+    session.set_oui(database);
+    Ok(entries)
+}
+
+/// Loads the MaxMind ASN database used to name the networks owning addresses,
+/// replacing any previous one, or unloads it without a path.
+#[tauri::command]
+fn load_asn_database(
+    path: Option<String>,
+    session: tauri::State<'_, Session>,
+) -> Result<(), KcpdumpError> {
+    session.set_asn(path.as_deref().map(AsnDatabase::open).transpose()?);
+    Ok(())
+}
+
+/// Returns endpoint traffic per autonomous system.
+#[tauri::command]
+async fn get_asn_report(
+    capture_id: Option<CaptureId>,
+    session: tauri::State<'_, Session>,
+) -> Result<AsnReport, KcpdumpError> {
+    let database = session
+        .asn()
+        .ok_or_else(|| KcpdumpError::Other("No ASN database loaded".to_string()))?;
+    let captures = session.select(capture_id)?;
+    spawn_analysis(move || asn::asn_report(&captures, |address| database.lookup(address))).await
+}
+
+/// Looks up the networks owning `addresses`, for annotating endpoint and flow
+/// tables. Addresses without a known owner are left out.
+#[tauri::command]
+fn lookup_asns(
+    addresses: Vec<String>,
+    session: tauri::State<'_, Session>,
+) -> Result<HashMap<String, AutonomousSystem>, KcpdumpError> {
+    let database = session
+        .asn()
+        .ok_or_else(|| KcpdumpError::Other("No ASN database loaded".to_string()))?;
+    let addresses = addresses
+        .iter()
+        .map(|address| {
+            address
+                .parse()
+                .map_err(|_| KcpdumpError::InvalidInput(format!("Invalid address: {}", address)))
+        })
+        .collect::<Result<Vec<IpAddr>, KcpdumpError>>()?;
+    Ok(asn::lookup_all(&addresses, |address| database.lookup(address))
+        .into_iter()
+        .map(|(address, system)| (address.to_string(), system))
+        .collect())
+}
+
+/// Returns the packets and bytes over time of one conversation, with markers for
+/// handshakes, retransmission bursts and connection teardown.
+#[tauri::command]
+async fn get_conversation_timeline(
+    capture_id: CaptureId,
+    conversation: FlowKey,
+    buckets: Option<usize>,
+    session: tauri::State<'_, Session>,
+) -> Result<ConversationTimeline, KcpdumpError> {
+    let capture = session
+        .capture(capture_id)
+        .ok_or(KcpdumpError::CaptureNotFound { id: capture_id })?;
+    let buckets = buckets.unwrap_or(timeline::DEFAULT_BUCKETS);
+    spawn_analysis(move || timeline::conversation_timeline(&capture, conversation, buckets)).await
+}
+
+/// Returns packets/s and bytes/s of one capture in intervals of `interval_ms`,
+/// for the packets matching the optional display filter, and optionally one
+/// series per protocol or conversation, for drawing bandwidth over time.
+#[tauri::command]
+async fn get_io_graph(
+    capture_id: CaptureId,
+    interval_ms: u64,
+    filter: Option<String>,
+    split: Option<IoGraphSplit>,
+    session: tauri::State<'_, Session>,
+    registry: tauri::State<'_, Arc<DissectorRegistry>>,
+) -> Result<IoGraph, KcpdumpError> {
+    let capture = session
+        .capture(capture_id)
+        .ok_or(KcpdumpError::CaptureNotFound { id: capture_id })?;
+    let filter = compile_display_filter(filter.as_deref(), &registry)?;
+    let interval_usec = i64::try_from(interval_ms.saturating_mul(1000)).unwrap_or(i64::MAX);
+    let registry = Arc::clone(&registry);
+    spawn_analysis(move || {
+        iograph::io_graph(&capture, interval_usec, split, filter.as_ref(), &registry)
+    })
+    .await
+}
+
+/// Returns the DHCP leases observed in one capture, or in all open captures,
+/// to answer which client had an address at a given time.
+#[tauri::command]
+async fn get_dhcp_leases(
+    capture_id: Option<CaptureId>,
+    session: tauri::State<'_, Session>,
+) -> Result<Vec<DhcpLease>, KcpdumpError> {
+    let captures = session.select(capture_id)?;
+    spawn_analysis(move || dhcp::lease_table(&captures)).await
+}
+
+/// Groups the DHCP messages of one capture, or of all open captures, into
+/// transactions per client MAC address, e.g. DISCOVER/OFFER/REQUEST/ACK.
+#[tauri::command]
+async fn get_dhcp_transactions(
+    capture_id: Option<CaptureId>,
+    session: tauri::State<'_, Session>,
+) -> Result<Vec<DhcpTransaction>, KcpdumpError> {
+    let captures = session.select(capture_id)?;
+    spawn_analysis(move || dhcp::transactions(&captures)).await
+}
+
+/// Returns the IPv6 neighbors, routers and duplicate address conflicts learned
+/// from neighbor discovery traffic.
+#[tauri::command]
+async fn get_neighbor_table(
+    capture_id: Option<CaptureId>,
+    session: tauri::State<'_, Session>,
+) -> Result<NeighborTable, KcpdumpError> {
+    let captures = session.select(capture_id)?;
+    spawn_analysis(move || ndp::neighbor_table(&captures)).await
+}
+
+/// Returns the local hosts of the captures with their MAC vendor, names,
+/// likely operating system, services and peers.
+#[tauri::command]
+async fn get_asset_inventory(
+    capture_id: Option<CaptureId>,
+    session: tauri::State<'_, Session>,
+) -> Result<Vec<Asset>, KcpdumpError> {
+    let captures = session.select(capture_id)?;
+    spawn_analysis(move || inventory::asset_inventory(&captures)).await
+}
+
+/// Returns the IPv4 to MAC bindings seen in ARP traffic, with the history of
+/// addresses that moved to another MAC.
+#[tauri::command]
+async fn get_arp_table(
+    capture_id: Option<CaptureId>,
+    session: tauri::State<'_, Session>,
+) -> Result<Vec<ArpEntry>, KcpdumpError> {
+    let captures = session.select(capture_id)?;
+    spawn_analysis(move || arp::arp_table(&captures)).await
+}
+
+/// Pairs the ICMP and ICMPv6 echo requests of the captures with their replies
+/// and round-trip times.
+#[tauri::command]
+async fn get_icmp_echo_pairs(
+    capture_id: Option<CaptureId>,
+    session: tauri::State<'_, Session>,
+) -> Result<Vec<EchoPair>, KcpdumpError> {
+    let captures = session.select(capture_id)?;
+    spawn_analysis(move || icmp::echo_pairs(&captures)).await
+}
+
+/// Lists the WPA 4-way handshakes found in an 802.11 capture, per access point
+/// and client.
+#[tauri::command]
+async fn wpa_handshakes(
+    capture_id: CaptureId,
+    session: tauri::State<'_, Session>,
+) -> Result<Vec<WpaHandshake>, KcpdumpError> {
+    let capture = session
+        .capture(capture_id)
+        .ok_or(KcpdumpError::CaptureNotFound { id: capture_id })?;
+    spawn_analysis(move || Ok(wlan::track_handshakes(&capture)?.handshakes())).await
+}
+
+/// Lists the access points and clients of an 802.11 capture with their
+/// signal strength over time.
+#[tauri::command]
+async fn wlan_inventory(
+    capture_id: CaptureId,
+    session: tauri::State<'_, Session>,
+) -> Result<WlanInventory, KcpdumpError> {
+    let capture = session
+        .capture(capture_id)
+        .ok_or(KcpdumpError::CaptureNotFound { id: capture_id })?;
+    spawn_analysis(move || wlaninventory::wlan_inventory(&capture)).await
+}
+
+/// Writes the handshakes of an 802.11 capture as hashcat mode 22000 lines for
+/// password audits. Returns the number of lines written.
+#[tauri::command]
+async fn export_wpa_handshakes(
+    capture_id: CaptureId,
+    output_path: String,
+    complete_only: Option<bool>,
+    session: tauri::State<'_, Session>,
+) -> Result<usize, KcpdumpError> {
+    let capture = session
+        .capture(capture_id)
+        .ok_or(KcpdumpError::CaptureNotFound { id: capture_id })?;
+    let complete_only = complete_only.unwrap_or(false);
+    let lines =
+        spawn_analysis(move || Ok(wlan::track_handshakes(&capture)?.hashcat_lines(complete_only)))
+            .await?;
+    if lines.is_empty() {
+        return Err(KcpdumpError::Other("No crackable handshakes in capture".to_string()));
+    }
+    let mut text = lines.join("\n");
+    text.push('\n');
+    tokio::fs::write(&output_path, &text)
+        .await
+        .map_err(|e| KcpdumpError::io("Failed to write file", e))?;
+    Ok(lines.len())
+}
+
+/// Writes packets, flows, DNS and HTTP transactions and expert alerts of the
+/// captures to a SQLite database at `output_path`, replacing the file.
+#[tauri::command]
+async fn export_sqlite(
+    capture_id: Option<CaptureId>,
+    output_path: String,
+    session: tauri::State<'_, Session>,
+) -> Result<(), KcpdumpError> {
+    let captures = session.select(capture_id)?;
+    tokio::task::spawn_blocking(move || dbexport::export_database(&captures, &output_path))
+        .await
+        .map_err(|e| KcpdumpError::Other(format!("SQLite export failed: {}", e)))?
+}
+
+/// Writes the packet summaries, or with `table` "flows" the flow records,
+/// of the captures to a Parquet file at `output_path`.
+#[tauri::command]
+async fn export_parquet(
+    capture_id: Option<CaptureId>,
+    table: String,
+    output_path: String,
+    session: tauri::State<'_, Session>,
+) -> Result<(), KcpdumpError> {
+    let captures = session.select(capture_id)?;
+    let export = match table.as_str() {
+        "packets" => parquet::export_packets,
+        "flows" => parquet::export_flows,
+        _ => {
+            return Err(KcpdumpError::InvalidInput(format!(
+                "Unknown table: {}",
+                table
+            )));
+        }
+    };
+    tokio::task::spawn_blocking(move || export(&captures, &output_path))
+        .await
+        .map_err(|e| KcpdumpError::Other(format!("Parquet export failed: {}", e)))?
+}
+
+/// Writes an HTML report of one capture, or of all open captures, to
+/// `output_path`, replacing the file.
+#[tauri::command]
+async fn export_report(
+    capture_id: Option<CaptureId>,
+    title: String,
+    output_path: String,
+    session: tauri::State<'_, Session>,
+    registry: tauri::State<'_, Arc<DissectorRegistry>>,
+) -> Result<(), KcpdumpError> {
+    let captures = session.select(capture_id)?;
+    let registry = Arc::clone(&registry);
+    let html = spawn_analysis(move || report::render_html(&captures, &registry, &title)).await?;
+    tokio::fs::write(&output_path, &html)
+        .await
+        .map_err(|e| KcpdumpError::io("Failed to write file", e))
+}
+
+/// Writes flows, DNS and HTTP transactions and TLS handshakes of the
+/// captures as Elastic Common Schema documents in `_bulk` format, indexed
+/// into `index`.
+#[tauri::command]
+async fn export_ecs(
+    capture_id: Option<CaptureId>,
+    index: String,
+    output_path: String,
+    session: tauri::State<'_, Session>,
+) -> Result<usize, KcpdumpError> {
+    let captures = session.select(capture_id)?;
+    let documents = spawn_analysis(move || ecs::ecs_documents(&captures)).await?;
+    tokio::fs::write(&output_path, ecs::bulk_ndjson(&documents, &index))
+        .await
+        .map_err(|e| KcpdumpError::io("Failed to write file", e))?;
+    Ok(documents.len())
+}
+
+/// Writes the asset inventory of the captures to `output_path` as "json" or
+/// "csv".
+#[tauri::command]
+async fn export_asset_inventory(
+    capture_id: Option<CaptureId>,
+    format: String,
+    output_path: String,
+    session: tauri::State<'_, Session>,
+) -> Result<(), KcpdumpError> {
+    let captures = session.select(capture_id)?;
+    let assets = spawn_analysis(move || inventory::asset_inventory(&captures)).await?;
+    let contents = match format.as_str() {
+        "json" => {
+            serde_json::to_string_pretty(&assets).map_err(|e| KcpdumpError::Other(e.to_string()))?
+        }
+        "csv" => inventory::inventory_csv(&assets),
+        _ => {
+            return Err(KcpdumpError::InvalidInput(format!(
+                "Unknown format: {}",
+                format
+            )));
+        }
+    };
+    tokio::fs::write(&output_path, contents)
+        .await
+        .map_err(|e| KcpdumpError::io("Failed to write file", e))
+}
+
+/// Writes a sampled subset of an open capture as a pcap file to `output_path`.
+#[tauri::command]
+async fn export_sampled_capture(
+    capture_id: CaptureId,
+    sampling: Sampling,
+    output_path: String,
+    session: tauri::State<'_, Session>,
+) -> Result<(), KcpdumpError> {
+    let capture = session
+        .capture(capture_id)
+        .ok_or(KcpdumpError::CaptureNotFound { id: capture_id })?;
+    spawn_analysis(move || sampling::export_sampled_pcap(&capture, &sampling, &output_path)).await
+}
+
+/// Interleaves the packets of several capture files in timestamp order into
+/// one pcap file at `output`, returning the number of packets written.
+#[tauri::command]
+async fn merge_captures(paths: Vec<String>, output: String) -> Result<usize, KcpdumpError> {
+    merge::merge_captures(&paths, &output).await
+}
+
+/// Splits a capture file into numbered pcap files by packet count, time span
+/// or size, returning the paths written.
+#[tauri::command]
+async fn split_capture(path: String, by: SplitMode) -> Result<Vec<String>, KcpdumpError> {
+    merge::split_capture(&path, by).await
+}
+
+/// Loads the DBC file used to decode CAN signals, replacing any previous one.
+#[tauri::command]
+fn load_dbc_file(path: String, session: tauri::State<'_, Session>) -> Result<(), KcpdumpError> {
+    session.set_dbc(DbcDatabase::open(&path)?);
+    Ok(())
+}
+
+/// Lists the CAN frames of one capture, or of all open SocketCAN captures,
+/// with signal values if a DBC file is loaded.
+#[tauri::command]
+async fn get_can_frames(
+    capture_id: Option<CaptureId>,
+    session: tauri::State<'_, Session>,
+) -> Result<Vec<CanFrameRow>, KcpdumpError> {
+    let captures = session.select(capture_id)?;
+    let database = session.dbc();
+    spawn_analysis(move || can::can_frames(&captures, database.as_deref())).await
+}
+
+/// Sets the Zigbee network key used to decrypt NWK payloads, given as hex.
+/// `None` forgets the key.
+#[tauri::command]
+fn set_zigbee_network_key(
+    key: Option<String>,
+    session: tauri::State<'_, Session>,
+) -> Result<(), KcpdumpError> {
+    let key = key.map(|key| zigbee::parse_network_key(&key)).transpose()?;
+    session.set_zigbee_network_key(key);
+    Ok(())
+}
+
+/// Lists the Zigbee frames of one capture, or of all open 802.15.4 captures,
+/// decrypting them when a network key is set.
+#[tauri::command]
+async fn get_zigbee_frames(
+    capture_id: Option<CaptureId>,
+    session: tauri::State<'_, Session>,
+) -> Result<Vec<ZigbeeFrameRow>, KcpdumpError> {
+    let captures = session.select(capture_id)?;
+    let key = session.zigbee_network_key();
+    spawn_analysis(move || zigbee::zigbee_frames(&captures, key.as_ref())).await
+}
+
+/// Sets the LoRaWAN session keys used to decrypt frame payloads, replacing
+/// any keys set before.
+#[tauri::command]
+fn set_lorawan_keys(
+    keys: Vec<SessionKeyConfig>,
+    session: tauri::State<'_, Session>,
+) -> Result<(), KcpdumpError> {
+    session.set_lorawan_keys(lorawan::parse_session_keys(&keys)?);
+    Ok(())
+}
+
+/// Lists the LoRaWAN frames of one capture, or of all open captures, from
+/// packet forwarder traffic and LoRaTap captures.
+#[tauri::command]
+async fn get_lorawan_frames(
+    capture_id: Option<CaptureId>,
+    session: tauri::State<'_, Session>,
+) -> Result<Vec<LoraWanFrameRow>, KcpdumpError> {
+    let captures = session.select(capture_id)?;
+    let keys = session.lorawan_keys();
+    spawn_analysis(move || lorawan::lorawan_frames(&captures, &keys)).await
+}
+
+/// Estimates clock offset and path delay from the PTP delay request-response
+/// exchanges of one capture, or of every open capture.
+#[tauri::command]
+async fn get_ptp_offsets(
+    capture_id: Option<CaptureId>,
+    session: tauri::State<'_, Session>,
+) -> Result<Vec<PtpOffsetSample>, KcpdumpError> {
+    let captures = session.select(capture_id)?;
+    spawn_analysis(move || ptp::ptp_offsets(&captures)).await
+}
+
+/// Lists hosts that resolve names over DNS over HTTPS, TLS or QUIC, which
+/// classic DNS statistics do not see.
+#[tauri::command]
+async fn get_encrypted_dns(
+    capture_id: Option<CaptureId>,
+    session: tauri::State<'_, Session>,
+) -> Result<Vec<EncryptedDnsUsage>, KcpdumpError> {
+    let captures = session.select(capture_id)?;
+    spawn_analysis(move || encrypteddns::encrypted_dns(&captures)).await
+}
+
+/// Lists the SSH connections of the captures with the HASSH and HASSHServer
+/// fingerprints of their key exchange.
+#[tauri::command]
+async fn get_ssh_fingerprints(
+    capture_id: Option<CaptureId>,
+    session: tauri::State<'_, Session>,
+) -> Result<Vec<SshFingerprint>, KcpdumpError> {
+    let captures = session.select(capture_id)?;
+    spawn_analysis(move || ssh::ssh_fingerprints(&captures)).await
+}
+
+/// Lists the MySQL, PostgreSQL and TDS connections of the captures with
+/// their logins, statements, response times, errors and row counts.
+#[tauri::command]
+async fn get_database_sessions(
+    capture_id: Option<CaptureId>,
+    session: tauri::State<'_, Session>,
+) -> Result<Vec<DatabaseSession>, KcpdumpError> {
+    let captures = session.select(capture_id)?;
+    spawn_analysis(move || database::database_sessions(&captures)).await
+}
+
+/// Lists the AMQP and Kafka connections of the captures with their methods
+/// and requests: exchanges, routing keys, queues, topics, partitions and
+/// errors.
+#[tauri::command]
+async fn get_message_bus_sessions(
+    capture_id: Option<CaptureId>,
+    session: tauri::State<'_, Session>,
+) -> Result<Vec<MessageBusSession>, KcpdumpError> {
+    let captures = session.select(capture_id)?;
+    spawn_analysis(move || messagebus::message_bus_sessions(&captures)).await
+}
+
+/// Lists the multicast streams of the captures with their MPEG-TS health and
+/// the IGMP joins and leaves of their groups.
+#[tauri::command]
+async fn get_multicast_report(
+    capture_id: Option<CaptureId>,
+    session: tauri::State<'_, Session>,
+) -> Result<MulticastReport, KcpdumpError> {
+    let captures = session.select(capture_id)?;
+    spawn_analysis(move || multicast::multicast_report(&captures)).await
+}
+
+/// Evaluates the Suricata rules in `rules_path` against the captures.
+/// `variables` defines the `$NAME` references used in rule headers.
+#[tauri::command]
+async fn scan_ids_rules(
+    capture_id: Option<CaptureId>,
+    rules_path: String,
+    variables: HashMap<String, String>,
+    session: tauri::State<'_, Session>,
+) -> Result<IdsScan, KcpdumpError> {
+    let text = tokio::fs::read_to_string(&rules_path)
+        .await
+        .map_err(|e| KcpdumpError::file(&rules_path, e))?;
+    let rules = ids::parse_rules(&text, &variables)?;
+    let captures = session.select(capture_id)?;
+    // Matching is CPU-bound, so it runs off the async runtime's workers.
+    tokio::task::spawn_blocking(move || ids::scan(&captures, &rules))
+        .await
+        .map_err(|e| KcpdumpError::Other(format!("IDS scan failed: {}", e)))?
+}
+
+/// Starts capturing on `interface`. Decoded rows are streamed to the frontend
+/// as `live-capture` events; the returned id is used to stop the capture.
+#[tauri::command]
+fn start_live_capture(
+    interface: String,
+    bpf: Option<String>,
+    options: Option<LiveCaptureOptions>,
+    app: tauri::AppHandle,
+    session: tauri::State<'_, Session>,
+) -> Result<CaptureId, KcpdumpError> {
+    use tauri::Emitter;
+
+    let filter = CaptureFilter::compile(bpf.as_deref().unwrap_or_default())?;
+    let id = session.next_capture_id();
+    let mode = session.settings().time_display_mode;
+    let handle = live::start(
+        id,
+        &interface,
+        filter,
+        options.unwrap_or_default(),
+        mode,
+        move |event| {
+            let _ = app.emit("live-capture", event);
+        },
+    )
+    .map_err(|e| KcpdumpError::io(&format!("Failed to start capture on {}", interface), e))?;
+    session.insert_live_capture(id, handle);
+    Ok(id)
+}
+
+#[tauri::command]
+fn stop_live_capture(capture_id: CaptureId, session: tauri::State<'_, Session>) -> bool {
+    session.stop_live_capture(capture_id)
+}
+
+/// Pauses (`paused = true`) or resumes a live capture without closing it.
+#[tauri::command]
+fn pause_live_capture(
+    capture_id: CaptureId,
+    paused: bool,
+    session: tauri::State<'_, Session>,
+) -> bool {
+    session.pause_live_capture(capture_id, paused)
+}
+
+/// Lists the network interfaces that live captures can be started on.
+#[tauri::command]
+fn list_interfaces() -> Result<Vec<NetworkInterface>, KcpdumpError> {
+    cap::live::list_interfaces().map_err(|e| KcpdumpError::io("Failed to list interfaces", e))
+}
+
+/// Returns up to `limit` rows of a live capture starting at frame index
+/// `offset`. Packets no longer held in memory are read back from the capture
+/// file.
+#[tauri::command]
+async fn get_live_window(
+    capture_id: CaptureId,
+    offset: usize,
+    limit: usize,
+    session: tauri::State<'_, Session>,
+) -> Result<LiveWindow, KcpdumpError> {
+    let ring = session
+        .live_ring(capture_id)
+        .ok_or(KcpdumpError::CaptureNotFound { id: capture_id })?;
+    spawn_analysis(move || ring.window(offset, limit)).await
+}
+
+/// Watches `directory` for new capture files and analyzes each with `profile`.
+/// Results are emitted as `directory-watch` events.
+#[tauri::command]
+fn start_directory_watch(
+    directory: String,
+    profile: Option<AnalysisProfile>,
+    app: tauri::AppHandle,
+    session: tauri::State<'_, Session>,
+) -> Result<WatchId, KcpdumpError> {
+    use tauri::Emitter;
+
+    let id = session.next_watch_id();
+    let handle = watch::start(
+        id,
+        std::path::Path::new(&directory),
+        profile.unwrap_or_default(),
+        move |event| {
+            let _ = app.emit("directory-watch", event);
+        },
+    )
+    .map_err(|e| KcpdumpError::io(&format!("Failed to watch {}", directory), e))?;
+    session.insert_watch(id, handle);
+    Ok(id)
+}
+
+#[tauri::command]
+fn stop_directory_watch(watch_id: WatchId, session: tauri::State<'_, Session>) -> bool {
+    session.stop_watch(watch_id)
+}
+
+/// Runs the blocking part of a command on tokio's blocking pool. Captures are
+/// read from disk on demand, so a scan run on the main thread would freeze
+/// the UI, and one run on an async worker would stall other commands.
+async fn spawn_analysis<T: Send + 'static>(
+    analysis: impl FnOnce() -> Result<T, KcpdumpError> + Send + 'static,
+) -> Result<T, KcpdumpError> {
+    tokio::task::spawn_blocking(analysis)
+        .await
+        .map_err(|e| KcpdumpError::Other(format!("Analysis failed: {}", e)))?
+}
+
+/// Compiles the optional display filter of an analysis command.
+fn compile_display_filter(
+    filter: Option<&str>,
+    registry: &DissectorRegistry,
+) -> Result<Option<DisplayFilter>, KcpdumpError> {
+    filter
+        .map(|expression| DisplayFilter::compile(expression, registry))
+        .transpose()
+}
+
+/// Looks up packet `number` of an open or live capture, with the link type
+/// of the capture.
+async fn session_packet(
+    session: &Session,
+    capture_id: CaptureId,
+    number: usize,
+) -> Result<(PcapPacket, u32), KcpdumpError> {
+    let packet = match session.capture(capture_id) {
+        Some(capture) => {
+            let link_type = capture.header.network;
+            spawn_analysis(move || capture.packet(number))
+                .await?
+                .map(|packet| (packet, link_type))
+        }
+        None => {
+            let ring = session
+                .live_ring(capture_id)
+                .ok_or(KcpdumpError::CaptureNotFound { id: capture_id })?;
+            let link_type = ring.link_type();
+            spawn_analysis(move || ring.packet(number))
+                .await?
+                .map(|packet| (packet, link_type))
+        }
+    };
+    packet.ok_or(KcpdumpError::PacketNotFound { capture_id, number })
+}
+
+/// Opens a capture for one of the `analyze_*` commands and returns it with
+/// its link type, rejecting link types whose frames cannot be decoded.
+async fn open_link_capture(file_path: &str) -> Result<(Capture, u32), KcpdumpError> {
+    let capture = Capture::from_file(file_path).await?;
+    let link_type = capture.header().network;
+    if !link::is_supported(link_type) {
+        return Err(LinkTypeError::Unsupported(link_type).into());
+    }
+    Ok((capture, link_type))
+}
+
+async fn collect_ethernet_tuples(
+    file_path: &str,
+    mode: TimeDisplayMode,
+    filter: Option<&DisplayFilter>,
+    vendors: Option<&OuiDatabase>,
+    registry: &DissectorRegistry,
+) -> Result<Vec<EthernetTuple>, KcpdumpError> {
+    let (mut capture, link_type) = open_link_capture(file_path).await?;
+    let mut formatter = TimeFormatter::with_resolution(mode, capture.resolution());
+    let mut results = Vec::new();
+    let mut number = 0;
+
+    while let Some(raw_packet) = capture.next_packet().await? {
+        number += 1;
+        let timestamp = raw_packet.header.timestamp;
+        if filter.is_some_and(|filter| !filter.matches_packet(registry, link_type, number, &raw_packet)) {
+            formatter.skip(timestamp);
+            continue;
+        }
+        if let Ok(link_frame) = LinkFrame::decode(link_type, raw_packet.data.as_slice()) {
+            let vendor = |mac: Option<MacAddress>| {
+                vendors.zip(mac).and_then(|(vendors, mac)| vendors.vendor(&mac).map(str::to_string))
+            };
+            results.push(EthernetTuple { 
+                eth_type: format!("{:?}", link_frame.ether_type),
+                source: link_frame.source().unwrap_or_default(),
+                target: link_frame.destination().unwrap_or_default(),
+                source_vendor: vendor(link_frame.source_mac()),
+                target_vendor: vendor(link_frame.destination_mac()),
+                vlan_ids: link_frame.vlan_ids(),
+                timestamp,
+                time: formatter.format(timestamp),
+            });
+        } else {
+            formatter.skip(timestamp);
+        }
+    }
+
+    Ok(results)
+}
+
+async fn collect_ipv4_tuples(
+    file_path: &str,
+    mode: TimeDisplayMode,
+    filter: Option<&DisplayFilter>,
+    mut names: Option<NameBatch>,
+    mut geo: Option<GeoEnricher>,
+    registry: &DissectorRegistry,
+) -> Result<Vec<IpPacketTuple>, KcpdumpError> {
+    let (mut capture, link_type) = open_link_capture(file_path).await?;
+    let mut formatter = TimeFormatter::with_resolution(mode, capture.resolution());
+    let mut results = Vec::new();
+    let mut number = 0;
+
+    while let Some(raw_packet) = capture.next_packet().await? {
+        number += 1;
+        let timestamp = raw_packet.header.timestamp;
+        if filter.is_some_and(|filter| !filter.matches_packet(registry, link_type, number, &raw_packet)) {
+            formatter.skip(timestamp);
+            continue;
+        }
+        let link_frame = LinkFrame::decode(link_type, raw_packet.data.as_slice()).ok();
+        let vlan_ids = link_frame
+            .as_ref()
+            .map(LinkFrame::vlan_ids)
+            .unwrap_or_default();
+        let tuple = match link_frame.as_ref().map(|link_frame| link_frame.ether_type) {
+            Some(EtherType::IPv4) => link_frame
+                .and_then(|link_frame| IPv4Packet::try_from(link_frame.payload.as_slice()).ok())
+                .map(|ipv4_packet| IpPacketTuple {
+                    version: 4,
+                    source_ip: add_name(&mut names, ipv4_packet.source_ip),
+                    dest_ip: add_name(&mut names, ipv4_packet.dest_ip),
+                    source_name: None,
+                    dest_name: None,
+                    source_geo: lookup_geo(&mut geo, ipv4_packet.source_ip),
+                    dest_geo: lookup_geo(&mut geo, ipv4_packet.dest_ip),
+                    protocol: ipv4_packet.protocol,
+                    ttl: ipv4_packet.ttl,
+                    timestamp,
+                    time: formatter.format(timestamp),
+                    total_length: ipv4_packet.total_length,
+                    traffic_class: ipv4_packet.tos,
+                    flow_label: None,
+                    checksum_valid: Some(
+                        ipv4_packet.validate_checksum()
+                            && ipv4_packet.validate_payload_checksum() != Some(false),
+                    ),
+                    vlan_ids,
+                }),
+            Some(EtherType::IPv6) => link_frame
+                .and_then(|link_frame| IPv6Packet::try_from(link_frame.payload.as_slice()).ok())
+                .map(|ipv6_packet| IpPacketTuple {
+                    version: ipv6_packet.version,
+                    source_ip: add_name(&mut names, ipv6_packet.source_ip),
+                    dest_ip: add_name(&mut names, ipv6_packet.dest_ip),
+                    source_name: None,
+                    dest_name: None,
+                    source_geo: lookup_geo(&mut geo, ipv6_packet.source_ip),
+                    dest_geo: lookup_geo(&mut geo, ipv6_packet.dest_ip),
+                    protocol: ipv6_packet.next_header,
+                    ttl: ipv6_packet.hop_limit,
+                    timestamp,
+                    time: formatter.format(timestamp),
+                    total_length: ipv6_packet.payload_length.saturating_add(40),
+                    traffic_class: ipv6_packet.traffic_class,
+                    flow_label: Some(ipv6_packet.flow_label),
+                    checksum_valid: ipv6_packet.validate_payload_checksum(),
+                    vlan_ids,
+                }),
+            _ => None,
+        };
+        match tuple {
+            Some(tuple) => results.push(tuple),
+            None => formatter.skip(timestamp),
+        }
+    }
+
+    if let Some(names) = names {
+        let names = names.finish().await;
+        for tuple in &mut results {
+            tuple.source_name = resolved_name(&names, &tuple.source_ip);
+            tuple.dest_name = resolved_name(&names, &tuple.dest_ip);
+        }
+    }
+    Ok(results)
+}
+
+async fn collect_tcp_tuples(
+    file_path: &str,
+    mode: TimeDisplayMode,
+    filter: Option<&DisplayFilter>,
+    names: Option<NameBatch>,
+    registry: &DissectorRegistry,
+) -> Result<Vec<TcpSegmentTuple>, KcpdumpError> {
+    let tuples = collect_transport_tuples(file_path, mode, filter, names, registry).await?;
+    Ok(tuples
+        .into_iter()
+        .filter_map(|tuple| match tuple {
+            TransportTuple::Tcp(tcp) => Some(tcp),
+            TransportTuple::Udp(_) => None,
+        })
+        .collect())
+}
+
+async fn collect_arp_analysis(
+    file_path: &str,
+    mode: TimeDisplayMode,
+    filter: Option<&DisplayFilter>,
+    registry: &DissectorRegistry,
+) -> Result<ArpAnalysis, KcpdumpError> {
+    let (mut capture, link_type) = open_link_capture(file_path).await?;
+    let mut formatter = TimeFormatter::with_resolution(mode, capture.resolution());
+    let mut table = ArpTableBuilder::default();
+    let mut packets = Vec::new();
+    let mut number = 0;
+
+    while let Some(raw_packet) = capture.next_packet().await? {
+        number += 1;
+        let timestamp = raw_packet.header.timestamp;
+        if filter.is_some_and(|filter| !filter.matches_packet(registry, link_type, number, &raw_packet)) {
+            formatter.skip(timestamp);
+            continue;
+        }
+        let layers = PacketLayers::decode_link(number, link_type, &raw_packet);
+        let Some(arp_packet) = &layers.arp else {
+            formatter.skip(timestamp);
+            continue;
+        };
+        packets.push(ArpPacketTuple {
+            number,
+            hardware_type: arp_packet.hardware_type,
+            protocol_type: arp_packet.protocol_type,
+            operation: arp_packet.operation_name(),
+            sender_mac: arp_packet.sender_mac.to_string(),
+            sender_ip: arp_packet.sender_ip.to_string(),
+            target_mac: arp_packet.target_mac.to_string(),
+            target_ip: arp_packet.target_ip.to_string(),
+            gratuitous: arp_packet.is_gratuitous(),
+            vlan_ids: layers
+                .ethernet
+                .as_ref()
+                .map(|eth_packet| eth_packet.header.vlan_ids())
+                .unwrap_or_default(),
+            timestamp,
+            time: formatter.format(timestamp),
+        });
+        table.push(&layers);
+    }
+
+    Ok(ArpAnalysis {
+        packets,
+        table: table.finish(),
+    })
+}
+
+async fn collect_dns_transactions(
+    file_path: &str,
+    filter: Option<&DisplayFilter>,
+    registry: &DissectorRegistry,
+) -> Result<Vec<DnsTransaction>, KcpdumpError> {
+    let (mut capture, link_type) = open_link_capture(file_path).await?;
+    let mut transactions = DnsTransactionBuilder::default();
+    let mut number = 0;
+
+    while let Some(raw_packet) = capture.next_packet().await? {
+        number += 1;
+        if filter.is_some_and(|filter| !filter.matches_packet(registry, link_type, number, &raw_packet)) {
+            continue;
+        }
+        transactions.push(&PacketLayers::decode_link(number, link_type, &raw_packet));
+    }
+
+    Ok(transactions.finish())
+}
+
+async fn collect_http_transactions(
+    file_path: &str,
+    filter: Option<&DisplayFilter>,
+    registry: &DissectorRegistry,
+) -> Result<Vec<HttpTransaction>, KcpdumpError> {
+    let (mut capture, link_type) = open_link_capture(file_path).await?;
+    let mut reassembler = StreamReassembler::default();
+    let mut number = 0;
+
+    while let Some(raw_packet) = capture.next_packet().await? {
+        number += 1;
+        if filter.is_some_and(|filter| !filter.matches_packet(registry, link_type, number, &raw_packet)) {
+            continue;
+        }
+        reassembler.push(&PacketLayers::decode_link(number, link_type, &raw_packet));
+    }
+
+    let mut transactions: Vec<_> = reassembler
+        .finish()
+        .iter()
+        .flat_map(http::transactions)
+        .collect();
+    for (id, transaction) in transactions.iter_mut().enumerate() {
+        transaction.id = id;
+    }
+    Ok(transactions)
+}
+
+async fn collect_tls_connections(
+    file_path: &str,
+    filter: Option<&DisplayFilter>,
+    registry: &DissectorRegistry,
+) -> Result<Vec<TlsConnection>, KcpdumpError> {
+    let (mut capture, link_type) = open_link_capture(file_path).await?;
+    let mut reassembler = StreamReassembler::default();
+    let mut number = 0;
+
+    while let Some(raw_packet) = capture.next_packet().await? {
+        number += 1;
+        if filter.is_some_and(|filter| !filter.matches_packet(registry, link_type, number, &raw_packet)) {
+            continue;
+        }
+        reassembler.push(&PacketLayers::decode_link(number, link_type, &raw_packet));
+    }
+
+    Ok(reassembler.finish().iter().filter_map(tls::connection).collect())
+}
+
+async fn collect_transport_tuples(
+    file_path: &str,
+    mode: TimeDisplayMode,
+    filter: Option<&DisplayFilter>,
+    mut names: Option<NameBatch>,
+    registry: &DissectorRegistry,
+) -> Result<Vec<TransportTuple>, KcpdumpError> {
+    let (mut capture, link_type) = open_link_capture(file_path).await?;
+    let mut formatter = TimeFormatter::with_resolution(mode, capture.resolution());
+    let mut results = Vec::new();
+    let mut number = 0;
+
+    while let Some(raw_packet) = capture.next_packet().await? {
+        number += 1;
+        let timestamp = raw_packet.header.timestamp;
+        if filter.is_some_and(|filter| !filter.matches_packet(registry, link_type, number, &raw_packet)) {
+            formatter.skip(timestamp);
+            continue;
+        }
+        let packets = LinkFrame::decode(link_type, raw_packet.data.as_slice())
+            .ok()
+            .filter(|link_frame| link_frame.ether_type == EtherType::IPv4)
+            .and_then(|link_frame| {
+                let ipv4_packet = IPv4Packet::try_from(link_frame.payload.as_slice()).ok()?;
+                Some((link_frame.vlan_ids(), ipv4_packet))
+            });
+        let Some((vlan_ids, ipv4_packet)) = packets else {
+            formatter.skip(timestamp);
+            continue;
+        };
+        let source_ip = add_name(&mut names, ipv4_packet.source_ip);
+        let dest_ip = add_name(&mut names, ipv4_packet.dest_ip);
+        let payload = ipv4_packet.payload.as_slice();
+        let checksum_valid = ipv4_packet.validate_payload_checksum();
+        let tuple = match IpProtocol::from(ipv4_packet.protocol) {
+            IpProtocol::TCP => TcpSegment::try_from(payload).ok().map(|tcp_segment| {
+                TransportTuple::Tcp(TcpSegmentTuple {
+                    source_ip,
+                    dest_ip,
+                    source_name: None,
+                    dest_name: None,
+                    source_port: tcp_segment.source_port,
+                    dest_port: tcp_segment.dest_port,
+                    sequence_number: tcp_segment.sequence_number,
+                    ack_number: tcp_segment.ack_number,
+                    flags: tcp_segment.flags.to_string(),
+                    window_size: tcp_segment.window_size,
+                    options: tcp_segment.parsed_options(),
+                    checksum_valid,
+                    payload_length: tcp_segment.payload.len(),
+                    vlan_ids,
+                    timestamp,
+                    time: formatter.format(timestamp),
+                })
+            }),
+            IpProtocol::UDP => UdpDatagram::try_from(payload).ok().map(|udp_datagram| {
+                TransportTuple::Udp(UdpDatagramTuple {
+                    source_ip,
+                    dest_ip,
+                    source_name: None,
+                    dest_name: None,
+                    source_port: udp_datagram.source_port,
+                    dest_port: udp_datagram.dest_port,
+                    length: udp_datagram.length,
+                    checksum: udp_datagram.checksum,
+                    checksum_valid,
+                    payload_length: udp_datagram.payload.len(),
+                    vlan_ids,
+                    timestamp,
+                    time: formatter.format(timestamp),
+                })
+            }),
+            _ => None,
+        };
+        match tuple {
+            Some(tuple) => results.push(tuple),
+            None => formatter.skip(timestamp),
+        }
+    }
+
+    if let Some(names) = names {
+        let names = names.finish().await;
+        for tuple in &mut results {
+            let (source_ip, dest_ip, source_name, dest_name) = match tuple {
+                TransportTuple::Tcp(tcp) => {
+                    (&tcp.source_ip, &tcp.dest_ip, &mut tcp.source_name, &mut tcp.dest_name)
+                }
+                TransportTuple::Udp(udp) => {
+                    (&udp.source_ip, &udp.dest_ip, &mut udp.source_name, &mut udp.dest_name)
+                }
+            };
+            *source_name = resolved_name(&names, source_ip);
+            *dest_name = resolved_name(&names, dest_ip);
+        }
+    }
+    Ok(results)
+}
+
+/// Starts resolving names in the session's mode if the command asked for them.
+fn name_batch(resolve_names: Option<bool>, session: &Session) -> Option<NameBatch> {
+    resolve_names
+        .unwrap_or_default()
+        .then(|| session.resolver().batch(session.settings().name_resolution))
+}
+
+/// Renders an address, first handing it to the batch so that its lookup
+/// runs while the remaining packets are read.
+fn add_name(names: &mut Option<NameBatch>, address: impl Into<IpAddr>) -> String {
+    let address = address.into();
+    if let Some(names) = names {
+        names.add(address);
+    }
+    address.to_string()
+}
+
+/// Sets up GeoIP enrichment if the command asked for it. Databases that are
+/// not loaded simply leave their fields empty.
+fn geo_enricher(geoip: Option<bool>, session: &Session) -> Option<GeoEnricher> {
+    geoip.unwrap_or_default().then(|| {
+        let (locations, owners) = (session.geoip(), session.asn());
+        GeoEnricher::new(
+            move |address| locations.as_ref()?.lookup(address),
+            move |address| owners.as_ref()?.lookup(address),
+        )
+    })
+}
+
+fn lookup_geo(geo: &mut Option<GeoEnricher>, address: impl Into<IpAddr>) -> Option<EndpointGeo> {
+    geo.as_mut()?.lookup(address.into())
+}
+
+fn resolved_name(names: &HashMap<IpAddr, String>, address: &str) -> Option<String> {
+    address
+        .parse()
+        .ok()
+        .and_then(|address| names.get(&address).cloned())
+}
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    tauri::Builder::default()
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_opener::init())
+        .manage(Session::default())
+        .manage(Arc::new(DissectorRegistry::default()))
+        .setup(|app| {
+            let file = app.path().app_data_dir()?.join("recent.json");
+            app.manage(RecentCaptures::load(file));
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            analyze_pcap,
+            analyze_ipv4_packets,
+            analyze_tcp_packets,
+            analyze_transport,
+            analyze_arp,
+            analyze_dns,
+            analyze_http,
+            export_http_object,
+            analyze_tls,
+            get_session_settings,
+            set_time_display_mode,
+            set_name_resolution_mode,
+            load_hosts_file,
+            list_filter_fields,
+            export_tcpdump_text,
+            get_flow_graph,
+            open_capture,
+            get_packets,
+            close_capture,
+            list_captures,
+            get_packet_list,
+            capture_summary,
+            capture_info,
+            get_expert_info,
+            get_expert_events,
+            start_live_capture,
+            stop_live_capture,
+            pause_live_capture,
+            list_interfaces,
+            get_live_window,
+            start_directory_watch,
+            stop_directory_watch,
+            list_recent_captures,
+            save_capture_state,
+            remove_recent_capture,
+            restore_recent_capture,
+            import_hex_dump,
+            export_packet_bytes,
+            get_packet_detail,
+            get_packet_bytes,
+            get_conversations,
+            extract_files,
+            save_extracted_files,
+            voip_calls,
+            get_voip_call,
+            load_geoip_database,
+            get_geo_map,
+            load_oui_database,
+            load_asn_database,
+            get_asn_report,
+            lookup_asns,
+            get_conversation_timeline,
+            get_io_graph,
+            get_dhcp_leases,
+            get_dhcp_transactions,
+            get_neighbor_table,
+            get_arp_table,
+            get_icmp_echo_pairs,
+            wpa_handshakes,
+            export_wpa_handshakes,
+            load_dbc_file,
+            get_can_frames,
+            set_zigbee_network_key,
+            get_zigbee_frames,
+            set_lorawan_keys,
+            get_lorawan_frames,
+            get_ptp_offsets,
+            wlan_inventory,
+            get_encrypted_dns,
+            get_ssh_fingerprints,
+            get_multicast_report,
+            export_sqlite,
+            export_parquet,
+            export_report,
+            export_ecs,
+            scan_ids_rules,
+            get_asset_inventory,
+            export_asset_inventory,
+            sample_capture,
+            export_sampled_capture,
+            merge_captures,
+            split_capture,
+            get_database_sessions,
+            get_message_bus_sessions
+        ])
+        .run(tauri::generate_context!())
+        .expect("error while running tauri application");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_analyze_pcap() {
+        let registry = DissectorRegistry::default();
+        let result =
+            collect_ethernet_tuples("sample.pcap", TimeDisplayMode::default(), None, None, &registry).await;
+        assert!(result.is_ok());
+        let packets = result.unwrap();
+        assert!(!packets.is_empty());
+        for eth_packet in &packets {
+            assert!(!eth_packet.eth_type.is_empty());
+            assert!(!eth_packet.source.is_empty());
+            assert!(!eth_packet.target.is_empty());
+        }
+        // Print first packet details for verification
+        if let Some(eth_packet) = packets.first() {
+            println!("First packet: EthType: {}, Src MAC: {}, Dest MAC: {}, Timestamp: {}", eth_packet.eth_type, eth_packet.source, eth_packet.target, eth_packet.timestamp);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_analyze_ipv4_packets() {
+        let registry = DissectorRegistry::default();
+        let result =
+            collect_ipv4_tuples("sample.pcap", TimeDisplayMode::default(), None, None, None, &registry).await;
+        assert!(result.is_ok());
+        let ipv4_packets = result.unwrap();
+        assert!(!ipv4_packets.is_empty());
+        
+        for ipv4_packet in &ipv4_packets {
+            // 验证IP地址格式是否正确
+            assert!(!ipv4_packet.source_ip.is_empty());
+            assert!(!ipv4_packet.dest_ip.is_empty());
+            
+            // 验证TTL值是否有效
+            assert!(ipv4_packet.ttl > 0);
+            
+            // 验证总长度是否有效
+            assert!(ipv4_packet.total_length > 0);
+        }
+        
+        // 打印第一个IPv4数据包的详细信息以便手动验证
+        if let Some(ipv4_packet) = ipv4_packets.first() {
+            println!(
+                "First IPv4 packet: Source IP: {}, Dest IP: {}, Protocol: {}, TTL: {}, Total Length: {}, Timestamp: {}", 
+                ipv4_packet.source_ip, 
+                ipv4_packet.dest_ip, 
+                ipv4_packet.protocol, 
+                ipv4_packet.ttl, 
+                ipv4_packet.total_length,
+                ipv4_packet.timestamp
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_analyze_tcp_packets() {
+        let registry = DissectorRegistry::default();
+        let segments = collect_tcp_tuples("sample.pcap", TimeDisplayMode::default(), None, None, &registry)
+            .await
+            .unwrap();
+        assert!(!segments.is_empty());
+        let http = segments
+            .iter()
+            .find(|segment| segment.dest_port == 80)
+            .unwrap();
+        assert!(!http.flags.is_empty());
+        assert!(!http.source_ip.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_analyze_transport() {
+        let registry = DissectorRegistry::default();
+        let tuples = collect_transport_tuples("sample.pcap", TimeDisplayMode::default(), None, None, &registry)
+            .await
+            .unwrap();
+        assert!(tuples.iter().any(|tuple| matches!(tuple, TransportTuple::Tcp(_))));
+        let json = serde_json::to_value(&tuples[0]).unwrap();
+        assert!(matches!(json["protocol"].as_str(), Some("tcp" | "udp")));
+        assert!(json["sourcePort"].is_u64());
+    }
+
+    #[tokio::test]
+    async fn test_analyze_arp() {
+        let registry = DissectorRegistry::default();
+        let analysis = collect_arp_analysis("sample.pcap", TimeDisplayMode::default(), None, &registry)
+            .await
+            .unwrap();
+        assert!(analysis.packets.iter().all(|packet| packet.number > 0));
+        assert!(analysis.table.len() <= analysis.packets.len());
+    }
+
+    #[tokio::test]
+    async fn test_analyze_dns() {
+        let registry = DissectorRegistry::default();
+        let transactions = collect_dns_transactions("sample.pcap", None, &registry)
+            .await
+            .unwrap();
+        for transaction in &transactions {
+            assert!(transaction.query_number.is_some() || transaction.response_number.is_some());
+            assert!(transaction.server.ends_with(":53"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_analyze_http() {
+        let registry = DissectorRegistry::default();
+        let transactions = collect_http_transactions("sample.pcap", None, &registry)
+            .await
+            .unwrap();
+        for (id, transaction) in transactions.iter().enumerate() {
+            assert_eq!(transaction.id, id);
+            assert_eq!(transaction.body_size, transaction.body.len());
+        }
+        let json = serde_json::to_value(&transactions).unwrap();
+        assert!(json.as_array().unwrap().iter().all(|t| t.get("body").is_none()));
+    }
+
+    #[tokio::test]
+    async fn test_analyze_tls() {
+        let registry = DissectorRegistry::default();
+        let connections = collect_tls_connections("sample.pcap", None, &registry)
+            .await
+            .unwrap();
+        for connection in &connections {
+            assert!(connection.ja3.is_some() || connection.ja3s.is_some());
+            assert_eq!(connection.ja3.is_some(), connection.ja3_string.is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_display_filter_on_analysis() {
+        let registry = DissectorRegistry::default();
+        let mode = TimeDisplayMode::default();
+        let filter = compile_display_filter(Some("tcp.port == 80"), &registry)
+            .unwrap()
+            .unwrap();
+        let all = collect_transport_tuples("sample.pcap", mode, None, None, &registry)
+            .await
+            .unwrap();
+        let http = collect_transport_tuples("sample.pcap", mode, Some(&filter), None, &registry)
+            .await
+            .unwrap();
+        assert!(!http.is_empty());
+        assert!(http.len() <= all.len());
+        assert!(http.iter().all(|tuple| match tuple {
+            TransportTuple::Tcp(tcp) => tcp.source_port == 80 || tcp.dest_port == 80,
+            TransportTuple::Udp(_) => false,
+        }));
+        assert!(compile_display_filter(Some("tcp.port == http"), &registry).is_err());
+        assert!(compile_display_filter(None, &registry).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_flow_graph_conversation() {
+        let filter = PacketFilter::default();
+        let mode = TimeDisplayMode::SinceStart;
+        let graph = flowgraph::build_flow_graph("sample.pcap", &filter, None, mode)
+            .await
+            .unwrap();
+        assert!(!graph.events.is_empty());
+        let Some(conversation) = graph.events.iter().find_map(|event| event.flow) else {
+            return;
+        };
+        let single = flowgraph::build_flow_graph("sample.pcap", &filter, Some(conversation), mode)
+            .await
+            .unwrap();
+        assert!(!single.events.is_empty());
+        assert!(single.nodes.len() <= 2);
+    }
+
+    #[tokio::test]
+    async fn test_workspace_union_view() {
+        let session = Session::default();
+        for _ in 0..2 {
+            let id = session.next_capture_id();
+            let capture = LoadedCapture::load(id, "sample.pcap").await.unwrap();
+            session.insert_capture(capture);
+        }
+        let captures = session.select(None).unwrap();
+        let rows = packetlist::build_rows(
+            &captures,
+            &PacketFilter::default(),
+            TimeDisplayMode::default(),
+            None,
+        )
+        .unwrap();
+        let total: usize = captures.iter().map(|capture| capture.packet_count()).sum();
+        assert_eq!(rows.len(), total);
+        assert!(rows.iter().any(|row| row.capture_id == captures[0].id));
+        assert!(rows.iter().any(|row| row.capture_id == captures[1].id));
+    }
+
+    #[tokio::test]
+    async fn test_ipv4_tuples_delta_displayed() {
+        let registry = DissectorRegistry::default();
+        let mode = TimeDisplayMode::DeltaDisplayed;
+        let packets = collect_ipv4_tuples("sample.pcap", mode, None, None, None, &registry)
+            .await
+            .unwrap();
+        assert_eq!(packets.first().unwrap().time, "0.000000");
+        for pair in packets.windows(2) {
+            let previous = pair[0].timestamp.as_secs_f64();
+            let current = pair[1].timestamp.as_secs_f64();
+            let delta: f64 = pair[1].time.parse().unwrap();
+            assert!((delta - (current - previous)).abs() < 1e-5);
+        }
+    }
+}
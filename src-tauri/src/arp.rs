@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc;
+
+use crate::dissect::PacketLayers;
+use crate::error::KcpdumpError;
+use crate::packet::MacAddress;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::packetlist;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::session::LoadedCapture;
+
+pub const ARP_REQUEST: u16 = 1;
+pub const ARP_REPLY: u16 = 2;
+
+/// ARP Packet
+/// An ARP message for IPv4 over Ethernet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArpPacket {
+    pub hardware_type: u16,
+    pub protocol_type: u16,
+    pub operation: u16,
+    pub sender_mac: MacAddress,
+    pub sender_ip: Ipv4Addr,
+    pub target_mac: MacAddress,
+    pub target_ip: Ipv4Addr,
+}
+
+impl TryFrom<&[u8]> for ArpPacket {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 28 {
+            return Err(KcpdumpError::malformed(0, "Data too short for ARP packet"));
+        }
+        let hardware_type = u16::from_be_bytes([data[0], data[1]]);
+        let protocol_type = u16::from_be_bytes([data[2], data[3]]);
+        if hardware_type != 1 || protocol_type != 0x0800 || data[4] != 6 || data[5] != 4 {
+            return Err(KcpdumpError::malformed(
+                0,
+                "Unsupported ARP hardware or protocol type",
+            ));
+        }
+        let mac = |offset: usize| {
+            MacAddress([
+                data[offset],
+                data[offset + 1],
+                data[offset + 2],
+                data[offset + 3],
+                data[offset + 4],
+                data[offset + 5],
+            ])
+        };
+        let ip = |offset: usize| {
+            Ipv4Addr::new(
+                data[offset],
+                data[offset + 1],
+                data[offset + 2],
+                data[offset + 3],
+            )
+        };
+        Ok(ArpPacket {
+            hardware_type,
+            protocol_type,
+            operation: u16::from_be_bytes([data[6], data[7]]),
+            sender_mac: mac(8),
+            sender_ip: ip(14),
+            target_mac: mac(18),
+            target_ip: ip(24),
+        })
+    }
+}
+
+impl ArpPacket {
+    /// A gratuitous ARP announces the sender's own address.
+    pub fn is_gratuitous(&self) -> bool {
+        self.sender_ip == self.target_ip
+    }
+
+    /// Names the operation, `request` and `reply` for the common cases.
+    pub fn operation_name(&self) -> String {
+        match self.operation {
+            ARP_REQUEST => "request".to_string(),
+            ARP_REPLY => "reply".to_string(),
+            3 => "rarp-request".to_string(),
+            4 => "rarp-reply".to_string(),
+            8 => "inarp-request".to_string(),
+            9 => "inarp-reply".to_string(),
+            operation => format!("unknown ({})", operation),
+        }
+    }
+}
+
+/// ARP Change
+/// A binding that moved to another MAC address.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ArpChange {
+    pub number: usize,
+    /// Seconds since the epoch.
+    pub time: f64,
+    pub previous_mac: String,
+    pub mac: String,
+}
+
+/// ARP Entry
+/// The MAC address an IPv4 address was last bound to, with its history.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ArpEntry {
+    pub ip: Ipv4Addr,
+    pub mac: String,
+    pub first_number: usize,
+    pub last_number: usize,
+    /// Seconds since the epoch.
+    pub first_seen: f64,
+    pub last_seen: f64,
+    /// ARP packets sent for this address.
+    pub packets: usize,
+    pub gratuitous: usize,
+    pub changes: Vec<ArpChange>,
+}
+
+/// ARP Table Builder
+/// Learns bindings from the sender fields of ARP packets fed in time order.
+/// Probes with an unspecified sender address are ignored.
+#[derive(Debug, Default)]
+pub struct ArpTableBuilder {
+    entries: Vec<ArpEntry>,
+    by_ip: HashMap<Ipv4Addr, usize>,
+}
+
+impl ArpTableBuilder {
+    pub fn push(&mut self, layers: &PacketLayers) {
+        let Some(arp) = &layers.arp else {
+            return;
+        };
+        if arp.sender_ip.is_unspecified() {
+            return;
+        }
+        let header = &layers.packet.header;
+        let time = header.timestamp.as_secs_f64();
+        let mac = arp.sender_mac.to_string();
+        let gratuitous = usize::from(arp.is_gratuitous());
+
+        let Some(&index) = self.by_ip.get(&arp.sender_ip) else {
+            self.by_ip.insert(arp.sender_ip, self.entries.len());
+            self.entries.push(ArpEntry {
+                ip: arp.sender_ip,
+                mac,
+                first_number: layers.number,
+                last_number: layers.number,
+                first_seen: time,
+                last_seen: time,
+                packets: 1,
+                gratuitous,
+                changes: Vec::new(),
+            });
+            return;
+        };
+        let entry = &mut self.entries[index];
+        if entry.mac != mac {
+            entry.changes.push(ArpChange {
+                number: layers.number,
+                time,
+                previous_mac: std::mem::replace(&mut entry.mac, mac.clone()),
+                mac,
+            });
+        }
+        entry.last_number = layers.number;
+        entry.last_seen = time;
+        entry.packets += 1;
+        entry.gratuitous += gratuitous;
+    }
+
+    /// Returns the entries in order of first appearance.
+    pub fn finish(self) -> Vec<ArpEntry> {
+        self.entries
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Reconstructs the ARP table of the given captures, interleaved by time.
+pub fn arp_table(captures: &[Arc<LoadedCapture>]) -> Result<Vec<ArpEntry>, KcpdumpError> {
+    let mut builder = ArpTableBuilder::default();
+    let link_types = packetlist::link_types(captures);
+    for item in packetlist::merged_packets(captures) {
+        let (capture_id, number, packet) = item?;
+        builder.push(&PacketLayers::decode_link(number, link_types[&capture_id], &packet));
+    }
+    Ok(builder.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timefmt::Timestamp;
+    use crate::cap::{PcapPacket, PcapPacketHeader};
+
+    fn arp_frame(
+        operation: u16,
+        sender: ([u8; 6], [u8; 4]),
+        target_ip: [u8; 4],
+        ts_sec: u32,
+    ) -> PcapPacket {
+        let mut data = vec![0xff; 6];
+        data.extend_from_slice(&sender.0);
+        data.extend_from_slice(&[0x08, 0x06, 0x00, 0x01, 0x08, 0x00, 6, 4]);
+        data.extend_from_slice(&operation.to_be_bytes());
+        data.extend_from_slice(&sender.0);
+        data.extend_from_slice(&sender.1);
+        data.extend_from_slice(&[0; 6]);
+        data.extend_from_slice(&target_ip);
+        PcapPacket {
+            header: PcapPacketHeader {
+                timestamp: Timestamp::new(ts_sec, 0),
+                incl_len: data.len() as u32,
+                orig_len: data.len() as u32,
+                link_type: None,
+            },
+            data,
+        }
+    }
+
+    #[test]
+    fn test_binding_changes_are_recorded() {
+        let gateway = [192, 168, 0, 1];
+        let genuine = [0x02, 0, 0, 0, 0, 0x01];
+        let attacker = [0x02, 0, 0, 0, 0, 0x66];
+        let packets = [
+            arp_frame(
+                ARP_REQUEST,
+                ([0x02, 0, 0, 0, 0, 0x0a], [192, 168, 0, 10]),
+                gateway,
+                10,
+            ),
+            arp_frame(ARP_REPLY, (genuine, gateway), [192, 168, 0, 10], 11),
+            arp_frame(ARP_REPLY, (attacker, gateway), gateway, 20),
+            arp_frame(
+                ARP_REQUEST,
+                ([0x02, 0, 0, 0, 0, 0x0b], [0, 0, 0, 0]),
+                [192, 168, 0, 11],
+                21,
+            ),
+        ];
+        let mut builder = ArpTableBuilder::default();
+        for (index, packet) in packets.iter().enumerate() {
+            builder.push(&PacketLayers::decode(index + 1, packet));
+        }
+        let table = builder.finish();
+        assert_eq!(table.len(), 2);
+        let entry = &table[1];
+        assert_eq!(entry.ip, Ipv4Addr::new(192, 168, 0, 1));
+        assert_eq!(entry.mac, "02:00:00:00:00:66");
+        assert_eq!(entry.packets, 2);
+        assert_eq!(entry.gratuitous, 1);
+        assert_eq!(entry.first_seen, 11.0);
+        assert_eq!(entry.last_seen, 20.0);
+        assert_eq!(
+            entry.changes,
+            vec![ArpChange {
+                number: 3,
+                time: 20.0,
+                previous_mac: "02:00:00:00:00:01".to_string(),
+                mac: "02:00:00:00:00:66".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_arp_packet_fields() {
+        let frame = arp_frame(
+            ARP_REPLY,
+            ([0x02, 0, 0, 0, 0, 0x01], [10, 0, 0, 1]),
+            [10, 0, 0, 2],
+            0,
+        );
+        let arp = ArpPacket::try_from(&frame.data[14..]).unwrap();
+        assert_eq!(arp.hardware_type, 1);
+        assert_eq!(arp.protocol_type, 0x0800);
+        assert_eq!(arp.operation_name(), "reply");
+        assert_eq!(arp.sender_mac.to_string(), "02:00:00:00:00:01");
+        assert_eq!(arp.target_ip, Ipv4Addr::new(10, 0, 0, 2));
+        assert!(!arp.is_gratuitous());
+        assert!(ArpPacket::try_from(&frame.data[14..40]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sample_arp() {
+        let capture = Arc::new(LoadedCapture::load(1, "sample.pcap").await.unwrap());
+        let table = arp_table(std::slice::from_ref(&capture)).unwrap();
+        assert!(table.iter().all(|entry| !entry.ip.is_unspecified()));
+        assert!(
+            table
+                .iter()
+                .all(|entry| entry.packets > entry.changes.len())
+        );
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::Ipv4Addr;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc;
+
+use maxminddb::{Reader, geoip2};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::dissect::PacketLayers;
+use crate::error::KcpdumpError;
+use crate::geoip::is_global;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::session::LoadedCapture;
+
+/// Autonomous System
+/// The network that announces an address.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AutonomousSystem {
+    pub number: u32,
+    pub organization: Option<String>,
+    /// Display name, e.g. `AS15169 Google LLC`.
+    pub label: String,
+}
+
+impl AutonomousSystem {
+    pub fn new(number: u32, organization: Option<String>) -> Self {
+        let label = match &organization {
+            Some(organization) => format!("AS{} {}", number, organization),
+            None => format!("AS{}", number),
+        };
+        AutonomousSystem {
+            number,
+            organization,
+            label,
+        }
+    }
+}
+
+/// ASN Database
+/// A MaxMind ASN database (e.g. GeoLite2-ASN.mmdb) loaded into memory.
+pub struct AsnDatabase {
+    path: String,
+    reader: Reader<Vec<u8>>,
+}
+
+impl std::fmt::Debug for AsnDatabase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsnDatabase")
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+impl AsnDatabase {
+    pub fn open(path: &str) -> Result<Self, KcpdumpError> {
+        let data = std::fs::read(path).map_err(|e| KcpdumpError::file(path, e))?;
+        let reader = Reader::from_source(data)
+            .map_err(|e| KcpdumpError::InvalidInput(format!("Invalid ASN database: {}", e)))?;
+        Ok(AsnDatabase {
+            path: path.to_string(),
+            reader,
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Looks up an address. Returns `None` for addresses no network announces.
+    pub fn lookup(&self, address: IpAddr) -> Option<AutonomousSystem> {
+        let asn: geoip2::Asn = self.reader.lookup(address).ok()?;
+        Some(AutonomousSystem::new(
+            asn.autonomous_system_number?,
+            asn.autonomous_system_organization.map(str::to_string),
+        ))
+    }
+}
+
+/// Looks up each distinct global address of `addresses`.
+pub fn lookup_all(
+    addresses: &[IpAddr],
+    lookup: impl Fn(IpAddr) -> Option<AutonomousSystem>,
+) -> HashMap<IpAddr, AutonomousSystem> {
+    let mut owners = HashMap::new();
+    for address in addresses
+        .iter()
+        .copied()
+        .filter(|address| is_global(*address))
+    {
+        if owners.contains_key(&address) {
+            continue;
+        }
+        if let Some(system) = lookup(address) {
+            owners.insert(address, system);
+        }
+    }
+    owners
+}
+
+/// ASN Traffic
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AsnTraffic {
+    pub system: AutonomousSystem,
+    pub packets: usize,
+    pub bytes: u64,
+    /// Distinct addresses announced by the network.
+    pub addresses: usize,
+}
+
+/// ASN Report
+/// Traffic per autonomous system. As for the geo map, every packet counts
+/// once for its source and once for its destination address.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AsnReport {
+    /// Sorted by bytes, descending.
+    pub networks: Vec<AsnTraffic>,
+    /// Endpoint traffic of private, local or unknown addresses.
+    pub unresolved_packets: usize,
+    pub unresolved_bytes: u64,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Aggregates endpoint traffic of the captures by the networks returned by `lookup`.
+pub fn asn_report(
+    captures: &[Arc<LoadedCapture>],
+    lookup: impl Fn(IpAddr) -> Option<AutonomousSystem>,
+) -> Result<AsnReport, KcpdumpError> {
+    let mut endpoints: HashMap<IpAddr, (usize, u64)> = HashMap::new();
+    for capture in captures {
+        for (index, packet) in capture.packets().enumerate() {
+            let packet = packet?;
+            let layers = PacketLayers::decode_link(index + 1, capture.header.network, &packet);
+            let Some(ip) = &layers.ipv4 else {
+                continue;
+            };
+            for address in [ip.source_ip, ip.dest_ip] {
+                let counter = endpoints
+                    .entry(IpAddr::V4(Ipv4Addr::from(address)))
+                    .or_default();
+                counter.0 += 1;
+                counter.1 += u64::from(packet.header.orig_len);
+            }
+        }
+    }
+
+    let mut report = AsnReport::default();
+    let mut networks: HashMap<u32, AsnTraffic> = HashMap::new();
+    for (address, (packets, bytes)) in endpoints {
+        let Some(system) = is_global(address).then(|| lookup(address)).flatten() else {
+            report.unresolved_packets += packets;
+            report.unresolved_bytes += bytes;
+            continue;
+        };
+        let network = networks.entry(system.number).or_insert_with(|| AsnTraffic {
+            system,
+            packets: 0,
+            bytes: 0,
+            addresses: 0,
+        });
+        network.packets += packets;
+        network.bytes += bytes;
+        network.addresses += 1;
+    }
+    report.networks = networks.into_values().collect();
+    report.networks.sort_by(|a, b| {
+        b.bytes
+            .cmp(&a.bytes)
+            .then_with(|| a.system.number.cmp(&b.system.number))
+    });
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owner(address: IpAddr) -> Option<AutonomousSystem> {
+        match address {
+            IpAddr::V4(address) if address.octets()[0] < 100 => {
+                Some(AutonomousSystem::new(15169, Some("Google LLC".to_string())))
+            }
+            _ => Some(AutonomousSystem::new(2497, None)),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_asn_report_of_sample() {
+        let capture = Arc::new(LoadedCapture::load(1, "sample.pcap").await.unwrap());
+        let report = asn_report(std::slice::from_ref(&capture), owner).unwrap();
+        let endpoints = 2 * capture
+            .packets()
+            .map(Result::unwrap)
+            .enumerate()
+            .filter(|(index, packet)| PacketLayers::decode(index + 1, packet).ipv4.is_some())
+            .count();
+        let resolved: usize = report.networks.iter().map(|network| network.packets).sum();
+        assert_eq!(resolved + report.unresolved_packets, endpoints);
+        assert!(report.networks.len() <= 2);
+        assert!(
+            report
+                .networks
+                .windows(2)
+                .all(|pair| pair[0].bytes >= pair[1].bytes)
+        );
+    }
+
+    #[test]
+    fn test_lookup_all() {
+        let addresses: Vec<IpAddr> = ["8.8.8.8", "8.8.8.8", "192.168.1.1", "203.0.113.9"]
+            .iter()
+            .map(|address| address.parse().unwrap())
+            .collect();
+        let owners = lookup_all(&addresses, owner);
+        assert_eq!(owners.len(), 2);
+        assert_eq!(owners[&addresses[0]].label, "AS15169 Google LLC");
+        assert_eq!(owners[&addresses[3]].label, "AS2497");
+        assert!(AsnDatabase::open("missing.mmdb").is_err());
+    }
+}
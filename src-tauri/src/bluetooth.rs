@@ -0,0 +1,331 @@
+use crate::error::KcpdumpError;
+use crate::summary::PacketSummary;
+
+/// Link type of H4 frames behind a 4-byte direction header, as converted
+/// from btsnoop logs.
+pub const LINKTYPE_BLUETOOTH_HCI_H4_WITH_PHDR: u32 = 201;
+
+pub const HCI_COMMAND: u8 = 0x01;
+pub const HCI_ACL: u8 = 0x02;
+pub const HCI_SCO: u8 = 0x03;
+pub const HCI_EVENT: u8 = 0x04;
+pub const HCI_ISO: u8 = 0x05;
+
+/// Hci Payload
+/// The packet-type specific header of an HCI packet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HciPayload {
+    Command {
+        opcode: u16,
+        parameters: Vec<u8>,
+    },
+    Acl {
+        handle: u16,
+        /// Packet boundary flag: 0b10 starts an L2CAP frame, 0b01 continues one.
+        boundary: u8,
+        data: Vec<u8>,
+    },
+    Sco {
+        handle: u16,
+        data: Vec<u8>,
+    },
+    Event {
+        code: u8,
+        parameters: Vec<u8>,
+    },
+    Iso {
+        handle: u16,
+        data: Vec<u8>,
+    },
+}
+
+/// Hci Packet
+/// An H4 HCI packet behind the 4-byte direction header of link type 201.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HciPacket {
+    /// Sent by the controller to the host.
+    pub received: bool,
+    pub payload: HciPayload,
+}
+
+impl TryFrom<&[u8]> for HciPacket {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 5 {
+            return Err(KcpdumpError::malformed(0, "Data too short for HCI packet"));
+        }
+        let received = u32::from_be_bytes([data[0], data[1], data[2], data[3]]) & 1 != 0;
+        let body = &data[5..];
+        let handle = |body: &[u8]| u16::from_le_bytes([body[0], body[1]]) & 0x0fff;
+        let payload = match data[4] {
+            HCI_COMMAND => {
+                let parameters = body
+                    .get(3..)
+                    .ok_or(KcpdumpError::malformed(3, "HCI command header truncated"))?;
+                HciPayload::Command {
+                    opcode: u16::from_le_bytes([body[0], body[1]]),
+                    parameters: parameters.to_vec(),
+                }
+            }
+            HCI_ACL => {
+                let data = body
+                    .get(4..)
+                    .ok_or(KcpdumpError::malformed(4, "HCI ACL header truncated"))?;
+                HciPayload::Acl {
+                    handle: handle(body),
+                    boundary: (body[1] >> 4) & 0x03,
+                    data: data.to_vec(),
+                }
+            }
+            HCI_SCO => {
+                let data = body
+                    .get(3..)
+                    .ok_or(KcpdumpError::malformed(3, "HCI SCO header truncated"))?;
+                HciPayload::Sco {
+                    handle: handle(body),
+                    data: data.to_vec(),
+                }
+            }
+            HCI_EVENT => {
+                let parameters = body
+                    .get(2..)
+                    .ok_or(KcpdumpError::malformed(2, "HCI event header truncated"))?;
+                HciPayload::Event {
+                    code: body[0],
+                    parameters: parameters.to_vec(),
+                }
+            }
+            HCI_ISO => {
+                let data = body
+                    .get(4..)
+                    .ok_or(KcpdumpError::malformed(4, "HCI ISO header truncated"))?;
+                HciPayload::Iso {
+                    handle: handle(body),
+                    data: data.to_vec(),
+                }
+            }
+            _ => return Err(KcpdumpError::malformed(0, "Unknown HCI packet type")),
+        };
+        Ok(HciPacket { received, payload })
+    }
+}
+
+/// Returns the name of a common HCI command.
+pub fn command_name(opcode: u16) -> Option<&'static str> {
+    Some(match opcode {
+        0x0401 => "Inquiry",
+        0x0405 => "Create Connection",
+        0x0406 => "Disconnect",
+        0x0c01 => "Set Event Mask",
+        0x0c03 => "Reset",
+        0x0c13 => "Write Local Name",
+        0x1001 => "Read Local Version Information",
+        0x1009 => "Read BD_ADDR",
+        0x2005 => "LE Set Random Address",
+        0x2006 => "LE Set Advertising Parameters",
+        0x200a => "LE Set Advertising Enable",
+        0x200b => "LE Set Scan Parameters",
+        0x200c => "LE Set Scan Enable",
+        0x200d => "LE Create Connection",
+        _ => return None,
+    })
+}
+
+/// Returns the name of a common HCI event.
+pub fn event_name(code: u8) -> Option<&'static str> {
+    Some(match code {
+        0x01 => "Inquiry Complete",
+        0x02 => "Inquiry Result",
+        0x03 => "Connection Complete",
+        0x05 => "Disconnection Complete",
+        0x0e => "Command Complete",
+        0x0f => "Command Status",
+        0x13 => "Number of Completed Packets",
+        0x3e => "LE Meta",
+        _ => return None,
+    })
+}
+
+fn le_subevent_name(code: u8) -> Option<&'static str> {
+    Some(match code {
+        0x01 => "LE Connection Complete",
+        0x02 => "LE Advertising Report",
+        0x03 => "LE Connection Update Complete",
+        0x0a => "LE Enhanced Connection Complete",
+        _ => return None,
+    })
+}
+
+fn command_label(opcode: u16) -> String {
+    match command_name(opcode) {
+        Some(name) => name.to_string(),
+        None => format!(
+            "Opcode 0x{:04x} (OGF 0x{:02x}, OCF 0x{:03x})",
+            opcode,
+            opcode >> 10,
+            opcode & 0x03ff
+        ),
+    }
+}
+
+fn event_info(code: u8, parameters: &[u8]) -> String {
+    match (code, parameters) {
+        // Command Complete: number of packets, opcode, return parameters.
+        (0x0e, [_, low, high, ..]) => format!(
+            "Rcvd Command Complete ({})",
+            command_label(u16::from_le_bytes([*low, *high]))
+        ),
+        // Command Status: status, number of packets, opcode.
+        (0x0f, [status, _, low, high, ..]) => format!(
+            "Rcvd Command Status ({}) status=0x{:02x}",
+            command_label(u16::from_le_bytes([*low, *high])),
+            status
+        ),
+        (0x3e, [subevent, ..]) => match le_subevent_name(*subevent) {
+            Some(name) => format!("Rcvd {}", name),
+            None => format!("Rcvd LE Meta (Subevent 0x{:02x})", subevent),
+        },
+        _ => match event_name(code) {
+            Some(name) => format!("Rcvd {}", name),
+            None => format!("Rcvd Event 0x{:02x}", code),
+        },
+    }
+}
+
+/// Summarizes an HCI packet for the packet list. Endpoints are the host and
+/// the controller, as seen from the direction header.
+pub fn summarize(frame: &[u8]) -> PacketSummary {
+    let packet = match HciPacket::try_from(frame) {
+        Ok(packet) => packet,
+        Err(e) => {
+            return PacketSummary {
+                source: String::new(),
+                destination: String::new(),
+                protocol: "Malformed".to_string(),
+                length: frame.len(),
+                info: e.to_string(),
+                flow: None,
+            };
+        }
+    };
+
+    let (source, destination) = if packet.received {
+        ("controller", "host")
+    } else {
+        ("host", "controller")
+    };
+    let direction = if packet.received { "Rcvd" } else { "Sent" };
+    let (protocol, info) = match &packet.payload {
+        HciPayload::Command { opcode, .. } => {
+            ("HCI_CMD", format!("Sent {}", command_label(*opcode)))
+        }
+        HciPayload::Event { code, parameters } => ("HCI_EVT", event_info(*code, parameters)),
+        HciPayload::Acl {
+            handle,
+            boundary,
+            data,
+        } => {
+            let info = match (boundary, data.get(2..4)) {
+                (0b00 | 0b10, Some(cid)) => format!(
+                    "{} L2CAP CID 0x{:04x} Handle 0x{:03x} Len={}",
+                    direction,
+                    u16::from_le_bytes([cid[0], cid[1]]),
+                    handle,
+                    data.len()
+                ),
+                _ => format!(
+                    "{} ACL continuation Handle 0x{:03x} Len={}",
+                    direction,
+                    handle,
+                    data.len()
+                ),
+            };
+            ("HCI_ACL", info)
+        }
+        HciPayload::Sco { handle, data } => (
+            "HCI_SCO",
+            format!(
+                "{} SCO Handle 0x{:03x} Len={}",
+                direction,
+                handle,
+                data.len()
+            ),
+        ),
+        HciPayload::Iso { handle, data } => (
+            "HCI_ISO",
+            format!(
+                "{} ISO Handle 0x{:03x} Len={}",
+                direction,
+                handle,
+                data.len()
+            ),
+        ),
+    };
+
+    PacketSummary {
+        source: source.to_string(),
+        destination: destination.to_string(),
+        protocol: protocol.to_string(),
+        length: frame.len(),
+        info,
+        flow: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_and_event() {
+        let reset = [0, 0, 0, 0, HCI_COMMAND, 0x03, 0x0c, 0x00];
+        let packet = HciPacket::try_from(reset.as_slice()).unwrap();
+        assert!(!packet.received);
+        assert_eq!(
+            packet.payload,
+            HciPayload::Command {
+                opcode: 0x0c03,
+                parameters: Vec::new()
+            }
+        );
+        let summary = summarize(&reset);
+        assert_eq!(summary.protocol, "HCI_CMD");
+        assert_eq!(summary.source, "host");
+        assert_eq!(summary.info, "Sent Reset");
+
+        let complete = [0, 0, 0, 1, HCI_EVENT, 0x0e, 0x04, 0x01, 0x03, 0x0c, 0x00];
+        let summary = summarize(&complete);
+        assert_eq!(summary.protocol, "HCI_EVT");
+        assert_eq!(summary.destination, "host");
+        assert_eq!(summary.info, "Rcvd Command Complete (Reset)");
+
+        let unknown = [0, 0, 0, 0, HCI_COMMAND, 0x42, 0xfc, 0x00];
+        assert_eq!(
+            summarize(&unknown).info,
+            "Sent Opcode 0xfc42 (OGF 0x3f, OCF 0x042)"
+        );
+    }
+
+    #[test]
+    fn test_acl() {
+        // Handle 0x040, first fragment, L2CAP length 4 on the ATT channel.
+        let acl = [
+            0, 0, 0, 1, HCI_ACL, 0x40, 0x20, 0x08, 0x00, 0x04, 0x00, 0x04, 0x00, 0x0a, 0x01, 0x00,
+            0x00,
+        ];
+        let packet = HciPacket::try_from(acl.as_slice()).unwrap();
+        assert!(matches!(
+            packet.payload,
+            HciPayload::Acl {
+                handle: 0x040,
+                boundary: 0b10,
+                ..
+            }
+        ));
+        let summary = summarize(&acl);
+        assert_eq!(summary.protocol, "HCI_ACL");
+        assert_eq!(summary.info, "Rcvd L2CAP CID 0x0004 Handle 0x040 Len=8");
+        assert_eq!(summarize(&acl[..4]).protocol, "Malformed");
+    }
+}
@@ -0,0 +1,504 @@
+use std::net::Ipv4Addr;
+
+use crate::error::KcpdumpError;
+use crate::flows;
+use crate::link::{LINKTYPE_ETHERNET, LinkFrame};
+use crate::packet::{EtherType, EthernetPacket, IPv4Packet, IpProtocol, MacAddress};
+
+/// Direction qualifier of a primitive (`src`, `dst` or either).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dir {
+    Src,
+    Dst,
+    Any,
+}
+
+/// Protocol qualifier of a primitive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Proto {
+    Ether,
+    Ip,
+    Ip6,
+    Arp,
+    Tcp,
+    Udp,
+    Icmp,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Proto(Proto),
+    Host(Dir, Ipv4Addr),
+    Net(Dir, Ipv4Addr, u8),
+    Port(Option<Proto>, Dir, u16, u16),
+    EtherHost(Dir, MacAddress),
+}
+
+/// Capture Filter
+/// A compiled filter in a subset of the libpcap/tcpdump filter syntax:
+/// `host`, `net`, `port`, `portrange`, `ether host`, protocol names
+/// (`ether`, `ip`, `ip6`, `arp`, `tcp`, `udp`, `icmp`) with optional
+/// `src`/`dst` qualifiers, combined with `and`/`or`/`not` and parentheses.
+/// As in tcpdump, a bare value after `and`/`or` reuses the previous qualifiers,
+/// so `port 80 or 443` means `port 80 or port 443`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptureFilter {
+    expr: Option<Expr>,
+}
+
+impl CaptureFilter {
+    /// Compiles a filter expression. An empty expression accepts every packet.
+    pub fn compile(expression: &str) -> Result<Self, KcpdumpError> {
+        let tokens = tokenize(expression)?;
+        if tokens.is_empty() {
+            return Ok(CaptureFilter { expr: None });
+        }
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+            last: None,
+        };
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.peek() {
+            return Err(KcpdumpError::InvalidFilter(format!(
+                "Unexpected token '{}'",
+                token
+            )));
+        }
+        Ok(CaptureFilter { expr: Some(expr) })
+    }
+
+    /// Evaluates the filter against an Ethernet frame.
+    pub fn matches(&self, frame: &[u8]) -> bool {
+        self.matches_link(LINKTYPE_ETHERNET, frame)
+    }
+
+    /// Evaluates the filter against a frame of any link type `LinkFrame`
+    /// decodes. Frames without MAC addresses never match `ether host`.
+    pub fn matches_link(&self, link_type: u32, frame: &[u8]) -> bool {
+        match &self.expr {
+            None => true,
+            Some(expr) => FrameView::decode(link_type, frame).is_some_and(|view| view.eval(expr)),
+        }
+    }
+
+    /// Whether the filter accepts every packet.
+    pub fn is_empty(&self) -> bool {
+        self.expr.is_none()
+    }
+}
+
+fn tokenize(expression: &str) -> Result<Vec<String>, KcpdumpError> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' => {
+                tokens.push(c.to_string());
+                chars.next();
+            }
+            '!' => {
+                tokens.push("not".to_string());
+                chars.next();
+            }
+            '&' | '|' => {
+                chars.next();
+                if chars.next() != Some(c) {
+                    return Err(KcpdumpError::InvalidFilter(format!(
+                        "Expected '{}{}'",
+                        c, c
+                    )));
+                }
+                tokens.push(if c == '&' { "and" } else { "or" }.to_string());
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, '(' | ')' | '!' | '&' | '|') {
+                        break;
+                    }
+                    word.push(c.to_ascii_lowercase());
+                    chars.next();
+                }
+                tokens.push(word);
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Qualifiers of the last primitive, reused by bare values.
+#[derive(Debug, Clone, Copy)]
+struct Qualifiers {
+    proto: Option<Proto>,
+    dir: Dir,
+    kind: Kind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Host,
+    Net,
+    Port,
+    PortRange,
+}
+
+struct Parser {
+    tokens: Vec<String>,
+    position: usize,
+    last: Option<Qualifiers>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.position).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Option<String> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expect_value(&mut self, what: &str) -> Result<String, KcpdumpError> {
+        match self.next() {
+            Some(token) if !matches!(token.as_str(), "and" | "or" | "not" | "(" | ")") => Ok(token),
+            _ => Err(KcpdumpError::InvalidFilter(format!("Expected {}", what))),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, KcpdumpError> {
+        let mut left = self.parse_and()?;
+        while self.peek() == Some("or") {
+            self.next();
+            let right = self.parse_and()?;
+            left = Expr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, KcpdumpError> {
+        let mut left = self.parse_not()?;
+        while self.peek() == Some("and") {
+            self.next();
+            let right = self.parse_not()?;
+            left = Expr::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr, KcpdumpError> {
+        match self.peek() {
+            Some("not") => {
+                self.next();
+                Ok(Expr::Not(Box::new(self.parse_not()?)))
+            }
+            Some("(") => {
+                self.next();
+                let expr = self.parse_or()?;
+                if self.next().as_deref() != Some(")") {
+                    return Err(KcpdumpError::InvalidFilter("Expected ')'".to_string()));
+                }
+                Ok(expr)
+            }
+            Some(_) => self.parse_primitive(),
+            None => Err(KcpdumpError::InvalidFilter(
+                "Unexpected end of expression".to_string(),
+            )),
+        }
+    }
+
+    fn parse_primitive(&mut self) -> Result<Expr, KcpdumpError> {
+        let mut proto = None;
+        let mut dir = None;
+        let mut kind = None;
+
+        if let Some(p) = self.peek().and_then(parse_proto) {
+            proto = Some(p);
+            self.next();
+        }
+        match self.peek() {
+            Some("src") => dir = Some(Dir::Src),
+            Some("dst") => dir = Some(Dir::Dst),
+            _ => {}
+        }
+        if dir.is_some() {
+            self.next();
+        }
+        match self.peek() {
+            Some("host") => kind = Some(Kind::Host),
+            Some("net") => kind = Some(Kind::Net),
+            Some("port") => kind = Some(Kind::Port),
+            Some("portrange") => kind = Some(Kind::PortRange),
+            _ => {}
+        }
+        if kind.is_some() {
+            self.next();
+        }
+
+        let qualifiers = match (proto, dir, kind) {
+            // A bare protocol name, e.g. `tcp`.
+            (Some(p), None, None) => return Ok(Expr::Proto(p)),
+            // A bare value reuses the previous qualifiers.
+            (None, None, None) => self.last.ok_or_else(|| {
+                KcpdumpError::InvalidFilter(format!(
+                    "Unknown primitive '{}'",
+                    self.peek().unwrap_or("")
+                ))
+            })?,
+            _ => Qualifiers {
+                proto,
+                dir: dir.unwrap_or(Dir::Any),
+                kind: kind.unwrap_or(Kind::Host),
+            },
+        };
+        self.last = Some(qualifiers);
+
+        let value = self.expect_value("a value")?;
+        let expr = match (qualifiers.proto, qualifiers.kind) {
+            (Some(Proto::Ether), Kind::Host) => Expr::EtherHost(qualifiers.dir, parse_mac(&value)?),
+            (_, Kind::Host) => Expr::Host(qualifiers.dir, parse_ipv4(&value)?),
+            (_, Kind::Net) => {
+                let (address, prefix) = parse_net(&value)?;
+                Expr::Net(qualifiers.dir, address, prefix)
+            }
+            (proto, Kind::Port) => {
+                let port = parse_port(&value)?;
+                Expr::Port(proto, qualifiers.dir, port, port)
+            }
+            (proto, Kind::PortRange) => {
+                let (low, high) = value.split_once('-').ok_or_else(|| {
+                    KcpdumpError::InvalidFilter(format!("Invalid port range '{}'", value))
+                })?;
+                Expr::Port(proto, qualifiers.dir, parse_port(low)?, parse_port(high)?)
+            }
+        };
+        // `ip host x` additionally requires the protocol itself.
+        Ok(match qualifiers.proto {
+            Some(p @ (Proto::Ip | Proto::Ip6 | Proto::Arp)) if qualifiers.kind != Kind::Port => {
+                Expr::And(Box::new(Expr::Proto(p)), Box::new(expr))
+            }
+            _ => expr,
+        })
+    }
+}
+
+fn parse_proto(token: &str) -> Option<Proto> {
+    match token {
+        "ether" => Some(Proto::Ether),
+        "ip" => Some(Proto::Ip),
+        "ip6" => Some(Proto::Ip6),
+        "arp" => Some(Proto::Arp),
+        "tcp" => Some(Proto::Tcp),
+        "udp" => Some(Proto::Udp),
+        "icmp" => Some(Proto::Icmp),
+        _ => None,
+    }
+}
+
+fn parse_ipv4(value: &str) -> Result<Ipv4Addr, KcpdumpError> {
+    value
+        .parse()
+        .map_err(|_| KcpdumpError::InvalidFilter(format!("Invalid IPv4 address '{}'", value)))
+}
+
+fn parse_net(value: &str) -> Result<(Ipv4Addr, u8), KcpdumpError> {
+    let (address, prefix) = match value.split_once('/') {
+        Some((address, prefix)) => (
+            address,
+            prefix
+                .parse::<u8>()
+                .ok()
+                .filter(|prefix| *prefix <= 32)
+                .ok_or_else(|| {
+                    KcpdumpError::InvalidFilter(format!("Invalid prefix length '{}'", prefix))
+                })?,
+        ),
+        None => (value, 32),
+    };
+    Ok((parse_ipv4(address)?, prefix))
+}
+
+fn parse_port(value: &str) -> Result<u16, KcpdumpError> {
+    value
+        .parse()
+        .map_err(|_| KcpdumpError::InvalidFilter(format!("Invalid port '{}'", value)))
+}
+
+pub(crate) fn parse_mac(value: &str) -> Result<MacAddress, KcpdumpError> {
+    let bytes: Vec<u8> = value
+        .split([':', '-'])
+        .map(|part| u8::from_str_radix(part, 16))
+        .collect::<Result<_, _>>()
+        .map_err(|_| KcpdumpError::InvalidFilter(format!("Invalid MAC address '{}'", value)))?;
+    let bytes: [u8; 6] = bytes
+        .try_into()
+        .map_err(|_| KcpdumpError::InvalidFilter(format!("Invalid MAC address '{}'", value)))?;
+    Ok(MacAddress(bytes))
+}
+
+/// The header fields of a frame that filter primitives look at.
+struct FrameView {
+    /// Source and destination MAC addresses of Ethernet frames.
+    macs: Option<(MacAddress, MacAddress)>,
+    ether_type: EtherType,
+    ipv4: Option<(Ipv4Addr, Ipv4Addr, u8)>,
+    ports: Option<(u16, u16)>,
+}
+
+impl FrameView {
+    fn decode(link_type: u32, frame: &[u8]) -> Option<Self> {
+        let (macs, ether_type, payload) = if link_type == LINKTYPE_ETHERNET {
+            let eth_packet = EthernetPacket::try_from(frame).ok()?;
+            let macs = (eth_packet.header.src_mac, eth_packet.header.dest_mac);
+            (Some(macs), eth_packet.header.ether_type, eth_packet.data)
+        } else {
+            let link_frame = LinkFrame::decode(link_type, frame).ok()?;
+            (None, link_frame.ether_type, link_frame.payload)
+        };
+        let ipv4_packet = (ether_type == EtherType::IPv4)
+            .then(|| IPv4Packet::try_from(payload.as_slice()).ok())
+            .flatten();
+        Some(FrameView {
+            macs,
+            ether_type,
+            ipv4: ipv4_packet.as_ref().map(|ip| {
+                (Ipv4Addr::from(ip.source_ip), Ipv4Addr::from(ip.dest_ip), ip.protocol)
+            }),
+            ports: ipv4_packet.as_ref().and_then(flows::transport_ports),
+        })
+    }
+
+    fn ip_protocol(&self) -> Option<IpProtocol> {
+        self.ipv4.map(|(_, _, protocol)| IpProtocol::from(protocol))
+    }
+
+    fn eval(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::And(left, right) => self.eval(left) && self.eval(right),
+            Expr::Or(left, right) => self.eval(left) || self.eval(right),
+            Expr::Not(inner) => !self.eval(inner),
+            Expr::Proto(proto) => self.has_proto(*proto),
+            Expr::Host(dir, address) => self
+                .ipv4
+                .is_some_and(|(src, dst, _)| by_dir(*dir, src, dst, |a| a == *address)),
+            Expr::Net(dir, network, prefix) => self.ipv4.is_some_and(|(src, dst, _)| {
+                by_dir(*dir, src, dst, |a| in_network(a, *network, *prefix))
+            }),
+            Expr::Port(proto, dir, low, high) => {
+                proto.is_none_or(|proto| self.has_proto(proto))
+                    && self.ports.is_some_and(|(src, dst)| {
+                        by_dir(*dir, src, dst, |port| (*low..=*high).contains(&port))
+                    })
+            }
+            Expr::EtherHost(dir, mac) => self
+                .macs
+                .is_some_and(|(src, dst)| by_dir(*dir, src, dst, |address| address == *mac)),
+        }
+    }
+
+    fn has_proto(&self, proto: Proto) -> bool {
+        match proto {
+            Proto::Ether => true,
+            Proto::Ip => self.ether_type == EtherType::IPv4,
+            Proto::Ip6 => self.ether_type == EtherType::IPv6,
+            Proto::Arp => self.ether_type == EtherType::ARP,
+            Proto::Tcp => self.ip_protocol() == Some(IpProtocol::TCP),
+            Proto::Udp => self.ip_protocol() == Some(IpProtocol::UDP),
+            Proto::Icmp => self.ip_protocol() == Some(IpProtocol::ICMP),
+        }
+    }
+}
+
+fn by_dir<T: Copy>(dir: Dir, src: T, dst: T, test: impl Fn(T) -> bool) -> bool {
+    match dir {
+        Dir::Src => test(src),
+        Dir::Dst => test(dst),
+        Dir::Any => test(src) || test(dst),
+    }
+}
+
+fn in_network(address: Ipv4Addr, network: Ipv4Addr, prefix: u8) -> bool {
+    let mask = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+    u32::from(address) & mask == u32::from(network) & mask
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ethernet + IPv4 + TCP header from 192.168.0.10:50000 to 93.184.216.34:80.
+    fn tcp_frame() -> Vec<u8> {
+        vec![
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0x08, 0x00,
+            0x45, 0x00, 0x00, 0x28, 0x00, 0x01, 0x40, 0x00, 0x40, 0x06, 0x00, 0x00, 0xc0, 0xa8,
+            0x00, 0x0a, 0x5d, 0xb8, 0xd8, 0x22, 0xc3, 0x50, 0x00, 0x50, 0x00, 0x00, 0x03, 0xe8,
+            0x00, 0x00, 0x00, 0x00, 0x50, 0x02, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00,
+        ]
+    }
+
+    fn matches(expression: &str) -> bool {
+        CaptureFilter::compile(expression).unwrap().matches(&tcp_frame())
+    }
+
+    #[test]
+    fn test_primitives() {
+        assert!(matches(""));
+        assert!(matches("tcp"));
+        assert!(!matches("udp"));
+        assert!(matches("host 192.168.0.10"));
+        assert!(matches("src host 192.168.0.10"));
+        assert!(!matches("dst host 192.168.0.10"));
+        assert!(matches("net 192.168.0.0/16"));
+        assert!(!matches("dst net 192.168.0.0/16"));
+        assert!(matches("tcp dst port 80"));
+        assert!(!matches("udp port 80"));
+        assert!(matches("portrange 49152-65535"));
+        assert!(matches("ether src 66:77:88:99:aa:bb"));
+        assert!(!matches("arp"));
+    }
+
+    #[test]
+    fn test_boolean_operators() {
+        assert!(matches("tcp and port 80"));
+        assert!(matches("udp or port 80"));
+        assert!(matches("not udp"));
+        assert!(matches("!(udp || icmp) && host 93.184.216.34"));
+        assert!(matches("port 443 or 80"));
+        assert!(!matches("port 443 or 8080"));
+    }
+
+    #[test]
+    fn test_other_link_types() {
+        // The same IPv4 packet as raw IP and behind a BSD loopback header.
+        let ip = tcp_frame()[14..].to_vec();
+        let mut loopback = 2u32.to_le_bytes().to_vec();
+        loopback.extend_from_slice(&ip);
+        for (link_type, frame) in [(101, ip), (0, loopback)] {
+            let filter = |expression| {
+                CaptureFilter::compile(expression)
+                    .unwrap()
+                    .matches_link(link_type, &frame)
+            };
+            assert!(filter("tcp and host 93.184.216.34 and port 80"));
+            assert!(!filter("udp"));
+            assert!(!filter("ether src 66:77:88:99:aa:bb"));
+            assert!(filter("not ether host 66:77:88:99:aa:bb"));
+        }
+    }
+
+    #[test]
+    fn test_compile_errors() {
+        assert!(CaptureFilter::compile("host").is_err());
+        assert!(CaptureFilter::compile("port http").is_err());
+        assert!(CaptureFilter::compile("(tcp").is_err());
+        assert!(CaptureFilter::compile("tcp udp").is_err());
+        assert!(CaptureFilter::compile("bogus").is_err());
+    }
+}
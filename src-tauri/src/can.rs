@@ -0,0 +1,482 @@
+use std::collections::HashMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc;
+
+use crate::error::KcpdumpError;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::packetlist;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::session::LoadedCapture;
+use crate::summary::PacketSummary;
+
+/// Link type of SocketCAN captures.
+pub const LINKTYPE_CAN_SOCKETCAN: u32 = 227;
+
+const CAN_EFF_FLAG: u32 = 0x8000_0000;
+const CAN_RTR_FLAG: u32 = 0x4000_0000;
+const CAN_ERR_FLAG: u32 = 0x2000_0000;
+const CAN_EFF_MASK: u32 = 0x1fff_ffff;
+const CAN_SFF_MASK: u32 = 0x0000_07ff;
+
+/// Flag in the FD flags byte marking a CAN FD frame.
+const CANFD_FDF: u8 = 0x04;
+const CANFD_BRS: u8 = 0x01;
+const CANFD_ESI: u8 = 0x02;
+
+/// CAN Frame
+/// A CAN 2.0 or CAN FD frame in the SocketCAN layout.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CanFrame {
+    /// 11-bit or 29-bit identifier.
+    pub id: u32,
+    pub extended: bool,
+    pub remote: bool,
+    pub error: bool,
+    pub fd: bool,
+    /// Bit rate switch and error state indicator of CAN FD frames.
+    pub bit_rate_switch: bool,
+    pub error_state: bool,
+    /// Data length code as sent on the bus; for CAN FD, the payload length.
+    pub length: u8,
+    pub data: Vec<u8>,
+}
+
+impl TryFrom<&[u8]> for CanFrame {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 8 {
+            return Err(KcpdumpError::malformed(
+                0,
+                "Data too short for SocketCAN header",
+            ));
+        }
+        // The identifier is in network byte order in pcap files.
+        let can_id = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+        let length = data[4];
+        let flags = data[5];
+        let extended = can_id & CAN_EFF_FLAG != 0;
+        let fd = flags & CANFD_FDF != 0 || data.len() > 16;
+        let max = if fd { 64 } else { 8 };
+        let payload = &data[8..];
+        let available = payload.len().min(usize::from(length)).min(max);
+        Ok(CanFrame {
+            id: can_id & if extended { CAN_EFF_MASK } else { CAN_SFF_MASK },
+            extended,
+            remote: can_id & CAN_RTR_FLAG != 0,
+            error: can_id & CAN_ERR_FLAG != 0,
+            fd,
+            bit_rate_switch: fd && flags & CANFD_BRS != 0,
+            error_state: fd && flags & CANFD_ESI != 0,
+            length,
+            data: payload[..available].to_vec(),
+        })
+    }
+}
+
+impl CanFrame {
+    pub fn id_label(&self) -> String {
+        if self.extended {
+            format!("0x{:08x}", self.id)
+        } else {
+            format!("0x{:03x}", self.id)
+        }
+    }
+}
+
+/// Summarizes a SocketCAN frame for the packet list.
+pub fn summarize(frame: &[u8]) -> PacketSummary {
+    let can = match CanFrame::try_from(frame) {
+        Ok(can) => can,
+        Err(e) => {
+            return PacketSummary {
+                source: String::new(),
+                destination: String::new(),
+                protocol: "Malformed".to_string(),
+                length: frame.len(),
+                info: e.to_string(),
+                flow: None,
+            };
+        }
+    };
+    let info = if can.error {
+        format!("Error frame, class 0x{:08x}", can.id)
+    } else if can.remote {
+        format!(
+            "ID: {}, Remote request, DLC: {}",
+            can.id_label(),
+            can.length
+        )
+    } else {
+        format!(
+            "ID: {}, {}: {}, Data: {}",
+            can.id_label(),
+            if can.fd { "Len" } else { "DLC" },
+            can.length,
+            hex(&can.data, " ")
+        )
+    };
+    PacketSummary {
+        source: can.id_label(),
+        destination: String::new(),
+        protocol: if can.fd { "CAN FD" } else { "CAN" }.to_string(),
+        length: frame.len(),
+        info,
+        flow: None,
+    }
+}
+
+fn hex(data: &[u8], separator: &str) -> String {
+    data.iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+/// DBC Signal
+/// One signal of a message as described in a DBC file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DbcSignal {
+    pub name: String,
+    pub start_bit: u16,
+    pub size: u16,
+    /// Intel byte order (`@1`); Motorola (`@0`) otherwise.
+    pub little_endian: bool,
+    pub signed: bool,
+    pub factor: f64,
+    pub offset: f64,
+    pub unit: String,
+}
+
+impl DbcSignal {
+    fn raw(&self, data: &[u8]) -> Option<u64> {
+        let bit = |index: u16| -> Option<u64> {
+            let byte = data.get(usize::from(index / 8))?;
+            Some(u64::from((byte >> (index % 8)) & 1))
+        };
+        let mut raw = 0u64;
+        if self.little_endian {
+            for index in (0..self.size).rev() {
+                raw = (raw << 1) | bit(self.start_bit + index)?;
+            }
+        } else {
+            // Motorola signals start at their most significant bit and walk
+            // down within a byte, then continue at the top of the next byte.
+            let mut index = self.start_bit;
+            for _ in 0..self.size {
+                raw = (raw << 1) | bit(index)?;
+                index = if index.is_multiple_of(8) {
+                    index + 15
+                } else {
+                    index - 1
+                };
+            }
+        }
+        Some(raw)
+    }
+
+    /// Physical value of the signal in `data`, or `None` if the frame is too short.
+    pub fn decode(&self, data: &[u8]) -> Option<f64> {
+        let raw = self.raw(data)?;
+        let value =
+            if self.signed && self.size > 0 && self.size < 64 && raw >> (self.size - 1) & 1 == 1 {
+                (raw as i64 - (1i64 << self.size)) as f64
+            } else if self.signed {
+                raw as i64 as f64
+            } else {
+                raw as f64
+            };
+        Some(value * self.factor + self.offset)
+    }
+}
+
+/// DBC Message
+#[derive(Debug, Clone, PartialEq)]
+pub struct DbcMessage {
+    pub id: u32,
+    pub extended: bool,
+    pub name: String,
+    pub signals: Vec<DbcSignal>,
+}
+
+/// DBC Database
+/// Message and signal definitions loaded from a DBC file. Only the `BO_` and
+/// `SG_` sections are read; multiplexed signals are decoded unconditionally.
+#[derive(Debug, Clone, Default)]
+pub struct DbcDatabase {
+    pub path: String,
+    messages: HashMap<(u32, bool), DbcMessage>,
+}
+
+impl DbcDatabase {
+    pub fn open(path: &str) -> Result<Self, KcpdumpError> {
+        let text = std::fs::read_to_string(path).map_err(|e| KcpdumpError::file(path, e))?;
+        let mut database = DbcDatabase::parse(&text)?;
+        database.path = path.to_string();
+        Ok(database)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, KcpdumpError> {
+        let mut database = DbcDatabase::default();
+        let mut current = None;
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            let error = |what: &str| {
+                KcpdumpError::InvalidInput(format!("Invalid {} on line {}", what, index + 1))
+            };
+            if let Some(rest) = line.strip_prefix("BO_ ") {
+                // BO_ <id> <name>: <dlc> <sender>
+                let mut parts = rest.split_whitespace();
+                let id: u32 = parts
+                    .next()
+                    .and_then(|id| id.parse().ok())
+                    .ok_or_else(|| error("message"))?;
+                let name = parts
+                    .next()
+                    .map(|name| name.trim_end_matches(':').to_string())
+                    .ok_or_else(|| error("message"))?;
+                let extended = id & CAN_EFF_FLAG != 0;
+                let key = (id & CAN_EFF_MASK, extended);
+                database.messages.insert(
+                    key,
+                    DbcMessage {
+                        id: key.0,
+                        extended,
+                        name,
+                        signals: Vec::new(),
+                    },
+                );
+                current = Some(key);
+            } else if let Some(rest) = line.strip_prefix("SG_ ") {
+                let signal = parse_signal(rest).ok_or_else(|| error("signal"))?;
+                let message = current
+                    .and_then(|key| database.messages.get_mut(&key))
+                    .ok_or_else(|| error("signal outside a message"))?;
+                message.signals.push(signal);
+            } else if !line.is_empty() {
+                current = None;
+            }
+        }
+        Ok(database)
+    }
+
+    pub fn message(&self, frame: &CanFrame) -> Option<&DbcMessage> {
+        self.messages.get(&(frame.id, frame.extended))
+    }
+}
+
+/// Parses `<name> [mux] : <start>|<size>@<order><sign> (<factor>,<offset>) [<min>|<max>] "<unit>" <receivers>`.
+fn parse_signal(text: &str) -> Option<DbcSignal> {
+    let (head, body) = text.split_once(':')?;
+    let name = head.split_whitespace().next()?.to_string();
+    let body = body.trim();
+    let (layout, rest) = body.split_once(' ')?;
+    let (start, rest_layout) = layout.split_once('|')?;
+    let (size, format) = rest_layout.split_once('@')?;
+    let mut format = format.chars();
+    let little_endian = format.next()? == '1';
+    let signed = format.next()? == '-';
+    let scale = rest.trim().strip_prefix('(')?;
+    let (scale, rest) = scale.split_once(')')?;
+    let (factor, offset) = scale.split_once(',')?;
+    let unit = rest
+        .split('"')
+        .nth(1)
+        .map(str::to_string)
+        .unwrap_or_default();
+    Some(DbcSignal {
+        name,
+        start_bit: start.parse().ok()?,
+        size: size.parse().ok()?,
+        little_endian,
+        signed,
+        factor: factor.trim().parse().ok()?,
+        offset: offset.trim().parse().ok()?,
+        unit,
+    })
+}
+
+/// CAN Signal Value
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CanSignalValue {
+    pub name: String,
+    pub value: f64,
+    pub unit: String,
+}
+
+/// CAN Frame Row
+/// A decoded CAN frame, with its signal values when a DBC file describes it.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CanFrameRow {
+    pub capture_id: u32,
+    pub number: usize,
+    /// Seconds since the epoch.
+    pub time: f64,
+    pub id: u32,
+    pub extended: bool,
+    pub remote: bool,
+    pub error: bool,
+    pub fd: bool,
+    /// Payload as lowercase hex.
+    pub data: String,
+    pub message: Option<String>,
+    pub signals: Vec<CanSignalValue>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Lists the CAN frames of the SocketCAN captures among `captures`, decoding
+/// signals with `database` if given.
+pub fn can_frames(
+    captures: &[Arc<LoadedCapture>],
+    database: Option<&DbcDatabase>,
+) -> Result<Vec<CanFrameRow>, KcpdumpError> {
+    let can_captures: Vec<_> = captures
+        .iter()
+        .filter(|capture| capture.header.network == LINKTYPE_CAN_SOCKETCAN)
+        .cloned()
+        .collect();
+    packetlist::merged_packets(&can_captures)
+        .filter_map(|item| {
+            item.map(|(capture_id, number, packet)| {
+                let frame = CanFrame::try_from(packet.data.as_slice()).ok()?;
+                let message = database.and_then(|database| database.message(&frame));
+                let signals = message
+                    .filter(|_| !frame.remote && !frame.error)
+                    .map(|message| {
+                        message
+                            .signals
+                            .iter()
+                            .filter_map(|signal| {
+                                Some(CanSignalValue {
+                                    name: signal.name.clone(),
+                                    value: signal.decode(&frame.data)?,
+                                    unit: signal.unit.clone(),
+                                })
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                Some(CanFrameRow {
+                    capture_id,
+                    number,
+                    time: packet.header.timestamp.as_secs_f64(),
+                    id: frame.id,
+                    extended: frame.extended,
+                    remote: frame.remote,
+                    error: frame.error,
+                    fd: frame.fd,
+                    data: hex(&frame.data, ""),
+                    message: message.map(|message| message.name.clone()),
+                    signals,
+                })
+            })
+            .transpose()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cap::{PcapPacket, PcapPacketHeader};
+    use crate::timefmt::Timestamp;
+
+    const DBC: &str = r#"
+VERSION ""
+
+BO_ 291 EngineData: 8 ECU
+ SG_ EngineSpeed : 0|16@1+ (0.25,0) [0|16383.75] "rpm" Dashboard
+ SG_ CoolantTemp : 16|8@1- (1,-40) [-40|215] "degC" Dashboard
+ SG_ Throttle : 31|8@0+ (0.5,0) [0|127.5] "%" Dashboard
+
+BO_ 2147485696 Extended: 8 ECU
+ SG_ Counter : 0|4@1+ (1,0) [0|15] "" Dashboard
+"#;
+
+    fn socketcan(can_id: u32, flags: u8, data: &[u8]) -> Vec<u8> {
+        let mut frame = can_id.to_be_bytes().to_vec();
+        frame.extend_from_slice(&[data.len() as u8, flags, 0, 0]);
+        frame.extend_from_slice(data);
+        frame
+    }
+
+    #[test]
+    fn test_can_frames() {
+        let frame = socketcan(0x123, 0, &[0x10, 0x27, 0xd8, 0x64]);
+        let can = CanFrame::try_from(frame.as_slice()).unwrap();
+        assert_eq!(can.id, 0x123);
+        assert!(!can.extended && !can.fd);
+        assert_eq!(can.data, [0x10, 0x27, 0xd8, 0x64]);
+        let summary = summarize(&frame);
+        assert_eq!(summary.protocol, "CAN");
+        assert_eq!(summary.info, "ID: 0x123, DLC: 4, Data: 10 27 d8 64");
+
+        let fd = socketcan(0x8000_0800, CANFD_FDF | CANFD_BRS, &[0; 12]);
+        let can = CanFrame::try_from(fd.as_slice()).unwrap();
+        assert!(can.extended && can.fd && can.bit_rate_switch);
+        assert_eq!(can.id, 0x800);
+        assert_eq!(can.data.len(), 12);
+        assert_eq!(summarize(&fd).protocol, "CAN FD");
+
+        let remote = socketcan(0x4000_0123, 0, &[]);
+        assert!(CanFrame::try_from(remote.as_slice()).unwrap().remote);
+    }
+
+    #[test]
+    fn test_dbc_signals() {
+        let database = DbcDatabase::parse(DBC).unwrap();
+        let frame = CanFrame::try_from(
+            socketcan(0x123, 0, &[0x10, 0x27, 0xd8, 0x64, 0, 0, 0, 0]).as_slice(),
+        )
+        .unwrap();
+        let message = database.message(&frame).unwrap();
+        assert_eq!(message.name, "EngineData");
+        let values: Vec<_> = message
+            .signals
+            .iter()
+            .map(|signal| signal.decode(&frame.data).unwrap())
+            .collect();
+        // 0x2710 * 0.25, -40 - 40 and 0x64 * 0.5.
+        assert_eq!(values, vec![2500.0, -80.0, 50.0]);
+        assert_eq!(message.signals[0].unit, "rpm");
+
+        let extended = CanFrame::try_from(socketcan(0x8000_0800, 0, &[0x0b]).as_slice()).unwrap();
+        let message = database.message(&extended).unwrap();
+        assert_eq!(message.signals[0].decode(&extended.data), Some(11.0));
+        assert!(DbcDatabase::parse("BO_ x").is_err());
+    }
+
+    #[test]
+    fn test_can_frame_rows() {
+        let data = socketcan(0x123, 0, &[0x10, 0x27, 0xd8, 0x64, 0, 0, 0, 0]);
+        let packet = PcapPacket {
+            header: PcapPacketHeader {
+                timestamp: Timestamp::from_micros(100, 500_000),
+                incl_len: data.len() as u32,
+                orig_len: data.len() as u32,
+                link_type: None,
+            },
+            data,
+        };
+        let capture = Arc::new(LoadedCapture::from_packets(
+            1,
+            "can.pcap",
+            LINKTYPE_CAN_SOCKETCAN,
+            &[packet],
+        ));
+        let database = DbcDatabase::parse(DBC).unwrap();
+        let rows = can_frames(std::slice::from_ref(&capture), Some(&database)).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].time, 100.5);
+        assert_eq!(rows[0].data, "1027d86400000000");
+        assert_eq!(rows[0].message.as_deref(), Some("EngineData"));
+        assert_eq!(rows[0].signals.len(), 3);
+        assert!(
+            can_frames(std::slice::from_ref(&capture), None).unwrap()[0]
+                .signals
+                .is_empty()
+        );
+    }
+}
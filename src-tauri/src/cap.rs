@@ -1,9 +1,117 @@
 use byteorder::{BigEndian, ByteOrder, LittleEndian};
+#[cfg(not(target_arch = "wasm32"))]
+use std::pin::Pin;
+#[cfg(not(target_arch = "wasm32"))]
+use std::task::{Context, Poll};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{fmt, fs};
+
+#[cfg(not(target_arch = "wasm32"))]
+use futures_executor::block_on;
+#[cfg(not(target_arch = "wasm32"))]
 use tokio::fs::File;
-use tokio::io::{self, AsyncReadExt, BufReader};
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::io::{
+    self, AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt,
+    BufReader, ReadBuf, SeekFrom,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::bpf::CaptureFilter;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::defrag::{DefragPolicy, Ipv4Defragmenter};
+use crate::error::KcpdumpError;
+use crate::timefmt::Timestamp;
+
+// File readers other than `parse_pcap_bytes` need tokio, so wasm32 builds
+// leave them out.
+#[cfg(not(target_arch = "wasm32"))]
+pub mod btsnoop;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod erf;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod live;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod netmon;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod pcapng;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod snoop;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use live::{LiveCapture, NetworkInterface};
+
+/// Magic numbers of libpcap files with microsecond and nanosecond
+/// timestamps, as read from a little-endian file.
+pub const PCAP_MAGIC: u32 = 0xa1b2c3d4;
+pub const PCAP_MAGIC_NANOS: u32 = 0xa1b23c4d;
+
+/// Size of the libpcap file header and of each record header.
+const PCAP_HEADER_LEN: usize = 24;
+const RECORD_HEADER_LEN: usize = 16;
+
+/// Largest record accepted from a capture file; anything bigger is taken as
+/// a damaged length field rather than allocated.
+pub(crate) const MAX_RECORD_LEN: u32 = 64 * 1024 * 1024;
+
+/// Timestamp Resolution
+/// Unit of the sub-second field in libpcap record headers, from coarsest
+/// to finest.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum TimestampResolution {
+    #[default]
+    Microsecond,
+    Nanosecond,
+}
+
+impl TimestampResolution {
+    fn magic_number(self) -> u32 {
+        match self {
+            TimestampResolution::Microsecond => PCAP_MAGIC,
+            TimestampResolution::Nanosecond => PCAP_MAGIC_NANOS,
+        }
+    }
+
+    /// The timestamp of a record header's seconds and sub-second fields.
+    fn timestamp(self, sec: u32, fraction: u32) -> Timestamp {
+        match self {
+            TimestampResolution::Microsecond => Timestamp::from_micros(sec, fraction),
+            TimestampResolution::Nanosecond => Timestamp::new(sec, fraction),
+        }
+    }
+
+    /// Decimal digits of the sub-second field.
+    pub fn digits(self) -> usize {
+        match self {
+            TimestampResolution::Microsecond => 6,
+            TimestampResolution::Nanosecond => 9,
+        }
+    }
+
+    /// The sub-second field of a record header for `timestamp`.
+    pub fn fraction(self, timestamp: Timestamp) -> u32 {
+        match self {
+            TimestampResolution::Microsecond => timestamp.usec(),
+            TimestampResolution::Nanosecond => timestamp.nsec,
+        }
+    }
+}
+
+/// Byte order and timestamp resolution of a libpcap magic number read as
+/// little-endian.
+fn pcap_magic(magic_number: u32) -> Option<(bool, TimestampResolution)> {
+    match magic_number {
+        PCAP_MAGIC => Some((false, TimestampResolution::Microsecond)),
+        PCAP_MAGIC_NANOS => Some((false, TimestampResolution::Nanosecond)),
+        0xd4c3b2a1 => Some((true, TimestampResolution::Microsecond)),
+        0x4d3cb2a1 => Some((true, TimestampResolution::Nanosecond)),
+        _ => None,
+    }
+}
 
 #[repr(C)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PcapHeader {
     pub magic_number: u32,
     pub version_major: u16,
@@ -14,47 +122,298 @@ pub struct PcapHeader {
     pub network: u32,
 }
 
+impl PcapHeader {
+    pub fn resolution(&self) -> TimestampResolution {
+        pcap_magic(self.magic_number)
+            .map(|(_, resolution)| resolution)
+            .unwrap_or_default()
+    }
+}
+
 #[repr(C)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PcapPacket {
     pub header: PcapPacketHeader,
     pub data: Vec<u8>,
 }
 
+impl PcapPacket {
+    /// Returns the link type of the packet, or `network`, the link type of
+    /// its capture, if the packet does not carry its own.
+    pub fn link_type(&self, network: u32) -> u32 {
+        self.header.link_type.unwrap_or(network)
+    }
+}
 
 #[repr(C)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PcapPacketHeader {
-    pub ts_sec: u32,
-    pub ts_usec: u32,
+    pub timestamp: Timestamp,
     pub incl_len: u32,
     pub orig_len: u32,
+    /// Link type of this packet, for formats such as pcapng where it can
+    /// differ from packet to packet.
+    pub link_type: Option<u32>,
+}
+
+/// Index Entry
+/// Where a record starts in its capture file and when it was captured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexEntry {
+    pub offset: u64,
+    pub timestamp: Timestamp,
+}
+
+/// Packet Index
+/// File offset and timestamp of each record of a capture, in file order, so
+/// that `Capture::seek_to_packet` can return to any of them without reading
+/// the records in between. Entries count records as stored, before IPv4
+/// reassembly.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PacketIndex {
+    entries: Vec<IndexEntry>,
+}
+
+impl PacketIndex {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entries(&self) -> &[IndexEntry] {
+        &self.entries
+    }
+
+    /// Adds the entry of the next packet.
+    pub fn push(&mut self, entry: IndexEntry) {
+        self.entries.push(entry);
+    }
+
+    /// The entry of packet `number`, starting at 1.
+    pub fn get(&self, number: usize) -> Option<&IndexEntry> {
+        number.checked_sub(1).and_then(|index| self.entries.get(index))
+    }
+
+    /// Number of the first packet captured at or after `timestamp`, assuming
+    /// packets are in time order.
+    pub fn number_at(&self, timestamp: Timestamp) -> usize {
+        self.entries
+            .partition_point(|entry| entry.timestamp < timestamp)
+            + 1
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// On-disk formats understood by `Capture`.
+#[derive(Debug, Clone)]
+enum Format {
+    Pcap {
+        big_endian: bool,
+        resolution: TimestampResolution,
+    },
+    Erf,
+    Snoop,
+    NetMon(netmon::NetMonReader),
+    Pcapng(pcapng::PcapngReader),
+    Btsnoop { datalink: u32 },
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// A capture file read through tokio, or with blocking calls for
+/// `BlockingCapture`.
+#[derive(Debug)]
+enum CaptureFile {
+    Async(File),
+    Blocking(fs::File),
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl AsyncRead for CaptureFile {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            CaptureFile::Async(file) => Pin::new(file).poll_read(cx, buf),
+            CaptureFile::Blocking(file) => {
+                let read = std::io::Read::read(file, buf.initialize_unfilled())?;
+                buf.advance(read);
+                Poll::Ready(Ok(()))
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl AsyncSeek for CaptureFile {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        match self.get_mut() {
+            CaptureFile::Async(file) => Pin::new(file).start_seek(position),
+            CaptureFile::Blocking(file) => std::io::Seek::seek(file, position).map(|_| ()),
+        }
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        match self.get_mut() {
+            CaptureFile::Async(file) => Pin::new(file).poll_complete(cx),
+            CaptureFile::Blocking(file) => Poll::Ready(std::io::Seek::stream_position(file)),
+        }
+    }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+/// Capture
+/// Reads a capture file record by record. Formats other than libpcap are
+/// converted on the fly, so their header is reported as a pcap header with
+/// the matching link type and timestamp resolution.
 pub struct Capture {
-    reader: BufReader<File>,
+    reader: BufReader<CaptureFile>,
     header: PcapHeader,
-    is_big_endian: bool,
+    format: Format,
+    /// Reassembles IPv4 fragments of Ethernet captures when set.
+    defrag: Option<Ipv4Defragmenter>,
+    /// Drops records before they are returned when set.
+    filter: Option<CaptureFilter>,
+    /// Records read so far, to number the packet in errors.
+    records: usize,
+    /// File offset of the next record, counted from the lengths of the
+    /// records read so that the buffered reader is never asked for it.
+    offset: usize,
+    /// Records seen so far, when indexing is on.
+    index: Option<PacketIndex>,
+    /// File offset of the first record, after the file header.
+    first_record: u64,
+    /// File offset of the record read last.
+    last_record: u64,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Header reported for files converted from another format.
+fn converted_header(network: u32, resolution: TimestampResolution) -> PcapHeader {
+    PcapHeader {
+        magic_number: resolution.magic_number(),
+        version_major: 2,
+        version_minor: 4,
+        thiszone: 0,
+        sigfigs: 0,
+        snaplen: 65535,
+        network,
+    }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 impl Capture {
-    pub async fn from_file(file_path: &str) -> io::Result<Self> {
-        let file = File::open(file_path).await?;
+    /// A capture whose first record starts at `offset`.
+    fn new(
+        reader: BufReader<CaptureFile>,
+        header: PcapHeader,
+        format: Format,
+        offset: usize,
+    ) -> Self {
+        Capture {
+            reader,
+            header,
+            format,
+            defrag: None,
+            filter: None,
+            records: 0,
+            offset,
+            index: None,
+            first_record: offset as u64,
+            last_record: offset as u64,
+        }
+    }
+
+    pub async fn from_file(file_path: &str) -> Result<Self, KcpdumpError> {
+        let file = File::open(file_path)
+            .await
+            .map_err(|e| KcpdumpError::file(file_path, e))?;
+        Self::open(CaptureFile::Async(file), file_path).await
+    }
+
+    async fn open(file: CaptureFile, file_path: &str) -> Result<Self, KcpdumpError> {
         let mut reader = BufReader::new(file);
 
+        let start = reader
+            .fill_buf()
+            .await
+            .map_err(|e| KcpdumpError::file(file_path, e))?;
+        if start.starts_with(snoop::SNOOP_MAGIC) {
+            let snoop_header = snoop::read_header(&mut reader).await?;
+            let network = snoop::link_type(snoop_header.datalink)
+                .ok_or_else(|| invalid_capture("Unsupported snoop datalink type"))?;
+            return Ok(Self::new(
+                reader,
+                converted_header(network, TimestampResolution::Microsecond),
+                Format::Snoop,
+                snoop::FILE_HEADER_LEN,
+            ));
+        }
+        if start.starts_with(btsnoop::BTSNOOP_MAGIC) {
+            let btsnoop_header = btsnoop::read_header(&mut reader).await?;
+            return Ok(Self::new(
+                reader,
+                converted_header(
+                    btsnoop::LINKTYPE_BLUETOOTH_HCI_H4_WITH_PHDR,
+                    TimestampResolution::Microsecond,
+                ),
+                Format::Btsnoop {
+                    datalink: btsnoop_header.datalink,
+                },
+                btsnoop::FILE_HEADER_LEN,
+            ));
+        }
+        if start.starts_with(netmon::NETMON_MAGIC) {
+            let (netmon_header, netmon_reader) = netmon::NetMonReader::open(&mut reader).await?;
+            let network = netmon::link_type(netmon_header.media_type)
+                .ok_or_else(|| invalid_capture("Unsupported NetMon media type"))?;
+            // Frames are found through the frame table; the first one listed
+            // is where reading starts over.
+            let offset = netmon_reader.next_offset() as usize;
+            return Ok(Self::new(
+                reader,
+                converted_header(network, TimestampResolution::Microsecond),
+                Format::NetMon(netmon_reader),
+                offset,
+            ));
+        }
+        if start.starts_with(pcapng::PCAPNG_MAGIC) {
+            let (network, pcapng_reader) = pcapng::PcapngReader::open(&mut reader).await?;
+            let offset = pcapng_reader.offset();
+            return Ok(Self::new(
+                reader,
+                converted_header(network, TimestampResolution::Nanosecond),
+                Format::Pcapng(pcapng_reader),
+                offset,
+            ));
+        }
+        if start.len() >= 4
+            && pcap_magic(LittleEndian::read_u32(start)).is_none()
+            && let Ok(record) = erf::ErfRecordHeader::try_from(start)
+            && erf::looks_like_erf(start)
+        {
+            let network = erf::link_type(record.record_type()).unwrap_or(1);
+            return Ok(Self::new(
+                reader,
+                converted_header(network, TimestampResolution::Nanosecond),
+                Format::Erf,
+                0,
+            ));
+        }
+
         // Read magic number
         let mut magic_number_buf = [0u8; 4];
-        reader.read_exact(&mut magic_number_buf).await?;
+        reader
+            .read_exact(&mut magic_number_buf)
+            .await
+            .map_err(|e| truncated_header(file_path, e))?;
         let magic_number = LittleEndian::read_u32(&magic_number_buf);
-        let is_big_endian = match magic_number {
-            0xa1b2c3d4 => false,
-            0xd4c3b2a1 => true,
-            _ => {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidData,
-                    "Invalid pcap file",
-                ));
-            }
+        let Some((is_big_endian, resolution)) = pcap_magic(magic_number) else {
+            return Err(invalid_capture("Unrecognized file format"));
         };
 
         let read_u16 = |buf: &[u8]| -> u16 {
@@ -75,7 +434,10 @@ impl Capture {
 
         // Read header
         let mut header_buf = [0u8; 20];
-        reader.read_exact(&mut header_buf).await?;
+        reader
+            .read_exact(&mut header_buf)
+            .await
+            .map_err(|e| truncated_header(file_path, e))?;
         let header = PcapHeader {
             magic_number,
             version_major: read_u16(&header_buf[0..2]),
@@ -86,20 +448,251 @@ impl Capture {
             network: read_u32(&header_buf[16..20]),
         };
 
-        Ok(Self {
+        Ok(Self::new(
             reader,
             header,
-            is_big_endian,
-        })
+            Format::Pcap {
+                big_endian: is_big_endian,
+                resolution,
+            },
+            PCAP_HEADER_LEN,
+        ))
     }
 
     pub fn header(&self) -> &PcapHeader {
         &self.header
     }
 
-    pub async fn next_packet(&mut self) -> io::Result<Option<PcapPacket>> {
+    /// Resolution of the timestamps in the file; converted formats report
+    /// nanoseconds when their own timestamps are finer than microseconds.
+    pub fn resolution(&self) -> TimestampResolution {
+        self.header.resolution()
+    }
+
+    /// Byte offset of the next record in the file, e.g. for progress
+    /// reporting. NetMon records are found through its frame table, so
+    /// their offset comes from there.
+    pub fn position(&self) -> u64 {
+        match &self.format {
+            Format::NetMon(netmon) => netmon.next_offset(),
+            _ => self.offset as u64,
+        }
+    }
+
+    /// Switches IPv4 reassembly on or off. With reassembly on, `next_packet`
+    /// holds fragments back and returns each reassembled datagram in place of
+    /// the fragment that completed it; otherwise fragments are returned as
+    /// captured. Only Ethernet captures are reassembled.
+    pub fn set_ipv4_reassembly(&mut self, policy: Option<DefragPolicy>) {
+        self.defrag = policy
+            .filter(|_| self.header.network == 1)
+            .map(Ipv4Defragmenter::new);
+    }
+
+    /// Sets or clears the capture filter. `next_packet` skips records the
+    /// filter rejects before any further decoding, and before IPv4
+    /// reassembly, so that as with tcpdump, fragments without a transport
+    /// header fail `port` primitives. Skipped records are still counted and
+    /// indexed.
+    pub fn set_capture_filter(&mut self, filter: Option<CaptureFilter>) {
+        self.filter = filter.filter(|filter| !filter.is_empty());
+    }
+
+    /// Switches indexing on, starting from `index` (e.g. one kept from an
+    /// earlier pass over the same file), or off. While on, every record read
+    /// past the end of the index is added to it.
+    pub fn set_index(&mut self, index: Option<PacketIndex>) {
+        self.index = index;
+    }
+
+    pub fn index(&self) -> Option<&PacketIndex> {
+        self.index.as_ref()
+    }
+
+    /// Completes the index by reading every record not indexed yet, and
+    /// returns it. The capture is left at its end; use `seek_to_packet` to
+    /// go back.
+    pub async fn build_index(&mut self) -> Result<&PacketIndex, KcpdumpError> {
+        let known = self.index.as_ref().map_or(0, PacketIndex::len);
+        self.seek_to_packet(known.max(1)).await?;
+        while self.next_record(false).await?.is_some() {}
+        Ok(self.index.get_or_insert_default())
+    }
+
+    /// File offset of the record the packet read last came from, e.g. to
+    /// index the packets kept by a capture filter.
+    pub fn packet_offset(&self) -> u64 {
+        self.last_record
+    }
+
+    /// Positions the capture so that the next record read is packet `number`,
+    /// starting at 1. Seeks directly within the index and reads forward,
+    /// indexing as it goes, past its end; fails if the capture has fewer
+    /// packets. Indexing is switched on if it was off, and pending IPv4
+    /// fragments are dropped.
+    pub async fn seek_to_packet(&mut self, number: usize) -> Result<(), KcpdumpError> {
+        if number == 0 {
+            return Err(KcpdumpError::Other("Packet numbers start at 1".to_string()));
+        }
+        let index = self.index.get_or_insert_default();
+        // The last known record up to `number`, or the first record.
+        let target = number.min(index.len()).max(1);
+        let offset = index.get(target).map_or(self.first_record, |entry| entry.offset);
+        self.seek(offset).await?;
+        self.records = target - 1;
+        // Read up to the record before `number` when it lies past the index.
+        while self.records + 1 < number {
+            if self.next_record(false).await?.is_none() {
+                return Err(KcpdumpError::Other(format!(
+                    "No packet {} in capture of {} packets",
+                    number, self.records
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the packet of the record at `offset`, as found in an index
+    /// entry, without counting or indexing it. Reading the records of an
+    /// index in order only seeks where they are not next to each other.
+    pub async fn read_at(&mut self, offset: u64) -> Result<Option<PcapPacket>, KcpdumpError> {
+        if self.position() != offset {
+            let end = match &self.format {
+                Format::Pcapng(pcapng) => pcapng.end() as u64,
+                _ => u64::MAX,
+            };
+            if offset <= end {
+                self.seek(offset).await?;
+            } else {
+                // Blocks past those read may start sections and add
+                // interfaces, so read through them instead of seeking.
+                if self.position() < end {
+                    self.seek(end).await?;
+                }
+                if let Format::Pcapng(pcapng) = &mut self.format {
+                    let found = pcapng.skip_to(&mut self.reader, offset as usize).await?;
+                    self.offset = pcapng.offset();
+                    if !found {
+                        return Ok(None);
+                    }
+                }
+            }
+        }
+        self.last_record = offset;
+        self.read_packet(true).await
+    }
+
+    /// Moves to the record at `offset`, dropping pending IPv4 fragments.
+    async fn seek(&mut self, offset: u64) -> io::Result<()> {
+        match &mut self.format {
+            Format::NetMon(netmon) => netmon.seek_to_offset(offset),
+            format => {
+                if let Format::Pcapng(pcapng) = format {
+                    pcapng.seek(offset as usize);
+                }
+                self.reader.seek(SeekFrom::Start(offset)).await?;
+            }
+        }
+        self.offset = offset as usize;
+        if let Some(defrag) = &mut self.defrag {
+            defrag.clear();
+        }
+        Ok(())
+    }
+
+    /// Reads the next record as stored, counting and indexing it. Without
+    /// `keep_data`, record data is skipped and left empty.
+    async fn next_record(&mut self, keep_data: bool) -> Result<Option<PcapPacket>, KcpdumpError> {
+        let number = self.records + 1;
+        let offset = self.position();
+        let packet = self
+            .read_packet(keep_data)
+            .await
+            .map_err(|e| e.at_packet(number))?;
+        if let Some(packet) = &packet {
+            self.records = number;
+            self.last_record = offset;
+            // Only records following the indexed ones are added.
+            if let Some(index) = &mut self.index
+                && index.len() + 1 == number
+            {
+                index.entries.push(IndexEntry {
+                    offset,
+                    timestamp: packet.header.timestamp,
+                });
+            }
+        }
+        Ok(packet)
+    }
+
+    /// Reads the next packet. A record cut short in its header ends the
+    /// capture; one cut short in its data is a `MalformedPacket` error with
+    /// the packet number and the record's file offset.
+    pub async fn next_packet(&mut self) -> Result<Option<PcapPacket>, KcpdumpError> {
+        loop {
+            let Some(packet) = self.next_record(true).await? else {
+                return Ok(None);
+            };
+            if let Some(filter) = &self.filter
+                && !filter.matches_link(self.header.network, &packet.data)
+            {
+                continue;
+            }
+            match &mut self.defrag {
+                Some(defrag) => {
+                    if let Some(packet) = defrag.push_frame(packet) {
+                        return Ok(Some(packet));
+                    }
+                }
+                None => return Ok(Some(packet)),
+            }
+        }
+    }
+
+    /// Reads the header of the next record and skips its data without
+    /// copying it. Records are returned as stored, without IPv4 reassembly.
+    pub async fn next_header(&mut self) -> Result<Option<PcapPacketHeader>, KcpdumpError> {
+        Ok(self.next_record(false).await?.map(|packet| packet.header))
+    }
+
+    /// Moves the offset past a record read with its length.
+    fn advance(&mut self, record: Option<(PcapPacket, usize)>) -> Option<PcapPacket> {
+        record.map(|(packet, length)| {
+            self.offset += length;
+            packet
+        })
+    }
+
+    async fn read_packet(&mut self, keep_data: bool) -> Result<Option<PcapPacket>, KcpdumpError> {
+        let (is_big_endian, resolution) = match &mut self.format {
+            Format::Pcap {
+                big_endian,
+                resolution,
+            } => (*big_endian, *resolution),
+            Format::NetMon(netmon) => {
+                return Ok(netmon.next_packet(&mut self.reader, keep_data).await?);
+            }
+            Format::Pcapng(pcapng) => {
+                let packet = pcapng.next_packet(&mut self.reader, keep_data).await?;
+                self.offset = pcapng.offset();
+                return Ok(packet);
+            }
+            Format::Erf => {
+                let record = erf::next_packet(&mut self.reader, keep_data).await?;
+                return Ok(self.advance(record));
+            }
+            Format::Snoop => {
+                let record = snoop::next_packet(&mut self.reader, keep_data).await?;
+                return Ok(self.advance(record));
+            }
+            Format::Btsnoop { datalink } => {
+                let datalink = *datalink;
+                let record = btsnoop::next_packet(&mut self.reader, datalink, keep_data).await?;
+                return Ok(self.advance(record));
+            }
+        };
         let read_u32 = |buf: &[u8]| -> u32 {
-            if self.is_big_endian {
+            if is_big_endian {
                 BigEndian::read_u32(buf)
             } else {
                 LittleEndian::read_u32(buf)
@@ -110,14 +703,34 @@ impl Capture {
         match self.reader.read_exact(&mut packet_header_buf).await {
             Ok(_) => {
                 let packet_header = PcapPacketHeader {
-                    ts_sec: read_u32(&packet_header_buf[0..4]),
-                    ts_usec: read_u32(&packet_header_buf[4..8]),
+                    timestamp: resolution.timestamp(
+                        read_u32(&packet_header_buf[0..4]),
+                        read_u32(&packet_header_buf[4..8]),
+                    ),
                     incl_len: read_u32(&packet_header_buf[8..12]),
                     orig_len: read_u32(&packet_header_buf[12..16]),
+                    link_type: None,
                 };
+                if packet_header.incl_len > MAX_RECORD_LEN {
+                    return Err(KcpdumpError::malformed(self.offset, "Record length too large"));
+                }
 
-                let mut packet_data = vec![0u8; packet_header.incl_len as usize];
-                self.reader.read_exact(&mut packet_data).await?;
+                let length = packet_header.incl_len as usize;
+                let mut packet_data = Vec::new();
+                let read = if keep_data {
+                    packet_data.resize(length, 0);
+                    self.reader.read_exact(&mut packet_data).await.map(|_| ())
+                } else {
+                    skip_bytes(&mut self.reader, length).await
+                };
+                match read {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                        return Err(KcpdumpError::malformed(self.offset, "Truncated packet record"));
+                    }
+                    Err(e) => return Err(KcpdumpError::io("Failed to read packet", e)),
+                }
+                self.offset += RECORD_HEADER_LEN + length;
 
                 Ok(Some(PcapPacket {
                     header: packet_header,
@@ -125,16 +738,227 @@ impl Capture {
                 }))
             }
             Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
-            Err(e) => Err(e),
+            Err(e) => Err(KcpdumpError::io("Failed to read packet", e)),
         }
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+/// Blocking Capture
+/// A `Capture` read with blocking file I/O, for synchronous code that reads
+/// packets from disk on demand instead of holding them in memory. Its reads
+/// are driven on the calling thread by a futures executor, not by tokio, so
+/// it works both inside and outside a runtime.
+pub struct BlockingCapture {
+    capture: Capture,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl BlockingCapture {
+    pub fn from_file(file_path: &str) -> Result<Self, KcpdumpError> {
+        let file = fs::File::open(file_path).map_err(|e| KcpdumpError::file(file_path, e))?;
+        let capture = block_on(Capture::open(CaptureFile::Blocking(file), file_path))?;
+        Ok(BlockingCapture { capture })
+    }
+
+    /// Goes on reading the file `capture` was opened from with blocking I/O.
+    /// What the capture has learned about the file is kept, such as the
+    /// sections of a pcapng file, so that every record read can be read
+    /// again.
+    pub fn from_capture(mut capture: Capture, file_path: &str) -> Result<Self, KcpdumpError> {
+        let file = fs::File::open(file_path).map_err(|e| KcpdumpError::file(file_path, e))?;
+        capture.reader = BufReader::new(CaptureFile::Blocking(file));
+        block_on(capture.seek(capture.position()))?;
+        Ok(BlockingCapture { capture })
+    }
+
+    pub fn header(&self) -> &PcapHeader {
+        &self.capture.header
+    }
+
+    /// Reads the packet of the record at `offset`, as `Capture::read_at`
+    /// does.
+    pub fn read_at(&mut self, offset: u64) -> Result<Option<PcapPacket>, KcpdumpError> {
+        block_on(self.capture.read_at(offset))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl fmt::Debug for BlockingCapture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlockingCapture")
+            .field("header", &self.capture.header)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Consumes `length` bytes from the buffer, refilling it as needed, so that
+/// skipped record data is never copied.
+async fn skip_bytes<R: AsyncBufRead + Unpin>(reader: &mut R, mut length: usize) -> io::Result<()> {
+    while length > 0 {
+        let available = reader.fill_buf().await?.len();
+        if available == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let consumed = available.min(length);
+        reader.consume(consumed);
+        length -= consumed;
+    }
+    Ok(())
+}
+
+fn invalid_capture(reason: &str) -> KcpdumpError {
+    KcpdumpError::InvalidCapture {
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn truncated_header(file_path: &str, error: io::Error) -> KcpdumpError {
+    match error.kind() {
+        io::ErrorKind::UnexpectedEof => invalid_capture("Truncated pcap header"),
+        _ => KcpdumpError::file(file_path, error),
+    }
+}
+
+/// Parses a libpcap file held in memory. Unlike `Capture`, this needs neither
+/// a runtime nor a filesystem, so it also serves targets such as wasm32.
+/// A trailing record cut short in its header ends the capture, as in
+/// `Capture::next_packet`.
+pub fn parse_pcap_bytes(data: &[u8]) -> Result<(PcapHeader, Vec<PcapPacket>), KcpdumpError> {
+    if data.len() < PCAP_HEADER_LEN {
+        return Err(invalid_capture("Truncated pcap header"));
+    }
+    let magic_number = LittleEndian::read_u32(&data[0..4]);
+    let Some((is_big_endian, resolution)) = pcap_magic(magic_number) else {
+        return Err(invalid_capture("Unrecognized file format"));
+    };
+    let read_u16 = |buf: &[u8]| {
+        if is_big_endian {
+            BigEndian::read_u16(buf)
+        } else {
+            LittleEndian::read_u16(buf)
+        }
+    };
+    let read_u32 = |buf: &[u8]| {
+        if is_big_endian {
+            BigEndian::read_u32(buf)
+        } else {
+            LittleEndian::read_u32(buf)
+        }
+    };
+    let header = PcapHeader {
+        magic_number,
+        version_major: read_u16(&data[4..6]),
+        version_minor: read_u16(&data[6..8]),
+        thiszone: LittleEndian::read_i32(&data[8..12]),
+        sigfigs: read_u32(&data[12..16]),
+        snaplen: read_u32(&data[16..20]),
+        network: read_u32(&data[20..24]),
+    };
+
+    let mut packets = Vec::new();
+    let mut rest = &data[PCAP_HEADER_LEN..];
+    while rest.len() >= RECORD_HEADER_LEN {
+        let packet_header = PcapPacketHeader {
+            timestamp: resolution.timestamp(read_u32(&rest[0..4]), read_u32(&rest[4..8])),
+            incl_len: read_u32(&rest[8..12]),
+            orig_len: read_u32(&rest[12..16]),
+            link_type: None,
+        };
+        let offset = data.len() - rest.len();
+        if packet_header.incl_len > MAX_RECORD_LEN {
+            return Err(KcpdumpError::malformed(offset, "Record length too large")
+                .at_packet(packets.len() + 1));
+        }
+        let end = RECORD_HEADER_LEN.checked_add(packet_header.incl_len as usize);
+        let packet_data = end.and_then(|end| rest.get(RECORD_HEADER_LEN..end)).ok_or_else(|| {
+            KcpdumpError::malformed(offset, "Truncated packet record").at_packet(packets.len() + 1)
+        })?;
+        packets.push(PcapPacket {
+            header: packet_header,
+            data: packet_data.to_vec(),
+        });
+        rest = &rest[RECORD_HEADER_LEN + packet_data.len()..];
+    }
+    Ok((header, packets))
+}
+
+/// Pcap Writer
+/// Writes packets as a classic little-endian libpcap file, with microsecond
+/// timestamps unless created `with_resolution`.
+pub struct PcapWriter<W: std::io::Write> {
+    writer: W,
+    resolution: TimestampResolution,
+    /// Bytes written so far.
+    position: u64,
+}
+
+impl<W: std::io::Write> PcapWriter<W> {
+    /// Writes the file header and returns a writer ready for packets.
+    pub fn new(writer: W, network: u32, snaplen: u32) -> std::io::Result<Self> {
+        Self::with_resolution(writer, network, snaplen, TimestampResolution::Microsecond)
+    }
+
+    /// Like `new`, with timestamps written at `resolution`.
+    pub fn with_resolution(
+        mut writer: W,
+        network: u32,
+        snaplen: u32,
+        resolution: TimestampResolution,
+    ) -> std::io::Result<Self> {
+        let mut header = [0u8; 24];
+        LittleEndian::write_u32(&mut header[0..4], resolution.magic_number());
+        LittleEndian::write_u16(&mut header[4..6], 2);
+        LittleEndian::write_u16(&mut header[6..8], 4);
+        LittleEndian::write_u32(&mut header[16..20], snaplen);
+        LittleEndian::write_u32(&mut header[20..24], network);
+        writer.write_all(&header)?;
+        Ok(PcapWriter {
+            writer,
+            resolution,
+            position: header.len() as u64,
+        })
+    }
+
+    /// File offset of the next record, as found in a `PacketIndex` entry.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    pub fn write_packet(&mut self, packet: &PcapPacket) -> std::io::Result<()> {
+        let mut header = [0u8; 16];
+        let timestamp = packet.header.timestamp;
+        LittleEndian::write_u32(&mut header[0..4], timestamp.sec);
+        LittleEndian::write_u32(&mut header[4..8], self.resolution.fraction(timestamp));
+        LittleEndian::write_u32(&mut header[8..12], packet.data.len() as u32);
+        LittleEndian::write_u32(&mut header[12..16], packet.header.orig_len);
+        self.writer.write_all(&header)?;
+        self.writer.write_all(&packet.data)?;
+        self.position += (header.len() + packet.data.len()) as u64;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::packet::EthernetPacket;
 
-    use super::Capture;
+    use super::{
+        Capture, CaptureFilter, PacketIndex, PcapPacket, PcapPacketHeader, PcapWriter,
+        TimestampResolution,
+    };
+    use crate::error::KcpdumpError;
+    use crate::timefmt::Timestamp;
     use tokio::fs::File;
     use tokio::io::AsyncWriteExt;
 
@@ -179,6 +1003,234 @@ mod tests {
         tokio::fs::remove_file(temp_file_path).await.unwrap();
     }
 
+    #[tokio::test]
+    async fn test_pcap_writer_roundtrip() {
+        let temp_file_path = "test_writer.pcap";
+        let packet = PcapPacket {
+            header: PcapPacketHeader {
+                timestamp: Timestamp::from_micros(1_700_000_000, 42),
+                incl_len: 4,
+                orig_len: 60,
+                link_type: None,
+            },
+            data: vec![0xde, 0xad, 0xbe, 0xef],
+        };
+        let mut writer = PcapWriter::new(Vec::new(), 1, 65535).unwrap();
+        writer.write_packet(&packet).unwrap();
+        tokio::fs::write(temp_file_path, writer.into_inner()).await.unwrap();
+
+        let mut capture = Capture::from_file(temp_file_path).await.unwrap();
+        assert_eq!(capture.header().network, 1);
+        let read = capture.next_packet().await.unwrap().unwrap();
+        assert_eq!(read.header.timestamp, packet.header.timestamp);
+        assert_eq!(read.header.orig_len, 60);
+        assert_eq!(read.data, packet.data);
+        assert!(capture.next_packet().await.unwrap().is_none());
+
+        tokio::fs::remove_file(temp_file_path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_nanosecond_capture() {
+        let temp_file_path = "test_nanos.pcap";
+        let packet = PcapPacket {
+            header: PcapPacketHeader {
+                timestamp: Timestamp::new(1_700_000_000, 123_456_789),
+                incl_len: 4,
+                orig_len: 4,
+                link_type: None,
+            },
+            data: vec![0xde, 0xad, 0xbe, 0xef],
+        };
+        let mut writer =
+            PcapWriter::with_resolution(Vec::new(), 1, 65535, TimestampResolution::Nanosecond)
+                .unwrap();
+        writer.write_packet(&packet).unwrap();
+        let data = writer.into_inner();
+        assert_eq!(data[..4], [0x4d, 0x3c, 0xb2, 0xa1]);
+        tokio::fs::write(temp_file_path, &data).await.unwrap();
+
+        let mut capture = Capture::from_file(temp_file_path).await.unwrap();
+        assert_eq!(capture.resolution(), TimestampResolution::Nanosecond);
+        let read = capture.next_packet().await.unwrap().unwrap();
+        assert_eq!(read.header.timestamp, packet.header.timestamp);
+        assert_eq!(
+            read.header.timestamp.to_iso8601(),
+            "2023-11-14T22:13:20.123456789Z"
+        );
+        let (_, packets) = super::parse_pcap_bytes(&data).unwrap();
+        assert_eq!(packets[0].header.timestamp, packet.header.timestamp);
+
+        // A microsecond writer drops the nanoseconds.
+        let mut writer = PcapWriter::new(Vec::new(), 1, 65535).unwrap();
+        writer.write_packet(&packet).unwrap();
+        let (header, packets) = super::parse_pcap_bytes(&writer.into_inner()).unwrap();
+        assert_eq!(header.resolution(), TimestampResolution::Microsecond);
+        assert_eq!(packets[0].header.timestamp.nsec, 123_456_000);
+
+        tokio::fs::remove_file(temp_file_path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_truncated_record() {
+        let temp_file_path = "test_truncated.pcap";
+        let packet = PcapPacket {
+            header: PcapPacketHeader {
+                timestamp: Timestamp::from_micros(1_700_000_000, 0),
+                incl_len: 4,
+                orig_len: 4,
+                link_type: None,
+            },
+            data: vec![0xde, 0xad, 0xbe, 0xef],
+        };
+        let mut writer = PcapWriter::new(Vec::new(), 1, 65535).unwrap();
+        writer.write_packet(&packet).unwrap();
+        writer.write_packet(&packet).unwrap();
+        let mut data = writer.into_inner();
+        data.pop();
+        tokio::fs::write(temp_file_path, &data).await.unwrap();
+
+        let mut capture = Capture::from_file(temp_file_path).await.unwrap();
+        assert!(capture.next_packet().await.unwrap().is_some());
+        let error = capture.next_packet().await.unwrap_err();
+        assert!(matches!(
+            error,
+            KcpdumpError::MalformedPacket {
+                index: Some(2),
+                offset: 44,
+                ..
+            }
+        ));
+        assert!(matches!(
+            super::parse_pcap_bytes(&data),
+            Err(KcpdumpError::MalformedPacket {
+                index: Some(2),
+                offset: 44,
+                ..
+            })
+        ));
+        tokio::fs::remove_file(temp_file_path).await.unwrap();
+
+        // A length past the cap is rejected before it is used.
+        data[32..36].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            super::parse_pcap_bytes(&data),
+            Err(KcpdumpError::MalformedPacket {
+                index: Some(1),
+                offset: 24,
+                ..
+            })
+        ));
+
+        assert!(matches!(
+            Capture::from_file(temp_file_path).await,
+            Err(KcpdumpError::FileNotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_seek_to_packet() {
+        let temp_file_path = "test_seek.pcap";
+        let mut writer = PcapWriter::new(Vec::new(), 1, 65535).unwrap();
+        for number in 1..=3u8 {
+            writer
+                .write_packet(&PcapPacket {
+                    header: PcapPacketHeader {
+                        timestamp: Timestamp::from_micros(1_700_000_000 + u32::from(number), 0),
+                        incl_len: u32::from(number),
+                        orig_len: u32::from(number),
+                        link_type: None,
+                    },
+                    data: vec![number; number as usize],
+                })
+                .unwrap();
+        }
+        tokio::fs::write(temp_file_path, writer.into_inner()).await.unwrap();
+
+        // Seeking past the index reads forward and indexes on the way.
+        let mut capture = Capture::from_file(temp_file_path).await.unwrap();
+        capture.seek_to_packet(3).await.unwrap();
+        assert_eq!(capture.next_packet().await.unwrap().unwrap().data, vec![3; 3]);
+        assert_eq!(capture.index().unwrap().len(), 3);
+        capture.seek_to_packet(2).await.unwrap();
+        assert_eq!(capture.next_packet().await.unwrap().unwrap().data, vec![2; 2]);
+        assert!(capture.seek_to_packet(5).await.is_err());
+
+        let mut capture = Capture::from_file(temp_file_path).await.unwrap();
+        capture.next_packet().await.unwrap();
+        let index = capture.build_index().await.unwrap().clone();
+        assert_eq!(index.len(), 3);
+        assert_eq!(index.get(1).unwrap().offset, 24);
+        assert_eq!(index.get(3).unwrap().offset, 24 + 17 + 18);
+        assert_eq!(index.number_at(Timestamp::from_micros(1_700_000_002, 0)), 2);
+        assert_eq!(index.number_at(Timestamp::from_micros(1_700_000_009, 0)), 4);
+
+        // An index kept from an earlier pass is reused.
+        let mut capture = Capture::from_file(temp_file_path).await.unwrap();
+        capture.set_index(Some(index));
+        capture.seek_to_packet(1).await.unwrap();
+        assert_eq!(capture.next_packet().await.unwrap().unwrap().data, vec![1]);
+        tokio::fs::remove_file(temp_file_path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_capture_filter() {
+        let temp_file_path = "test_capture_filter.pcap";
+        let mut writer = PcapWriter::new(Vec::new(), 1, 65535).unwrap();
+        for (sec, ether_type) in [(1, [0x08, 0x06]), (2, [0x08, 0x00]), (3, [0x08, 0x06])] {
+            let mut data = vec![0u8; 12];
+            data.extend_from_slice(&ether_type);
+            data.resize(60, 0);
+            writer
+                .write_packet(&PcapPacket {
+                    header: PcapPacketHeader {
+                        timestamp: Timestamp::new(sec, 0),
+                        incl_len: 60,
+                        orig_len: 60,
+                        link_type: None,
+                    },
+                    data,
+                })
+                .unwrap();
+        }
+        tokio::fs::write(temp_file_path, writer.into_inner()).await.unwrap();
+
+        let mut capture = Capture::from_file(temp_file_path).await.unwrap();
+        capture.set_capture_filter(Some(CaptureFilter::compile("arp").unwrap()));
+        capture.set_index(Some(PacketIndex::default()));
+        let first = capture.next_packet().await.unwrap().unwrap();
+        let second = capture.next_packet().await.unwrap().unwrap();
+        assert!(capture.next_packet().await.unwrap().is_none());
+        assert_eq!(first.header.timestamp, Timestamp::new(1, 0));
+        assert_eq!(second.header.timestamp, Timestamp::new(3, 0));
+        // Rejected records still take their place in the index.
+        assert_eq!(capture.index().unwrap().len(), 3);
+
+        capture.set_capture_filter(Some(CaptureFilter::compile("").unwrap()));
+        capture.seek_to_packet(2).await.unwrap();
+        let packet = capture.next_packet().await.unwrap().unwrap();
+        assert_eq!(packet.header.timestamp, Timestamp::new(2, 0));
+        tokio::fs::remove_file(temp_file_path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_parse_pcap_bytes() {
+        let data = tokio::fs::read("sample.pcap").await.unwrap();
+        let (header, packets) = super::parse_pcap_bytes(&data).unwrap();
+        assert_eq!(header.network, 1);
+
+        let mut capture = Capture::from_file("sample.pcap").await.unwrap();
+        for packet in &packets {
+            let read = capture.next_packet().await.unwrap().unwrap();
+            assert_eq!(read.header.timestamp, packet.header.timestamp);
+            assert_eq!(read.data, packet.data);
+        }
+        assert!(capture.next_packet().await.unwrap().is_none());
+
+        assert!(super::parse_pcap_bytes(&data[..data.len() - 1]).is_err());
+        assert!(super::parse_pcap_bytes(&data[..20]).is_err());
+    }
+
     #[tokio::test]
     async fn test_tcpdump_file() {
         let temp_file_path = "sample.pcap";
@@ -188,7 +1240,7 @@ mod tests {
         assert_eq!(header.magic_number, 0xa1b2c3d4);
         // Sum packet number
         let mut packet_count = 0;
-        while let Some(_) = capture.next_packet().await.unwrap() {
+        while capture.next_packet().await.unwrap().is_some() {
             packet_count += 1;
             // println!("{:?}", packet);
         }
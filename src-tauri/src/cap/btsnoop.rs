@@ -0,0 +1,213 @@
+use byteorder::{BigEndian, ByteOrder};
+use tokio::io::{self, AsyncBufRead, AsyncRead, AsyncReadExt};
+
+use super::{MAX_RECORD_LEN, PcapPacket, PcapPacketHeader, invalid_capture, skip_bytes};
+use crate::error::KcpdumpError;
+use crate::timefmt::Timestamp;
+
+/// File magic of btsnoop logs.
+pub const BTSNOOP_MAGIC: &[u8; 8] = b"btsnoop\0";
+
+/// Datalink types of HCI logs without and with the H4 packet type byte.
+pub const DATALINK_H1: u32 = 1001;
+pub const DATALINK_H4: u32 = 1002;
+
+/// Link type of the converted packets.
+pub use crate::bluetooth::LINKTYPE_BLUETOOTH_HCI_H4_WITH_PHDR;
+
+/// Microseconds from 0000-01-01 to the Unix epoch, as used by btsnoop timestamps.
+const EPOCH_OFFSET: i64 = 0x00dc_ddb3_0f2f_8000;
+
+pub(crate) const FILE_HEADER_LEN: usize = 16;
+const RECORD_HEADER_LEN: usize = 24;
+
+const FLAG_RECEIVED: u32 = 0x01;
+const FLAG_COMMAND_OR_EVENT: u32 = 0x02;
+
+/// Btsnoop Header
+#[derive(Debug, Clone, PartialEq)]
+pub struct BtsnoopHeader {
+    pub version: u32,
+    pub datalink: u32,
+}
+
+impl TryFrom<&[u8]> for BtsnoopHeader {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < FILE_HEADER_LEN {
+            return Err(invalid_capture("Data too short for btsnoop header"));
+        }
+        if &data[0..8] != BTSNOOP_MAGIC {
+            return Err(invalid_capture("Invalid btsnoop magic"));
+        }
+        let version = BigEndian::read_u32(&data[8..12]);
+        if version != 1 {
+            return Err(invalid_capture("Unsupported btsnoop version"));
+        }
+        let datalink = BigEndian::read_u32(&data[12..16]);
+        if !matches!(datalink, DATALINK_H1 | DATALINK_H4) {
+            return Err(invalid_capture("Unsupported btsnoop datalink type"));
+        }
+        Ok(BtsnoopHeader { version, datalink })
+    }
+}
+
+/// Reads and validates the file header.
+pub async fn read_header<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<BtsnoopHeader> {
+    let mut header_buf = [0u8; FILE_HEADER_LEN];
+    reader.read_exact(&mut header_buf).await?;
+    BtsnoopHeader::try_from(header_buf.as_slice()).map_err(io::Error::from)
+}
+
+/// Reads the next record and converts it to an H4 frame with a direction
+/// header. H1 records get their packet type byte from the record flags.
+/// Without `keep_data`, the record is skipped and the data left empty. The
+/// packet is returned with the length of the record in the file.
+pub async fn next_packet<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    datalink: u32,
+    keep_data: bool,
+) -> io::Result<Option<(PcapPacket, usize)>> {
+    let mut record_buf = [0u8; RECORD_HEADER_LEN];
+    match reader.read_exact(&mut record_buf).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let orig_len = BigEndian::read_u32(&record_buf[0..4]);
+    let incl_len = BigEndian::read_u32(&record_buf[4..8]);
+    if incl_len > MAX_RECORD_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Btsnoop record length too large",
+        ));
+    }
+    let flags = BigEndian::read_u32(&record_buf[8..12]);
+    let micros = BigEndian::read_i64(&record_buf[16..24]) - EPOCH_OFFSET;
+
+    let mut data = (flags & FLAG_RECEIVED).to_be_bytes().to_vec();
+    let mut prefix = 4;
+    if datalink == DATALINK_H1 {
+        let packet_type = match (
+            flags & FLAG_COMMAND_OR_EVENT != 0,
+            flags & FLAG_RECEIVED != 0,
+        ) {
+            (true, false) => 0x01,
+            (true, true) => 0x04,
+            (false, _) => 0x02,
+        };
+        data.push(packet_type);
+        prefix += 1;
+    }
+    if keep_data {
+        let mut record = vec![0u8; incl_len as usize];
+        reader.read_exact(&mut record).await?;
+        data.extend_from_slice(&record);
+    } else {
+        skip_bytes(reader, incl_len as usize).await?;
+        data.clear();
+    }
+
+    let packet = PcapPacket {
+        header: PcapPacketHeader {
+            timestamp: Timestamp::from_nanos(micros * 1000),
+            incl_len: prefix + incl_len,
+            orig_len: orig_len + prefix,
+            link_type: None,
+        },
+        data,
+    };
+    Ok(Some((packet, RECORD_HEADER_LEN + incl_len as usize)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cap::Capture;
+
+    fn btsnoop_file(datalink: u32, records: &[(u32, &[u8])]) -> Vec<u8> {
+        let mut data = BTSNOOP_MAGIC.to_vec();
+        data.extend_from_slice(&1u32.to_be_bytes());
+        data.extend_from_slice(&datalink.to_be_bytes());
+        for (index, (flags, record)) in records.iter().enumerate() {
+            let micros = EPOCH_OFFSET + 1_700_000_000_000_000 + index as i64 * 250_000;
+            data.extend_from_slice(&(record.len() as u32).to_be_bytes());
+            data.extend_from_slice(&(record.len() as u32).to_be_bytes());
+            data.extend_from_slice(&flags.to_be_bytes());
+            data.extend_from_slice(&0u32.to_be_bytes());
+            data.extend_from_slice(&micros.to_be_bytes());
+            data.extend_from_slice(record);
+        }
+        data
+    }
+
+    #[test]
+    fn test_header() {
+        let data = btsnoop_file(DATALINK_H4, &[]);
+        let header = BtsnoopHeader::try_from(data.as_slice()).unwrap();
+        assert_eq!(header.datalink, DATALINK_H4);
+        let data = btsnoop_file(2001, &[]);
+        assert!(BtsnoopHeader::try_from(data.as_slice()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_btsnoop_h4_capture() {
+        let temp_file_path = "test_h4.btsnoop";
+        let reset = [0x01, 0x03, 0x0c, 0x00];
+        let complete = [0x04, 0x0e, 0x04, 0x01, 0x03, 0x0c, 0x00];
+        let data = btsnoop_file(DATALINK_H4, &[(0x02, &reset), (0x03, &complete)]);
+        tokio::fs::write(temp_file_path, &data).await.unwrap();
+
+        let mut capture = Capture::from_file(temp_file_path).await.unwrap();
+        assert_eq!(
+            capture.header().network,
+            LINKTYPE_BLUETOOTH_HCI_H4_WITH_PHDR
+        );
+        let first = capture.next_packet().await.unwrap().unwrap();
+        assert_eq!(first.header.timestamp, Timestamp::new(1_700_000_000, 0));
+        assert_eq!(first.data, [0, 0, 0, 0, 0x01, 0x03, 0x0c, 0x00]);
+        let second = capture.next_packet().await.unwrap().unwrap();
+        assert_eq!(second.header.timestamp.usec(), 250_000);
+        assert_eq!(&second.data[..5], [0, 0, 0, 1, 0x04]);
+        assert_eq!(second.header.orig_len, 11);
+        assert!(capture.next_packet().await.unwrap().is_none());
+
+        let mut capture = Capture::from_file(temp_file_path).await.unwrap();
+        assert_eq!(capture.next_header().await.unwrap().unwrap().incl_len, 8);
+        assert_eq!(capture.next_header().await.unwrap().unwrap().incl_len, 11);
+        assert!(capture.next_header().await.unwrap().is_none());
+
+        tokio::fs::remove_file(temp_file_path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_btsnoop_h1_capture() {
+        let temp_file_path = "test_h1.btsnoop";
+        let data = btsnoop_file(
+            DATALINK_H1,
+            &[
+                (0x02, &[0x03, 0x0c, 0x00]),
+                (0x01, &[0x01, 0x20, 0x00, 0x00]),
+            ],
+        );
+        tokio::fs::write(temp_file_path, &data).await.unwrap();
+
+        let mut capture = Capture::from_file(temp_file_path).await.unwrap();
+        let command = capture.next_packet().await.unwrap().unwrap();
+        assert_eq!(command.data, [0, 0, 0, 0, 0x01, 0x03, 0x0c, 0x00]);
+        let acl = capture.next_packet().await.unwrap().unwrap();
+        assert_eq!(&acl.data[..5], [0, 0, 0, 1, 0x02]);
+
+        tokio::fs::remove_file(temp_file_path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_oversized_record() {
+        let mut data = btsnoop_file(DATALINK_H4, &[(0x02, &[0x01, 0x03, 0x0c, 0x00])]);
+        data[FILE_HEADER_LEN + 4..FILE_HEADER_LEN + 8].copy_from_slice(&u32::MAX.to_be_bytes());
+        let mut records = &data[FILE_HEADER_LEN..];
+        let error = next_packet(&mut records, DATALINK_H4, true).await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
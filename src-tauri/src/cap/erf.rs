@@ -0,0 +1,241 @@
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use tokio::io::{self, AsyncBufRead, AsyncReadExt};
+
+use super::{PcapPacket, PcapPacketHeader, invalid_capture, skip_bytes};
+use crate::error::KcpdumpError;
+use crate::timefmt::Timestamp;
+
+/// ERF record types with a matching pcap link type.
+pub const ERF_TYPE_HDLC_POS: u8 = 1;
+pub const ERF_TYPE_ETH: u8 = 2;
+pub const ERF_TYPE_IPV4: u8 = 22;
+pub const ERF_TYPE_IPV6: u8 = 23;
+/// Padding records carry no packet and are skipped.
+pub const ERF_TYPE_PAD: u8 = 48;
+
+const RECORD_HEADER_LEN: usize = 16;
+const EXTENSION_HEADER_LEN: usize = 8;
+
+/// Returns the pcap link type of the packets carried by an ERF record type.
+pub fn link_type(erf_type: u8) -> Option<u32> {
+    match erf_type {
+        ERF_TYPE_HDLC_POS => Some(50),
+        ERF_TYPE_ETH => Some(1),
+        ERF_TYPE_IPV4 => Some(228),
+        ERF_TYPE_IPV6 => Some(229),
+        _ => None,
+    }
+}
+
+/// ERF Record Header
+/// The fixed 16-byte header in front of every ERF record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErfRecordHeader {
+    /// Little-endian fixed point: seconds in the upper 32 bits, binary fraction below.
+    pub timestamp: u64,
+    pub erf_type: u8,
+    pub flags: u8,
+    /// Length of the whole record including this header and padding.
+    pub rlen: u16,
+    pub lctr: u16,
+    /// Length of the packet on the wire.
+    pub wlen: u16,
+}
+
+impl TryFrom<&[u8]> for ErfRecordHeader {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < RECORD_HEADER_LEN {
+            return Err(invalid_capture("Data too short for ERF record header"));
+        }
+        let header = ErfRecordHeader {
+            timestamp: LittleEndian::read_u64(&data[0..8]),
+            erf_type: data[8],
+            flags: data[9],
+            rlen: BigEndian::read_u16(&data[10..12]),
+            lctr: BigEndian::read_u16(&data[12..14]),
+            wlen: BigEndian::read_u16(&data[14..16]),
+        };
+        if (header.rlen as usize) < RECORD_HEADER_LEN {
+            return Err(invalid_capture("ERF record length shorter than its header"));
+        }
+        Ok(header)
+    }
+}
+
+impl ErfRecordHeader {
+    /// Record type without the extension header flag.
+    pub fn record_type(&self) -> u8 {
+        self.erf_type & 0x7f
+    }
+
+    pub fn has_extensions(&self) -> bool {
+        self.erf_type & 0x80 != 0
+    }
+
+    pub fn ts_sec(&self) -> u32 {
+        (self.timestamp >> 32) as u32
+    }
+
+    /// The 32.32 fixed-point timestamp, rounded down to nanoseconds.
+    pub fn timestamp(&self) -> Timestamp {
+        let nsec = ((self.timestamp & 0xffff_ffff) * 1_000_000_000) >> 32;
+        Timestamp::new(self.ts_sec(), nsec as u32)
+    }
+}
+
+/// Checks whether `data`, the start of a file, begins with a plausible ERF
+/// record. ERF has no magic number, so this is a heuristic on the first header.
+pub fn looks_like_erf(data: &[u8]) -> bool {
+    let Ok(header) = ErfRecordHeader::try_from(data) else {
+        return false;
+    };
+    link_type(header.record_type()).is_some() && header.ts_sec() != 0
+}
+
+/// Reads the next packet record, skipping padding records, and returns it
+/// with the number of bytes read. Without `keep_data` only the headers are
+/// read and the packet bytes are skipped.
+pub async fn next_packet<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    keep_data: bool,
+) -> io::Result<Option<(PcapPacket, usize)>> {
+    let mut consumed = 0;
+    loop {
+        let mut header_buf = [0u8; RECORD_HEADER_LEN];
+        match reader.read_exact(&mut header_buf).await {
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        let header = ErfRecordHeader::try_from(header_buf.as_slice()).map_err(io::Error::from)?;
+        let body_len = header.rlen as usize - RECORD_HEADER_LEN;
+        consumed += header.rlen as usize;
+        if header.record_type() == ERF_TYPE_PAD {
+            skip_bytes(reader, body_len).await?;
+            continue;
+        }
+
+        let mut offset = 0;
+        if header.has_extensions() {
+            // Each extension header has a "more follow" flag in its top bit.
+            loop {
+                let mut extension = [0u8; EXTENSION_HEADER_LEN];
+                if offset + EXTENSION_HEADER_LEN > body_len {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "ERF extension headers truncated",
+                    ));
+                }
+                reader.read_exact(&mut extension).await?;
+                offset += EXTENSION_HEADER_LEN;
+                if extension[0] & 0x80 == 0 {
+                    break;
+                }
+            }
+        }
+        if header.record_type() == ERF_TYPE_ETH {
+            // Offset and pad bytes in front of the Ethernet frame.
+            let pad = 2.min(body_len - offset);
+            skip_bytes(reader, pad).await?;
+            offset += pad;
+        }
+        let payload_len = body_len - offset;
+        // Records are padded to eight bytes; the padding is only recognizable
+        // when it runs past the wire length.
+        let incl_len = payload_len.min(header.wlen as usize);
+        let data = if keep_data {
+            let mut data = vec![0u8; incl_len];
+            reader.read_exact(&mut data).await?;
+            data
+        } else {
+            skip_bytes(reader, incl_len).await?;
+            Vec::new()
+        };
+        skip_bytes(reader, payload_len - incl_len).await?;
+
+        let packet = PcapPacket {
+            header: PcapPacketHeader {
+                timestamp: header.timestamp(),
+                incl_len: incl_len as u32,
+                orig_len: u32::from(header.wlen),
+                link_type: None,
+            },
+            data,
+        };
+        return Ok(Some((packet, consumed)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cap::Capture;
+
+    fn record(erf_type: u8, ts_sec: u32, frame: &[u8], wlen: u16) -> Vec<u8> {
+        let mut body = Vec::new();
+        if erf_type & 0x80 != 0 {
+            body.extend_from_slice(&[0x01, 0, 0, 0, 0, 0, 0, 0]);
+        }
+        if erf_type & 0x7f == ERF_TYPE_ETH {
+            body.extend_from_slice(&[0, 0]);
+        }
+        body.extend_from_slice(frame);
+        while !(RECORD_HEADER_LEN + body.len()).is_multiple_of(8) {
+            body.push(0);
+        }
+        // Half a second as a binary fraction.
+        let timestamp = (u64::from(ts_sec) << 32) | 0x8000_0000;
+        let mut data = timestamp.to_le_bytes().to_vec();
+        data.extend_from_slice(&[erf_type, 0x04]);
+        data.extend_from_slice(&((RECORD_HEADER_LEN + body.len()) as u16).to_be_bytes());
+        data.extend_from_slice(&[0, 0]);
+        data.extend_from_slice(&wlen.to_be_bytes());
+        data.extend_from_slice(&body);
+        data
+    }
+
+    #[test]
+    fn test_record_header() {
+        let data = record(ERF_TYPE_ETH | 0x80, 1_700_000_000, &[0xde, 0xad], 2);
+        let header = ErfRecordHeader::try_from(data.as_slice()).unwrap();
+        assert_eq!(header.record_type(), ERF_TYPE_ETH);
+        assert!(header.has_extensions());
+        assert_eq!(header.ts_sec(), 1_700_000_000);
+        assert_eq!(header.timestamp().nsec, 500_000_000);
+        assert_eq!(header.rlen, 32);
+        assert!(looks_like_erf(&data));
+        assert!(!looks_like_erf(&[
+            0xd4, 0xc3, 0xb2, 0xa1, 0x02, 0x00, 0x04, 0x00
+        ]));
+    }
+
+    #[tokio::test]
+    async fn test_erf_capture() {
+        let temp_file_path = "test.erf";
+        let frame: Vec<u8> = (0..62).collect();
+        let mut data = record(ERF_TYPE_ETH, 1_700_000_000, &frame, 66);
+        data.extend(record(ERF_TYPE_PAD, 1_700_000_001, &[0; 8], 0));
+        data.extend(record(ERF_TYPE_ETH | 0x80, 1_700_000_002, &frame[..14], 14));
+        tokio::fs::write(temp_file_path, &data).await.unwrap();
+
+        let mut capture = Capture::from_file(temp_file_path).await.unwrap();
+        assert_eq!(capture.header().network, 1);
+        let first = capture.next_packet().await.unwrap().unwrap();
+        assert_eq!(first.header.timestamp, Timestamp::new(1_700_000_000, 500_000_000));
+        assert_eq!(first.header.orig_len, 66);
+        assert_eq!(first.data, frame);
+        let second = capture.next_packet().await.unwrap().unwrap();
+        assert_eq!(second.header.timestamp.sec, 1_700_000_002);
+        assert_eq!(second.data, &frame[..14]);
+        assert!(capture.next_packet().await.unwrap().is_none());
+
+        let mut capture = Capture::from_file(temp_file_path).await.unwrap();
+        assert_eq!(capture.next_header().await.unwrap().unwrap().incl_len, 62);
+        assert_eq!(capture.next_header().await.unwrap().unwrap().incl_len, 14);
+        assert!(capture.next_header().await.unwrap().is_none());
+
+        tokio::fs::remove_file(temp_file_path).await.unwrap();
+    }
+}
@@ -0,0 +1,400 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tokio::io;
+
+use super::{PcapPacket, PcapPacketHeader, TimestampResolution};
+use crate::ieee802154::LINKTYPE_IEEE802_15_4_NOFCS;
+use crate::link::{LINKTYPE_ETHERNET, LINKTYPE_RAW};
+use crate::timefmt::Timestamp;
+use crate::wlan::{LINKTYPE_IEEE802_11, LINKTYPE_IEEE802_11_RADIOTAP};
+
+/// Length of the Linux cooked (SLL) header written in front of frames of
+/// interfaces without a link type of their own.
+const SLL_HEADER_LEN: usize = 16;
+
+/// Live Capture
+/// Reads frames from a network interface. The Linux backend uses an
+/// `AF_PACKET` raw socket, which requires `CAP_NET_RAW`; other platforms
+/// report `Unsupported` when opened.
+///
+/// The link type follows the interface's hardware type; interfaces without
+/// a matching link type are captured with Linux cooked (SLL) headers.
+///
+/// Reads are blocking with a short timeout so that callers running the
+/// capture loop on a worker thread can check for stop requests.
+pub struct LiveCapture {
+    inner: imp::RawSocket,
+    snaplen: u32,
+    buffer: Vec<u8>,
+}
+
+impl LiveCapture {
+    /// Opens `interface` for capturing, keeping at most `snaplen` bytes per frame.
+    pub fn open(interface: &str, snaplen: u32, promiscuous: bool) -> io::Result<Self> {
+        let inner = imp::RawSocket::open(interface, promiscuous)?;
+        Ok(LiveCapture {
+            inner,
+            snaplen,
+            buffer: vec![0u8; 65536],
+        })
+    }
+
+    /// Link type of the frames returned by `next_packet`.
+    pub fn network(&self) -> u32 {
+        self.inner.link_type()
+    }
+
+    pub fn snaplen(&self) -> u32 {
+        self.snaplen
+    }
+
+    /// Resolution of the timestamps of `next_packet`, which are taken from
+    /// the system clock in nanoseconds.
+    pub fn resolution(&self) -> TimestampResolution {
+        TimestampResolution::Nanosecond
+    }
+
+    /// Waits for the next frame. Returns `Ok(None)` when the read timed out.
+    pub fn next_packet(&mut self) -> io::Result<Option<PcapPacket>> {
+        let Some(orig_len) = self.inner.recv(&mut self.buffer)? else {
+            return Ok(None);
+        };
+        let incl_len = orig_len.min(self.buffer.len()).min(self.snaplen as usize);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Ok(Some(PcapPacket {
+            header: PcapPacketHeader {
+                timestamp: Timestamp::new(now.as_secs() as u32, now.subsec_nanos()),
+                incl_len: incl_len as u32,
+                orig_len: orig_len as u32,
+                link_type: None,
+            },
+            data: self.buffer[..incl_len].to_vec(),
+        }))
+    }
+}
+
+/// Network Interface
+/// An interface that `LiveCapture::open` accepts.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkInterface {
+    pub name: String,
+    pub index: u32,
+    pub mac: Option<String>,
+    pub mtu: Option<u32>,
+    /// Whether the link is operationally up.
+    pub up: bool,
+    pub loopback: bool,
+}
+
+/// Lists the interfaces available for live capture, ordered by index.
+pub fn list_interfaces() -> io::Result<Vec<NetworkInterface>> {
+    imp::list_interfaces()
+}
+
+/// Maps a Linux ARPHRD hardware type to the link type of the frames a raw
+/// packet socket returns for it, or `None` if it has none and frames need a
+/// cooked header.
+pub fn link_type(hardware_type: u16) -> Option<u32> {
+    match hardware_type {
+        // ARPHRD_ETHER and ARPHRD_LOOPBACK, whose frames carry a zeroed
+        // Ethernet header.
+        1 | 772 => Some(LINKTYPE_ETHERNET),
+        // ARPHRD_TUNNEL, ARPHRD_TUNNEL6, ARPHRD_SIT and ARPHRD_NONE (tun
+        // devices) deliver bare IP packets. ARPHRD_PPP frames need not be
+        // bare IP, so PPP devices are captured cooked as libpcap does.
+        768 | 769 | 776 | 0xfffe => Some(LINKTYPE_RAW),
+        // ARPHRD_IEEE80211 and ARPHRD_IEEE80211_RADIOTAP.
+        801 => Some(LINKTYPE_IEEE802_11),
+        803 => Some(LINKTYPE_IEEE802_11_RADIOTAP),
+        // ARPHRD_IEEE802154.
+        804 => Some(LINKTYPE_IEEE802_15_4_NOFCS),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use std::ffi::CString;
+    use std::mem;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+    use tokio::io;
+
+    use super::SLL_HEADER_LEN;
+    use crate::link::LINKTYPE_LINUX_SLL;
+
+    pub struct RawSocket {
+        fd: OwnedFd,
+        link_type: u32,
+        /// Whether the socket strips link headers and `recv` writes an SLL
+        /// header in their place.
+        cooked: bool,
+    }
+
+    fn check(result: libc::c_int) -> io::Result<libc::c_int> {
+        if result < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(result)
+        }
+    }
+
+    impl RawSocket {
+        pub fn open(interface: &str, promiscuous: bool) -> io::Result<Self> {
+            let name = CString::new(interface)?;
+            // SAFETY: `name` is a valid NUL-terminated string.
+            let index = unsafe { libc::if_nametoindex(name.as_ptr()) };
+            if index == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("No such interface: {}", interface),
+                ));
+            }
+
+            let hardware_type =
+                std::fs::read_to_string(format!("/sys/class/net/{}/type", interface))?
+                    .trim()
+                    .parse()
+                    .map_err(|_| {
+                        io::Error::new(io::ErrorKind::InvalidData, "Invalid interface type")
+                    })?;
+            let (link_type, cooked) = match super::link_type(hardware_type) {
+                Some(link_type) => (link_type, false),
+                None => (LINKTYPE_LINUX_SLL, true),
+            };
+            let kind = if cooked {
+                libc::SOCK_DGRAM
+            } else {
+                libc::SOCK_RAW
+            };
+
+            let protocol = (libc::ETH_P_ALL as u16).to_be();
+            // SAFETY: plain socket(2) call; the descriptor is owned right after.
+            let fd = check(unsafe {
+                libc::socket(libc::AF_PACKET, kind, libc::c_int::from(protocol))
+            })?;
+            // SAFETY: `fd` is a freshly created descriptor not owned elsewhere.
+            let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+            // SAFETY: sockaddr_ll is plain old data, zero is a valid bit pattern.
+            let mut address: libc::sockaddr_ll = unsafe { mem::zeroed() };
+            address.sll_family = libc::AF_PACKET as u16;
+            address.sll_protocol = protocol;
+            address.sll_ifindex = index as i32;
+            // SAFETY: `address` outlives the call and the length matches its type.
+            check(unsafe {
+                libc::bind(
+                    fd.as_raw_fd(),
+                    &address as *const libc::sockaddr_ll as *const libc::sockaddr,
+                    mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t,
+                )
+            })?;
+
+            if promiscuous {
+                let request = libc::packet_mreq {
+                    mr_ifindex: index as i32,
+                    mr_type: libc::PACKET_MR_PROMISC as u16,
+                    mr_alen: 0,
+                    mr_address: [0; 8],
+                };
+                set_option(&fd, libc::SOL_PACKET, libc::PACKET_ADD_MEMBERSHIP, &request)?;
+            }
+
+            let timeout = libc::timeval {
+                tv_sec: 0,
+                tv_usec: 100_000,
+            };
+            set_option(&fd, libc::SOL_SOCKET, libc::SO_RCVTIMEO, &timeout)?;
+
+            Ok(RawSocket {
+                fd,
+                link_type,
+                cooked,
+            })
+        }
+
+        pub fn link_type(&self) -> u32 {
+            self.link_type
+        }
+
+        /// Receives one frame into `buffer`, returning its length on the wire.
+        pub fn recv(&mut self, buffer: &mut [u8]) -> io::Result<Option<usize>> {
+            if !self.cooked {
+                // SAFETY: the pointer and length describe the writable `buffer`.
+                let received = unsafe {
+                    libc::recv(
+                        self.fd.as_raw_fd(),
+                        buffer.as_mut_ptr() as *mut libc::c_void,
+                        buffer.len(),
+                        libc::MSG_TRUNC,
+                    )
+                };
+                return received_len(received);
+            }
+
+            let (header, payload) = buffer.split_at_mut(SLL_HEADER_LEN);
+            // SAFETY: sockaddr_ll is plain old data, zero is a valid bit pattern.
+            let mut address: libc::sockaddr_ll = unsafe { mem::zeroed() };
+            let mut address_len = mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t;
+            // SAFETY: the pointers and lengths describe the writable `payload`
+            // and `address`.
+            let received = unsafe {
+                libc::recvfrom(
+                    self.fd.as_raw_fd(),
+                    payload.as_mut_ptr() as *mut libc::c_void,
+                    payload.len(),
+                    libc::MSG_TRUNC,
+                    &mut address as *mut libc::sockaddr_ll as *mut libc::sockaddr,
+                    &mut address_len,
+                )
+            };
+            let Some(len) = received_len(received)? else {
+                return Ok(None);
+            };
+            header[0..2].copy_from_slice(&u16::from(address.sll_pkttype).to_be_bytes());
+            header[2..4].copy_from_slice(&address.sll_hatype.to_be_bytes());
+            header[4..6].copy_from_slice(&u16::from(address.sll_halen).to_be_bytes());
+            header[6..14].copy_from_slice(&address.sll_addr);
+            // Already in network byte order.
+            header[14..16].copy_from_slice(&address.sll_protocol.to_ne_bytes());
+            Ok(Some(SLL_HEADER_LEN + len))
+        }
+    }
+
+    /// Turns the result of a receive call into a frame length, treating
+    /// timeouts and interruptions as no frame.
+    fn received_len(received: isize) -> io::Result<Option<usize>> {
+        if received < 0 {
+            let error = io::Error::last_os_error();
+            return match error.kind() {
+                io::ErrorKind::WouldBlock
+                | io::ErrorKind::TimedOut
+                | io::ErrorKind::Interrupted => Ok(None),
+                _ => Err(error),
+            };
+        }
+        Ok(Some(received as usize))
+    }
+
+    /// Reads the interfaces from sysfs.
+    pub fn list_interfaces() -> io::Result<Vec<super::NetworkInterface>> {
+        // ARPHRD_LOOPBACK.
+        const LOOPBACK_TYPE: &str = "772";
+
+        let mut interfaces = Vec::new();
+        for entry in std::fs::read_dir("/sys/class/net")? {
+            let entry = entry?;
+            let attribute = |name: &str| {
+                std::fs::read_to_string(entry.path().join(name))
+                    .ok()
+                    .map(|value| value.trim().to_string())
+            };
+            let Some(index) = attribute("ifindex").and_then(|index| index.parse().ok()) else {
+                continue;
+            };
+            interfaces.push(super::NetworkInterface {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                index,
+                mac: attribute("address").filter(|mac| !mac.is_empty()),
+                mtu: attribute("mtu").and_then(|mtu| mtu.parse().ok()),
+                // Loopback and some virtual links never report "up".
+                up: attribute("operstate")
+                    .is_some_and(|state| state == "up" || state == "unknown"),
+                loopback: attribute("type").as_deref() == Some(LOOPBACK_TYPE),
+            });
+        }
+        interfaces.sort_by_key(|interface| interface.index);
+        Ok(interfaces)
+    }
+
+    fn set_option<T>(
+        fd: &OwnedFd,
+        level: libc::c_int,
+        name: libc::c_int,
+        value: &T,
+    ) -> io::Result<()> {
+        // SAFETY: `value` is a valid reference and the length matches its type.
+        check(unsafe {
+            libc::setsockopt(
+                fd.as_raw_fd(),
+                level,
+                name,
+                value as *const T as *const libc::c_void,
+                mem::size_of::<T>() as libc::socklen_t,
+            )
+        })?;
+        Ok(())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod imp {
+    use tokio::io;
+
+    pub struct RawSocket;
+
+    impl RawSocket {
+        pub fn open(_interface: &str, _promiscuous: bool) -> io::Result<Self> {
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Live capture is not supported on this platform",
+            ))
+        }
+
+        pub fn link_type(&self) -> u32 {
+            super::LINKTYPE_ETHERNET
+        }
+
+        pub fn recv(&mut self, _buffer: &mut [u8]) -> io::Result<Option<usize>> {
+            Ok(None)
+        }
+    }
+
+    pub fn list_interfaces() -> io::Result<Vec<super::NetworkInterface>> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Live capture is not supported on this platform",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_unknown_interface() {
+        let error = LiveCapture::open("kcpdump-missing0", 65535, false)
+            .err()
+            .unwrap();
+        assert!(matches!(
+            error.kind(),
+            io::ErrorKind::NotFound | io::ErrorKind::Unsupported
+        ));
+    }
+
+    #[test]
+    fn test_link_type_of_hardware_type() {
+        assert_eq!(link_type(1), Some(LINKTYPE_ETHERNET));
+        assert_eq!(link_type(772), Some(LINKTYPE_ETHERNET));
+        assert_eq!(link_type(0xfffe), Some(LINKTYPE_RAW));
+        assert_eq!(link_type(803), Some(LINKTYPE_IEEE802_11_RADIOTAP));
+        // ARPHRD_INFINIBAND has no link type of its own.
+        assert_eq!(link_type(32), None);
+        // Nor does ARPHRD_PPP, whose frames need not be bare IP.
+        assert_eq!(link_type(512), None);
+    }
+
+    #[test]
+    fn test_list_interfaces() {
+        match list_interfaces() {
+            Ok(interfaces) => {
+                assert!(interfaces.windows(2).all(|pair| pair[0].index < pair[1].index));
+            }
+            Err(error) => assert_eq!(error.kind(), io::ErrorKind::Unsupported),
+        }
+    }
+}
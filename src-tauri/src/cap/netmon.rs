@@ -0,0 +1,266 @@
+use byteorder::{ByteOrder, LittleEndian};
+use chrono::NaiveDate;
+use tokio::io::{self, AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, SeekFrom};
+
+use super::{MAX_RECORD_LEN, PcapPacket, PcapPacketHeader, invalid_capture};
+use crate::error::KcpdumpError;
+use crate::timefmt::Timestamp;
+
+/// File magic of Network Monitor 2.x captures.
+pub const NETMON_MAGIC: &[u8; 4] = b"GMBU";
+
+const FILE_HEADER_LEN: usize = 32;
+const FRAME_HEADER_LEN: usize = 16;
+
+/// Returns the pcap link type of a Network Monitor media type.
+pub fn link_type(media_type: u16) -> Option<u32> {
+    match media_type {
+        1 => Some(1),
+        2 => Some(6),
+        3 => Some(10),
+        _ => None,
+    }
+}
+
+/// NetMon Header
+/// The fields of the Network Monitor 2.x file header needed to read frames.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetMonHeader {
+    pub version_major: u8,
+    pub version_minor: u8,
+    pub media_type: u16,
+    /// Capture start, in microseconds since the epoch.
+    pub start_time: i64,
+    pub frame_table_offset: u32,
+    /// Length of the frame table in bytes.
+    pub frame_table_length: u32,
+}
+
+impl TryFrom<&[u8]> for NetMonHeader {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < FILE_HEADER_LEN {
+            return Err(invalid_capture("Data too short for NetMon header"));
+        }
+        if &data[0..4] != NETMON_MAGIC {
+            return Err(invalid_capture("Invalid NetMon magic"));
+        }
+        if data[5] != 2 {
+            return Err(invalid_capture("Unsupported NetMon version"));
+        }
+        // SYSTEMTIME: year, month, day of week, day, hour, minute, second, milliseconds.
+        let field = |index: usize| LittleEndian::read_u16(&data[8 + index * 2..10 + index * 2]);
+        let start_time = NaiveDate::from_ymd_opt(
+            i32::from(field(0)),
+            u32::from(field(1)),
+            u32::from(field(3)),
+        )
+        .and_then(|date| {
+            date.and_hms_milli_opt(
+                u32::from(field(4)),
+                u32::from(field(5)),
+                u32::from(field(6)),
+                u32::from(field(7)),
+            )
+        })
+        .ok_or_else(|| invalid_capture("Invalid NetMon capture start time"))?
+        .and_utc()
+        .timestamp_micros();
+        Ok(NetMonHeader {
+            version_major: data[5],
+            version_minor: data[4],
+            media_type: LittleEndian::read_u16(&data[6..8]),
+            start_time,
+            frame_table_offset: LittleEndian::read_u32(&data[24..28]),
+            frame_table_length: LittleEndian::read_u32(&data[28..32]),
+        })
+    }
+}
+
+/// NetMon Reader
+/// Walks the frame table of a Network Monitor file. Frames are stored at the
+/// offsets listed in the table, so reading them requires seeking.
+#[derive(Debug, Clone)]
+pub struct NetMonReader {
+    start_time: i64,
+    frames: Vec<u32>,
+    next: usize,
+    /// File offset of the frame table, which follows the frames.
+    table_offset: u32,
+}
+
+impl NetMonReader {
+    /// Reads the file header and the frame table.
+    pub async fn open<R: AsyncRead + AsyncSeek + Unpin>(
+        reader: &mut R,
+    ) -> io::Result<(NetMonHeader, Self)> {
+        let mut header_buf = [0u8; FILE_HEADER_LEN];
+        reader.read_exact(&mut header_buf).await?;
+        let header = NetMonHeader::try_from(header_buf.as_slice())?;
+
+        reader
+            .seek(SeekFrom::Start(u64::from(header.frame_table_offset)))
+            .await?;
+        if header.frame_table_length > MAX_RECORD_LEN {
+            return Err(invalid_capture("NetMon frame table too large").into());
+        }
+        let mut table = vec![0u8; header.frame_table_length as usize];
+        reader.read_exact(&mut table).await?;
+        let frames = table.chunks_exact(4).map(LittleEndian::read_u32).collect();
+        Ok((
+            header.clone(),
+            NetMonReader {
+                start_time: header.start_time,
+                frames,
+                next: 0,
+                table_offset: header.frame_table_offset,
+            },
+        ))
+    }
+
+    /// File offset of the next frame, or of the frame table after the last
+    /// one.
+    pub fn next_offset(&self) -> u64 {
+        u64::from(self.frames.get(self.next).copied().unwrap_or(self.table_offset))
+    }
+
+    /// Makes the frame stored at `offset` the next frame to read, or ends
+    /// the capture if no frame is stored there.
+    pub fn seek_to_offset(&mut self, offset: u64) {
+        self.next = self
+            .frames
+            .iter()
+            .position(|&frame| u64::from(frame) == offset)
+            .unwrap_or(self.frames.len());
+    }
+
+    /// Reads the next frame; without `keep_data` only its header is read.
+    pub async fn next_packet<R: AsyncRead + AsyncSeek + Unpin>(
+        &mut self,
+        reader: &mut R,
+        keep_data: bool,
+    ) -> io::Result<Option<PcapPacket>> {
+        let Some(&offset) = self.frames.get(self.next) else {
+            return Ok(None);
+        };
+        self.next += 1;
+        reader.seek(SeekFrom::Start(u64::from(offset))).await?;
+        let mut frame_buf = [0u8; FRAME_HEADER_LEN];
+        reader.read_exact(&mut frame_buf).await?;
+        // Offset from the capture start in microseconds.
+        let delta = LittleEndian::read_u64(&frame_buf[0..8]) as i64;
+        let orig_len = LittleEndian::read_u32(&frame_buf[8..12]);
+        let incl_len = LittleEndian::read_u32(&frame_buf[12..16]);
+        if incl_len > MAX_RECORD_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "NetMon frame length too large",
+            ));
+        }
+        let mut data = Vec::new();
+        if keep_data {
+            data.resize(incl_len as usize, 0);
+            reader.read_exact(&mut data).await?;
+        }
+
+        let micros = self.start_time + delta;
+        Ok(Some(PcapPacket {
+            header: PcapPacketHeader {
+                timestamp: Timestamp::from_nanos(micros * 1000),
+                incl_len,
+                orig_len,
+                link_type: None,
+            },
+            data,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cap::Capture;
+
+    fn netmon_file(frames: &[(u64, &[u8])]) -> Vec<u8> {
+        let mut data = NETMON_MAGIC.to_vec();
+        data.extend_from_slice(&[0x01, 0x02]);
+        data.extend_from_slice(&1u16.to_le_bytes());
+        // 2023-11-14 22:13:20.000 UTC, a Tuesday.
+        for field in [2023u16, 11, 2, 14, 22, 13, 20, 0] {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+        data.extend_from_slice(&[0; 8]);
+        data.resize(128, 0);
+
+        let mut offsets = Vec::new();
+        for (delta, frame) in frames {
+            offsets.push(data.len() as u32);
+            data.extend_from_slice(&delta.to_le_bytes());
+            data.extend_from_slice(&(frame.len() as u32 + 4).to_le_bytes());
+            data.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            data.extend_from_slice(frame);
+            // Version 2.1 trailer with the media type of the frame.
+            data.extend_from_slice(&1u16.to_le_bytes());
+        }
+        let table_offset = data.len() as u32;
+        for offset in &offsets {
+            data.extend_from_slice(&offset.to_le_bytes());
+        }
+        data[24..28].copy_from_slice(&table_offset.to_le_bytes());
+        data[28..32].copy_from_slice(&(offsets.len() as u32 * 4).to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_header() {
+        let data = netmon_file(&[]);
+        let header = NetMonHeader::try_from(data.as_slice()).unwrap();
+        assert_eq!((header.version_major, header.version_minor), (2, 1));
+        assert_eq!(header.media_type, 1);
+        assert_eq!(header.start_time, 1_700_000_000_000_000);
+        assert_eq!(header.frame_table_offset, 128);
+        assert_eq!(header.frame_table_length, 0);
+    }
+
+    #[tokio::test]
+    async fn test_netmon_capture() {
+        let temp_file_path = "test_netmon.cap";
+        let frame: Vec<u8> = (0..60).collect();
+        let data = netmon_file(&[(0, &frame), (1_500_000, &frame[..14])]);
+        tokio::fs::write(temp_file_path, &data).await.unwrap();
+
+        let mut capture = Capture::from_file(temp_file_path).await.unwrap();
+        assert_eq!(capture.header().network, 1);
+        let first = capture.next_packet().await.unwrap().unwrap();
+        assert_eq!(first.header.timestamp, Timestamp::new(1_700_000_000, 0));
+        assert_eq!(first.header.orig_len, 64);
+        assert_eq!(first.data, frame);
+        let second = capture.next_packet().await.unwrap().unwrap();
+        assert_eq!(second.header.timestamp, Timestamp::new(1_700_000_001, 500_000_000));
+        assert_eq!(second.data, &frame[..14]);
+        assert!(capture.next_packet().await.unwrap().is_none());
+
+        // Frames are found again through the frame table.
+        capture.seek_to_packet(2).await.unwrap();
+        assert_eq!(capture.next_packet().await.unwrap().unwrap().data, &frame[..14]);
+        capture.seek_to_packet(1).await.unwrap();
+        assert_eq!(capture.next_packet().await.unwrap().unwrap().data, frame);
+
+        tokio::fs::remove_file(temp_file_path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_oversized_lengths() {
+        let mut data = netmon_file(&[(0, &[0; 14])]);
+        data[128 + 12..128 + 16].copy_from_slice(&u32::MAX.to_le_bytes());
+        let mut reader = std::io::Cursor::new(data.clone());
+        let (_, mut netmon) = NetMonReader::open(&mut reader).await.unwrap();
+        let error = netmon.next_packet(&mut reader, true).await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        data[28..32].copy_from_slice(&u32::MAX.to_le_bytes());
+        let error = NetMonReader::open(&mut std::io::Cursor::new(data)).await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,529 @@
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use tokio::io::{self, AsyncBufRead, AsyncReadExt};
+
+use super::{MAX_RECORD_LEN, PcapPacket, PcapPacketHeader, skip_bytes};
+use crate::error::KcpdumpError;
+use crate::timefmt::Timestamp;
+
+/// Block type of the Section Header Block, which starts every pcapng file.
+/// It reads the same in either byte order.
+pub const PCAPNG_MAGIC: &[u8; 4] = b"\x0a\x0d\x0d\x0a";
+
+const BYTE_ORDER_MAGIC: u32 = 0x1a2b3c4d;
+
+const SECTION_HEADER_BLOCK: u32 = 0x0a0d0d0a;
+const INTERFACE_DESCRIPTION_BLOCK: u32 = 1;
+const OBSOLETE_PACKET_BLOCK: u32 = 2;
+const SIMPLE_PACKET_BLOCK: u32 = 3;
+const ENHANCED_PACKET_BLOCK: u32 = 6;
+
+const OPTION_END: u16 = 0;
+const IF_TSRESOL: u16 = 9;
+const IF_TSOFFSET: u16 = 14;
+
+fn invalid(e: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// Interface
+/// What an Interface Description Block says about the packets captured on
+/// it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interface {
+    pub link_type: u32,
+    pub snaplen: u32,
+    /// Timestamp units per second, from `if_tsresol`; microseconds by default.
+    pub units_per_second: u64,
+    /// Seconds added to every timestamp, from `if_tsoffset`.
+    pub offset: i64,
+}
+
+impl Interface {
+    /// Converts a timestamp in interface units.
+    fn timestamp(&self, units: u64) -> Timestamp {
+        let seconds = (units / self.units_per_second) as i64 + self.offset;
+        let fraction = u128::from(units % self.units_per_second) * 1_000_000_000
+            / u128::from(self.units_per_second);
+        Timestamp::new(seconds as u32, fraction as u32)
+    }
+}
+
+/// Section
+/// Where a section starts, its byte order and the position of its first
+/// interface among those of all sections.
+#[derive(Debug, Clone, Copy)]
+struct Section {
+    offset: usize,
+    big_endian: bool,
+    first_interface: usize,
+}
+
+/// Pcapng Reader
+/// Walks the blocks of a pcapng file. Each section sets its own byte order
+/// and interfaces; packets carry the link type and timestamps of the
+/// interface they were captured on. Sections and interfaces are kept once
+/// read, so that after a seek back the reader still knows them.
+#[derive(Debug, Clone)]
+pub struct PcapngReader {
+    big_endian: bool,
+    /// Interfaces of all sections read so far, in file order.
+    interfaces: Vec<Interface>,
+    sections: Vec<Section>,
+    /// Position of the current section in `sections`.
+    section: usize,
+    /// File offset of the next block.
+    offset: usize,
+    /// End of the furthest block read; blocks before it are only read again
+    /// after a seek.
+    end: usize,
+}
+
+impl PcapngReader {
+    /// Reads blocks up to and including the first Interface Description
+    /// Block, and returns its link type with the reader.
+    pub async fn open<R: AsyncBufRead + Unpin>(reader: &mut R) -> io::Result<(u32, Self)> {
+        let mut pcapng = PcapngReader {
+            big_endian: false,
+            interfaces: Vec::new(),
+            sections: Vec::new(),
+            section: 0,
+            offset: 0,
+            end: 0,
+        };
+        while let Some((kind, body, _)) = pcapng.read_block(reader, true).await? {
+            match kind {
+                SECTION_HEADER_BLOCK
+                    if body.len() < 16 || pcapng.read_u16(&body[4..6]) != 1 =>
+                {
+                    return Err(invalid("Unsupported pcapng version"));
+                }
+                INTERFACE_DESCRIPTION_BLOCK => {
+                    let link_type = pcapng.interfaces[0].link_type;
+                    return Ok((link_type, pcapng));
+                }
+                OBSOLETE_PACKET_BLOCK | SIMPLE_PACKET_BLOCK | ENHANCED_PACKET_BLOCK => {
+                    return Err(invalid("pcapng packet for an undefined interface"));
+                }
+                _ => {}
+            }
+        }
+        Err(invalid("pcapng file without interfaces"))
+    }
+
+    /// Interfaces of the current section.
+    pub fn interfaces(&self) -> &[Interface] {
+        let Some(section) = self.sections.get(self.section) else {
+            return &[];
+        };
+        let end = self
+            .sections
+            .get(self.section + 1)
+            .map_or(self.interfaces.len(), |next| next.first_interface);
+        &self.interfaces[section.first_interface..end]
+    }
+
+    /// File offset of the next block.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// End of the furthest block read. Only blocks before it can be sought
+    /// to; later ones may follow sections and interfaces not read yet.
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// Makes the block at `offset`, which must have been read before, the
+    /// next one, in the byte order and with the interfaces of its section.
+    pub fn seek(&mut self, offset: usize) {
+        self.offset = offset;
+        self.section = self
+            .sections
+            .partition_point(|section| section.offset <= offset)
+            .saturating_sub(1);
+        if let Some(section) = self.sections.get(self.section) {
+            self.big_endian = section.big_endian;
+        }
+    }
+
+    fn read_u16(&self, buf: &[u8]) -> u16 {
+        if self.big_endian {
+            BigEndian::read_u16(buf)
+        } else {
+            LittleEndian::read_u16(buf)
+        }
+    }
+
+    fn read_u32(&self, buf: &[u8]) -> u32 {
+        if self.big_endian {
+            BigEndian::read_u32(buf)
+        } else {
+            LittleEndian::read_u32(buf)
+        }
+    }
+
+    /// Reads a timestamp stored as high and low 32-bit words.
+    fn timestamp(&self, buf: &[u8]) -> u64 {
+        u64::from(self.read_u32(&buf[0..4])) << 32 | u64::from(self.read_u32(&buf[4..8]))
+    }
+
+    fn read_u64(&self, buf: &[u8]) -> u64 {
+        if self.big_endian {
+            BigEndian::read_u64(buf)
+        } else {
+            LittleEndian::read_u64(buf)
+        }
+    }
+
+    /// Reads the next block as its type, body and body length. A Section
+    /// Header Block starts a new section with its own byte order and
+    /// interfaces, and an Interface Description Block adds an interface to
+    /// it. Without `keep_data` only the fixed fields of packet blocks are read
+    /// and their packet bytes are skipped.
+    async fn read_block<R: AsyncBufRead + Unpin>(
+        &mut self,
+        reader: &mut R,
+        keep_data: bool,
+    ) -> io::Result<Option<(u32, Vec<u8>, usize)>> {
+        // Type, total length and the first word of the body, or the
+        // trailing length of an empty block.
+        let mut head = [0u8; 12];
+        match reader.read_exact(&mut head).await {
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        let start = self.offset;
+        let known = start < self.end;
+        if head[0..4] == *PCAPNG_MAGIC {
+            self.big_endian = match LittleEndian::read_u32(&head[8..12]) {
+                BYTE_ORDER_MAGIC => false,
+                magic if magic.swap_bytes() == BYTE_ORDER_MAGIC => true,
+                _ => return Err(invalid("Invalid pcapng byte-order magic")),
+            };
+            if known {
+                self.seek(start);
+            } else {
+                self.sections.push(Section {
+                    offset: start,
+                    big_endian: self.big_endian,
+                    first_interface: self.interfaces.len(),
+                });
+                self.section = self.sections.len() - 1;
+            }
+        }
+        let kind = self.read_u32(&head[0..4]);
+        let total = self.read_u32(&head[4..8]) as usize;
+        if total < 12 || !total.is_multiple_of(4) {
+            return Err(invalid("Invalid pcapng block length"));
+        }
+        if total > MAX_RECORD_LEN as usize {
+            return Err(KcpdumpError::malformed(self.offset, "pcapng block too large").into());
+        }
+        self.offset += total;
+        let body_len = total - 12;
+        let read_len = match kind {
+            ENHANCED_PACKET_BLOCK | OBSOLETE_PACKET_BLOCK if !keep_data => body_len.min(20),
+            SIMPLE_PACKET_BLOCK if !keep_data => body_len.min(4),
+            // The trailing copy of the total length.
+            _ => body_len + 4,
+        };
+        let mut body = head[8..12].to_vec();
+        body.resize(read_len.max(4), 0);
+        reader.read_exact(&mut body[4..]).await?;
+        skip_bytes(reader, body_len + 4 - body.len()).await?;
+        body.truncate(read_len.min(body_len));
+        self.end = self.end.max(self.offset);
+        if kind == INTERFACE_DESCRIPTION_BLOCK && !known {
+            let interface = self.interface(&body)?;
+            self.interfaces.push(interface);
+        }
+        Ok(Some((kind, body, body_len)))
+    }
+
+    /// Splits an option list into codes and values. Values are padded to
+    /// four bytes.
+    fn options<'a>(&self, mut data: &'a [u8]) -> Vec<(u16, &'a [u8])> {
+        let mut options = Vec::new();
+        while data.len() >= 4 {
+            let code = self.read_u16(&data[0..2]);
+            let length = self.read_u16(&data[2..4]) as usize;
+            let Some(value) = data.get(4..4 + length).filter(|_| code != OPTION_END) else {
+                break;
+            };
+            options.push((code, value));
+            data = data.get(4 + length.div_ceil(4) * 4..).unwrap_or_default();
+        }
+        options
+    }
+
+    fn interface(&self, body: &[u8]) -> io::Result<Interface> {
+        if body.len() < 8 {
+            return Err(invalid("pcapng interface description too short"));
+        }
+        let mut interface = Interface {
+            link_type: u32::from(self.read_u16(&body[0..2])),
+            snaplen: self.read_u32(&body[4..8]),
+            units_per_second: 1_000_000,
+            offset: 0,
+        };
+        for (code, value) in self.options(&body[8..]) {
+            match code {
+                IF_TSRESOL if value.len() == 1 => {
+                    // A negative power of two if the top bit is set, of ten
+                    // otherwise.
+                    let exponent = u32::from(value[0] & 0x7f);
+                    let units = if value[0] & 0x80 != 0 {
+                        1u64.checked_shl(exponent)
+                    } else {
+                        10u64.checked_pow(exponent)
+                    };
+                    interface.units_per_second =
+                        units.ok_or_else(|| invalid("Unsupported pcapng timestamp resolution"))?;
+                }
+                IF_TSOFFSET if value.len() == 8 => {
+                    interface.offset = self.read_u64(value) as i64;
+                }
+                _ => {}
+            }
+        }
+        Ok(interface)
+    }
+
+    fn packet(
+        &self,
+        interface: u32,
+        timestamp: Option<u64>,
+        incl_len: usize,
+        orig_len: u32,
+        data: Vec<u8>,
+    ) -> io::Result<PcapPacket> {
+        let interface = self
+            .interfaces()
+            .get(interface as usize)
+            .ok_or_else(|| invalid("pcapng packet for an undefined interface"))?;
+        let timestamp = timestamp.map_or_else(Timestamp::default, |units| interface.timestamp(units));
+        Ok(PcapPacket {
+            header: PcapPacketHeader {
+                timestamp,
+                incl_len: incl_len as u32,
+                orig_len,
+                link_type: Some(interface.link_type),
+            },
+            data,
+        })
+    }
+
+    /// Reads the blocks before `offset`, which must start a block, so that
+    /// the sections and interfaces they hold are known when the block at
+    /// `offset` is read. Returns false if the file ends first.
+    pub async fn skip_to<R: AsyncBufRead + Unpin>(
+        &mut self,
+        reader: &mut R,
+        offset: usize,
+    ) -> io::Result<bool> {
+        while self.offset < offset {
+            if self.read_block(reader, false).await?.is_none() {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Reads blocks until the next packet. Enhanced, simple and obsolete
+    /// packet blocks are returned; other blocks are skipped. Without
+    /// `keep_data` only the packet headers are read.
+    pub async fn next_packet<R: AsyncBufRead + Unpin>(
+        &mut self,
+        reader: &mut R,
+        keep_data: bool,
+    ) -> io::Result<Option<PcapPacket>> {
+        while let Some((kind, body, body_len)) = self.read_block(reader, keep_data).await? {
+            match kind {
+                ENHANCED_PACKET_BLOCK if body.len() >= 20 => {
+                    let incl_len = self.read_u32(&body[12..16]) as usize;
+                    if incl_len > body_len - 20 {
+                        return Err(invalid("pcapng packet longer than its block"));
+                    }
+                    let data = body.get(20..20 + incl_len).unwrap_or_default().to_vec();
+                    return self
+                        .packet(
+                            self.read_u32(&body[0..4]),
+                            Some(self.timestamp(&body[4..12])),
+                            incl_len,
+                            self.read_u32(&body[16..20]),
+                            data,
+                        )
+                        .map(Some);
+                }
+                OBSOLETE_PACKET_BLOCK if body.len() >= 20 => {
+                    let incl_len = self.read_u32(&body[12..16]) as usize;
+                    if incl_len > body_len - 20 {
+                        return Err(invalid("pcapng packet longer than its block"));
+                    }
+                    let data = body.get(20..20 + incl_len).unwrap_or_default().to_vec();
+                    return self
+                        .packet(
+                            u32::from(self.read_u16(&body[0..2])),
+                            Some(self.timestamp(&body[4..12])),
+                            incl_len,
+                            self.read_u32(&body[16..20]),
+                            data,
+                        )
+                        .map(Some);
+                }
+                SIMPLE_PACKET_BLOCK if body.len() >= 4 => {
+                    // Captured on the first interface, without a timestamp;
+                    // the captured length follows from its snaplen.
+                    let orig_len = self.read_u32(&body[0..4]);
+                    let snaplen = self.interfaces().first().map_or(0, |first| first.snaplen);
+                    let mut incl_len = (body_len - 4).min(orig_len as usize);
+                    if snaplen != 0 {
+                        incl_len = incl_len.min(snaplen as usize);
+                    }
+                    let data = body.get(4..4 + incl_len).unwrap_or_default().to_vec();
+                    return self.packet(0, None, incl_len, orig_len, data).map(Some);
+                }
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cap::{Capture, Format};
+
+    fn block(kind: u32, body: &[u8]) -> Vec<u8> {
+        let padded = body.len().div_ceil(4) * 4;
+        let total = (12 + padded) as u32;
+        let mut data = kind.to_le_bytes().to_vec();
+        data.extend_from_slice(&total.to_le_bytes());
+        data.extend_from_slice(body);
+        data.resize(8 + padded, 0);
+        data.extend_from_slice(&total.to_le_bytes());
+        data
+    }
+
+    fn interface(link_type: u16, options: &[u8]) -> Vec<u8> {
+        let mut body = link_type.to_le_bytes().to_vec();
+        body.extend_from_slice(&[0, 0]);
+        body.extend_from_slice(&65535u32.to_le_bytes());
+        body.extend_from_slice(options);
+        block(INTERFACE_DESCRIPTION_BLOCK, &body)
+    }
+
+    fn enhanced_packet(interface: u32, timestamp: u64, frame: &[u8]) -> Vec<u8> {
+        let mut body = interface.to_le_bytes().to_vec();
+        body.extend_from_slice(&((timestamp >> 32) as u32).to_le_bytes());
+        body.extend_from_slice(&(timestamp as u32).to_le_bytes());
+        body.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        body.extend_from_slice(&(frame.len() as u32 + 4).to_le_bytes());
+        body.extend_from_slice(frame);
+        block(ENHANCED_PACKET_BLOCK, &body)
+    }
+
+    #[tokio::test]
+    async fn test_pcapng_capture() {
+        let temp_file_path = "test.pcapng";
+        let mut section = BYTE_ORDER_MAGIC.to_le_bytes().to_vec();
+        section.extend_from_slice(&[1, 0, 0, 0]);
+        section.extend_from_slice(&(-1i64).to_le_bytes());
+        let mut data = block(SECTION_HEADER_BLOCK, &section);
+        data.extend(interface(1, &[]));
+        // Raw IP with nanosecond resolution, then the end of options.
+        data.extend(interface(101, &[9, 0, 1, 0, 9, 0, 0, 0, 0, 0, 0, 0]));
+        let frame: Vec<u8> = (0..61).collect();
+        data.extend(enhanced_packet(0, 1_700_000_000_000_250, &frame));
+        data.extend(block(5, b"stats"));
+        data.extend(enhanced_packet(1, 1_700_000_001_000_750_999, &frame[..14]));
+        tokio::fs::write(temp_file_path, &data).await.unwrap();
+
+        let mut capture = Capture::from_file(temp_file_path).await.unwrap();
+        assert_eq!(capture.header().network, 1);
+        let first = capture.next_packet().await.unwrap().unwrap();
+        assert_eq!(first.header.timestamp, Timestamp::new(1_700_000_000, 250_000));
+        assert_eq!(first.header.incl_len, 61);
+        assert_eq!(first.header.orig_len, 65);
+        assert_eq!(first.header.link_type, Some(1));
+        assert_eq!(first.data, frame);
+        let second = capture.next_packet().await.unwrap().unwrap();
+        assert_eq!(second.header.timestamp, Timestamp::new(1_700_000_001, 750_999));
+        assert_eq!(second.link_type(capture.header().network), 101);
+        assert_eq!(second.data, &frame[..14]);
+        assert!(capture.next_packet().await.unwrap().is_none());
+
+        let mut capture = Capture::from_file(temp_file_path).await.unwrap();
+        assert_eq!(capture.next_header().await.unwrap().unwrap().incl_len, 61);
+        assert_eq!(capture.next_header().await.unwrap().unwrap().incl_len, 14);
+        assert!(capture.next_header().await.unwrap().is_none());
+
+        tokio::fs::remove_file(temp_file_path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_seek_across_sections() {
+        let temp_file_path = "test_sections.pcapng";
+        let mut section = BYTE_ORDER_MAGIC.to_le_bytes().to_vec();
+        section.extend_from_slice(&[1, 0, 0, 0]);
+        section.extend_from_slice(&(-1i64).to_le_bytes());
+        let mut data = Vec::new();
+        for link_type in [1, 101] {
+            data.extend(block(SECTION_HEADER_BLOCK, &section));
+            data.extend(interface(link_type, &[]));
+            data.extend(enhanced_packet(0, 1_700_000_000_000_000, &[0; 20]));
+            data.extend(interface(113, &[]));
+            data.extend(enhanced_packet(1, 1_700_000_000_000_000, &[0; 16]));
+        }
+        tokio::fs::write(temp_file_path, &data).await.unwrap();
+
+        let mut capture = Capture::from_file(temp_file_path).await.unwrap();
+        let index = capture.build_index().await.unwrap().clone();
+        assert_eq!(index.len(), 4);
+        assert_eq!(index.get(1).unwrap().offset, 28 + 20);
+        // Going back restores the interfaces of the earlier section, and
+        // reading forward again does not add its interfaces twice.
+        for (number, link_type) in [(2, 113), (1, 1), (3, 101), (2, 113), (4, 113)] {
+            capture.seek_to_packet(number).await.unwrap();
+            let packet = capture.next_packet().await.unwrap().unwrap();
+            assert_eq!(packet.header.link_type, Some(link_type));
+        }
+        capture.seek_to_packet(1).await.unwrap();
+        while capture.next_packet().await.unwrap().is_some() {}
+        let Format::Pcapng(pcapng) = &capture.format else {
+            panic!("not read as pcapng");
+        };
+        assert_eq!(pcapng.interfaces.len(), 4);
+
+        tokio::fs::remove_file(temp_file_path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_packet_before_interface() {
+        let mut section = BYTE_ORDER_MAGIC.to_le_bytes().to_vec();
+        section.extend_from_slice(&[1, 0, 0, 0]);
+        section.extend_from_slice(&(-1i64).to_le_bytes());
+        let mut data = block(SECTION_HEADER_BLOCK, &section);
+        data.extend(enhanced_packet(0, 0, &[0; 14]));
+        assert!(PcapngReader::open(&mut data.as_slice()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_oversized_block() {
+        let mut section = BYTE_ORDER_MAGIC.to_le_bytes().to_vec();
+        section.extend_from_slice(&[1, 0, 0, 0]);
+        section.extend_from_slice(&(-1i64).to_le_bytes());
+        let mut data = block(SECTION_HEADER_BLOCK, &section);
+        let interface_offset = data.len();
+        data.extend(interface(1, &[]));
+        data[interface_offset + 4..interface_offset + 8]
+            .copy_from_slice(&(MAX_RECORD_LEN + 4).to_le_bytes());
+        let error = PcapngReader::open(&mut data.as_slice()).await.unwrap_err();
+        assert!(matches!(
+            KcpdumpError::from(error),
+            KcpdumpError::MalformedPacket { offset, .. } if offset == interface_offset
+        ));
+    }
+}
@@ -0,0 +1,186 @@
+use byteorder::{BigEndian, ByteOrder};
+use tokio::io::{self, AsyncBufRead, AsyncRead, AsyncReadExt};
+
+use super::{MAX_RECORD_LEN, PcapPacket, PcapPacketHeader, invalid_capture, skip_bytes};
+use crate::error::KcpdumpError;
+use crate::timefmt::Timestamp;
+
+/// File magic of RFC 1761 snoop files.
+pub const SNOOP_MAGIC: &[u8; 8] = b"snoop\0\0\0";
+
+pub(crate) const FILE_HEADER_LEN: usize = 16;
+const RECORD_HEADER_LEN: usize = 24;
+
+/// Returns the pcap link type of a snoop datalink type.
+pub fn link_type(datalink: u32) -> Option<u32> {
+    match datalink {
+        // IEEE 802.3 and Ethernet.
+        0 | 4 => Some(1),
+        // IEEE 802.5 token ring.
+        2 => Some(6),
+        // FDDI.
+        8 => Some(10),
+        _ => None,
+    }
+}
+
+/// Snoop Header
+/// The file header following the magic.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnoopHeader {
+    pub version: u32,
+    pub datalink: u32,
+}
+
+impl TryFrom<&[u8]> for SnoopHeader {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < FILE_HEADER_LEN {
+            return Err(invalid_capture("Data too short for snoop header"));
+        }
+        if &data[0..8] != SNOOP_MAGIC {
+            return Err(invalid_capture("Invalid snoop magic"));
+        }
+        let version = BigEndian::read_u32(&data[8..12]);
+        if version != 2 {
+            return Err(invalid_capture("Unsupported snoop version"));
+        }
+        Ok(SnoopHeader {
+            version,
+            datalink: BigEndian::read_u32(&data[12..16]),
+        })
+    }
+}
+
+/// Reads and validates the file header.
+pub async fn read_header<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<SnoopHeader> {
+    let mut header_buf = [0u8; FILE_HEADER_LEN];
+    reader.read_exact(&mut header_buf).await?;
+    SnoopHeader::try_from(header_buf.as_slice()).map_err(io::Error::from)
+}
+
+/// Reads the next packet record and returns it with the length of the
+/// record in the file. Records are padded to four bytes. Without
+/// `keep_data`, the packet data is skipped and left empty.
+pub async fn next_packet<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    keep_data: bool,
+) -> io::Result<Option<(PcapPacket, usize)>> {
+    let mut record_buf = [0u8; RECORD_HEADER_LEN];
+    match reader.read_exact(&mut record_buf).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let orig_len = BigEndian::read_u32(&record_buf[0..4]);
+    let incl_len = BigEndian::read_u32(&record_buf[4..8]);
+    let record_len = BigEndian::read_u32(&record_buf[8..12]);
+    if incl_len > MAX_RECORD_LEN || record_len > MAX_RECORD_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Snoop record length too large",
+        ));
+    }
+    let record_len = record_len as usize;
+    if record_len < RECORD_HEADER_LEN + incl_len as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Snoop record shorter than its packet",
+        ));
+    }
+
+    let mut body = Vec::new();
+    if keep_data {
+        body.resize(record_len - RECORD_HEADER_LEN, 0);
+        reader.read_exact(&mut body).await?;
+        body.truncate(incl_len as usize);
+    } else {
+        skip_bytes(reader, record_len - RECORD_HEADER_LEN).await?;
+    }
+    let packet = PcapPacket {
+        header: PcapPacketHeader {
+            timestamp: Timestamp::from_micros(
+                BigEndian::read_u32(&record_buf[16..20]),
+                BigEndian::read_u32(&record_buf[20..24]),
+            ),
+            incl_len,
+            orig_len,
+            link_type: None,
+        },
+        data: body,
+    };
+    Ok(Some((packet, record_len)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cap::Capture;
+
+    fn record(ts_sec: u32, frame: &[u8]) -> Vec<u8> {
+        let padded = frame.len().div_ceil(4) * 4;
+        let mut data = Vec::new();
+        data.extend_from_slice(&(frame.len() as u32 + 4).to_be_bytes());
+        data.extend_from_slice(&(frame.len() as u32).to_be_bytes());
+        data.extend_from_slice(&((RECORD_HEADER_LEN + padded) as u32).to_be_bytes());
+        data.extend_from_slice(&0u32.to_be_bytes());
+        data.extend_from_slice(&ts_sec.to_be_bytes());
+        data.extend_from_slice(&250u32.to_be_bytes());
+        data.extend_from_slice(frame);
+        data.resize(RECORD_HEADER_LEN + padded, 0);
+        data
+    }
+
+    #[test]
+    fn test_header() {
+        let mut data = SNOOP_MAGIC.to_vec();
+        data.extend_from_slice(&2u32.to_be_bytes());
+        data.extend_from_slice(&4u32.to_be_bytes());
+        let header = SnoopHeader::try_from(data.as_slice()).unwrap();
+        assert_eq!(header.datalink, 4);
+        assert_eq!(link_type(header.datalink), Some(1));
+        data[11] = 1;
+        assert!(SnoopHeader::try_from(data.as_slice()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_snoop_capture() {
+        let temp_file_path = "test.snoop";
+        let mut data = SNOOP_MAGIC.to_vec();
+        data.extend_from_slice(&2u32.to_be_bytes());
+        data.extend_from_slice(&4u32.to_be_bytes());
+        let frame: Vec<u8> = (0..61).collect();
+        data.extend(record(1_700_000_000, &frame));
+        data.extend(record(1_700_000_001, &frame[..14]));
+        tokio::fs::write(temp_file_path, &data).await.unwrap();
+
+        let mut capture = Capture::from_file(temp_file_path).await.unwrap();
+        assert_eq!(capture.header().network, 1);
+        let first = capture.next_packet().await.unwrap().unwrap();
+        assert_eq!(first.header.timestamp, Timestamp::from_micros(1_700_000_000, 250));
+        assert_eq!(first.header.incl_len, 61);
+        assert_eq!(first.header.orig_len, 65);
+        assert_eq!(first.data, frame);
+        let second = capture.next_packet().await.unwrap().unwrap();
+        assert_eq!(second.data, &frame[..14]);
+        assert!(capture.next_packet().await.unwrap().is_none());
+
+        // Records are indexed at their offsets, padding included.
+        let index = capture.build_index().await.unwrap().clone();
+        assert_eq!(index.get(1).unwrap().offset, 16);
+        assert_eq!(index.get(2).unwrap().offset, 16 + 24 + 64);
+        capture.seek_to_packet(2).await.unwrap();
+        assert_eq!(capture.next_packet().await.unwrap().unwrap().data, &frame[..14]);
+
+        tokio::fs::remove_file(temp_file_path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_oversized_record() {
+        let mut data = record(1_700_000_000, &[0; 4]);
+        data[8..12].copy_from_slice(&u32::MAX.to_be_bytes());
+        let error = next_packet(&mut data.as_slice(), true).await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,277 @@
+use crate::dissect::{Dissector, FieldInfo, FieldType, FieldValue, FieldValues, PacketLayers};
+use crate::error::KcpdumpError;
+use crate::wlan::LINKTYPE_IEEE802_11;
+
+/// UDP ports of the CAPWAP control and data channels.
+pub const CAPWAP_CONTROL_PORT: u16 = 5246;
+pub const CAPWAP_DATA_PORT: u16 = 5247;
+
+/// Wireless binding ID of IEEE 802.11.
+pub const WBID_IEEE802_11: u8 = 1;
+
+/// Capwap Packet
+/// A CAPWAP header and the control message or frame it carries.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapwapPacket {
+    /// The payload is DTLS encrypted.
+    pub dtls: bool,
+    pub radio_id: u8,
+    pub wbid: u8,
+    /// Data frames use the native format of the binding instead of 802.3.
+    pub native: bool,
+    pub fragment: bool,
+    pub last_fragment: bool,
+    pub keep_alive: bool,
+    pub fragment_id: u16,
+    pub fragment_offset: u16,
+    pub radio_mac: Option<Vec<u8>>,
+    pub payload: Vec<u8>,
+}
+
+impl TryFrom<&[u8]> for CapwapPacket {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.is_empty() || data[0] >> 4 != 0 {
+            return Err(KcpdumpError::malformed(0, "Unsupported CAPWAP version"));
+        }
+        if data[0] & 0x0f == 1 {
+            return Ok(CapwapPacket {
+                dtls: true,
+                radio_id: 0,
+                wbid: 0,
+                native: false,
+                fragment: false,
+                last_fragment: false,
+                keep_alive: false,
+                fragment_id: 0,
+                fragment_offset: 0,
+                radio_mac: None,
+                payload: data.get(4..).unwrap_or_default().to_vec(),
+            });
+        }
+        if data.len() < 8 {
+            return Err(KcpdumpError::malformed(
+                0,
+                "Data too short for CAPWAP header",
+            ));
+        }
+        let word = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+        let header_len = ((word >> 19) & 0x1f) as usize * 4;
+        let payload = data
+            .get(header_len.max(8)..)
+            .ok_or(KcpdumpError::malformed(
+                header_len.max(8),
+                "CAPWAP header truncated",
+            ))?;
+        let flags = word & 0x1ff;
+        let radio_mac = if flags & 0x10 != 0 {
+            let length = usize::from(
+                *data
+                    .get(8)
+                    .ok_or(KcpdumpError::malformed(8, "CAPWAP header truncated"))?,
+            );
+            Some(
+                data.get(9..9 + length)
+                    .ok_or(KcpdumpError::malformed(9, "CAPWAP radio MAC truncated"))?
+                    .to_vec(),
+            )
+        } else {
+            None
+        };
+        Ok(CapwapPacket {
+            dtls: false,
+            radio_id: ((word >> 14) & 0x1f) as u8,
+            wbid: ((word >> 9) & 0x1f) as u8,
+            native: flags & 0x100 != 0,
+            fragment: flags & 0x80 != 0,
+            last_fragment: flags & 0x40 != 0,
+            keep_alive: flags & 0x08 != 0,
+            fragment_id: u16::from_be_bytes([data[4], data[5]]),
+            fragment_offset: u16::from_be_bytes([data[6], data[7]]) >> 3,
+            radio_mac,
+            payload: payload.to_vec(),
+        })
+    }
+}
+
+impl CapwapPacket {
+    /// Link type of the tunneled frame of a data channel packet, if it is
+    /// a complete frame.
+    pub fn link_type(&self) -> Option<u32> {
+        if self.dtls || self.keep_alive || self.fragment {
+            return None;
+        }
+        match (self.native, self.wbid) {
+            (false, _) => Some(1),
+            (true, WBID_IEEE802_11) => Some(LINKTYPE_IEEE802_11),
+            _ => None,
+        }
+    }
+}
+
+/// Control Message
+/// The header of a CAPWAP control channel message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ControlMessage {
+    /// Vendor enterprise number, 0 for standard messages.
+    pub enterprise: u32,
+    pub message_type: u8,
+    pub sequence: u8,
+    /// Message elements as (type, value) pairs.
+    pub elements: Vec<(u16, Vec<u8>)>,
+}
+
+impl TryFrom<&[u8]> for ControlMessage {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 8 {
+            return Err(KcpdumpError::malformed(
+                0,
+                "Data too short for CAPWAP control message",
+            ));
+        }
+        // The element length counts the flags byte.
+        let length = usize::from(u16::from_be_bytes([data[5], data[6]]));
+        let mut rest = data
+            .get(8..7 + length.max(1))
+            .ok_or(KcpdumpError::malformed(
+                8,
+                "CAPWAP message elements truncated",
+            ))?;
+        let mut elements = Vec::new();
+        while rest.len() >= 4 {
+            let element_type = u16::from_be_bytes([rest[0], rest[1]]);
+            let element_len = usize::from(u16::from_be_bytes([rest[2], rest[3]]));
+            let value = rest.get(4..4 + element_len).ok_or(KcpdumpError::malformed(
+                4,
+                "CAPWAP message element truncated",
+            ))?;
+            elements.push((element_type, value.to_vec()));
+            rest = &rest[4 + element_len..];
+        }
+        Ok(ControlMessage {
+            enterprise: u32::from_be_bytes([0, data[0], data[1], data[2]]),
+            message_type: data[3],
+            sequence: data[4],
+            elements,
+        })
+    }
+}
+
+impl ControlMessage {
+    pub fn name(&self) -> String {
+        if self.enterprise != 0 {
+            return format!("Vendor message {}", self.message_type);
+        }
+        let name = match self.message_type {
+            1 => "Discovery Request",
+            2 => "Discovery Response",
+            3 => "Join Request",
+            4 => "Join Response",
+            5 => "Configuration Status Request",
+            6 => "Configuration Status Response",
+            7 => "Configuration Update Request",
+            8 => "Configuration Update Response",
+            9 => "WTP Event Request",
+            10 => "WTP Event Response",
+            11 => "Change State Event Request",
+            12 => "Change State Event Response",
+            13 => "Echo Request",
+            14 => "Echo Response",
+            15 => "Image Data Request",
+            16 => "Image Data Response",
+            17 => "Reset Request",
+            18 => "Reset Response",
+            19 => "Primary Discovery Request",
+            20 => "Primary Discovery Response",
+            21 => "Data Transfer Request",
+            22 => "Data Transfer Response",
+            23 => "Clear Configuration Request",
+            24 => "Clear Configuration Response",
+            25 => "Station Configuration Request",
+            26 => "Station Configuration Response",
+            other => return format!("Message type {}", other),
+        };
+        name.to_string()
+    }
+}
+
+/// Capwap Dissector
+pub struct CapwapDissector;
+
+const CAPWAP_FIELDS: &[FieldInfo] = &[
+    FieldInfo::new("capwap.rid", FieldType::UInt, "Radio ID"),
+    FieldInfo::new("capwap.wbid", FieldType::UInt, "Wireless binding ID"),
+    FieldInfo::new("capwap.flags.t", FieldType::Bool, "Native frame format"),
+    FieldInfo::new("capwap.flags.k", FieldType::Bool, "Data channel keep-alive"),
+];
+
+impl Dissector for CapwapDissector {
+    fn protocol(&self) -> &'static str {
+        "capwap"
+    }
+
+    fn description(&self) -> &'static str {
+        "Control And Provisioning of Wireless Access Points"
+    }
+
+    fn fields(&self) -> &'static [FieldInfo] {
+        CAPWAP_FIELDS
+    }
+
+    fn dissect(&self, layers: &PacketLayers, values: &mut FieldValues) {
+        let Some(capwap) = &layers.capwap else {
+            return;
+        };
+        values.push("capwap", FieldValue::Protocol);
+        if capwap.dtls {
+            return;
+        }
+        values.push("capwap.rid", FieldValue::UInt(capwap.radio_id.into()));
+        values.push("capwap.wbid", FieldValue::UInt(capwap.wbid.into()));
+        values.push("capwap.flags.t", FieldValue::Bool(capwap.native));
+        values.push("capwap.flags.k", FieldValue::Bool(capwap.keep_alive));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data_channel() {
+        // HLEN 2, RID 1, WBID 1, T set: an 802.11 frame follows.
+        let mut data = vec![0x00, 0x10, 0x43, 0x00, 0x00, 0x00, 0x00, 0x00];
+        data.extend_from_slice(&[0x08, 0x01]);
+        let capwap = CapwapPacket::try_from(data.as_slice()).unwrap();
+        assert_eq!(capwap.radio_id, 1);
+        assert_eq!(capwap.wbid, WBID_IEEE802_11);
+        assert!(capwap.native);
+        assert_eq!(capwap.payload, [0x08, 0x01]);
+        assert_eq!(capwap.link_type(), Some(LINKTYPE_IEEE802_11));
+
+        // T cleared: an 802.3 frame follows.
+        data[2] = 0x42;
+        let capwap = CapwapPacket::try_from(data.as_slice()).unwrap();
+        assert_eq!(capwap.link_type(), Some(1));
+
+        let dtls = CapwapPacket::try_from(&[0x01, 0x00, 0x00, 0x00, 0x16][..]).unwrap();
+        assert!(dtls.dtls);
+        assert_eq!(dtls.link_type(), None);
+    }
+
+    #[test]
+    fn test_control_message() {
+        // Discovery Request, sequence 5, with a Discovery Type element.
+        let data = [
+            0x00, 0x00, 0x00, 0x01, 0x05, 0x00, 0x06, 0x00, 0x00, 0x14, 0x00, 0x01, 0x00,
+        ];
+        let message = ControlMessage::try_from(&data[..]).unwrap();
+        assert_eq!(message.name(), "Discovery Request");
+        assert_eq!(message.sequence, 5);
+        assert_eq!(message.elements, vec![(20, vec![0x00])]);
+        assert!(ControlMessage::try_from(&data[..10]).is_err());
+    }
+}
@@ -0,0 +1,175 @@
+use std::sync::Arc;
+
+use crate::dissect::PacketLayers;
+use crate::error::KcpdumpError;
+use crate::reassembly::{StreamReassembler, TcpStream};
+use crate::session::{CaptureId, LoadedCapture};
+
+pub mod mysql;
+pub mod postgres;
+pub mod tds;
+
+/// Parsed Query
+/// A statement and its response as found by a protocol parser, located by
+/// offsets into the reassembled client and server data.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedQuery {
+    pub statement: String,
+    pub client_offset: usize,
+    /// Start of the response; `None` if the server did not answer.
+    pub server_offset: Option<usize>,
+    pub error: Option<String>,
+    /// Rows returned or affected, if the response reports them.
+    pub rows: Option<u64>,
+}
+
+/// Parsed Session
+/// What a protocol parser learned from one database connection.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedSession {
+    pub server_version: Option<String>,
+    pub user: Option<String>,
+    pub database: Option<String>,
+    /// Error returned in place of a successful login.
+    pub login_error: Option<String>,
+    /// Whether the connection switched to TLS, hiding some or all of it.
+    pub encrypted: bool,
+    pub queries: Vec<ParsedQuery>,
+}
+
+/// Database Query
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseQuery {
+    /// Frame carrying the start of the statement.
+    pub number: usize,
+    /// Frame carrying the start of the response.
+    pub response_number: Option<usize>,
+    /// Seconds since the epoch.
+    pub time: f64,
+    /// Seconds from the statement to the start of its response.
+    pub response_time: Option<f64>,
+    pub statement: String,
+    pub error: Option<String>,
+    pub rows: Option<u64>,
+}
+
+/// Database Session
+/// One client connection to a MySQL, PostgreSQL or SQL Server (TDS) server.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseSession {
+    pub capture_id: CaptureId,
+    /// `mysql`, `postgresql` or `tds`.
+    pub protocol: String,
+    pub first_number: usize,
+    pub client: String,
+    pub server: String,
+    pub server_version: Option<String>,
+    pub user: Option<String>,
+    pub database: Option<String>,
+    pub login_error: Option<String>,
+    pub encrypted: bool,
+    pub queries: Vec<DatabaseQuery>,
+}
+
+/// Detects the database protocol of a stream from its content.
+fn parse_stream(stream: &TcpStream) -> Option<(&'static str, ParsedSession)> {
+    let (client, server) = (&stream.client_data.data, &stream.server_data.data);
+    if let Some(session) = mysql::parse(client, server) {
+        return Some(("mysql", session));
+    }
+    if let Some(session) = postgres::parse(client, server) {
+        return Some(("postgresql", session));
+    }
+    tds::parse(client, server).map(|session| ("tds", session))
+}
+
+fn session(capture: &LoadedCapture, stream: &TcpStream) -> Option<DatabaseSession> {
+    let (protocol, parsed) = parse_stream(stream)?;
+    let time = |number: usize| {
+        capture
+            .index
+            .get(number)
+            .map(|entry| entry.timestamp.as_secs_f64())
+    };
+    let queries = parsed
+        .queries
+        .into_iter()
+        .map(|query| {
+            let number = stream
+                .client_data
+                .frame_at(query.client_offset)
+                .unwrap_or(stream.first_number);
+            let response_number = query
+                .server_offset
+                .and_then(|offset| stream.server_data.frame_at(offset));
+            let sent = time(number).unwrap_or_default();
+            DatabaseQuery {
+                number,
+                response_number,
+                time: sent,
+                response_time: response_number
+                    .and_then(time)
+                    .map(|received| received - sent),
+                statement: query.statement,
+                error: query.error,
+                rows: query.rows,
+            }
+        })
+        .collect();
+    Some(DatabaseSession {
+        capture_id: capture.id,
+        protocol: protocol.to_string(),
+        first_number: stream.first_number,
+        client: format!("{}:{}", stream.client.0, stream.client.1),
+        server: format!("{}:{}", stream.server.0, stream.server.1),
+        server_version: parsed.server_version,
+        user: parsed.user,
+        database: parsed.database,
+        login_error: parsed.login_error,
+        encrypted: parsed.encrypted,
+        queries,
+    })
+}
+
+/// Finds the MySQL, PostgreSQL and TDS connections of the captures with
+/// their logins and statements. Protocols are recognised by content, so
+/// servers on non-standard ports are found as well.
+pub fn database_sessions(
+    captures: &[Arc<LoadedCapture>],
+) -> Result<Vec<DatabaseSession>, KcpdumpError> {
+    let mut sessions = Vec::new();
+    for capture in captures {
+        let mut reassembler = StreamReassembler::default();
+        for (index, packet) in capture.packets().enumerate() {
+            let packet = packet?;
+            reassembler.push(&PacketLayers::decode_link(index + 1, capture.header.network, &packet));
+        }
+        sessions.extend(
+            reassembler
+                .finish()
+                .iter()
+                .filter_map(|stream| session(capture, stream)),
+        );
+    }
+    Ok(sessions)
+}
+
+/// Decodes UTF-16LE text, as used by TDS.
+fn utf16le(data: &[u8]) -> String {
+    let units: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+/// Splits a NUL-terminated string from the front of `data`.
+fn c_string(data: &[u8]) -> Option<(String, &[u8])> {
+    let end = data.iter().position(|&byte| byte == 0)?;
+    Some((
+        String::from_utf8_lossy(&data[..end]).into_owned(),
+        &data[end + 1..],
+    ))
+}
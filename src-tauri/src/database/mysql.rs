@@ -0,0 +1,356 @@
+use std::collections::HashMap;
+
+use super::{ParsedQuery, ParsedSession, c_string};
+
+const COM_QUIT: u8 = 0x01;
+const COM_INIT_DB: u8 = 0x02;
+const COM_QUERY: u8 = 0x03;
+const COM_PING: u8 = 0x0e;
+const COM_STMT_PREPARE: u8 = 0x16;
+const COM_STMT_EXECUTE: u8 = 0x17;
+const COM_STMT_SEND_LONG_DATA: u8 = 0x18;
+const COM_STMT_CLOSE: u8 = 0x19;
+
+const CLIENT_CONNECT_WITH_DB: u32 = 0x0000_0008;
+const CLIENT_SSL: u32 = 0x0000_0800;
+const CLIENT_SECURE_CONNECTION: u32 = 0x0000_8000;
+const CLIENT_PLUGIN_AUTH_LENENC_CLIENT_DATA: u32 = 0x0020_0000;
+const CLIENT_DEPRECATE_EOF: u32 = 0x0100_0000;
+
+/// A MySQL packet: 3-byte length, sequence id and payload.
+#[derive(Clone, Copy)]
+struct Packet<'a> {
+    offset: usize,
+    sequence: u8,
+    payload: &'a [u8],
+}
+
+impl Packet<'_> {
+    fn is_ok(&self) -> bool {
+        self.payload.first() == Some(&0x00)
+    }
+
+    fn is_err(&self) -> bool {
+        self.payload.first() == Some(&0xff)
+    }
+
+    /// EOF, or the OK packet that replaces it with CLIENT_DEPRECATE_EOF.
+    fn is_eof(&self, deprecate_eof: bool) -> bool {
+        self.payload.first() == Some(&0xfe) && (deprecate_eof || self.payload.len() < 9)
+    }
+}
+
+fn packets(data: &[u8]) -> Vec<Packet<'_>> {
+    let mut packets = Vec::new();
+    let mut offset = 0;
+    while offset + 4 <= data.len() {
+        let length = u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], 0]);
+        let end = offset + 4 + length as usize;
+        if length == 0 || end > data.len() {
+            break;
+        }
+        packets.push(Packet {
+            offset,
+            sequence: data[offset + 3],
+            payload: &data[offset + 4..end],
+        });
+        offset = end;
+    }
+    packets
+}
+
+/// Reads a length-encoded integer, returning it with its size.
+fn lenenc(data: &[u8]) -> Option<(u64, usize)> {
+    let le = |bytes: &[u8]| {
+        bytes
+            .iter()
+            .rev()
+            .fold(0u64, |value, &byte| value << 8 | u64::from(byte))
+    };
+    match *data.first()? {
+        byte @ 0x00..=0xfa => Some((u64::from(byte), 1)),
+        0xfc => Some((le(data.get(1..3)?), 3)),
+        0xfd => Some((le(data.get(1..4)?), 4)),
+        0xfe => Some((le(data.get(1..9)?), 9)),
+        _ => None,
+    }
+}
+
+/// Formats an ERR packet as `ERROR <code> (<state>): <message>`.
+fn error_message(payload: &[u8]) -> String {
+    let code = payload
+        .get(1..3)
+        .map_or(0, |code| u16::from_le_bytes([code[0], code[1]]));
+    let (state, message) = match payload.get(3) {
+        Some(b'#') if payload.len() >= 9 => {
+            (Some(String::from_utf8_lossy(&payload[4..9])), &payload[9..])
+        }
+        _ => (None, payload.get(3..).unwrap_or_default()),
+    };
+    let message = String::from_utf8_lossy(message);
+    match state {
+        Some(state) => format!("ERROR {} ({}): {}", code, state, message),
+        None => format!("ERROR {}: {}", code, message),
+    }
+}
+
+/// Parses the server greeting: version and capability flags.
+fn greeting(payload: &[u8]) -> Option<(String, u32)> {
+    if payload.first() != Some(&0x0a) {
+        return None;
+    }
+    let (version, rest) = c_string(&payload[1..])?;
+    if version.is_empty() || !version.chars().all(|c| c.is_ascii_graphic()) {
+        return None;
+    }
+    // Connection id, first auth data part and filler precede the flags.
+    let lower = rest
+        .get(13..15)
+        .map_or(0, |flags| u16::from_le_bytes([flags[0], flags[1]]));
+    let upper = rest
+        .get(18..20)
+        .map_or(0, |flags| u16::from_le_bytes([flags[0], flags[1]]));
+    Some((version, u32::from(upper) << 16 | u32::from(lower)))
+}
+
+/// Parses a HandshakeResponse41 into its flags, user and database.
+fn login(payload: &[u8]) -> Option<(u32, Option<String>, Option<String>)> {
+    let flags = u32::from_le_bytes(payload.get(..4)?.try_into().ok()?);
+    let Some((user, rest)) = payload.get(32..).and_then(c_string) else {
+        return Some((flags, None, None));
+    };
+    let auth_length = if flags & CLIENT_PLUGIN_AUTH_LENENC_CLIENT_DATA != 0 {
+        lenenc(rest).map(|(length, size)| length as usize + size)
+    } else if flags & CLIENT_SECURE_CONNECTION != 0 {
+        rest.first().map(|&length| usize::from(length) + 1)
+    } else {
+        rest.iter().position(|&byte| byte == 0).map(|end| end + 1)
+    };
+    let database = if flags & CLIENT_CONNECT_WITH_DB != 0 {
+        auth_length
+            .and_then(|length| rest.get(length..))
+            .and_then(c_string)
+            .map(|(database, _)| database)
+    } else {
+        None
+    };
+    Some((flags, Some(user), database))
+}
+
+/// What a command response reports.
+struct Response {
+    error: Option<String>,
+    rows: Option<u64>,
+    /// Statement id of a COM_STMT_PREPARE response.
+    statement_id: Option<u32>,
+}
+
+fn response(group: &[Packet], deprecate_eof: bool, prepare: bool) -> Response {
+    let mut result = Response {
+        error: None,
+        rows: None,
+        statement_id: None,
+    };
+    let Some(first) = group.first() else {
+        return result;
+    };
+    if first.is_err() {
+        result.error = Some(error_message(first.payload));
+    } else if first.is_ok() && prepare {
+        result.statement_id = first
+            .payload
+            .get(1..5)
+            .map(|id| u32::from_le_bytes([id[0], id[1], id[2], id[3]]));
+    } else if first.is_ok() {
+        result.rows = lenenc(&first.payload[1..]).map(|(rows, _)| rows);
+    } else if let Some((columns, _)) = lenenc(first.payload) {
+        let mut rest = group.get(1 + columns as usize..).unwrap_or_default();
+        if !deprecate_eof && rest.first().is_some_and(|packet| packet.is_eof(false)) {
+            rest = &rest[1..];
+        }
+        let rows = rest
+            .iter()
+            .take_while(|packet| !packet.is_eof(deprecate_eof) && !packet.is_err())
+            .count();
+        result.rows = Some(rows as u64);
+        result.error = rest
+            .get(rows)
+            .filter(|packet| packet.is_err())
+            .map(|packet| error_message(packet.payload));
+    }
+    result
+}
+
+/// Parses a MySQL connection: the greeting, login and each command with
+/// its OK, ERR or result set response. Returns `None` unless the server
+/// opens with a greeting (or an error in its place).
+pub fn parse(client: &[u8], server: &[u8]) -> Option<ParsedSession> {
+    let server_packets = packets(server);
+    let first = server_packets
+        .first()
+        .filter(|packet| packet.sequence == 0)?;
+    let mut session = ParsedSession::default();
+    if first.is_err() && first.payload.len() > 3 {
+        // Refused before the handshake, e.g. "Host is not allowed to connect".
+        session.login_error = Some(error_message(first.payload));
+        return Some(session);
+    }
+    let (version, server_flags) = greeting(first.payload)?;
+    session.server_version = Some(version);
+
+    let client_packets = packets(client);
+    let Some(handshake) = client_packets.first() else {
+        return Some(session);
+    };
+    let Some((client_flags, user, database)) = login(handshake.payload) else {
+        return Some(session);
+    };
+    if client_flags & CLIENT_SSL != 0 && handshake.payload.len() == 32 {
+        session.encrypted = true;
+        return Some(session);
+    }
+    session.user = user;
+    session.database = database;
+    let deprecate_eof = client_flags & server_flags & CLIENT_DEPRECATE_EOF != 0;
+
+    // Authentication ends with the first OK or ERR after the greeting.
+    let mut responses = server_packets[1..].iter();
+    for packet in responses.by_ref() {
+        if packet.is_err() {
+            session.login_error = Some(error_message(packet.payload));
+            return Some(session);
+        }
+        if packet.is_ok() {
+            break;
+        }
+    }
+
+    // Each command starts a new sequence, and so does its response.
+    let mut groups: Vec<Vec<Packet>> = Vec::new();
+    for packet in responses {
+        match groups.last_mut() {
+            Some(group) if packet.sequence != 1 => group.push(*packet),
+            _ => groups.push(vec![*packet]),
+        }
+    }
+    let mut groups = groups.into_iter();
+    let mut prepared: HashMap<u32, String> = HashMap::new();
+    for command in client_packets.iter().filter(|packet| packet.sequence == 0) {
+        let argument = &command.payload[1..];
+        let statement = match command.payload[0] {
+            COM_QUIT | COM_STMT_SEND_LONG_DATA | COM_STMT_CLOSE => continue,
+            COM_QUERY | COM_STMT_PREPARE => String::from_utf8_lossy(argument).into_owned(),
+            COM_INIT_DB => {
+                let database = String::from_utf8_lossy(argument).into_owned();
+                let statement = format!("USE `{}`", database);
+                session.database = Some(database);
+                statement
+            }
+            COM_STMT_EXECUTE => {
+                let id = argument
+                    .get(..4)
+                    .map_or(0, |id| u32::from_le_bytes([id[0], id[1], id[2], id[3]]));
+                prepared
+                    .get(&id)
+                    .cloned()
+                    .unwrap_or_else(|| format!("EXECUTE statement {}", id))
+            }
+            COM_PING => "PING".to_string(),
+            other => format!("Command 0x{:02x}", other),
+        };
+        let group = groups.next().unwrap_or_default();
+        let result = response(
+            &group,
+            deprecate_eof,
+            command.payload[0] == COM_STMT_PREPARE,
+        );
+        if let Some(id) = result.statement_id {
+            prepared.insert(id, statement.clone());
+        }
+        session.queries.push(ParsedQuery {
+            statement,
+            client_offset: command.offset,
+            server_offset: group.first().map(|packet| packet.offset),
+            error: result.error,
+            rows: result.rows,
+        });
+    }
+    Some(session)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(sequence: u8, payload: &[u8]) -> Vec<u8> {
+        let mut packet = (payload.len() as u32).to_le_bytes()[..3].to_vec();
+        packet.push(sequence);
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    #[test]
+    fn test_mysql_session() {
+        let mut greeting = b"\x0a8.0.36\0".to_vec();
+        greeting.extend_from_slice(&[1, 0, 0, 0]); // connection id
+        greeting.extend_from_slice(&[0x41; 8]); // auth data
+        greeting.push(0);
+        greeting.extend_from_slice(&0xffffu16.to_le_bytes());
+        greeting.extend_from_slice(&[0xff, 2, 0]);
+        greeting.extend_from_slice(&0x01ffu16.to_le_bytes());
+        let mut login = (CLIENT_SECURE_CONNECTION | CLIENT_CONNECT_WITH_DB)
+            .to_le_bytes()
+            .to_vec();
+        login.extend_from_slice(&[0; 28]);
+        login.extend_from_slice(b"app\0\x02ab");
+        login.extend_from_slice(b"shop\0");
+
+        let mut client = packet(1, &login);
+        client.extend(packet(0, b"\x03SELECT id FROM orders"));
+        let update = client.len();
+        client.extend(packet(0, b"\x03UPDATE orders SET paid = 1"));
+        client.extend(packet(0, b"\x03SELECT * FROM missing"));
+        client.extend(packet(0, &[COM_QUIT]));
+
+        let mut server = packet(0, &greeting);
+        server.extend(packet(2, &[0, 0, 0, 2, 0, 0, 0]));
+        let result_set = server.len();
+        server.extend(packet(1, &[1]));
+        server.extend(packet(2, b"\x03def"));
+        server.extend(packet(3, &[0xfe, 0, 0, 2, 0]));
+        server.extend(packet(4, b"\x011"));
+        server.extend(packet(5, b"\x012"));
+        server.extend(packet(6, &[0xfe, 0, 0, 2, 0]));
+        server.extend(packet(1, &[0, 3, 0, 2, 0, 0, 0]));
+        server.extend(packet(
+            1,
+            b"\xff\x7a\x04#42S02Table 'shop.missing' doesn't exist",
+        ));
+
+        let session = parse(&client, &server).unwrap();
+        assert_eq!(session.server_version.as_deref(), Some("8.0.36"));
+        assert_eq!(session.user.as_deref(), Some("app"));
+        assert_eq!(session.database.as_deref(), Some("shop"));
+        assert_eq!(session.queries.len(), 3);
+        assert_eq!(session.queries[0].statement, "SELECT id FROM orders");
+        assert_eq!(session.queries[0].rows, Some(2));
+        assert_eq!(session.queries[0].server_offset, Some(result_set));
+        assert_eq!(session.queries[1].client_offset, update);
+        assert_eq!(session.queries[1].rows, Some(3));
+        assert_eq!(
+            session.queries[2].error.as_deref(),
+            Some("ERROR 1146 (42S02): Table 'shop.missing' doesn't exist")
+        );
+
+        // Failed login.
+        let mut server = packet(0, &greeting);
+        server.extend(packet(2, b"\xff\x15\x04#28000Access denied for user 'app'"));
+        let session = parse(&packet(1, &login), &server).unwrap();
+        assert_eq!(
+            session.login_error.as_deref(),
+            Some("ERROR 1045 (28000): Access denied for user 'app'")
+        );
+
+        assert!(parse(b"GET / HTTP/1.1\r\n\r\n", b"HTTP/1.1 200 OK\r\n\r\n").is_none());
+    }
+}
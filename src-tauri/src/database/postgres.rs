@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+
+use super::{ParsedQuery, ParsedSession, c_string};
+
+const PROTOCOL_3_0: u32 = 196608;
+const CANCEL_REQUEST: u32 = 80877102;
+const SSL_REQUEST: u32 = 80877103;
+const GSSENC_REQUEST: u32 = 80877104;
+
+/// A message after startup: type byte, 4-byte length and body.
+struct Message<'a> {
+    offset: usize,
+    tag: u8,
+    body: &'a [u8],
+}
+
+fn be32(data: &[u8]) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(..4)?.try_into().ok()?))
+}
+
+fn messages(data: &[u8], mut offset: usize) -> Vec<Message<'_>> {
+    let mut messages = Vec::new();
+    while let Some(length) = data.get(offset + 1..).and_then(be32) {
+        let end = offset + 1 + length as usize;
+        if length < 4 || end > data.len() {
+            break;
+        }
+        messages.push(Message {
+            offset,
+            tag: data[offset],
+            body: &data[offset + 5..end],
+        });
+        offset = end;
+    }
+    messages
+}
+
+/// Formats an ErrorResponse as `<severity> <code>: <message>`.
+fn error_message(body: &[u8]) -> String {
+    let mut fields = HashMap::new();
+    let mut rest = body;
+    while let Some((&kind, tail)) = rest.split_first() {
+        let Some((value, tail)) = c_string(tail).filter(|_| kind != 0) else {
+            break;
+        };
+        fields.insert(kind, value);
+        rest = tail;
+    }
+    let field = |kind: u8| fields.get(&kind).map_or("", String::as_str);
+    format!("{} {}: {}", field(b'S'), field(b'C'), field(b'M'))
+}
+
+/// Rows reported by a CommandComplete tag such as `SELECT 5` or
+/// `INSERT 0 1`.
+fn tag_rows(body: &[u8]) -> Option<u64> {
+    let (tag, _) = c_string(body)?;
+    tag.rsplit(' ').next()?.parse().ok()
+}
+
+/// Parses a PostgreSQL connection: the startup message, authentication
+/// and each simple or extended query with its CommandComplete or
+/// ErrorResponse. Returns `None` unless the client opens with a startup,
+/// SSL or cancel request.
+pub fn parse(client: &[u8], server: &[u8]) -> Option<ParsedSession> {
+    let mut session = ParsedSession::default();
+    let (mut client_offset, mut server_offset) = (0, 0);
+    loop {
+        let length = be32(client.get(client_offset..)?)? as usize;
+        let code = be32(client.get(client_offset + 4..)?)?;
+        match code {
+            SSL_REQUEST | GSSENC_REQUEST if length == 8 => match server.get(server_offset) {
+                Some(b'N') => {
+                    client_offset += 8;
+                    server_offset += 1;
+                }
+                Some(b'S' | b'G') => {
+                    session.encrypted = true;
+                    return Some(session);
+                }
+                _ => return Some(session),
+            },
+            CANCEL_REQUEST if length == 16 => return Some(session),
+            PROTOCOL_3_0 if length >= 9 => {
+                let mut parameters = client.get(client_offset + 8..client_offset + length)?;
+                while let Some((name, rest)) = c_string(parameters).filter(|(n, _)| !n.is_empty()) {
+                    let (value, rest) = c_string(rest)?;
+                    match name.as_str() {
+                        "user" => session.user = Some(value),
+                        "database" => session.database = Some(value),
+                        _ => {}
+                    }
+                    parameters = rest;
+                }
+                client_offset += length;
+                break;
+            }
+            _ => return None,
+        }
+    }
+    if session.database.is_none() {
+        // The database defaults to the user name.
+        session.database = session.user.clone();
+    }
+
+    // Authentication ends with ReadyForQuery, or an error closes the
+    // connection.
+    let server_messages = messages(server, server_offset);
+    let mut responses = server_messages.iter();
+    for message in responses.by_ref() {
+        match message.tag {
+            b'E' => {
+                session.login_error = Some(error_message(message.body));
+                return Some(session);
+            }
+            b'S' => {
+                if let Some((name, rest)) = c_string(message.body)
+                    && name == "server_version"
+                {
+                    session.server_version = c_string(rest).map(|(version, _)| version);
+                }
+            }
+            b'Z' => break,
+            _ => {}
+        }
+    }
+
+    // Every simple query, and every extended query up to Sync, is answered
+    // by messages ending in ReadyForQuery.
+    let mut groups: Vec<Vec<&Message>> = vec![Vec::new()];
+    for message in responses {
+        groups.last_mut().unwrap().push(message);
+        if message.tag == b'Z' {
+            groups.push(Vec::new());
+        }
+    }
+    let mut groups = groups.into_iter().filter(|group| !group.is_empty());
+    let mut prepared: HashMap<String, String> = HashMap::new();
+    let mut pending: Option<(usize, Option<String>)> = None;
+    let mut requests = Vec::new();
+    for message in messages(client, client_offset) {
+        match message.tag {
+            b'Q' => {
+                let statement = c_string(message.body).map(|(text, _)| text);
+                requests.push((message.offset, statement));
+            }
+            b'P' => {
+                let Some((name, rest)) = c_string(message.body) else {
+                    continue;
+                };
+                let statement = c_string(rest).map(|(text, _)| text);
+                if let Some(statement) = &statement {
+                    prepared.insert(name, statement.clone());
+                }
+                let (_, text) = pending.get_or_insert((message.offset, None));
+                if text.is_none() {
+                    *text = statement;
+                }
+            }
+            b'B' => {
+                let statement = c_string(message.body)
+                    .and_then(|(_, rest)| c_string(rest))
+                    .and_then(|(name, _)| prepared.get(&name).cloned());
+                let (_, text) = pending.get_or_insert((message.offset, None));
+                if text.is_none() {
+                    *text = statement;
+                }
+            }
+            b'E' | b'D' => {
+                pending.get_or_insert((message.offset, None));
+            }
+            b'S' => requests.extend(pending.take()),
+            _ => {}
+        }
+    }
+    for (offset, statement) in requests {
+        let group = groups.next().unwrap_or_default();
+        let error = group
+            .iter()
+            .find(|message| message.tag == b'E')
+            .map(|message| error_message(message.body));
+        let mut rows = None;
+        for message in group.iter().filter(|message| message.tag == b'C') {
+            if let Some(count) = tag_rows(message.body) {
+                *rows.get_or_insert(0) += count;
+            }
+        }
+        session.queries.push(ParsedQuery {
+            statement: statement.unwrap_or_else(|| "(unnamed statement)".to_string()),
+            client_offset: offset,
+            server_offset: group.first().map(|message| message.offset),
+            error,
+            rows,
+        });
+    }
+    Some(session)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(tag: u8, body: &[u8]) -> Vec<u8> {
+        let mut message = vec![tag];
+        message.extend_from_slice(&(body.len() as u32 + 4).to_be_bytes());
+        message.extend_from_slice(body);
+        message
+    }
+
+    #[test]
+    fn test_postgres_session() {
+        let mut client = SSL_REQUEST.to_be_bytes().to_vec();
+        client.splice(0..0, 8u32.to_be_bytes());
+        let parameters = b"user\0app\0database\0shop\0\0";
+        client.extend_from_slice(&(parameters.len() as u32 + 8).to_be_bytes());
+        client.extend_from_slice(&PROTOCOL_3_0.to_be_bytes());
+        client.extend_from_slice(parameters);
+        client.extend(message(b'p', b"secret\0"));
+        client.extend(message(b'Q', b"SELECT * FROM orders\0"));
+        let extended = client.len();
+        client.extend(message(b'P', b"s1\0UPDATE orders SET paid = $1\0\0\0"));
+        client.extend(message(b'B', b"\0s1\0\0\0\0\0\0\0"));
+        client.extend(message(b'E', b"\0\0\0\0\0"));
+        client.extend(message(b'S', b""));
+        client.extend(message(b'Q', b"SELECT * FROM missing\0"));
+        client.extend(message(b'X', b""));
+
+        let mut server = b"N".to_vec();
+        server.extend(message(b'R', &3u32.to_be_bytes()));
+        server.extend(message(b'R', &0u32.to_be_bytes()));
+        server.extend(message(b'S', b"server_version\x0016.2\0"));
+        server.extend(message(b'Z', b"I"));
+        let select = server.len();
+        server.extend(message(b'T', b"\0\0"));
+        server.extend(message(b'D', b"\0\0"));
+        server.extend(message(b'C', b"SELECT 2\0"));
+        server.extend(message(b'Z', b"I"));
+        server.extend(message(b'1', b""));
+        server.extend(message(b'2', b""));
+        server.extend(message(b'C', b"UPDATE 4\0"));
+        server.extend(message(b'Z', b"I"));
+        server.extend(message(
+            b'E',
+            b"SERROR\0C42P01\0Mrelation \"missing\" does not exist\0\0",
+        ));
+        server.extend(message(b'Z', b"I"));
+
+        let session = parse(&client, &server).unwrap();
+        assert_eq!(session.server_version.as_deref(), Some("16.2"));
+        assert_eq!(session.user.as_deref(), Some("app"));
+        assert_eq!(session.database.as_deref(), Some("shop"));
+        assert!(!session.encrypted);
+        assert_eq!(session.queries.len(), 3);
+        assert_eq!(session.queries[0].statement, "SELECT * FROM orders");
+        assert_eq!(session.queries[0].rows, Some(2));
+        assert_eq!(session.queries[0].server_offset, Some(select));
+        assert_eq!(session.queries[1].statement, "UPDATE orders SET paid = $1");
+        assert_eq!(session.queries[1].client_offset, extended);
+        assert_eq!(session.queries[1].rows, Some(4));
+        assert_eq!(
+            session.queries[2].error.as_deref(),
+            Some("ERROR 42P01: relation \"missing\" does not exist")
+        );
+
+        // Failed login.
+        let server = message(b'E', b"SFATAL\0C28P01\0Mpassword authentication failed\0\0");
+        let session = parse(&client[8..], &server).unwrap();
+        assert_eq!(
+            session.login_error.as_deref(),
+            Some("FATAL 28P01: password authentication failed")
+        );
+
+        // TLS accepted.
+        assert!(parse(&client, b"S").unwrap().encrypted);
+        assert!(parse(b"GET / HTTP/1.1\r\n\r\n", b"").is_none());
+    }
+}
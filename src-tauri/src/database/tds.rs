@@ -0,0 +1,506 @@
+use super::{ParsedQuery, ParsedSession, utf16le};
+
+const SQL_BATCH: u8 = 0x01;
+const RPC: u8 = 0x03;
+const TABULAR_RESULT: u8 = 0x04;
+const ATTENTION: u8 = 0x06;
+const BULK_LOAD: u8 = 0x07;
+const TRANSACTION_MANAGER: u8 = 0x0e;
+const LOGIN7: u8 = 0x10;
+const SSPI: u8 = 0x11;
+const PRELOGIN: u8 = 0x12;
+
+const TOKEN_RETURN_STATUS: u8 = 0x79;
+const TOKEN_ERROR: u8 = 0xaa;
+const TOKEN_LOGIN_ACK: u8 = 0xad;
+const TOKEN_FEATURE_EXT_ACK: u8 = 0xae;
+const TOKEN_DONE: u8 = 0xfd;
+const TOKEN_DONE_PROC: u8 = 0xfe;
+const TOKEN_DONE_IN_PROC: u8 = 0xff;
+
+const DONE_COUNT: u16 = 0x0010;
+
+/// A TDS message reassembled from its packets, or a TLS record carrying an
+/// encrypted message.
+enum Message {
+    Tds {
+        offset: usize,
+        packet_type: u8,
+        data: Vec<u8>,
+    },
+    Tls {
+        offset: usize,
+    },
+}
+
+fn messages(data: &[u8]) -> Vec<Message> {
+    let mut messages = Vec::new();
+    let mut current: Option<(usize, u8, Vec<u8>)> = None;
+    let mut offset = 0;
+    while offset + 5 <= data.len() {
+        let header = &data[offset..];
+        if (0x14..=0x17).contains(&header[0]) && header[1] == 0x03 {
+            let end = offset + 5 + usize::from(u16::from_be_bytes([header[3], header[4]]));
+            messages.push(Message::Tls { offset });
+            offset = end;
+            continue;
+        }
+        let length = usize::from(u16::from_be_bytes([header[2], header[3]]));
+        let known = matches!(
+            header[0],
+            SQL_BATCH
+                | RPC
+                | TABULAR_RESULT
+                | ATTENTION
+                | BULK_LOAD
+                | TRANSACTION_MANAGER
+                | LOGIN7
+                | SSPI
+                | PRELOGIN
+        );
+        if !known || length < 8 || offset + length > data.len() {
+            break;
+        }
+        let (_, _, payload) = current.get_or_insert((offset, header[0], Vec::new()));
+        payload.extend_from_slice(&header[8..length]);
+        if header[1] & 0x01 != 0 {
+            let (offset, packet_type, data) = current.take().unwrap_or_default();
+            messages.push(Message::Tds {
+                offset,
+                packet_type,
+                data,
+            });
+        }
+        offset += length;
+    }
+    messages
+}
+
+fn le16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn le32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+/// Reads a LOGIN7 offset/length pair and the UTF-16 string it points at.
+fn login_field(data: &[u8], field: usize) -> Option<String> {
+    let offset = usize::from(le16(data, field)?);
+    let length = usize::from(le16(data, field + 2)?) * 2;
+    let value = utf16le(data.get(offset..offset + length)?);
+    (!value.is_empty()).then_some(value)
+}
+
+/// Skips the ALL_HEADERS block that TDS 7.2+ puts before batches and RPCs.
+fn strip_headers(data: &[u8]) -> &[u8] {
+    match le32(data, 0) {
+        Some(total)
+            if total >= 4
+                && total as usize <= data.len()
+                && le32(data, 4).is_some_and(|first| first <= total) =>
+        {
+            &data[total as usize..]
+        }
+        _ => data,
+    }
+}
+
+fn procedure_name(id: u16) -> String {
+    let name = match id {
+        1 => "sp_cursor",
+        2 => "sp_cursoropen",
+        3 => "sp_cursorprepare",
+        4 => "sp_cursorexecute",
+        5 => "sp_cursorprepexec",
+        6 => "sp_cursorunprepare",
+        7 => "sp_cursorfetch",
+        8 => "sp_cursoroption",
+        9 => "sp_cursorclose",
+        10 => "sp_executesql",
+        11 => "sp_prepare",
+        12 => "sp_execute",
+        13 => "sp_prepexec",
+        14 => "sp_prepexecrpc",
+        15 => "sp_unprepare",
+        _ => return format!("procedure {}", id),
+    };
+    name.to_string()
+}
+
+/// Reads the value of an RPC parameter, returning it as text if it is a
+/// Unicode string, and the remaining data.
+fn rpc_parameter(data: &[u8]) -> Option<(Option<String>, &[u8])> {
+    let name_length = usize::from(*data.first()?) * 2;
+    // Name, status flags, then TYPE_INFO.
+    let rest = data.get(1 + name_length + 1..)?;
+    match *rest.first()? {
+        // INTN and friends: a length byte in TYPE_INFO and before the value.
+        0x26 | 0x68 | 0x6d | 0x6e | 0x6f => {
+            let length = usize::from(*rest.get(2)?);
+            Some((None, rest.get(3 + length..)?))
+        }
+        // NVARCHAR and NCHAR: maximum length and collation.
+        0xe7 | 0xef => {
+            let maximum = le16(rest, 1)?;
+            let rest = rest.get(8..)?;
+            if maximum != 0xffff {
+                let length = le16(rest, 0)?;
+                if length == 0xffff {
+                    return Some((None, rest.get(2..)?));
+                }
+                let end = 2 + usize::from(length);
+                return Some((Some(utf16le(rest.get(2..end)?)), rest.get(end..)?));
+            }
+            // PLP: total length, then chunks ending with an empty one.
+            let mut rest = rest.get(8..)?;
+            let mut value = Vec::new();
+            loop {
+                let length = le32(rest, 0)? as usize;
+                rest = rest.get(4..)?;
+                if length == 0 {
+                    break;
+                }
+                value.extend_from_slice(rest.get(..length)?);
+                rest = &rest[length..];
+            }
+            Some((Some(utf16le(&value)), rest))
+        }
+        _ => None,
+    }
+}
+
+/// Describes an RPC request, using the SQL text of sp_executesql,
+/// sp_prepare and sp_prepexec calls.
+fn rpc_statement(data: &[u8]) -> Option<String> {
+    let data = strip_headers(data);
+    let name_length = le16(data, 0)?;
+    let (name, rest) = if name_length == 0xffff {
+        (procedure_name(le16(data, 2)?), data.get(4..)?)
+    } else {
+        let end = 2 + usize::from(name_length) * 2;
+        (utf16le(data.get(2..end)?), data.get(end..)?)
+    };
+    // Option flags precede the parameters.
+    let mut rest = rest.get(2..)?;
+    let statement_index = match name.as_str() {
+        "sp_executesql" => 0,
+        "sp_prepare" | "sp_prepexec" => 2,
+        _ => return Some(format!("EXEC {}", name)),
+    };
+    for _ in 0..statement_index {
+        let Some((_, tail)) = rpc_parameter(rest) else {
+            return Some(format!("EXEC {}", name));
+        };
+        rest = tail;
+    }
+    match rpc_parameter(rest) {
+        Some((Some(statement), _)) => Some(statement),
+        _ => Some(format!("EXEC {}", name)),
+    }
+}
+
+/// What a tabular result reports.
+#[derive(Default)]
+struct Response {
+    server_version: Option<String>,
+    logged_in: bool,
+    error: Option<String>,
+    rows: Option<u64>,
+}
+
+fn done_rows(data: &[u8]) -> Option<u64> {
+    let status = le16(data, 1)?;
+    let rows = u64::from_le_bytes(data.get(5..13)?.try_into().ok()?);
+    (status & DONE_COUNT != 0).then_some(rows)
+}
+
+/// Walks the tokens of a tabular result up to the first one whose size
+/// depends on column metadata, then reads the final DONE token.
+fn response(data: &[u8]) -> Response {
+    let mut result = Response::default();
+    let mut offset = 0;
+    while let Some(&token) = data.get(offset) {
+        match token {
+            TOKEN_DONE | TOKEN_DONE_PROC | TOKEN_DONE_IN_PROC => {
+                if let Some(rows) = done_rows(&data[offset..]) {
+                    *result.rows.get_or_insert(0) += rows;
+                }
+                offset += 13;
+            }
+            TOKEN_RETURN_STATUS => offset += 5,
+            TOKEN_FEATURE_EXT_ACK => {
+                offset += 1;
+                while let Some(&feature) = data.get(offset) {
+                    offset += 1;
+                    if feature == 0xff {
+                        break;
+                    }
+                    offset += 4 + le32(data, offset).unwrap_or(u32::MAX) as usize;
+                }
+            }
+            // ERROR, INFO, LOGINACK, ENVCHANGE, ORDER, TABNAME, COLINFO and
+            // SSPI carry a 2-byte length.
+            0xaa | 0xab | 0xad | 0xe3 | 0xa9 | 0xa4 | 0xa5 | 0xed => {
+                let Some(length) = le16(data, offset + 1) else {
+                    break;
+                };
+                let body = data
+                    .get(offset + 3..offset + 3 + usize::from(length))
+                    .unwrap_or_default();
+                if token == TOKEN_ERROR && result.error.is_none() {
+                    result.error = error_token(body);
+                } else if token == TOKEN_LOGIN_ACK {
+                    result.logged_in = true;
+                    result.server_version = login_ack_version(body);
+                }
+                offset += 3 + usize::from(length);
+            }
+            _ => {
+                // Rows follow; the last token is the DONE of the batch.
+                if let Some(tail) = data.len().checked_sub(13).map(|start| &data[start..])
+                    && matches!(tail[0], TOKEN_DONE | TOKEN_DONE_PROC | TOKEN_DONE_IN_PROC)
+                    && let Some(rows) = done_rows(tail)
+                {
+                    *result.rows.get_or_insert(0) += rows;
+                }
+                break;
+            }
+        }
+    }
+    result
+}
+
+/// Formats an ERROR token like SQL Server tools do.
+fn error_token(body: &[u8]) -> Option<String> {
+    let number = le32(body, 0)?;
+    let (state, class) = (*body.get(4)?, *body.get(5)?);
+    let length = usize::from(le16(body, 6)?) * 2;
+    let message = utf16le(body.get(8..8 + length)?);
+    Some(format!(
+        "Msg {}, Level {}, State {}: {}",
+        number, class, state, message
+    ))
+}
+
+/// Reads the program name and version from a LOGINACK token.
+fn login_ack_version(body: &[u8]) -> Option<String> {
+    // Interface and TDS version precede the program name.
+    let length = usize::from(*body.get(5)?) * 2;
+    let name = utf16le(body.get(6..6 + length)?);
+    let version = body.get(6 + length..10 + length)?;
+    Some(format!(
+        "{} {}.{}.{}",
+        name.trim_end_matches('\0'),
+        version[0],
+        version[1],
+        u16::from_be_bytes([version[2], version[3]])
+    ))
+}
+
+/// Parses a TDS (SQL Server) connection: pre-login, the LOGIN7 request
+/// unless it was encrypted, and each SQL batch or RPC with the error and
+/// row count of its tabular result. Returns `None` unless the client opens
+/// with a PRELOGIN or LOGIN7 message.
+pub fn parse(client: &[u8], server: &[u8]) -> Option<ParsedSession> {
+    let client_messages = messages(client);
+    match client_messages.first()? {
+        Message::Tds { packet_type, .. } if *packet_type == PRELOGIN || *packet_type == LOGIN7 => {}
+        _ => return None,
+    }
+    let mut session = ParsedSession::default();
+    let mut login_sent = false;
+    // Every request but the TLS handshake, which is wrapped in PRELOGIN
+    // packets, is answered by one tabular result.
+    let mut responses = messages(server)
+        .into_iter()
+        .filter_map(|message| match message {
+            Message::Tds {
+                offset,
+                packet_type: TABULAR_RESULT,
+                data,
+            } => Some((offset, data)),
+            _ => None,
+        });
+    for message in &client_messages {
+        let (offset, statement) = match message {
+            Message::Tls { offset } => {
+                session.encrypted = true;
+                // Application data here is an encrypted LOGIN7 when only
+                // the login is protected; after that the session is plain.
+                if client.get(*offset) != Some(&0x17) || login_sent {
+                    continue;
+                }
+                login_sent = true;
+                (*offset, None)
+            }
+            Message::Tds {
+                offset,
+                packet_type,
+                data,
+            } => match *packet_type {
+                PRELOGIN if data.first() == Some(&0x16) => continue,
+                PRELOGIN => {
+                    responses.next();
+                    continue;
+                }
+                LOGIN7 => {
+                    login_sent = true;
+                    session.user = login_field(data, 40);
+                    session.database = login_field(data, 68);
+                    (*offset, None)
+                }
+                SQL_BATCH => (*offset, Some(utf16le(strip_headers(data)))),
+                RPC => (*offset, rpc_statement(data)),
+                ATTENTION => (*offset, Some("ATTENTION".to_string())),
+                _ => continue,
+            },
+        };
+        let answer = responses.next();
+        let result = answer
+            .as_ref()
+            .map(|(_, data)| response(data))
+            .unwrap_or_default();
+        let Some(statement) = statement else {
+            // The login request.
+            session.server_version = result.server_version.or(session.server_version);
+            if !result.logged_in && result.error.is_some() {
+                session.login_error = result.error;
+            }
+            continue;
+        };
+        session.queries.push(ParsedQuery {
+            statement,
+            client_offset: offset,
+            server_offset: answer.map(|(offset, _)| offset),
+            error: result.error,
+            rows: result.rows,
+        });
+    }
+    Some(session)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(packet_type: u8, payload: &[u8]) -> Vec<u8> {
+        let mut packet = vec![packet_type, 0x01];
+        packet.extend_from_slice(&(payload.len() as u16 + 8).to_be_bytes());
+        packet.extend_from_slice(&[0, 0, 1, 0]);
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    fn utf16(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    fn done(status: u16, rows: u64) -> Vec<u8> {
+        let mut token = vec![TOKEN_DONE];
+        token.extend_from_slice(&status.to_le_bytes());
+        token.extend_from_slice(&[0xc1, 0]);
+        token.extend_from_slice(&rows.to_le_bytes());
+        token
+    }
+
+    fn error(number: u32, message: &str) -> Vec<u8> {
+        let mut body = number.to_le_bytes().to_vec();
+        body.extend_from_slice(&[1, 16]);
+        body.extend_from_slice(&(message.len() as u16).to_le_bytes());
+        body.extend(utf16(message));
+        body.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0]);
+        let mut token = vec![TOKEN_ERROR];
+        token.extend_from_slice(&(body.len() as u16).to_le_bytes());
+        token.extend(body);
+        token
+    }
+
+    fn login7(user: &str, database: &str) -> Vec<u8> {
+        let mut login = vec![0; 94];
+        let mut strings = utf16(user);
+        strings.extend(utf16(database));
+        login[40..42].copy_from_slice(&94u16.to_le_bytes());
+        login[42..44].copy_from_slice(&(user.len() as u16).to_le_bytes());
+        login[68..70].copy_from_slice(&(94 + user.len() as u16 * 2).to_le_bytes());
+        login[70..72].copy_from_slice(&(database.len() as u16).to_le_bytes());
+        login.extend(strings);
+        login
+    }
+
+    #[test]
+    fn test_tds_session() {
+        let mut client = packet(PRELOGIN, &[0xff]);
+        client.extend(packet(LOGIN7, &login7("app", "shop")));
+        let batch = client.len();
+        let mut headers = 22u32.to_le_bytes().to_vec();
+        headers.extend_from_slice(&18u32.to_le_bytes());
+        headers.extend_from_slice(&[2, 0]);
+        headers.extend_from_slice(&[0; 12]);
+        let mut payload = headers.clone();
+        payload.extend(utf16("SELECT * FROM orders"));
+        client.extend(packet(SQL_BATCH, &payload));
+
+        let mut rpc = headers.clone();
+        rpc.extend_from_slice(&[0xff, 0xff, 10, 0, 0, 0]);
+        rpc.extend_from_slice(&[0, 0, 0xe7, 0x40, 0x1f, 0, 0, 0, 0, 0]);
+        let statement = utf16("SELECT * FROM missing");
+        rpc.extend_from_slice(&(statement.len() as u16).to_le_bytes());
+        rpc.extend(statement);
+        client.extend(packet(RPC, &rpc));
+
+        let mut server = packet(TABULAR_RESULT, &[0xff]);
+        let mut ack = vec![TOKEN_LOGIN_ACK];
+        let mut body = vec![1, 0x74, 0, 0, 4, 3];
+        body.extend(utf16("SQL"));
+        body.extend_from_slice(&[16, 0, 0x10, 0x03]);
+        ack.extend_from_slice(&(body.len() as u16).to_le_bytes());
+        ack.extend(body);
+        ack.extend(done(0, 0));
+        server.extend(packet(TABULAR_RESULT, &ack));
+        let result = server.len();
+        let mut rows = vec![0x81, 1, 0];
+        rows.extend_from_slice(&[0; 9]);
+        rows.extend(done(DONE_COUNT, 12));
+        server.extend(packet(TABULAR_RESULT, &rows));
+        let mut failure = error(208, "Invalid object name 'missing'.");
+        failure.extend(done(0x0002, 0));
+        server.extend(packet(TABULAR_RESULT, &failure));
+
+        let session = parse(&client, &server).unwrap();
+        assert_eq!(session.user.as_deref(), Some("app"));
+        assert_eq!(session.database.as_deref(), Some("shop"));
+        assert_eq!(session.server_version.as_deref(), Some("SQL 16.0.4099"));
+        assert_eq!(session.login_error, None);
+        assert_eq!(session.queries.len(), 2);
+        assert_eq!(session.queries[0].statement, "SELECT * FROM orders");
+        assert_eq!(session.queries[0].client_offset, batch);
+        assert_eq!(session.queries[0].server_offset, Some(result));
+        assert_eq!(session.queries[0].rows, Some(12));
+        assert_eq!(session.queries[1].statement, "SELECT * FROM missing");
+        assert_eq!(
+            session.queries[1].error.as_deref(),
+            Some("Msg 208, Level 16, State 1: Invalid object name 'missing'.")
+        );
+
+        // Failed login.
+        let mut client = packet(PRELOGIN, &[0xff]);
+        client.extend(packet(LOGIN7, &login7("app", "shop")));
+        let mut server = packet(TABULAR_RESULT, &[0xff]);
+        server.extend(packet(
+            TABULAR_RESULT,
+            &error(18456, "Login failed for user 'app'."),
+        ));
+        let session = parse(&client, &server).unwrap();
+        assert_eq!(
+            session.login_error.as_deref(),
+            Some("Msg 18456, Level 16, State 1: Login failed for user 'app'.")
+        );
+
+        assert!(parse(b"GET / HTTP/1.1\r\n\r\n", b"").is_none());
+    }
+}
@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+use std::io;
+use std::sync::Arc;
+
+use rusqlite::{Connection, params};
+
+use crate::dissect::PacketLayers;
+use crate::dns::{self, DnsRecordData};
+use crate::error::KcpdumpError;
+use crate::expert::ExpertAnalyzer;
+use crate::flows::{self, StreamTable};
+use crate::http;
+use crate::packetlist;
+use crate::reassembly::StreamReassembler;
+use crate::session::{CaptureId, LoadedCapture};
+use crate::summary;
+
+/// Tables of an exported database. Times are seconds since the epoch,
+/// `number` is the frame number within the capture and `stream` the
+/// conversation index of `tcp.stream`, so rows join across tables on
+/// `(capture_id, number)` or `(capture_id, stream)`.
+pub const SCHEMA: &[(&str, &str)] = &[
+    (
+        "packets",
+        "CREATE TABLE packets (capture_id INTEGER, number INTEGER, time REAL, \
+         length INTEGER, protocol TEXT, source TEXT, destination TEXT, info TEXT, \
+         stream INTEGER)",
+    ),
+    (
+        "flows",
+        "CREATE TABLE flows (capture_id INTEGER, stream INTEGER, protocol INTEGER, \
+         address_a TEXT, port_a INTEGER, address_b TEXT, port_b INTEGER, \
+         packets INTEGER, bytes INTEGER, first_seen REAL, last_seen REAL)",
+    ),
+    (
+        "dns",
+        "CREATE TABLE dns (capture_id INTEGER, number INTEGER, time REAL, \
+         transaction_id INTEGER, response INTEGER, rcode INTEGER, name TEXT, \
+         type TEXT, answers TEXT)",
+    ),
+    (
+        "http",
+        "CREATE TABLE http (capture_id INTEGER, stream INTEGER, number INTEGER, \
+         method TEXT, host TEXT, uri TEXT, status INTEGER, content_type TEXT, \
+         request_length INTEGER, response_length INTEGER)",
+    ),
+    (
+        "alerts",
+        "CREATE TABLE alerts (capture_id INTEGER, number INTEGER, severity TEXT, \
+         expert_group TEXT, protocol TEXT, message TEXT)",
+    ),
+];
+
+/// Writes packets, flows, DNS and HTTP transactions and expert alerts of the
+/// captures to a new SQLite database at `path` laid out as in `SCHEMA`,
+/// replacing any file there. Rows are inserted as packets are read, within a
+/// single transaction.
+pub fn export_database(captures: &[Arc<LoadedCapture>], path: &str) -> Result<(), KcpdumpError> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(KcpdumpError::file(path, e)),
+        _ => {}
+    }
+    let mut connection = Connection::open(path)?;
+    let transaction = connection.transaction()?;
+    for (_, sql) in SCHEMA {
+        transaction.execute(sql, [])?;
+    }
+    write_rows(&transaction, captures)?;
+    transaction.commit()?;
+    Ok(())
+}
+
+fn write_rows(
+    connection: &Connection,
+    captures: &[Arc<LoadedCapture>],
+) -> Result<(), KcpdumpError> {
+    let mut insert_packet =
+        connection.prepare("INSERT INTO packets VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)")?;
+    let mut insert_dns =
+        connection.prepare("INSERT INTO dns VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)")?;
+    let mut insert_alert = connection.prepare("INSERT INTO alerts VALUES (?, ?, ?, ?, ?, ?)")?;
+    let mut insert_flow =
+        connection.prepare("INSERT INTO flows VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)")?;
+    let mut insert_http =
+        connection.prepare("INSERT INTO http VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)")?;
+
+    let link_types = packetlist::link_types(captures);
+    let mut streams: HashMap<CaptureId, StreamTable> = HashMap::new();
+    let mut analyzers: HashMap<CaptureId, ExpertAnalyzer> = HashMap::new();
+
+    for item in packetlist::merged_packets(captures) {
+        let (capture_id, number, packet) = item?;
+        let time = packet.header.timestamp.as_secs_f64();
+        let summary = summary::summarize_link(link_types[&capture_id], &packet.data);
+        let stream = summary
+            .flow
+            .map(|key| streams.entry(capture_id).or_default().id(key));
+        insert_packet.execute(params![
+            capture_id,
+            number,
+            time,
+            packet.header.orig_len,
+            summary.protocol,
+            summary.source,
+            summary.destination,
+            summary.info,
+            stream,
+        ])?;
+
+        let layers = PacketLayers::decode_link(number, link_types[&capture_id], &packet);
+        if let Some(message) = &layers.dns {
+            let question = message.questions.first();
+            let answers: Vec<String> = message
+                .answers
+                .iter()
+                .filter_map(|answer| match &answer.data {
+                    DnsRecordData::A(address) => Some(address.to_string()),
+                    DnsRecordData::Aaaa(address) => Some(address.to_string()),
+                    DnsRecordData::Name(name) => Some(name.clone()),
+                    DnsRecordData::Other(_) => None,
+                })
+                .collect();
+            insert_dns.execute(params![
+                capture_id,
+                number,
+                time,
+                message.id,
+                message.is_response(),
+                message.rcode(),
+                question.map(|question| question.name.as_str()),
+                question.map(|question| dns::record_type_name(question.record_type)),
+                answers.join(","),
+            ])?;
+        }
+        for item in analyzers.entry(capture_id).or_default().analyze(&layers) {
+            insert_alert.execute(params![
+                capture_id,
+                item.number,
+                format!("{:?}", item.severity),
+                format!("{:?}", item.group),
+                item.protocol,
+                item.message,
+            ])?;
+        }
+    }
+
+    for flow in flows::flow_records(captures)? {
+        insert_flow.execute(params![
+            flow.capture_id,
+            flow.stream,
+            flow.key.protocol,
+            flow.key.address_a.to_string(),
+            flow.key.port_a,
+            flow.key.address_b.to_string(),
+            flow.key.port_b,
+            flow.packets,
+            flow.bytes,
+            flow.first_seen,
+            flow.last_seen,
+        ])?;
+    }
+
+    for capture in captures {
+        let mut reassembler = StreamReassembler::default();
+        for (index, packet) in capture.packets().enumerate() {
+            let packet = packet?;
+            reassembler.push(&PacketLayers::decode_link(index + 1, capture.header.network, &packet));
+        }
+        let table = streams.entry(capture.id).or_default();
+        for stream in reassembler.finish() {
+            let requests = http::parse_messages(&stream.client_data.data, false);
+            let mut responses = http::parse_messages(&stream.server_data.data, true)
+                .into_iter()
+                .filter(|response| !matches!(response.status(), Some(100..=199)));
+            for request in requests {
+                let response = responses.next();
+                insert_http.execute(params![
+                    capture.id,
+                    table.id(stream.key),
+                    stream.first_number,
+                    request.start_line.split_whitespace().next(),
+                    request.header("Host"),
+                    request.uri(),
+                    response.as_ref().and_then(|r| r.status()),
+                    response.as_ref().and_then(|r| r.header("Content-Type")),
+                    request.body.len(),
+                    response.as_ref().map(|r| r.body.len()),
+                ])?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cap::{PcapPacket, PcapPacketHeader};
+    use crate::link::LINKTYPE_ETHERNET;
+    use crate::timefmt::Timestamp;
+
+    fn temp_path(name: &str) -> String {
+        let name = format!("kcpdump-{}-{}.db", name, std::process::id());
+        std::env::temp_dir()
+            .join(name)
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn count(connection: &Connection, table: &str) -> usize {
+        connection
+            .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+                row.get(0)
+            })
+            .unwrap()
+    }
+
+    /// A UDP datagram from 10.0.0.1:1000 to 10.0.0.2:2000.
+    fn udp(seconds: u32) -> PcapPacket {
+        let mut data = vec![0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 1, 0x08, 0x00];
+        data.extend_from_slice(&[0x45, 0, 0, 28, 0, 0, 0, 0, 64, 17, 0, 0]);
+        data.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2]);
+        data.extend_from_slice(&[0x03, 0xe8, 0x07, 0xd0, 0, 8, 0, 0]);
+        PcapPacket {
+            header: PcapPacketHeader {
+                timestamp: Timestamp::new(seconds, 0),
+                incl_len: data.len() as u32,
+                orig_len: data.len() as u32,
+                link_type: None,
+            },
+            data,
+        }
+    }
+
+    #[test]
+    fn test_export_packets_and_flows() {
+        let packets = [udp(1), udp(2), udp(3)];
+        let capture = LoadedCapture::from_packets(1, "udp.pcap", LINKTYPE_ETHERNET, &packets);
+        let path = temp_path("udp");
+        export_database(&[Arc::new(capture)], &path).unwrap();
+        let connection = Connection::open(&path).unwrap();
+        assert_eq!(count(&connection, "packets"), 3);
+        let (packets, first_seen): (usize, f64) = connection
+            .query_row("SELECT packets, first_seen FROM flows", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!((packets, first_seen), (3, 1.0));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_export_database() {
+        let capture = Arc::new(LoadedCapture::load(1, "sample.pcap").await.unwrap());
+        let path = temp_path("export");
+        export_database(std::slice::from_ref(&capture), &path).unwrap();
+        // A second export replaces the database instead of adding to it.
+        export_database(std::slice::from_ref(&capture), &path).unwrap();
+        let connection = Connection::open(&path).unwrap();
+        assert_eq!(count(&connection, "packets"), capture.packet_count());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
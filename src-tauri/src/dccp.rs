@@ -0,0 +1,273 @@
+use crate::dissect::{Dissector, FieldInfo, FieldType, FieldValue, FieldValues, PacketLayers};
+use crate::error::KcpdumpError;
+
+/// Packet Type
+/// The type field of a DCCP generic header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketType {
+    Request,
+    Response,
+    Data,
+    Ack,
+    DataAck,
+    CloseReq,
+    Close,
+    Reset,
+    Sync,
+    SyncAck,
+    Unknown(u8),
+}
+
+impl From<u8> for PacketType {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => PacketType::Request,
+            1 => PacketType::Response,
+            2 => PacketType::Data,
+            3 => PacketType::Ack,
+            4 => PacketType::DataAck,
+            5 => PacketType::CloseReq,
+            6 => PacketType::Close,
+            7 => PacketType::Reset,
+            8 => PacketType::Sync,
+            9 => PacketType::SyncAck,
+            other => PacketType::Unknown(other),
+        }
+    }
+}
+
+impl From<PacketType> for u8 {
+    fn from(packet_type: PacketType) -> Self {
+        match packet_type {
+            PacketType::Request => 0,
+            PacketType::Response => 1,
+            PacketType::Data => 2,
+            PacketType::Ack => 3,
+            PacketType::DataAck => 4,
+            PacketType::CloseReq => 5,
+            PacketType::Close => 6,
+            PacketType::Reset => 7,
+            PacketType::Sync => 8,
+            PacketType::SyncAck => 9,
+            PacketType::Unknown(value) => value,
+        }
+    }
+}
+
+/// DCCP Packet
+/// Represents a DCCP header and its payload.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DccpPacket {
+    pub source_port: u16,
+    pub dest_port: u16,
+    /// Offset of the payload in 32-bit words.
+    pub data_offset: u8,
+    pub ccval: u8,
+    pub checksum_coverage: u8,
+    pub checksum: u16,
+    pub packet_type: PacketType,
+    /// 48-bit sequence number, or 24 bits when extended numbers are off.
+    pub sequence: u64,
+    pub ack: Option<u64>,
+    /// Service code of Request and Response packets.
+    pub service_code: Option<u32>,
+    /// Reset code of Reset packets.
+    pub reset_code: Option<u8>,
+    pub payload: Vec<u8>,
+}
+
+impl TryFrom<&[u8]> for DccpPacket {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 12 {
+            return Err(KcpdumpError::malformed(0, "Data too short for DCCP packet"));
+        }
+        let data_offset = data[4];
+        let header_len = data_offset as usize * 4;
+        if header_len < 12 || header_len > data.len() {
+            return Err(KcpdumpError::malformed(4, "Invalid DCCP data offset"));
+        }
+        let header = &data[..header_len];
+        let packet_type = PacketType::from((data[8] >> 1) & 0x0f);
+        let extended = data[8] & 0x01 != 0;
+
+        let number = |bytes: &[u8]| bytes.iter().fold(0u64, |n, &b| n << 8 | u64::from(b));
+        let (sequence, mut offset) = if extended {
+            (
+                number(
+                    header
+                        .get(10..16)
+                        .ok_or(KcpdumpError::malformed(10, "DCCP header truncated"))?,
+                ),
+                16,
+            )
+        } else {
+            (number(&header[9..12]), 12)
+        };
+        let ack = if matches!(packet_type, PacketType::Request | PacketType::Data) {
+            None
+        } else if extended {
+            let ack = number(
+                header
+                    .get(offset + 2..offset + 8)
+                    .ok_or(KcpdumpError::malformed(offset + 2, "DCCP header truncated"))?,
+            );
+            offset += 8;
+            Some(ack)
+        } else {
+            let ack = number(
+                header
+                    .get(offset + 1..offset + 4)
+                    .ok_or(KcpdumpError::malformed(offset + 1, "DCCP header truncated"))?,
+            );
+            offset += 4;
+            Some(ack)
+        };
+        let mut service_code = None;
+        let mut reset_code = None;
+        match packet_type {
+            PacketType::Request | PacketType::Response => {
+                let bytes = header
+                    .get(offset..offset + 4)
+                    .ok_or(KcpdumpError::malformed(offset, "DCCP header truncated"))?;
+                service_code = Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+            }
+            PacketType::Reset => {
+                reset_code = Some(
+                    *header
+                        .get(offset)
+                        .ok_or(KcpdumpError::malformed(offset, "DCCP header truncated"))?,
+                );
+            }
+            _ => {}
+        }
+        Ok(DccpPacket {
+            source_port: u16::from_be_bytes([data[0], data[1]]),
+            dest_port: u16::from_be_bytes([data[2], data[3]]),
+            data_offset,
+            ccval: data[5] >> 4,
+            checksum_coverage: data[5] & 0x0f,
+            checksum: u16::from_be_bytes([data[6], data[7]]),
+            packet_type,
+            sequence,
+            ack,
+            service_code,
+            reset_code,
+            payload: data[header_len..].to_vec(),
+        })
+    }
+}
+
+impl DccpPacket {
+    /// Describes the packet for the packet list, e.g. `5000 → 80 [Request] Seq=1 Service=42`.
+    pub fn describe(&self) -> String {
+        let mut info = format!(
+            "{} → {} [{:?}] Seq={}",
+            self.source_port, self.dest_port, self.packet_type, self.sequence
+        );
+        if let Some(ack) = self.ack {
+            info += &format!(" Ack={}", ack);
+        }
+        if let Some(service_code) = self.service_code {
+            info += &format!(" Service={}", service_code);
+        }
+        if let Some(reset_code) = self.reset_code {
+            info += &format!(" Reset={}", reset_code);
+        }
+        info
+    }
+}
+
+/// DCCP Dissector
+pub struct DccpDissector;
+
+const DCCP_FIELDS: &[FieldInfo] = &[
+    FieldInfo::new("dccp.srcport", FieldType::UInt, "Source port"),
+    FieldInfo::new("dccp.dstport", FieldType::UInt, "Destination port"),
+    FieldInfo::new("dccp.port", FieldType::UInt, "Source or destination port"),
+    FieldInfo::new("dccp.type", FieldType::UInt, "Packet type"),
+    FieldInfo::new("dccp.seq", FieldType::UInt, "Sequence number"),
+    FieldInfo::new("dccp.ack", FieldType::UInt, "Acknowledgement number"),
+    FieldInfo::new("dccp.service_code", FieldType::UInt, "Service code"),
+    FieldInfo::new("dccp.reset_code", FieldType::UInt, "Reset code"),
+    FieldInfo::new("dccp.cscov", FieldType::UInt, "Checksum coverage"),
+];
+
+impl Dissector for DccpDissector {
+    fn protocol(&self) -> &'static str {
+        "dccp"
+    }
+
+    fn description(&self) -> &'static str {
+        "Datagram Congestion Control Protocol"
+    }
+
+    fn fields(&self) -> &'static [FieldInfo] {
+        DCCP_FIELDS
+    }
+
+    fn dissect(&self, layers: &PacketLayers, values: &mut FieldValues) {
+        let Some(dccp) = &layers.dccp else {
+            return;
+        };
+        values.push("dccp", FieldValue::Protocol);
+        values.push("dccp.srcport", FieldValue::UInt(dccp.source_port.into()));
+        values.push("dccp.dstport", FieldValue::UInt(dccp.dest_port.into()));
+        values.push("dccp.port", FieldValue::UInt(dccp.source_port.into()));
+        values.push("dccp.port", FieldValue::UInt(dccp.dest_port.into()));
+        values.push(
+            "dccp.type",
+            FieldValue::UInt(u8::from(dccp.packet_type).into()),
+        );
+        values.push("dccp.seq", FieldValue::UInt(dccp.sequence));
+        if let Some(ack) = dccp.ack {
+            values.push("dccp.ack", FieldValue::UInt(ack));
+        }
+        if let Some(service_code) = dccp.service_code {
+            values.push("dccp.service_code", FieldValue::UInt(service_code.into()));
+        }
+        if let Some(reset_code) = dccp.reset_code {
+            values.push("dccp.reset_code", FieldValue::UInt(reset_code.into()));
+        }
+        values.push(
+            "dccp.cscov",
+            FieldValue::UInt(dccp.checksum_coverage.into()),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request() {
+        // Request with extended sequence number 1 and service code 42.
+        let data = [
+            0x13, 0x88, 0x00, 0x50, 0x05, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x01, 0x00, 0x00, 0x00, 0x2a,
+        ];
+        let packet = DccpPacket::try_from(&data[..]).unwrap();
+        assert_eq!(packet.packet_type, PacketType::Request);
+        assert_eq!(packet.sequence, 1);
+        assert_eq!(packet.ack, None);
+        assert_eq!(packet.service_code, Some(42));
+        assert_eq!(packet.describe(), "5000 → 80 [Request] Seq=1 Service=42");
+    }
+
+    #[test]
+    fn test_data_ack() {
+        // DataAck with short sequence numbers and a two byte payload.
+        let data = [
+            0x00, 0x50, 0x13, 0x88, 0x04, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x10, 0x00, 0x00,
+            0x00, 0x07, 0xca, 0xfe,
+        ];
+        let packet = DccpPacket::try_from(&data[..]).unwrap();
+        assert_eq!(packet.packet_type, PacketType::DataAck);
+        assert_eq!(packet.sequence, 16);
+        assert_eq!(packet.ack, Some(7));
+        assert_eq!(packet.payload, [0xca, 0xfe]);
+        assert!(DccpPacket::try_from(&data[..8]).is_err());
+    }
+}
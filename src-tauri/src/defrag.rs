@@ -0,0 +1,295 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::cap::{PcapPacket, PcapPacketHeader};
+use crate::packet::{EtherType, EthernetPacket, IPv4Packet};
+
+/// More Fragments bit of the IPv4 flags.
+const MORE_FRAGMENTS: u8 = 0x1;
+
+/// Largest datagram an IPv4 total length can describe.
+const MAX_DATAGRAM_SIZE: usize = 65535;
+
+/// Defrag Policy
+/// Bounds the state kept for datagrams whose fragments are still missing.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DefragPolicy {
+    /// Seconds after its first fragment that an incomplete datagram is dropped.
+    pub timeout: u32,
+    /// Incomplete datagrams kept at once; the oldest is dropped to make room.
+    pub max_pending: usize,
+}
+
+impl Default for DefragPolicy {
+    fn default() -> Self {
+        DefragPolicy {
+            timeout: 30,
+            max_pending: 1024,
+        }
+    }
+}
+
+/// Source, destination, protocol and identification of a datagram.
+type FragmentKey = ([u8; 4], [u8; 4], u8, u16);
+
+struct PendingDatagram {
+    first_seen: u32,
+    /// Arrival order, used to drop the oldest datagram when full.
+    sequence: u64,
+    /// Header of the fragment at offset 0, options included.
+    header: Option<Vec<u8>>,
+    /// Payload length, known once the last fragment arrived.
+    length: Option<usize>,
+    /// Fragment payloads by byte offset; the first copy of a range wins.
+    fragments: BTreeMap<usize, Vec<u8>>,
+}
+
+impl PendingDatagram {
+    /// The payload, if every byte up to the last fragment has arrived.
+    fn assemble(&self) -> Option<Vec<u8>> {
+        let length = self.length?;
+        self.header.as_ref()?;
+        let mut payload = Vec::with_capacity(length);
+        for (&offset, data) in &self.fragments {
+            if offset > payload.len() {
+                return None;
+            }
+            let end = (offset + data.len()).min(length);
+            if end > payload.len() {
+                payload.extend_from_slice(&data[payload.len() - offset..end - offset]);
+            }
+        }
+        (payload.len() == length).then_some(payload)
+    }
+}
+
+/// IPv4 Defragmenter
+/// Reassembles fragmented IPv4 datagrams keyed on source, destination,
+/// protocol and identification, using the fragment offset and the More
+/// Fragments flag to place and complete them.
+pub struct Ipv4Defragmenter {
+    policy: DefragPolicy,
+    pending: HashMap<FragmentKey, PendingDatagram>,
+    next_sequence: u64,
+    dropped: usize,
+}
+
+impl Ipv4Defragmenter {
+    pub fn new(policy: DefragPolicy) -> Self {
+        Ipv4Defragmenter {
+            policy,
+            pending: HashMap::new(),
+            next_sequence: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Whether a packet is one fragment of a larger datagram.
+    pub fn is_fragment(packet: &IPv4Packet) -> bool {
+        packet.fragment_offset != 0 || packet.flags & MORE_FRAGMENTS != 0
+    }
+
+    /// Incomplete datagrams given up on because of the timeout, the pending
+    /// limit or an invalid size.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Forgets every incomplete datagram, e.g. after seeking elsewhere in a
+    /// capture.
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Adds one fragment, given as the raw IPv4 datagram captured at `ts_sec`.
+    /// Returns the reassembled datagram, header included, once the fragment
+    /// completes it.
+    pub fn push(&mut self, ts_sec: u32, datagram: &[u8]) -> Option<Vec<u8>> {
+        self.expire(ts_sec);
+        let packet = IPv4Packet::try_from(datagram).ok()?;
+        let key = (
+            packet.source_ip,
+            packet.dest_ip,
+            packet.protocol,
+            packet.identification,
+        );
+        let offset = usize::from(packet.fragment_offset) * 8;
+        if offset + packet.payload.len() > MAX_DATAGRAM_SIZE {
+            self.dropped += usize::from(self.pending.remove(&key).is_some());
+            return None;
+        }
+
+        if !self.pending.contains_key(&key) && self.pending.len() >= self.policy.max_pending {
+            let oldest = self
+                .pending
+                .iter()
+                .min_by_key(|(_, datagram)| datagram.sequence)
+                .map(|(key, _)| *key);
+            if let Some(oldest) = oldest {
+                self.pending.remove(&oldest);
+                self.dropped += 1;
+            }
+        }
+        let sequence = self.next_sequence;
+        let pending = self.pending.entry(key).or_insert_with(|| PendingDatagram {
+            first_seen: ts_sec,
+            sequence,
+            header: None,
+            length: None,
+            fragments: BTreeMap::new(),
+        });
+        self.next_sequence += 1;
+        if offset == 0 {
+            pending
+                .header
+                .get_or_insert_with(|| datagram[..usize::from(packet.ihl) * 4].to_vec());
+        }
+        if packet.flags & MORE_FRAGMENTS == 0 {
+            pending.length.get_or_insert(offset + packet.payload.len());
+        }
+        pending.fragments.entry(offset).or_insert(packet.payload);
+
+        let payload = pending.assemble()?;
+        let mut datagram = self.pending.remove(&key)?.header?;
+        let total_length = (datagram.len() + payload.len()).min(MAX_DATAGRAM_SIZE) as u16;
+        datagram[2..4].copy_from_slice(&total_length.to_be_bytes());
+        // Clear More Fragments and the offset, keeping Don't Fragment.
+        datagram[6] &= 0x40;
+        datagram[7] = 0;
+        datagram[10..12].copy_from_slice(&[0, 0]);
+        let checksum = header_checksum(&datagram);
+        datagram[10..12].copy_from_slice(&checksum.to_be_bytes());
+        datagram.extend_from_slice(&payload);
+        Some(datagram)
+    }
+
+    /// Handles one Ethernet frame. Frames that are not IPv4 fragments are
+    /// returned unchanged and fragments are held back, except the one that
+    /// completes a datagram: it is returned as a frame carrying the whole
+    /// datagram, with its own timestamp.
+    pub fn push_frame(&mut self, packet: PcapPacket) -> Option<PcapPacket> {
+        let header_size = EthernetPacket::try_from(packet.data.as_slice())
+            .ok()
+            .filter(|eth_packet| eth_packet.header.ether_type == EtherType::IPv4)
+            .filter(|eth_packet| {
+                IPv4Packet::try_from(eth_packet.data.as_slice())
+                    .is_ok_and(|ipv4_packet| Self::is_fragment(&ipv4_packet))
+            })
+            .map(|eth_packet| eth_packet.header.size());
+        let Some(header_size) = header_size else {
+            return Some(packet);
+        };
+        let datagram = self.push(packet.header.timestamp.sec, &packet.data[header_size..])?;
+        let mut data = packet.data[..header_size].to_vec();
+        data.extend_from_slice(&datagram);
+        Some(PcapPacket {
+            header: PcapPacketHeader {
+                timestamp: packet.header.timestamp,
+                incl_len: data.len() as u32,
+                orig_len: data.len() as u32,
+                link_type: None,
+            },
+            data,
+        })
+    }
+
+    fn expire(&mut self, ts_sec: u32) {
+        let timeout = self.policy.timeout;
+        let before = self.pending.len();
+        self.pending
+            .retain(|_, datagram| ts_sec.saturating_sub(datagram.first_seen) <= timeout);
+        self.dropped += before - self.pending.len();
+    }
+}
+
+fn header_checksum(header: &[u8]) -> u16 {
+    let mut sum: u32 = header
+        .chunks(2)
+        .map(|chunk| u32::from(u16::from_be_bytes([chunk[0], *chunk.get(1).unwrap_or(&0)])))
+        .sum();
+    while (sum >> 16) != 0 {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timefmt::Timestamp;
+
+    /// An IPv4 fragment of a UDP datagram from 10.0.0.1 to 10.0.0.2.
+    fn fragment(identification: u16, offset: usize, more: bool, payload: &[u8]) -> Vec<u8> {
+        let total_length = (20 + payload.len()) as u16;
+        let flags_offset = (offset / 8) as u16 | if more { 0x2000 } else { 0 };
+        let mut data = vec![0x45, 0];
+        data.extend_from_slice(&total_length.to_be_bytes());
+        data.extend_from_slice(&identification.to_be_bytes());
+        data.extend_from_slice(&flags_offset.to_be_bytes());
+        data.extend_from_slice(&[64, 17, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2]);
+        data.extend_from_slice(payload);
+        data
+    }
+
+    #[test]
+    fn test_out_of_order_fragments() {
+        let mut defrag = Ipv4Defragmenter::new(DefragPolicy::default());
+        let payload: Vec<u8> = (0..40).collect();
+        assert_eq!(defrag.push(1, &fragment(7, 24, false, &payload[24..])), None);
+        assert_eq!(defrag.push(1, &fragment(7, 0, true, &payload[..16])), None);
+        // A retransmitted copy of the first fragment changes nothing.
+        assert_eq!(defrag.push(1, &fragment(7, 0, true, &payload[..16])), None);
+        let datagram = defrag.push(2, &fragment(7, 16, true, &payload[16..24])).unwrap();
+
+        let packet = IPv4Packet::try_from(datagram.as_slice()).unwrap();
+        assert_eq!(packet.total_length, 60);
+        assert_eq!(packet.payload, payload);
+        assert!(!Ipv4Defragmenter::is_fragment(&packet));
+        assert!(packet.validate_checksum());
+        assert_eq!(defrag.dropped(), 0);
+    }
+
+    #[test]
+    fn test_timeout_and_limit() {
+        let policy = DefragPolicy {
+            timeout: 5,
+            max_pending: 1,
+        };
+        let mut defrag = Ipv4Defragmenter::new(policy);
+        defrag.push(0, &fragment(1, 0, true, &[0; 8]));
+        assert_eq!(defrag.push(10, &fragment(1, 8, false, &[0; 8])), None);
+        assert_eq!(defrag.dropped(), 1);
+
+        // The pending limit makes room for a new datagram by dropping the oldest.
+        defrag.push(11, &fragment(2, 0, true, &[0; 8]));
+        assert_eq!(defrag.dropped(), 2);
+        assert!(defrag.push(12, &fragment(2, 8, false, &[0; 8])).is_some());
+    }
+
+    #[test]
+    fn test_frames_pass_through() {
+        let mut defrag = Ipv4Defragmenter::new(DefragPolicy::default());
+        let frame = |datagram: Vec<u8>| {
+            let mut data = vec![0; 12];
+            data.extend_from_slice(&[0x08, 0x00]);
+            data.extend_from_slice(&datagram);
+            PcapPacket {
+                header: PcapPacketHeader {
+                    timestamp: Timestamp::from_micros(0, 0),
+                    incl_len: data.len() as u32,
+                    orig_len: data.len() as u32,
+                    link_type: None,
+                },
+                data,
+            }
+        };
+        let whole = frame(fragment(3, 0, false, &[1; 8]));
+        assert_eq!(defrag.push_frame(whole.clone()).unwrap().data, whole.data);
+        assert!(defrag.push_frame(frame(fragment(4, 0, true, &[1; 8]))).is_none());
+        let packet = defrag
+            .push_frame(frame(fragment(4, 8, false, &[2; 8])))
+            .unwrap();
+        assert_eq!(packet.data.len(), 14 + 36);
+        assert_eq!(packet.header.incl_len, 50);
+    }
+}
@@ -0,0 +1,425 @@
+use std::collections::HashMap;
+
+use crate::dissect::{DissectorRegistry, FieldValue, PacketLayers};
+use crate::link::LinkHeader;
+
+/// Detail Node
+/// A protocol or field of the packet details pane with the bytes of the frame
+/// it was decoded from, so the hex view can highlight them. Values derived
+/// from the capture rather than the frame, like `frame.len`, have length 0.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DetailNode {
+    /// Filter name of the protocol or field, e.g. `ip` or `ip.src`.
+    pub name: String,
+    pub label: String,
+    /// Display text of the value; empty for protocols.
+    pub value: String,
+    pub offset: usize,
+    pub length: usize,
+    /// Fields of a protocol, or the subfields of a field such as the bits of
+    /// `tcp.flags`.
+    pub children: Vec<DetailNode>,
+}
+
+impl DetailNode {
+    fn new(name: &str, label: &str, value: String, (offset, length): (usize, usize)) -> Self {
+        DetailNode {
+            name: name.to_string(),
+            label: label.to_string(),
+            value,
+            offset,
+            length,
+            children: Vec::new(),
+        }
+    }
+
+    /// Adds a field, nesting it under the previous field when its name
+    /// extends that field's name.
+    fn push_field(&mut self, field: DetailNode) {
+        let mut siblings = &mut self.children;
+        while siblings
+            .last()
+            .is_some_and(|last| field.name.starts_with(&format!("{}.", last.name)))
+        {
+            siblings = &mut siblings.last_mut().unwrap().children;
+        }
+        siblings.push(field);
+    }
+}
+
+/// Start of each decoded layer within the frame.
+struct LayerOffsets {
+    link: usize,
+    network: Option<usize>,
+    transport: Option<usize>,
+    application: Option<usize>,
+}
+
+impl LayerOffsets {
+    fn new(layers: &PacketLayers) -> Self {
+        let data = &layers.packet.data;
+        let (link, network) = if let Some(eth) = &layers.ethernet {
+            let header_len = 14 + 4 * eth.header.vlan_tags.len();
+            // Tunneled frames are decoded in place of the outer packet and
+            // run to its end.
+            let start = data.len().saturating_sub(header_len + eth.data.len());
+            (start, Some(start + header_len))
+        } else if let Some(link) = &layers.link {
+            (0, Some(data.len() - link.payload.len()))
+        } else {
+            (0, None)
+        };
+        let transport = network.and_then(|start| {
+            if let Some(ip) = &layers.ipv4 {
+                Some(start + usize::from(ip.ihl) * 4)
+            } else {
+                layers.ipv6.as_ref().map(|ip| {
+                    start + 40 + usize::from(ip.payload_length) - ip.payload.len()
+                })
+            }
+        });
+        let application = transport.and_then(|start| {
+            if let Some(tcp) = &layers.tcp {
+                Some(start + usize::from(tcp.data_offset) * 4)
+            } else {
+                layers.udp.as_ref().map(|_| start + 8)
+            }
+        });
+        LayerOffsets {
+            link,
+            network,
+            transport,
+            application,
+        }
+    }
+}
+
+/// Builds the details tree of a decoded packet: one node per protocol, in
+/// the order of the registry's dissection, with the fields it extracted.
+pub fn packet_detail(layers: &PacketLayers, registry: &DissectorRegistry) -> Vec<DetailNode> {
+    let offsets = LayerOffsets::new(layers);
+    let mut tree: Vec<DetailNode> = Vec::new();
+    let mut occurrences: HashMap<&str, usize> = HashMap::new();
+    for (name, value) in registry.dissect(layers).iter() {
+        let label = registry.field(name).map_or("", |field| field.description);
+        if *value == FieldValue::Protocol {
+            tree.push(DetailNode::new(name, label, String::new(), layer_range(layers, &offsets, name)));
+            occurrences.clear();
+            continue;
+        }
+        let Some(protocol) = tree.last_mut() else {
+            continue;
+        };
+        let occurrence = occurrences.entry(name).or_default();
+        let range = field_range(layers, name, *occurrence)
+            .map(|(start, length)| (protocol.offset + start, length))
+            .unwrap_or((protocol.offset, protocol.length));
+        *occurrence += 1;
+        protocol.push_field(DetailNode::new(name, label, value.to_string(), range));
+    }
+
+    // Layers decoded without a registered dissector.
+    let position = tree
+        .iter()
+        .take_while(|node| ["frame", "eth", "vlan"].contains(&node.name.as_str()))
+        .count();
+    let unregistered = [link_node(layers), arp_node(layers, &offsets), ipv6_node(layers, &offsets)];
+    tree.splice(position..position, unregistered.into_iter().flatten());
+    tree
+}
+
+/// Bytes of the frame covered by a protocol: the header of the lower layers
+/// and the whole message of the others.
+fn layer_range(layers: &PacketLayers, offsets: &LayerOffsets, protocol: &str) -> (usize, usize) {
+    let frame_len = layers.packet.data.len();
+    let tags = layers
+        .ethernet
+        .as_ref()
+        .map_or(0, |eth| eth.header.vlan_tags.len());
+    match protocol {
+        "frame" => (0, frame_len),
+        "eth" => (offsets.link, 14 + 4 * tags),
+        "vlan" => (offsets.link + 12, 4 * tags),
+        // The outer headers of a tunneled frame precede it.
+        "tzsp" | "erspan" | "capwap" => (0, offsets.link),
+        "ip" => match (offsets.network, &layers.ipv4) {
+            (Some(start), Some(ip)) => (start, usize::from(ip.ihl) * 4),
+            _ => (0, 0),
+        },
+        "tcp" => match (offsets.transport, &layers.tcp) {
+            (Some(start), Some(tcp)) => (start, usize::from(tcp.data_offset) * 4),
+            _ => (0, 0),
+        },
+        "udp" => (offsets.transport.unwrap_or(0), 8),
+        _ => {
+            let (start, length) = if let Some(start) = offsets.application {
+                let length = match (&layers.tcp, &layers.udp) {
+                    (Some(tcp), _) => tcp.payload.len(),
+                    (_, Some(udp)) => udp.payload.len(),
+                    _ => 0,
+                };
+                (start, length)
+            } else if let Some(start) = offsets.transport {
+                let length = match (&layers.ipv4, &layers.ipv6) {
+                    (Some(ip), _) => ip.payload.len(),
+                    (_, Some(ip)) => ip.payload.len(),
+                    _ => 0,
+                };
+                (start, length)
+            } else if let Some(start) = offsets.network {
+                (start, frame_len - start)
+            } else {
+                (0, frame_len)
+            };
+            (start, length.min(frame_len.saturating_sub(start)))
+        }
+    }
+}
+
+/// Bytes of the `occurrence`-th value of a field relative to the start of its
+/// protocol, for fields at a known position. Values derived from the capture
+/// or from several fields get length 0; other fields cover their protocol.
+fn field_range(layers: &PacketLayers, name: &str, occurrence: usize) -> Option<(usize, usize)> {
+    let tcp_header_len = layers
+        .tcp
+        .as_ref()
+        .map_or(20, |tcp| usize::from(tcp.data_offset) * 4);
+    let range = match name {
+        name if name.starts_with("frame.") => (0, 0),
+        "eth.dst" => (0, 6),
+        "eth.src" => (6, 6),
+        // Pushed as source, then destination.
+        "eth.addr" => [(6, 6), (0, 6)][occurrence.min(1)],
+        "eth.type" => (12, 2),
+        "vlan.priority" | "vlan.dei" | "vlan.id" => (4 * occurrence + 2, 2),
+        "vlan.etype" => (4 * occurrence + 4, 2),
+        "ip.version" | "ip.hdr_len" => (0, 1),
+        "ip.dsfield" => (1, 1),
+        "ip.len" => (2, 2),
+        "ip.id" => (4, 2),
+        "ip.flags" => (6, 1),
+        "ip.frag_offset" => (6, 2),
+        "ip.ttl" => (8, 1),
+        "ip.proto" => (9, 1),
+        "ip.checksum" | "ip.checksum_good" => (10, 2),
+        "ip.src" => (12, 4),
+        "ip.dst" => (16, 4),
+        "ip.addr" => [(12, 4), (16, 4)][occurrence.min(1)],
+        "icmp.type" => (0, 1),
+        "icmp.code" => (1, 1),
+        "icmp.checksum_good" => (2, 2),
+        "tcp.srcport" | "udp.srcport" => (0, 2),
+        "tcp.dstport" | "udp.dstport" => (2, 2),
+        "tcp.port" | "udp.port" => [(0, 2), (2, 2)][occurrence.min(1)],
+        "tcp.seq" => (4, 4),
+        "tcp.ack" => (8, 4),
+        "tcp.hdr_len" => (12, 1),
+        "tcp.window_size" => (14, 2),
+        "tcp.checksum" | "tcp.checksum_good" => (16, 2),
+        "tcp.urgent_pointer" => (18, 2),
+        name if name.starts_with("tcp.flags") => (12, 2),
+        name if name.starts_with("tcp.options.") => (20, tcp_header_len - 20),
+        "tcp.len" => (0, 0),
+        "tcp.payload" => (tcp_header_len, layers.tcp.as_ref()?.payload.len()),
+        "udp.length" => (4, 2),
+        "udp.checksum" | "udp.checksum_good" => (6, 2),
+        "udp.payload" => (8, layers.udp.as_ref()?.payload.len()),
+        _ => return None,
+    };
+    Some(range)
+}
+
+fn field(name: &str, label: &str, value: impl ToString, range: (usize, usize)) -> DetailNode {
+    DetailNode::new(name, label, value.to_string(), range)
+}
+
+/// The link-layer header of loopback and Linux cooked captures.
+fn link_node(layers: &PacketLayers) -> Option<DetailNode> {
+    let link = layers.link.as_ref()?;
+    let header_len = layers.packet.data.len() - link.payload.len();
+    let node = match &link.header {
+        LinkHeader::Loopback { family } => {
+            let mut node = field("null", "Null/Loopback", "", (0, header_len));
+            node.children.push(field("null.family", "Address family", family, (0, 4)));
+            node
+        }
+        LinkHeader::LinuxSll {
+            packet_type,
+            hardware_type,
+            address,
+            interface_index,
+        } => {
+            let address_text: Vec<_> = address.iter().map(|byte| format!("{:02x}", byte)).collect();
+            let address_text = address_text.join(":");
+            let mut node = field("sll", "Linux cooked capture", "", (0, header_len));
+            let fields = match interface_index {
+                None => vec![
+                    field("sll.pkttype", "Packet type", packet_type, (0, 2)),
+                    field("sll.hatype", "Link-layer address type", hardware_type, (2, 2)),
+                    field("sll.src", "Source address", address_text, (6, address.len())),
+                    field("sll.etype", "Protocol", u16::from(link.ether_type), (14, 2)),
+                ],
+                Some(index) => vec![
+                    field("sll.etype", "Protocol", u16::from(link.ether_type), (0, 2)),
+                    field("sll.ifindex", "Interface index", index, (4, 4)),
+                    field("sll.hatype", "Link-layer address type", hardware_type, (8, 2)),
+                    field("sll.pkttype", "Packet type", packet_type, (10, 1)),
+                    field("sll.src", "Source address", address_text, (12, address.len())),
+                ],
+            };
+            node.children = fields;
+            node
+        }
+        LinkHeader::Raw | LinkHeader::Ethernet(_) => return None,
+    };
+    Some(node)
+}
+
+fn arp_node(layers: &PacketLayers, offsets: &LayerOffsets) -> Option<DetailNode> {
+    let arp = layers.arp.as_ref()?;
+    let start = offsets.network?;
+    let at = |offset: usize, length: usize| (start + offset, length);
+    let mut node = DetailNode::new("arp", "Address Resolution Protocol", String::new(), at(0, 28));
+    node.children = vec![
+        field("arp.hw.type", "Hardware type", arp.hardware_type, at(0, 2)),
+        field("arp.proto.type", "Protocol type", arp.protocol_type, at(2, 2)),
+        field("arp.opcode", "Opcode", arp.operation, at(6, 2)),
+        field("arp.src.hw_mac", "Sender MAC address", arp.sender_mac, at(8, 6)),
+        field("arp.src.proto_ipv4", "Sender IP address", arp.sender_ip, at(14, 4)),
+        field("arp.dst.hw_mac", "Target MAC address", arp.target_mac, at(18, 6)),
+        field("arp.dst.proto_ipv4", "Target IP address", arp.target_ip, at(24, 4)),
+    ];
+    Some(node)
+}
+
+fn ipv6_node(layers: &PacketLayers, offsets: &LayerOffsets) -> Option<DetailNode> {
+    let ip = layers.ipv6.as_ref()?;
+    let start = offsets.network?;
+    let header_len = offsets.transport? - start;
+    let at = |offset: usize, length: usize| (start + offset, length);
+    let mut node = DetailNode::new(
+        "ipv6",
+        "Internet Protocol Version 6",
+        String::new(),
+        at(0, header_len),
+    );
+    node.children = vec![
+        field("ipv6.version", "Version", ip.version, at(0, 1)),
+        field("ipv6.tclass", "Traffic class", ip.traffic_class, at(0, 2)),
+        field("ipv6.flow", "Flow label", ip.flow_label, at(1, 3)),
+        field("ipv6.plen", "Payload length", ip.payload_length, at(4, 2)),
+        field("ipv6.nxt", "Next header", ip.next_header, at(6, 1)),
+        field("ipv6.hlim", "Hop limit", ip.hop_limit, at(7, 1)),
+        field("ipv6.src", "Source address", ip.source_ip, at(8, 16)),
+        field("ipv6.dst", "Destination address", ip.dest_ip, at(24, 16)),
+    ];
+    Some(node)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cap::{PcapPacket, PcapPacketHeader};
+    use crate::link::LINKTYPE_LINUX_SLL;
+    use crate::timefmt::Timestamp;
+
+    fn packet(data: Vec<u8>) -> PcapPacket {
+        PcapPacket {
+            header: PcapPacketHeader {
+                timestamp: Timestamp::default(),
+                incl_len: data.len() as u32,
+                orig_len: data.len() as u32,
+                link_type: None,
+            },
+            data,
+        }
+    }
+
+    fn ipv4_udp(payload: &[u8]) -> Vec<u8> {
+        let total_length = (20 + 8 + payload.len()) as u16;
+        let mut data = vec![0x45, 0x00];
+        data.extend_from_slice(&total_length.to_be_bytes());
+        data.extend_from_slice(&[0, 1, 0x40, 0, 64, 17, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2]);
+        data.extend_from_slice(&[0x30, 0x39, 0x00, 0x50]);
+        data.extend_from_slice(&((8 + payload.len()) as u16).to_be_bytes());
+        data.extend_from_slice(&[0, 0]);
+        data.extend_from_slice(payload);
+        data
+    }
+
+    fn find<'a>(nodes: &'a [DetailNode], name: &str) -> &'a DetailNode {
+        nodes
+            .iter()
+            .find_map(|node| {
+                if node.name == name {
+                    Some(node)
+                } else {
+                    node.children.iter().find(|child| child.name == name)
+                }
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_vlan_udp_offsets() {
+        let mut data = vec![0xff; 6];
+        data.extend_from_slice(&[0x02, 0, 0, 0, 0, 0x01]);
+        data.extend_from_slice(&[0x81, 0x00, 0x00, 0x64, 0x08, 0x00]);
+        data.extend(ipv4_udp(b"ping"));
+        let packet = packet(data);
+        let registry = DissectorRegistry::default();
+        let tree = packet_detail(&PacketLayers::decode(1, &packet), &registry);
+
+        let names: Vec<_> = tree.iter().map(|node| node.name.as_str()).collect();
+        assert_eq!(names, vec!["frame", "eth", "vlan", "ip", "udp"]);
+        let frame = find(&tree, "frame.len");
+        assert_eq!((frame.offset, frame.length), (0, 0));
+        let eth = find(&tree, "eth");
+        assert_eq!((eth.offset, eth.length), (0, 18));
+        let vlan_id = find(&tree, "vlan.id");
+        assert_eq!((vlan_id.offset, vlan_id.length, vlan_id.value.as_str()), (14, 2, "100"));
+        let ip_src = find(&tree, "ip.src");
+        assert_eq!((ip_src.offset, ip_src.length, ip_src.value.as_str()), (30, 4, "10.0.0.1"));
+        let port = find(&tree, "udp.dstport");
+        assert_eq!((port.offset, port.length, port.value.as_str()), (40, 2, "80"));
+        let payload = find(&tree, "udp.payload");
+        assert_eq!((payload.offset, payload.length), (46, 4));
+    }
+
+    #[test]
+    fn test_tcp_flags_are_nested() {
+        let mut data = vec![0xff; 12];
+        data.extend_from_slice(&[0x08, 0x00]);
+        data.extend_from_slice(&[0x45, 0, 0, 40, 0, 1, 0x40, 0, 64, 6, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2]);
+        data.extend_from_slice(&[0x30, 0x39, 0x00, 0x50, 0, 0, 0, 1, 0, 0, 0, 0, 0x50, 0x02]);
+        data.extend_from_slice(&[0xff, 0xff, 0, 0, 0, 0]);
+        let packet = packet(data);
+        let registry = DissectorRegistry::default();
+        let tree = packet_detail(&PacketLayers::decode(1, &packet), &registry);
+
+        let tcp = find(&tree, "tcp");
+        assert_eq!((tcp.offset, tcp.length), (34, 20));
+        let flags = find(&tcp.children, "tcp.flags");
+        let syn = flags.children.iter().find(|node| node.name == "tcp.flags.syn").unwrap();
+        assert_eq!((syn.offset, syn.length, syn.value.as_str()), (46, 2, "True"));
+        assert!(tcp.children.iter().all(|node| node.name != "tcp.flags.syn"));
+    }
+
+    #[test]
+    fn test_linux_cooked_capture() {
+        let mut data = vec![0, 0, 0, 1, 0, 6, 0x02, 0, 0, 0, 0, 0x01, 0, 0, 0x08, 0x00];
+        data.extend(ipv4_udp(&[]));
+        let packet = packet(data);
+        let registry = DissectorRegistry::default();
+        let layers = PacketLayers::decode_link(1, LINKTYPE_LINUX_SLL, &packet);
+        let tree = packet_detail(&layers, &registry);
+
+        let names: Vec<_> = tree.iter().map(|node| node.name.as_str()).collect();
+        assert_eq!(names, vec!["frame", "sll", "ip", "udp"]);
+        let source = find(&tree, "sll.src");
+        assert_eq!((source.offset, source.length, source.value.as_str()), (6, 6, "02:00:00:00:00:01"));
+        let ip = find(&tree, "ip");
+        assert_eq!((ip.offset, ip.length), (16, 20));
+    }
+}
@@ -0,0 +1,763 @@
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc;
+
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::dissect::{Dissector, FieldInfo, FieldType, FieldValue, FieldValues, PacketLayers};
+use crate::error::KcpdumpError;
+use crate::packet::MacAddress;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::packetlist;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::session::LoadedCapture;
+
+/// Well-known DHCP server and client ports.
+pub const DHCP_SERVER_PORT: u16 = 67;
+pub const DHCP_CLIENT_PORT: u16 = 68;
+
+/// Magic cookie that starts the options field.
+const MAGIC_COOKIE: u32 = 0x6382_5363;
+
+/// Offset of the options field, after the fixed BOOTP header and the cookie.
+const OPTIONS_OFFSET: usize = 240;
+
+pub const DHCP_DISCOVER: u8 = 1;
+pub const DHCP_OFFER: u8 = 2;
+pub const DHCP_REQUEST: u8 = 3;
+pub const DHCP_DECLINE: u8 = 4;
+pub const DHCP_ACK: u8 = 5;
+pub const DHCP_NAK: u8 = 6;
+pub const DHCP_RELEASE: u8 = 7;
+pub const DHCP_INFORM: u8 = 8;
+
+/// DHCP Message
+/// A BOOTP/DHCP message with its options in wire order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DhcpMessage {
+    /// 1 for requests from the client, 2 for replies from the server.
+    pub op: u8,
+    pub xid: u32,
+    pub client_ip: Ipv4Addr,
+    pub your_ip: Ipv4Addr,
+    pub server_ip: Ipv4Addr,
+    pub relay_ip: Ipv4Addr,
+    pub client_mac: MacAddress,
+    pub options: Vec<(u8, Vec<u8>)>,
+}
+
+impl DhcpMessage {
+    pub fn option(&self, code: u8) -> Option<&[u8]> {
+        self.options
+            .iter()
+            .find(|(option, _)| *option == code)
+            .map(|(_, data)| data.as_slice())
+    }
+
+    /// DHCP message type (option 53), e.g. `DHCP_ACK`.
+    pub fn message_type(&self) -> Option<u8> {
+        self.option(53)?.first().copied()
+    }
+
+    /// Client host name (option 12).
+    pub fn hostname(&self) -> Option<String> {
+        self.option(12).map(|name| {
+            String::from_utf8_lossy(name)
+                .trim_end_matches('\0')
+                .to_string()
+        })
+    }
+
+    /// Requested IP address (option 50).
+    pub fn requested_ip(&self) -> Option<Ipv4Addr> {
+        self.option(50).and_then(ipv4)
+    }
+
+    /// Server identifier (option 54).
+    pub fn server_id(&self) -> Option<Ipv4Addr> {
+        self.option(54).and_then(ipv4)
+    }
+
+    /// IP address lease time in seconds (option 51).
+    pub fn lease_time(&self) -> Option<u32> {
+        self.option(51)
+            .filter(|data| data.len() == 4)
+            .map(BigEndian::read_u32)
+    }
+
+    /// Domain name servers (option 6), in order of preference.
+    pub fn dns_servers(&self) -> Vec<Ipv4Addr> {
+        self.option(6)
+            .map(|data| data.chunks_exact(4).filter_map(ipv4).collect())
+            .unwrap_or_default()
+    }
+
+    /// Relay agent information (option 82) added by a relay on the way to
+    /// the server.
+    pub fn relay_agent(&self) -> Option<RelayAgentInfo> {
+        let mut data = self.option(82)?;
+        let mut info = RelayAgentInfo::default();
+        while let [code, length, rest @ ..] = data {
+            let Some(value) = rest.get(..usize::from(*length)) else {
+                break;
+            };
+            match code {
+                1 => info.circuit_id = Some(value.to_vec()),
+                2 => info.remote_id = Some(value.to_vec()),
+                _ => {}
+            }
+            data = &rest[value.len()..];
+        }
+        Some(info)
+    }
+
+    /// Name of the message, from its DHCP message type or, for plain BOOTP,
+    /// its op code.
+    pub fn name(&self) -> String {
+        match (self.message_type(), self.op) {
+            (Some(message_type), _) => message_type_name(message_type),
+            (None, 1) => "BOOTP Request".to_string(),
+            (None, _) => "BOOTP Reply".to_string(),
+        }
+    }
+}
+
+/// Relay Agent Info
+/// The sub-options of DHCP option 82 that identify where a client is
+/// attached, e.g. the switch port and the switch.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RelayAgentInfo {
+    pub circuit_id: Option<Vec<u8>>,
+    pub remote_id: Option<Vec<u8>>,
+}
+
+fn ipv4(data: &[u8]) -> Option<Ipv4Addr> {
+    let octets: [u8; 4] = data.try_into().ok()?;
+    Some(Ipv4Addr::from(octets))
+}
+
+impl TryFrom<&[u8]> for DhcpMessage {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < OPTIONS_OFFSET {
+            return Err(KcpdumpError::malformed(
+                0,
+                "Data too short for DHCP message",
+            ));
+        }
+        if BigEndian::read_u32(&data[236..240]) != MAGIC_COOKIE {
+            return Err(KcpdumpError::malformed(236, "Missing DHCP magic cookie"));
+        }
+
+        let mut options = Vec::new();
+        let mut offset = OPTIONS_OFFSET;
+        while let Some(&code) = data.get(offset) {
+            match code {
+                0 => offset += 1,
+                255 => break,
+                _ => {
+                    let length = *data
+                        .get(offset + 1)
+                        .ok_or(KcpdumpError::malformed(offset + 1, "DHCP option truncated"))?
+                        as usize;
+                    let value = data
+                        .get(offset + 2..offset + 2 + length)
+                        .ok_or(KcpdumpError::malformed(offset + 2, "DHCP option truncated"))?;
+                    options.push((code, value.to_vec()));
+                    offset += 2 + length;
+                }
+            }
+        }
+
+        let address =
+            |offset: usize| Ipv4Addr::from(BigEndian::read_u32(&data[offset..offset + 4]));
+        Ok(DhcpMessage {
+            op: data[0],
+            xid: BigEndian::read_u32(&data[4..8]),
+            client_ip: address(12),
+            your_ip: address(16),
+            server_ip: address(20),
+            relay_ip: address(24),
+            client_mac: MacAddress([data[28], data[29], data[30], data[31], data[32], data[33]]),
+            options,
+        })
+    }
+}
+
+/// Returns the name of a DHCP message type.
+pub fn message_type_name(message_type: u8) -> String {
+    match message_type {
+        DHCP_DISCOVER => "Discover".to_string(),
+        DHCP_OFFER => "Offer".to_string(),
+        DHCP_REQUEST => "Request".to_string(),
+        DHCP_DECLINE => "Decline".to_string(),
+        DHCP_ACK => "ACK".to_string(),
+        DHCP_NAK => "NAK".to_string(),
+        DHCP_RELEASE => "Release".to_string(),
+        DHCP_INFORM => "Inform".to_string(),
+        other => format!("Unknown ({})", other),
+    }
+}
+
+/// DHCP Dissector
+/// Registers the DHCP fields with the dissector registry.
+pub struct DhcpDissector;
+
+const DHCP_FIELDS: &[FieldInfo] = &[
+    FieldInfo::new("dhcp.type", FieldType::UInt, "Message op code, 1 for requests and 2 for replies"),
+    FieldInfo::new("dhcp.id", FieldType::UInt, "Transaction ID"),
+    FieldInfo::new("dhcp.ip.client", FieldType::Ipv4Address, "Client IP address"),
+    FieldInfo::new("dhcp.ip.your", FieldType::Ipv4Address, "Your (client) IP address"),
+    FieldInfo::new("dhcp.ip.server", FieldType::Ipv4Address, "Next server IP address"),
+    FieldInfo::new("dhcp.ip.relay", FieldType::Ipv4Address, "Relay agent IP address"),
+    FieldInfo::new("dhcp.hw.mac_addr", FieldType::MacAddress, "Client MAC address"),
+    FieldInfo::new("dhcp.option.dhcp", FieldType::UInt, "DHCP message type"),
+    FieldInfo::new("dhcp.option.hostname", FieldType::Text, "Host name"),
+    FieldInfo::new("dhcp.option.requested_ip_address", FieldType::Ipv4Address, "Requested IP address"),
+    FieldInfo::new("dhcp.option.dhcp_server_id", FieldType::Ipv4Address, "DHCP server identifier"),
+    FieldInfo::new("dhcp.option.ip_address_lease_time", FieldType::UInt, "IP address lease time in seconds"),
+    FieldInfo::new("dhcp.option.domain_name_server", FieldType::Ipv4Address, "Domain name server"),
+    FieldInfo::new("dhcp.option.agent_information_option.agent_circuit_id", FieldType::Bytes, "Relay agent circuit ID"),
+    FieldInfo::new("dhcp.option.agent_information_option.agent_remote_id", FieldType::Bytes, "Relay agent remote ID"),
+];
+
+impl Dissector for DhcpDissector {
+    fn protocol(&self) -> &'static str {
+        "dhcp"
+    }
+
+    fn description(&self) -> &'static str {
+        "Dynamic Host Configuration Protocol"
+    }
+
+    fn fields(&self) -> &'static [FieldInfo] {
+        DHCP_FIELDS
+    }
+
+    fn dissect(&self, layers: &PacketLayers, values: &mut FieldValues) {
+        let Some(dhcp) = &layers.dhcp else {
+            return;
+        };
+        values.push("dhcp", FieldValue::Protocol);
+        values.push("dhcp.type", FieldValue::UInt(dhcp.op.into()));
+        values.push("dhcp.id", FieldValue::UInt(dhcp.xid.into()));
+        values.push("dhcp.ip.client", FieldValue::Ipv4Address(dhcp.client_ip));
+        values.push("dhcp.ip.your", FieldValue::Ipv4Address(dhcp.your_ip));
+        values.push("dhcp.ip.server", FieldValue::Ipv4Address(dhcp.server_ip));
+        values.push("dhcp.ip.relay", FieldValue::Ipv4Address(dhcp.relay_ip));
+        values.push("dhcp.hw.mac_addr", FieldValue::MacAddress(dhcp.client_mac));
+        if let Some(message_type) = dhcp.message_type() {
+            values.push("dhcp.option.dhcp", FieldValue::UInt(message_type.into()));
+        }
+        if let Some(hostname) = dhcp.hostname() {
+            values.push("dhcp.option.hostname", FieldValue::Text(hostname));
+        }
+        if let Some(address) = dhcp.requested_ip() {
+            values.push(
+                "dhcp.option.requested_ip_address",
+                FieldValue::Ipv4Address(address),
+            );
+        }
+        if let Some(address) = dhcp.server_id() {
+            values.push(
+                "dhcp.option.dhcp_server_id",
+                FieldValue::Ipv4Address(address),
+            );
+        }
+        if let Some(seconds) = dhcp.lease_time() {
+            values.push(
+                "dhcp.option.ip_address_lease_time",
+                FieldValue::UInt(seconds.into()),
+            );
+        }
+        for address in dhcp.dns_servers() {
+            values.push(
+                "dhcp.option.domain_name_server",
+                FieldValue::Ipv4Address(address),
+            );
+        }
+        if let Some(relay) = dhcp.relay_agent() {
+            if let Some(circuit_id) = relay.circuit_id {
+                values.push(
+                    "dhcp.option.agent_information_option.agent_circuit_id",
+                    FieldValue::Bytes(circuit_id),
+                );
+            }
+            if let Some(remote_id) = relay.remote_id {
+                values.push(
+                    "dhcp.option.agent_information_option.agent_remote_id",
+                    FieldValue::Bytes(remote_id),
+                );
+            }
+        }
+    }
+}
+
+/// DHCP Lease
+/// An address assignment acknowledged by a DHCP server. Renewals of the same
+/// address by the same client extend the lease instead of starting a new one.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DhcpLease {
+    pub client_mac: String,
+    pub ip: Ipv4Addr,
+    pub hostname: Option<String>,
+    pub server: Option<Ipv4Addr>,
+    /// Lease time in seconds granted by the last ACK.
+    pub lease_time: Option<u32>,
+    /// Frame number of the first ACK.
+    pub first_number: usize,
+    /// Time of the first ACK, in seconds since the epoch.
+    pub start: f64,
+    /// Time of the release, or of the last ACK plus its lease time.
+    /// `None` if the lease time is unknown and no release was seen.
+    pub end: Option<f64>,
+    pub renewals: usize,
+    pub released: bool,
+}
+
+/// DHCP Lease Table
+/// Reconstructs leases from DHCP traffic fed in time order.
+#[derive(Debug, Default)]
+pub struct DhcpLeaseTable {
+    leases: Vec<DhcpLease>,
+    /// Open lease per client MAC address.
+    open: HashMap<[u8; 6], usize>,
+    /// Host names announced by clients in requests, by MAC address.
+    hostnames: HashMap<[u8; 6], String>,
+}
+
+impl DhcpLeaseTable {
+    pub fn push(&mut self, layers: &PacketLayers) {
+        let Some(dhcp) = &layers.dhcp else {
+            return;
+        };
+        let header = &layers.packet.header;
+        let time = header.timestamp.as_secs_f64();
+        let mac = dhcp.client_mac.0;
+        if let Some(hostname) = dhcp.hostname() {
+            self.hostnames.insert(mac, hostname);
+        }
+
+        match dhcp.message_type() {
+            Some(DHCP_ACK) if !dhcp.your_ip.is_unspecified() => {
+                let end = dhcp.lease_time().map(|seconds| time + f64::from(seconds));
+                let server = dhcp
+                    .server_id()
+                    .or_else(|| layers.ipv4.as_ref().map(|ip| Ipv4Addr::from(ip.source_ip)));
+                let hostname = dhcp
+                    .hostname()
+                    .or_else(|| self.hostnames.get(&mac).cloned());
+                if let Some(&index) = self.open.get(&mac)
+                    && self.leases[index].ip == dhcp.your_ip
+                {
+                    let lease = &mut self.leases[index];
+                    lease.renewals += 1;
+                    lease.end = end;
+                    lease.lease_time = dhcp.lease_time();
+                    lease.server = server.or(lease.server);
+                    lease.hostname = hostname.or(lease.hostname.take());
+                    return;
+                }
+                // A different address ends the previous lease of the client.
+                if let Some(index) = self.open.remove(&mac) {
+                    let lease = &mut self.leases[index];
+                    lease.end = Some(lease.end.map_or(time, |end| end.min(time)));
+                }
+                self.open.insert(mac, self.leases.len());
+                self.leases.push(DhcpLease {
+                    client_mac: dhcp.client_mac.to_string(),
+                    ip: dhcp.your_ip,
+                    hostname,
+                    server,
+                    lease_time: dhcp.lease_time(),
+                    first_number: layers.number,
+                    start: time,
+                    end,
+                    renewals: 0,
+                    released: false,
+                });
+            }
+            Some(DHCP_RELEASE) => {
+                if let Some(index) = self.open.remove(&mac) {
+                    let lease = &mut self.leases[index];
+                    lease.end = Some(time);
+                    lease.released = true;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns the leases ordered by start time.
+    pub fn finish(self) -> Vec<DhcpLease> {
+        self.leases
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Reconstructs the DHCP leases of the given captures, interleaved by time.
+pub fn lease_table(captures: &[Arc<LoadedCapture>]) -> Result<Vec<DhcpLease>, KcpdumpError> {
+    let mut table = DhcpLeaseTable::default();
+    let link_types = packetlist::link_types(captures);
+    for item in packetlist::merged_packets(captures) {
+        let (capture_id, number, packet) = item?;
+        table.push(&PacketLayers::decode_link(number, link_types[&capture_id], &packet));
+    }
+    Ok(table.finish())
+}
+
+/// DHCP Outcome
+/// How a DHCP transaction ended.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum DhcpOutcome {
+    /// No ACK, NAK, decline or release was seen.
+    #[default]
+    Pending,
+    /// The server acknowledged the request.
+    Bound,
+    /// The server refused the request with a NAK.
+    Rejected,
+    /// The client declined the address, e.g. because it was already in use.
+    Declined,
+    /// The client gave the address back.
+    Released,
+}
+
+/// DHCP Transaction Message
+/// One message of a DHCP transaction.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DhcpTransactionMessage {
+    pub number: usize,
+    /// Seconds since the epoch.
+    pub time: f64,
+    /// Message name, e.g. `Discover` or `ACK`.
+    pub name: String,
+    /// Whether the server sent it.
+    pub reply: bool,
+}
+
+/// DHCP Transaction
+/// The messages sharing a client MAC address and transaction ID, e.g. a
+/// DISCOVER/OFFER/REQUEST/ACK exchange, with the configuration the client
+/// asked for and the server handed out.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DhcpTransaction {
+    pub client_mac: String,
+    pub xid: u32,
+    pub hostname: Option<String>,
+    /// Address requested by the client (option 50).
+    pub requested_ip: Option<Ipv4Addr>,
+    /// Address offered or acknowledged by the server.
+    pub ip: Option<Ipv4Addr>,
+    pub server: Option<Ipv4Addr>,
+    pub lease_time: Option<u32>,
+    pub dns_servers: Vec<Ipv4Addr>,
+    /// Relay agent the messages went through, with the hex-encoded
+    /// option 82 circuit and remote IDs it added.
+    pub relay: Option<Ipv4Addr>,
+    pub circuit_id: Option<String>,
+    pub remote_id: Option<String>,
+    pub messages: Vec<DhcpTransactionMessage>,
+    pub outcome: DhcpOutcome,
+    /// Seconds from the first to the last message.
+    pub duration: f64,
+}
+
+/// DHCP Transaction Table
+/// Groups DHCP messages fed in time order into transactions.
+#[derive(Debug, Default)]
+pub struct DhcpTransactionTable {
+    transactions: Vec<DhcpTransaction>,
+    /// Transaction per client MAC address and transaction ID.
+    open: HashMap<([u8; 6], u32), usize>,
+}
+
+impl DhcpTransactionTable {
+    pub fn push(&mut self, layers: &PacketLayers) {
+        let Some(dhcp) = &layers.dhcp else {
+            return;
+        };
+        let time = layers.packet.header.timestamp.as_secs_f64();
+        let index = *self
+            .open
+            .entry((dhcp.client_mac.0, dhcp.xid))
+            .or_insert_with(|| {
+                self.transactions.push(DhcpTransaction {
+                    client_mac: dhcp.client_mac.to_string(),
+                    xid: dhcp.xid,
+                    hostname: None,
+                    requested_ip: None,
+                    ip: None,
+                    server: None,
+                    lease_time: None,
+                    dns_servers: Vec::new(),
+                    relay: None,
+                    circuit_id: None,
+                    remote_id: None,
+                    messages: Vec::new(),
+                    outcome: DhcpOutcome::Pending,
+                    duration: 0.0,
+                });
+                self.transactions.len() - 1
+            });
+        let transaction = &mut self.transactions[index];
+        let reply = dhcp.op == 2;
+        transaction.messages.push(DhcpTransactionMessage {
+            number: layers.number,
+            time,
+            name: dhcp.name(),
+            reply,
+        });
+        transaction.duration = time - transaction.messages[0].time;
+
+        transaction.hostname = dhcp.hostname().or(transaction.hostname.take());
+        transaction.requested_ip = dhcp.requested_ip().or(transaction.requested_ip);
+        if !dhcp.relay_ip.is_unspecified() {
+            transaction.relay = Some(dhcp.relay_ip);
+        }
+        if let Some(relay) = dhcp.relay_agent() {
+            if let Some(circuit_id) = relay.circuit_id {
+                transaction.circuit_id = Some(hex(&circuit_id));
+            }
+            if let Some(remote_id) = relay.remote_id {
+                transaction.remote_id = Some(hex(&remote_id));
+            }
+        }
+        if reply {
+            if !dhcp.your_ip.is_unspecified() {
+                transaction.ip = Some(dhcp.your_ip);
+            }
+            transaction.server = dhcp
+                .server_id()
+                .or_else(|| layers.ipv4.as_ref().map(|ip| Ipv4Addr::from(ip.source_ip)))
+                .or(transaction.server);
+            transaction.lease_time = dhcp.lease_time().or(transaction.lease_time);
+            let dns_servers = dhcp.dns_servers();
+            if !dns_servers.is_empty() {
+                transaction.dns_servers = dns_servers;
+            }
+        }
+        transaction.outcome = match dhcp.message_type() {
+            Some(DHCP_ACK) => DhcpOutcome::Bound,
+            Some(DHCP_NAK) => DhcpOutcome::Rejected,
+            Some(DHCP_DECLINE) => DhcpOutcome::Declined,
+            Some(DHCP_RELEASE) => DhcpOutcome::Released,
+            _ => transaction.outcome,
+        };
+    }
+
+    /// Returns the transactions ordered by their first message.
+    pub fn finish(self) -> Vec<DhcpTransaction> {
+        self.transactions
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Groups the DHCP messages of the given captures into transactions,
+/// interleaved by time.
+pub fn transactions(captures: &[Arc<LoadedCapture>]) -> Result<Vec<DhcpTransaction>, KcpdumpError> {
+    let mut table = DhcpTransactionTable::default();
+    let link_types = packetlist::link_types(captures);
+    for item in packetlist::merged_packets(captures) {
+        let (capture_id, number, packet) = item?;
+        table.push(&PacketLayers::decode_link(number, link_types[&capture_id], &packet));
+    }
+    Ok(table.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timefmt::Timestamp;
+    use crate::cap::{PcapPacket, PcapPacketHeader};
+
+    const CLIENT_MAC: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x01];
+
+    fn dhcp_payload(op: u8, message_type: u8, your_ip: [u8; 4], extra: &[(u8, &[u8])]) -> Vec<u8> {
+        let mut data = vec![0u8; OPTIONS_OFFSET];
+        data[0] = op;
+        data[1] = 1;
+        data[2] = 6;
+        data[4..8].copy_from_slice(&0xdeadbeefu32.to_be_bytes());
+        data[16..20].copy_from_slice(&your_ip);
+        data[28..34].copy_from_slice(&CLIENT_MAC);
+        data[236..240].copy_from_slice(&MAGIC_COOKIE.to_be_bytes());
+        data.extend_from_slice(&[53, 1, message_type]);
+        for (code, value) in extra {
+            data.push(*code);
+            data.push(value.len() as u8);
+            data.extend_from_slice(value);
+        }
+        data.push(255);
+        data
+    }
+
+    /// A DHCP datagram from 192.168.0.1:67 to 255.255.255.255:68 at `ts_sec`.
+    fn dhcp_frame(ts_sec: u32, payload: &[u8]) -> PcapPacket {
+        let mut data = vec![
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x08, 0x00,
+            0x45, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x40, 0x11, 0x00, 0x00, 192, 168, 0, 1,
+            255, 255, 255, 255, 0x00, 0x43, 0x00, 0x44, 0x00, 0x00, 0x00, 0x00,
+        ];
+        data[16..18].copy_from_slice(&(28 + payload.len() as u16).to_be_bytes());
+        data[38..40].copy_from_slice(&(8 + payload.len() as u16).to_be_bytes());
+        data.extend_from_slice(payload);
+        PcapPacket {
+            header: PcapPacketHeader {
+                timestamp: Timestamp::new(ts_sec, 0),
+                incl_len: data.len() as u32,
+                orig_len: data.len() as u32,
+                link_type: None,
+            },
+            data,
+        }
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let payload = dhcp_payload(
+            2,
+            DHCP_ACK,
+            [192, 168, 0, 50],
+            &[(51, &[0, 0, 0x0e, 0x10]), (12, b"laptop")],
+        );
+        let message = DhcpMessage::try_from(payload.as_slice()).unwrap();
+        assert_eq!(message.op, 2);
+        assert_eq!(message.xid, 0xdeadbeef);
+        assert_eq!(message.message_type(), Some(DHCP_ACK));
+        assert_eq!(message.your_ip, Ipv4Addr::new(192, 168, 0, 50));
+        assert_eq!(message.lease_time(), Some(3600));
+        assert_eq!(message.hostname().as_deref(), Some("laptop"));
+        assert_eq!(message.client_mac, MacAddress(CLIENT_MAC));
+        assert_eq!(message_type_name(DHCP_ACK), "ACK");
+        assert!(DhcpMessage::try_from(&payload[..100]).is_err());
+    }
+
+    #[test]
+    fn test_dns_servers_and_relay_agent() {
+        let payload = dhcp_payload(
+            2,
+            DHCP_OFFER,
+            [192, 168, 0, 50],
+            &[
+                (6, &[8, 8, 8, 8, 1, 1, 1, 1]),
+                (82, &[1, 3, b'e', b't', b'1', 2, 2, 0xab, 0xcd, 9, 1]),
+            ],
+        );
+        let message = DhcpMessage::try_from(payload.as_slice()).unwrap();
+        assert_eq!(
+            message.dns_servers(),
+            vec![Ipv4Addr::new(8, 8, 8, 8), Ipv4Addr::new(1, 1, 1, 1)]
+        );
+        let relay = message.relay_agent().unwrap();
+        assert_eq!(relay.circuit_id.as_deref(), Some(b"et1".as_slice()));
+        assert_eq!(relay.remote_id, Some(vec![0xab, 0xcd]));
+        assert_eq!(message.name(), "Offer");
+    }
+
+    #[test]
+    fn test_transactions() {
+        let your_ip = [192, 168, 0, 50];
+        let server: &[u8] = &[192, 168, 0, 1];
+        let dns: &[u8] = &[192, 168, 0, 1];
+        let packets = [
+            dhcp_frame(100, &dhcp_payload(1, DHCP_DISCOVER, [0; 4], &[(12, b"laptop")])),
+            dhcp_frame(101, &dhcp_payload(2, DHCP_OFFER, your_ip, &[(54, server), (6, dns)])),
+            dhcp_frame(102, &dhcp_payload(1, DHCP_REQUEST, [0; 4], &[(50, &your_ip)])),
+            dhcp_frame(
+                103,
+                &dhcp_payload(2, DHCP_ACK, your_ip, &[(54, server), (51, &[0, 0, 0x0e, 0x10])]),
+            ),
+        ];
+        let mut table = DhcpTransactionTable::default();
+        for (index, packet) in packets.iter().enumerate() {
+            table.push(&PacketLayers::decode(index + 1, packet));
+        }
+        // A NAK in another exchange of the same client.
+        let mut nak = dhcp_payload(2, DHCP_NAK, [0; 4], &[]);
+        nak[4..8].copy_from_slice(&1u32.to_be_bytes());
+        table.push(&PacketLayers::decode(5, &dhcp_frame(200, &nak)));
+
+        let transactions = table.finish();
+        assert_eq!(transactions.len(), 2);
+        let dora = &transactions[0];
+        let names: Vec<_> = dora.messages.iter().map(|message| message.name.as_str()).collect();
+        assert_eq!(names, ["Discover", "Offer", "Request", "ACK"]);
+        assert_eq!(dora.client_mac, "02:00:00:00:00:01");
+        assert_eq!(dora.hostname.as_deref(), Some("laptop"));
+        assert_eq!(dora.requested_ip, Some(Ipv4Addr::from(your_ip)));
+        assert_eq!(dora.ip, Some(Ipv4Addr::from(your_ip)));
+        assert_eq!(dora.server, Some(Ipv4Addr::new(192, 168, 0, 1)));
+        assert_eq!(dora.lease_time, Some(3600));
+        assert_eq!(dora.dns_servers, vec![Ipv4Addr::new(192, 168, 0, 1)]);
+        assert_eq!(dora.outcome, DhcpOutcome::Bound);
+        assert_eq!(dora.duration, 3.0);
+        assert_eq!(transactions[1].xid, 1);
+        assert_eq!(transactions[1].outcome, DhcpOutcome::Rejected);
+        assert_eq!(transactions[1].ip, None);
+    }
+
+    #[test]
+    fn test_lease_renewal_and_release() {
+        let server: &[u8] = &[192, 168, 0, 1];
+        let lease_time: &[u8] = &[0, 0, 0x0e, 0x10];
+        let packets = [
+            dhcp_frame(
+                100,
+                &dhcp_payload(1, DHCP_REQUEST, [0; 4], &[(12, b"laptop")]),
+            ),
+            dhcp_frame(
+                101,
+                &dhcp_payload(
+                    2,
+                    DHCP_ACK,
+                    [192, 168, 0, 50],
+                    &[(54, server), (51, lease_time)],
+                ),
+            ),
+            dhcp_frame(
+                1900,
+                &dhcp_payload(
+                    2,
+                    DHCP_ACK,
+                    [192, 168, 0, 50],
+                    &[(54, server), (51, lease_time)],
+                ),
+            ),
+            dhcp_frame(2000, &dhcp_payload(1, DHCP_RELEASE, [0; 4], &[])),
+            dhcp_frame(
+                3000,
+                &dhcp_payload(2, DHCP_ACK, [192, 168, 0, 60], &[(51, lease_time)]),
+            ),
+        ];
+        let mut table = DhcpLeaseTable::default();
+        for (index, packet) in packets.iter().enumerate() {
+            table.push(&PacketLayers::decode(index + 1, packet));
+        }
+        let leases = table.finish();
+        assert_eq!(leases.len(), 2);
+        assert_eq!(leases[0].ip, Ipv4Addr::new(192, 168, 0, 50));
+        assert_eq!(leases[0].client_mac, "02:00:00:00:00:01");
+        assert_eq!(leases[0].hostname.as_deref(), Some("laptop"));
+        assert_eq!(leases[0].server, Some(Ipv4Addr::new(192, 168, 0, 1)));
+        assert_eq!(leases[0].first_number, 2);
+        assert_eq!(leases[0].start, 101.0);
+        assert_eq!(leases[0].renewals, 1);
+        assert!(leases[0].released);
+        assert_eq!(leases[0].end, Some(2000.0));
+        assert_eq!(leases[1].ip, Ipv4Addr::new(192, 168, 0, 60));
+        assert_eq!(leases[1].end, Some(6600.0));
+        // Without a server identifier the sender of the ACK is the server.
+        assert_eq!(leases[1].server, Some(Ipv4Addr::new(192, 168, 0, 1)));
+    }
+}
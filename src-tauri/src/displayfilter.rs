@@ -0,0 +1,536 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use crate::cap::PcapPacket;
+use crate::dissect::{DissectorRegistry, FieldType, FieldValue, FieldValues, PacketLayers};
+use crate::error::KcpdumpError;
+use crate::packet::EtherType;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    /// A protocol or field that is present at least once.
+    Exists(String),
+    Compare(String, Op, FieldValue),
+    Subnet(String, Op, Ipv4Addr, u8),
+    Contains(String, Vec<u8>),
+}
+
+/// Display Filter
+/// A compiled display filter over the fields of the dissector registry, in
+/// the style of Wireshark: `ip.src == 192.168.0.1 && tcp.port == 443`,
+/// `eth.type == arp`, `ip.addr == 10.0.0.0/8`, `!dns`. Comparisons are
+/// `==`, `!=`, `<`, `<=`, `>`, `>=` (or `eq`, `ne`, `lt`, `le`, `gt`, `ge`)
+/// and `contains`; terms combine with `&&`/`and`, `||`/`or`, `!`/`not` and
+/// parentheses. A comparison holds if any occurrence of the field satisfies
+/// it, except `!=`, which holds if none equals the value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisplayFilter {
+    expr: Option<Expr>,
+}
+
+impl DisplayFilter {
+    /// Compiles a filter expression, checking field names and values against
+    /// the registry. An empty expression accepts every packet.
+    pub fn compile(expression: &str, registry: &DissectorRegistry) -> Result<Self, KcpdumpError> {
+        let tokens = tokenize(expression)?;
+        if tokens.is_empty() {
+            return Ok(DisplayFilter { expr: None });
+        }
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+            registry,
+        };
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.peek() {
+            return Err(KcpdumpError::InvalidFilter(format!(
+                "Unexpected token '{}'",
+                token.text()
+            )));
+        }
+        Ok(DisplayFilter { expr: Some(expr) })
+    }
+
+    /// Evaluates the filter against the field values of a dissected packet.
+    pub fn matches(&self, values: &FieldValues) -> bool {
+        self.expr.as_ref().is_none_or(|expr| eval(expr, values))
+    }
+
+    /// Dissects a packet of the given link type with the registry and
+    /// evaluates the filter.
+    pub fn matches_packet(
+        &self,
+        registry: &DissectorRegistry,
+        link_type: u32,
+        number: usize,
+        packet: &PcapPacket,
+    ) -> bool {
+        self.expr.is_none() || {
+            let layers = PacketLayers::decode_link(number, link_type, packet);
+            self.matches(&registry.dissect(&layers))
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    /// A double-quoted string.
+    Quoted(String),
+    Symbol(&'static str),
+}
+
+impl Token {
+    fn text(&self) -> &str {
+        match self {
+            Token::Word(word) | Token::Quoted(word) => word,
+            Token::Symbol(symbol) => symbol,
+        }
+    }
+
+    /// The operator or keyword a token stands for, if any.
+    fn keyword(&self) -> Option<&'static str> {
+        match self {
+            Token::Symbol(symbol) => Some(symbol),
+            Token::Word(word) => match word.to_ascii_lowercase().as_str() {
+                "and" => Some("&&"),
+                "or" => Some("||"),
+                "not" => Some("!"),
+                "eq" => Some("=="),
+                "ne" => Some("!="),
+                "lt" => Some("<"),
+                "le" => Some("<="),
+                "gt" => Some(">"),
+                "ge" => Some(">="),
+                "contains" => Some("contains"),
+                _ => None,
+            },
+            Token::Quoted(_) => None,
+        }
+    }
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, KcpdumpError> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        chars.next();
+        let symbol = match c {
+            c if c.is_whitespace() => continue,
+            '(' => "(",
+            ')' => ")",
+            '&' | '|' => {
+                if chars.next() != Some(c) {
+                    return Err(KcpdumpError::InvalidFilter(format!(
+                        "Expected '{}{}'",
+                        c, c
+                    )));
+                }
+                if c == '&' { "&&" } else { "||" }
+            }
+            '=' => {
+                if chars.next() != Some('=') {
+                    return Err(KcpdumpError::InvalidFilter("Expected '=='".to_string()));
+                }
+                "=="
+            }
+            '!' | '<' | '>' => {
+                let equals = chars.next_if_eq(&'=').is_some();
+                match (c, equals) {
+                    ('!', false) => "!",
+                    ('!', true) => "!=",
+                    ('<', false) => "<",
+                    ('<', true) => "<=",
+                    ('>', false) => ">",
+                    _ => ">=",
+                }
+            }
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => text.extend(chars.next()),
+                        Some(c) => text.push(c),
+                        None => {
+                            return Err(KcpdumpError::InvalidFilter(
+                                "Unterminated string".to_string(),
+                            ));
+                        }
+                    }
+                }
+                tokens.push(Token::Quoted(text));
+                continue;
+            }
+            _ => {
+                let mut word = c.to_string();
+                while let Some(c) =
+                    chars.next_if(|c| !c.is_whitespace() && !"()&|=!<>\"".contains(*c))
+                {
+                    word.push(c);
+                }
+                tokens.push(Token::Word(word));
+                continue;
+            }
+        };
+        tokens.push(Token::Symbol(symbol));
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    registry: &'a DissectorRegistry,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn peek_keyword(&self) -> Option<&'static str> {
+        self.peek().and_then(Token::keyword)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, KcpdumpError> {
+        let mut left = self.parse_and()?;
+        while self.peek_keyword() == Some("||") {
+            self.next();
+            let right = self.parse_and()?;
+            left = Expr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, KcpdumpError> {
+        let mut left = self.parse_not()?;
+        while self.peek_keyword() == Some("&&") {
+            self.next();
+            let right = self.parse_not()?;
+            left = Expr::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr, KcpdumpError> {
+        match self.peek_keyword() {
+            Some("!") => {
+                self.next();
+                Ok(Expr::Not(Box::new(self.parse_not()?)))
+            }
+            Some("(") => {
+                self.next();
+                let expr = self.parse_or()?;
+                if self.next().and_then(|token| token.keyword()) != Some(")") {
+                    return Err(KcpdumpError::InvalidFilter("Expected ')'".to_string()));
+                }
+                Ok(expr)
+            }
+            _ => self.parse_comparison(),
+        }
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, KcpdumpError> {
+        let name = match self.next() {
+            Some(token @ Token::Word(_)) if token.keyword().is_none() => token.text().to_string(),
+            Some(token) => {
+                return Err(KcpdumpError::InvalidFilter(format!(
+                    "Expected a field name, found '{}'",
+                    token.text()
+                )));
+            }
+            None => {
+                return Err(KcpdumpError::InvalidFilter(
+                    "Unexpected end of expression".to_string(),
+                ));
+            }
+        };
+        let field = self
+            .registry
+            .field(&name)
+            .ok_or_else(|| KcpdumpError::InvalidFilter(format!("Unknown field '{}'", name)))?;
+        let op = match self.peek_keyword() {
+            Some("==") => Op::Eq,
+            Some("!=") => Op::Ne,
+            Some("<") => Op::Lt,
+            Some("<=") => Op::Le,
+            Some(">") => Op::Gt,
+            Some(">=") => Op::Ge,
+            Some("contains") => {
+                self.next();
+                let value = self.expect_value()?;
+                let bytes = match field.field_type {
+                    FieldType::Text => value.into_bytes(),
+                    FieldType::Bytes => parse_bytes(&value)?,
+                    _ => {
+                        return Err(KcpdumpError::InvalidFilter(format!(
+                            "'{}' does not support 'contains'",
+                            name
+                        )));
+                    }
+                };
+                return Ok(Expr::Contains(name, bytes));
+            }
+            _ => return Ok(Expr::Exists(name)),
+        };
+        self.next();
+        let value = self.expect_value()?;
+        let ordered = matches!(op, Op::Lt | Op::Le | Op::Gt | Op::Ge);
+        if ordered && field.field_type != FieldType::UInt {
+            return Err(KcpdumpError::InvalidFilter(format!(
+                "'{}' cannot be ordered",
+                name
+            )));
+        }
+        let value = match field.field_type {
+            FieldType::Protocol => {
+                return Err(KcpdumpError::InvalidFilter(format!(
+                    "'{}' is a protocol, not a field",
+                    name
+                )));
+            }
+            FieldType::UInt => FieldValue::UInt(parse_uint(&name, &value)?),
+            FieldType::Bool => match value.to_ascii_lowercase().as_str() {
+                "true" | "1" => FieldValue::Bool(true),
+                "false" | "0" => FieldValue::Bool(false),
+                _ => {
+                    return Err(KcpdumpError::InvalidFilter(format!(
+                        "Invalid boolean '{}'",
+                        value
+                    )));
+                }
+            },
+            FieldType::MacAddress => FieldValue::MacAddress(crate::bpf::parse_mac(&value)?),
+            FieldType::Ipv4Address => match value.split_once('/') {
+                Some((address, prefix)) => {
+                    let address = address.parse().map_err(|_| {
+                        KcpdumpError::InvalidFilter(format!("Invalid IPv4 address '{}'", address))
+                    })?;
+                    let prefix = prefix
+                        .parse()
+                        .ok()
+                        .filter(|prefix| *prefix <= 32)
+                        .ok_or_else(|| {
+                            KcpdumpError::InvalidFilter(format!(
+                                "Invalid prefix length '{}'",
+                                prefix
+                            ))
+                        })?;
+                    return Ok(Expr::Subnet(name, op, address, prefix));
+                }
+                None => FieldValue::Ipv4Address(value.parse().map_err(|_| {
+                    KcpdumpError::InvalidFilter(format!("Invalid IPv4 address '{}'", value))
+                })?),
+            },
+            FieldType::Ipv6Address => {
+                FieldValue::Ipv6Address(value.parse::<Ipv6Addr>().map_err(|_| {
+                    KcpdumpError::InvalidFilter(format!("Invalid IPv6 address '{}'", value))
+                })?)
+            }
+            FieldType::Text => FieldValue::Text(value),
+            FieldType::Bytes => FieldValue::Bytes(parse_bytes(&value)?),
+        };
+        Ok(Expr::Compare(name, op, value))
+    }
+
+    fn expect_value(&mut self) -> Result<String, KcpdumpError> {
+        match self.next() {
+            Some(Token::Quoted(text)) => Ok(text),
+            Some(Token::Word(word)) => Ok(word),
+            _ => Err(KcpdumpError::InvalidFilter("Expected a value".to_string())),
+        }
+    }
+}
+
+/// Parses a decimal or `0x` prefixed number, or one of the names some
+/// fields accept in place of their numeric value.
+fn parse_uint(field: &str, value: &str) -> Result<u64, KcpdumpError> {
+    let parsed = match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    };
+    parsed
+        .or_else(|| named_value(field, &value.to_ascii_lowercase()))
+        .ok_or_else(|| {
+            KcpdumpError::InvalidFilter(format!("Invalid value '{}' for '{}'", value, field))
+        })
+}
+
+fn named_value(field: &str, name: &str) -> Option<u64> {
+    let ether_type = |ether_type: EtherType| Some(u64::from(u16::from(ether_type)));
+    match (field, name) {
+        ("eth.type", "ip" | "ipv4") => ether_type(EtherType::IPv4),
+        ("eth.type", "arp") => ether_type(EtherType::ARP),
+        ("eth.type", "ipv6") => ether_type(EtherType::IPv6),
+        ("eth.type", "vlan") => Some(0x8100),
+        ("ip.proto", "icmp") => Some(1),
+        ("ip.proto", "igmp") => Some(2),
+        ("ip.proto", "tcp") => Some(6),
+        ("ip.proto", "udp") => Some(17),
+        ("ip.proto", "gre") => Some(47),
+        ("ip.proto", "esp") => Some(50),
+        ("ip.proto", "sctp") => Some(132),
+        _ => None,
+    }
+}
+
+/// Parses bytes written as hex pairs separated by `:`, `-` or `.`, or run
+/// together.
+fn parse_bytes(value: &str) -> Result<Vec<u8>, KcpdumpError> {
+    let digits: String = value.chars().filter(|c| !":-.".contains(*c)).collect();
+    if digits.is_empty() || !digits.len().is_multiple_of(2) {
+        return Err(KcpdumpError::InvalidFilter(format!(
+            "Invalid bytes '{}'",
+            value
+        )));
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(&digits[index..index + 2], 16))
+        .collect::<Result<_, _>>()
+        .map_err(|_| KcpdumpError::InvalidFilter(format!("Invalid bytes '{}'", value)))
+}
+
+fn eval(expr: &Expr, values: &FieldValues) -> bool {
+    match expr {
+        Expr::And(left, right) => eval(left, values) && eval(right, values),
+        Expr::Or(left, right) => eval(left, values) || eval(right, values),
+        Expr::Not(inner) => !eval(inner, values),
+        Expr::Exists(name) => values.contains(name),
+        Expr::Compare(name, Op::Ne, value) => !values.get(name).any(|found| found == value),
+        Expr::Compare(name, op, value) => {
+            values.get(name).any(|found| compare(found, *op, value))
+        }
+        Expr::Subnet(name, op, network, prefix) => {
+            let in_subnet = values.get(name).any(|found| match found {
+                FieldValue::Ipv4Address(address) => in_subnet(*address, *network, *prefix),
+                _ => false,
+            });
+            in_subnet == (*op != Op::Ne)
+        }
+        Expr::Contains(name, needle) => values.get(name).any(|found| {
+            let haystack = match found {
+                FieldValue::Text(text) => text.as_bytes(),
+                FieldValue::Bytes(bytes) => bytes.as_slice(),
+                _ => return false,
+            };
+            needle.is_empty()
+                || haystack
+                    .windows(needle.len())
+                    .any(|window| window == needle.as_slice())
+        }),
+    }
+}
+
+fn compare(found: &FieldValue, op: Op, value: &FieldValue) -> bool {
+    match (found, value) {
+        (FieldValue::UInt(found), FieldValue::UInt(value)) => match op {
+            Op::Eq => found == value,
+            Op::Ne => found != value,
+            Op::Lt => found < value,
+            Op::Le => found <= value,
+            Op::Gt => found > value,
+            Op::Ge => found >= value,
+        },
+        _ => op == Op::Eq && found == value,
+    }
+}
+
+fn in_subnet(address: Ipv4Addr, network: Ipv4Addr, prefix: u8) -> bool {
+    let mask = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+    u32::from(address) & mask == u32::from(network) & mask
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timefmt::Timestamp;
+    use crate::cap::PcapPacketHeader;
+    use crate::link::LINKTYPE_ETHERNET;
+
+    /// Ethernet + IPv4 + TCP header from 192.168.0.10:50000 to 93.184.216.34:443.
+    fn tcp_packet() -> PcapPacket {
+        let data = vec![
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0x08, 0x00,
+            0x45, 0x00, 0x00, 0x28, 0x00, 0x01, 0x40, 0x00, 0x40, 0x06, 0x00, 0x00, 0xc0, 0xa8,
+            0x00, 0x0a, 0x5d, 0xb8, 0xd8, 0x22, 0xc3, 0x50, 0x01, 0xbb, 0x00, 0x00, 0x03, 0xe8,
+            0x00, 0x00, 0x00, 0x00, 0x50, 0x02, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00,
+        ];
+        PcapPacket {
+            header: PcapPacketHeader {
+                timestamp: Timestamp::from_micros(0, 0),
+                incl_len: data.len() as u32,
+                orig_len: data.len() as u32,
+                link_type: None,
+            },
+            data,
+        }
+    }
+
+    fn matches(expression: &str) -> bool {
+        let registry = DissectorRegistry::default();
+        DisplayFilter::compile(expression, &registry)
+            .unwrap()
+            .matches_packet(&registry, LINKTYPE_ETHERNET, 1, &tcp_packet())
+    }
+
+    #[test]
+    fn test_comparisons() {
+        assert!(matches(""));
+        assert!(matches("tcp"));
+        assert!(!matches("udp"));
+        assert!(matches("ip.src == 192.168.0.10 && tcp.port == 443"));
+        assert!(!matches("ip.dst == 192.168.0.10"));
+        assert!(matches("eth.type == ip"));
+        assert!(!matches("eth.type == arp"));
+        assert!(matches("ip.proto eq tcp"));
+        assert!(matches("ip.addr == 93.184.0.0/16"));
+        assert!(matches("ip.addr != 10.0.0.0/8"));
+        assert!(matches("ip.addr != 10.0.0.1"));
+        assert!(!matches("tcp.port != 443"));
+        assert!(matches("tcp.srcport > 1024 and tcp.dstport <= 0x1bb"));
+        assert!(matches("tcp.flags.syn == true"));
+        assert!(matches("eth.src == 66:77:88:99:aa:bb"));
+    }
+
+    #[test]
+    fn test_boolean_operators() {
+        assert!(matches("!(udp || icmp) && tcp"));
+        assert!(matches("udp or ip.ttl == 64"));
+        assert!(matches("not udp"));
+        assert!(!matches("tcp && !(tcp.port == 443)"));
+    }
+
+    #[test]
+    fn test_errors() {
+        let registry = DissectorRegistry::default();
+        let compile = |expression| DisplayFilter::compile(expression, &registry);
+        assert!(compile("foo.bar == 1").is_err());
+        assert!(compile("ip.src == 300.1.1.1").is_err());
+        assert!(compile("ip.src > 10.0.0.1").is_err());
+        assert!(compile("eth.type == banana").is_err());
+        assert!(compile("tcp &&").is_err());
+        assert!(compile("(tcp").is_err());
+        assert!(compile("tcp udp").is_err());
+        assert!(compile("ip.src = 1.2.3.4").is_err());
+    }
+}
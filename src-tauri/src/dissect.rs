@@ -0,0 +1,636 @@
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use crate::arp::ArpPacket;
+use crate::cap::PcapPacket;
+use crate::error::KcpdumpError;
+use crate::capwap::{CAPWAP_CONTROL_PORT, CAPWAP_DATA_PORT, CapwapPacket};
+use crate::dccp::DccpPacket;
+use crate::dhcp::{DHCP_CLIENT_PORT, DHCP_SERVER_PORT, DhcpMessage};
+use crate::dns::{DNS_PORT, DnsMessage};
+use crate::erspan::{ErspanPacket, IP_PROTOCOL_GRE};
+use crate::icmp::IcmpMessage;
+use crate::link::{LINKTYPE_ETHERNET, LinkFrame};
+use crate::ndp::ICMPV6;
+use crate::packet::{
+    EtherType, EthernetPacket, IPv4Packet, IPv6Packet, IpProtocol, MacAddress, TcpSegment,
+    UdpDatagram,
+};
+use crate::ptp::{ETHERTYPE_PTP, PTP_EVENT_PORT, PTP_GENERAL_PORT, PtpMessage};
+use crate::someip::SomeIpMessage;
+use crate::tzsp::{TZSP_PORT, TzspPacket};
+use crate::udplite::UdpLiteDatagram;
+
+/// Field Type
+/// The value type of a filterable field, used by the filter bar to validate operands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FieldType {
+    /// The protocol itself; only tests for presence.
+    Protocol,
+    UInt,
+    Bool,
+    MacAddress,
+    Ipv4Address,
+    Ipv6Address,
+    Text,
+    Bytes,
+}
+
+/// Field Info
+/// Describes one filterable field exposed by a dissector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldInfo {
+    pub name: &'static str,
+    pub field_type: FieldType,
+    pub description: &'static str,
+}
+
+impl FieldInfo {
+    pub const fn new(name: &'static str, field_type: FieldType, description: &'static str) -> Self {
+        FieldInfo {
+            name,
+            field_type,
+            description,
+        }
+    }
+}
+
+/// Field Value
+/// The value of one field occurrence in a dissected packet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    /// Marks the presence of a protocol.
+    Protocol,
+    UInt(u64),
+    Bool(bool),
+    MacAddress(MacAddress),
+    Ipv4Address(Ipv4Addr),
+    Ipv6Address(Ipv6Addr),
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldValue::Protocol => Ok(()),
+            FieldValue::UInt(value) => write!(f, "{}", value),
+            FieldValue::Bool(value) => f.write_str(if *value { "True" } else { "False" }),
+            FieldValue::MacAddress(value) => write!(f, "{}", value),
+            FieldValue::Ipv4Address(value) => write!(f, "{}", value),
+            FieldValue::Ipv6Address(value) => write!(f, "{}", value),
+            FieldValue::Text(value) => f.write_str(value),
+            FieldValue::Bytes(value) => {
+                let hex: Vec<_> = value.iter().map(|byte| format!("{:02x}", byte)).collect();
+                f.write_str(&hex.join(":"))
+            }
+        }
+    }
+}
+
+/// Field Values
+/// Every field occurrence of a packet in dissection order. A field may occur
+/// several times, e.g. `ip.addr` or `dns.qry.name`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FieldValues {
+    values: Vec<(&'static str, FieldValue)>,
+}
+
+impl FieldValues {
+    pub fn push(&mut self, name: &'static str, value: FieldValue) {
+        self.values.push((name, value));
+    }
+
+    /// All occurrences of the field `name`.
+    pub fn get<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a FieldValue> + 'a {
+        self.values
+            .iter()
+            .filter(move |(field, _)| *field == name)
+            .map(|(_, value)| value)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).next().is_some()
+    }
+
+    /// Renders all occurrences of `name` separated by commas, like a Wireshark custom column.
+    pub fn display(&self, name: &str) -> String {
+        let values: Vec<_> = self.get(name).map(|value| value.to_string()).collect();
+        values.join(",")
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(&'static str, FieldValue)> {
+        self.values.iter()
+    }
+
+    /// Groups the fields under the protocol entry preceding them, giving the
+    /// dissection tree of the packet as exposed to scripting bindings.
+    pub fn tree(&self) -> Vec<ProtocolNode> {
+        let mut tree: Vec<ProtocolNode> = Vec::new();
+        for (name, value) in &self.values {
+            let is_protocol = *value == FieldValue::Protocol;
+            if is_protocol || tree.is_empty() {
+                tree.push(ProtocolNode {
+                    protocol: name.split('.').next().unwrap_or(name).to_string(),
+                    fields: Vec::new(),
+                });
+            }
+            if !is_protocol && let Some(node) = tree.last_mut() {
+                node.fields.push(FieldNode {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                });
+            }
+        }
+        tree
+    }
+}
+
+/// Protocol Node
+/// One protocol of a dissection tree with the fields it extracted, in order.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProtocolNode {
+    pub protocol: String,
+    pub fields: Vec<FieldNode>,
+}
+
+/// Field Node
+/// A field occurrence of a dissection tree, rendered as display text.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldNode {
+    pub name: String,
+    pub value: String,
+}
+
+/// Packet Layers
+/// A packet decoded once, layer by layer, and shared by every dissector while
+/// extracting field values.
+pub struct PacketLayers<'a> {
+    /// Frame number within its capture, starting at 1.
+    pub number: usize,
+    pub packet: &'a PcapPacket,
+    pub ethernet: Option<EthernetPacket>,
+    pub arp: Option<ArpPacket>,
+    pub ipv4: Option<IPv4Packet>,
+    pub ipv6: Option<IPv6Packet>,
+    pub icmp: Option<IcmpMessage>,
+    pub icmpv6: Option<IcmpMessage>,
+    pub tcp: Option<TcpSegment>,
+    pub udp: Option<UdpDatagram>,
+    pub udplite: Option<UdpLiteDatagram>,
+    pub dccp: Option<DccpPacket>,
+    pub dns: Option<DnsMessage>,
+    pub dhcp: Option<DhcpMessage>,
+    pub ptp: Option<PtpMessage>,
+    /// SOME/IP message of a UDP datagram no other protocol claimed.
+    pub someip: Option<SomeIpMessage>,
+    /// TZSP header of a streamed packet; the other layers describe its inner frame.
+    pub tzsp: Option<TzspPacket>,
+    /// ERSPAN header of a mirrored packet; the other layers describe its inner frame.
+    pub erspan: Option<ErspanPacket>,
+    /// CAPWAP header; for 802.3 data frames the other layers describe the tunneled frame.
+    pub capwap: Option<CapwapPacket>,
+    /// Link-layer frame of loopback, raw IP and Linux cooked captures, which
+    /// have no Ethernet header.
+    pub link: Option<LinkFrame>,
+    /// Link type the frame was decoded as.
+    pub link_type: u32,
+}
+
+impl<'a> PacketLayers<'a> {
+    /// Decodes an Ethernet frame as far as the known protocols go. Ethernet
+    /// frames streamed over TZSP, mirrored over ERSPAN or tunneled over CAPWAP
+    /// are decoded in place of the outer packet.
+    pub fn decode(number: usize, packet: &'a PcapPacket) -> Self {
+        Self::decode_frame(number, packet, &packet.data)
+    }
+
+    /// Decodes a frame of the given link type, or of its own link type if the
+    /// packet carries one. Frames of other link types than Ethernet start from
+    /// their network-layer payload; frames of unsupported link types decode to
+    /// no layers at all.
+    pub fn decode_link(number: usize, link_type: u32, packet: &'a PcapPacket) -> Self {
+        let link_type = packet.link_type(link_type);
+        if link_type == LINKTYPE_ETHERNET {
+            return Self::decode(number, packet);
+        }
+        let link = LinkFrame::decode(link_type, &packet.data).ok();
+        Self::decode_layers(number, packet, link_type, None, link)
+    }
+
+    fn decode_frame(number: usize, packet: &'a PcapPacket, frame: &[u8]) -> Self {
+        let ethernet = EthernetPacket::try_from(frame).ok();
+        Self::decode_layers(number, packet, LINKTYPE_ETHERNET, ethernet, None)
+    }
+
+    fn decode_layers(
+        number: usize,
+        packet: &'a PcapPacket,
+        link_type: u32,
+        ethernet: Option<EthernetPacket>,
+        link: Option<LinkFrame>,
+    ) -> Self {
+        let network = match (&ethernet, &link) {
+            (Some(eth), _) => Some((eth.header.ether_type, eth.data.as_slice())),
+            (_, Some(link)) => Some((link.ether_type, link.payload.as_slice())),
+            _ => None,
+        };
+        let arp = network
+            .filter(|(ether_type, _)| *ether_type == EtherType::ARP)
+            .and_then(|(_, payload)| ArpPacket::try_from(payload).ok());
+        let ipv4 = network
+            .filter(|(ether_type, _)| *ether_type == EtherType::IPv4)
+            .and_then(|(_, payload)| IPv4Packet::try_from(payload).ok());
+        let ipv6 = network
+            .filter(|(ether_type, _)| *ether_type == EtherType::IPv6)
+            .and_then(|(_, payload)| IPv6Packet::try_from(payload).ok());
+        let erspan = ipv4
+            .as_ref()
+            .filter(|ip| ip.protocol == IP_PROTOCOL_GRE)
+            .and_then(|ip| ErspanPacket::from_gre(&ip.payload).ok());
+        if let Some(erspan) = erspan {
+            let mut inner = Self::decode_frame(number, packet, &erspan.frame);
+            inner.erspan = Some(erspan);
+            return inner;
+        }
+        let protocol = ipv4.as_ref().map(|ip| IpProtocol::from(ip.protocol));
+        let icmp = match (&ipv4, protocol) {
+            (Some(ip), Some(IpProtocol::ICMP)) => IcmpMessage::try_from(ip.payload.as_slice())
+                .ok()
+                .map(|icmp| IcmpMessage {
+                    checksum_valid: ip.validate_payload_checksum(),
+                    ..icmp
+                }),
+            _ => None,
+        };
+        let icmpv6 = ipv6
+            .as_ref()
+            .filter(|ip| ip.next_header == ICMPV6)
+            .and_then(|ip| {
+                IcmpMessage::from_icmpv6(&ip.payload)
+                    .ok()
+                    .map(|icmp| IcmpMessage {
+                        checksum_valid: ip.validate_payload_checksum(),
+                        ..icmp
+                    })
+            });
+        let tcp = match (&ipv4, protocol) {
+            (Some(ip), Some(IpProtocol::TCP)) => TcpSegment::try_from(ip.payload.as_slice())
+                .ok()
+                .map(|tcp| TcpSegment {
+                    checksum_valid: ip.validate_payload_checksum(),
+                    ..tcp
+                }),
+            _ => ipv6
+                .as_ref()
+                .filter(|ip| IpProtocol::from(ip.next_header) == IpProtocol::TCP)
+                .and_then(|ip| {
+                    TcpSegment::try_from(ip.payload.as_slice())
+                        .ok()
+                        .map(|tcp| TcpSegment {
+                            checksum_valid: ip.validate_payload_checksum(),
+                            ..tcp
+                        })
+                }),
+        };
+        let udp = match (&ipv4, protocol) {
+            (Some(ip), Some(IpProtocol::UDP)) => UdpDatagram::try_from(ip.payload.as_slice())
+                .ok()
+                .map(|udp| UdpDatagram {
+                    checksum_valid: ip.validate_payload_checksum(),
+                    ..udp
+                }),
+            _ => None,
+        };
+        let udplite = match (&ipv4, protocol) {
+            (Some(ip), Some(IpProtocol::UDPLite)) => {
+                UdpLiteDatagram::try_from(ip.payload.as_slice()).ok()
+            }
+            _ => None,
+        };
+        let dccp = match (&ipv4, protocol) {
+            (Some(ip), Some(IpProtocol::DCCP)) => DccpPacket::try_from(ip.payload.as_slice()).ok(),
+            _ => None,
+        };
+        let tzsp = udp
+            .as_ref()
+            .filter(|udp| udp.source_port == TZSP_PORT || udp.dest_port == TZSP_PORT)
+            .and_then(|udp| TzspPacket::try_from(udp.payload.as_slice()).ok());
+        if let Some(tzsp) = tzsp.filter(|tzsp| tzsp.link_type() == Some(1)) {
+            let mut inner = Self::decode_frame(number, packet, &tzsp.frame);
+            inner.tzsp = Some(tzsp);
+            return inner;
+        }
+        let capwap = udp
+            .as_ref()
+            .filter(|udp| {
+                [CAPWAP_CONTROL_PORT, CAPWAP_DATA_PORT].contains(&udp.source_port)
+                    || [CAPWAP_CONTROL_PORT, CAPWAP_DATA_PORT].contains(&udp.dest_port)
+            })
+            .and_then(|udp| CapwapPacket::try_from(udp.payload.as_slice()).ok());
+        if let Some(capwap) = capwap
+            .as_ref()
+            .filter(|capwap| capwap.link_type() == Some(1))
+        {
+            let mut inner = Self::decode_frame(number, packet, &capwap.payload);
+            inner.capwap = Some(capwap.clone());
+            return inner;
+        }
+        let dns = udp
+            .as_ref()
+            .filter(|udp| udp.source_port == DNS_PORT || udp.dest_port == DNS_PORT)
+            .and_then(|udp| DnsMessage::try_from(udp.payload.as_slice()).ok())
+            .or_else(|| {
+                tcp.as_ref()
+                    .filter(|tcp| tcp.source_port == DNS_PORT || tcp.dest_port == DNS_PORT)
+                    .and_then(|tcp| DnsMessage::from_tcp(&tcp.payload).ok())
+            });
+        let ptp = match (network, &udp) {
+            (Some((ether_type, payload)), _)
+                if ether_type == EtherType::Unknown(ETHERTYPE_PTP) =>
+            {
+                PtpMessage::try_from(payload).ok()
+            }
+            (_, Some(udp)) if [PTP_EVENT_PORT, PTP_GENERAL_PORT].contains(&udp.dest_port) => {
+                PtpMessage::try_from(udp.payload.as_slice()).ok()
+            }
+            _ => None,
+        };
+        let dhcp = udp
+            .as_ref()
+            .filter(|udp| {
+                [DHCP_SERVER_PORT, DHCP_CLIENT_PORT].contains(&udp.source_port)
+                    && [DHCP_SERVER_PORT, DHCP_CLIENT_PORT].contains(&udp.dest_port)
+            })
+            .and_then(|udp| DhcpMessage::try_from(udp.payload.as_slice()).ok());
+        let someip = udp
+            .as_ref()
+            .filter(|_| dns.is_none() && ptp.is_none() && dhcp.is_none() && capwap.is_none())
+            .and_then(SomeIpMessage::detect);
+        PacketLayers {
+            number,
+            packet,
+            ethernet,
+            arp,
+            ipv4,
+            ipv6,
+            icmp,
+            icmpv6,
+            tcp,
+            udp,
+            udplite,
+            dccp,
+            dns,
+            dhcp,
+            ptp,
+            someip,
+            tzsp: None,
+            erspan: None,
+            capwap,
+            link,
+            link_type,
+        }
+    }
+}
+
+/// Dissector
+/// A protocol decoder registered with the `DissectorRegistry`.
+pub trait Dissector: Send + Sync {
+    /// Short protocol name, also used as the prefix of its fields (e.g. `ip`).
+    fn protocol(&self) -> &'static str;
+
+    /// Human readable protocol name.
+    fn description(&self) -> &'static str;
+
+    /// Fields this dissector can produce, excluding the protocol itself.
+    fn fields(&self) -> &'static [FieldInfo];
+
+    /// Appends the protocol entry and the field values found in `layers`.
+    /// Does nothing if the protocol is not present in the packet.
+    fn dissect(&self, layers: &PacketLayers, values: &mut FieldValues);
+}
+
+/// Frame Dissector
+/// Pseudo-protocol carrying capture metadata of every packet.
+pub struct FrameDissector;
+
+const FRAME_FIELDS: &[FieldInfo] = &[
+    FieldInfo::new("frame.number", FieldType::UInt, "Frame number, starting at 1"),
+    FieldInfo::new("frame.len", FieldType::UInt, "Frame length on the wire"),
+    FieldInfo::new("frame.cap_len", FieldType::UInt, "Frame length stored in the capture"),
+    FieldInfo::new("frame.time_epoch", FieldType::UInt, "Capture time in seconds since the Unix epoch"),
+];
+
+impl Dissector for FrameDissector {
+    fn protocol(&self) -> &'static str {
+        "frame"
+    }
+
+    fn description(&self) -> &'static str {
+        "Frame"
+    }
+
+    fn fields(&self) -> &'static [FieldInfo] {
+        FRAME_FIELDS
+    }
+
+    fn dissect(&self, layers: &PacketLayers, values: &mut FieldValues) {
+        let header = &layers.packet.header;
+        values.push("frame", FieldValue::Protocol);
+        values.push("frame.number", FieldValue::UInt(layers.number as u64));
+        values.push("frame.len", FieldValue::UInt(header.orig_len.into()));
+        values.push("frame.cap_len", FieldValue::UInt(header.incl_len.into()));
+        values.push("frame.time_epoch", FieldValue::UInt(header.timestamp.sec.into()));
+    }
+}
+
+/// Dissector Registry
+/// Holds every known dissector so that field metadata can be looked up by name.
+pub struct DissectorRegistry {
+    dissectors: Vec<Box<dyn Dissector>>,
+}
+
+impl Default for DissectorRegistry {
+    fn default() -> Self {
+        let mut registry = DissectorRegistry::empty();
+        registry.register(FrameDissector);
+        registry.register(crate::packet::EthernetDissector);
+        registry.register(crate::packet::VlanDissector);
+        registry.register(crate::packet::IPv4Dissector);
+        registry.register(crate::icmp::IcmpDissector);
+        registry.register(crate::packet::TcpDissector);
+        registry.register(crate::packet::UdpDissector);
+        registry.register(crate::udplite::UdpLiteDissector);
+        registry.register(crate::dccp::DccpDissector);
+        registry.register(crate::dns::DnsDissector);
+        registry.register(crate::dhcp::DhcpDissector);
+        registry.register(crate::ptp::PtpDissector);
+        registry.register(crate::someip::SomeIpDissector);
+        registry.register(crate::someip::SomeIpSdDissector);
+        registry.register(crate::tzsp::TzspDissector);
+        registry.register(crate::erspan::ErspanDissector);
+        registry.register(crate::capwap::CapwapDissector);
+        registry
+    }
+}
+
+impl DissectorRegistry {
+    /// Creates a registry without any dissectors.
+    pub fn empty() -> Self {
+        DissectorRegistry {
+            dissectors: Vec::new(),
+        }
+    }
+
+    pub fn register<D: Dissector + 'static>(&mut self, dissector: D) {
+        self.dissectors.push(Box::new(dissector));
+    }
+
+    pub fn dissectors(&self) -> impl Iterator<Item = &dyn Dissector> {
+        self.dissectors.iter().map(|d| d.as_ref())
+    }
+
+    /// Lists every filterable field, including one protocol entry per dissector.
+    pub fn fields(&self) -> Vec<FieldInfo> {
+        self.dissectors()
+            .flat_map(|d| {
+                let protocol =
+                    FieldInfo::new(d.protocol(), FieldType::Protocol, d.description());
+                std::iter::once(protocol).chain(d.fields().iter().copied())
+            })
+            .collect()
+    }
+
+    /// Looks up a single field (or protocol) by its filter name.
+    pub fn field(&self, name: &str) -> Option<FieldInfo> {
+        self.fields().into_iter().find(|field| field.name == name)
+    }
+
+    /// Extracts every field value of a packet.
+    pub fn dissect(&self, layers: &PacketLayers) -> FieldValues {
+        let mut values = FieldValues::default();
+        for dissector in self.dissectors() {
+            dissector.dissect(layers, &mut values);
+        }
+        values
+    }
+}
+
+/// Dissection trees of every packet of a libpcap file held in memory, the
+/// byte-slice entry point for viewers without filesystem access.
+pub fn dissect_pcap_bytes(data: &[u8]) -> Result<Vec<Vec<ProtocolNode>>, KcpdumpError> {
+    let (header, packets) = crate::cap::parse_pcap_bytes(data)?;
+    let registry = DissectorRegistry::default();
+    Ok(packets
+        .iter()
+        .enumerate()
+        .map(|(index, packet)| {
+            registry
+                .dissect(&PacketLayers::decode_link(index + 1, header.network, packet))
+                .tree()
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_lists_fields() {
+        let registry = DissectorRegistry::default();
+        let fields = registry.fields();
+        assert!(fields.iter().any(|f| f.name == "ip.src"));
+        assert_eq!(registry.field("eth").unwrap().field_type, FieldType::Protocol);
+        assert_eq!(
+            registry.field("ip.ttl").unwrap().field_type,
+            FieldType::UInt
+        );
+        assert!(registry.field("nonexistent.field").is_none());
+    }
+
+    #[test]
+    fn test_field_names_are_prefixed() {
+        let registry = DissectorRegistry::default();
+        for dissector in registry.dissectors() {
+            for field in dissector.fields() {
+                assert!(field.name.starts_with(&format!("{}.", dissector.protocol())));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dissect_sample_fields() {
+        let mut capture = crate::cap::Capture::from_file("sample.pcap").await.unwrap();
+        let registry = DissectorRegistry::default();
+        let mut number = 0;
+        let mut query_names = Vec::new();
+        let mut saw_tcp = false;
+        while let Some(packet) = capture.next_packet().await.unwrap() {
+            number += 1;
+            let values = registry.dissect(&PacketLayers::decode(number, &packet));
+            assert_eq!(values.display("frame.number"), number.to_string());
+            for (name, _) in values.iter() {
+                assert!(registry.field(name).is_some(), "unregistered field {}", name);
+            }
+            if values.contains("dns") {
+                query_names.push(values.display("dns.qry.name"));
+            }
+            if values.contains("tcp") {
+                saw_tcp = true;
+                assert!(!values.display("tcp.window_size").is_empty());
+                assert_eq!(values.get("tcp.port").count(), 2);
+            }
+        }
+        assert!(!query_names.is_empty());
+        assert!(query_names.iter().all(|name| !name.is_empty()));
+        assert!(saw_tcp);
+    }
+
+    #[tokio::test]
+    async fn test_dissect_pcap_bytes() {
+        let data = tokio::fs::read("sample.pcap").await.unwrap();
+        let trees = dissect_pcap_bytes(&data).unwrap();
+        assert_eq!(trees.len(), 14);
+        assert!(trees.iter().all(|tree| tree[0].protocol == "frame"));
+        assert!(trees.iter().any(|tree| tree.iter().any(|node| node.protocol == "dns")));
+    }
+
+    #[test]
+    fn test_field_value_display() {
+        assert_eq!(FieldValue::UInt(42).to_string(), "42");
+        assert_eq!(FieldValue::Bool(true).to_string(), "True");
+        assert_eq!(FieldValue::Bytes(vec![0xde, 0xad]).to_string(), "de:ad");
+        let mut values = FieldValues::default();
+        values.push("ip.addr", FieldValue::Ipv4Address(Ipv4Addr::new(10, 0, 0, 1)));
+        values.push("ip.addr", FieldValue::Ipv4Address(Ipv4Addr::new(10, 0, 0, 2)));
+        assert_eq!(values.display("ip.addr"), "10.0.0.1,10.0.0.2");
+        assert_eq!(values.display("ip.ttl"), "");
+    }
+
+    #[test]
+    fn test_field_tree() {
+        let mut values = FieldValues::default();
+        values.push("frame", FieldValue::Protocol);
+        values.push("frame.number", FieldValue::UInt(1));
+        values.push("udp", FieldValue::Protocol);
+        values.push("udp.srcport", FieldValue::UInt(53));
+        values.push("udp.dstport", FieldValue::UInt(53000));
+        let tree = values.tree();
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree[0].protocol, "frame");
+        assert_eq!(tree[0].fields.len(), 1);
+        assert_eq!(tree[1].protocol, "udp");
+        assert_eq!(
+            tree[1].fields[1],
+            FieldNode {
+                name: "udp.dstport".to_string(),
+                value: "53000".to_string(),
+            }
+        );
+    }
+}
@@ -0,0 +1,654 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::dissect::{Dissector, FieldInfo, FieldType, FieldValue, FieldValues, PacketLayers};
+use crate::error::KcpdumpError;
+
+/// Well-known DNS server port.
+pub const DNS_PORT: u16 = 53;
+
+/// Upper bound on compression pointers followed while reading one name.
+const MAX_POINTERS: usize = 32;
+
+/// DNS Question
+#[derive(Debug, Clone, PartialEq)]
+pub struct DnsQuestion {
+    pub name: String,
+    pub record_type: u16,
+    pub class: u16,
+}
+
+/// DNS Record Data
+/// Decoded resource record data for the common record types.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DnsRecordData {
+    A(Ipv4Addr),
+    Aaaa(Ipv6Addr),
+    /// CNAME, NS and PTR targets.
+    Name(String),
+    Other(Vec<u8>),
+}
+
+impl fmt::Display for DnsRecordData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DnsRecordData::A(address) => write!(f, "{}", address),
+            DnsRecordData::Aaaa(address) => write!(f, "{}", address),
+            DnsRecordData::Name(name) => f.write_str(name),
+            DnsRecordData::Other(data) => {
+                data.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+            }
+        }
+    }
+}
+
+/// DNS Record
+#[derive(Debug, Clone, PartialEq)]
+pub struct DnsRecord {
+    pub name: String,
+    pub record_type: u16,
+    pub class: u16,
+    pub ttl: u32,
+    pub data: DnsRecordData,
+}
+
+/// DNS Message
+/// A DNS query or response as carried in a UDP datagram or, after its
+/// length prefix, in a TCP segment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DnsMessage {
+    pub id: u16,
+    pub flags: u16,
+    pub questions: Vec<DnsQuestion>,
+    pub answers: Vec<DnsRecord>,
+    pub authority_count: u16,
+    pub additional_count: u16,
+    /// Authority records; may hold fewer than `authority_count` if the
+    /// message was cut short.
+    pub authorities: Vec<DnsRecord>,
+    /// Additional records, with the same caveat.
+    pub additionals: Vec<DnsRecord>,
+}
+
+impl DnsMessage {
+    pub fn is_response(&self) -> bool {
+        self.flags & 0x8000 != 0
+    }
+
+    pub fn opcode(&self) -> u8 {
+        ((self.flags >> 11) & 0x0f) as u8
+    }
+
+    pub fn rcode(&self) -> u8 {
+        (self.flags & 0x000f) as u8
+    }
+
+    pub fn is_authoritative(&self) -> bool {
+        self.flags & 0x0400 != 0
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.flags & 0x0200 != 0
+    }
+
+    pub fn recursion_desired(&self) -> bool {
+        self.flags & 0x0100 != 0
+    }
+
+    pub fn recursion_available(&self) -> bool {
+        self.flags & 0x0080 != 0
+    }
+
+    /// Parses the first message of a TCP segment, which is preceded by a
+    /// two-byte length.
+    pub fn from_tcp(data: &[u8]) -> Result<Self, KcpdumpError> {
+        let length = data.get(..2).ok_or(KcpdumpError::malformed(
+            0,
+            "Data too short for DNS over TCP",
+        ))?;
+        let length = BigEndian::read_u16(length) as usize;
+        let message = data
+            .get(2..2 + length)
+            .ok_or(KcpdumpError::malformed(2, "DNS over TCP message truncated"))?;
+        Self::try_from(message)
+    }
+}
+
+impl TryFrom<&[u8]> for DnsMessage {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 12 {
+            return Err(KcpdumpError::malformed(0, "Data too short for DNS message"));
+        }
+        let question_count = BigEndian::read_u16(&data[4..6]);
+        let answer_count = BigEndian::read_u16(&data[6..8]);
+
+        let mut offset = 12;
+        let mut questions = Vec::new();
+        for _ in 0..question_count {
+            let (name, next) = read_name(data, offset)?;
+            let fixed = data
+                .get(next..next + 4)
+                .ok_or(KcpdumpError::malformed(next, "DNS question truncated"))?;
+            questions.push(DnsQuestion {
+                name,
+                record_type: BigEndian::read_u16(&fixed[0..2]),
+                class: BigEndian::read_u16(&fixed[2..4]),
+            });
+            offset = next + 4;
+        }
+
+        let mut answers = Vec::new();
+        for _ in 0..answer_count {
+            let (record, next) = read_record(data, offset)?;
+            answers.push(record);
+            offset = next;
+        }
+
+        // The remaining sections are informational, so a message cut short
+        // within them still parses with the records read so far.
+        let authority_count = BigEndian::read_u16(&data[8..10]);
+        let additional_count = BigEndian::read_u16(&data[10..12]);
+        let mut authorities = Vec::new();
+        let mut additionals = Vec::new();
+        for index in 0..usize::from(authority_count) + usize::from(additional_count) {
+            let Ok((record, next)) = read_record(data, offset) else {
+                break;
+            };
+            if index < usize::from(authority_count) {
+                authorities.push(record);
+            } else {
+                additionals.push(record);
+            }
+            offset = next;
+        }
+
+        Ok(DnsMessage {
+            id: BigEndian::read_u16(&data[0..2]),
+            flags: BigEndian::read_u16(&data[2..4]),
+            questions,
+            answers,
+            authority_count,
+            additional_count,
+            authorities,
+            additionals,
+        })
+    }
+}
+
+fn read_record(data: &[u8], offset: usize) -> Result<(DnsRecord, usize), KcpdumpError> {
+    let (name, next) = read_name(data, offset)?;
+    let fixed = data
+        .get(next..next + 10)
+        .ok_or(KcpdumpError::malformed(next, "DNS record truncated"))?;
+    let record_type = BigEndian::read_u16(&fixed[0..2]);
+    let rdlength = BigEndian::read_u16(&fixed[8..10]) as usize;
+    let start = next + 10;
+    let rdata = data
+        .get(start..start + rdlength)
+        .ok_or(KcpdumpError::malformed(start, "DNS record data truncated"))?;
+    let data = match (record_type, rdata.len()) {
+        (1, 4) => DnsRecordData::A(Ipv4Addr::new(rdata[0], rdata[1], rdata[2], rdata[3])),
+        (28, 16) => {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(rdata);
+            DnsRecordData::Aaaa(Ipv6Addr::from(octets))
+        }
+        (2 | 5 | 12, _) => DnsRecordData::Name(read_name(data, start)?.0),
+        _ => DnsRecordData::Other(rdata.to_vec()),
+    };
+    Ok((
+        DnsRecord {
+            name,
+            record_type,
+            class: BigEndian::read_u16(&fixed[2..4]),
+            ttl: BigEndian::read_u32(&fixed[4..8]),
+            data,
+        },
+        start + rdlength,
+    ))
+}
+
+/// Reads a possibly compressed domain name, returning it and the offset after it.
+fn read_name(data: &[u8], offset: usize) -> Result<(String, usize), KcpdumpError> {
+    let mut labels = Vec::new();
+    let mut position = offset;
+    let mut end = None;
+    let mut pointers = 0;
+
+    loop {
+        let length = *data
+            .get(position)
+            .ok_or(KcpdumpError::malformed(position, "DNS name truncated"))?
+            as usize;
+        match length {
+            0 => break,
+            _ if length & 0xc0 == 0xc0 => {
+                let low = *data
+                    .get(position + 1)
+                    .ok_or(KcpdumpError::malformed(position + 1, "DNS name truncated"))?
+                    as usize;
+                end.get_or_insert(position + 2);
+                pointers += 1;
+                if pointers > MAX_POINTERS {
+                    return Err(KcpdumpError::malformed(
+                        position,
+                        "DNS name compression loop",
+                    ));
+                }
+                position = ((length & 0x3f) << 8) | low;
+            }
+            _ => {
+                let label = data
+                    .get(position + 1..position + 1 + length)
+                    .ok_or(KcpdumpError::malformed(position + 1, "DNS label truncated"))?;
+                labels.push(String::from_utf8_lossy(label).into_owned());
+                position += 1 + length;
+            }
+        }
+    }
+
+    let name = if labels.is_empty() {
+        "<Root>".to_string()
+    } else {
+        labels.join(".")
+    };
+    Ok((name, end.unwrap_or(position + 1)))
+}
+
+/// Returns the mnemonic of a DNS record type.
+pub fn record_type_name(record_type: u16) -> String {
+    match record_type {
+        1 => "A".to_string(),
+        2 => "NS".to_string(),
+        5 => "CNAME".to_string(),
+        6 => "SOA".to_string(),
+        12 => "PTR".to_string(),
+        15 => "MX".to_string(),
+        16 => "TXT".to_string(),
+        28 => "AAAA".to_string(),
+        33 => "SRV".to_string(),
+        41 => "OPT".to_string(),
+        64 => "SVCB".to_string(),
+        65 => "HTTPS".to_string(),
+        255 => "ANY".to_string(),
+        other => format!("TYPE{}", other),
+    }
+}
+
+/// Returns the mnemonic of a DNS response code.
+pub fn rcode_name(rcode: u8) -> String {
+    match rcode {
+        0 => "NOERROR".to_string(),
+        1 => "FORMERR".to_string(),
+        2 => "SERVFAIL".to_string(),
+        3 => "NXDOMAIN".to_string(),
+        4 => "NOTIMP".to_string(),
+        5 => "REFUSED".to_string(),
+        other => format!("RCODE{}", other),
+    }
+}
+
+/// DNS Answer
+/// A resource record of a response, rendered for display.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DnsAnswer {
+    pub name: String,
+    pub record_type: String,
+    pub ttl: u32,
+    pub data: String,
+}
+
+impl From<&DnsRecord> for DnsAnswer {
+    fn from(record: &DnsRecord) -> Self {
+        DnsAnswer {
+            name: record.name.clone(),
+            record_type: record_type_name(record.record_type),
+            ttl: record.ttl,
+            data: record.data.to_string(),
+        }
+    }
+}
+
+/// DNS Transaction
+/// A query with the response that answered it. Either side may be missing
+/// when it was not captured.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DnsTransaction {
+    pub id: u16,
+    /// `udp` or `tcp`.
+    pub transport: String,
+    pub client: String,
+    pub server: String,
+    /// Name of the first question.
+    pub name: String,
+    pub query_type: String,
+    pub query_number: Option<usize>,
+    /// Seconds since the epoch.
+    pub query_time: Option<f64>,
+    pub response_number: Option<usize>,
+    pub response_time: Option<f64>,
+    /// Seconds from the query to its response.
+    pub latency: Option<f64>,
+    pub rcode: Option<String>,
+    pub answers: Vec<DnsAnswer>,
+    pub authorities: Vec<DnsAnswer>,
+}
+
+/// Client and server endpoints, transport and transaction id of a query.
+type TransactionKey = (IpAddr, u16, IpAddr, u16, bool, u16);
+
+/// DNS Transaction Builder
+/// Pairs queries and responses fed in frame order. A response answers the
+/// oldest unanswered query with the same endpoints and id.
+#[derive(Debug, Default)]
+pub struct DnsTransactionBuilder {
+    transactions: Vec<DnsTransaction>,
+    unanswered: HashMap<TransactionKey, VecDeque<usize>>,
+}
+
+impl DnsTransactionBuilder {
+    pub fn push(&mut self, layers: &PacketLayers) {
+        let Some(dns) = &layers.dns else {
+            return;
+        };
+        let (source, dest) = match (&layers.ipv4, &layers.ipv6) {
+            (Some(ip), _) => (IpAddr::from(ip.source_ip), IpAddr::from(ip.dest_ip)),
+            (_, Some(ip)) => (IpAddr::from(ip.source_ip), IpAddr::from(ip.dest_ip)),
+            _ => return,
+        };
+        let (source_port, dest_port, tcp) = match (&layers.udp, &layers.tcp) {
+            (Some(udp), _) => (udp.source_port, udp.dest_port, false),
+            (_, Some(tcp)) => (tcp.source_port, tcp.dest_port, true),
+            _ => return,
+        };
+        let header = &layers.packet.header;
+        let time = header.timestamp.as_secs_f64();
+
+        if !dns.is_response() {
+            let key = (source, source_port, dest, dest_port, tcp, dns.id);
+            self.unanswered
+                .entry(key)
+                .or_default()
+                .push_back(self.transactions.len());
+            let question = dns.questions.first();
+            self.transactions.push(DnsTransaction {
+                id: dns.id,
+                transport: if tcp { "tcp" } else { "udp" }.to_string(),
+                client: endpoint(source, source_port),
+                server: endpoint(dest, dest_port),
+                name: question.map(|q| q.name.clone()).unwrap_or_default(),
+                query_type: question
+                    .map(|q| record_type_name(q.record_type))
+                    .unwrap_or_default(),
+                query_number: Some(layers.number),
+                query_time: Some(time),
+                response_number: None,
+                response_time: None,
+                latency: None,
+                rcode: None,
+                answers: Vec::new(),
+                authorities: Vec::new(),
+            });
+            return;
+        }
+
+        let key = (dest, dest_port, source, source_port, tcp, dns.id);
+        let index = self
+            .unanswered
+            .get_mut(&key)
+            .and_then(VecDeque::pop_front);
+        let transaction = match index {
+            Some(index) => &mut self.transactions[index],
+            None => {
+                let question = dns.questions.first();
+                self.transactions.push(DnsTransaction {
+                    id: dns.id,
+                    transport: if tcp { "tcp" } else { "udp" }.to_string(),
+                    client: endpoint(dest, dest_port),
+                    server: endpoint(source, source_port),
+                    name: question.map(|q| q.name.clone()).unwrap_or_default(),
+                    query_type: question
+                        .map(|q| record_type_name(q.record_type))
+                        .unwrap_or_default(),
+                    query_number: None,
+                    query_time: None,
+                    response_number: None,
+                    response_time: None,
+                    latency: None,
+                    rcode: None,
+                    answers: Vec::new(),
+                    authorities: Vec::new(),
+                });
+                self.transactions.last_mut().unwrap()
+            }
+        };
+        transaction.response_number = Some(layers.number);
+        transaction.response_time = Some(time);
+        transaction.latency = transaction.query_time.map(|query_time| time - query_time);
+        transaction.rcode = Some(rcode_name(dns.rcode()));
+        transaction.answers = dns.answers.iter().map(DnsAnswer::from).collect();
+        transaction.authorities = dns.authorities.iter().map(DnsAnswer::from).collect();
+    }
+
+    /// Returns the transactions in order of their first message.
+    pub fn finish(self) -> Vec<DnsTransaction> {
+        self.transactions
+    }
+}
+
+fn endpoint(address: IpAddr, port: u16) -> String {
+    match address {
+        IpAddr::V4(address) => format!("{}:{}", address, port),
+        IpAddr::V6(address) => format!("[{}]:{}", address, port),
+    }
+}
+
+/// DNS Dissector
+/// Registers the DNS fields with the dissector registry.
+pub struct DnsDissector;
+
+const DNS_FIELDS: &[FieldInfo] = &[
+    FieldInfo::new("dns.id", FieldType::UInt, "Transaction ID"),
+    FieldInfo::new("dns.flags", FieldType::UInt, "Flags"),
+    FieldInfo::new("dns.flags.response", FieldType::Bool, "Message is a response"),
+    FieldInfo::new("dns.flags.opcode", FieldType::UInt, "Opcode"),
+    FieldInfo::new("dns.flags.rcode", FieldType::UInt, "Reply code"),
+    FieldInfo::new("dns.count.queries", FieldType::UInt, "Number of questions"),
+    FieldInfo::new("dns.count.answers", FieldType::UInt, "Number of answer records"),
+    FieldInfo::new("dns.qry.name", FieldType::Text, "Queried name"),
+    FieldInfo::new("dns.qry.type", FieldType::UInt, "Queried record type"),
+    FieldInfo::new("dns.resp.name", FieldType::Text, "Answer record name"),
+    FieldInfo::new("dns.resp.ttl", FieldType::UInt, "Answer record time to live"),
+    FieldInfo::new("dns.a", FieldType::Ipv4Address, "IPv4 address in an A record"),
+    FieldInfo::new("dns.aaaa", FieldType::Ipv6Address, "IPv6 address in an AAAA record"),
+    FieldInfo::new("dns.cname", FieldType::Text, "Canonical name in a CNAME record"),
+];
+
+impl Dissector for DnsDissector {
+    fn protocol(&self) -> &'static str {
+        "dns"
+    }
+
+    fn description(&self) -> &'static str {
+        "Domain Name System"
+    }
+
+    fn fields(&self) -> &'static [FieldInfo] {
+        DNS_FIELDS
+    }
+
+    fn dissect(&self, layers: &PacketLayers, values: &mut FieldValues) {
+        let Some(dns) = &layers.dns else {
+            return;
+        };
+        values.push("dns", FieldValue::Protocol);
+        values.push("dns.id", FieldValue::UInt(dns.id.into()));
+        values.push("dns.flags", FieldValue::UInt(dns.flags.into()));
+        values.push("dns.flags.response", FieldValue::Bool(dns.is_response()));
+        values.push("dns.flags.opcode", FieldValue::UInt(dns.opcode().into()));
+        values.push("dns.flags.rcode", FieldValue::UInt(dns.rcode().into()));
+        values.push(
+            "dns.count.queries",
+            FieldValue::UInt(dns.questions.len() as u64),
+        );
+        values.push(
+            "dns.count.answers",
+            FieldValue::UInt(dns.answers.len() as u64),
+        );
+        for question in &dns.questions {
+            values.push("dns.qry.name", FieldValue::Text(question.name.clone()));
+            values.push(
+                "dns.qry.type",
+                FieldValue::UInt(question.record_type.into()),
+            );
+        }
+        for answer in &dns.answers {
+            values.push("dns.resp.name", FieldValue::Text(answer.name.clone()));
+            values.push("dns.resp.ttl", FieldValue::UInt(answer.ttl.into()));
+            match &answer.data {
+                DnsRecordData::A(address) => {
+                    values.push("dns.a", FieldValue::Ipv4Address(*address))
+                }
+                DnsRecordData::Aaaa(address) => {
+                    values.push("dns.aaaa", FieldValue::Ipv6Address(*address))
+                }
+                DnsRecordData::Name(name) if answer.record_type == 5 => {
+                    values.push("dns.cname", FieldValue::Text(name.clone()))
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timefmt::Timestamp;
+
+    /// Response for example.com A with a compressed answer name.
+    const RESPONSE: &[u8] = &[
+        0x12, 0x34, 0x81, 0x80, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, // header
+        0x07, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 0x03, b'c', b'o', b'm', 0x00, // name
+        0x00, 0x01, 0x00, 0x01, // type A, class IN
+        0xc0, 0x0c, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x0e, 0x10, 0x00, 0x04, // answer
+        93, 184, 216, 34,
+    ];
+
+    #[test]
+    fn test_parse_response() {
+        let message = DnsMessage::try_from(RESPONSE).unwrap();
+        assert_eq!(message.id, 0x1234);
+        assert!(message.is_response());
+        assert_eq!(message.rcode(), 0);
+        assert_eq!(message.questions[0].name, "example.com");
+        assert_eq!(record_type_name(message.questions[0].record_type), "A");
+        assert_eq!(message.answers[0].name, "example.com");
+        assert_eq!(message.answers[0].ttl, 3600);
+        assert_eq!(
+            message.answers[0].data,
+            DnsRecordData::A(Ipv4Addr::new(93, 184, 216, 34))
+        );
+    }
+
+    #[test]
+    fn test_compression_loop_is_rejected() {
+        let mut data = RESPONSE[..12].to_vec();
+        data.extend_from_slice(&[0xc0, 0x0c]);
+        assert!(DnsMessage::try_from(data.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_authority_and_additional_records() {
+        let mut data = RESPONSE.to_vec();
+        data[9] = 1; // one authority record
+        data[11] = 2; // two additional records, the second one missing
+        data.extend_from_slice(&[0xc0, 0x0c, 0x00, 0x02, 0x00, 0x01, 0x00, 0x00, 0x00, 0x3c]);
+        data.extend_from_slice(&[0x00, 0x05, 0x02, b'n', b's', 0xc0, 0x0c]);
+        data.extend_from_slice(&[0x00, 0x00, 0x29, 0x10, 0x00, 0, 0, 0, 0, 0x00, 0x00]);
+        let message = DnsMessage::try_from(data.as_slice()).unwrap();
+        assert_eq!(message.authorities.len(), 1);
+        assert_eq!(
+            message.authorities[0].data,
+            DnsRecordData::Name("ns.example.com".to_string())
+        );
+        assert_eq!(message.additional_count, 2);
+        assert_eq!(record_type_name(message.additionals[0].record_type), "OPT");
+        assert_eq!(message.additionals.len(), 1);
+        assert!(message.recursion_desired() && message.recursion_available());
+
+        let mut tcp = (data.len() as u16).to_be_bytes().to_vec();
+        tcp.extend_from_slice(&data);
+        assert_eq!(DnsMessage::from_tcp(&tcp).unwrap(), message);
+        assert!(DnsMessage::from_tcp(&tcp[..20]).is_err());
+    }
+
+    #[test]
+    fn test_transactions() {
+        use crate::cap::{PcapPacket, PcapPacketHeader};
+
+        let frame = |response: bool, ts_usec: u32| {
+            let mut payload = RESPONSE.to_vec();
+            let (source, dest, ports) = if response {
+                ([8, 8, 8, 8], [10, 0, 0, 1], [0, 53, 0xc0, 0x00])
+            } else {
+                payload.truncate(29);
+                payload[2] = 0x01;
+                payload[3] = 0x00;
+                payload[7] = 0;
+                ([10, 0, 0, 1], [8, 8, 8, 8], [0xc0, 0x00, 0, 53])
+            };
+            let mut data = vec![0; 12];
+            data.extend_from_slice(&[0x08, 0x00, 0x45, 0]);
+            data.extend_from_slice(&((28 + payload.len()) as u16).to_be_bytes());
+            data.extend_from_slice(&[0, 0, 0, 0, 64, 17, 0, 0]);
+            data.extend_from_slice(&source);
+            data.extend_from_slice(&dest);
+            data.extend_from_slice(&ports);
+            data.extend_from_slice(&((8 + payload.len()) as u16).to_be_bytes());
+            data.extend_from_slice(&[0, 0]);
+            data.extend_from_slice(&payload);
+            PcapPacket {
+                header: PcapPacketHeader {
+                    timestamp: Timestamp::from_micros(1, ts_usec),
+                    incl_len: data.len() as u32,
+                    orig_len: data.len() as u32,
+                    link_type: None,
+                },
+                data,
+            }
+        };
+        let packets = [frame(false, 0), frame(true, 20_000), frame(true, 30_000)];
+        let mut builder = DnsTransactionBuilder::default();
+        for (index, packet) in packets.iter().enumerate() {
+            builder.push(&PacketLayers::decode(index + 1, packet));
+        }
+        let transactions = builder.finish();
+        assert_eq!(transactions.len(), 2);
+        let first = &transactions[0];
+        assert_eq!(first.client, "10.0.0.1:49152");
+        assert_eq!(first.server, "8.8.8.8:53");
+        assert_eq!(first.name, "example.com");
+        assert_eq!(first.query_type, "A");
+        assert_eq!(first.response_number, Some(2));
+        assert!((first.latency.unwrap() - 0.02).abs() < 1e-9);
+        assert_eq!(first.rcode.as_deref(), Some("NOERROR"));
+        assert_eq!(first.answers[0].data, "93.184.216.34");
+        assert_eq!(first.answers[0].ttl, 3600);
+        // A response without a captured query still shows up.
+        assert_eq!(transactions[1].query_number, None);
+        assert_eq!(transactions[1].response_number, Some(3));
+    }
+
+    #[test]
+    fn test_truncated_message() {
+        assert!(DnsMessage::try_from(&RESPONSE[..20]).is_err());
+    }
+}
@@ -0,0 +1,321 @@
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
+
+use chrono::{DateTime, SecondsFormat};
+use serde_json::{Value, json};
+
+use crate::cap::PcapPacket;
+use crate::dissect::PacketLayers;
+use crate::dns::{self, DnsRecordData};
+use crate::error::KcpdumpError;
+use crate::flows;
+use crate::http;
+use crate::packetlist;
+use crate::reassembly::StreamReassembler;
+use crate::session::{CaptureId, LoadedCapture};
+use crate::tls::ClientHello;
+
+/// ECS version the documents conform to.
+pub const ECS_VERSION: &str = "8.11.0";
+
+/// Formats seconds since the epoch as an ECS `@timestamp`.
+fn timestamp(seconds: f64) -> String {
+    let nanos = (seconds.fract() * 1e9).round() as u32;
+    DateTime::from_timestamp(seconds.trunc() as i64, nanos.min(999_999_999))
+        .unwrap_or_default()
+        .to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+fn packet_time(packet: &PcapPacket) -> f64 {
+    packet.header.timestamp.as_secs_f64()
+}
+
+/// ECS `network.transport` name of an IP protocol number.
+fn transport(protocol: u8) -> String {
+    match protocol {
+        1 => "icmp".to_string(),
+        6 => "tcp".to_string(),
+        17 => "udp".to_string(),
+        33 => "dccp".to_string(),
+        132 => "sctp".to_string(),
+        136 => "udplite".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Fields shared by every document.
+fn base(capture_id: CaptureId, time: f64, dataset: &str, category: &[&str], kind: &str) -> Value {
+    json!({
+        "@timestamp": timestamp(time),
+        "ecs": { "version": ECS_VERSION },
+        "event": {
+            "kind": "event",
+            "category": category,
+            "type": [kind],
+            "dataset": dataset,
+            "module": "kcpdump",
+        },
+        "labels": { "capture_id": capture_id.to_string() },
+    })
+}
+
+fn endpoint(address: IpAddr, port: u16) -> Value {
+    json!({ "ip": address.to_string(), "port": port })
+}
+
+/// Merges the fields of `extra` into the document `target`.
+fn merge(target: &mut Value, extra: Value) {
+    if let (Value::Object(target), Value::Object(extra)) = (target, extra) {
+        for (key, value) in extra {
+            match target.get_mut(&key) {
+                Some(existing @ Value::Object(_)) => merge(existing, value),
+                _ => {
+                    target.insert(key, value);
+                }
+            }
+        }
+    }
+}
+
+/// Builds ECS documents for the flows, DNS messages, HTTP transactions and
+/// TLS handshakes of the captures.
+pub fn ecs_documents(captures: &[Arc<LoadedCapture>]) -> Result<Vec<Value>, KcpdumpError> {
+    let mut documents = Vec::new();
+
+    for flow in flows::flow_records(captures)? {
+        let mut document = base(
+            flow.capture_id,
+            flow.first_seen,
+            "kcpdump.flow",
+            &["network"],
+            "connection",
+        );
+        merge(
+            &mut document,
+            json!({
+                "event": {
+                    "start": timestamp(flow.first_seen),
+                    "end": timestamp(flow.last_seen),
+                    "duration": ((flow.last_seen - flow.first_seen) * 1e9).round() as u64,
+                },
+                "source": endpoint(flow.key.address_a, flow.key.port_a),
+                "destination": endpoint(flow.key.address_b, flow.key.port_b),
+                "network": {
+                    "transport": transport(flow.key.protocol),
+                    "iana_number": flow.key.protocol.to_string(),
+                    "packets": flow.packets,
+                    "bytes": flow.bytes,
+                },
+            }),
+        );
+        documents.push(document);
+    }
+
+    let link_types = packetlist::link_types(captures);
+    for item in packetlist::merged_packets(captures) {
+        let (capture_id, number, packet) = item?;
+        let layers = PacketLayers::decode_link(number, link_types[&capture_id], &packet);
+        let (Some(message), Some(ip), Some(udp)) = (&layers.dns, &layers.ipv4, &layers.udp) else {
+            continue;
+        };
+        let answers: Vec<Value> = message
+            .answers
+            .iter()
+            .filter_map(|answer| {
+                let data = match &answer.data {
+                    DnsRecordData::A(address) => address.to_string(),
+                    DnsRecordData::Aaaa(address) => address.to_string(),
+                    DnsRecordData::Name(name) => name.clone(),
+                    DnsRecordData::Other(_) => return None,
+                };
+                Some(json!({
+                    "name": answer.name,
+                    "type": dns::record_type_name(answer.record_type),
+                    "ttl": answer.ttl,
+                    "data": data,
+                }))
+            })
+            .collect();
+        let resolved: Vec<String> = message
+            .answers
+            .iter()
+            .filter_map(|answer| match &answer.data {
+                DnsRecordData::A(address) => Some(address.to_string()),
+                DnsRecordData::Aaaa(address) => Some(address.to_string()),
+                _ => None,
+            })
+            .collect();
+        let mut dns_fields = json!({
+            "id": message.id.to_string(),
+            "type": if message.is_response() { "answer" } else { "query" },
+        });
+        if let Some(question) = message.questions.first() {
+            dns_fields["question"] = json!({
+                "name": question.name,
+                "type": dns::record_type_name(question.record_type),
+            });
+        }
+        if message.is_response() {
+            dns_fields["response_code"] = json!(dns::rcode_name(message.rcode()));
+            dns_fields["answers"] = json!(answers);
+            dns_fields["resolved_ip"] = json!(resolved);
+        }
+        let mut document = base(
+            capture_id,
+            packet_time(&packet),
+            "kcpdump.dns",
+            &["network"],
+            "protocol",
+        );
+        merge(
+            &mut document,
+            json!({
+                "source": endpoint(IpAddr::V4(Ipv4Addr::from(ip.source_ip)), udp.source_port),
+                "destination": endpoint(IpAddr::V4(Ipv4Addr::from(ip.dest_ip)), udp.dest_port),
+                "network": { "transport": "udp", "protocol": "dns" },
+                "dns": dns_fields,
+            }),
+        );
+        documents.push(document);
+    }
+
+    for capture in captures {
+        let mut reassembler = StreamReassembler::default();
+        for (index, packet) in capture.packets().enumerate() {
+            let packet = packet?;
+            reassembler.push(&PacketLayers::decode_link(index + 1, capture.header.network, &packet));
+        }
+        for stream in reassembler.finish() {
+            let time = capture
+                .index
+                .get(stream.first_number)
+                .map_or(0.0, |entry| entry.timestamp.as_secs_f64());
+            let endpoints = json!({
+                "source": endpoint(stream.client.0, stream.client.1),
+                "destination": endpoint(stream.server.0, stream.server.1),
+            });
+
+            if let Ok(hello) = ClientHello::try_from(stream.client_data.data.as_slice()) {
+                let mut document = base(capture.id, time, "kcpdump.tls", &["network"], "protocol");
+                merge(&mut document, endpoints.clone());
+                let mut client = json!({
+                    "supported_ciphers": hello
+                        .cipher_suites
+                        .iter()
+                        .map(|suite| format!("0x{:04x}", suite))
+                        .collect::<Vec<_>>(),
+                });
+                if let Some(server_name) = hello.server_name() {
+                    client["server_name"] = json!(server_name);
+                }
+                let mut tls = json!({ "version_protocol": "tls", "client": client });
+                if let Some(protocol) = hello.alpn().into_iter().next() {
+                    tls["next_protocol"] = json!(protocol);
+                }
+                merge(
+                    &mut document,
+                    json!({ "network": { "transport": "tcp", "protocol": "tls" }, "tls": tls }),
+                );
+                documents.push(document);
+            }
+
+            let requests = http::parse_messages(&stream.client_data.data, false);
+            let mut responses = http::parse_messages(&stream.server_data.data, true)
+                .into_iter()
+                .filter(|response| !matches!(response.status(), Some(100..=199)));
+            for request in requests {
+                let response = responses.next();
+                let mut document = base(
+                    capture.id,
+                    time,
+                    "kcpdump.http",
+                    &["network", "web"],
+                    "access",
+                );
+                merge(&mut document, endpoints.clone());
+                let mut http_fields = json!({
+                    "request": { "body": { "bytes": request.body.len() } },
+                });
+                if let Some(method) = request.start_line.split_whitespace().next() {
+                    http_fields["request"]["method"] = json!(method);
+                }
+                if let Some(response) = &response {
+                    http_fields["response"] = json!({ "body": { "bytes": response.body.len() } });
+                    if let Some(status) = response.status() {
+                        http_fields["response"]["status_code"] = json!(status);
+                    }
+                    if let Some(mime_type) = response.header("Content-Type") {
+                        http_fields["response"]["mime_type"] = json!(mime_type);
+                    }
+                }
+                let mut url = json!({});
+                if let Some(uri) = request.uri() {
+                    url["original"] = json!(uri);
+                }
+                if let Some(host) = request.header("Host") {
+                    url["domain"] = json!(host);
+                }
+                merge(
+                    &mut document,
+                    json!({
+                        "network": { "transport": "tcp", "protocol": "http" },
+                        "http": http_fields,
+                        "url": url,
+                    }),
+                );
+                documents.push(document);
+            }
+        }
+    }
+    Ok(documents)
+}
+
+/// Formats documents for the Elasticsearch `_bulk` API, indexing each into
+/// `index`.
+pub fn bulk_ndjson(documents: &[Value], index: &str) -> String {
+    let action = json!({ "index": { "_index": index } }).to_string();
+    let mut out = String::new();
+    for document in documents {
+        out.push_str(&action);
+        out.push('\n');
+        out.push_str(&document.to_string());
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_ecs_documents() {
+        let capture = Arc::new(LoadedCapture::load(1, "sample.pcap").await.unwrap());
+        let documents = ecs_documents(std::slice::from_ref(&capture)).unwrap();
+        let dataset = |document: &Value| document["event"]["dataset"].as_str().unwrap().to_string();
+        let flow = documents
+            .iter()
+            .find(|document| dataset(document) == "kcpdump.flow")
+            .unwrap();
+        assert_eq!(flow["network"]["transport"], "udp");
+        assert_eq!(flow["ecs"]["version"], ECS_VERSION);
+        let query = documents
+            .iter()
+            .find(|document| dataset(document) == "kcpdump.dns")
+            .unwrap();
+        assert_eq!(query["dns"]["type"], "query");
+        assert!(query["dns"]["question"]["name"].is_string());
+        assert_eq!(query["labels"]["capture_id"], "1");
+
+        let bulk = bulk_ndjson(&documents, "packets");
+        assert_eq!(bulk.lines().count(), documents.len() * 2);
+        assert!(bulk.starts_with(r#"{"index":{"_index":"packets"}}"#));
+    }
+
+    #[test]
+    fn test_timestamp() {
+        assert_eq!(timestamp(1_700_000_000.25), "2023-11-14T22:13:20.250000Z");
+        assert_eq!(transport(6), "tcp");
+        assert_eq!(transport(47), "47");
+    }
+}
@@ -0,0 +1,361 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
+
+use crate::dissect::PacketLayers;
+use crate::error::KcpdumpError;
+use crate::flows::FlowKey;
+use crate::packetlist;
+use crate::session::LoadedCapture;
+use crate::tls::ClientHello;
+
+/// Port of DNS over TLS (RFC 7858) and DNS over QUIC (RFC 9250).
+pub const DOT_PORT: u16 = 853;
+const HTTPS_PORT: u16 = 443;
+
+/// Public resolvers that answer DNS over HTTPS on their anycast addresses.
+const KNOWN_RESOLVERS: &[[u8; 4]] = &[
+    [1, 1, 1, 1],
+    [1, 0, 0, 1],
+    [8, 8, 8, 8],
+    [8, 8, 4, 4],
+    [9, 9, 9, 9],
+    [149, 112, 112, 112],
+    [208, 67, 222, 222],
+    [208, 67, 220, 220],
+    [94, 140, 14, 14],
+    [94, 140, 15, 15],
+];
+
+/// Host names of public DoH endpoints; subdomains match as well.
+const DOH_HOSTS: &[&str] = &[
+    "cloudflare-dns.com",
+    "dns.google",
+    "dns.quad9.net",
+    "doh.opendns.com",
+    "dns.adguard-dns.com",
+    "dns.nextdns.io",
+    "doh.cleanbrowsing.org",
+    "dns.mullvad.net",
+];
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EncryptedDnsProtocol {
+    DoH,
+    DoT,
+    DoQ,
+}
+
+/// Encrypted Dns Usage
+/// Traffic of one host to one resolver over an encrypted DNS transport.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptedDnsUsage {
+    pub client: String,
+    pub resolver: String,
+    pub protocol: EncryptedDnsProtocol,
+    /// SNI of the first ClientHello seen, if any.
+    pub server_name: Option<String>,
+    /// Why the traffic was recognised, e.g. `SNI dns.google, ALPN h2`.
+    pub detection: String,
+    pub connections: usize,
+    pub packets: usize,
+    pub bytes: u64,
+    pub first_seen: f64,
+    pub last_seen: f64,
+}
+
+struct Detection {
+    client: Ipv4Addr,
+    resolver: Ipv4Addr,
+    protocol: EncryptedDnsProtocol,
+    reason: String,
+}
+
+#[derive(Default)]
+struct FlowState {
+    detection: Option<Detection>,
+    server_name: Option<String>,
+    packets: usize,
+    bytes: u64,
+    first_seen: f64,
+    last_seen: f64,
+}
+
+/// Returns whether `name` is a known DoH endpoint or one of its subdomains.
+pub fn is_doh_host(name: &str) -> bool {
+    let name = name.trim_end_matches('.').to_ascii_lowercase();
+    DOH_HOSTS.iter().any(|host| {
+        name == *host
+            || name
+                .strip_suffix(host)
+                .is_some_and(|prefix| prefix.ends_with('.'))
+    })
+}
+
+fn is_known_resolver(address: Ipv4Addr) -> bool {
+    KNOWN_RESOLVERS.contains(&address.octets())
+}
+
+/// Recognises a DNS query sent over cleartext HTTP, e.g. to a local proxy.
+fn is_doh_request(payload: &[u8]) -> bool {
+    let Some(line_end) = payload.windows(2).position(|pair| pair == b"\r\n") else {
+        return false;
+    };
+    let line = &payload[..line_end];
+    let is_request = line.starts_with(b"GET ") || line.starts_with(b"POST ");
+    is_request
+        && (line.windows(10).any(|window| window == b"/dns-query")
+            || payload
+                .windows(23)
+                .any(|window| window.eq_ignore_ascii_case(b"application/dns-message")))
+}
+
+fn detect(layers: &PacketLayers, hello: Option<&ClientHello>) -> Option<Detection> {
+    let ip = layers.ipv4.as_ref()?;
+    let source = Ipv4Addr::from(ip.source_ip);
+    let dest = Ipv4Addr::from(ip.dest_ip);
+    let toward_server = |source_port: u16, dest_port: u16, protocol, reason: &str| {
+        let (client, resolver) = if dest_port == DOT_PORT || source_port != DOT_PORT {
+            (source, dest)
+        } else {
+            (dest, source)
+        };
+        Some(Detection {
+            client,
+            resolver,
+            protocol,
+            reason: reason.to_string(),
+        })
+    };
+    if let Some(udp) = &layers.udp {
+        if udp.source_port == DOT_PORT || udp.dest_port == DOT_PORT {
+            return toward_server(
+                udp.source_port,
+                udp.dest_port,
+                EncryptedDnsProtocol::DoQ,
+                "UDP port 853",
+            );
+        }
+        return None;
+    }
+    let tcp = layers.tcp.as_ref()?;
+    if tcp.source_port == DOT_PORT || tcp.dest_port == DOT_PORT {
+        return toward_server(
+            tcp.source_port,
+            tcp.dest_port,
+            EncryptedDnsProtocol::DoT,
+            "TCP port 853",
+        );
+    }
+    let doh = |client, resolver, reason| {
+        Some(Detection {
+            client,
+            resolver,
+            protocol: EncryptedDnsProtocol::DoH,
+            reason,
+        })
+    };
+    if let Some(hello) = hello {
+        let http2 = if hello.alpn().iter().any(|protocol| protocol == "h2") {
+            ", ALPN h2"
+        } else {
+            ""
+        };
+        if let Some(name) = hello.server_name().filter(|name| is_doh_host(name)) {
+            return doh(source, dest, format!("SNI {}{}", name, http2));
+        }
+        if is_known_resolver(dest) {
+            return doh(source, dest, format!("TLS to resolver {}{}", dest, http2));
+        }
+    }
+    if is_doh_request(&tcp.payload) {
+        return doh(source, dest, "HTTP request to /dns-query".to_string());
+    }
+    // Connections already open when the capture started have no ClientHello.
+    match (tcp.dest_port, tcp.source_port) {
+        (HTTPS_PORT, _) if is_known_resolver(dest) => {
+            doh(source, dest, format!("HTTPS to resolver {}", dest))
+        }
+        (_, HTTPS_PORT) if is_known_resolver(source) => {
+            doh(dest, source, format!("HTTPS to resolver {}", source))
+        }
+        _ => None,
+    }
+}
+
+/// Finds connections that likely carry DNS over HTTPS, TLS or QUIC and
+/// totals them per client and resolver, by bytes, descending.
+pub fn encrypted_dns(
+    captures: &[Arc<LoadedCapture>],
+) -> Result<Vec<EncryptedDnsUsage>, KcpdumpError> {
+    let mut flows: HashMap<FlowKey, FlowState> = HashMap::new();
+    let mut order = Vec::new();
+
+    let link_types = packetlist::link_types(captures);
+    for item in packetlist::merged_packets(captures) {
+        let (capture_id, number, packet) = item?;
+        let layers = PacketLayers::decode_link(number, link_types[&capture_id], &packet);
+        let Some(ip) = &layers.ipv4 else {
+            continue;
+        };
+        if layers.tcp.is_none() && layers.udp.is_none() {
+            continue;
+        }
+        let time = packet.header.timestamp.as_secs_f64();
+        let key = FlowKey::from_ipv4(ip);
+        let flow = flows.entry(key).or_insert_with(|| {
+            order.push(key);
+            FlowState {
+                first_seen: time,
+                ..Default::default()
+            }
+        });
+        flow.packets += 1;
+        flow.bytes += u64::from(packet.header.orig_len);
+        flow.last_seen = time;
+
+        let hello = layers
+            .tcp
+            .as_ref()
+            .and_then(|tcp| ClientHello::try_from(tcp.payload.as_slice()).ok());
+        if flow.server_name.is_none() {
+            flow.server_name = hello.as_ref().and_then(ClientHello::server_name);
+        }
+        if flow.detection.is_none() {
+            flow.detection = detect(&layers, hello.as_ref());
+        }
+    }
+
+    let mut usages: Vec<EncryptedDnsUsage> = Vec::new();
+    let mut index = HashMap::new();
+    for key in order {
+        let flow = flows.remove(&key).expect("flow recorded in order");
+        let Some(detection) = flow.detection else {
+            continue;
+        };
+        let slot = *index
+            .entry((detection.client, detection.resolver, detection.protocol))
+            .or_insert_with(|| {
+                usages.push(EncryptedDnsUsage {
+                    client: IpAddr::V4(detection.client).to_string(),
+                    resolver: IpAddr::V4(detection.resolver).to_string(),
+                    protocol: detection.protocol,
+                    server_name: None,
+                    detection: detection.reason.clone(),
+                    connections: 0,
+                    packets: 0,
+                    bytes: 0,
+                    first_seen: flow.first_seen,
+                    last_seen: flow.last_seen,
+                });
+                usages.len() - 1
+            });
+        let usage = &mut usages[slot];
+        usage.server_name = usage.server_name.take().or(flow.server_name);
+        usage.connections += 1;
+        usage.packets += flow.packets;
+        usage.bytes += flow.bytes;
+        usage.first_seen = usage.first_seen.min(flow.first_seen);
+        usage.last_seen = usage.last_seen.max(flow.last_seen);
+    }
+    usages.sort_by_key(|usage| std::cmp::Reverse(usage.bytes));
+    Ok(usages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cap::{PcapPacket, PcapPacketHeader};
+    use crate::timefmt::Timestamp;
+
+    /// A TCP segment between 192.168.0.10 and `server`, client to server
+    /// unless `reply` is set.
+    fn tcp_frame(
+        ts_sec: u32,
+        server: [u8; 4],
+        port: u16,
+        reply: bool,
+        payload: &[u8],
+    ) -> PcapPacket {
+        let client = [192, 168, 0, 10];
+        let (source, dest) = if reply {
+            (server, client)
+        } else {
+            (client, server)
+        };
+        let (source_port, dest_port) = if reply { (port, 50000) } else { (50000, port) };
+        let mut data = vec![
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0x08, 0x00,
+            0x45, 0x00, 0x00, 0x28, 0x00, 0x01, 0x40, 0x00, 0x40, 0x06, 0x00, 0x00,
+        ];
+        data.extend_from_slice(&source);
+        data.extend_from_slice(&dest);
+        data.extend_from_slice(&source_port.to_be_bytes());
+        data.extend_from_slice(&dest_port.to_be_bytes());
+        data.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 0, 0x50, 0x18, 0xff, 0xff, 0, 0, 0, 0]);
+        data[16..18].copy_from_slice(&(40 + payload.len() as u16).to_be_bytes());
+        data.extend_from_slice(payload);
+        PcapPacket {
+            header: PcapPacketHeader {
+                timestamp: Timestamp::new(ts_sec, 0),
+                incl_len: data.len() as u32,
+                orig_len: data.len() as u32,
+                link_type: None,
+            },
+            data,
+        }
+    }
+
+    fn capture(packets: Vec<PcapPacket>) -> Arc<LoadedCapture> {
+        Arc::new(LoadedCapture::from_packets(1, "dns.pcap", 1, &packets))
+    }
+
+    #[test]
+    fn test_doh_host() {
+        assert!(is_doh_host("dns.google"));
+        assert!(is_doh_host("mozilla.cloudflare-dns.com."));
+        assert!(!is_doh_host("notcloudflare-dns.com"));
+        assert!(!is_doh_host("www.google.com"));
+    }
+
+    #[test]
+    fn test_encrypted_dns() {
+        let quad9 = [9, 9, 9, 9];
+        let resolver = [1, 1, 1, 1];
+        let web = [93, 184, 216, 34];
+        let usages = encrypted_dns(&[capture(vec![
+            tcp_frame(1, quad9, DOT_PORT, false, &[0x17, 0x03, 0x03, 0x00, 0x00]),
+            tcp_frame(2, quad9, DOT_PORT, true, &[0x17, 0x03, 0x03, 0x00, 0x00]),
+            tcp_frame(3, resolver, HTTPS_PORT, true, &[0x17, 0x03, 0x03]),
+            tcp_frame(4, web, HTTPS_PORT, false, &[0x17, 0x03, 0x03]),
+            tcp_frame(
+                5,
+                web,
+                80,
+                false,
+                b"GET /dns-query?dns=AAAB HTTP/1.1\r\n\r\n",
+            ),
+        ])]).unwrap();
+        assert_eq!(usages.len(), 3);
+        let dot = &usages[0];
+        assert_eq!(dot.protocol, EncryptedDnsProtocol::DoT);
+        assert_eq!(dot.client, "192.168.0.10");
+        assert_eq!(dot.resolver, "9.9.9.9");
+        assert_eq!(dot.packets, 2);
+        assert_eq!((dot.first_seen, dot.last_seen), (1.0, 2.0));
+
+        let doh = usages
+            .iter()
+            .find(|usage| usage.resolver == "1.1.1.1")
+            .unwrap();
+        assert_eq!(doh.protocol, EncryptedDnsProtocol::DoH);
+        assert_eq!(doh.client, "192.168.0.10");
+        assert_eq!(doh.detection, "HTTPS to resolver 1.1.1.1");
+        assert!(
+            usages
+                .iter()
+                .any(|usage| usage.detection == "HTTP request to /dns-query")
+        );
+    }
+}
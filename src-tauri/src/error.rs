@@ -0,0 +1,277 @@
+use std::io;
+
+use serde::ser::SerializeStruct;
+
+use crate::link::LinkTypeError;
+
+/// Kcpdump Error
+/// Errors surfaced by capture reading, packet parsing and the Tauri
+/// commands. Commands serialize it as `{ kind, message, ... }` so the
+/// frontend can tell a missing file from a damaged one.
+#[derive(Debug, thiserror::Error)]
+pub enum KcpdumpError {
+    #[error("File not found: {path}")]
+    FileNotFound { path: String },
+    #[error("{context}: {source}")]
+    Io {
+        context: String,
+        #[source]
+        source: io::Error,
+    },
+    /// The file is not a capture in a supported format, or its header is
+    /// damaged.
+    #[error("Invalid capture file: {reason}")]
+    InvalidCapture { reason: String },
+    /// A record or packet could not be decoded. `index` is the 1-based
+    /// packet number when known, and `offset` the byte offset of the
+    /// problem, within the file for records and within the layer being
+    /// decoded otherwise.
+    #[error("{}", malformed_message(.index, .offset, .reason))]
+    MalformedPacket {
+        index: Option<usize>,
+        offset: usize,
+        reason: &'static str,
+    },
+    /// No capture of the session has this id, e.g. one closed since.
+    #[error("No open capture with id {id}")]
+    CaptureNotFound { id: u32 },
+    /// The capture has fewer packets than `number`.
+    #[error("No packet {number} in capture {capture_id}")]
+    PacketNotFound { capture_id: u32, number: usize },
+    /// An argument or a user supplied file, such as a key or a DBC
+    /// database, that could not be parsed.
+    #[error("{0}")]
+    InvalidInput(String),
+    #[error(transparent)]
+    LinkType(#[from] LinkTypeError),
+    #[error("Invalid filter: {0}")]
+    InvalidFilter(String),
+    /// An IDS rule that could not be parsed. `line` is the 1-based line of
+    /// the rules file the rule starts on, when known.
+    #[error("{}", invalid_rule_message(.line, .reason))]
+    InvalidRule { line: Option<usize>, reason: String },
+    #[error("{0}")]
+    Other(String),
+}
+
+fn malformed_message(index: &Option<usize>, offset: &usize, reason: &str) -> String {
+    match index {
+        Some(index) => format!("Malformed packet {} at offset {}: {}", index, offset, reason),
+        None => format!("Malformed packet at offset {}: {}", offset, reason),
+    }
+}
+
+fn invalid_rule_message(line: &Option<usize>, reason: &str) -> String {
+    match line {
+        Some(line) => format!("Invalid rule on line {}: {}", line, reason),
+        None => format!("Invalid rule: {}", reason),
+    }
+}
+
+impl KcpdumpError {
+    /// A packet that could not be decoded, before its number is known.
+    pub fn malformed(offset: usize, reason: &'static str) -> Self {
+        KcpdumpError::MalformedPacket {
+            index: None,
+            offset,
+            reason,
+        }
+    }
+
+    /// An I/O error while working on `path`, reported as `FileNotFound`
+    /// when the file does not exist.
+    pub fn file(path: &str, error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::NotFound => KcpdumpError::FileNotFound {
+                path: path.to_string(),
+            },
+            _ => KcpdumpError::Io {
+                context: path.to_string(),
+                source: error,
+            },
+        }
+    }
+
+    /// An I/O error with a description of what failed, e.g.
+    /// `"Failed to write capture file"`.
+    pub fn io(context: &str, error: io::Error) -> Self {
+        KcpdumpError::Io {
+            context: context.to_string(),
+            source: error,
+        }
+    }
+
+    /// Attaches the packet number to a malformed packet error.
+    pub fn at_packet(self, number: usize) -> Self {
+        match self {
+            KcpdumpError::MalformedPacket {
+                index: None,
+                offset,
+                reason,
+            } => KcpdumpError::MalformedPacket {
+                index: Some(number),
+                offset,
+                reason,
+            },
+            other => other,
+        }
+    }
+
+    /// A rule that could not be parsed, before its line is known.
+    pub fn invalid_rule(reason: impl Into<String>) -> Self {
+        KcpdumpError::InvalidRule {
+            line: None,
+            reason: reason.into(),
+        }
+    }
+
+    /// Attaches the line of the rules file to an invalid rule error.
+    pub fn at_line(self, line: usize) -> Self {
+        match self {
+            KcpdumpError::InvalidRule { line: None, reason } => KcpdumpError::InvalidRule {
+                line: Some(line),
+                reason,
+            },
+            other => other,
+        }
+    }
+
+    /// Stable camelCase name of the variant for the frontend.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            KcpdumpError::FileNotFound { .. } => "fileNotFound",
+            KcpdumpError::Io { .. } => "io",
+            KcpdumpError::InvalidCapture { .. } => "invalidCapture",
+            KcpdumpError::MalformedPacket { .. } => "malformedPacket",
+            KcpdumpError::CaptureNotFound { .. } => "captureNotFound",
+            KcpdumpError::PacketNotFound { .. } => "packetNotFound",
+            KcpdumpError::InvalidInput(_) => "invalidInput",
+            KcpdumpError::LinkType(_) => "unsupportedLinkType",
+            KcpdumpError::InvalidFilter(_) => "invalidFilter",
+            KcpdumpError::InvalidRule { .. } => "invalidRule",
+            KcpdumpError::Other(_) => "other",
+        }
+    }
+}
+
+impl From<io::Error> for KcpdumpError {
+    fn from(error: io::Error) -> Self {
+        // Errors that went through an `io::Result` come back unchanged.
+        match error.downcast::<KcpdumpError>() {
+            Ok(error) => error,
+            Err(error) if error.kind() == io::ErrorKind::InvalidData => {
+                KcpdumpError::InvalidCapture {
+                    reason: error.to_string(),
+                }
+            }
+            Err(error) => KcpdumpError::io("I/O error", error),
+        }
+    }
+}
+
+/// Lets functions returning `io::Result` use `?` on capture reads; the
+/// original error is kept so it converts back losslessly.
+impl From<KcpdumpError> for io::Error {
+    fn from(error: KcpdumpError) -> Self {
+        match error {
+            KcpdumpError::Io { source, .. } => source,
+            KcpdumpError::FileNotFound { .. } => io::Error::new(io::ErrorKind::NotFound, error),
+            other => io::Error::new(io::ErrorKind::InvalidData, other),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<rusqlite::Error> for KcpdumpError {
+    fn from(error: rusqlite::Error) -> Self {
+        KcpdumpError::Other(format!("Database error: {}", error))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<parquet::errors::ParquetError> for KcpdumpError {
+    fn from(error: parquet::errors::ParquetError) -> Self {
+        KcpdumpError::Other(format!("Parquet error: {}", error))
+    }
+}
+
+impl serde::Serialize for KcpdumpError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("KcpdumpError", 5)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        match self {
+            KcpdumpError::FileNotFound { path } => state.serialize_field("path", path)?,
+            KcpdumpError::CaptureNotFound { id } => state.serialize_field("captureId", id)?,
+            KcpdumpError::PacketNotFound { capture_id, number } => {
+                state.serialize_field("captureId", capture_id)?;
+                state.serialize_field("number", number)?;
+            }
+            KcpdumpError::MalformedPacket { index, offset, .. } => {
+                state.serialize_field("index", index)?;
+                state.serialize_field("offset", offset)?;
+            }
+            KcpdumpError::InvalidRule { line, .. } => state.serialize_field("line", line)?,
+            KcpdumpError::LinkType(
+                LinkTypeError::Unsupported(link_type) | LinkTypeError::Malformed { link_type, .. },
+            ) => state.serialize_field("linkType", link_type)?,
+            _ => {}
+        }
+        state.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialize() {
+        let error = KcpdumpError::file("missing.pcap", io::Error::from(io::ErrorKind::NotFound));
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({
+                "kind": "fileNotFound",
+                "message": "File not found: missing.pcap",
+                "path": "missing.pcap",
+            })
+        );
+
+        let error = KcpdumpError::malformed(14, "Data too short for IPv4 packet").at_packet(7);
+        let value = serde_json::to_value(&error).unwrap();
+        assert_eq!(value["kind"], "malformedPacket");
+        assert_eq!(value["index"], 7);
+        assert_eq!(value["offset"], 14);
+        assert_eq!(
+            value["message"],
+            "Malformed packet 7 at offset 14: Data too short for IPv4 packet"
+        );
+
+        let error = KcpdumpError::PacketNotFound {
+            capture_id: 2,
+            number: 9,
+        };
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({
+                "kind": "packetNotFound",
+                "message": "No packet 9 in capture 2",
+                "captureId": 2,
+                "number": 9,
+            })
+        );
+
+        let error = KcpdumpError::invalid_rule("Rule has no sid").at_line(3);
+        assert_eq!(error.to_string(), "Invalid rule on line 3: Rule has no sid");
+    }
+
+    #[test]
+    fn test_io_roundtrip() {
+        let error = io::Error::from(KcpdumpError::malformed(40, "Truncated packet record"));
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(matches!(
+            KcpdumpError::from(error),
+            KcpdumpError::MalformedPacket { offset: 40, .. }
+        ));
+    }
+}
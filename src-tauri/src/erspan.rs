@@ -0,0 +1,207 @@
+use crate::dissect::{Dissector, FieldInfo, FieldType, FieldValue, FieldValues, PacketLayers};
+use crate::error::KcpdumpError;
+
+/// IP protocol number of GRE.
+pub const IP_PROTOCOL_GRE: u8 = 47;
+
+pub const GRE_ERSPAN_II: u16 = 0x88be;
+pub const GRE_ERSPAN_III: u16 = 0x22eb;
+
+/// Gre Header
+/// The fixed GRE header and the optional fields its flags announce.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GreHeader {
+    pub protocol_type: u16,
+    pub key: Option<u32>,
+    pub sequence: Option<u32>,
+    /// Offset of the GRE payload.
+    pub length: usize,
+}
+
+impl TryFrom<&[u8]> for GreHeader {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 4 {
+            return Err(KcpdumpError::malformed(0, "Data too short for GRE header"));
+        }
+        let flags = data[0];
+        let mut length = 4;
+        let mut field = |present: bool| -> Result<Option<u32>, Self::Error> {
+            if !present {
+                return Ok(None);
+            }
+            let bytes = data
+                .get(length..length + 4)
+                .ok_or(KcpdumpError::malformed(length, "GRE header truncated"))?;
+            length += 4;
+            Ok(Some(u32::from_be_bytes([
+                bytes[0], bytes[1], bytes[2], bytes[3],
+            ])))
+        };
+        // Checksum and reserved word, not kept.
+        field(flags & 0x80 != 0)?;
+        let key = field(flags & 0x20 != 0)?;
+        let sequence = field(flags & 0x10 != 0)?;
+        Ok(GreHeader {
+            protocol_type: u16::from_be_bytes([data[2], data[3]]),
+            key,
+            sequence,
+            length,
+        })
+    }
+}
+
+/// Erspan Packet
+/// An ERSPAN Type II or III header and the mirrored Ethernet frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErspanPacket {
+    /// ERSPAN version: 1 for Type II, 2 for Type III.
+    pub version: u8,
+    pub vlan: u16,
+    pub cos: u8,
+    pub truncated: bool,
+    pub session_id: u16,
+    /// Type III timestamp, in units given by `granularity`.
+    pub timestamp: Option<u32>,
+    /// Type III timestamp granularity: 0 for 100 microseconds, 1 for 100
+    /// nanoseconds, 2 for IEEE 1588 and 3 for platform specific.
+    pub granularity: Option<u8>,
+    pub sequence: Option<u32>,
+    pub frame: Vec<u8>,
+}
+
+impl ErspanPacket {
+    /// Parses the payload of an IP packet with protocol GRE.
+    pub fn from_gre(data: &[u8]) -> Result<Self, KcpdumpError> {
+        let gre = GreHeader::try_from(data)?;
+        let data = &data[gre.length..];
+        let header_len = match gre.protocol_type {
+            GRE_ERSPAN_II => 8,
+            GRE_ERSPAN_III => 12,
+            _ => return Err(KcpdumpError::malformed(0, "GRE payload is not ERSPAN")),
+        };
+        if data.len() < header_len {
+            return Err(KcpdumpError::malformed(
+                gre.length,
+                "ERSPAN header truncated",
+            ));
+        }
+        let version = data[0] >> 4;
+        let mut frame = &data[header_len..];
+        let (mut timestamp, mut granularity) = (None, None);
+        if gre.protocol_type == GRE_ERSPAN_III {
+            timestamp = Some(u32::from_be_bytes([data[4], data[5], data[6], data[7]]));
+            granularity = Some((data[11] >> 1) & 0x03);
+            // Optional platform specific subheader.
+            if data[11] & 0x01 != 0 {
+                frame = frame
+                    .get(8..)
+                    .ok_or(KcpdumpError::malformed(8, "ERSPAN subheader truncated"))?;
+            }
+        }
+        Ok(ErspanPacket {
+            version,
+            vlan: u16::from_be_bytes([data[0], data[1]]) & 0x0fff,
+            cos: data[2] >> 5,
+            truncated: data[2] & 0x04 != 0,
+            session_id: u16::from_be_bytes([data[2], data[3]]) & 0x03ff,
+            timestamp,
+            granularity,
+            sequence: gre.sequence,
+            frame: frame.to_vec(),
+        })
+    }
+}
+
+/// Erspan Dissector
+pub struct ErspanDissector;
+
+const ERSPAN_FIELDS: &[FieldInfo] = &[
+    FieldInfo::new("erspan.version", FieldType::UInt, "Version"),
+    FieldInfo::new("erspan.vlan", FieldType::UInt, "VLAN of the mirrored frame"),
+    FieldInfo::new("erspan.cos", FieldType::UInt, "Class of service"),
+    FieldInfo::new(
+        "erspan.truncated",
+        FieldType::Bool,
+        "Mirrored frame was truncated",
+    ),
+    FieldInfo::new("erspan.spanid", FieldType::UInt, "Session ID"),
+    FieldInfo::new("erspan.timestamp", FieldType::UInt, "Type III timestamp"),
+    FieldInfo::new("erspan.gra", FieldType::UInt, "Timestamp granularity"),
+];
+
+impl Dissector for ErspanDissector {
+    fn protocol(&self) -> &'static str {
+        "erspan"
+    }
+
+    fn description(&self) -> &'static str {
+        "Encapsulated Remote Switch Packet Analyzer"
+    }
+
+    fn fields(&self) -> &'static [FieldInfo] {
+        ERSPAN_FIELDS
+    }
+
+    fn dissect(&self, layers: &PacketLayers, values: &mut FieldValues) {
+        let Some(erspan) = &layers.erspan else {
+            return;
+        };
+        values.push("erspan", FieldValue::Protocol);
+        values.push("erspan.version", FieldValue::UInt(erspan.version.into()));
+        values.push("erspan.vlan", FieldValue::UInt(erspan.vlan.into()));
+        values.push("erspan.cos", FieldValue::UInt(erspan.cos.into()));
+        values.push("erspan.truncated", FieldValue::Bool(erspan.truncated));
+        values.push("erspan.spanid", FieldValue::UInt(erspan.session_id.into()));
+        if let Some(timestamp) = erspan.timestamp {
+            values.push("erspan.timestamp", FieldValue::UInt(timestamp.into()));
+        }
+        if let Some(granularity) = erspan.granularity {
+            values.push("erspan.gra", FieldValue::UInt(granularity.into()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INNER: [u8; 14] = [
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0x88, 0xcc,
+    ];
+
+    #[test]
+    fn test_erspan_type_ii() {
+        // GRE with sequence number, ERSPAN version 1, VLAN 100, session 42.
+        let mut data = vec![0x10, 0x00, 0x88, 0xbe, 0x00, 0x00, 0x00, 0x07];
+        data.extend_from_slice(&[0x10, 0x64, 0x00, 0x2a, 0x00, 0x00, 0x00, 0x00]);
+        data.extend_from_slice(&INNER);
+        let erspan = ErspanPacket::from_gre(&data).unwrap();
+        assert_eq!(erspan.version, 1);
+        assert_eq!(erspan.vlan, 100);
+        assert_eq!(erspan.session_id, 42);
+        assert_eq!(erspan.sequence, Some(7));
+        assert_eq!(erspan.timestamp, None);
+        assert_eq!(erspan.frame, INNER);
+    }
+
+    #[test]
+    fn test_erspan_type_iii() {
+        // Timestamp 1000 with 100ns granularity, then the platform subheader.
+        let mut data = vec![0x00, 0x00, 0x22, 0xeb];
+        data.extend_from_slice(&[0x20, 0x00, 0x03, 0xff, 0x00, 0x00, 0x03, 0xe8]);
+        data.extend_from_slice(&[0x00, 0x00, 0x00, 0x03]);
+        data.extend_from_slice(&[0u8; 8]);
+        data.extend_from_slice(&INNER);
+        let erspan = ErspanPacket::from_gre(&data).unwrap();
+        assert_eq!(erspan.version, 2);
+        assert_eq!(erspan.session_id, 0x3ff);
+        assert_eq!(erspan.timestamp, Some(1000));
+        assert_eq!(erspan.granularity, Some(1));
+        assert_eq!(erspan.frame, INNER);
+
+        assert!(ErspanPacket::from_gre(&[0x00, 0x00, 0x08, 0x00]).is_err());
+        assert!(ErspanPacket::from_gre(&data[..10]).is_err());
+    }
+}
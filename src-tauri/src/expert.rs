@@ -0,0 +1,706 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
+
+use crate::dissect::PacketLayers;
+use crate::error::KcpdumpError;
+use crate::flows::FlowKey;
+use crate::link::LINKTYPE_ETHERNET;
+use crate::packet::{EtherType, MacAddress, TcpFlags};
+use crate::packetlist;
+use crate::session::{CaptureId, LoadedCapture};
+
+/// Example packets kept per expert entry.
+const MAX_EXAMPLES: usize = 10;
+
+/// Message of the item reported for retransmitted TCP segments.
+pub const RETRANSMISSION: &str = "This frame is a (suspected) retransmission";
+
+/// Message of the item reported for segments filling an earlier gap.
+pub const OUT_OF_ORDER: &str = "This frame is a (suspected) out-of-order segment";
+
+/// Unfilled sequence gaps remembered per flow direction.
+const MAX_GAPS: usize = 32;
+
+/// Resets within this window count towards an RST storm.
+const RST_STORM_WINDOW_USEC: i64 = 1_000_000;
+
+/// Resets within the window that make up an RST storm.
+const RST_STORM_THRESHOLD: usize = 10;
+
+/// Expert Severity
+/// Ordered from least to most severe, as in Wireshark's expert info.
+#[derive(
+    serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum ExpertSeverity {
+    Chat,
+    Note,
+    Warning,
+    Error,
+}
+
+/// Expert Group
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum ExpertGroup {
+    Checksum,
+    Sequence,
+    ResponseCode,
+    Protocol,
+    Malformed,
+}
+
+/// Expert Item
+/// A notable condition found in one packet.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpertItem {
+    pub number: usize,
+    pub severity: ExpertSeverity,
+    pub group: ExpertGroup,
+    pub protocol: String,
+    pub message: String,
+}
+
+/// Expert Analyzer
+/// Inspects packets in capture order. Sequence analysis keeps per-direction
+/// state, so packets must be fed in the order they were captured.
+#[derive(Debug, Default)]
+pub struct ExpertAnalyzer {
+    /// Next expected sequence number per flow and direction.
+    next_seq: HashMap<(FlowKey, bool), u32>,
+    /// Sequence ranges skipped per flow and direction, oldest first.
+    gaps: HashMap<(FlowKey, bool), VecDeque<(u32, u32)>>,
+    /// Last acknowledgment number and window per flow and direction, with
+    /// the number of duplicates seen since.
+    last_ack: HashMap<(FlowKey, bool), (u32, u16, usize)>,
+    /// Times of the resets within the RST storm window.
+    resets: VecDeque<i64>,
+    in_storm: bool,
+    /// MAC address and frame number that each IPv4 address was last claimed by in ARP.
+    arp_claims: HashMap<Ipv4Addr, (MacAddress, usize)>,
+}
+
+impl ExpertAnalyzer {
+    pub fn analyze(&mut self, layers: &PacketLayers) -> Vec<ExpertItem> {
+        let mut items = Vec::new();
+        let mut add = |severity, group, protocol: &str, message: String| {
+            items.push(ExpertItem {
+                number: layers.number,
+                severity,
+                group,
+                protocol: protocol.to_string(),
+                message,
+            })
+        };
+
+        let ether_type = match (&layers.ethernet, &layers.link) {
+            (Some(eth), _) => Some(eth.header.ether_type),
+            (_, Some(link)) => Some(link.ether_type),
+            _ => None,
+        };
+        if ether_type.is_none() && layers.link_type == LINKTYPE_ETHERNET {
+            add(
+                ExpertSeverity::Error,
+                ExpertGroup::Malformed,
+                "eth",
+                "Frame too short for Ethernet".to_string(),
+            );
+            return items;
+        }
+        if ether_type == Some(EtherType::IPv4) && layers.ipv4.is_none() {
+            add(
+                ExpertSeverity::Error,
+                ExpertGroup::Malformed,
+                "ip",
+                "Malformed IPv4 packet".to_string(),
+            );
+        }
+
+        if let Some(ip) = &layers.ipv4
+            && !ip.validate_checksum()
+        {
+            add(
+                ExpertSeverity::Error,
+                ExpertGroup::Checksum,
+                "ip",
+                "Bad IPv4 header checksum".to_string(),
+            );
+        }
+
+        let addresses = match (&layers.ipv4, &layers.ipv6) {
+            (Some(ip), _) => Some((IpAddr::from(ip.source_ip), IpAddr::from(ip.dest_ip))),
+            (_, Some(ip)) => Some((IpAddr::from(ip.source_ip), IpAddr::from(ip.dest_ip))),
+            _ => None,
+        };
+        if let (Some((source_ip, dest_ip)), Some(tcp)) = (addresses, &layers.tcp) {
+            let flags = tcp.flags;
+            if flags.contains(TcpFlags::SYN) {
+                let message = if flags.contains(TcpFlags::ACK) {
+                    "Connection establish acknowledge (SYN+ACK)"
+                } else {
+                    "Connection establish request (SYN)"
+                };
+                add(
+                    ExpertSeverity::Chat,
+                    ExpertGroup::Sequence,
+                    "tcp",
+                    message.to_string(),
+                );
+            }
+            if flags.contains(TcpFlags::FIN) {
+                add(
+                    ExpertSeverity::Chat,
+                    ExpertGroup::Sequence,
+                    "tcp",
+                    "Connection finish (FIN)".to_string(),
+                );
+            }
+            if flags.contains(TcpFlags::RST) {
+                add(
+                    ExpertSeverity::Warning,
+                    ExpertGroup::Sequence,
+                    "tcp",
+                    "Connection reset (RST)".to_string(),
+                );
+                let micros = layers.packet.header.timestamp.as_micros();
+                while self
+                    .resets
+                    .front()
+                    .is_some_and(|&first| micros - first > RST_STORM_WINDOW_USEC)
+                {
+                    self.resets.pop_front();
+                }
+                self.resets.push_back(micros);
+                if self.resets.len() < RST_STORM_THRESHOLD {
+                    self.in_storm = false;
+                } else if !self.in_storm {
+                    self.in_storm = true;
+                    add(
+                        ExpertSeverity::Warning,
+                        ExpertGroup::Sequence,
+                        "tcp",
+                        format!(
+                            "RST storm: {} connection resets within {} s",
+                            RST_STORM_THRESHOLD,
+                            RST_STORM_WINDOW_USEC / 1_000_000
+                        ),
+                    );
+                }
+            } else if tcp.window_size == 0 && !flags.contains(TcpFlags::SYN) {
+                add(
+                    ExpertSeverity::Warning,
+                    ExpertGroup::Sequence,
+                    "tcp",
+                    "TCP zero window".to_string(),
+                );
+            }
+
+            let source = (source_ip, tcp.source_port);
+            let destination = (dest_ip, tcp.dest_port);
+            let key = FlowKey::new(6, source, destination);
+            let forward = (key.address_a, key.port_a) == source;
+            let consumed = tcp.payload.len() as u32
+                + u32::from(flags.contains(TcpFlags::SYN))
+                + u32::from(flags.contains(TcpFlags::FIN));
+            let end = tcp.sequence_number.wrapping_add(consumed);
+            match self.next_seq.get_mut(&(key, forward)) {
+                Some(next) => {
+                    let gaps = self.gaps.entry((key, forward)).or_default();
+                    if consumed > 0 && !is_after(end, *next) && !flags.contains(TcpFlags::SYN) {
+                        if fill_gap(gaps, tcp.sequence_number, end) {
+                            add(
+                                ExpertSeverity::Warning,
+                                ExpertGroup::Sequence,
+                                "tcp",
+                                OUT_OF_ORDER.to_string(),
+                            );
+                        } else {
+                            add(
+                                ExpertSeverity::Note,
+                                ExpertGroup::Sequence,
+                                "tcp",
+                                RETRANSMISSION.to_string(),
+                            );
+                        }
+                    } else if is_after(tcp.sequence_number, *next) {
+                        add(
+                            ExpertSeverity::Warning,
+                            ExpertGroup::Sequence,
+                            "tcp",
+                            "Previous segment not captured".to_string(),
+                        );
+                        if gaps.len() == MAX_GAPS {
+                            gaps.pop_front();
+                        }
+                        gaps.push_back((*next, tcp.sequence_number));
+                    }
+                    if is_after(end, *next) || flags.contains(TcpFlags::SYN) {
+                        *next = end;
+                    }
+                }
+                None => {
+                    self.next_seq.insert((key, forward), end);
+                }
+            }
+
+            // A pure ACK repeating the previous acknowledgment and window of
+            // its direction, as sent by a receiver missing a segment.
+            if flags.contains(TcpFlags::ACK) {
+                let pure = tcp.payload.is_empty()
+                    && tcp.window_size != 0
+                    && !flags.contains(TcpFlags::SYN)
+                    && !flags.contains(TcpFlags::FIN)
+                    && !flags.contains(TcpFlags::RST);
+                match self.last_ack.get_mut(&(key, forward)) {
+                    Some((ack, window, duplicates))
+                        if pure && (*ack, *window) == (tcp.ack_number, tcp.window_size) =>
+                    {
+                        *duplicates += 1;
+                        add(
+                            ExpertSeverity::Note,
+                            ExpertGroup::Sequence,
+                            "tcp",
+                            "Duplicate ACK".to_string(),
+                        );
+                    }
+                    _ => {
+                        self.last_ack
+                            .insert((key, forward), (tcp.ack_number, tcp.window_size, 0));
+                    }
+                }
+            }
+        }
+
+        if let Some(icmp) = layers.icmp.as_ref().or(layers.icmpv6.as_ref()) {
+            let message = match (icmp.version, icmp.icmp_type, icmp.code) {
+                (4, 11, 0) => Some(("icmp", "Time to live exceeded in transit")),
+                (6, 3, 0) => Some(("icmpv6", "Hop limit exceeded in transit")),
+                _ => None,
+            };
+            if let Some((protocol, message)) = message {
+                add(
+                    ExpertSeverity::Note,
+                    ExpertGroup::Protocol,
+                    protocol,
+                    message.to_string(),
+                );
+            }
+        }
+
+        if let Some(arp) = &layers.arp
+            && !arp.sender_ip.is_unspecified()
+        {
+            let claim = (arp.sender_mac, layers.number);
+            if let Some((mac, number)) = self.arp_claims.insert(arp.sender_ip, claim)
+                && mac != arp.sender_mac
+            {
+                add(
+                    ExpertSeverity::Warning,
+                    ExpertGroup::Protocol,
+                    "arp",
+                    format!(
+                        "Duplicate IP address detected for {} ({}) - also in use by {} (frame {})",
+                        arp.sender_ip, arp.sender_mac, mac, number
+                    ),
+                );
+            }
+        }
+
+        if let Some(dns) = &layers.dns
+            && dns.is_response()
+            && dns.rcode() != 0
+        {
+            add(
+                ExpertSeverity::Warning,
+                ExpertGroup::ResponseCode,
+                "dns",
+                format!("DNS response with error: {}", rcode_name(dns.rcode())),
+            );
+        }
+
+        items
+    }
+}
+
+/// Packet Ref
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PacketRef {
+    pub capture_id: CaptureId,
+    pub number: usize,
+}
+
+/// Expert Entry
+/// All items sharing the same severity, group, protocol and message.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpertEntry {
+    pub severity: ExpertSeverity,
+    pub group: ExpertGroup,
+    pub protocol: String,
+    pub message: String,
+    pub count: usize,
+    /// The first few packets the item was reported for.
+    pub examples: Vec<PacketRef>,
+}
+
+/// Expert Summary
+/// Aggregated expert info, most severe and most frequent entries first.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpertSummary {
+    pub counts: BTreeMap<ExpertSeverity, usize>,
+    pub entries: Vec<ExpertEntry>,
+}
+
+/// Runs expert analysis over the given captures and aggregates the items.
+pub fn summarize_captures(captures: &[Arc<LoadedCapture>]) -> Result<ExpertSummary, KcpdumpError> {
+    let mut analyzers: HashMap<CaptureId, ExpertAnalyzer> = HashMap::new();
+    let mut counts = BTreeMap::new();
+    let mut entries: Vec<ExpertEntry> = Vec::new();
+    let mut index: HashMap<(ExpertSeverity, ExpertGroup, String, String), usize> = HashMap::new();
+
+    let link_types = packetlist::link_types(captures);
+    for item in packetlist::merged_packets(captures) {
+        let (capture_id, number, packet) = item?;
+        let layers = PacketLayers::decode_link(number, link_types[&capture_id], &packet);
+        for item in analyzers.entry(capture_id).or_default().analyze(&layers) {
+            *counts.entry(item.severity).or_default() += 1;
+            let key = (item.severity, item.group, item.protocol, item.message);
+            let position = *index.entry(key.clone()).or_insert_with(|| {
+                entries.push(ExpertEntry {
+                    severity: key.0,
+                    group: key.1,
+                    protocol: key.2,
+                    message: key.3,
+                    count: 0,
+                    examples: Vec::new(),
+                });
+                entries.len() - 1
+            });
+            let entry = &mut entries[position];
+            entry.count += 1;
+            if entry.examples.len() < MAX_EXAMPLES {
+                entry.examples.push(PacketRef { capture_id, number });
+            }
+        }
+    }
+
+    entries.sort_by(|a, b| {
+        b.severity
+            .cmp(&a.severity)
+            .then_with(|| b.count.cmp(&a.count))
+            .then_with(|| a.message.cmp(&b.message))
+    });
+    Ok(ExpertSummary { counts, entries })
+}
+
+/// Runs expert analysis over one capture and returns every item, in capture
+/// order, for listing findings next to the packets they were reported for.
+pub fn capture_events(capture: &LoadedCapture) -> Result<Vec<ExpertItem>, KcpdumpError> {
+    let mut analyzer = ExpertAnalyzer::default();
+    let mut items = Vec::new();
+    for (index, packet) in capture.packets().enumerate() {
+        let packet = packet?;
+        let layers = PacketLayers::decode_link(index + 1, capture.header.network, &packet);
+        items.extend(analyzer.analyze(&layers));
+    }
+    Ok(items)
+}
+
+/// Removes the segment `start..end` from the gap it begins in, if any, and
+/// reports whether there was one.
+fn fill_gap(gaps: &mut VecDeque<(u32, u32)>, start: u32, end: u32) -> bool {
+    let Some(position) = gaps
+        .iter()
+        .position(|&(from, to)| !is_after(from, start) && is_after(to, start))
+    else {
+        return false;
+    };
+    let (from, to) = gaps[position];
+    gaps.remove(position);
+    if is_after(to, end) {
+        gaps.insert(position, (end, to));
+    }
+    if is_after(start, from) {
+        gaps.insert(position, (from, start));
+    }
+    true
+}
+
+/// Whether sequence number `a` lies after `b`, allowing for wrap-around.
+fn is_after(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) > 0
+}
+
+fn rcode_name(rcode: u8) -> String {
+    match rcode {
+        1 => "Format error".to_string(),
+        2 => "Server failure".to_string(),
+        3 => "No such name".to_string(),
+        4 => "Not implemented".to_string(),
+        5 => "Refused".to_string(),
+        other => format!("Code {}", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cap::{Capture, PcapPacket, PcapPacketHeader};
+    use crate::link::LINKTYPE_RAW;
+    use crate::timefmt::Timestamp;
+
+    /// A TCP segment from 192.168.0.10:50000 to 93.184.216.34:80.
+    fn tcp_frame(seq: u32, flags: u8, payload: &[u8]) -> PcapPacket {
+        let mut data = vec![
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0x08, 0x00,
+            0x45, 0x00, 0x00, 0x28, 0x00, 0x01, 0x40, 0x00, 0x40, 0x06, 0x00, 0x00, 0xc0, 0xa8,
+            0x00, 0x0a, 0x5d, 0xb8, 0xd8, 0x22, 0xc3, 0x50, 0x00, 0x50, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x50, flags, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00,
+        ];
+        data[38..42].copy_from_slice(&seq.to_be_bytes());
+        data[16..18].copy_from_slice(&(40 + payload.len() as u16).to_be_bytes());
+        data.extend_from_slice(payload);
+        // Fill in a valid header checksum so only the tested condition is reported.
+        let sum: u32 = data[14..34]
+            .chunks(2)
+            .map(|word| u32::from(u16::from_be_bytes([word[0], word[1]])))
+            .sum();
+        let checksum = !(((sum & 0xffff) + (sum >> 16)) as u16);
+        data[24..26].copy_from_slice(&checksum.to_be_bytes());
+        PcapPacket {
+            header: PcapPacketHeader {
+                timestamp: Timestamp::from_micros(0, 0),
+                incl_len: data.len() as u32,
+                orig_len: data.len() as u32,
+                link_type: None,
+            },
+            data,
+        }
+    }
+
+    /// A raw IPv6 TCP segment from [2001:db8::1]:50000 to [2001:db8::2]:80.
+    fn tcp6_packet(seq: u32, ack: u32, flags: u8, payload: &[u8]) -> PcapPacket {
+        let mut data = vec![0x60, 0x00, 0x00, 0x00];
+        data.extend_from_slice(&(20 + payload.len() as u16).to_be_bytes());
+        data.extend_from_slice(&[0x06, 0x40]);
+        for address in ["2001:db8::1", "2001:db8::2"] {
+            data.extend_from_slice(&address.parse::<std::net::Ipv6Addr>().unwrap().octets());
+        }
+        data.extend_from_slice(&[0xc3, 0x50, 0x00, 0x50]);
+        data.extend_from_slice(&seq.to_be_bytes());
+        data.extend_from_slice(&ack.to_be_bytes());
+        data.extend_from_slice(&[0x50, flags, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00]);
+        data.extend_from_slice(payload);
+        PcapPacket {
+            header: PcapPacketHeader {
+                timestamp: Timestamp::from_micros(0, 0),
+                incl_len: data.len() as u32,
+                orig_len: data.len() as u32,
+                link_type: None,
+            },
+            data,
+        }
+    }
+
+    fn messages(analyzer: &mut ExpertAnalyzer, number: usize, packet: &PcapPacket) -> Vec<String> {
+        analyzer
+            .analyze(&PacketLayers::decode(number, packet))
+            .into_iter()
+            .map(|item| item.message)
+            .collect()
+    }
+
+    #[test]
+    fn test_retransmission_and_gap() {
+        let mut analyzer = ExpertAnalyzer::default();
+        assert!(messages(&mut analyzer, 1, &tcp_frame(1000, 0x18, b"hello")).is_empty());
+        assert_eq!(
+            messages(&mut analyzer, 2, &tcp_frame(1000, 0x18, b"hello")),
+            vec!["This frame is a (suspected) retransmission"]
+        );
+        assert!(messages(&mut analyzer, 3, &tcp_frame(1005, 0x18, b"world")).is_empty());
+        assert_eq!(
+            messages(&mut analyzer, 4, &tcp_frame(2000, 0x18, b"later")),
+            vec!["Previous segment not captured"]
+        );
+    }
+
+    #[test]
+    fn test_out_of_order_and_duplicate_ack() {
+        let mut analyzer = ExpertAnalyzer::default();
+        assert!(messages(&mut analyzer, 1, &tcp_frame(1000, 0x18, b"hello")).is_empty());
+        assert_eq!(
+            messages(&mut analyzer, 2, &tcp_frame(1010, 0x18, b"later")),
+            vec!["Previous segment not captured"]
+        );
+        assert_eq!(
+            messages(&mut analyzer, 3, &tcp_frame(1005, 0x18, b"world")),
+            vec![OUT_OF_ORDER]
+        );
+        // The gap is filled, so the same segment again is a retransmission.
+        assert_eq!(
+            messages(&mut analyzer, 4, &tcp_frame(1005, 0x18, b"world")),
+            vec![RETRANSMISSION]
+        );
+
+        let ack = |ack_number: u32| {
+            let mut packet = tcp_frame(1015, 0x10, b"");
+            packet.data[42..46].copy_from_slice(&ack_number.to_be_bytes());
+            packet
+        };
+        assert!(messages(&mut analyzer, 5, &ack(1)).is_empty());
+        assert_eq!(messages(&mut analyzer, 6, &ack(1)), vec!["Duplicate ACK"]);
+        assert_eq!(messages(&mut analyzer, 7, &ack(1)), vec!["Duplicate ACK"]);
+        assert!(messages(&mut analyzer, 8, &ack(2)).is_empty());
+    }
+
+    #[test]
+    fn test_ipv6_sequence_analysis() {
+        let mut analyzer = ExpertAnalyzer::default();
+        let mut analyze = |number: usize, packet: PcapPacket| -> Vec<String> {
+            analyzer
+                .analyze(&PacketLayers::decode_link(number, LINKTYPE_RAW, &packet))
+                .into_iter()
+                .map(|item| item.message)
+                .collect()
+        };
+        assert!(analyze(1, tcp6_packet(1000, 1, 0x18, b"hello")).is_empty());
+        assert_eq!(
+            analyze(2, tcp6_packet(1010, 1, 0x18, b"later")),
+            vec!["Previous segment not captured"]
+        );
+        assert_eq!(
+            analyze(3, tcp6_packet(1005, 1, 0x18, b"world")),
+            vec![OUT_OF_ORDER]
+        );
+        assert!(analyze(4, tcp6_packet(1015, 2, 0x10, b"")).is_empty());
+        assert_eq!(
+            analyze(5, tcp6_packet(1015, 2, 0x10, b"")),
+            vec!["Duplicate ACK"]
+        );
+    }
+
+    #[test]
+    fn test_rst_storm() {
+        let mut analyzer = ExpertAnalyzer::default();
+        let storms: Vec<usize> = (1..=RST_STORM_THRESHOLD + 5)
+            .filter(|&number| {
+                messages(&mut analyzer, number, &tcp_frame(number as u32, 0x14, b""))
+                    .iter()
+                    .any(|message| message.starts_with("RST storm"))
+            })
+            .collect();
+        assert_eq!(storms, vec![RST_STORM_THRESHOLD]);
+    }
+
+    #[test]
+    fn test_arp_conflict_and_ttl_exceeded() {
+        let arp = |sender_mac: u8| {
+            let mut data = vec![0xff; 6];
+            data.extend_from_slice(&[0x02, 0, 0, 0, 0, sender_mac, 0x08, 0x06]);
+            data.extend_from_slice(&[0, 1, 0x08, 0x00, 6, 4, 0, 2]);
+            data.extend_from_slice(&[0x02, 0, 0, 0, 0, sender_mac, 192, 168, 0, 1]);
+            data.extend_from_slice(&[0; 6]);
+            data.extend_from_slice(&[192, 168, 0, 2]);
+            PcapPacket {
+                header: PcapPacketHeader {
+                    timestamp: Timestamp::from_micros(0, 0),
+                    incl_len: data.len() as u32,
+                    orig_len: data.len() as u32,
+                    link_type: None,
+                },
+                data,
+            }
+        };
+        let mut analyzer = ExpertAnalyzer::default();
+        assert!(messages(&mut analyzer, 1, &arp(1)).is_empty());
+        assert!(messages(&mut analyzer, 2, &arp(1)).is_empty());
+        assert_eq!(
+            messages(&mut analyzer, 3, &arp(2)),
+            vec![
+                "Duplicate IP address detected for 192.168.0.1 (02:00:00:00:00:02) - also in use by 02:00:00:00:00:01 (frame 2)"
+            ]
+        );
+
+        // ICMP time exceeded quoting the first bytes of a UDP datagram.
+        let mut ttl = tcp_frame(0, 0, b"");
+        ttl.data.truncate(34);
+        ttl.data[23] = 1;
+        ttl.data[16..18].copy_from_slice(&32u16.to_be_bytes());
+        let sum: u32 = ttl.data[14..34]
+            .chunks(2)
+            .map(|word| u32::from(u16::from_be_bytes([word[0], word[1]])))
+            .sum::<u32>()
+            - u32::from(u16::from_be_bytes([ttl.data[24], ttl.data[25]]));
+        let checksum = !(((sum & 0xffff) + (sum >> 16)) as u16);
+        ttl.data[24..26].copy_from_slice(&checksum.to_be_bytes());
+        ttl.data.extend_from_slice(&[11, 0, 0xf4, 0xff, 0, 0, 0, 0, 0x45, 0, 0, 28]);
+        assert_eq!(
+            messages(&mut analyzer, 4, &ttl),
+            vec!["Time to live exceeded in transit"]
+        );
+    }
+
+    #[test]
+    fn test_reset_is_a_warning() {
+        let mut analyzer = ExpertAnalyzer::default();
+        let items = analyzer.analyze(&PacketLayers::decode(1, &tcp_frame(1, 0x14, b"")));
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].severity, ExpertSeverity::Warning);
+        assert_eq!(items[0].message, "Connection reset (RST)");
+    }
+
+    #[tokio::test]
+    async fn test_sample_handshake() {
+        let mut capture = Capture::from_file("sample.pcap").await.unwrap();
+        let mut analyzer = ExpertAnalyzer::default();
+        let mut items = Vec::new();
+        let mut number = 0;
+        while let Some(packet) = capture.next_packet().await.unwrap() {
+            number += 1;
+            items.extend(analyzer.analyze(&PacketLayers::decode(number, &packet)));
+        }
+        assert!(items.iter().any(|item| item.message.contains("(SYN)")));
+        assert!(items.iter().any(|item| item.message.contains("(FIN)")));
+        assert!(
+            items
+                .iter()
+                .all(|item| item.severity < ExpertSeverity::Error)
+        );
+    }
+
+    #[test]
+    fn test_summarize_captures_groups_items() {
+        let packets = vec![
+            tcp_frame(1000, 0x18, b"hello"),
+            tcp_frame(1000, 0x18, b"hello"),
+            tcp_frame(1000, 0x18, b"hello"),
+            tcp_frame(1005, 0x14, b""),
+        ];
+        let capture = LoadedCapture::from_packets(4, "", 1, &packets);
+        let summary = summarize_captures(&[Arc::new(capture)]).unwrap();
+        assert_eq!(summary.counts[&ExpertSeverity::Warning], 1);
+        assert_eq!(summary.counts[&ExpertSeverity::Note], 2);
+        assert_eq!(summary.entries[0].message, "Connection reset (RST)");
+        let retransmissions = &summary.entries[1];
+        assert_eq!(retransmissions.count, 2);
+        assert_eq!(
+            retransmissions.examples,
+            vec![
+                PacketRef {
+                    capture_id: 4,
+                    number: 2
+                },
+                PacketRef {
+                    capture_id: 4,
+                    number: 3
+                }
+            ]
+        );
+    }
+}
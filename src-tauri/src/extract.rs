@@ -0,0 +1,264 @@
+use std::collections::HashSet;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::dissect::PacketLayers;
+use crate::error::KcpdumpError;
+use crate::flows::FlowKey;
+use crate::http;
+use crate::reassembly::{StreamReassembler, TcpStream};
+use crate::session::LoadedCapture;
+
+/// File Origin
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum FileOrigin {
+    /// An HTTP response body.
+    Http,
+    /// Found by scanning stream data for file signatures.
+    Carved,
+}
+
+/// Extracted File
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractedFile {
+    /// Index in the extraction result, used to select files for saving.
+    pub id: usize,
+    pub name: String,
+    pub content_type: String,
+    pub size: usize,
+    pub origin: FileOrigin,
+    pub source: FlowKey,
+    /// Frame number of the first packet of the source connection.
+    pub first_number: usize,
+    #[serde(skip)]
+    pub data: Vec<u8>,
+}
+
+/// A file signature: magic bytes, optional trailer ending the file, and its type.
+struct Signature {
+    magic: &'static [u8],
+    trailer: Option<&'static [u8]>,
+    extension: &'static str,
+    content_type: &'static str,
+}
+
+const SIGNATURES: &[Signature] = &[
+    Signature {
+        magic: b"\x89PNG\r\n\x1a\n",
+        trailer: Some(b"IEND\xae\x42\x60\x82"),
+        extension: "png",
+        content_type: "image/png",
+    },
+    Signature {
+        magic: b"\xff\xd8\xff",
+        trailer: Some(b"\xff\xd9"),
+        extension: "jpg",
+        content_type: "image/jpeg",
+    },
+    Signature {
+        magic: b"GIF89a",
+        trailer: Some(b"\x00\x3b"),
+        extension: "gif",
+        content_type: "image/gif",
+    },
+    Signature {
+        magic: b"%PDF-",
+        trailer: Some(b"%%EOF"),
+        extension: "pdf",
+        content_type: "application/pdf",
+    },
+    Signature {
+        magic: b"PK\x03\x04",
+        trailer: None,
+        extension: "zip",
+        content_type: "application/zip",
+    },
+];
+
+/// Scans `data` for known file signatures. A file ends at its trailer, or at
+/// the end of the data for formats without one.
+pub fn carve(data: &[u8]) -> Vec<(&'static str, &'static str, Vec<u8>)> {
+    let mut files = Vec::new();
+    let mut position = 0;
+    while position < data.len() {
+        let found = SIGNATURES
+            .iter()
+            .find(|signature| data[position..].starts_with(signature.magic));
+        let Some(signature) = found else {
+            position += 1;
+            continue;
+        };
+        let rest = &data[position..];
+        let length = signature
+            .trailer
+            .and_then(|trailer| {
+                rest.windows(trailer.len())
+                    .skip(signature.magic.len())
+                    .position(|window| window == trailer)
+                    .map(|index| index + signature.magic.len() + trailer.len())
+            })
+            .unwrap_or(rest.len());
+        files.push((
+            signature.extension,
+            signature.content_type,
+            rest[..length].to_vec(),
+        ));
+        position += length;
+    }
+    files
+}
+
+/// Collects HTTP objects and carved files from every TCP stream of the captures.
+/// Streams that carry HTTP are not carved again.
+pub fn extract_files(captures: &[Arc<LoadedCapture>]) -> Result<Vec<ExtractedFile>, KcpdumpError> {
+    let mut files = Vec::new();
+    for capture in captures {
+        let mut reassembler = StreamReassembler::default();
+        for (index, packet) in capture.packets().enumerate() {
+            let packet = packet?;
+            reassembler.push(&PacketLayers::decode_link(index + 1, capture.header.network, &packet));
+        }
+        for stream in reassembler.finish() {
+            extract_stream(&stream, &mut files);
+        }
+    }
+    Ok(files)
+}
+
+fn extract_stream(stream: &TcpStream, files: &mut Vec<ExtractedFile>) {
+    let mut add = |name: String, content_type: String, origin, data: Vec<u8>| {
+        files.push(ExtractedFile {
+            id: files.len(),
+            name,
+            content_type,
+            size: data.len(),
+            origin,
+            source: stream.key,
+            first_number: stream.first_number,
+            data,
+        })
+    };
+
+    let objects = http::objects(stream);
+    if !objects.is_empty() {
+        for object in objects {
+            let content_type = object
+                .content_type
+                .unwrap_or_else(|| "application/octet-stream".to_string());
+            add(object.filename, content_type, FileOrigin::Http, object.data);
+        }
+        return;
+    }
+
+    let carved = carve(&stream.client_data.data)
+        .into_iter()
+        .chain(carve(&stream.server_data.data));
+    for (index, (extension, content_type, data)) in carved.enumerate() {
+        let name = format!("frame{}-{}.{}", stream.first_number, index + 1, extension);
+        add(name, content_type.to_string(), FileOrigin::Carved, data);
+    }
+}
+
+/// Writes the selected files into `directory`, returning the paths written.
+/// File names are reduced to their last path component and made unique.
+pub fn save_files(
+    files: &[ExtractedFile],
+    ids: &[usize],
+    directory: &Path,
+) -> io::Result<Vec<String>> {
+    let mut used = HashSet::new();
+    let mut written = Vec::new();
+    for &id in ids {
+        let file = files.get(id).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("No extracted file {}", id))
+        })?;
+        let name = unique_name(&sanitize(&file.name), &mut used);
+        let path = directory.join(name);
+        std::fs::write(&path, &file.data)?;
+        written.push(path.to_string_lossy().into_owned());
+    }
+    Ok(written)
+}
+
+fn sanitize(name: &str) -> String {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_control() || ":*?\"<>|".contains(c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    match name.trim_matches('.') {
+        "" => "file".to_string(),
+        _ => name,
+    }
+}
+
+fn unique_name(name: &str, used: &mut HashSet<String>) -> String {
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, format!(".{}", extension)),
+        _ => (name, String::new()),
+    };
+    let mut candidate = name.to_string();
+    let mut counter = 1;
+    while !used.insert(candidate.clone()) {
+        candidate = format!("{}({}){}", stem, counter, extension);
+        counter += 1;
+    }
+    candidate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_carve_signatures() {
+        let mut data = b"noise".to_vec();
+        data.extend_from_slice(b"%PDF-1.4 body %%EOF");
+        data.extend_from_slice(b"between");
+        data.extend_from_slice(b"\xff\xd8\xff\xe0jpeg\xff\xd9");
+        let files = carve(&data);
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].0, "pdf");
+        assert_eq!(files[0].2, b"%PDF-1.4 body %%EOF");
+        assert_eq!(files[1].1, "image/jpeg");
+        assert!(files[1].2.ends_with(b"\xff\xd9"));
+    }
+
+    #[test]
+    fn test_sanitize_and_unique_names() {
+        assert_eq!(sanitize("../../etc/passwd"), "passwd");
+        assert_eq!(sanitize("a:b?.txt"), "a_b_.txt");
+        assert_eq!(sanitize(".."), "file");
+        let mut used = HashSet::new();
+        assert_eq!(unique_name("index.html", &mut used), "index.html");
+        assert_eq!(unique_name("index.html", &mut used), "index(1).html");
+    }
+
+    #[tokio::test]
+    async fn test_extract_and_save_sample_http_object() {
+        let capture = LoadedCapture::load(1, "sample.pcap").await.unwrap();
+        let files = extract_files(&[Arc::new(capture)]).unwrap();
+        let html = files.iter().find(|file| file.name == "index.html").unwrap();
+        assert_eq!(html.origin, FileOrigin::Http);
+        assert_eq!(html.content_type, "text/html");
+        assert_eq!(html.data, b"<html>hello</html>");
+
+        let directory =
+            std::env::temp_dir().join(format!("kcpdump-extract-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let written = save_files(&files, &[html.id, html.id], &directory).unwrap();
+        assert_eq!(written.len(), 2);
+        assert_eq!(std::fs::read(&written[1]).unwrap(), html.data);
+        assert!(save_files(&files, &[files.len()], &directory).is_err());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
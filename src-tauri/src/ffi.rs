@@ -0,0 +1,152 @@
+use std::ffi::{CStr, CString, c_char, c_int};
+use std::ptr;
+
+use crate::cap::{Capture, PcapPacket};
+use crate::dissect::{DissectorRegistry, PacketLayers};
+
+/// Kcpdump Capture
+/// Opaque handle of the C ABI declared in `include/kcpdump.h`, returned by
+/// `kcpdump_open` and advanced packet by packet.
+pub struct KcpdumpCapture {
+    runtime: tokio::runtime::Runtime,
+    capture: Capture,
+    registry: DissectorRegistry,
+    number: usize,
+    packet: Option<PcapPacket>,
+}
+
+/// Opens the capture file at `path`. Returns NULL if the file cannot be read.
+///
+/// # Safety
+/// `path` must be a NUL terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kcpdump_open(path: *const c_char) -> *mut KcpdumpCapture {
+    if path.is_null() {
+        return ptr::null_mut();
+    }
+    let Ok(path) = unsafe { CStr::from_ptr(path) }.to_str() else {
+        return ptr::null_mut();
+    };
+    let Ok(runtime) = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    else {
+        return ptr::null_mut();
+    };
+    match runtime.block_on(Capture::from_file(path)) {
+        Ok(capture) => Box::into_raw(Box::new(KcpdumpCapture {
+            runtime,
+            capture,
+            registry: DissectorRegistry::default(),
+            number: 0,
+            packet: None,
+        })),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Advances to the next packet. Returns 1 if a packet was read, 0 at the end
+/// of the capture and -1 on a read error.
+///
+/// # Safety
+/// `capture` must be a handle returned by `kcpdump_open`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kcpdump_next_packet(capture: *mut KcpdumpCapture) -> c_int {
+    let Some(capture) = (unsafe { capture.as_mut() }) else {
+        return -1;
+    };
+    match capture.runtime.block_on(capture.capture.next_packet()) {
+        Ok(Some(packet)) => {
+            capture.number += 1;
+            capture.packet = Some(packet);
+            1
+        }
+        Ok(None) => {
+            capture.packet = None;
+            0
+        }
+        Err(_) => {
+            capture.packet = None;
+            -1
+        }
+    }
+}
+
+/// Returns the dissection tree of the current packet as a JSON array of
+/// `{"protocol", "fields": [{"name", "value"}]}` objects, or NULL if there is
+/// no current packet. Release the string with `kcpdump_string_free`.
+///
+/// # Safety
+/// `capture` must be a handle returned by `kcpdump_open`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kcpdump_field_tree_json(capture: *const KcpdumpCapture) -> *mut c_char {
+    let Some(capture) = (unsafe { capture.as_ref() }) else {
+        return ptr::null_mut();
+    };
+    let Some(packet) = &capture.packet else {
+        return ptr::null_mut();
+    };
+    let link_type = capture.capture.header().network;
+    let values = capture
+        .registry
+        .dissect(&PacketLayers::decode_link(capture.number, link_type, packet));
+    serde_json::to_string(&values.tree())
+        .ok()
+        .and_then(|json| CString::new(json).ok())
+        .map_or(ptr::null_mut(), CString::into_raw)
+}
+
+/// Frees a string returned by `kcpdump_field_tree_json`. NULL is ignored.
+///
+/// # Safety
+/// `string` must come from this library and not have been freed already.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kcpdump_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(unsafe { CString::from_raw(string) });
+    }
+}
+
+/// Closes a capture handle. NULL is ignored.
+///
+/// # Safety
+/// `capture` must be a handle returned by `kcpdump_open` and not have been
+/// closed already.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kcpdump_close(capture: *mut KcpdumpCapture) {
+    if !capture.is_null() {
+        drop(unsafe { Box::from_raw(capture) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dissect::ProtocolNode;
+
+    #[test]
+    fn test_iterate_capture() {
+        let path = CString::new("sample.pcap").unwrap();
+        unsafe {
+            let capture = kcpdump_open(path.as_ptr());
+            assert!(!capture.is_null());
+            assert!(kcpdump_field_tree_json(capture).is_null());
+
+            let mut packets = 0;
+            while kcpdump_next_packet(capture) == 1 {
+                packets += 1;
+                let json = kcpdump_field_tree_json(capture);
+                let tree: Vec<ProtocolNode> =
+                    serde_json::from_str(CStr::from_ptr(json).to_str().unwrap()).unwrap();
+                assert_eq!(tree[0].protocol, "frame");
+                kcpdump_string_free(json);
+            }
+            assert_eq!(packets, 14);
+            assert_eq!(kcpdump_next_packet(capture), 0);
+            kcpdump_close(capture);
+        }
+
+        let missing = CString::new("missing.pcap").unwrap();
+        assert!(unsafe { kcpdump_open(missing.as_ptr()) }.is_null());
+    }
+}
@@ -0,0 +1,146 @@
+use crate::packet::{EtherType, EthernetPacket, IPv4Packet};
+use crate::timefmt::Timestamp;
+
+/// Address Direction
+/// Which address of a packet the filter address is compared with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AddressDirection {
+    Source,
+    Dest,
+    #[default]
+    Any,
+}
+
+/// Packet Filter
+/// Server-side counterpart of the IPv4 filter form in the frontend.
+/// Times are milliseconds since the Unix epoch, as produced by the date pickers.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PacketFilter {
+    pub start_time: Option<i64>,
+    pub end_time: Option<i64>,
+    pub ip_address: String,
+    pub direction: AddressDirection,
+    /// Only keep IPv4 packets whose header, TCP, UDP or ICMP checksum is invalid.
+    pub bad_checksum: bool,
+}
+
+impl PacketFilter {
+    /// Checks the capture timestamp against the time range.
+    pub fn matches_time(&self, timestamp: Timestamp) -> bool {
+        let millis = timestamp.as_micros() / 1000;
+        self.start_time.is_none_or(|start| millis >= start)
+            && self.end_time.is_none_or(|end| millis <= end)
+    }
+
+    /// Checks the IPv4 addresses of a packet; packets without an IPv4 layer
+    /// only match when no address is set.
+    pub fn matches_address(&self, ipv4_packet: Option<&IPv4Packet>) -> bool {
+        let address = self.ip_address.trim();
+        if address.is_empty() {
+            return true;
+        }
+        let Some(ipv4_packet) = ipv4_packet else {
+            return false;
+        };
+        let Ok(address) = address.parse::<std::net::Ipv4Addr>() else {
+            return false;
+        };
+        let address = address.octets();
+        match self.direction {
+            AddressDirection::Source => ipv4_packet.source_ip == address,
+            AddressDirection::Dest => ipv4_packet.dest_ip == address,
+            AddressDirection::Any => {
+                ipv4_packet.source_ip == address || ipv4_packet.dest_ip == address
+            }
+        }
+    }
+
+    /// Checks the checksums of a packet when only invalid ones are wanted.
+    pub fn matches_checksum(&self, ipv4_packet: Option<&IPv4Packet>) -> bool {
+        !self.bad_checksum
+            || ipv4_packet.is_some_and(|ipv4_packet| {
+                !ipv4_packet.validate_checksum()
+                    || ipv4_packet.validate_payload_checksum() == Some(false)
+            })
+    }
+
+    pub fn matches(&self, timestamp: Timestamp, ipv4_packet: Option<&IPv4Packet>) -> bool {
+        self.matches_time(timestamp)
+            && self.matches_address(ipv4_packet)
+            && self.matches_checksum(ipv4_packet)
+    }
+
+    /// Checks a raw Ethernet frame, decoding its IPv4 layer only when needed.
+    pub fn matches_frame(&self, timestamp: Timestamp, frame: &[u8]) -> bool {
+        if !self.matches_time(timestamp) {
+            return false;
+        }
+        if self.ip_address.trim().is_empty() && !self.bad_checksum {
+            return true;
+        }
+        let ipv4_packet = EthernetPacket::try_from(frame)
+            .ok()
+            .filter(|eth_packet| eth_packet.header.ether_type == EtherType::IPv4)
+            .and_then(|eth_packet| IPv4Packet::try_from(eth_packet.data.as_slice()).ok());
+        self.matches_address(ipv4_packet.as_ref()) && self.matches_checksum(ipv4_packet.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ipv4_packet() -> IPv4Packet {
+        let data: [u8; 20] = [
+            0x45, 0x00, 0x00, 0x14, 0x00, 0x01, 0x40, 0x00, 0x40, 0x06, 0x00, 0x00, 0xc0, 0xa8,
+            0x00, 0x01, 0xc0, 0xa8, 0x00, 0xc7,
+        ];
+        IPv4Packet::try_from(&data[..]).unwrap()
+    }
+
+    #[test]
+    fn test_time_range() {
+        let filter = PacketFilter {
+            start_time: Some(1_000_000),
+            end_time: Some(2_000_000),
+            ..Default::default()
+        };
+        assert!(filter.matches_time(Timestamp::from_micros(1_500, 0)));
+        assert!(filter.matches_time(Timestamp::from_micros(2_000, 0)));
+        assert!(!filter.matches_time(Timestamp::from_micros(2_000, 1_000)));
+        assert!(!filter.matches_time(Timestamp::from_micros(999, 999_999)));
+    }
+
+    #[test]
+    fn test_address_direction() {
+        let packet = ipv4_packet();
+        let mut filter = PacketFilter {
+            ip_address: "192.168.0.199".to_string(),
+            ..Default::default()
+        };
+        assert!(filter.matches_address(Some(&packet)));
+        filter.direction = AddressDirection::Dest;
+        assert!(filter.matches_address(Some(&packet)));
+        filter.direction = AddressDirection::Source;
+        assert!(!filter.matches_address(Some(&packet)));
+        assert!(!filter.matches_address(None));
+        assert!(PacketFilter::default().matches_address(None));
+    }
+
+    #[test]
+    fn test_bad_checksum() {
+        let filter = PacketFilter {
+            bad_checksum: true,
+            ..Default::default()
+        };
+        // The header checksum is left at zero.
+        let mut packet = ipv4_packet();
+        assert!(filter.matches_checksum(Some(&packet)));
+        packet.header_checksum = 0xb8ca;
+        assert!(!filter.matches_checksum(Some(&packet)));
+        assert!(!filter.matches_checksum(None));
+        assert!(PacketFilter::default().matches_checksum(None));
+    }
+}
@@ -0,0 +1,144 @@
+use tokio::io;
+
+use crate::cap::{Capture, TimestampResolution};
+use crate::filter::PacketFilter;
+use crate::flows::FlowKey;
+use crate::summary::{self, PacketSummary};
+use crate::timefmt::{TimeDisplayMode, TimeFormatter, Timestamp};
+
+/// Flow Event
+/// One arrow of a message-sequence (ladder) diagram.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FlowEvent {
+    pub frame_number: usize,
+    pub timestamp: Timestamp,
+    pub time: String,
+    pub source: String,
+    pub destination: String,
+    pub protocol: String,
+    pub label: String,
+    pub flow: Option<FlowKey>,
+}
+
+/// Flow Graph
+/// Time-ordered events plus the participating nodes in order of first appearance,
+/// which is the left-to-right order of the diagram columns.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct FlowGraph {
+    pub nodes: Vec<String>,
+    pub events: Vec<FlowEvent>,
+}
+
+impl FlowGraph {
+    /// Appends an event, adding its endpoints to the nodes if they are new.
+    pub fn push(&mut self, event: FlowEvent) {
+        for node in [&event.source, &event.destination] {
+            if !self.nodes.contains(node) {
+                self.nodes.push(node.clone());
+            }
+        }
+        self.events.push(event);
+    }
+}
+
+/// Flow Graph Builder
+/// Collects events for packets in capture order.
+pub struct FlowGraphBuilder {
+    formatter: TimeFormatter,
+    graph: FlowGraph,
+}
+
+impl FlowGraphBuilder {
+    /// A builder showing times in `mode` at `resolution`.
+    pub fn new(mode: TimeDisplayMode, resolution: TimestampResolution) -> Self {
+        FlowGraphBuilder {
+            formatter: TimeFormatter::with_resolution(mode, resolution),
+            graph: FlowGraph::default(),
+        }
+    }
+
+    pub fn push(&mut self, frame_number: usize, timestamp: Timestamp, summary: PacketSummary) {
+        self.graph.push(FlowEvent {
+            frame_number,
+            timestamp,
+            time: self.formatter.format(timestamp),
+            source: summary.source,
+            destination: summary.destination,
+            protocol: summary.protocol,
+            label: summary.info,
+            flow: summary.flow,
+        });
+    }
+
+    /// Records a packet that is not part of the graph.
+    pub fn skip(&mut self, timestamp: Timestamp) {
+        self.formatter.skip(timestamp);
+    }
+
+    pub fn finish(self) -> FlowGraph {
+        self.graph
+    }
+}
+
+/// Builds the flow graph of the packets matching `filter` and, if given,
+/// belonging to `conversation`.
+pub async fn build_flow_graph(
+    file_path: &str,
+    filter: &PacketFilter,
+    conversation: Option<FlowKey>,
+    mode: TimeDisplayMode,
+) -> io::Result<FlowGraph> {
+    let mut capture = Capture::from_file(file_path).await?;
+    let mut builder = FlowGraphBuilder::new(mode, capture.resolution());
+    let mut frame_number = 0;
+
+    while let Some(raw_packet) = capture.next_packet().await? {
+        frame_number += 1;
+        let timestamp = raw_packet.header.timestamp;
+        if !filter.matches_frame(timestamp, &raw_packet.data) {
+            builder.skip(timestamp);
+            continue;
+        }
+        let link_type = raw_packet.link_type(capture.header().network);
+        let summary = summary::summarize_link(link_type, &raw_packet.data);
+        if conversation.is_some() && summary.flow != conversation {
+            builder.skip(timestamp);
+            continue;
+        }
+        builder.push(frame_number, timestamp, summary);
+    }
+
+    Ok(builder.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(source: &str, destination: &str, info: &str) -> PacketSummary {
+        PacketSummary {
+            source: source.to_string(),
+            destination: destination.to_string(),
+            protocol: "TCP".to_string(),
+            length: 60,
+            info: info.to_string(),
+            flow: None,
+        }
+    }
+
+    #[test]
+    fn test_nodes_in_order_of_appearance() {
+        let mut builder = FlowGraphBuilder::new(TimeDisplayMode::SinceStart, TimestampResolution::Microsecond);
+        builder.push(1, Timestamp::from_micros(10, 0), summary("10.0.0.2", "10.0.0.1", "SYN"));
+        builder.skip(Timestamp::from_micros(10, 100_000));
+        builder.push(3, Timestamp::from_micros(10, 500_000), summary("10.0.0.1", "10.0.0.3", "SYN, ACK"));
+        let graph = builder.finish();
+        assert_eq!(graph.nodes, vec!["10.0.0.2", "10.0.0.1", "10.0.0.3"]);
+        assert_eq!(graph.events.len(), 2);
+        assert_eq!(graph.events[1].frame_number, 3);
+        assert_eq!(graph.events[1].time, "0.500000");
+        assert_eq!(graph.events[1].label, "SYN, ACK");
+    }
+}
@@ -0,0 +1,475 @@
+use std::collections::HashMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc;
+
+use crate::dccp::DccpPacket;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::dissect::PacketLayers;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::error::KcpdumpError;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::geoip::EndpointGeo;
+use crate::packet::{IPv4Packet, IpProtocol, TcpSegment, UdpDatagram};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::packet::MacAddress;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::packetlist;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::session::{CaptureId, LoadedCapture};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::summary;
+use crate::udplite::UdpLiteDatagram;
+
+/// Flow Key
+/// Identifies a bidirectional conversation by its 5-tuple.
+/// The endpoints are stored in a canonical order so that both directions
+/// of a conversation produce the same key. Protocols without ports use port 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlowKey {
+    pub protocol: u8,
+    pub address_a: IpAddr,
+    pub port_a: u16,
+    pub address_b: IpAddr,
+    pub port_b: u16,
+}
+
+impl FlowKey {
+    pub fn new(protocol: u8, source: (IpAddr, u16), destination: (IpAddr, u16)) -> Self {
+        let (a, b) = if source <= destination {
+            (source, destination)
+        } else {
+            (destination, source)
+        };
+        FlowKey {
+            protocol,
+            address_a: a.0,
+            port_a: a.1,
+            address_b: b.0,
+            port_b: b.1,
+        }
+    }
+
+    /// Builds the key of the conversation an IPv4 packet belongs to.
+    pub fn from_ipv4(ipv4_packet: &IPv4Packet) -> Self {
+        let (source_port, dest_port) = transport_ports(ipv4_packet).unwrap_or((0, 0));
+        FlowKey::new(
+            ipv4_packet.protocol,
+            (
+                IpAddr::V4(Ipv4Addr::from(ipv4_packet.source_ip)),
+                source_port,
+            ),
+            (IpAddr::V4(Ipv4Addr::from(ipv4_packet.dest_ip)), dest_port),
+        )
+    }
+}
+
+/// Extracts the TCP, UDP, UDP-Lite or DCCP ports of an IPv4 packet.
+pub fn transport_ports(ipv4_packet: &IPv4Packet) -> Option<(u16, u16)> {
+    let payload = ipv4_packet.payload.as_slice();
+    match IpProtocol::from(ipv4_packet.protocol) {
+        IpProtocol::TCP => TcpSegment::try_from(payload)
+            .ok()
+            .map(|segment| (segment.source_port, segment.dest_port)),
+        IpProtocol::UDP => UdpDatagram::try_from(payload)
+            .ok()
+            .map(|datagram| (datagram.source_port, datagram.dest_port)),
+        IpProtocol::UDPLite => UdpLiteDatagram::try_from(payload)
+            .ok()
+            .map(|datagram| (datagram.source_port, datagram.dest_port)),
+        IpProtocol::DCCP => DccpPacket::try_from(payload)
+            .ok()
+            .map(|packet| (packet.source_port, packet.dest_port)),
+        _ => None,
+    }
+}
+
+/// Stream Table
+/// Numbers conversations in order of first appearance, like Wireshark's
+/// `tcp.stream`, starting at 0.
+#[derive(Debug, Default)]
+pub struct StreamTable {
+    ids: HashMap<FlowKey, u32>,
+}
+
+impl StreamTable {
+    pub fn id(&mut self, key: FlowKey) -> u32 {
+        let next = self.ids.len() as u32;
+        *self.ids.entry(key).or_insert(next)
+    }
+
+    /// The id of a conversation seen before.
+    pub fn get(&self, key: &FlowKey) -> Option<u32> {
+        self.ids.get(key).copied()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Flow Record
+/// Packet and byte totals of one conversation of a capture.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FlowRecord {
+    pub capture_id: CaptureId,
+    /// Stream index within the capture, as assigned by `StreamTable`.
+    pub stream: u32,
+    pub key: FlowKey,
+    pub packets: usize,
+    pub bytes: u64,
+    /// Seconds since the epoch.
+    pub first_seen: f64,
+    pub last_seen: f64,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Totals the conversations of the captures, in order of first appearance.
+pub fn flow_records(captures: &[Arc<LoadedCapture>]) -> Result<Vec<FlowRecord>, KcpdumpError> {
+    let link_types = packetlist::link_types(captures);
+    let mut streams: HashMap<CaptureId, StreamTable> = HashMap::new();
+    let mut records: Vec<FlowRecord> = Vec::new();
+    let mut index = HashMap::new();
+    for item in packetlist::merged_packets(captures) {
+        let (capture_id, _, packet) = item?;
+        let Some(key) = summary::summarize_link(link_types[&capture_id], &packet.data).flow else {
+            continue;
+        };
+        let time = packet.header.timestamp.as_secs_f64();
+        let slot = *index.entry((capture_id, key)).or_insert_with(|| {
+            records.push(FlowRecord {
+                capture_id,
+                stream: streams.entry(capture_id).or_default().id(key),
+                key,
+                packets: 0,
+                bytes: 0,
+                first_seen: time,
+                last_seen: time,
+            });
+            records.len() - 1
+        });
+        let record = &mut records[slot];
+        record.packets += 1;
+        record.bytes += u64::from(packet.header.orig_len);
+        record.last_seen = time;
+    }
+    Ok(records)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Conversation Layer
+/// The addresses that identify a conversation.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ConversationLayer {
+    /// MAC address pair.
+    Ethernet,
+    /// IPv4 or IPv6 address pair.
+    Ip,
+    /// Address and port pairs of TCP, UDP, UDP-Lite and DCCP.
+    Transport,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Conversation
+/// Traffic between two endpoints at one layer. Endpoint A is the source of
+/// the first packet seen.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Conversation {
+    pub capture_id: CaptureId,
+    pub layer: ConversationLayer,
+    /// Transport protocol, e.g. `TCP`; only set at the transport layer.
+    pub protocol: Option<String>,
+    pub address_a: String,
+    pub port_a: Option<u16>,
+    pub address_b: String,
+    pub port_b: Option<u16>,
+    pub packets: usize,
+    pub bytes: u64,
+    pub packets_a_to_b: usize,
+    pub bytes_a_to_b: u64,
+    pub packets_b_to_a: usize,
+    pub bytes_b_to_a: u64,
+    /// Seconds since the epoch.
+    pub start: f64,
+    pub end: f64,
+    /// Seconds from the first to the last packet.
+    pub duration: f64,
+    /// Location and owner of each IP address, when requested.
+    pub geo_a: Option<EndpointGeo>,
+    pub geo_b: Option<EndpointGeo>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Address {
+    Mac([u8; 6]),
+    Ip(IpAddr),
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Address::Mac(mac) => write!(f, "{}", MacAddress(*mac)),
+            Address::Ip(address) => write!(f, "{}", address),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+type Endpoint = (Address, Option<u16>);
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Conversation Builder
+/// Totals the conversations of one capture at one layer. Feed packets in
+/// capture order and call `finish` to obtain them in order of first
+/// appearance.
+pub struct ConversationBuilder {
+    capture_id: CaptureId,
+    layer: ConversationLayer,
+    conversations: Vec<Conversation>,
+    /// Slot in `conversations` and endpoint A, by protocol and both
+    /// endpoints in canonical order.
+    index: HashMap<(u8, Endpoint, Endpoint), (usize, Endpoint)>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ConversationBuilder {
+    pub fn new(capture_id: CaptureId, layer: ConversationLayer) -> Self {
+        ConversationBuilder {
+            capture_id,
+            layer,
+            conversations: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+
+    pub fn push(&mut self, layers: &PacketLayers) {
+        let Some((protocol, source, destination)) = self.endpoints(layers) else {
+            return;
+        };
+        let header = &layers.packet.header;
+        let time = header.timestamp.as_secs_f64();
+        let key = (protocol, source.min(destination), source.max(destination));
+        let (slot, a) = *self.index.entry(key).or_insert_with(|| {
+            self.conversations.push(Conversation {
+                capture_id: self.capture_id,
+                layer: self.layer,
+                protocol: (self.layer == ConversationLayer::Transport)
+                    .then(|| protocol_name(protocol)),
+                address_a: source.0.to_string(),
+                port_a: source.1,
+                address_b: destination.0.to_string(),
+                port_b: destination.1,
+                packets: 0,
+                bytes: 0,
+                packets_a_to_b: 0,
+                bytes_a_to_b: 0,
+                packets_b_to_a: 0,
+                bytes_b_to_a: 0,
+                start: time,
+                end: time,
+                duration: 0.0,
+                geo_a: None,
+                geo_b: None,
+            });
+            (self.conversations.len() - 1, source)
+        });
+        let conversation = &mut self.conversations[slot];
+        let bytes = u64::from(header.orig_len);
+        conversation.packets += 1;
+        conversation.bytes += bytes;
+        // Packets from an endpoint to itself count as A to B.
+        if source == a {
+            conversation.packets_a_to_b += 1;
+            conversation.bytes_a_to_b += bytes;
+        } else {
+            conversation.packets_b_to_a += 1;
+            conversation.bytes_b_to_a += bytes;
+        }
+        conversation.end = conversation.end.max(time);
+        conversation.start = conversation.start.min(time);
+        conversation.duration = conversation.end - conversation.start;
+    }
+
+    pub fn finish(self) -> Vec<Conversation> {
+        self.conversations
+    }
+
+    /// Protocol, source and destination of a packet at the builder's layer.
+    fn endpoints(&self, layers: &PacketLayers) -> Option<(u8, Endpoint, Endpoint)> {
+        let ip = match (&layers.ipv4, &layers.ipv6) {
+            (Some(ip), _) => Some((
+                ip.protocol,
+                Address::Ip(IpAddr::from(ip.source_ip)),
+                Address::Ip(IpAddr::from(ip.dest_ip)),
+            )),
+            (_, Some(ip)) => Some((
+                ip.next_header,
+                Address::Ip(IpAddr::from(ip.source_ip)),
+                Address::Ip(IpAddr::from(ip.dest_ip)),
+            )),
+            _ => None,
+        };
+        match self.layer {
+            ConversationLayer::Ethernet => {
+                let ethernet = layers.ethernet.as_ref()?;
+                Some((
+                    0,
+                    (Address::Mac(ethernet.header.src_mac.into()), None),
+                    (Address::Mac(ethernet.header.dest_mac.into()), None),
+                ))
+            }
+            ConversationLayer::Ip => {
+                let (_, source, destination) = ip?;
+                Some((0, (source, None), (destination, None)))
+            }
+            ConversationLayer::Transport => {
+                let (protocol, source, destination) = ip?;
+                let (source_port, dest_port) = match (
+                    &layers.tcp,
+                    &layers.udp,
+                    &layers.udplite,
+                    &layers.dccp,
+                ) {
+                    (Some(tcp), ..) => (tcp.source_port, tcp.dest_port),
+                    (_, Some(udp), ..) => (udp.source_port, udp.dest_port),
+                    (_, _, Some(udplite), _) => (udplite.source_port, udplite.dest_port),
+                    (.., Some(dccp)) => (dccp.source_port, dccp.dest_port),
+                    _ => return None,
+                };
+                Some((
+                    protocol,
+                    (source, Some(source_port)),
+                    (destination, Some(dest_port)),
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn protocol_name(protocol: u8) -> String {
+    match IpProtocol::from(protocol) {
+        IpProtocol::TCP => "TCP".to_string(),
+        IpProtocol::UDP => "UDP".to_string(),
+        IpProtocol::UDPLite => "UDP-Lite".to_string(),
+        IpProtocol::DCCP => "DCCP".to_string(),
+        _ => protocol.to_string(),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Lists the conversations of each capture at `layer`.
+pub fn conversations(
+    captures: &[Arc<LoadedCapture>],
+    layer: ConversationLayer,
+) -> Result<Vec<Conversation>, KcpdumpError> {
+    let mut conversations = Vec::new();
+    for capture in captures {
+        let mut builder = ConversationBuilder::new(capture.id, layer);
+        for (index, packet) in capture.packets().enumerate() {
+            let packet = packet?;
+            builder.push(&PacketLayers::decode_link(index + 1, capture.header.network, &packet));
+        }
+        conversations.extend(builder.finish());
+    }
+    Ok(conversations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timefmt::Timestamp;
+
+    #[test]
+    fn test_flow_key_is_bidirectional() {
+        let a = (IpAddr::V4(Ipv4Addr::new(192, 168, 0, 10)), 50000);
+        let b = (IpAddr::V4(Ipv4Addr::new(93, 184, 216, 34)), 80);
+        let forward = FlowKey::new(6, a, b);
+        let backward = FlowKey::new(6, b, a);
+        assert_eq!(forward, backward);
+        assert_ne!(forward, FlowKey::new(17, a, b));
+    }
+
+    #[test]
+    fn test_stream_ids_follow_first_appearance() {
+        let a = (IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 1000);
+        let b = (IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)), 80);
+        let c = (IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3)), 53);
+        let mut streams = StreamTable::default();
+        assert_eq!(streams.id(FlowKey::new(6, a, b)), 0);
+        assert_eq!(streams.id(FlowKey::new(17, a, c)), 1);
+        assert_eq!(streams.id(FlowKey::new(6, b, a)), 0);
+    }
+
+    #[test]
+    fn test_conversations_by_layer() {
+        use crate::cap::{PcapPacket, PcapPacketHeader};
+
+        // UDP between 10.0.0.1:1000 and 10.0.0.2:53, with MACs 02:..:01 and 02:..:02.
+        let frame = |forward: bool, port: u16, ts_sec: u32| {
+            let (mac_a, mac_b) = ([2, 0, 0, 0, 0, 1], [2, 0, 0, 0, 0, 2]);
+            let (ip_a, ip_b) = ([10, 0, 0, 1], [10, 0, 0, 2]);
+            let (ports_a, ports_b) = (port.to_be_bytes(), 53u16.to_be_bytes());
+            let (src_mac, dst_mac, src_ip, dst_ip, src_port, dst_port) = if forward {
+                (mac_b, mac_a, ip_a, ip_b, ports_a, ports_b)
+            } else {
+                (mac_a, mac_b, ip_b, ip_a, ports_b, ports_a)
+            };
+            let mut data = dst_mac.to_vec();
+            data.extend_from_slice(&src_mac);
+            data.extend_from_slice(&[0x08, 0x00, 0x45, 0, 0, 28, 0, 0, 0, 0, 64, 17, 0, 0]);
+            data.extend_from_slice(&src_ip);
+            data.extend_from_slice(&dst_ip);
+            data.extend_from_slice(&src_port);
+            data.extend_from_slice(&dst_port);
+            data.extend_from_slice(&[0, 8, 0, 0]);
+            PcapPacket {
+                header: PcapPacketHeader {
+                    timestamp: Timestamp::new(ts_sec, 0),
+                    incl_len: data.len() as u32,
+                    orig_len: data.len() as u32,
+                    link_type: None,
+                },
+                data,
+            }
+        };
+        let packets = [
+            frame(true, 1000, 10),
+            frame(false, 1000, 11),
+            frame(true, 1001, 12),
+            frame(true, 1000, 14),
+        ];
+        let totals = |layer| {
+            let mut builder = ConversationBuilder::new(1, layer);
+            for (index, packet) in packets.iter().enumerate() {
+                builder.push(&PacketLayers::decode(index + 1, packet));
+            }
+            builder.finish()
+        };
+
+        let ethernet = totals(ConversationLayer::Ethernet);
+        assert_eq!(ethernet.len(), 1);
+        assert_eq!(ethernet[0].address_a, "02:00:00:00:00:02");
+        assert_eq!(ethernet[0].packets, 4);
+
+        let ip = totals(ConversationLayer::Ip);
+        assert_eq!(ip.len(), 1);
+        assert_eq!(ip[0].address_a, "10.0.0.1");
+        assert_eq!((ip[0].packets_a_to_b, ip[0].packets_b_to_a), (3, 1));
+        assert_eq!(ip[0].bytes, 4 * 42);
+        assert_eq!(ip[0].duration, 4.0);
+
+        let transport = totals(ConversationLayer::Transport);
+        assert_eq!(transport.len(), 2);
+        assert_eq!(transport[0].protocol.as_deref(), Some("UDP"));
+        assert_eq!((transport[0].port_a, transport[0].port_b), (Some(1000), Some(53)));
+        assert_eq!(transport[0].packets, 3);
+        assert_eq!((transport[0].start, transport[0].end), (10.0, 14.0));
+        assert_eq!(transport[1].port_a, Some(1001));
+    }
+}
@@ -0,0 +1,381 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::Ipv4Addr;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc;
+
+use maxminddb::{Reader, geoip2};
+
+use crate::asn::AutonomousSystem;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::dissect::PacketLayers;
+use crate::error::KcpdumpError;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::session::LoadedCapture;
+
+/// Geo Location
+/// What a GeoIP database knows about one address.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct GeoLocation {
+    /// ISO 3166-1 alpha-2 code, e.g. `JP`.
+    pub country_code: Option<String>,
+    pub country: Option<String>,
+    pub city: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+/// GeoIP Database
+/// A MaxMind City or Country database (e.g. GeoLite2-City.mmdb) loaded into memory.
+pub struct GeoIpDatabase {
+    path: String,
+    reader: Reader<Vec<u8>>,
+}
+
+impl std::fmt::Debug for GeoIpDatabase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GeoIpDatabase")
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+impl GeoIpDatabase {
+    pub fn open(path: &str) -> Result<Self, KcpdumpError> {
+        let data = std::fs::read(path).map_err(|e| KcpdumpError::file(path, e))?;
+        let reader = Reader::from_source(data)
+            .map_err(|e| KcpdumpError::InvalidInput(format!("Invalid GeoIP database: {}", e)))?;
+        Ok(GeoIpDatabase {
+            path: path.to_string(),
+            reader,
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Looks up an address. Returns `None` for addresses the database does not cover.
+    pub fn lookup(&self, address: IpAddr) -> Option<GeoLocation> {
+        let city: geoip2::City = self.reader.lookup(address).ok()?;
+        let english = |names: Option<std::collections::BTreeMap<&str, &str>>| {
+            names.and_then(|names| names.get("en").map(|name| name.to_string()))
+        };
+        let location = city.location.as_ref();
+        Some(GeoLocation {
+            country_code: city
+                .country
+                .as_ref()
+                .and_then(|country| country.iso_code)
+                .map(str::to_string),
+            country: english(city.country.and_then(|country| country.names)),
+            city: english(city.city.and_then(|city| city.names)),
+            latitude: location.and_then(|location| location.latitude),
+            longitude: location.and_then(|location| location.longitude),
+        })
+    }
+}
+
+/// Endpoint Geo
+/// Where an address is and which network owns it, as far as the loaded
+/// databases know.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointGeo {
+    pub country_code: Option<String>,
+    pub country: Option<String>,
+    pub city: Option<String>,
+    pub asn: Option<AutonomousSystem>,
+}
+
+type Lookup<T> = Box<dyn Fn(IpAddr) -> Option<T> + Send + Sync>;
+
+/// Geo Enricher
+/// Annotates addresses with their location from `locate` and owner from
+/// `owner`, e.g. GeoIP and ASN database lookups, looking each address up
+/// once. Addresses that are not globally routable are never looked up.
+pub struct GeoEnricher {
+    locate: Lookup<GeoLocation>,
+    owner: Lookup<AutonomousSystem>,
+    cache: HashMap<IpAddr, Option<EndpointGeo>>,
+}
+
+impl GeoEnricher {
+    pub fn new(
+        locate: impl Fn(IpAddr) -> Option<GeoLocation> + Send + Sync + 'static,
+        owner: impl Fn(IpAddr) -> Option<AutonomousSystem> + Send + Sync + 'static,
+    ) -> Self {
+        GeoEnricher {
+            locate: Box::new(locate),
+            owner: Box::new(owner),
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Returns what is known about `address`, or `None` if nothing is.
+    pub fn lookup(&mut self, address: IpAddr) -> Option<EndpointGeo> {
+        let (locate, owner) = (&self.locate, &self.owner);
+        self.cache
+            .entry(address)
+            .or_insert_with(|| {
+                if !is_global(address) {
+                    return None;
+                }
+                let location = locate(address);
+                let asn = owner(address);
+                if location.is_none() && asn.is_none() {
+                    return None;
+                }
+                let location = location.unwrap_or_default();
+                Some(EndpointGeo {
+                    country_code: location.country_code,
+                    country: location.country,
+                    city: location.city,
+                    asn,
+                })
+            })
+            .clone()
+    }
+
+    /// Like `lookup`, for an address as rendered in a result; anything that
+    /// is not an IP address, such as a MAC address, has no location.
+    pub fn lookup_str(&mut self, address: &str) -> Option<EndpointGeo> {
+        self.lookup(address.parse().ok()?)
+    }
+}
+
+/// Country Traffic
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CountryTraffic {
+    pub country_code: String,
+    pub country: Option<String>,
+    pub packets: usize,
+    pub bytes: u64,
+    /// Distinct addresses located in the country.
+    pub addresses: usize,
+}
+
+/// Geo Point
+/// Traffic of all addresses that resolve to the same coordinates.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GeoPoint {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub country_code: Option<String>,
+    pub city: Option<String>,
+    pub packets: usize,
+    pub bytes: u64,
+    pub addresses: usize,
+}
+
+/// Geo Map
+/// Traffic volumes for a world map view. Every packet counts once for its
+/// source and once for its destination address, so totals are per endpoint.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GeoMap {
+    /// Sorted by bytes, descending.
+    pub countries: Vec<CountryTraffic>,
+    /// Sorted by bytes, descending.
+    pub points: Vec<GeoPoint>,
+    /// Endpoint traffic of private, local or unknown addresses.
+    pub unresolved_packets: usize,
+    pub unresolved_bytes: u64,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Default)]
+struct Counter {
+    packets: usize,
+    bytes: u64,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Aggregates endpoint traffic of the captures by the locations returned by `lookup`.
+pub fn geo_map(
+    captures: &[Arc<LoadedCapture>],
+    lookup: impl Fn(IpAddr) -> Option<GeoLocation>,
+) -> Result<GeoMap, KcpdumpError> {
+    let mut endpoints: HashMap<IpAddr, Counter> = HashMap::new();
+    for capture in captures {
+        for (index, packet) in capture.packets().enumerate() {
+            let packet = packet?;
+            let layers = PacketLayers::decode_link(index + 1, capture.header.network, &packet);
+            let Some(ip) = &layers.ipv4 else {
+                continue;
+            };
+            for address in [ip.source_ip, ip.dest_ip] {
+                let counter = endpoints
+                    .entry(IpAddr::V4(Ipv4Addr::from(address)))
+                    .or_default();
+                counter.packets += 1;
+                counter.bytes += u64::from(packet.header.orig_len);
+            }
+        }
+    }
+
+    let mut map = GeoMap::default();
+    let mut countries: HashMap<String, CountryTraffic> = HashMap::new();
+    // Coordinates are keyed by their bit patterns since f64 is not hashable.
+    let mut points: HashMap<(u64, u64), GeoPoint> = HashMap::new();
+    for (address, counter) in endpoints {
+        let location = is_global(address).then(|| lookup(address)).flatten();
+        let Some(location) = location
+            .filter(|location| location.country_code.is_some() || location.latitude.is_some())
+        else {
+            map.unresolved_packets += counter.packets;
+            map.unresolved_bytes += counter.bytes;
+            continue;
+        };
+        if let Some(code) = &location.country_code {
+            let country = countries
+                .entry(code.clone())
+                .or_insert_with(|| CountryTraffic {
+                    country_code: code.clone(),
+                    country: location.country.clone(),
+                    packets: 0,
+                    bytes: 0,
+                    addresses: 0,
+                });
+            country.packets += counter.packets;
+            country.bytes += counter.bytes;
+            country.addresses += 1;
+        }
+        if let (Some(latitude), Some(longitude)) = (location.latitude, location.longitude) {
+            let point = points
+                .entry((latitude.to_bits(), longitude.to_bits()))
+                .or_insert_with(|| GeoPoint {
+                    latitude,
+                    longitude,
+                    country_code: location.country_code.clone(),
+                    city: location.city.clone(),
+                    packets: 0,
+                    bytes: 0,
+                    addresses: 0,
+                });
+            point.packets += counter.packets;
+            point.bytes += counter.bytes;
+            point.addresses += 1;
+        }
+    }
+
+    map.countries = countries.into_values().collect();
+    map.countries.sort_by(|a, b| {
+        b.bytes
+            .cmp(&a.bytes)
+            .then_with(|| a.country_code.cmp(&b.country_code))
+    });
+    map.points = points.into_values().collect();
+    map.points.sort_by(|a, b| {
+        b.bytes
+            .cmp(&a.bytes)
+            .then_with(|| a.latitude.total_cmp(&b.latitude))
+            .then_with(|| a.longitude.total_cmp(&b.longitude))
+    });
+    Ok(map)
+}
+
+/// Whether an address can appear in a GeoIP or ASN database.
+pub(crate) fn is_global(address: IpAddr) -> bool {
+    match address {
+        IpAddr::V4(address) => {
+            !(address.is_private()
+                || address.is_loopback()
+                || address.is_link_local()
+                || address.is_multicast()
+                || address.is_broadcast()
+                || address.is_unspecified())
+        }
+        IpAddr::V6(address) => {
+            !(address.is_loopback()
+                || address.is_multicast()
+                || address.is_unspecified()
+                || address.is_unicast_link_local()
+                || address.is_unique_local())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(code: &str, latitude: f64, longitude: f64) -> GeoLocation {
+        GeoLocation {
+            country_code: Some(code.to_string()),
+            country: None,
+            city: None,
+            latitude: Some(latitude),
+            longitude: Some(longitude),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_geo_map_of_sample() {
+        let capture = Arc::new(LoadedCapture::load(1, "sample.pcap").await.unwrap());
+        let map = geo_map(std::slice::from_ref(&capture), |address| match address {
+            IpAddr::V4(address) if address.octets()[0] < 100 => Some(location("US", 37.75, -97.82)),
+            _ => Some(location("JP", 35.69, 139.69)),
+        })
+        .unwrap();
+        let endpoints = 2 * capture
+            .packets()
+            .map(Result::unwrap)
+            .enumerate()
+            .filter(|(index, packet)| PacketLayers::decode(index + 1, packet).ipv4.is_some())
+            .count();
+        let located: usize = map.countries.iter().map(|country| country.packets).sum();
+        let points: usize = map.points.iter().map(|point| point.packets).sum();
+        assert_eq!(located, points);
+        assert_eq!(located + map.unresolved_packets, endpoints);
+        assert!(
+            map.countries
+                .windows(2)
+                .all(|pair| pair[0].bytes >= pair[1].bytes)
+        );
+        assert!(map.points.len() <= 2);
+    }
+
+    #[test]
+    fn test_private_addresses_are_not_looked_up() {
+        assert!(!is_global(IpAddr::V4(Ipv4Addr::new(192, 168, 0, 10))));
+        assert!(!is_global(IpAddr::V4(Ipv4Addr::new(255, 255, 255, 255))));
+        assert!(is_global(IpAddr::V4(Ipv4Addr::new(93, 184, 216, 34))));
+        assert!(GeoIpDatabase::open("missing.mmdb").is_err());
+    }
+
+    #[test]
+    fn test_enricher() {
+        let lookups = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counted = Arc::clone(&lookups);
+        let mut enricher = GeoEnricher::new(
+            move |address| {
+                counted.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                (address == IpAddr::V4(Ipv4Addr::new(93, 184, 216, 34)))
+                    .then(|| location("US", 37.75, -97.82))
+            },
+            |address| match address {
+                IpAddr::V4(address) if address.octets()[0] == 8 => {
+                    Some(AutonomousSystem::new(15169, Some("Google LLC".to_string())))
+                }
+                _ => None,
+            },
+        );
+        let web = enricher.lookup_str("93.184.216.34").unwrap();
+        assert_eq!(web.country_code.as_deref(), Some("US"));
+        assert_eq!(web.asn, None);
+        let dns = enricher.lookup(IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8))).unwrap();
+        assert_eq!(dns.country_code, None);
+        assert_eq!(dns.asn.unwrap().label, "AS15169 Google LLC");
+        assert_eq!(enricher.lookup_str("1.1.1.1"), None);
+        assert_eq!(enricher.lookup_str("192.168.0.10"), None);
+        assert_eq!(enricher.lookup_str("00:11:22:33:44:55"), None);
+        // Each global address is looked up once.
+        enricher.lookup_str("93.184.216.34");
+        assert_eq!(lookups.load(std::sync::atomic::Ordering::Relaxed), 3);
+    }
+}
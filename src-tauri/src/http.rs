@@ -0,0 +1,357 @@
+use crate::flows::FlowKey;
+use crate::reassembly::TcpStream;
+
+/// HTTP Message
+/// An HTTP/1.x request or response parsed from a reassembled stream.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpMessage {
+    pub start_line: String,
+    pub headers: Vec<(String, String)>,
+    /// Body with any chunked transfer coding removed.
+    pub body: Vec<u8>,
+}
+
+impl HttpMessage {
+    /// Case-insensitive header lookup.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Status code of a response, e.g. 200.
+    pub fn status(&self) -> Option<u16> {
+        let mut parts = self.start_line.split_whitespace();
+        let version = parts.next()?;
+        if !version.starts_with("HTTP/") {
+            return None;
+        }
+        parts.next()?.parse().ok()
+    }
+
+    /// Method of a request, e.g. `GET`.
+    pub fn method(&self) -> Option<&str> {
+        let method = self.start_line.split_whitespace().next()?;
+        (!method.starts_with("HTTP/")).then_some(method)
+    }
+
+    /// Request target of a request, e.g. `/index.html`.
+    pub fn uri(&self) -> Option<&str> {
+        let mut parts = self.start_line.split_whitespace();
+        let method = parts.next()?;
+        if method.starts_with("HTTP/") {
+            return None;
+        }
+        parts.next()
+    }
+}
+
+/// Splits one direction of a stream into HTTP messages. Parsing stops at the
+/// first byte that does not start a message.
+pub fn parse_messages(data: &[u8], responses: bool) -> Vec<HttpMessage> {
+    parse_messages_at(data, responses)
+        .into_iter()
+        .map(|(_, message)| message)
+        .collect()
+}
+
+/// Like `parse_messages`, with the offset at which each message starts.
+fn parse_messages_at(data: &[u8], responses: bool) -> Vec<(usize, HttpMessage)> {
+    let mut messages = Vec::new();
+    let mut rest = data;
+    while let Some((message, next)) = parse_message(rest, responses) {
+        messages.push((data.len() - rest.len(), message));
+        rest = next;
+    }
+    messages
+}
+
+fn parse_message(data: &[u8], response: bool) -> Option<(HttpMessage, &[u8])> {
+    let header_end = find(data, b"\r\n\r\n")?;
+    let head = std::str::from_utf8(&data[..header_end]).ok()?;
+    let mut lines = head.split("\r\n");
+    let start_line = lines.next()?.to_string();
+    let is_response = start_line.starts_with("HTTP/");
+    if is_response != response || (!is_response && !start_line.contains(" HTTP/")) {
+        return None;
+    }
+    let headers: Vec<_> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect();
+    let mut message = HttpMessage {
+        start_line,
+        headers,
+        body: Vec::new(),
+    };
+
+    let rest = &data[header_end + 4..];
+    let chunked = message
+        .header("Transfer-Encoding")
+        .is_some_and(|value| value.to_ascii_lowercase().contains("chunked"));
+    let rest = if chunked {
+        let (body, rest) = decode_chunked(rest);
+        message.body = body;
+        rest
+    } else if let Some(length) = message
+        .header("Content-Length")
+        .and_then(|value| value.parse::<usize>().ok())
+    {
+        let length = length.min(rest.len());
+        message.body = rest[..length].to_vec();
+        &rest[length..]
+    } else if response && !matches!(message.status(), Some(100..=199 | 204 | 304)) {
+        // Without framing the body runs until the connection closes.
+        message.body = rest.to_vec();
+        &rest[rest.len()..]
+    } else {
+        rest
+    };
+    Some((message, rest))
+}
+
+/// Decodes a chunked body, returning it and the bytes after the last chunk.
+fn decode_chunked(mut data: &[u8]) -> (Vec<u8>, &[u8]) {
+    let mut body = Vec::new();
+    loop {
+        let Some(line_end) = find(data, b"\r\n") else {
+            return (body, &data[data.len()..]);
+        };
+        let size = std::str::from_utf8(&data[..line_end])
+            .ok()
+            .and_then(|line| usize::from_str_radix(line.split(';').next()?.trim(), 16).ok());
+        let Some(size) = size else {
+            return (body, &data[data.len()..]);
+        };
+        data = &data[line_end + 2..];
+        if size == 0 {
+            // Skip optional trailers up to the terminating empty line.
+            let end = find(data, b"\r\n\r\n")
+                .filter(|_| !data.starts_with(b"\r\n"))
+                .map_or(2, |position| position + 4);
+            return (body, &data[end.min(data.len())..]);
+        }
+        let available = size.min(data.len());
+        body.extend_from_slice(&data[..available]);
+        data = &data[(available + 2).min(data.len())..];
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// HTTP Object
+/// A response body paired with the request that fetched it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpObject {
+    pub host: Option<String>,
+    pub uri: String,
+    pub content_type: Option<String>,
+    pub filename: String,
+    pub data: Vec<u8>,
+}
+
+/// Extracts the non-empty response bodies of a stream.
+pub fn objects(stream: &TcpStream) -> Vec<HttpObject> {
+    let requests = parse_messages(&stream.client_data.data, false);
+    let responses = parse_messages(&stream.server_data.data, true);
+    responses
+        .into_iter()
+        .filter(|response| !matches!(response.status(), Some(100..=199)))
+        .zip(requests.iter().map(Some).chain(std::iter::repeat(None)))
+        .filter(|(response, _)| !response.body.is_empty())
+        .map(|(response, request)| {
+            let uri = request
+                .and_then(|r| r.uri())
+                .unwrap_or_default()
+                .to_string();
+            let filename = response
+                .header("Content-Disposition")
+                .and_then(disposition_filename)
+                .unwrap_or_else(|| uri_filename(&uri));
+            HttpObject {
+                host: request.and_then(|r| r.header("Host")).map(str::to_string),
+                content_type: response.header("Content-Type").map(str::to_string),
+                uri,
+                filename,
+                data: response.body,
+            }
+        })
+        .collect()
+}
+
+/// HTTP Transaction
+/// A request and the response that answered it; either may be missing when
+/// it was not captured.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HttpTransaction {
+    /// Index in the analysis result, used to select a body for export.
+    pub id: usize,
+    pub stream: FlowKey,
+    /// Frame number of the segment carrying the start of the request.
+    pub request_number: Option<usize>,
+    /// Frame number of the segment carrying the start of the response.
+    pub response_number: Option<usize>,
+    pub method: Option<String>,
+    pub uri: Option<String>,
+    pub host: Option<String>,
+    pub status: Option<u16>,
+    pub content_type: Option<String>,
+    pub request_body_size: usize,
+    /// Size of the response body after removing chunked transfer coding.
+    pub body_size: usize,
+    /// File name suggested for the response body.
+    pub filename: String,
+    #[serde(skip)]
+    pub body: Vec<u8>,
+}
+
+/// Pairs the requests of a stream with its final responses in order, as
+/// HTTP/1.x pipelining requires. Ids are left at zero for the caller to assign.
+pub fn transactions(stream: &TcpStream) -> Vec<HttpTransaction> {
+    let requests = parse_messages_at(&stream.client_data.data, false);
+    let responses: Vec<_> = parse_messages_at(&stream.server_data.data, true)
+        .into_iter()
+        .filter(|(_, response)| !matches!(response.status(), Some(100..=199)))
+        .collect();
+    let count = requests.len().max(responses.len());
+    let mut requests = requests.into_iter();
+    let mut responses = responses.into_iter();
+    (0..count)
+        .map(|_| {
+            let request = requests.next();
+            let response = responses.next();
+            let uri = request
+                .as_ref()
+                .and_then(|(_, r)| r.uri())
+                .map(str::to_string);
+            let filename = response
+                .as_ref()
+                .and_then(|(_, r)| r.header("Content-Disposition"))
+                .and_then(disposition_filename)
+                .unwrap_or_else(|| uri_filename(uri.as_deref().unwrap_or_default()));
+            HttpTransaction {
+                id: 0,
+                stream: stream.key,
+                request_number: request
+                    .as_ref()
+                    .and_then(|&(offset, _)| stream.client_data.frame_at(offset)),
+                response_number: response
+                    .as_ref()
+                    .and_then(|&(offset, _)| stream.server_data.frame_at(offset)),
+                method: request
+                    .as_ref()
+                    .and_then(|(_, r)| r.method())
+                    .map(str::to_string),
+                uri,
+                host: request
+                    .as_ref()
+                    .and_then(|(_, r)| r.header("Host"))
+                    .map(str::to_string),
+                status: response.as_ref().and_then(|(_, r)| r.status()),
+                content_type: response
+                    .as_ref()
+                    .and_then(|(_, r)| r.header("Content-Type"))
+                    .map(str::to_string),
+                request_body_size: request.as_ref().map_or(0, |(_, r)| r.body.len()),
+                body_size: response.as_ref().map_or(0, |(_, r)| r.body.len()),
+                filename,
+                body: response.map(|(_, r)| r.body).unwrap_or_default(),
+            }
+        })
+        .collect()
+}
+
+fn disposition_filename(value: &str) -> Option<String> {
+    value.split(';').find_map(|part| {
+        let (key, name) = part.trim().split_once('=')?;
+        (key.eq_ignore_ascii_case("filename")).then(|| name.trim_matches('"').to_string())
+    })
+}
+
+fn uri_filename(uri: &str) -> String {
+    let path = uri.split(['?', '#']).next().unwrap_or_default();
+    match path.rsplit('/').next() {
+        Some(name) if !name.is_empty() => name.to_string(),
+        _ => "index.html".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pipelined_responses() {
+        let data = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhelloHTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n2\r\nde\r\n0\r\n\r\n";
+        let messages = parse_messages(data, true);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].body, b"hello");
+        assert_eq!(messages[1].body, b"abcde");
+        assert_eq!(messages[1].status(), Some(200));
+    }
+
+    #[test]
+    fn test_requests_and_filenames() {
+        let data = b"GET /files/report.pdf?x=1 HTTP/1.1\r\nHost: example.com\r\n\r\nGET / HTTP/1.1\r\n\r\n";
+        let messages = parse_messages(data, false);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].uri(), Some("/files/report.pdf?x=1"));
+        assert_eq!(messages[0].header("host"), Some("example.com"));
+        assert_eq!(uri_filename("/files/report.pdf?x=1"), "report.pdf");
+        assert_eq!(uri_filename("/"), "index.html");
+        assert_eq!(
+            disposition_filename("attachment; filename=\"data.csv\""),
+            Some("data.csv".to_string())
+        );
+        assert!(parse_messages(b"\x16\x03\x01 not http", false).is_empty());
+    }
+
+    #[test]
+    fn test_transactions() {
+        use crate::reassembly::StreamDirection;
+        use std::net::{IpAddr, Ipv4Addr};
+
+        let client = (IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 49152);
+        let server = (IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)), 80);
+        let request = b"GET /a.txt HTTP/1.1\r\nHost: example.com\r\n\r\n";
+        let mut client_data = request.to_vec();
+        client_data.extend_from_slice(b"POST /form HTTP/1.1\r\nContent-Length: 3\r\n\r\nx=1");
+        let response = b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 2\r\n\r\nhi";
+        let stream = TcpStream {
+            key: FlowKey::new(6, client, server),
+            client,
+            server,
+            first_number: 1,
+            client_data: StreamDirection {
+                data: client_data,
+                complete: true,
+                frames: vec![(0, 4), (request.len(), 7)],
+            },
+            server_data: StreamDirection {
+                data: response.to_vec(),
+                complete: true,
+                frames: vec![(0, 5)],
+            },
+        };
+        let pairs = transactions(&stream);
+        assert_eq!(pairs.len(), 2);
+        assert_eq!(pairs[0].method.as_deref(), Some("GET"));
+        assert_eq!(pairs[0].host.as_deref(), Some("example.com"));
+        assert_eq!(pairs[0].request_number, Some(4));
+        assert_eq!(pairs[0].response_number, Some(5));
+        assert_eq!(pairs[0].status, Some(200));
+        assert_eq!(pairs[0].content_type.as_deref(), Some("text/plain"));
+        assert_eq!(pairs[0].body, b"hi");
+        assert_eq!(pairs[0].filename, "a.txt");
+        // The second request was not answered within the capture.
+        assert_eq!(pairs[1].request_number, Some(7));
+        assert_eq!(pairs[1].request_body_size, 3);
+        assert_eq!(pairs[1].status, None);
+    }
+}
@@ -0,0 +1,436 @@
+use crate::error::KcpdumpError;
+
+/// HTTP/3 frame types (RFC 9114, section 7.2).
+pub const FRAME_DATA: u64 = 0x00;
+pub const FRAME_HEADERS: u64 = 0x01;
+pub const FRAME_CANCEL_PUSH: u64 = 0x03;
+pub const FRAME_SETTINGS: u64 = 0x04;
+pub const FRAME_PUSH_PROMISE: u64 = 0x05;
+pub const FRAME_GOAWAY: u64 = 0x07;
+pub const FRAME_MAX_PUSH_ID: u64 = 0x0d;
+
+/// QPACK static table (RFC 9204, appendix A).
+const STATIC_TABLE: [(&str, &str); 99] = [
+    (":authority", ""),
+    (":path", "/"),
+    ("age", "0"),
+    ("content-disposition", ""),
+    ("content-length", "0"),
+    ("cookie", ""),
+    ("date", ""),
+    ("etag", ""),
+    ("if-modified-since", ""),
+    ("if-none-match", ""),
+    ("last-modified", ""),
+    ("link", ""),
+    ("location", ""),
+    ("referer", ""),
+    ("set-cookie", ""),
+    (":method", "CONNECT"),
+    (":method", "DELETE"),
+    (":method", "GET"),
+    (":method", "HEAD"),
+    (":method", "OPTIONS"),
+    (":method", "POST"),
+    (":method", "PUT"),
+    (":scheme", "http"),
+    (":scheme", "https"),
+    (":status", "103"),
+    (":status", "200"),
+    (":status", "304"),
+    (":status", "404"),
+    (":status", "503"),
+    ("accept", "*/*"),
+    ("accept", "application/dns-message"),
+    ("accept-encoding", "gzip, deflate, br"),
+    ("accept-ranges", "bytes"),
+    ("access-control-allow-headers", "cache-control"),
+    ("access-control-allow-headers", "content-type"),
+    ("access-control-allow-origin", "*"),
+    ("cache-control", "max-age=0"),
+    ("cache-control", "max-age=2592000"),
+    ("cache-control", "max-age=604800"),
+    ("cache-control", "no-cache"),
+    ("cache-control", "no-store"),
+    ("cache-control", "public, max-age=31536000"),
+    ("content-encoding", "br"),
+    ("content-encoding", "gzip"),
+    ("content-type", "application/dns-message"),
+    ("content-type", "application/javascript"),
+    ("content-type", "application/json"),
+    ("content-type", "application/x-www-form-urlencoded"),
+    ("content-type", "image/gif"),
+    ("content-type", "image/jpeg"),
+    ("content-type", "image/png"),
+    ("content-type", "text/css"),
+    ("content-type", "text/html; charset=utf-8"),
+    ("content-type", "text/plain"),
+    ("content-type", "text/plain;charset=utf-8"),
+    ("range", "bytes=0-"),
+    ("strict-transport-security", "max-age=31536000"),
+    (
+        "strict-transport-security",
+        "max-age=31536000; includesubdomains",
+    ),
+    (
+        "strict-transport-security",
+        "max-age=31536000; includesubdomains; preload",
+    ),
+    ("vary", "accept-encoding"),
+    ("vary", "origin"),
+    ("x-content-type-options", "nosniff"),
+    ("x-xss-protection", "1; mode=block"),
+    (":status", "100"),
+    (":status", "204"),
+    (":status", "206"),
+    (":status", "302"),
+    (":status", "400"),
+    (":status", "403"),
+    (":status", "421"),
+    (":status", "425"),
+    (":status", "500"),
+    ("accept-language", ""),
+    ("access-control-allow-credentials", "FALSE"),
+    ("access-control-allow-credentials", "TRUE"),
+    ("access-control-allow-headers", "*"),
+    ("access-control-allow-methods", "get"),
+    ("access-control-allow-methods", "get, post, options"),
+    ("access-control-allow-methods", "options"),
+    ("access-control-expose-headers", "content-length"),
+    ("access-control-request-headers", "content-type"),
+    ("access-control-request-method", "get"),
+    ("access-control-request-method", "post"),
+    ("alt-svc", "clear"),
+    ("authorization", ""),
+    (
+        "content-security-policy",
+        "script-src 'none'; object-src 'none'; base-uri 'none'",
+    ),
+    ("early-data", "1"),
+    ("expect-ct", ""),
+    ("forwarded", ""),
+    ("if-range", ""),
+    ("origin", ""),
+    ("purpose", "prefetch"),
+    ("server", ""),
+    ("timing-allow-origin", "*"),
+    ("upgrade-insecure-requests", "1"),
+    ("user-agent", ""),
+    ("x-forwarded-for", ""),
+    ("x-frame-options", "deny"),
+    ("x-frame-options", "sameorigin"),
+];
+
+/// Reads a QUIC variable-length integer, returning it and its length.
+pub fn read_varint(data: &[u8]) -> Option<(u64, usize)> {
+    let first = *data.first()?;
+    let length = 1usize << (first >> 6);
+    let bytes = data.get(..length)?;
+    let value = bytes[1..]
+        .iter()
+        .fold(u64::from(first & 0x3f), |value, &byte| {
+            value << 8 | u64::from(byte)
+        });
+    Some((value, length))
+}
+
+/// Http3 Frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct Http3Frame {
+    pub frame_type: u64,
+    pub payload: Vec<u8>,
+}
+
+impl Http3Frame {
+    pub fn name(&self) -> String {
+        let name = match self.frame_type {
+            FRAME_DATA => "DATA",
+            FRAME_HEADERS => "HEADERS",
+            FRAME_CANCEL_PUSH => "CANCEL_PUSH",
+            FRAME_SETTINGS => "SETTINGS",
+            FRAME_PUSH_PROMISE => "PUSH_PROMISE",
+            FRAME_GOAWAY => "GOAWAY",
+            FRAME_MAX_PUSH_ID => "MAX_PUSH_ID",
+            // Reserved types exercise the requirement to ignore unknown frames.
+            other if other >= 0x21 && (other - 0x21) % 0x1f == 0 => "Reserved",
+            other => return format!("Unknown (0x{:x})", other),
+        };
+        name.to_string()
+    }
+
+    /// Identifier and value pairs of a SETTINGS frame.
+    pub fn settings(&self) -> Vec<(u64, u64)> {
+        let mut settings = Vec::new();
+        let mut rest = self.payload.as_slice();
+        while let Some((id, id_len)) = read_varint(rest) {
+            let Some((value, value_len)) = read_varint(&rest[id_len..]) else {
+                break;
+            };
+            settings.push((id, value));
+            rest = &rest[id_len + value_len..];
+        }
+        settings
+    }
+}
+
+/// Splits the decrypted data of one stream direction into HTTP/3 frames.
+/// A truncated last frame is left out.
+pub fn parse_frames(mut data: &[u8]) -> Vec<Http3Frame> {
+    let mut frames = Vec::new();
+    while let Some((frame_type, type_len)) = read_varint(data) {
+        let Some((length, length_len)) = read_varint(&data[type_len..]) else {
+            break;
+        };
+        let start = type_len + length_len;
+        let Some(payload) = usize::try_from(length)
+            .ok()
+            .and_then(|length| data.get(start..start.checked_add(length)?))
+        else {
+            break;
+        };
+        frames.push(Http3Frame {
+            frame_type,
+            payload: payload.to_vec(),
+        });
+        data = &data[start + payload.len()..];
+    }
+    frames
+}
+
+/// Reads an HPACK/QPACK prefixed integer whose prefix has `bits` bits.
+fn read_prefixed(data: &[u8], bits: u32) -> Result<(u64, usize), KcpdumpError> {
+    let mask = ((1u16 << bits) - 1) as u8;
+    let first = *data
+        .first()
+        .ok_or(KcpdumpError::malformed(0, "QPACK integer truncated"))?;
+    let mut value = u64::from(first & mask);
+    if value < u64::from(mask) {
+        return Ok((value, 1));
+    }
+    for (index, &byte) in data[1..].iter().enumerate() {
+        let shift = 7 * index as u32;
+        if shift > 56 {
+            return Err(KcpdumpError::malformed(
+                1 + index,
+                "QPACK integer too large",
+            ));
+        }
+        value += u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok((value, index + 2));
+        }
+    }
+    Err(KcpdumpError::malformed(0, "QPACK integer truncated"))
+}
+
+/// Reads a string literal whose length has a `bits` bit prefix, preceded by
+/// the Huffman flag.
+fn read_string(data: &[u8], bits: u32) -> Result<(String, usize), KcpdumpError> {
+    let first = *data
+        .first()
+        .ok_or(KcpdumpError::malformed(0, "QPACK string truncated"))?;
+    if first & (1 << bits) != 0 {
+        return Err(KcpdumpError::malformed(
+            0,
+            "Huffman coded QPACK strings are not supported",
+        ));
+    }
+    let (length, length_len) = read_prefixed(data, bits)?;
+    let end = usize::try_from(length)
+        .ok()
+        .and_then(|length| length_len.checked_add(length))
+        .ok_or(KcpdumpError::malformed(0, "QPACK string too long"))?;
+    let bytes = data.get(length_len..end).ok_or(KcpdumpError::malformed(
+        length_len,
+        "QPACK string truncated",
+    ))?;
+    Ok((String::from_utf8_lossy(bytes).into_owned(), end))
+}
+
+fn static_entry(index: u64) -> Result<(&'static str, &'static str), KcpdumpError> {
+    usize::try_from(index)
+        .ok()
+        .and_then(|index| STATIC_TABLE.get(index))
+        .copied()
+        .ok_or(KcpdumpError::malformed(
+            0,
+            "QPACK static table index out of range",
+        ))
+}
+
+/// Decodes a QPACK field section, such as the payload of a HEADERS frame.
+/// Only sections that do not reference the dynamic table are supported.
+pub fn decode_field_section(data: &[u8]) -> Result<Vec<(String, String)>, KcpdumpError> {
+    let (required_insert_count, length) = read_prefixed(data, 8)?;
+    if required_insert_count != 0 {
+        return Err(KcpdumpError::malformed(
+            0,
+            "QPACK dynamic table references are not supported",
+        ));
+    }
+    let (_, base_len) = read_prefixed(data.get(length..).unwrap_or_default(), 7)?;
+    let mut rest = &data[length + base_len..];
+    let mut fields = Vec::new();
+    while let Some(&first) = rest.first() {
+        let (name, value, consumed) = if first & 0x80 != 0 {
+            // Indexed field line.
+            if first & 0x40 == 0 {
+                return Err(KcpdumpError::malformed(
+                    0,
+                    "QPACK dynamic table references are not supported",
+                ));
+            }
+            let (index, consumed) = read_prefixed(rest, 6)?;
+            let (name, value) = static_entry(index)?;
+            (name.to_string(), value.to_string(), consumed)
+        } else if first & 0x40 != 0 {
+            // Literal field line with name reference.
+            if first & 0x10 == 0 {
+                return Err(KcpdumpError::malformed(
+                    0,
+                    "QPACK dynamic table references are not supported",
+                ));
+            }
+            let (index, name_len) = read_prefixed(rest, 4)?;
+            let (value, value_len) = read_string(&rest[name_len..], 7)?;
+            (
+                static_entry(index)?.0.to_string(),
+                value,
+                name_len + value_len,
+            )
+        } else if first & 0x20 != 0 {
+            // Literal field line with literal name.
+            let (name, name_len) = read_string(rest, 3)?;
+            let (value, value_len) = read_string(&rest[name_len..], 7)?;
+            (name, value, name_len + value_len)
+        } else {
+            return Err(KcpdumpError::malformed(
+                0,
+                "QPACK dynamic table references are not supported",
+            ));
+        };
+        fields.push((name, value));
+        rest = &rest[consumed..];
+    }
+    Ok(fields)
+}
+
+/// Http3 Transaction
+/// A request and its response, decoded from one bidirectional stream.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Http3Transaction {
+    pub method: Option<String>,
+    pub authority: Option<String>,
+    pub path: Option<String>,
+    pub status: Option<u16>,
+    pub request_headers: Vec<(String, String)>,
+    pub response_headers: Vec<(String, String)>,
+    pub request_body_len: usize,
+    pub response_body_len: usize,
+    /// Why a header block could not be decoded, if one could not.
+    pub error: Option<String>,
+}
+
+/// Builds the transaction of a request stream from the decrypted bytes the
+/// client and the server sent on it.
+pub fn parse_transaction(request: &[u8], response: &[u8]) -> Http3Transaction {
+    let mut error = None;
+    let mut direction = |data: &[u8]| {
+        let mut headers = Vec::new();
+        let mut body_len = 0;
+        for frame in parse_frames(data) {
+            match frame.frame_type {
+                FRAME_HEADERS => match decode_field_section(&frame.payload) {
+                    Ok(fields) => headers.extend(fields),
+                    Err(message) => error = Some(message.to_string()),
+                },
+                FRAME_DATA => body_len += frame.payload.len(),
+                _ => {}
+            }
+        }
+        (headers, body_len)
+    };
+    let (request_headers, request_body_len) = direction(request);
+    let (response_headers, response_body_len) = direction(response);
+    let find = |headers: &[(String, String)], name: &str| {
+        headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.clone())
+    };
+    Http3Transaction {
+        method: find(&request_headers, ":method"),
+        authority: find(&request_headers, ":authority"),
+        path: find(&request_headers, ":path"),
+        status: find(&response_headers, ":status").and_then(|status| status.parse().ok()),
+        request_headers,
+        response_headers,
+        request_body_len,
+        response_body_len,
+        error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_varint() {
+        assert_eq!(read_varint(&[0x25]), Some((37, 1)));
+        assert_eq!(read_varint(&[0x7b, 0xbd]), Some((15293, 2)));
+        assert_eq!(read_varint(&[0x9d, 0x7f, 0x3e, 0x7d]), Some((494878333, 4)));
+        assert_eq!(read_varint(&[0x40]), None);
+    }
+
+    #[test]
+    fn test_transaction() {
+        // GET https://example.com/index.html with a literal user-agent.
+        let mut section = vec![0x00, 0x00, 0xd1, 0xd7, 0x50, 0x0b];
+        section.extend_from_slice(b"example.com");
+        section.extend_from_slice(&[0x51, 0x0b]);
+        section.extend_from_slice(b"/index.html");
+        section.extend_from_slice(&[0x24, b'x', b'-', b'i', b'd', 0x01, b'7']);
+        let mut request = vec![0x01, section.len() as u8];
+        request.extend_from_slice(&section);
+
+        // 200 with text/html, then a 5 byte DATA frame.
+        let mut response = vec![0x01, 0x04, 0x00, 0x00, 0xd9, 0xf4];
+        response.extend_from_slice(&[0x00, 0x05]);
+        response.extend_from_slice(b"hello");
+        // A truncated frame at the end is ignored.
+        response.extend_from_slice(&[0x00, 0x05, b'x']);
+
+        let transaction = parse_transaction(&request, &response);
+        assert_eq!(transaction.method.as_deref(), Some("GET"));
+        assert_eq!(transaction.authority.as_deref(), Some("example.com"));
+        assert_eq!(transaction.path.as_deref(), Some("/index.html"));
+        assert_eq!(
+            transaction.request_headers.last(),
+            Some(&("x-id".to_string(), "7".to_string()))
+        );
+        assert_eq!(transaction.status, Some(200));
+        assert_eq!(
+            transaction.response_headers[1],
+            (
+                "content-type".to_string(),
+                "text/html; charset=utf-8".to_string()
+            )
+        );
+        assert_eq!(transaction.response_body_len, 5);
+        assert_eq!(transaction.error, None);
+
+        let dynamic = parse_transaction(&[0x01, 0x03, 0x02, 0x00, 0x80], &[]);
+        assert!(dynamic.error.is_some());
+    }
+
+    #[test]
+    fn test_settings() {
+        let frames = parse_frames(&[0x04, 0x05, 0x06, 0x44, 0x00, 0x01, 0x00, 0x21, 0x00]);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].name(), "SETTINGS");
+        assert_eq!(frames[0].settings(), [(6, 1024), (1, 0)]);
+        assert_eq!(frames[1].name(), "Reserved");
+    }
+}
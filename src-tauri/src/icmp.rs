@@ -0,0 +1,587 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::collections::HashMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::IpAddr;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc;
+
+use crate::dissect::{Dissector, FieldInfo, FieldType, FieldValue, FieldValues, PacketLayers};
+use crate::error::KcpdumpError;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::session::{CaptureId, LoadedCapture};
+
+pub const ICMP_ECHO_REPLY: u8 = 0;
+pub const ICMP_DEST_UNREACHABLE: u8 = 3;
+pub const ICMP_ECHO_REQUEST: u8 = 8;
+pub const ICMP_TIME_EXCEEDED: u8 = 11;
+pub const ICMP_PARAMETER_PROBLEM: u8 = 12;
+
+pub const ICMPV6_DEST_UNREACHABLE: u8 = 1;
+pub const ICMPV6_PACKET_TOO_BIG: u8 = 2;
+pub const ICMPV6_TIME_EXCEEDED: u8 = 3;
+pub const ICMPV6_PARAMETER_PROBLEM: u8 = 4;
+pub const ICMPV6_ECHO_REQUEST: u8 = 128;
+pub const ICMPV6_ECHO_REPLY: u8 = 129;
+
+/// Extension object class of an MPLS label stack (RFC 4950).
+pub const CLASS_MPLS_LABEL_STACK: u8 = 1;
+
+/// Offset extensions start at when a sender sets no original datagram
+/// length, as pre-RFC 4884 MPLS routers do.
+const LEGACY_EXTENSION_OFFSET: usize = 128;
+
+/// Mpls Label
+/// One label stack entry quoted in an ICMP extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MplsLabel {
+    pub label: u32,
+    /// Traffic class, formerly the experimental bits.
+    pub traffic_class: u8,
+    pub bottom_of_stack: bool,
+    pub ttl: u8,
+}
+
+/// Icmp Extension Object
+#[derive(Debug, Clone, PartialEq)]
+pub struct IcmpExtensionObject {
+    pub class_num: u8,
+    pub c_type: u8,
+    pub payload: Vec<u8>,
+}
+
+impl IcmpExtensionObject {
+    /// Label stack entries of an MPLS incoming label stack object.
+    pub fn mpls_labels(&self) -> Vec<MplsLabel> {
+        if self.class_num != CLASS_MPLS_LABEL_STACK || self.c_type != 1 {
+            return Vec::new();
+        }
+        self.payload
+            .chunks_exact(4)
+            .map(|entry| {
+                let word = u32::from_be_bytes([entry[0], entry[1], entry[2], entry[3]]);
+                MplsLabel {
+                    label: word >> 12,
+                    traffic_class: ((word >> 9) & 0x07) as u8,
+                    bottom_of_stack: word & 0x100 != 0,
+                    ttl: (word & 0xff) as u8,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Icmp Message
+/// An ICMP or ICMPv6 message, with the multipart extension structure
+/// (RFC 4884) of error messages separated from the quoted original datagram.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IcmpMessage {
+    /// 4 for ICMP, 6 for ICMPv6; the two number their types differently.
+    pub version: u8,
+    pub icmp_type: u8,
+    pub code: u8,
+    pub checksum: u16,
+    /// The second word of the header: identifier and sequence of echo
+    /// messages, or the original datagram length of error messages.
+    pub rest_of_header: [u8; 4],
+    /// Echo data, or the quoted original datagram of error messages.
+    pub data: Vec<u8>,
+    /// Version of the extension structure, if one is present.
+    pub extension_version: Option<u8>,
+    pub extensions: Vec<IcmpExtensionObject>,
+    /// Whether the checksum matches, once validated against the carrying IP
+    /// packet, which ICMPv6 includes through its pseudo-header.
+    pub checksum_valid: Option<bool>,
+}
+
+impl TryFrom<&[u8]> for IcmpMessage {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        Self::parse(data, 4)
+    }
+}
+
+impl IcmpMessage {
+    /// Parses the payload of an IPv6 packet whose next header is ICMPv6.
+    pub fn from_icmpv6(data: &[u8]) -> Result<Self, KcpdumpError> {
+        Self::parse(data, 6)
+    }
+
+    fn parse(data: &[u8], version: u8) -> Result<Self, KcpdumpError> {
+        if data.len() < 8 {
+            return Err(KcpdumpError::malformed(
+                0,
+                "Data too short for ICMP message",
+            ));
+        }
+        let body = &data[8..];
+        let mut message = IcmpMessage {
+            version,
+            icmp_type: data[0],
+            code: data[1],
+            checksum: u16::from_be_bytes([data[2], data[3]]),
+            rest_of_header: [data[4], data[5], data[6], data[7]],
+            data: body.to_vec(),
+            extension_version: None,
+            extensions: Vec::new(),
+            checksum_valid: None,
+        };
+        if !message.is_error() || (version == 6 && message.icmp_type == ICMPV6_PACKET_TOO_BIG) {
+            return Ok(message);
+        }
+        // ICMP counts the original datagram length in 4-byte words, ICMPv6
+        // in 8-byte words and in the first byte of the second header word.
+        let length = match version {
+            6 => usize::from(data[4]) * 8,
+            _ => usize::from(data[5]) * 4,
+        };
+        let offset = match length {
+            0 if version == 4 && body.len() > LEGACY_EXTENSION_OFFSET => LEGACY_EXTENSION_OFFSET,
+            0 => return Ok(message),
+            // RFC 4884 pads the original datagram to at least 128 bytes.
+            length => length.max(LEGACY_EXTENSION_OFFSET),
+        };
+        if let Some((version, extensions)) = body.get(offset..).and_then(parse_extensions) {
+            message.data.truncate(offset);
+            message.extension_version = Some(version);
+            message.extensions = extensions;
+        }
+        Ok(message)
+    }
+}
+
+/// Parses an extension structure: a version and checksum header followed by
+/// length, class and type prefixed objects.
+fn parse_extensions(mut data: &[u8]) -> Option<(u8, Vec<IcmpExtensionObject>)> {
+    let version = data.first()? >> 4;
+    if version != 2 || data.len() < 4 {
+        return None;
+    }
+    data = &data[4..];
+    let mut objects = Vec::new();
+    while !data.is_empty() {
+        let header = data.get(..4)?;
+        let length = usize::from(u16::from_be_bytes([header[0], header[1]]));
+        if length < 4 {
+            return None;
+        }
+        objects.push(IcmpExtensionObject {
+            class_num: header[2],
+            c_type: header[3],
+            payload: data.get(4..length)?.to_vec(),
+        });
+        data = &data[length..];
+    }
+    Some((version, objects))
+}
+
+impl IcmpMessage {
+    pub fn is_echo_request(&self) -> bool {
+        match self.version {
+            6 => self.icmp_type == ICMPV6_ECHO_REQUEST,
+            _ => self.icmp_type == ICMP_ECHO_REQUEST,
+        }
+    }
+
+    pub fn is_echo_reply(&self) -> bool {
+        match self.version {
+            6 => self.icmp_type == ICMPV6_ECHO_REPLY,
+            _ => self.icmp_type == ICMP_ECHO_REPLY,
+        }
+    }
+
+    /// Whether the message reports an error and quotes the datagram that
+    /// caused it.
+    pub fn is_error(&self) -> bool {
+        match self.version {
+            6 => self.icmp_type < 128,
+            _ => matches!(
+                self.icmp_type,
+                ICMP_DEST_UNREACHABLE | ICMP_TIME_EXCEEDED | ICMP_PARAMETER_PROBLEM
+            ),
+        }
+    }
+
+    /// Identifier of an echo message.
+    pub fn identifier(&self) -> u16 {
+        u16::from_be_bytes([self.rest_of_header[0], self.rest_of_header[1]])
+    }
+
+    /// Sequence number of an echo message.
+    pub fn sequence(&self) -> u16 {
+        u16::from_be_bytes([self.rest_of_header[2], self.rest_of_header[3]])
+    }
+
+    /// The quoted header and leading payload of the datagram an error
+    /// message refers to.
+    pub fn original_datagram(&self) -> Option<&[u8]> {
+        Some(self.data.as_slice()).filter(|_| self.is_error())
+    }
+
+    /// Every MPLS label stack entry quoted in the extensions.
+    pub fn mpls_labels(&self) -> Vec<MplsLabel> {
+        self.extensions
+            .iter()
+            .flat_map(IcmpExtensionObject::mpls_labels)
+            .collect()
+    }
+
+    /// Describes the message for the packet list.
+    pub fn describe(&self) -> String {
+        let (id, seq) = (self.identifier(), self.sequence());
+        let mut info = match (self.icmp_type, self.code) {
+            (_, 0) if self.is_echo_request() => {
+                format!("Echo (ping) request id=0x{:04x}, seq={}", id, seq)
+            }
+            (_, 0) if self.is_echo_reply() => {
+                format!("Echo (ping) reply id=0x{:04x}, seq={}", id, seq)
+            }
+            (ICMPV6_DEST_UNREACHABLE, code) if self.version == 6 => {
+                format!("Destination unreachable (Code {})", code)
+            }
+            (ICMPV6_PACKET_TOO_BIG, _) if self.version == 6 => format!(
+                "Packet too big (MTU {})",
+                u32::from_be_bytes(self.rest_of_header)
+            ),
+            (ICMPV6_TIME_EXCEEDED, 0) if self.version == 6 => {
+                "Time exceeded (Hop limit exceeded in transit)".to_string()
+            }
+            (icmp_type, code) if self.version == 6 => format!("Type {} Code {}", icmp_type, code),
+            (ICMP_TIME_EXCEEDED, 0) => {
+                "Time-to-live exceeded (Time to live exceeded in transit)".to_string()
+            }
+            (ICMP_TIME_EXCEEDED, 1) => {
+                "Time-to-live exceeded (Fragment reassembly time exceeded)".to_string()
+            }
+            (ICMP_DEST_UNREACHABLE, code) => format!("Destination unreachable (Code {})", code),
+            (icmp_type, code) => format!("Type {} Code {}", icmp_type, code),
+        };
+        let labels = self.mpls_labels();
+        if !labels.is_empty() {
+            let stack: Vec<String> = labels
+                .iter()
+                .map(|entry| format!("{}", entry.label))
+                .collect();
+            info += &format!(" [MPLS Label={}]", stack.join("/"));
+        }
+        info
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Echo Pair
+/// An echo request with the reply that answered it, if any.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EchoPair {
+    pub capture_id: CaptureId,
+    /// 4 for ICMP, 6 for ICMPv6.
+    pub version: u8,
+    /// The host that sent the request.
+    pub source: IpAddr,
+    pub destination: IpAddr,
+    pub identifier: u16,
+    pub sequence: u16,
+    pub request_number: usize,
+    /// Seconds since the epoch.
+    pub request_time: f64,
+    /// TTL or hop limit of the request.
+    pub request_ttl: u8,
+    pub reply_number: Option<usize>,
+    pub reply_time: Option<f64>,
+    pub reply_ttl: Option<u8>,
+    /// Seconds from the request to its reply.
+    pub rtt: Option<f64>,
+    /// Echo data bytes of the request.
+    pub data_length: usize,
+    /// Further replies to the same request.
+    pub duplicate_replies: usize,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Echo Pair Builder
+/// Pairs echo requests and replies of one capture fed in frame order. A
+/// reply answers the oldest unanswered request with the same addresses,
+/// identifier and sequence number.
+#[derive(Debug, Default)]
+pub struct EchoPairBuilder {
+    capture_id: CaptureId,
+    pairs: Vec<EchoPair>,
+    by_key: HashMap<(IpAddr, IpAddr, u16, u16), Vec<usize>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl EchoPairBuilder {
+    pub fn new(capture_id: CaptureId) -> Self {
+        EchoPairBuilder {
+            capture_id,
+            ..Default::default()
+        }
+    }
+
+    pub fn push(&mut self, layers: &PacketLayers) {
+        let (icmp, source, destination, ttl) = match (&layers.ipv4, &layers.ipv6) {
+            (Some(ip), _) => match &layers.icmp {
+                Some(icmp) => (icmp, IpAddr::from(ip.source_ip), IpAddr::from(ip.dest_ip), ip.ttl),
+                None => return,
+            },
+            (_, Some(ip)) => match &layers.icmpv6 {
+                Some(icmp) => (icmp, ip.source_ip.into(), ip.dest_ip.into(), ip.hop_limit),
+                None => return,
+            },
+            _ => return,
+        };
+        let header = &layers.packet.header;
+        let time = header.timestamp.as_secs_f64();
+        let (identifier, sequence) = (icmp.identifier(), icmp.sequence());
+
+        if icmp.is_echo_request() {
+            self.by_key
+                .entry((source, destination, identifier, sequence))
+                .or_default()
+                .push(self.pairs.len());
+            self.pairs.push(EchoPair {
+                capture_id: self.capture_id,
+                version: icmp.version,
+                source,
+                destination,
+                identifier,
+                sequence,
+                request_number: layers.number,
+                request_time: time,
+                request_ttl: ttl,
+                reply_number: None,
+                reply_time: None,
+                reply_ttl: None,
+                rtt: None,
+                data_length: icmp.data.len(),
+                duplicate_replies: 0,
+            });
+        } else if icmp.is_echo_reply() {
+            let Some(indices) = self.by_key.get(&(destination, source, identifier, sequence))
+            else {
+                return;
+            };
+            let unanswered = indices
+                .iter()
+                .find(|&&index| self.pairs[index].reply_number.is_none());
+            match unanswered {
+                Some(&index) => {
+                    let pair = &mut self.pairs[index];
+                    pair.reply_number = Some(layers.number);
+                    pair.reply_time = Some(time);
+                    pair.reply_ttl = Some(ttl);
+                    pair.rtt = Some(time - pair.request_time);
+                }
+                None => {
+                    if let Some(&index) = indices.last() {
+                        self.pairs[index].duplicate_replies += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the pairs in request order.
+    pub fn finish(self) -> Vec<EchoPair> {
+        self.pairs
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Pairs the ICMP and ICMPv6 echo requests of the captures with their replies.
+pub fn echo_pairs(captures: &[Arc<LoadedCapture>]) -> Result<Vec<EchoPair>, KcpdumpError> {
+    let mut pairs = Vec::new();
+    for capture in captures {
+        let mut builder = EchoPairBuilder::new(capture.id);
+        for (index, packet) in capture.packets().enumerate() {
+            let packet = packet?;
+            builder.push(&PacketLayers::decode_link(index + 1, capture.header.network, &packet));
+        }
+        pairs.extend(builder.finish());
+    }
+    Ok(pairs)
+}
+
+/// ICMP Dissector
+pub struct IcmpDissector;
+
+const ICMP_FIELDS: &[FieldInfo] = &[
+    FieldInfo::new("icmp.type", FieldType::UInt, "Type"),
+    FieldInfo::new("icmp.code", FieldType::UInt, "Code"),
+    FieldInfo::new("icmp.checksum_good", FieldType::Bool, "Checksum is valid"),
+    FieldInfo::new("icmp.ext.version", FieldType::UInt, "Extension version"),
+    FieldInfo::new("icmp.ext.class", FieldType::UInt, "Extension object class"),
+    FieldInfo::new("icmp.mpls.label", FieldType::UInt, "MPLS label"),
+    FieldInfo::new("icmp.mpls.tc", FieldType::UInt, "MPLS traffic class"),
+    FieldInfo::new("icmp.mpls.s", FieldType::Bool, "MPLS bottom of stack"),
+    FieldInfo::new("icmp.mpls.ttl", FieldType::UInt, "MPLS TTL"),
+];
+
+impl Dissector for IcmpDissector {
+    fn protocol(&self) -> &'static str {
+        "icmp"
+    }
+
+    fn description(&self) -> &'static str {
+        "Internet Control Message Protocol"
+    }
+
+    fn fields(&self) -> &'static [FieldInfo] {
+        ICMP_FIELDS
+    }
+
+    fn dissect(&self, layers: &PacketLayers, values: &mut FieldValues) {
+        let Some(icmp) = &layers.icmp else {
+            return;
+        };
+        values.push("icmp", FieldValue::Protocol);
+        values.push("icmp.type", FieldValue::UInt(icmp.icmp_type.into()));
+        values.push("icmp.code", FieldValue::UInt(icmp.code.into()));
+        if let Some(valid) = icmp.checksum_valid {
+            values.push("icmp.checksum_good", FieldValue::Bool(valid));
+        }
+        if let Some(version) = icmp.extension_version {
+            values.push("icmp.ext.version", FieldValue::UInt(version.into()));
+        }
+        for object in &icmp.extensions {
+            values.push("icmp.ext.class", FieldValue::UInt(object.class_num.into()));
+        }
+        for entry in icmp.mpls_labels() {
+            values.push("icmp.mpls.label", FieldValue::UInt(entry.label.into()));
+            values.push("icmp.mpls.tc", FieldValue::UInt(entry.traffic_class.into()));
+            values.push("icmp.mpls.s", FieldValue::Bool(entry.bottom_of_stack));
+            values.push("icmp.mpls.ttl", FieldValue::UInt(entry.ttl.into()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timefmt::Timestamp;
+    use crate::cap::{PcapPacket, PcapPacketHeader};
+
+    /// Time exceeded quoting a 128 byte datagram, then an MPLS object with
+    /// labels 16010 and 24001.
+    fn time_exceeded(length_words: u8) -> Vec<u8> {
+        let mut data = vec![ICMP_TIME_EXCEEDED, 0, 0, 0, 0, length_words, 0, 0];
+        data.extend_from_slice(&[0x45; 128]);
+        data.extend_from_slice(&[0x20, 0x00, 0x00, 0x00]);
+        data.extend_from_slice(&[0x00, 0x0c, 0x01, 0x01]);
+        data.extend_from_slice(&[0x03, 0xe8, 0xa0, 0x01]);
+        data.extend_from_slice(&[0x05, 0xdc, 0x11, 0x01]);
+        data
+    }
+
+    #[test]
+    fn test_mpls_extension() {
+        let message = IcmpMessage::try_from(time_exceeded(32).as_slice()).unwrap();
+        assert_eq!(message.data.len(), 128);
+        assert_eq!(message.extension_version, Some(2));
+        assert_eq!(
+            message.mpls_labels(),
+            [
+                MplsLabel {
+                    label: 16010,
+                    traffic_class: 0,
+                    bottom_of_stack: false,
+                    ttl: 1,
+                },
+                MplsLabel {
+                    label: 24001,
+                    traffic_class: 0,
+                    bottom_of_stack: true,
+                    ttl: 1,
+                },
+            ]
+        );
+        assert_eq!(
+            message.describe(),
+            "Time-to-live exceeded (Time to live exceeded in transit) [MPLS Label=16010/24001]"
+        );
+
+        // Senders predating RFC 4884 leave the length unset.
+        let legacy = IcmpMessage::try_from(time_exceeded(0).as_slice()).unwrap();
+        assert_eq!(legacy.mpls_labels().len(), 2);
+    }
+
+    fn echo_frame(request: bool, sequence: u16, ts_usec: u32) -> PcapPacket {
+        let (source, destination) = if request {
+            ([10, 0, 0, 1], [10, 0, 0, 2])
+        } else {
+            ([10, 0, 0, 2], [10, 0, 0, 1])
+        };
+        let icmp_type = if request { ICMP_ECHO_REQUEST } else { ICMP_ECHO_REPLY };
+        let mut data = vec![0x02, 0, 0, 0, 0, 0x02, 0x02, 0, 0, 0, 0, 0x01, 0x08, 0x00];
+        data.extend_from_slice(&[0x45, 0, 0, 32, 0, 0, 0, 0, 64, 1, 0, 0]);
+        data.extend_from_slice(&source);
+        data.extend_from_slice(&destination);
+        data.extend_from_slice(&[icmp_type, 0, 0, 0, 0x12, 0x34]);
+        data.extend_from_slice(&sequence.to_be_bytes());
+        data.extend_from_slice(b"abcd");
+        PcapPacket {
+            header: PcapPacketHeader {
+                timestamp: Timestamp::from_micros(1, ts_usec),
+                incl_len: data.len() as u32,
+                orig_len: data.len() as u32,
+                link_type: None,
+            },
+            data,
+        }
+    }
+
+    #[test]
+    fn test_echo_pairs() {
+        let packets = [
+            echo_frame(true, 1, 0),
+            echo_frame(true, 2, 100_000),
+            echo_frame(false, 1, 25_000),
+            echo_frame(false, 1, 30_000),
+        ];
+        let mut builder = EchoPairBuilder::new(7);
+        for (index, packet) in packets.iter().enumerate() {
+            builder.push(&PacketLayers::decode(index + 1, packet));
+        }
+        let pairs = builder.finish();
+        assert_eq!(pairs.len(), 2);
+        assert_eq!(pairs[0].capture_id, 7);
+        assert_eq!(pairs[0].identifier, 0x1234);
+        assert_eq!(pairs[0].reply_number, Some(3));
+        assert_eq!(pairs[0].reply_ttl, Some(64));
+        assert!((pairs[0].rtt.unwrap() - 0.025).abs() < 1e-9);
+        assert_eq!(pairs[0].duplicate_replies, 1);
+        assert_eq!(pairs[0].data_length, 4);
+        assert_eq!(pairs[1].sequence, 2);
+        assert_eq!(pairs[1].rtt, None);
+    }
+
+    #[test]
+    fn test_icmpv6() {
+        let echo = [ICMPV6_ECHO_REPLY, 0, 0, 0, 0x00, 0x07, 0x00, 0x03];
+        let message = IcmpMessage::from_icmpv6(&echo[..]).unwrap();
+        assert!(message.is_echo_reply());
+        assert_eq!(message.describe(), "Echo (ping) reply id=0x0007, seq=3");
+
+        let mut unreachable = vec![ICMPV6_DEST_UNREACHABLE, 4, 0, 0, 0, 0, 0, 0];
+        unreachable.extend_from_slice(&[0x60; 48]);
+        let message = IcmpMessage::from_icmpv6(&unreachable).unwrap();
+        assert!(message.is_error());
+        assert_eq!(message.original_datagram().map(<[u8]>::len), Some(48));
+        assert_eq!(message.describe(), "Destination unreachable (Code 4)");
+    }
+
+    #[test]
+    fn test_no_extension() {
+        let mut data = time_exceeded(0);
+        data.truncate(8 + 28);
+        let message = IcmpMessage::try_from(data.as_slice()).unwrap();
+        assert_eq!(message.data.len(), 28);
+        assert_eq!(message.extension_version, None);
+
+        let echo = [ICMP_ECHO_REQUEST, 0, 0, 0, 0x00, 0x42, 0x00, 0x01];
+        let message = IcmpMessage::try_from(&echo[..]).unwrap();
+        assert_eq!(message.describe(), "Echo (ping) request id=0x0042, seq=1");
+        assert!(IcmpMessage::try_from(&echo[..4]).is_err());
+    }
+}
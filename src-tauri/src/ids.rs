@@ -0,0 +1,1062 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
+
+use crate::dissect::PacketLayers;
+use crate::error::KcpdumpError;
+use crate::flows::FlowKey;
+use crate::packetlist;
+use crate::reassembly::{StreamDirection, StreamReassembler};
+use crate::session::{CaptureId, LoadedCapture};
+
+pub mod pcre;
+
+use pcre::{Regex, StepLimit};
+
+/// Variables assumed when a rule file refers to them and the caller did not
+/// define them.
+const DEFAULT_VARIABLES: &[(&str, &str)] = &[
+    ("HOME_NET", "any"),
+    ("EXTERNAL_NET", "any"),
+    ("HTTP_PORTS", "80"),
+];
+
+/// Options that only describe a rule and do not affect matching.
+const METADATA_OPTIONS: &[&str] = &[
+    "reference",
+    "metadata",
+    "priority",
+    "gid",
+    "target",
+    "fast_pattern",
+    "rawbytes",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum AddressSpec {
+    Any,
+    Network(IpAddr, u8),
+    List(Vec<AddressSpec>),
+    Not(Box<AddressSpec>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum PortSpec {
+    Any,
+    Range(u16, u16),
+    List(Vec<PortSpec>),
+    Not(Box<PortSpec>),
+}
+
+/// Matches a list: any positive entry (or none given) and no negated one.
+fn list_matches<T>(
+    entries: &[T],
+    negated: impl Fn(&T) -> Option<&T>,
+    matches: impl Fn(&T) -> bool,
+) -> bool {
+    let mut positive = None;
+    for entry in entries {
+        match negated(entry) {
+            Some(inner) if matches(inner) => return false,
+            Some(_) => {}
+            None => positive = Some(positive.unwrap_or(false) || matches(entry)),
+        }
+    }
+    positive.unwrap_or(true)
+}
+
+impl AddressSpec {
+    fn matches(&self, address: IpAddr) -> bool {
+        match self {
+            AddressSpec::Any => true,
+            AddressSpec::Network(IpAddr::V4(network), prefix) => match address {
+                IpAddr::V4(address) => {
+                    let mask = u32::MAX.checked_shl(32 - u32::from(*prefix)).unwrap_or(0);
+                    u32::from(address) & mask == u32::from(*network) & mask
+                }
+                IpAddr::V6(_) => false,
+            },
+            AddressSpec::Network(IpAddr::V6(network), prefix) => match address {
+                IpAddr::V6(address) => {
+                    let mask = u128::MAX.checked_shl(128 - u32::from(*prefix)).unwrap_or(0);
+                    u128::from(address) & mask == u128::from(*network) & mask
+                }
+                IpAddr::V4(_) => false,
+            },
+            AddressSpec::List(entries) => list_matches(
+                entries,
+                |entry| match entry {
+                    AddressSpec::Not(inner) => Some(inner),
+                    _ => None,
+                },
+                |entry| entry.matches(address),
+            ),
+            AddressSpec::Not(inner) => !inner.matches(address),
+        }
+    }
+}
+
+impl PortSpec {
+    /// Whether `port` matches; packets without ports only match `any`.
+    fn matches(&self, port: Option<u16>) -> bool {
+        match (self, port) {
+            (PortSpec::Any, _) => true,
+            (_, None) => false,
+            (PortSpec::Range(low, high), Some(port)) => (*low..=*high).contains(&port),
+            (PortSpec::List(entries), Some(_)) => list_matches(
+                entries,
+                |entry| match entry {
+                    PortSpec::Not(inner) => Some(inner),
+                    _ => None,
+                },
+                |entry| entry.matches(port),
+            ),
+            (PortSpec::Not(inner), Some(_)) => !inner.matches(port),
+        }
+    }
+}
+
+/// Splits on commas outside brackets.
+fn split_list(text: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (index, c) in text.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(text[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(text[start..].trim());
+    parts
+}
+
+/// Rule Variables
+/// Values of `$NAME` references in rule headers.
+struct Variables<'a>(&'a HashMap<String, String>);
+
+impl Variables<'_> {
+    fn resolve(&self, name: &str) -> Result<String, KcpdumpError> {
+        self.0
+            .get(name)
+            .cloned()
+            .or_else(|| {
+                DEFAULT_VARIABLES
+                    .iter()
+                    .find(|(default, _)| *default == name)
+                    .map(|(_, value)| value.to_string())
+            })
+            .ok_or_else(|| KcpdumpError::invalid_rule(format!("Undefined variable ${}", name)))
+    }
+
+    fn address(&self, text: &str, depth: usize) -> Result<AddressSpec, KcpdumpError> {
+        if depth > 8 {
+            return Err(KcpdumpError::invalid_rule("Variables nested too deeply"));
+        }
+        let text = text.trim();
+        if let Some(inner) = text.strip_prefix('!') {
+            return Ok(AddressSpec::Not(Box::new(self.address(inner, depth)?)));
+        }
+        if let Some(name) = text.strip_prefix('$') {
+            return self.address(&self.resolve(name)?, depth + 1);
+        }
+        if let Some(inner) = text
+            .strip_prefix('[')
+            .and_then(|text| text.strip_suffix(']'))
+        {
+            return split_list(inner)
+                .into_iter()
+                .map(|entry| self.address(entry, depth))
+                .collect::<Result<_, _>>()
+                .map(AddressSpec::List);
+        }
+        if text == "any" {
+            return Ok(AddressSpec::Any);
+        }
+        let (address, prefix) = text.split_once('/').unwrap_or((text, ""));
+        let address: IpAddr = address
+            .parse()
+            .map_err(|_| KcpdumpError::invalid_rule(format!("Invalid address '{}'", text)))?;
+        let bits = if address.is_ipv4() { 32 } else { 128 };
+        let prefix = if prefix.is_empty() {
+            bits
+        } else {
+            prefix
+                .parse()
+                .ok()
+                .filter(|prefix| *prefix <= bits)
+                .ok_or_else(|| {
+                    KcpdumpError::invalid_rule(format!("Invalid prefix length in '{}'", text))
+                })?
+        };
+        Ok(AddressSpec::Network(address, prefix))
+    }
+
+    fn port(&self, text: &str, depth: usize) -> Result<PortSpec, KcpdumpError> {
+        if depth > 8 {
+            return Err(KcpdumpError::invalid_rule("Variables nested too deeply"));
+        }
+        let text = text.trim();
+        if let Some(inner) = text.strip_prefix('!') {
+            return Ok(PortSpec::Not(Box::new(self.port(inner, depth)?)));
+        }
+        if let Some(name) = text.strip_prefix('$') {
+            return self.port(&self.resolve(name)?, depth + 1);
+        }
+        if let Some(inner) = text
+            .strip_prefix('[')
+            .and_then(|text| text.strip_suffix(']'))
+        {
+            return split_list(inner)
+                .into_iter()
+                .map(|entry| self.port(entry, depth))
+                .collect::<Result<_, _>>()
+                .map(PortSpec::List);
+        }
+        if text == "any" {
+            return Ok(PortSpec::Any);
+        }
+        let parse = |value: &str, default: u16| -> Result<u16, KcpdumpError> {
+            if value.is_empty() {
+                return Ok(default);
+            }
+            value
+                .parse()
+                .map_err(|_| KcpdumpError::invalid_rule(format!("Invalid port '{}'", text)))
+        };
+        let (low, high) = match text.split_once(':') {
+            Some((low, high)) => (parse(low, 0)?, parse(high, u16::MAX)?),
+            None => {
+                let port = parse(text, 0)?;
+                (port, port)
+            }
+        };
+        Ok(PortSpec::Range(low, high))
+    }
+}
+
+/// Rule Protocol
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RuleProtocol {
+    Ip,
+    Tcp,
+    Udp,
+    Icmp,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct ContentMatch {
+    pattern: Vec<u8>,
+    negated: bool,
+    nocase: bool,
+    offset: usize,
+    depth: Option<usize>,
+    distance: Option<isize>,
+    within: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Matcher {
+    Content(ContentMatch),
+    Pcre {
+        regex: Regex,
+        negated: bool,
+        relative: bool,
+        anchored: bool,
+    },
+}
+
+/// Ids Rule
+/// One rule of the supported Suricata/Snort subset: header address and port
+/// constraints, `flow` direction and state, and `content`/`pcre` matchers
+/// with their position modifiers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdsRule {
+    pub sid: u32,
+    pub rev: u32,
+    pub msg: String,
+    pub classtype: Option<String>,
+    protocol: RuleProtocol,
+    source: AddressSpec,
+    source_ports: PortSpec,
+    destination: AddressSpec,
+    destination_ports: PortSpec,
+    bidirectional: bool,
+    to_server: Option<bool>,
+    established: bool,
+    matchers: Vec<Matcher>,
+}
+
+/// Splits the option list on `;` outside quotes, honoring `\` escapes.
+fn split_options(text: &str) -> Vec<String> {
+    let mut options = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                current.push(c);
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            '"' => {
+                quoted = !quoted;
+                current.push(c);
+            }
+            ';' if !quoted => {
+                options.push(current.trim().to_string());
+                current.clear();
+            }
+            _ => current.push(c),
+        }
+    }
+    if !current.trim().is_empty() {
+        options.push(current.trim().to_string());
+    }
+    options
+}
+
+fn strip_quotes(value: &str) -> Result<&str, KcpdumpError> {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .ok_or_else(|| {
+            KcpdumpError::invalid_rule(format!("Expected a quoted value, found '{}'", value))
+        })
+}
+
+/// Removes the quotes and `\` escapes of an option value.
+fn unquote(value: &str) -> Result<String, KcpdumpError> {
+    let mut out = String::new();
+    let mut chars = strip_quotes(value)?.chars();
+    while let Some(c) = chars.next() {
+        out.push(if c == '\\' {
+            chars.next().unwrap_or('\\')
+        } else {
+            c
+        });
+    }
+    Ok(out)
+}
+
+/// Decodes a content string with `|41 42|` hex sections.
+fn content_bytes(text: &str) -> Result<Vec<u8>, KcpdumpError> {
+    let mut bytes = Vec::new();
+    for (index, part) in text.split('|').enumerate() {
+        if index % 2 == 0 {
+            bytes.extend_from_slice(part.as_bytes());
+            continue;
+        }
+        let digits: String = part.chars().filter(|c| !c.is_whitespace()).collect();
+        if !digits.len().is_multiple_of(2) {
+            return Err(KcpdumpError::invalid_rule(format!(
+                "Odd number of hex digits in |{}|",
+                part
+            )));
+        }
+        for pair in digits.as_bytes().chunks(2) {
+            let pair = std::str::from_utf8(pair).unwrap_or("");
+            bytes.push(
+                u8::from_str_radix(pair, 16).map_err(|_| {
+                    KcpdumpError::invalid_rule(format!("Invalid hex byte '{}'", pair))
+                })?,
+            );
+        }
+    }
+    if text.split('|').count().is_multiple_of(2) {
+        return Err(KcpdumpError::invalid_rule(
+            "Unterminated hex section in content",
+        ));
+    }
+    Ok(bytes)
+}
+
+fn parse_pcre(value: &str) -> Result<Matcher, KcpdumpError> {
+    let (negated, value) = match value.strip_prefix('!') {
+        Some(value) => (true, value),
+        None => (false, value),
+    };
+    // Escapes are left to the regex, which reads `\;` and `\"` literally.
+    let expression = strip_quotes(value)?;
+    let body = expression
+        .strip_prefix('/')
+        .ok_or_else(|| KcpdumpError::invalid_rule("pcre must have the form \"/pattern/flags\""))?;
+    let end = body
+        .rfind('/')
+        .ok_or_else(|| KcpdumpError::invalid_rule("pcre must have the form \"/pattern/flags\""))?;
+    let (mut caseless, mut dotall, mut multiline, mut relative, mut anchored) =
+        (false, false, false, false, false);
+    for flag in body[end + 1..].chars() {
+        match flag {
+            'i' => caseless = true,
+            's' => dotall = true,
+            'm' => multiline = true,
+            'R' => relative = true,
+            'A' => anchored = true,
+            other => {
+                return Err(KcpdumpError::invalid_rule(format!(
+                    "Unsupported pcre flag '{}'",
+                    other
+                )));
+            }
+        }
+    }
+    Ok(Matcher::Pcre {
+        regex: Regex::new(&body.as_bytes()[..end], caseless, dotall, multiline)?,
+        negated,
+        relative,
+        anchored,
+    })
+}
+
+impl IdsRule {
+    fn parse(line: &str, variables: &Variables) -> Result<Self, KcpdumpError> {
+        let open = line
+            .find('(')
+            .ok_or_else(|| KcpdumpError::invalid_rule("Missing rule options"))?;
+        let close = line
+            .rfind(')')
+            .ok_or_else(|| KcpdumpError::invalid_rule("Missing ')' after rule options"))?;
+        let header: Vec<&str> = line[..open].split_whitespace().collect();
+        let [
+            action,
+            protocol,
+            source,
+            source_ports,
+            direction,
+            destination,
+            destination_ports,
+        ] = header.as_slice()
+        else {
+            return Err(KcpdumpError::invalid_rule(
+                "Rule header must have seven fields",
+            ));
+        };
+        if *action != "alert" {
+            return Err(KcpdumpError::invalid_rule(format!(
+                "Unsupported action '{}'",
+                action
+            )));
+        }
+        let protocol = match *protocol {
+            "ip" => RuleProtocol::Ip,
+            "tcp" | "http" | "tls" | "ssh" | "smtp" | "ftp" => RuleProtocol::Tcp,
+            "udp" | "dns" => RuleProtocol::Udp,
+            "icmp" => RuleProtocol::Icmp,
+            other => {
+                return Err(KcpdumpError::invalid_rule(format!(
+                    "Unsupported protocol '{}'",
+                    other
+                )));
+            }
+        };
+        let bidirectional = match *direction {
+            "->" => false,
+            "<>" => true,
+            other => {
+                return Err(KcpdumpError::invalid_rule(format!(
+                    "Invalid direction '{}'",
+                    other
+                )));
+            }
+        };
+        let mut rule = IdsRule {
+            sid: 0,
+            rev: 0,
+            msg: String::new(),
+            classtype: None,
+            protocol,
+            source: variables.address(source, 0)?,
+            source_ports: variables.port(source_ports, 0)?,
+            destination: variables.address(destination, 0)?,
+            destination_ports: variables.port(destination_ports, 0)?,
+            bidirectional,
+            to_server: None,
+            established: false,
+            matchers: Vec::new(),
+        };
+
+        for option in split_options(&line[open + 1..close]) {
+            let (name, value) = match option.split_once(':') {
+                Some((name, value)) => (name.trim(), value.trim()),
+                None => (option.as_str(), ""),
+            };
+            let number = |value: &str| -> Result<usize, KcpdumpError> {
+                value.parse().map_err(|_| {
+                    KcpdumpError::invalid_rule(format!("Invalid {} value '{}'", name, value))
+                })
+            };
+            let last_content = rule
+                .matchers
+                .iter_mut()
+                .rev()
+                .find_map(|matcher| match matcher {
+                    Matcher::Content(content) => Some(content),
+                    Matcher::Pcre { .. } => None,
+                });
+            match name {
+                "msg" => rule.msg = unquote(value)?,
+                "sid" => rule.sid = number(value)? as u32,
+                "rev" => rule.rev = number(value)? as u32,
+                "classtype" => rule.classtype = Some(value.to_string()),
+                "flow" => {
+                    for flag in value.split(',').map(str::trim) {
+                        match flag {
+                            "to_server" | "from_client" => rule.to_server = Some(true),
+                            "to_client" | "from_server" => rule.to_server = Some(false),
+                            "established" => rule.established = true,
+                            "stateless" => {}
+                            other => {
+                                return Err(KcpdumpError::invalid_rule(format!(
+                                    "Unsupported flow option '{}'",
+                                    other
+                                )));
+                            }
+                        }
+                    }
+                }
+                "content" => {
+                    let (negated, value) = match value.strip_prefix('!') {
+                        Some(value) => (true, value.trim()),
+                        None => (false, value),
+                    };
+                    let pattern = content_bytes(&unquote(value)?)?;
+                    if pattern.is_empty() {
+                        return Err(KcpdumpError::invalid_rule("Empty content"));
+                    }
+                    rule.matchers.push(Matcher::Content(ContentMatch {
+                        pattern,
+                        negated,
+                        nocase: false,
+                        offset: 0,
+                        depth: None,
+                        distance: None,
+                        within: None,
+                    }));
+                }
+                "nocase" | "offset" | "depth" | "distance" | "within" => {
+                    let content = last_content.ok_or_else(|| {
+                        KcpdumpError::invalid_rule(format!(
+                            "'{}' without a preceding content",
+                            name
+                        ))
+                    })?;
+                    match name {
+                        "nocase" => content.nocase = true,
+                        "offset" => content.offset = number(value)?,
+                        "depth" => content.depth = Some(number(value)?),
+                        "distance" => {
+                            content.distance = Some(value.parse().map_err(|_| {
+                                KcpdumpError::invalid_rule(format!(
+                                    "Invalid distance value '{}'",
+                                    value
+                                ))
+                            })?)
+                        }
+                        _ => content.within = Some(number(value)?),
+                    }
+                }
+                "pcre" => rule.matchers.push(parse_pcre(value)?),
+                name if METADATA_OPTIONS.contains(&name) => {}
+                other => {
+                    return Err(KcpdumpError::invalid_rule(format!(
+                        "Unsupported rule option '{}'",
+                        other
+                    )));
+                }
+            }
+        }
+        if rule.sid == 0 {
+            return Err(KcpdumpError::invalid_rule("Rule has no sid"));
+        }
+        Ok(rule)
+    }
+
+    /// Checks the header and flow options for traffic from `sender` to
+    /// `receiver`.
+    fn matches_header(
+        &self,
+        sender: (IpAddr, Option<u16>),
+        receiver: (IpAddr, Option<u16>),
+        to_server: bool,
+        established: bool,
+    ) -> bool {
+        let oriented = |from: (IpAddr, Option<u16>), to: (IpAddr, Option<u16>)| {
+            self.source.matches(from.0)
+                && self.source_ports.matches(from.1)
+                && self.destination.matches(to.0)
+                && self.destination_ports.matches(to.1)
+        };
+        (oriented(sender, receiver) || (self.bidirectional && oriented(receiver, sender)))
+            && self.to_server.is_none_or(|expected| expected == to_server)
+            && (!self.established || established)
+    }
+
+    /// Runs the matchers over `buffer`, returning the offset of the first
+    /// matched byte.
+    fn match_buffer(&self, buffer: &[u8]) -> Result<Option<usize>, StepLimit> {
+        search(&self.matchers, buffer, None, None)
+    }
+}
+
+fn find_content(content: &ContentMatch, buffer: &[u8], start: usize, end: usize) -> Vec<usize> {
+    let length = content.pattern.len();
+    if start >= end || end - start < length {
+        return Vec::new();
+    }
+    (start..=end - length)
+        .filter(|&position| {
+            let window = &buffer[position..position + length];
+            if content.nocase {
+                window.eq_ignore_ascii_case(&content.pattern)
+            } else {
+                window == content.pattern.as_slice()
+            }
+        })
+        .collect()
+}
+
+/// Evaluates matchers in order. `cursor` is the end of the previous match,
+/// which relative modifiers count from; positive matches backtrack over
+/// every occurrence. A pcre that gives up stops the whole search.
+fn search(
+    matchers: &[Matcher],
+    buffer: &[u8],
+    cursor: Option<usize>,
+    first: Option<usize>,
+) -> Result<Option<usize>, StepLimit> {
+    let Some((matcher, rest)) = matchers.split_first() else {
+        return Ok(Some(first.unwrap_or(0)));
+    };
+    match matcher {
+        Matcher::Content(content) => {
+            let relative = content.distance.is_some() || content.within.is_some();
+            let start = if relative {
+                let base = cursor.unwrap_or(0) as isize + content.distance.unwrap_or(0);
+                base.max(0) as usize
+            } else {
+                content.offset
+            };
+            let limit = if relative {
+                content.within
+            } else {
+                content.depth
+            };
+            let end = limit.map_or(buffer.len(), |limit| (start + limit).min(buffer.len()));
+            let positions = find_content(content, buffer, start, end);
+            if content.negated {
+                return match positions.is_empty() {
+                    true => search(rest, buffer, cursor, first),
+                    false => Ok(None),
+                };
+            }
+            for position in positions {
+                let found = search(
+                    rest,
+                    buffer,
+                    Some(position + content.pattern.len()),
+                    first.or(Some(position)),
+                )?;
+                if found.is_some() {
+                    return Ok(found);
+                }
+            }
+            Ok(None)
+        }
+        Matcher::Pcre {
+            regex,
+            negated,
+            relative,
+            anchored,
+        } => {
+            let start = if *relative { cursor.unwrap_or(0) } else { 0 };
+            if start > buffer.len() {
+                return Ok(None);
+            }
+            if *anchored {
+                let found = regex.match_at(buffer, start)?;
+                return match (found, negated) {
+                    (None, true) => search(rest, buffer, cursor, first),
+                    (Some(end), false) => search(rest, buffer, Some(end), first.or(Some(start))),
+                    _ => Ok(None),
+                };
+            }
+            if *negated {
+                return match regex.find_at(buffer, start)? {
+                    None => search(rest, buffer, cursor, first),
+                    Some(_) => Ok(None),
+                };
+            }
+            let mut from = start;
+            while let Some((match_start, match_end)) = regex.find_at(buffer, from)? {
+                let found = search(rest, buffer, Some(match_end), first.or(Some(match_start)))?;
+                if found.is_some() {
+                    return Ok(found);
+                }
+                from = match_start + 1;
+                if from > buffer.len() {
+                    break;
+                }
+            }
+            Ok(None)
+        }
+    }
+}
+
+/// Parses a rule file. Blank lines and `#` comments are skipped, and a
+/// trailing `\` continues a rule on the next line.
+pub fn parse_rules(
+    text: &str,
+    variables: &HashMap<String, String>,
+) -> Result<Vec<IdsRule>, KcpdumpError> {
+    let variables = Variables(variables);
+    let mut rules = Vec::new();
+    let mut pending = String::new();
+    let mut first_line = 0;
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if pending.is_empty() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            first_line = index + 1;
+        }
+        if let Some(continued) = line.strip_suffix('\\') {
+            pending.push_str(continued);
+            continue;
+        }
+        pending.push_str(line);
+        let rule = IdsRule::parse(&pending, &variables).map_err(|e| e.at_line(first_line))?;
+        rules.push(rule);
+        pending.clear();
+    }
+    Ok(rules)
+}
+
+/// Ids Alert
+/// A rule match, reported at the packet carrying the first matched byte.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct IdsAlert {
+    pub capture_id: CaptureId,
+    pub number: usize,
+    pub sid: u32,
+    pub rev: u32,
+    pub msg: String,
+    pub classtype: Option<String>,
+    pub source: String,
+    pub destination: String,
+}
+
+/// Ids Warning
+/// A rule whose pcre gave up on some buffers before deciding whether it
+/// matched; those buffers raised no alert.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct IdsWarning {
+    pub sid: u32,
+    pub rev: u32,
+    pub msg: String,
+    /// Number of streams and packets the rule could not be evaluated on.
+    pub buffers: usize,
+}
+
+/// Ids Scan
+/// The alerts of a scan, and the rules that could not be fully evaluated.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct IdsScan {
+    pub alerts: Vec<IdsAlert>,
+    pub warnings: Vec<IdsWarning>,
+}
+
+fn endpoint_text(address: IpAddr, port: Option<u16>) -> String {
+    match (address, port) {
+        (IpAddr::V6(address), Some(port)) => format!("[{}]:{}", address, port),
+        (address, Some(port)) => format!("{}:{}", address, port),
+        (address, None) => address.to_string(),
+    }
+}
+
+fn alert(
+    rule: &IdsRule,
+    capture_id: CaptureId,
+    number: usize,
+    sender: (IpAddr, Option<u16>),
+    receiver: (IpAddr, Option<u16>),
+) -> IdsAlert {
+    IdsAlert {
+        capture_id,
+        number,
+        sid: rule.sid,
+        rev: rule.rev,
+        msg: rule.msg.clone(),
+        classtype: rule.classtype.clone(),
+        source: endpoint_text(sender.0, sender.1),
+        destination: endpoint_text(receiver.0, receiver.1),
+    }
+}
+
+/// Evaluates the rules against the captures: TCP rules against each
+/// direction of the reassembled streams, other rules against single packets.
+/// A TCP stream counts as established, a UDP flow once its first reply has
+/// been seen. Buffers on which a rule's pcre gives up are counted in the
+/// rule's warning instead of raising an alert.
+pub fn scan(captures: &[Arc<LoadedCapture>], rules: &[IdsRule]) -> Result<IdsScan, KcpdumpError> {
+    let mut alerts = Vec::new();
+    let mut gave_up = vec![0; rules.len()];
+
+    for capture in captures {
+        let mut reassembler = StreamReassembler::default();
+        for (index, packet) in capture.packets().enumerate() {
+            let packet = packet?;
+            reassembler.push(&PacketLayers::decode_link(index + 1, capture.header.network, &packet));
+        }
+        for stream in reassembler.finish() {
+            let client = (stream.client.0, Some(stream.client.1));
+            let server = (stream.server.0, Some(stream.server.1));
+            let directions: [(&StreamDirection, _, _, bool); 2] = [
+                (&stream.client_data, client, server, true),
+                (&stream.server_data, server, client, false),
+            ];
+            for (direction, sender, receiver, to_server) in directions {
+                for (rule, gave_up) in rules
+                    .iter()
+                    .zip(gave_up.iter_mut())
+                    .filter(|(rule, _)| rule.protocol == RuleProtocol::Tcp)
+                {
+                    if !rule.matches_header(sender, receiver, to_server, true)
+                        || (direction.data.is_empty() && !rule.matchers.is_empty())
+                    {
+                        continue;
+                    }
+                    let offset = match rule.match_buffer(&direction.data) {
+                        Ok(Some(offset)) => offset,
+                        Ok(None) => continue,
+                        Err(StepLimit) => {
+                            *gave_up += 1;
+                            continue;
+                        }
+                    };
+                    let number = direction.frame_at(offset).unwrap_or(stream.first_number);
+                    alerts.push(alert(rule, capture.id, number, sender, receiver));
+                }
+            }
+        }
+    }
+
+    // UDP clients are the endpoint that sent the first datagram of a flow.
+    let mut udp_flows: HashMap<(CaptureId, FlowKey), ((IpAddr, u16), bool)> = HashMap::new();
+    let link_types = packetlist::link_types(captures);
+    for item in packetlist::merged_packets(captures) {
+        let (capture_id, number, packet) = item?;
+        let layers = PacketLayers::decode_link(number, link_types[&capture_id], &packet);
+        let Some(ip) = &layers.ipv4 else {
+            continue;
+        };
+        let source = IpAddr::V4(Ipv4Addr::from(ip.source_ip));
+        let destination = IpAddr::V4(Ipv4Addr::from(ip.dest_ip));
+        let (protocol, ports, payload, to_server, established) = if let Some(udp) = &layers.udp {
+            let key = FlowKey::new(
+                ip.protocol,
+                (source, udp.source_port),
+                (destination, udp.dest_port),
+            );
+            let (client, replied) = udp_flows
+                .entry((capture_id, key))
+                .or_insert(((source, udp.source_port), false));
+            let to_server = *client == (source, udp.source_port);
+            *replied |= !to_server;
+            (
+                RuleProtocol::Udp,
+                Some((udp.source_port, udp.dest_port)),
+                udp.payload.as_slice(),
+                to_server,
+                *replied,
+            )
+        } else if let Some(icmp) = &layers.icmp {
+            (RuleProtocol::Icmp, None, icmp.data.as_slice(), true, false)
+        } else if let Some(tcp) = &layers.tcp {
+            (
+                RuleProtocol::Tcp,
+                Some((tcp.source_port, tcp.dest_port)),
+                tcp.payload.as_slice(),
+                true,
+                false,
+            )
+        } else {
+            (RuleProtocol::Ip, None, ip.payload.as_slice(), true, false)
+        };
+        let sender = (source, ports.map(|(source, _)| source));
+        let receiver = (destination, ports.map(|(_, destination)| destination));
+        for (rule, gave_up) in rules.iter().zip(gave_up.iter_mut()) {
+            let applies = match rule.protocol {
+                RuleProtocol::Ip => true,
+                RuleProtocol::Tcp => false,
+                other => other == protocol,
+            };
+            if !applies || !rule.matches_header(sender, receiver, to_server, established) {
+                continue;
+            }
+            match rule.match_buffer(payload) {
+                Ok(Some(_)) => alerts.push(alert(rule, capture_id, number, sender, receiver)),
+                Ok(None) => {}
+                Err(StepLimit) => *gave_up += 1,
+            }
+        }
+    }
+
+    alerts.sort_by_key(|alert| (alert.capture_id, alert.number, alert.sid));
+    let warnings = rules
+        .iter()
+        .zip(gave_up)
+        .filter(|&(_, buffers)| buffers > 0)
+        .map(|(rule, buffers)| IdsWarning {
+            sid: rule.sid,
+            rev: rule.rev,
+            msg: rule.msg.clone(),
+            buffers,
+        })
+        .collect();
+    Ok(IdsScan { alerts, warnings })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(text: &str) -> IdsRule {
+        parse_rules(text, &HashMap::new()).unwrap().remove(0)
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let mut variables = HashMap::new();
+        variables.insert("HOME_NET".to_string(), "[10.0.0.0/8,!10.0.0.1]".to_string());
+        let rules = parse_rules(
+            "# comment\n\
+             alert tcp $EXTERNAL_NET any -> $HOME_NET [80,8000:8080] \\\n\
+               (msg:\"Admin; access\"; flow:to_server,established; content:\"GET\"; depth:3; \
+               content:\"|2f|admin\"; nocase; distance:1; sid:1000001; rev:2;)\n",
+            &variables,
+        )
+        .unwrap();
+        assert_eq!(rules.len(), 1);
+        let rule = &rules[0];
+        assert_eq!(rule.msg, "Admin; access");
+        assert_eq!((rule.sid, rule.rev), (1000001, 2));
+        assert!(rule.destination.matches("10.1.2.3".parse().unwrap()));
+        assert!(!rule.destination.matches("10.0.0.1".parse().unwrap()));
+        assert!(rule.destination_ports.matches(Some(8080)));
+        assert!(!rule.destination_ports.matches(Some(443)));
+        assert_eq!(rule.match_buffer(b"GET /ADMIN HTTP/1.1"), Ok(Some(0)));
+        assert_eq!(rule.match_buffer(b"POST /admin"), Ok(None));
+
+        for invalid in [
+            "drop tcp any any -> any any (sid:1;)",
+            "alert tcp any any -> any any (content:\"a\";)",
+            "alert tcp any any -> any any (nocase; sid:1;)",
+            "alert tcp any any -> $NOWHERE any (sid:1;)",
+            "alert tcp any any -> any any (http_uri; sid:1;)",
+            "alert tcp any any -> any any (content:\"|4\"; sid:1;)",
+        ] {
+            let error = parse_rules(invalid, &HashMap::new()).unwrap_err();
+            assert!(
+                matches!(error, KcpdumpError::InvalidRule { line: Some(1), .. }),
+                "{}",
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn test_relative_and_negated_matches() {
+        let rule = rule(
+            "alert tcp any any -> any any (content:\"a\"; content:\"b\"; distance:0; within:2; \
+             content:!\"z\"; pcre:\"/c+d/R\"; sid:1;)",
+        );
+        // The first "a" is too far from a "b"; the second one is not.
+        assert_eq!(rule.match_buffer(b"a..b ab ccd"), Ok(Some(5)));
+        assert_eq!(rule.match_buffer(b"ab ccd z"), Ok(None));
+        assert_eq!(rule.match_buffer(b"ab"), Ok(None));
+
+        let anchored = rule_with_pcre("/^USER\\s+root/Ai");
+        assert!(anchored.match_buffer(b"user  root\r\n").unwrap().is_some());
+        assert!(
+            anchored
+                .match_buffer(b"PASS x\r\nUSER root")
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_step_limit() {
+        let positive = rule_with_pcre("/(a|a)*b/");
+        assert_eq!(positive.match_buffer(&[b'a'; 40]), Err(StepLimit));
+        // A negated pcre that gave up is not taken as a non-match either.
+        let negated = rule("alert tcp any any -> any any (pcre:!\"/(a|a)*b/\"; sid:3;)");
+        assert_eq!(negated.match_buffer(&[b'a'; 40]), Err(StepLimit));
+    }
+
+    fn rule_with_pcre(pcre: &str) -> IdsRule {
+        rule(&format!(
+            "alert tcp any any -> any any (pcre:\"{}\"; sid:2;)",
+            pcre
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_scan_sample() {
+        let capture = Arc::new(LoadedCapture::load(1, "sample.pcap").await.unwrap());
+        let rules = parse_rules(
+            "alert tcp any any -> any 80 (msg:\"index\"; flow:to_server; content:\"/index.html\"; sid:1;)\n\
+             alert tcp any any -> any 80 (msg:\"wrong way\"; flow:to_client; content:\"GET\"; sid:2;)\n\
+             alert tcp any 80 -> any any (msg:\"ok\"; content:\"200 OK\"; sid:3;)\n\
+             alert udp any any -> any 53 (msg:\"dns query\"; flow:to_server; content:\"|01 00|\"; offset:2; depth:2; sid:4;)\n",
+            &HashMap::new(),
+        )
+        .unwrap();
+        let result = scan(std::slice::from_ref(&capture), &rules).unwrap();
+        assert!(result.warnings.is_empty());
+        let alerts = result.alerts;
+        let sids: Vec<u32> = alerts.iter().map(|alert| alert.sid).collect();
+        assert!(sids.contains(&1));
+        assert!(sids.contains(&3));
+        assert!(sids.contains(&4));
+        assert!(!sids.contains(&2));
+        let index = alerts.iter().find(|alert| alert.sid == 1).unwrap();
+        assert!(index.destination.ends_with(":80"));
+        assert!(index.number > 1);
+    }
+
+    #[tokio::test]
+    async fn test_scan_reports_step_limit() {
+        let capture = Arc::new(LoadedCapture::load(1, "sample.pcap").await.unwrap());
+        let rules = parse_rules(
+            "alert tcp any any -> any 80 (msg:\"slow\"; flow:to_server; pcre:\"/(.|.)*zzz/\"; sid:5;)\n\
+             alert tcp any any -> any 80 (msg:\"index\"; content:\"/index.html\"; sid:6;)\n",
+            &HashMap::new(),
+        )
+        .unwrap();
+        let result = scan(std::slice::from_ref(&capture), &rules).unwrap();
+        assert_eq!(
+            result.warnings,
+            [IdsWarning {
+                sid: 5,
+                rev: 0,
+                msg: "slow".to_string(),
+                buffers: 1,
+            }]
+        );
+        let sids: Vec<u32> = result.alerts.iter().map(|alert| alert.sid).collect();
+        assert_eq!(sids, [6]);
+    }
+}
@@ -0,0 +1,711 @@
+use crate::error::KcpdumpError;
+
+/// Largest number of backtracking steps spent on one search; patterns that
+/// need more give up with `StepLimit`.
+const MAX_STEPS: usize = 1_000_000;
+
+/// Step Limit
+/// A search that gave up after `MAX_STEPS` backtracking steps without
+/// deciding whether the pattern matches.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StepLimit;
+
+/// A set of bytes, as a 256 bit bitmap.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ByteSet([u64; 4]);
+
+impl ByteSet {
+    fn empty() -> Self {
+        ByteSet([0; 4])
+    }
+
+    fn of(predicate: impl Fn(u8) -> bool) -> Self {
+        let mut set = ByteSet::empty();
+        for byte in (0..=255u8).filter(|byte| predicate(*byte)) {
+            set.insert(byte);
+        }
+        set
+    }
+
+    fn insert(&mut self, byte: u8) {
+        self.0[usize::from(byte >> 6)] |= 1 << (byte & 63);
+    }
+
+    fn insert_range(&mut self, low: u8, high: u8) {
+        for byte in low..=high {
+            self.insert(byte);
+        }
+    }
+
+    fn union(&mut self, other: &ByteSet) {
+        for (word, other) in self.0.iter_mut().zip(other.0) {
+            *word |= other;
+        }
+    }
+
+    fn negate(&mut self) {
+        for word in self.0.iter_mut() {
+            *word = !*word;
+        }
+    }
+
+    /// Adds the other case of every ASCII letter.
+    fn fold_case(&mut self) {
+        for byte in b'a'..=b'z' {
+            let upper = byte.to_ascii_uppercase();
+            if self.contains(byte) || self.contains(upper) {
+                self.insert(byte);
+                self.insert(upper);
+            }
+        }
+    }
+
+    fn contains(&self, byte: u8) -> bool {
+        self.0[usize::from(byte >> 6)] & (1 << (byte & 63)) != 0
+    }
+}
+
+fn is_word(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Set(ByteSet),
+    LineStart,
+    LineEnd,
+    WordBoundary(bool),
+    Concat(Vec<Node>),
+    Alternation(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+        greedy: bool,
+    },
+}
+
+/// Regex
+/// A Perl-compatible regular expression over bytes, covering the syntax
+/// commonly found in IDS rules: classes, escapes, anchors, groups,
+/// alternation and greedy or lazy quantifiers. Backreferences and
+/// lookaround are not supported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Regex {
+    program: Vec<Inst>,
+    /// Number of unbounded repeats, each tracking where its iteration began.
+    registers: usize,
+    multiline: bool,
+}
+
+struct Parser<'a> {
+    pattern: &'a [u8],
+    position: usize,
+    caseless: bool,
+    dotall: bool,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.pattern.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Result<u8, KcpdumpError> {
+        let byte = self
+            .peek()
+            .ok_or_else(|| KcpdumpError::invalid_rule("Unexpected end of pattern"))?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        let found = self.peek() == Some(byte);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn parse_alternation(&mut self) -> Result<Node, KcpdumpError> {
+        let mut branches = vec![self.parse_concat()?];
+        while self.eat(b'|') {
+            branches.push(self.parse_concat()?);
+        }
+        Ok(if branches.len() == 1 {
+            branches.remove(0)
+        } else {
+            Node::Alternation(branches)
+        })
+    }
+
+    fn parse_concat(&mut self) -> Result<Node, KcpdumpError> {
+        let mut nodes = Vec::new();
+        while let Some(byte) = self.peek() {
+            if byte == b'|' || byte == b')' {
+                break;
+            }
+            let atom = self.parse_atom()?;
+            nodes.push(self.parse_quantifier(atom)?);
+        }
+        Ok(Node::Concat(nodes))
+    }
+
+    fn parse_quantifier(&mut self, node: Node) -> Result<Node, KcpdumpError> {
+        let (min, max) = match self.peek() {
+            Some(b'{') => match self.parse_braces()? {
+                Some(bounds) => bounds,
+                None => return Ok(node),
+            },
+            Some(symbol @ (b'*' | b'+' | b'?')) => {
+                self.position += 1;
+                match symbol {
+                    b'*' => (0, None),
+                    b'+' => (1, None),
+                    _ => (0, Some(1)),
+                }
+            }
+            _ => return Ok(node),
+        };
+        if matches!(
+            node,
+            Node::LineStart | Node::LineEnd | Node::WordBoundary(_)
+        ) {
+            return Err(KcpdumpError::invalid_rule("Quantifier after an assertion"));
+        }
+        let greedy = !self.eat(b'?');
+        // Possessive quantifiers behave as greedy ones here.
+        self.eat(b'+');
+        Ok(Node::Repeat {
+            node: Box::new(node),
+            min,
+            max,
+            greedy,
+        })
+    }
+
+    /// Parses `{n}`, `{n,}` or `{n,m}`. A brace that does not start a
+    /// valid quantifier is a literal, as in PCRE.
+    fn parse_braces(&mut self) -> Result<Option<(usize, Option<usize>)>, KcpdumpError> {
+        let rest = &self.pattern[self.position + 1..];
+        let Some(close) = rest.iter().position(|&byte| byte == b'}') else {
+            return Ok(None);
+        };
+        let Ok(inner) = std::str::from_utf8(&rest[..close]) else {
+            return Ok(None);
+        };
+        let (min, max) = match inner.split_once(',') {
+            Some((min, "")) => (min.parse().ok(), Some(None)),
+            Some((min, max)) => (min.parse().ok(), max.parse().ok().map(Some)),
+            None => (inner.parse().ok(), inner.parse().ok().map(Some)),
+        };
+        let (Some(min), Some(max)) = (min, max) else {
+            return Ok(None);
+        };
+        if max.is_some_and(|max| max < min) {
+            return Err(KcpdumpError::invalid_rule("Quantifier range out of order"));
+        }
+        self.position += close + 2;
+        Ok(Some((min, max)))
+    }
+
+    fn literal(&self, byte: u8) -> Node {
+        let mut set = ByteSet::empty();
+        set.insert_range(byte, byte);
+        if self.caseless {
+            set.fold_case();
+        }
+        Node::Set(set)
+    }
+
+    fn parse_atom(&mut self) -> Result<Node, KcpdumpError> {
+        match self.next()? {
+            b'(' => {
+                if self.eat(b'?') && !self.eat(b':') {
+                    return Err(KcpdumpError::invalid_rule("Unsupported group syntax"));
+                }
+                let node = self.parse_alternation()?;
+                if !self.eat(b')') {
+                    return Err(KcpdumpError::invalid_rule("Missing ')'"));
+                }
+                Ok(node)
+            }
+            b'[' => self.parse_class(),
+            b'.' => Ok(Node::Set(ByteSet::of(|byte| self.dotall || byte != b'\n'))),
+            b'^' => Ok(Node::LineStart),
+            b'$' => Ok(Node::LineEnd),
+            b'\\' => match self.next()? {
+                b'b' => Ok(Node::WordBoundary(true)),
+                b'B' => Ok(Node::WordBoundary(false)),
+                escape => match Self::class_escape(escape) {
+                    Some(set) => Ok(Node::Set(set)),
+                    None => {
+                        let byte = self.escaped_byte(escape)?;
+                        Ok(self.literal(byte))
+                    }
+                },
+            },
+            b')' => Err(KcpdumpError::invalid_rule("Unmatched ')'")),
+            b'*' | b'+' | b'?' => Err(KcpdumpError::invalid_rule("Nothing to repeat")),
+            byte => Ok(self.literal(byte)),
+        }
+    }
+
+    /// The set of a class escape such as `\d`, or `None` for other escapes.
+    fn class_escape(escape: u8) -> Option<ByteSet> {
+        let mut set = match escape.to_ascii_lowercase() {
+            b'd' => ByteSet::of(|byte| byte.is_ascii_digit()),
+            b'w' => ByteSet::of(is_word),
+            b's' => ByteSet::of(|byte| matches!(byte, b' ' | b'\t' | b'\n' | b'\r' | 0x0b | 0x0c)),
+            _ => return None,
+        };
+        if escape.is_ascii_uppercase() {
+            set.negate();
+        }
+        Some(set)
+    }
+
+    fn escaped_byte(&mut self, escape: u8) -> Result<u8, KcpdumpError> {
+        Ok(match escape {
+            b'n' => b'\n',
+            b'r' => b'\r',
+            b't' => b'\t',
+            b'f' => 0x0c,
+            b'v' => 0x0b,
+            b'0' => 0,
+            b'x' => {
+                let high = self.next()?;
+                let low = self.next()?;
+                let hex = [high, low];
+                let hex = std::str::from_utf8(&hex)
+                    .map_err(|_| KcpdumpError::invalid_rule("Invalid \\x escape"))?;
+                u8::from_str_radix(hex, 16)
+                    .map_err(|_| KcpdumpError::invalid_rule("Invalid \\x escape"))?
+            }
+            byte if byte.is_ascii_alphanumeric() => {
+                return Err(KcpdumpError::invalid_rule(format!(
+                    "Unsupported escape \\{}",
+                    byte as char
+                )));
+            }
+            byte => byte,
+        })
+    }
+
+    fn parse_class(&mut self) -> Result<Node, KcpdumpError> {
+        let negated = self.eat(b'^');
+        let mut set = ByteSet::empty();
+        let mut first = true;
+        loop {
+            let byte = self
+                .next()
+                .map_err(|_| KcpdumpError::invalid_rule("Missing ']'"))?;
+            if byte == b']' && !first {
+                break;
+            }
+            first = false;
+            let low = if byte == b'\\' {
+                let escape = self.next()?;
+                if let Some(class) = Self::class_escape(escape) {
+                    set.union(&class);
+                    continue;
+                }
+                self.escaped_byte(escape)?
+            } else {
+                byte
+            };
+            if self.peek() == Some(b'-') && self.pattern.get(self.position + 1) != Some(&b']') {
+                self.position += 1;
+                let high = match self.next()? {
+                    b'\\' => {
+                        let escape = self.next()?;
+                        self.escaped_byte(escape)?
+                    }
+                    high => high,
+                };
+                if high < low {
+                    return Err(KcpdumpError::invalid_rule("Class range out of order"));
+                }
+                set.insert_range(low, high);
+            } else {
+                set.insert_range(low, low);
+            }
+        }
+        if self.caseless {
+            set.fold_case();
+        }
+        if negated {
+            set.negate();
+        }
+        Ok(Node::Set(set))
+    }
+}
+
+/// Largest number of instructions a compiled pattern may have; counted
+/// repeats are expanded, so `x{1000}` alone takes a thousand.
+const MAX_PROGRAM_LEN: usize = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Assertion {
+    LineStart,
+    LineEnd,
+    WordBoundary(bool),
+}
+
+/// One instruction of a compiled pattern. Alternatives are tried in order
+/// by `Split`, which remembers the second target for backtracking.
+#[derive(Debug, Clone, PartialEq)]
+enum Inst {
+    Set(ByteSet),
+    Assert(Assertion),
+    Split(usize, usize),
+    Jump(usize),
+    /// Records the position an iteration of an unbounded repeat starts at.
+    Mark(usize),
+    /// Fails if the iteration begun at the matching `Mark` consumed nothing,
+    /// as it could never make progress.
+    Progress(usize),
+    Match,
+}
+
+/// State to go back to when an instruction fails.
+enum Backtrack {
+    Resume { pc: usize, position: usize },
+    Restore { register: usize, position: usize },
+}
+
+#[derive(Default)]
+struct Compiler {
+    program: Vec<Inst>,
+    registers: usize,
+}
+
+impl Compiler {
+    fn push(&mut self, inst: Inst) -> Result<usize, KcpdumpError> {
+        if self.program.len() == MAX_PROGRAM_LEN {
+            return Err(KcpdumpError::invalid_rule("Pattern too large"));
+        }
+        self.program.push(inst);
+        Ok(self.program.len() - 1)
+    }
+
+    /// Emits an instruction that is filled in later by `patch`.
+    fn placeholder(&mut self) -> Result<usize, KcpdumpError> {
+        self.push(Inst::Jump(usize::MAX))
+    }
+
+    fn patch(&mut self, at: usize, inst: Inst) {
+        self.program[at] = inst;
+    }
+
+    /// A split trying another iteration at `body` first when greedy, and
+    /// leaving the repeat at `exit` first when lazy.
+    fn split(greedy: bool, body: usize, exit: usize) -> Inst {
+        if greedy {
+            Inst::Split(body, exit)
+        } else {
+            Inst::Split(exit, body)
+        }
+    }
+
+    fn compile(&mut self, node: &Node) -> Result<(), KcpdumpError> {
+        match node {
+            Node::Set(set) => {
+                self.push(Inst::Set(*set))?;
+            }
+            Node::LineStart => {
+                self.push(Inst::Assert(Assertion::LineStart))?;
+            }
+            Node::LineEnd => {
+                self.push(Inst::Assert(Assertion::LineEnd))?;
+            }
+            Node::WordBoundary(expected) => {
+                self.push(Inst::Assert(Assertion::WordBoundary(*expected)))?;
+            }
+            Node::Concat(nodes) => {
+                for node in nodes {
+                    self.compile(node)?;
+                }
+            }
+            Node::Alternation(branches) => {
+                let mut jumps = Vec::new();
+                for (index, branch) in branches.iter().enumerate() {
+                    if index + 1 == branches.len() {
+                        self.compile(branch)?;
+                        break;
+                    }
+                    let split = self.placeholder()?;
+                    self.compile(branch)?;
+                    jumps.push(self.placeholder()?);
+                    let next = self.program.len();
+                    self.patch(split, Inst::Split(split + 1, next));
+                }
+                let end = self.program.len();
+                for jump in jumps {
+                    self.patch(jump, Inst::Jump(end));
+                }
+            }
+            Node::Repeat {
+                node,
+                min,
+                max,
+                greedy,
+            } => {
+                for _ in 0..*min {
+                    self.compile(node)?;
+                }
+                match max {
+                    None => {
+                        let register = self.registers;
+                        self.registers += 1;
+                        let split = self.placeholder()?;
+                        self.push(Inst::Mark(register))?;
+                        self.compile(node)?;
+                        self.push(Inst::Progress(register))?;
+                        self.push(Inst::Jump(split))?;
+                        let exit = self.program.len();
+                        self.patch(split, Self::split(*greedy, split + 1, exit));
+                    }
+                    Some(max) => {
+                        let mut splits = Vec::new();
+                        for _ in *min..*max {
+                            splits.push(self.placeholder()?);
+                            self.compile(node)?;
+                        }
+                        let exit = self.program.len();
+                        for split in splits {
+                            self.patch(split, Self::split(*greedy, split + 1, exit));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Regex {
+    /// Compiles a pattern with the PCRE flags `i` (caseless), `s` (dotall)
+    /// and `m` (multiline).
+    pub fn new(
+        pattern: &[u8],
+        caseless: bool,
+        dotall: bool,
+        multiline: bool,
+    ) -> Result<Self, KcpdumpError> {
+        let mut parser = Parser {
+            pattern,
+            position: 0,
+            caseless,
+            dotall,
+        };
+        let node = parser.parse_alternation()?;
+        if parser.position != pattern.len() {
+            return Err(KcpdumpError::invalid_rule("Unmatched ')'"));
+        }
+        let mut compiler = Compiler::default();
+        compiler.compile(&node)?;
+        compiler.push(Inst::Match)?;
+        Ok(Regex {
+            program: compiler.program,
+            registers: compiler.registers,
+            multiline,
+        })
+    }
+
+    fn holds(&self, assertion: Assertion, input: &[u8], position: usize) -> bool {
+        match assertion {
+            Assertion::LineStart => {
+                position == 0 || (self.multiline && input[position - 1] == b'\n')
+            }
+            Assertion::LineEnd => {
+                let length = input.len();
+                position == length
+                    || (position + 1 == length && input[position] == b'\n')
+                    || (self.multiline && input[position] == b'\n')
+            }
+            Assertion::WordBoundary(expected) => {
+                let before = position > 0 && is_word(input[position - 1]);
+                let after = input.get(position).is_some_and(|&byte| is_word(byte));
+                (before != after) == expected
+            }
+        }
+    }
+
+    /// Runs the program from `start`, backtracking through an explicit
+    /// stack so long inputs cannot exhaust the call stack. Returns the end
+    /// of the first match found, or `StepLimit` once `steps` passes
+    /// `MAX_STEPS`.
+    fn run(
+        &self,
+        input: &[u8],
+        start: usize,
+        steps: &mut usize,
+    ) -> Result<Option<usize>, StepLimit> {
+        let mut registers = vec![usize::MAX; self.registers];
+        let mut stack = Vec::new();
+        let (mut pc, mut position) = (0, start);
+        loop {
+            *steps += 1;
+            if *steps > MAX_STEPS {
+                return Err(StepLimit);
+            }
+            let matched = match &self.program[pc] {
+                Inst::Set(set) => match input.get(position) {
+                    Some(&byte) if set.contains(byte) => {
+                        position += 1;
+                        true
+                    }
+                    _ => false,
+                },
+                Inst::Assert(assertion) => self.holds(*assertion, input, position),
+                Inst::Split(first, second) => {
+                    stack.push(Backtrack::Resume {
+                        pc: *second,
+                        position,
+                    });
+                    pc = *first;
+                    continue;
+                }
+                Inst::Jump(target) => {
+                    pc = *target;
+                    continue;
+                }
+                Inst::Mark(register) => {
+                    stack.push(Backtrack::Restore {
+                        register: *register,
+                        position: registers[*register],
+                    });
+                    registers[*register] = position;
+                    true
+                }
+                Inst::Progress(register) => registers[*register] != position,
+                Inst::Match => return Ok(Some(position)),
+            };
+            if matched {
+                pc += 1;
+                continue;
+            }
+            loop {
+                let Some(frame) = stack.pop() else {
+                    return Ok(None);
+                };
+                match frame {
+                    Backtrack::Restore {
+                        register,
+                        position: saved,
+                    } => registers[register] = saved,
+                    Backtrack::Resume {
+                        pc: target,
+                        position: saved,
+                    } => {
+                        pc = target;
+                        position = saved;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Finds the leftmost match starting at or after `start`, returning its
+    /// start and end offsets. The step budget is shared by all start
+    /// positions.
+    pub fn find_at(&self, input: &[u8], start: usize) -> Result<Option<(usize, usize)>, StepLimit> {
+        let mut steps = 0;
+        for from in start..=input.len() {
+            if let Some(end) = self.run(input, from, &mut steps)? {
+                return Ok(Some((from, end)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Anchors the match at `start`, as PCRE's `A` flag does.
+    pub fn match_at(&self, input: &[u8], start: usize) -> Result<Option<usize>, StepLimit> {
+        self.run(input, start, &mut 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(pattern: &str, input: &str) -> Option<(usize, usize)> {
+        Regex::new(pattern.as_bytes(), false, false, false)
+            .unwrap()
+            .find_at(input.as_bytes(), 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_find() {
+        assert_eq!(find("b+c", "abbbcd"), Some((1, 5)));
+        assert_eq!(find("^GET\\s+/admin", "GET  /admin.php"), Some((0, 11)));
+        assert_eq!(find("^admin", "GET /admin"), None);
+        assert_eq!(
+            find("(?:cmd|powershell)\\.exe", "run powershell.exe"),
+            Some((4, 18))
+        );
+        assert_eq!(find("[0-9]{3}-[^x]", "id 123-4"), Some((3, 8)));
+        assert_eq!(find("a.*?b", "aXbXb"), Some((0, 3)));
+        assert_eq!(find("a.*b", "aXbXb"), Some((0, 5)));
+        assert_eq!(find("\\x41\\bB?", "xA "), Some((1, 2)));
+        assert_eq!(find("end$", "the end\n"), Some((4, 7)));
+    }
+
+    #[test]
+    fn test_step_limit() {
+        let regex = Regex::new(b"(a|a)*b", false, false, false).unwrap();
+        let input = vec![b'a'; 40];
+        assert_eq!(regex.find_at(&input, 0), Err(StepLimit));
+        assert_eq!(regex.match_at(&input, 0), Err(StepLimit));
+        assert_eq!(regex.find_at(b"aab", 0), Ok(Some((0, 3))));
+        let nested = Regex::new(b"(a*)*b", false, false, false).unwrap();
+        assert_eq!(
+            nested.find_at(b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaac", 0),
+            Err(StepLimit)
+        );
+    }
+
+    #[test]
+    fn test_long_input() {
+        let regex = Regex::new(b".*b", false, false, false).unwrap();
+        let mut input = vec![b'a'; 50_000];
+        assert_eq!(regex.find_at(&input, 0), Err(StepLimit));
+        input.push(b'b');
+        assert_eq!(regex.find_at(&input, 0), Ok(Some((0, 50_001))));
+        let lazy = Regex::new(b"a+?b", false, false, false).unwrap();
+        assert_eq!(lazy.match_at(&input, 0), Ok(Some(50_001)));
+    }
+
+    #[test]
+    fn test_flags() {
+        let caseless = Regex::new(b"user-agent: [a-z]+", true, false, false).unwrap();
+        assert_eq!(caseless.find_at(b"User-Agent: CURL", 0), Ok(Some((0, 16))));
+        let dotall = Regex::new(b"a.b", false, true, false).unwrap();
+        assert!(dotall.find_at(b"a\nb", 0).unwrap().is_some());
+        let multiline = Regex::new(b"^Host", false, false, true).unwrap();
+        assert_eq!(multiline.find_at(b"GET /\nHost: x", 0), Ok(Some((6, 10))));
+        assert_eq!(multiline.match_at(b"GET /\nHost: x", 0), Ok(None));
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        for pattern in [
+            "(ab",
+            "ab)",
+            "[b-a]",
+            "*a",
+            "(?=a)",
+            "\\q",
+            "a{3,1}",
+            "a{50000}b{50000}c{50000}",
+        ] {
+            assert!(
+                Regex::new(pattern.as_bytes(), false, false, false).is_err(),
+                "{}",
+                pattern
+            );
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use crate::error::KcpdumpError;
+use crate::summary::PacketSummary;
+use crate::zigbee::NwkFrame;
+
+/// Link types of IEEE 802.15.4 captures with and without the trailing FCS.
+pub const LINKTYPE_IEEE802_15_4_WITHFCS: u32 = 195;
+pub const LINKTYPE_IEEE802_15_4_NOFCS: u32 = 230;
+
+pub const FRAME_BEACON: u8 = 0;
+pub const FRAME_DATA: u8 = 1;
+pub const FRAME_ACK: u8 = 2;
+pub const FRAME_COMMAND: u8 = 3;
+
+/// Mac Address
+/// A short or extended 802.15.4 address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MacAddress {
+    Short(u16),
+    Extended(u64),
+}
+
+impl std::fmt::Display for MacAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MacAddress::Short(address) => write!(f, "0x{:04x}", address),
+            MacAddress::Extended(address) => {
+                let bytes = address.to_be_bytes();
+                let parts: Vec<_> = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+                write!(f, "{}", parts.join(":"))
+            }
+        }
+    }
+}
+
+/// Mac Frame
+/// An IEEE 802.15.4 MAC frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MacFrame {
+    pub frame_type: u8,
+    pub security: bool,
+    pub ack_request: bool,
+    pub sequence: u8,
+    pub dest_pan: Option<u16>,
+    pub dest: Option<MacAddress>,
+    pub source_pan: Option<u16>,
+    pub source: Option<MacAddress>,
+    /// MAC payload, without the FCS.
+    pub payload: Vec<u8>,
+}
+
+impl TryFrom<&[u8]> for MacFrame {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 3 {
+            return Err(KcpdumpError::malformed(
+                0,
+                "Data too short for IEEE 802.15.4 frame",
+            ));
+        }
+        let control = u16::from_le_bytes([data[0], data[1]]);
+        let dest_mode = (control >> 10) & 0x03;
+        let source_mode = (control >> 14) & 0x03;
+        let pan_compression = control & 0x0040 != 0;
+        let mut offset = 3;
+
+        let mut read = |length: usize| -> Result<&[u8], Self::Error> {
+            let field = data
+                .get(offset..offset + length)
+                .ok_or(KcpdumpError::malformed(
+                    offset,
+                    "IEEE 802.15.4 addressing fields truncated",
+                ))?;
+            offset += length;
+            Ok(field)
+        };
+        let pan = |field: &[u8]| u16::from_le_bytes([field[0], field[1]]);
+        let address = |mode: u16, field: &[u8]| match mode {
+            2 => MacAddress::Short(u16::from_le_bytes([field[0], field[1]])),
+            _ => MacAddress::Extended(u64::from_le_bytes(field.try_into().unwrap_or_default())),
+        };
+        let address_length = |mode: u16| if mode == 3 { 8 } else { 2 };
+
+        let (mut dest_pan, mut dest) = (None, None);
+        if dest_mode >= 2 {
+            dest_pan = Some(pan(read(2)?));
+            dest = Some(address(dest_mode, read(address_length(dest_mode))?));
+        }
+        let (mut source_pan, mut source) = (None, None);
+        if source_mode >= 2 {
+            source_pan = if pan_compression {
+                dest_pan
+            } else {
+                Some(pan(read(2)?))
+            };
+            source = Some(address(source_mode, read(address_length(source_mode))?));
+        }
+        Ok(MacFrame {
+            frame_type: (control & 0x07) as u8,
+            security: control & 0x0008 != 0,
+            ack_request: control & 0x0020 != 0,
+            sequence: data[2],
+            dest_pan,
+            dest,
+            source_pan,
+            source,
+            payload: data[offset..].to_vec(),
+        })
+    }
+}
+
+impl MacFrame {
+    /// Decodes a frame of the given link type, dropping the FCS if present.
+    pub fn decode(link_type: u32, data: &[u8]) -> Result<Self, KcpdumpError> {
+        let data = match link_type {
+            LINKTYPE_IEEE802_15_4_WITHFCS => {
+                data.get(..data.len().saturating_sub(2))
+                    .ok_or(KcpdumpError::malformed(
+                        0,
+                        "Data too short for IEEE 802.15.4 frame",
+                    ))?
+            }
+            _ => data,
+        };
+        MacFrame::try_from(data)
+    }
+
+    /// Zigbee network layer frame carried by an unsecured MAC data frame.
+    pub fn nwk(&self) -> Option<NwkFrame> {
+        if self.frame_type != FRAME_DATA || self.security {
+            return None;
+        }
+        NwkFrame::try_from(self.payload.as_slice()).ok()
+    }
+}
+
+fn frame_type_name(frame_type: u8) -> &'static str {
+    match frame_type {
+        FRAME_BEACON => "Beacon",
+        FRAME_DATA => "Data",
+        FRAME_ACK => "Ack",
+        FRAME_COMMAND => "Command",
+        _ => "Reserved",
+    }
+}
+
+/// Summarizes an 802.15.4 frame for the packet list, showing the Zigbee
+/// network layer when present.
+pub fn summarize(link_type: u32, frame: &[u8]) -> PacketSummary {
+    let mac = match MacFrame::decode(link_type, frame) {
+        Ok(mac) => mac,
+        Err(e) => {
+            return PacketSummary {
+                source: String::new(),
+                destination: String::new(),
+                protocol: "Malformed".to_string(),
+                length: frame.len(),
+                info: e.to_string(),
+                flow: None,
+            };
+        }
+    };
+    let label = |address: Option<MacAddress>| address.map(|a| a.to_string()).unwrap_or_default();
+    let mut summary = PacketSummary {
+        source: label(mac.source),
+        destination: label(mac.dest),
+        protocol: "IEEE 802.15.4".to_string(),
+        length: frame.len(),
+        info: format!("{}, Seq {}", frame_type_name(mac.frame_type), mac.sequence),
+        flow: None,
+    };
+    if let Some(nwk) = mac.nwk() {
+        summary.source = format!("0x{:04x}", nwk.source);
+        summary.destination = format!("0x{:04x}", nwk.dest);
+        summary.protocol = "ZigBee".to_string();
+        summary.info = nwk.describe();
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mac_frame() {
+        // Data frame, PAN compression, short addresses 0xffff <- 0x0001 on PAN 0x1a62.
+        let frame = [
+            0x41, 0x88, 0x05, 0x62, 0x1a, 0xff, 0xff, 0x01, 0x00, 0xde, 0xad, 0x12, 0x34,
+        ];
+        let mac = MacFrame::decode(LINKTYPE_IEEE802_15_4_WITHFCS, &frame).unwrap();
+        assert_eq!(mac.frame_type, FRAME_DATA);
+        assert_eq!(mac.sequence, 5);
+        assert_eq!(mac.dest_pan, Some(0x1a62));
+        assert_eq!(mac.source_pan, Some(0x1a62));
+        assert_eq!(mac.dest, Some(MacAddress::Short(0xffff)));
+        assert_eq!(mac.source, Some(MacAddress::Short(0x0001)));
+        assert_eq!(mac.payload, [0xde, 0xad]);
+
+        let summary = summarize(LINKTYPE_IEEE802_15_4_WITHFCS, &frame);
+        assert_eq!(summary.protocol, "IEEE 802.15.4");
+        assert_eq!(summary.source, "0x0001");
+        assert_eq!(summary.info, "Data, Seq 5");
+
+        let ack = [0x02, 0x00, 0x07];
+        let mac = MacFrame::decode(LINKTYPE_IEEE802_15_4_NOFCS, &ack).unwrap();
+        assert_eq!(mac.frame_type, FRAME_ACK);
+        assert!(mac.source.is_none());
+        assert_eq!(
+            summarize(LINKTYPE_IEEE802_15_4_NOFCS, &ack[..2]).protocol,
+            "Malformed"
+        );
+    }
+
+    #[test]
+    fn test_extended_address() {
+        assert_eq!(
+            MacAddress::Extended(0x0011_2233_4455_6677).to_string(),
+            "00:11:22:33:44:55:66:77"
+        );
+    }
+}
@@ -0,0 +1,394 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::Write;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
+
+use crate::dhcp;
+use crate::dissect::PacketLayers;
+use crate::dns::DnsRecordData;
+use crate::error::KcpdumpError;
+use crate::geoip::is_global;
+use crate::oui;
+use crate::packet::TcpFlags;
+use crate::packetlist;
+use crate::session::LoadedCapture;
+
+/// Number of peers listed per asset.
+const TOP_PEERS: usize = 10;
+
+/// Names of well-known service ports.
+const SERVICE_NAMES: &[(u16, &str)] = &[
+    (21, "ftp"),
+    (22, "ssh"),
+    (23, "telnet"),
+    (25, "smtp"),
+    (53, "dns"),
+    (67, "dhcp"),
+    (80, "http"),
+    (110, "pop3"),
+    (123, "ntp"),
+    (143, "imap"),
+    (161, "snmp"),
+    (443, "https"),
+    (445, "smb"),
+    (514, "syslog"),
+    (993, "imaps"),
+    (995, "pop3s"),
+    (1883, "mqtt"),
+    (3306, "mysql"),
+    (3389, "rdp"),
+    (5353, "mdns"),
+    (5432, "postgresql"),
+    (8080, "http-alt"),
+];
+
+/// OS Guess
+/// Operating system family inferred from the initial TTL and, if the host
+/// opened a connection, the window size of its SYN.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OsGuess {
+    pub family: String,
+    pub initial_ttl: u8,
+    pub syn_window: Option<u16>,
+}
+
+impl OsGuess {
+    fn new(ttl: u8, syn_window: Option<u16>) -> Option<Self> {
+        let initial_ttl = [32, 64, 128, 255]
+            .into_iter()
+            .find(|initial| ttl <= *initial)?;
+        let family = match (initial_ttl, syn_window) {
+            (64, Some(65535)) => "macOS or BSD",
+            (64, Some(_)) => "Linux",
+            (64, None) => "Linux, macOS or Unix",
+            (128, _) => "Windows",
+            (255, _) => "Network device",
+            _ => return None,
+        };
+        Some(OsGuess {
+            family: family.to_string(),
+            initial_ttl,
+            syn_window,
+        })
+    }
+}
+
+/// Asset Service
+/// A port the host answered on.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetService {
+    pub protocol: String,
+    pub port: u16,
+    pub name: Option<String>,
+    /// Distinct client addresses.
+    pub clients: usize,
+}
+
+/// Asset Peer
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetPeer {
+    pub address: IpAddr,
+    pub packets: usize,
+    pub bytes: u64,
+}
+
+/// Asset
+/// A host on the local network: what it is, what it serves and who it
+/// talks to.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Asset {
+    pub address: IpAddr,
+    pub macs: Vec<String>,
+    pub vendor: Option<String>,
+    /// Names from DHCP leases and DNS answers.
+    pub hostnames: Vec<String>,
+    pub os: Option<OsGuess>,
+    pub services: Vec<AssetService>,
+    /// Sorted by bytes, descending, and limited to the top peers.
+    pub peers: Vec<AssetPeer>,
+    pub packets_sent: usize,
+    pub packets_received: usize,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    /// Seconds since the epoch.
+    pub first_seen: f64,
+    pub last_seen: f64,
+}
+
+#[derive(Default)]
+struct AssetBuilder {
+    macs: BTreeSet<String>,
+    vendor: Option<&'static str>,
+    hostnames: BTreeSet<String>,
+    ttl: Option<u8>,
+    syn: Option<(u8, u16)>,
+    services: BTreeMap<(&'static str, u16), HashSet<IpAddr>>,
+    peers: HashMap<IpAddr, (usize, u64)>,
+    packets_sent: usize,
+    packets_received: usize,
+    bytes_sent: u64,
+    bytes_received: u64,
+    first_seen: f64,
+    last_seen: f64,
+}
+
+/// Whether an address belongs to a host on the local network rather than to
+/// a remote host reached through a router.
+fn is_local_host(address: Ipv4Addr) -> bool {
+    !(is_global(IpAddr::V4(address))
+        || address.is_multicast()
+        || address.is_broadcast()
+        || address.is_unspecified())
+}
+
+/// Builds the inventory of local hosts of the captures. MAC addresses and
+/// OS guesses come from the frames the hosts sent, services from the TCP
+/// ports they accepted connections on and the UDP ports they answered from.
+pub fn asset_inventory(captures: &[Arc<LoadedCapture>]) -> Result<Vec<Asset>, KcpdumpError> {
+    let mut assets: BTreeMap<Ipv4Addr, AssetBuilder> = BTreeMap::new();
+    // UDP requests seen, as (client, client port, server, server port).
+    let mut udp_requests: HashSet<(Ipv4Addr, u16, Ipv4Addr, u16)> = HashSet::new();
+
+    let link_types = packetlist::link_types(captures);
+    for item in packetlist::merged_packets(captures) {
+        let (capture_id, number, packet) = item?;
+        let layers = PacketLayers::decode_link(number, link_types[&capture_id], &packet);
+        let Some(ip) = &layers.ipv4 else {
+            continue;
+        };
+        let source = Ipv4Addr::from(ip.source_ip);
+        let destination = Ipv4Addr::from(ip.dest_ip);
+        let length = u64::from(packet.header.orig_len);
+        let time = packet.header.timestamp.as_secs_f64();
+
+        for (address, peer, sent) in [(source, destination, true), (destination, source, false)] {
+            if !is_local_host(address) {
+                continue;
+            }
+            let asset = assets.entry(address).or_insert_with(|| AssetBuilder {
+                first_seen: time,
+                ..AssetBuilder::default()
+            });
+            asset.first_seen = asset.first_seen.min(time);
+            asset.last_seen = asset.last_seen.max(time);
+            let counter = asset.peers.entry(IpAddr::V4(peer)).or_default();
+            counter.0 += 1;
+            counter.1 += length;
+            if sent {
+                asset.packets_sent += 1;
+                asset.bytes_sent += length;
+            } else {
+                asset.packets_received += 1;
+                asset.bytes_received += length;
+            }
+        }
+
+        if let Some(asset) = assets.get_mut(&source) {
+            if let Some(ethernet) = &layers.ethernet {
+                asset.macs.insert(ethernet.header.src_mac.to_string());
+                asset.vendor = asset.vendor.or(oui::vendor(&ethernet.header.src_mac));
+            }
+            asset.ttl = Some(asset.ttl.map_or(ip.ttl, |ttl| ttl.max(ip.ttl)));
+            if let Some(tcp) = &layers.tcp {
+                let syn = tcp.flags.contains(TcpFlags::SYN);
+                let ack = tcp.flags.contains(TcpFlags::ACK);
+                if syn && !ack {
+                    asset.syn = Some((ip.ttl, tcp.window_size));
+                }
+                if syn && ack {
+                    asset
+                        .services
+                        .entry(("tcp", tcp.source_port))
+                        .or_default()
+                        .insert(IpAddr::V4(destination));
+                }
+            }
+            if let Some(udp) = &layers.udp
+                && udp_requests.contains(&(destination, udp.dest_port, source, udp.source_port))
+            {
+                asset
+                    .services
+                    .entry(("udp", udp.source_port))
+                    .or_default()
+                    .insert(IpAddr::V4(destination));
+            }
+        }
+        if let Some(udp) = &layers.udp
+            && !udp_requests.contains(&(destination, udp.dest_port, source, udp.source_port))
+        {
+            udp_requests.insert((source, udp.source_port, destination, udp.dest_port));
+        }
+
+        if let Some(dns) = layers.dns.as_ref().filter(|dns| dns.is_response()) {
+            for answer in &dns.answers {
+                if let DnsRecordData::A(address) = answer.data
+                    && let Some(asset) = assets.get_mut(&address)
+                {
+                    asset.hostnames.insert(answer.name.clone());
+                }
+            }
+        }
+    }
+
+    for lease in dhcp::lease_table(captures)? {
+        if let (Some(asset), Some(hostname)) = (assets.get_mut(&lease.ip), lease.hostname) {
+            asset.hostnames.insert(hostname);
+        }
+    }
+
+    Ok(assets
+        .into_iter()
+        .map(|(address, asset)| {
+            let os = match asset.syn {
+                Some((ttl, window)) => OsGuess::new(ttl, Some(window)),
+                None => asset.ttl.and_then(|ttl| OsGuess::new(ttl, None)),
+            };
+            let services = asset
+                .services
+                .into_iter()
+                .map(|((protocol, port), clients)| AssetService {
+                    protocol: protocol.to_string(),
+                    port,
+                    name: SERVICE_NAMES
+                        .iter()
+                        .find(|(known, _)| *known == port)
+                        .map(|(_, name)| name.to_string()),
+                    clients: clients.len(),
+                })
+                .collect();
+            let mut peers: Vec<AssetPeer> = asset
+                .peers
+                .into_iter()
+                .map(|(address, (packets, bytes))| AssetPeer {
+                    address,
+                    packets,
+                    bytes,
+                })
+                .collect();
+            peers.sort_by(|a, b| {
+                b.bytes
+                    .cmp(&a.bytes)
+                    .then_with(|| a.address.cmp(&b.address))
+            });
+            peers.truncate(TOP_PEERS);
+            Asset {
+                address: IpAddr::V4(address),
+                macs: asset.macs.into_iter().collect(),
+                vendor: asset.vendor.map(str::to_string),
+                hostnames: asset.hostnames.into_iter().collect(),
+                os,
+                services,
+                peers,
+                packets_sent: asset.packets_sent,
+                packets_received: asset.packets_received,
+                bytes_sent: asset.bytes_sent,
+                bytes_received: asset.bytes_received,
+                first_seen: asset.first_seen,
+                last_seen: asset.last_seen,
+            }
+        })
+        .collect())
+}
+
+/// Quotes a CSV field if it contains a separator, quote or line break.
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Formats the inventory as CSV, one host per line. Lists are joined with
+/// spaces.
+pub fn inventory_csv(assets: &[Asset]) -> String {
+    let mut out = String::from(
+        "address,macs,vendor,hostnames,os,services,peers,packets_sent,packets_received,bytes_sent,bytes_received,first_seen,last_seen\n",
+    );
+    for asset in assets {
+        let services: Vec<String> = asset
+            .services
+            .iter()
+            .map(|service| match &service.name {
+                Some(name) => format!("{}/{}({})", service.port, service.protocol, name),
+                None => format!("{}/{}", service.port, service.protocol),
+            })
+            .collect();
+        let peers: Vec<String> = asset
+            .peers
+            .iter()
+            .map(|peer| peer.address.to_string())
+            .collect();
+        let fields = [
+            asset.address.to_string(),
+            asset.macs.join(" "),
+            asset.vendor.clone().unwrap_or_default(),
+            asset.hostnames.join(" "),
+            asset
+                .os
+                .as_ref()
+                .map(|os| os.family.clone())
+                .unwrap_or_default(),
+            services.join(" "),
+            peers.join(" "),
+            asset.packets_sent.to_string(),
+            asset.packets_received.to_string(),
+            asset.bytes_sent.to_string(),
+            asset.bytes_received.to_string(),
+            format!("{:.6}", asset.first_seen),
+            format!("{:.6}", asset.last_seen),
+        ];
+        let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        let _ = writeln!(out, "{}", fields.join(","));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_asset_inventory() {
+        let capture = Arc::new(LoadedCapture::load(1, "sample.pcap").await.unwrap());
+        let assets = asset_inventory(std::slice::from_ref(&capture)).unwrap();
+        let addresses: Vec<String> = assets
+            .iter()
+            .map(|asset| asset.address.to_string())
+            .collect();
+        assert_eq!(addresses, ["192.168.0.1", "192.168.0.10"]);
+
+        let resolver = &assets[0];
+        assert_eq!(resolver.macs, ["00:11:22:33:44:55"]);
+        assert_eq!(resolver.services.len(), 1);
+        assert_eq!(resolver.services[0].port, 53);
+        assert_eq!(resolver.services[0].name.as_deref(), Some("dns"));
+
+        let client = &assets[1];
+        assert!(client.services.is_empty());
+        assert_eq!(client.os.as_ref().unwrap().syn_window, Some(65535));
+        assert_eq!(client.os.as_ref().unwrap().initial_ttl, 64);
+        assert!(
+            client
+                .peers
+                .iter()
+                .any(|peer| peer.address.to_string() == "93.184.216.34")
+        );
+
+        let csv = inventory_csv(&assets);
+        assert_eq!(csv.lines().count(), 3);
+        assert!(csv.contains("53/udp(dns)"));
+    }
+
+    #[test]
+    fn test_os_guess() {
+        assert_eq!(OsGuess::new(117, None).unwrap().family, "Windows");
+        assert_eq!(OsGuess::new(63, Some(64240)).unwrap().family, "Linux");
+        assert_eq!(OsGuess::new(250, None).unwrap().initial_ttl, 255);
+        assert_eq!(csv_field("a,\"b\""), "\"a,\"\"b\"\"\"");
+    }
+}
@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+
+use crate::displayfilter::DisplayFilter;
+use crate::dissect::DissectorRegistry;
+use crate::error::KcpdumpError;
+use crate::flows::FlowKey;
+use crate::session::LoadedCapture;
+use crate::summary;
+
+/// Most intervals a graph may have, to keep tiny intervals over long
+/// captures from producing huge responses.
+pub const MAX_INTERVALS: usize = 100_000;
+
+/// Most series a split graph returns; smaller ones are merged into "Other".
+pub const MAX_SERIES: usize = 16;
+
+/// I/O Graph Split
+/// How packets are divided into separate series.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum IoGraphSplit {
+    /// One series per highest decoded protocol.
+    Protocol,
+    /// One series per IP conversation.
+    Conversation,
+}
+
+/// I/O Graph Series
+/// Rates of one group of packets, one value per interval.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct IoGraphSeries {
+    pub label: String,
+    /// The conversation of the series when split by conversation.
+    pub flow: Option<FlowKey>,
+    pub packets: usize,
+    pub bytes: u64,
+    pub packets_per_sec: Vec<f64>,
+    pub bytes_per_sec: Vec<f64>,
+}
+
+/// I/O Graph
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct IoGraph {
+    /// Capture timestamp of the first packet, in seconds since the epoch.
+    /// Intervals start there whatever the filter, so graphs line up.
+    pub start_time: f64,
+    /// Length of each interval in seconds.
+    pub interval: f64,
+    /// All matching packets.
+    pub total: IoGraphSeries,
+    /// Matching packets split as requested, largest first by bytes.
+    pub series: Vec<IoGraphSeries>,
+}
+
+struct Counts {
+    packets: Vec<usize>,
+    bytes: Vec<u64>,
+}
+
+impl Counts {
+    fn new(intervals: usize) -> Self {
+        Counts {
+            packets: vec![0; intervals],
+            bytes: vec![0; intervals],
+        }
+    }
+
+    fn add(&mut self, other: &Counts) {
+        for (index, packets) in other.packets.iter().enumerate() {
+            self.packets[index] += packets;
+            self.bytes[index] += other.bytes[index];
+        }
+    }
+
+    fn into_series(self, label: String, flow: Option<FlowKey>, interval: f64) -> IoGraphSeries {
+        IoGraphSeries {
+            label,
+            flow,
+            packets: self.packets.iter().sum(),
+            bytes: self.bytes.iter().sum(),
+            packets_per_sec: self.packets.iter().map(|&count| count as f64 / interval).collect(),
+            bytes_per_sec: self.bytes.iter().map(|&count| count as f64 / interval).collect(),
+        }
+    }
+}
+
+/// Buckets the packets of a capture into intervals of `interval_usec`
+/// microseconds and returns packet and byte rates, for all packets matching
+/// `filter` and optionally split per protocol or conversation.
+pub fn io_graph(
+    capture: &LoadedCapture,
+    interval_usec: i64,
+    split: Option<IoGraphSplit>,
+    filter: Option<&DisplayFilter>,
+    registry: &DissectorRegistry,
+) -> Result<IoGraph, KcpdumpError> {
+    if interval_usec <= 0 {
+        return Err(KcpdumpError::Other(
+            "The I/O graph interval must be positive".to_string(),
+        ));
+    }
+    // The range comes from the index, so packets are only read once.
+    let entries = capture.index.entries();
+    let times = entries.iter().map(|entry| entry.timestamp.as_micros());
+    let (Some(first), Some(last)) = (times.clone().min(), times.max()) else {
+        return Ok(IoGraph::default());
+    };
+    let intervals = ((last - first) / interval_usec + 1) as usize;
+    if intervals > MAX_INTERVALS {
+        return Err(KcpdumpError::Other(format!(
+            "An interval of {} ms gives {} intervals, more than the {} allowed",
+            interval_usec as f64 / 1000.0,
+            intervals,
+            MAX_INTERVALS
+        )));
+    }
+    let interval = interval_usec as f64 / 1_000_000.0;
+
+    let mut total = Counts::new(intervals);
+    // Counts and conversation of each group, in order of first appearance.
+    let mut groups: Vec<(String, Option<FlowKey>, Counts)> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let link_type = capture.header.network;
+    for (number, packet) in capture.packets().enumerate() {
+        let packet = packet?;
+        if filter
+            .is_some_and(|filter| !filter.matches_packet(registry, link_type, number + 1, &packet))
+        {
+            continue;
+        }
+        let slot = ((packet.header.timestamp.as_micros() - first) / interval_usec) as usize;
+        let bytes = u64::from(packet.header.orig_len);
+        total.packets[slot] += 1;
+        total.bytes[slot] += bytes;
+
+        let Some(split) = split else {
+            continue;
+        };
+        let summary = summary::summarize_link(link_type, &packet.data);
+        let (label, flow) = match split {
+            IoGraphSplit::Protocol => (summary.protocol, None),
+            IoGraphSplit::Conversation => match summary.flow {
+                Some(flow) => (conversation_label(&flow), Some(flow)),
+                None => ("Other".to_string(), None),
+            },
+        };
+        let group = *index.entry(label.clone()).or_insert_with(|| {
+            groups.push((label, flow, Counts::new(intervals)));
+            groups.len() - 1
+        });
+        let counts = &mut groups[group].2;
+        counts.packets[slot] += 1;
+        counts.bytes[slot] += bytes;
+    }
+
+    groups.sort_by_key(|(_, _, counts)| std::cmp::Reverse(counts.bytes.iter().sum::<u64>()));
+    let mut series = Vec::new();
+    let mut other: Option<Counts> = None;
+    for (label, flow, counts) in groups {
+        if series.len() < MAX_SERIES - 1 || label == "Other" {
+            series.push((label, flow, counts));
+        } else {
+            other.get_or_insert_with(|| Counts::new(intervals)).add(&counts);
+        }
+    }
+    if let Some(rest) = other {
+        match series.iter_mut().find(|(label, ..)| label == "Other") {
+            Some((_, _, counts)) => counts.add(&rest),
+            None => series.push(("Other".to_string(), None, rest)),
+        }
+    }
+
+    Ok(IoGraph {
+        start_time: first as f64 / 1_000_000.0,
+        interval,
+        total: total.into_series("All packets".to_string(), None, interval),
+        series: series
+            .into_iter()
+            .map(|(label, flow, counts)| counts.into_series(label, flow, interval))
+            .collect(),
+    })
+}
+
+fn conversation_label(flow: &FlowKey) -> String {
+    if flow.port_a == 0 && flow.port_b == 0 {
+        format!("{} <-> {}", flow.address_a, flow.address_b)
+    } else {
+        format!(
+            "{}:{} <-> {}:{}",
+            flow.address_a, flow.port_a, flow.address_b, flow.port_b
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cap::{PcapPacket, PcapPacketHeader};
+    use crate::link::LINKTYPE_ETHERNET;
+    use crate::timefmt::Timestamp;
+
+    /// A UDP packet between 10.0.0.1 and 10.0.0.2; the client at 10.0.0.1
+    /// uses ports other than 53.
+    fn udp(micros: i64, source_port: u16, dest_port: u16) -> PcapPacket {
+        let mut data = vec![0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 1, 0x08, 0x00];
+        data.extend_from_slice(&[0x45, 0, 0, 28, 0, 0, 0, 0, 64, 17, 0, 0]);
+        if source_port == 53 {
+            data.extend_from_slice(&[10, 0, 0, 2, 10, 0, 0, 1]);
+        } else {
+            data.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2]);
+        }
+        data.extend_from_slice(&source_port.to_be_bytes());
+        data.extend_from_slice(&dest_port.to_be_bytes());
+        data.extend_from_slice(&[0, 8, 0, 0]);
+        PcapPacket {
+            header: PcapPacketHeader {
+                timestamp: Timestamp::from_nanos(micros * 1000),
+                incl_len: data.len() as u32,
+                orig_len: data.len() as u32,
+                link_type: None,
+            },
+            data,
+        }
+    }
+
+    #[test]
+    fn test_io_graph() {
+        let registry = DissectorRegistry::default();
+        let packets = [
+            udp(1_000_000, 1000, 53),
+            udp(1_200_000, 53, 1000),
+            udp(1_600_000, 1001, 53),
+            udp(2_900_000, 1000, 53),
+        ];
+        let capture = LoadedCapture::from_packets(1, "udp.pcap", LINKTYPE_ETHERNET, &packets);
+        let graph = io_graph(&capture, 500_000, None, None, &registry).unwrap();
+        assert_eq!(graph.start_time, 1.0);
+        assert_eq!(graph.interval, 0.5);
+        assert_eq!(graph.total.packets_per_sec, vec![4.0, 2.0, 0.0, 2.0]);
+        assert_eq!(graph.total.bytes_per_sec[0], 2.0 * 42.0 / 0.5);
+        assert_eq!(graph.total.packets, 4);
+        assert!(graph.series.is_empty());
+
+        let split = Some(IoGraphSplit::Conversation);
+        let graph = io_graph(&capture, 500_000, split, None, &registry).unwrap();
+        assert_eq!(graph.series.len(), 2);
+        assert_eq!(graph.series[0].label, "10.0.0.1:1000 <-> 10.0.0.2:53");
+        assert_eq!(graph.series[0].packets_per_sec, vec![4.0, 0.0, 0.0, 2.0]);
+        assert_eq!(graph.series[1].packets, 1);
+
+        let filter = DisplayFilter::compile("udp.srcport == 53", &registry).unwrap();
+        let split = Some(IoGraphSplit::Protocol);
+        let graph = io_graph(&capture, 500_000, split, Some(&filter), &registry).unwrap();
+        assert_eq!(graph.total.packets_per_sec, vec![2.0, 0.0, 0.0, 0.0]);
+        assert_eq!(graph.series.len(), 1);
+        assert_eq!(graph.series[0].packets, 1);
+    }
+
+    #[test]
+    fn test_interval_limits() {
+        let registry = DissectorRegistry::default();
+        let packets = [udp(0, 1, 2), udp(3_600_000_000, 1, 2)];
+        let capture = LoadedCapture::from_packets(1, "udp.pcap", LINKTYPE_ETHERNET, &packets);
+        let empty = LoadedCapture::from_packets(2, "empty.pcap", LINKTYPE_ETHERNET, &[]);
+        assert!(io_graph(&capture, 0, None, None, &registry).is_err());
+        assert!(io_graph(&capture, 1000, None, None, &registry).is_err());
+        let graph = io_graph(&empty, 1000, None, None, &registry).unwrap();
+        assert!(graph.total.packets_per_sec.is_empty());
+    }
+}
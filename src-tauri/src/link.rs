@@ -0,0 +1,366 @@
+use std::fmt;
+
+use crate::error::KcpdumpError;
+use crate::packet::{EtherType, EthernetHeader, EthernetPacket, IPv4Packet, IPv6Packet, MacAddress};
+use crate::summary::{self, PacketSummary};
+
+/// Link types whose frames carry IP, decoded by `LinkFrame`.
+pub const LINKTYPE_NULL: u32 = 0;
+pub const LINKTYPE_ETHERNET: u32 = 1;
+pub const LINKTYPE_RAW: u32 = 101;
+pub const LINKTYPE_LOOP: u32 = 108;
+pub const LINKTYPE_LINUX_SLL: u32 = 113;
+pub const LINKTYPE_IPV4: u32 = 228;
+pub const LINKTYPE_IPV6: u32 = 229;
+pub const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+/// DLT_RAW as some platforms write it instead of LINKTYPE_RAW.
+const DLT_RAW_ALIASES: [u32; 2] = [12, 14];
+
+/// Address family values of IPv4 and IPv6 in loopback headers; IPv6 differs
+/// between operating systems.
+const AF_INET: u32 = 2;
+const AF_INET6: [u32; 4] = [10, 24, 28, 30];
+
+/// Link Type Error
+/// Why a frame could not be decoded for its link type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkTypeError {
+    /// The capture uses a link type without a decoder.
+    Unsupported(u32),
+    /// The frame is too short or inconsistent for its link type.
+    Malformed {
+        link_type: u32,
+        reason: &'static str,
+    },
+}
+
+impl fmt::Display for LinkTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkTypeError::Unsupported(link_type) => write!(
+                f,
+                "Unsupported link type {} ({})",
+                link_type,
+                link_type_name(*link_type)
+            ),
+            LinkTypeError::Malformed { link_type, reason } => {
+                write!(f, "Malformed {} frame: {}", link_type_name(*link_type), reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LinkTypeError {}
+
+/// Human readable name of a link type.
+pub fn link_type_name(link_type: u32) -> &'static str {
+    match link_type {
+        LINKTYPE_NULL => "BSD loopback",
+        LINKTYPE_ETHERNET => "Ethernet",
+        LINKTYPE_RAW => "Raw IP",
+        link_type if DLT_RAW_ALIASES.contains(&link_type) => "Raw IP",
+        LINKTYPE_LOOP => "OpenBSD loopback",
+        LINKTYPE_LINUX_SLL => "Linux cooked capture v1",
+        LINKTYPE_IPV4 => "Raw IPv4",
+        LINKTYPE_IPV6 => "Raw IPv6",
+        LINKTYPE_LINUX_SLL2 => "Linux cooked capture v2",
+        _ => "unknown",
+    }
+}
+
+/// Whether `LinkFrame::decode` understands a link type.
+pub fn is_supported(link_type: u32) -> bool {
+    matches!(
+        link_type,
+        LINKTYPE_NULL
+            | LINKTYPE_ETHERNET
+            | LINKTYPE_RAW
+            | LINKTYPE_LOOP
+            | LINKTYPE_LINUX_SLL
+            | LINKTYPE_IPV4
+            | LINKTYPE_IPV6
+            | LINKTYPE_LINUX_SLL2
+    ) || DLT_RAW_ALIASES.contains(&link_type)
+}
+
+/// Link Header
+/// The link-layer header of a frame, by link type.
+#[derive(Debug)]
+pub enum LinkHeader {
+    Ethernet(EthernetHeader),
+    /// BSD or OpenBSD loopback with the protocol's address family.
+    Loopback { family: u32 },
+    /// IP without any link-layer header.
+    Raw,
+    /// Linux cooked capture, as written for the `any` interface.
+    LinuxSll {
+        /// 0 to us, 1 broadcast, 2 multicast, 3 to another host, 4 sent by us.
+        packet_type: u16,
+        /// ARPHRD_ type of the interface.
+        hardware_type: u16,
+        /// Link-layer source address, e.g. a MAC address.
+        address: Vec<u8>,
+        /// Interface index; only recorded by version 2.
+        interface_index: Option<u32>,
+    },
+}
+
+/// Link Frame
+/// A frame of any supported link type, split into its link-layer header and
+/// the network-layer payload.
+#[derive(Debug)]
+pub struct LinkFrame {
+    pub header: LinkHeader,
+    pub ether_type: EtherType,
+    pub payload: Vec<u8>,
+}
+
+impl LinkFrame {
+    /// Decodes a frame of the given link type.
+    pub fn decode(link_type: u32, data: &[u8]) -> Result<Self, LinkTypeError> {
+        let malformed = |reason| LinkTypeError::Malformed { link_type, reason };
+        match link_type {
+            LINKTYPE_ETHERNET => {
+                let eth_packet = EthernetPacket::try_from(data).map_err(|e| match e {
+                    KcpdumpError::MalformedPacket { reason, .. } => malformed(reason),
+                    _ => malformed("Invalid Ethernet header"),
+                })?;
+                Ok(LinkFrame {
+                    ether_type: eth_packet.header.ether_type,
+                    header: LinkHeader::Ethernet(eth_packet.header),
+                    payload: eth_packet.data,
+                })
+            }
+            LINKTYPE_NULL | LINKTYPE_LOOP => {
+                let family = data
+                    .get(..4)
+                    .ok_or_else(|| malformed("Data too short for loopback header"))?;
+                let family = if link_type == LINKTYPE_LOOP {
+                    u32::from_be_bytes([family[0], family[1], family[2], family[3]])
+                } else {
+                    // Written in the byte order of the capturing host.
+                    let value = u32::from_le_bytes([family[0], family[1], family[2], family[3]]);
+                    if value > 0xffff { value.swap_bytes() } else { value }
+                };
+                let ether_type = match family {
+                    AF_INET => EtherType::IPv4,
+                    family if AF_INET6.contains(&family) => EtherType::IPv6,
+                    _ => return Err(malformed("Unknown address family")),
+                };
+                Ok(LinkFrame {
+                    header: LinkHeader::Loopback { family },
+                    ether_type,
+                    payload: data[4..].to_vec(),
+                })
+            }
+            LINKTYPE_RAW | LINKTYPE_IPV4 | LINKTYPE_IPV6 | 12 | 14 => {
+                let ether_type = match data.first().map(|byte| byte >> 4) {
+                    Some(4) => EtherType::IPv4,
+                    Some(6) => EtherType::IPv6,
+                    _ => return Err(malformed("Not an IP packet")),
+                };
+                Ok(LinkFrame {
+                    header: LinkHeader::Raw,
+                    ether_type,
+                    payload: data.to_vec(),
+                })
+            }
+            LINKTYPE_LINUX_SLL => {
+                if data.len() < 16 {
+                    return Err(malformed("Data too short for Linux cooked header"));
+                }
+                let read_u16 = |offset: usize| u16::from_be_bytes([data[offset], data[offset + 1]]);
+                let address_len = usize::from(read_u16(4)).min(8);
+                Ok(LinkFrame {
+                    header: LinkHeader::LinuxSll {
+                        packet_type: read_u16(0),
+                        hardware_type: read_u16(2),
+                        address: data[6..6 + address_len].to_vec(),
+                        interface_index: None,
+                    },
+                    ether_type: EtherType::from(read_u16(14)),
+                    payload: data[16..].to_vec(),
+                })
+            }
+            LINKTYPE_LINUX_SLL2 => {
+                if data.len() < 20 {
+                    return Err(malformed("Data too short for Linux cooked v2 header"));
+                }
+                let address_len = usize::from(data[11]).min(8);
+                Ok(LinkFrame {
+                    header: LinkHeader::LinuxSll {
+                        packet_type: u16::from(data[10]),
+                        hardware_type: u16::from_be_bytes([data[8], data[9]]),
+                        address: data[12..12 + address_len].to_vec(),
+                        interface_index: Some(u32::from_be_bytes([
+                            data[4], data[5], data[6], data[7],
+                        ])),
+                    },
+                    ether_type: EtherType::from(u16::from_be_bytes([data[0], data[1]])),
+                    payload: data[20..].to_vec(),
+                })
+            }
+            _ => Err(LinkTypeError::Unsupported(link_type)),
+        }
+    }
+
+    /// Link-layer source address, if the header has one.
+    pub fn source(&self) -> Option<String> {
+        match &self.header {
+            LinkHeader::Ethernet(header) => Some(header.src_mac.to_string()),
+            LinkHeader::LinuxSll { address, .. } if !address.is_empty() => Some(
+                address
+                    .iter()
+                    .map(|byte| format!("{:02X}", byte))
+                    .collect::<Vec<_>>()
+                    .join(":"),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Source MAC address of an Ethernet frame, or the link-layer source
+    /// address of a Linux cooked frame when it is six bytes long.
+    pub fn source_mac(&self) -> Option<MacAddress> {
+        match &self.header {
+            LinkHeader::Ethernet(header) => Some(header.src_mac),
+            LinkHeader::LinuxSll { address, .. } => {
+                <[u8; 6]>::try_from(address.as_slice()).ok().map(MacAddress)
+            }
+            _ => None,
+        }
+    }
+
+    /// Destination MAC address of an Ethernet frame.
+    pub fn destination_mac(&self) -> Option<MacAddress> {
+        match &self.header {
+            LinkHeader::Ethernet(header) => Some(header.dest_mac),
+            _ => None,
+        }
+    }
+
+    /// Link-layer destination address, if the header has one.
+    pub fn destination(&self) -> Option<String> {
+        match &self.header {
+            LinkHeader::Ethernet(header) => Some(header.dest_mac.to_string()),
+            _ => None,
+        }
+    }
+
+    /// IDs of the VLAN tags of an Ethernet frame, outermost first.
+    pub fn vlan_ids(&self) -> Vec<u16> {
+        match &self.header {
+            LinkHeader::Ethernet(header) => header.vlan_ids(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Summarizes a loopback, raw IP or Linux cooked frame for the packet list.
+pub fn summarize(link_type: u32, frame: &[u8]) -> PacketSummary {
+    let mut summary = PacketSummary {
+        source: String::new(),
+        destination: String::new(),
+        protocol: link_type_name(link_type).to_string(),
+        length: frame.len(),
+        info: String::new(),
+        flow: None,
+    };
+    let link_frame = match LinkFrame::decode(link_type, frame) {
+        Ok(link_frame) => link_frame,
+        Err(e) => {
+            summary.protocol = "Malformed".to_string();
+            summary.info = e.to_string();
+            return summary;
+        }
+    };
+    summary.source = link_frame.source().unwrap_or_default();
+
+    match link_frame.ether_type {
+        EtherType::IPv4 => match IPv4Packet::try_from(link_frame.payload.as_slice()) {
+            Ok(ipv4_packet) => summary::summarize_ipv4(&ipv4_packet, &mut summary),
+            Err(e) => summary.info = e.to_string(),
+        },
+        EtherType::IPv6 => match IPv6Packet::try_from(link_frame.payload.as_slice()) {
+            Ok(ipv6_packet) => {
+                summary.source = ipv6_packet.source_ip.to_string();
+                summary.destination = ipv6_packet.dest_ip.to_string();
+                summary.protocol = "IPv6".to_string();
+                summary.info = format!("IPv6 next header {}", ipv6_packet.next_header);
+            }
+            Err(e) => summary.info = e.to_string(),
+        },
+        ether_type => {
+            summary.info = format!(
+                "{}, protocol 0x{:04x}",
+                link_type_name(link_type),
+                u16::from(ether_type)
+            )
+        }
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An IPv4 header without payload from 10.0.0.1 to 10.0.0.2.
+    const IPV4: [u8; 20] = [
+        0x45, 0, 0, 20, 0, 0, 0, 0, 64, 17, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2,
+    ];
+
+    #[test]
+    fn test_link_types() {
+        let mut null = 2u32.to_le_bytes().to_vec();
+        null.extend_from_slice(&IPV4);
+        let frame = LinkFrame::decode(LINKTYPE_NULL, &null).unwrap();
+        assert_eq!(frame.ether_type, EtherType::IPv4);
+        assert_eq!(frame.payload, IPV4);
+
+        // A big-endian host wrote AF_INET6 as on macOS.
+        let mut null = 30u32.to_be_bytes().to_vec();
+        null.extend_from_slice(&[0x60; 40]);
+        assert_eq!(
+            LinkFrame::decode(LINKTYPE_NULL, &null).unwrap().ether_type,
+            EtherType::IPv6
+        );
+
+        let frame = LinkFrame::decode(LINKTYPE_RAW, &IPV4).unwrap();
+        assert!(matches!(frame.header, LinkHeader::Raw));
+        assert!(LinkFrame::decode(LINKTYPE_RAW, &[0x00]).is_err());
+
+        let mut sll = vec![0, 4, 0, 1, 0, 6, 0x02, 0, 0, 0, 0, 0x01, 0, 0, 0x08, 0x00];
+        sll.extend_from_slice(&IPV4);
+        let frame = LinkFrame::decode(LINKTYPE_LINUX_SLL, &sll).unwrap();
+        assert_eq!(frame.ether_type, EtherType::IPv4);
+        assert_eq!(frame.source().as_deref(), Some("02:00:00:00:00:01"));
+        assert_eq!(frame.payload, IPV4);
+
+        let mut sll2 = vec![0x86, 0xdd, 0, 0, 0, 0, 0, 3, 0, 1, 0, 6];
+        sll2.extend_from_slice(&[0x02, 0, 0, 0, 0, 0x01, 0, 0]);
+        sll2.extend_from_slice(&[0x60; 40]);
+        let frame = LinkFrame::decode(LINKTYPE_LINUX_SLL2, &sll2).unwrap();
+        assert_eq!(frame.ether_type, EtherType::IPv6);
+        assert!(matches!(
+            frame.header,
+            LinkHeader::LinuxSll {
+                interface_index: Some(3),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_unsupported_link_type() {
+        let error = LinkFrame::decode(147, &IPV4).unwrap_err();
+        assert_eq!(error, LinkTypeError::Unsupported(147));
+        assert!(!is_supported(147));
+        assert_eq!(error.to_string(), "Unsupported link type 147 (unknown)");
+
+        let summary = summarize(LINKTYPE_RAW, &IPV4);
+        assert_eq!(summary.source, "10.0.0.1");
+        assert_eq!(summarize(LINKTYPE_LINUX_SLL, &[0; 4]).protocol, "Malformed");
+    }
+}
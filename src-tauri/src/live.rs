@@ -0,0 +1,552 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::BufWriter;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tokio::io;
+
+use crate::bpf::CaptureFilter;
+use crate::cap::{
+    BlockingCapture, IndexEntry, LiveCapture, PacketIndex, PcapPacket, PcapWriter,
+    TimestampResolution,
+};
+use crate::error::KcpdumpError;
+use crate::flows::{FlowKey, StreamTable};
+use crate::packetlist::PacketRow;
+use crate::session::CaptureId;
+use crate::summary;
+use crate::timefmt::{TimeDisplayMode, TimeFormatter};
+
+/// Rows are delivered at least this often while packets arrive.
+const BATCH_INTERVAL: Duration = Duration::from_millis(100);
+/// Upper bound on the number of rows in one event.
+const BATCH_SIZE: usize = 256;
+
+/// Live Capture Options
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LiveCaptureOptions {
+    pub snaplen: u32,
+    pub promiscuous: bool,
+    /// Pcap file receiving every captured packet. A file in the temporary
+    /// directory is used when omitted.
+    pub output_path: Option<String>,
+    /// Number of most recent packets kept in memory for the packet list.
+    pub ring_size: usize,
+}
+
+impl Default for LiveCaptureOptions {
+    fn default() -> Self {
+        LiveCaptureOptions {
+            snaplen: 65535,
+            promiscuous: true,
+            output_path: None,
+            ring_size: 10_000,
+        }
+    }
+}
+
+/// Live Window
+/// A page of rows of a live capture. Rows of packets still held in memory
+/// are copied from the ring; older ones are read back from the capture file
+/// at `output_path`.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LiveWindow {
+    /// Number of packets captured so far.
+    pub total: usize,
+    /// Frame number of the oldest packet still in memory.
+    pub first_number: usize,
+    pub output_path: String,
+    pub rows: Vec<PacketRow>,
+}
+
+/// Live Ring
+/// Bounded buffer of the most recent packets of a live capture, with an
+/// index of every packet written to the capture file so that packets which
+/// left the buffer can be read back from there.
+#[derive(Debug)]
+pub struct LiveRing {
+    capture_id: CaptureId,
+    capacity: usize,
+    output_path: String,
+    link_type: u32,
+    mode: TimeDisplayMode,
+    resolution: TimestampResolution,
+    state: Mutex<RingState>,
+    streams: Mutex<StreamTable>,
+    /// Reader of the capture file, opened when a packet is first read back.
+    reader: Mutex<Option<BlockingCapture>>,
+}
+
+#[derive(Debug, Default)]
+struct RingState {
+    entries: VecDeque<(PacketRow, PcapPacket)>,
+    /// Every packet written to the capture file, in capture order.
+    index: PacketIndex,
+    /// Number of packets flushed to the capture file. Only these leave the
+    /// ring, so that every packet can be read from one place or the other.
+    flushed: usize,
+}
+
+impl RingState {
+    /// Index of the oldest packet in memory, which is the number of packets
+    /// that left the ring.
+    fn first_index(&self) -> usize {
+        self.index.len() - self.entries.len()
+    }
+
+    /// Drops the oldest packets beyond `capacity` that are in the file.
+    fn evict(&mut self, capacity: usize) {
+        while self.entries.len() > capacity && self.first_index() < self.flushed {
+            self.entries.pop_front();
+        }
+    }
+}
+
+impl LiveRing {
+    /// A ring for a capture written to `output_path`, whose rows show times
+    /// in `mode` at `resolution`.
+    pub fn new(
+        capture_id: CaptureId,
+        capacity: usize,
+        output_path: String,
+        link_type: u32,
+        mode: TimeDisplayMode,
+        resolution: TimestampResolution,
+    ) -> Self {
+        LiveRing {
+            capture_id,
+            capacity: capacity.max(1),
+            output_path,
+            link_type,
+            mode,
+            resolution,
+            state: Mutex::new(RingState::default()),
+            streams: Mutex::new(StreamTable::default()),
+            reader: Mutex::new(None),
+        }
+    }
+
+    /// Appends a packet written to the capture file at `offset`, evicting
+    /// the oldest ones once the ring is full. Packets not yet flushed stay
+    /// until `set_flushed` covers them, so the ring can briefly hold more
+    /// than its capacity.
+    pub fn push(&self, row: PacketRow, packet: PcapPacket, offset: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.index.push(IndexEntry {
+            offset,
+            timestamp: packet.header.timestamp,
+        });
+        state.entries.push_back((row, packet));
+        state.evict(self.capacity);
+    }
+
+    /// Records that every packet pushed so far is flushed to the capture
+    /// file.
+    pub fn set_flushed(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.flushed = state.index.len();
+        state.evict(self.capacity);
+    }
+
+    /// The stream index of a conversation, numbering new ones in order of
+    /// appearance.
+    pub fn stream_id(&self, flow: FlowKey) -> u32 {
+        self.streams.lock().unwrap().id(flow)
+    }
+
+    /// Link type of the captured packets.
+    pub fn link_type(&self) -> u32 {
+        self.link_type
+    }
+
+    pub fn total(&self) -> usize {
+        self.state.lock().unwrap().index.len()
+    }
+
+    /// Up to `limit` rows starting at frame index `offset` (frame number - 1).
+    /// Rows of packets that left the ring are rebuilt from the capture file.
+    pub fn window(&self, offset: usize, limit: usize) -> Result<LiveWindow, KcpdumpError> {
+        let state = self.state.lock().unwrap();
+        let total = state.index.len();
+        let first_index = state.first_index();
+        let end = offset.saturating_add(limit).min(total);
+        let file_end = end.min(first_index);
+        let entries: Vec<IndexEntry> = state
+            .index
+            .entries()
+            .get(offset..file_end)
+            .unwrap_or_default()
+            .to_vec();
+        // Times relative to the first and previous packets start from there.
+        let mut formatter = TimeFormatter::with_resolution(self.mode, self.resolution);
+        for number in [1, offset] {
+            if let Some(entry) = state.index.get(number) {
+                formatter.format(entry.timestamp);
+            }
+        }
+        let memory_rows: Vec<PacketRow> = state
+            .entries
+            .iter()
+            .skip(offset.saturating_sub(first_index))
+            .take(end.saturating_sub(offset.max(first_index)))
+            .map(|(row, _)| row.clone())
+            .collect();
+        // The capture keeps running while packets are read back.
+        drop(state);
+
+        let mut rows = Vec::with_capacity(entries.len() + memory_rows.len());
+        for (entry, number) in entries.iter().zip(offset + 1..) {
+            let packet = self.read_entry(entry)?;
+            let timestamp = packet.header.timestamp;
+            let summary = summary::summarize_link(self.link_type, &packet.data);
+            rows.push(PacketRow {
+                capture_id: self.capture_id,
+                number,
+                timestamp,
+                time: formatter.format(timestamp),
+                stream: summary
+                    .flow
+                    .and_then(|flow| self.streams.lock().unwrap().get(&flow)),
+                summary,
+                fields: Vec::new(),
+            });
+        }
+        rows.extend(memory_rows);
+        Ok(LiveWindow {
+            total,
+            first_number: first_index + 1,
+            output_path: self.output_path.clone(),
+            rows,
+        })
+    }
+
+    /// The raw packet with frame number `number`, from memory or else from
+    /// the capture file.
+    pub fn packet(&self, number: usize) -> Result<Option<PcapPacket>, KcpdumpError> {
+        let state = self.state.lock().unwrap();
+        let first_index = state.first_index();
+        let Some(index) = number.checked_sub(1) else {
+            return Ok(None);
+        };
+        if let Some((_, packet)) = index
+            .checked_sub(first_index)
+            .and_then(|index| state.entries.get(index))
+        {
+            return Ok(Some(packet.clone()));
+        }
+        let Some(entry) = state.index.get(number).copied() else {
+            return Ok(None);
+        };
+        drop(state);
+        self.read_entry(&entry).map(Some)
+    }
+
+    fn read_entry(&self, entry: &IndexEntry) -> Result<PcapPacket, KcpdumpError> {
+        let mut reader = self.reader.lock().unwrap();
+        let reader = match &mut *reader {
+            Some(reader) => reader,
+            empty => empty.insert(BlockingCapture::from_file(&self.output_path)?),
+        };
+        reader
+            .read_at(entry.offset)?
+            .ok_or_else(|| KcpdumpError::malformed(entry.offset as usize, "Indexed packet missing"))
+    }
+}
+
+/// Live Capture Event
+/// Emitted to the frontend while a live capture runs.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum LiveCaptureEvent {
+    #[serde(rename_all = "camelCase")]
+    Packets {
+        capture_id: CaptureId,
+        rows: Vec<PacketRow>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Stopped {
+        capture_id: CaptureId,
+        packet_count: usize,
+        error: Option<String>,
+    },
+}
+
+/// Live Capture Handle
+/// Keeps a running capture alive and gives access to its ring of recent
+/// packets; stopping is cooperative and takes effect within one read timeout.
+#[derive(Debug)]
+pub struct LiveCaptureHandle {
+    stop: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+    ring: Arc<LiveRing>,
+}
+
+impl LiveCaptureHandle {
+    /// Requests the capture to stop. Returns false if it was already stopped.
+    pub fn stop(&self) -> bool {
+        !self.stop.swap(true, Ordering::Relaxed)
+    }
+
+    /// Pauses or resumes the capture. Frames arriving while paused are
+    /// dropped, so numbering and the capture file continue where they left
+    /// off. Returns false if the capture was already in that state.
+    pub fn set_paused(&self, paused: bool) -> bool {
+        self.paused.swap(paused, Ordering::Relaxed) != paused
+    }
+
+    pub fn ring(&self) -> Arc<LiveRing> {
+        self.ring.clone()
+    }
+}
+
+impl Drop for LiveCaptureHandle {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Opens `interface` and captures on a worker thread, passing decoded rows to `sink`.
+/// Packets rejected by `filter` are dropped before decoding, like a kernel BPF filter.
+/// Every packet is written to the capture file, while only the most recent
+/// `ring_size` packets stay in memory.
+pub fn start<F>(
+    capture_id: CaptureId,
+    interface: &str,
+    filter: CaptureFilter,
+    options: LiveCaptureOptions,
+    mode: TimeDisplayMode,
+    mut sink: F,
+) -> io::Result<LiveCaptureHandle>
+where
+    F: FnMut(LiveCaptureEvent) + Send + 'static,
+{
+    let mut capture = LiveCapture::open(interface, options.snaplen, options.promiscuous)?;
+    let output_path = options.output_path.clone().unwrap_or_else(|| {
+        let name = format!("kcpdump-live-{}-{}.pcap", std::process::id(), capture_id);
+        std::env::temp_dir().join(name).to_string_lossy().into_owned()
+    });
+    let resolution = capture.resolution();
+    let mut writer = PcapWriter::with_resolution(
+        BufWriter::new(File::create(&output_path)?),
+        capture.network(),
+        capture.snaplen(),
+        resolution,
+    )?;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let paused = Arc::new(AtomicBool::new(false));
+    let ring = Arc::new(LiveRing::new(
+        capture_id,
+        options.ring_size,
+        output_path,
+        capture.network(),
+        mode,
+        resolution,
+    ));
+    let handle = LiveCaptureHandle {
+        stop: stop.clone(),
+        paused: paused.clone(),
+        ring: ring.clone(),
+    };
+
+    let link_type = capture.network();
+    thread::spawn(move || {
+        let mut formatter = TimeFormatter::with_resolution(mode, resolution);
+        let mut rows = Vec::new();
+        let mut packet_count = 0;
+        let mut last_batch = Instant::now();
+        let mut error = None;
+
+        while !stop.load(Ordering::Relaxed) {
+            match capture.next_packet() {
+                Ok(Some(packet))
+                    if !paused.load(Ordering::Relaxed)
+                        && filter.matches_link(link_type, &packet.data) =>
+                {
+                    packet_count += 1;
+                    let offset = writer.position();
+                    if let Err(e) = writer.write_packet(&packet) {
+                        error = Some(format!("Failed to write capture file: {}", e));
+                        break;
+                    }
+                    let timestamp = packet.header.timestamp;
+                    let summary = summary::summarize_link(link_type, &packet.data);
+                    let row = PacketRow {
+                        capture_id,
+                        number: packet_count,
+                        timestamp,
+                        time: formatter.format(timestamp),
+                        stream: summary.flow.map(|flow| ring.stream_id(flow)),
+                        summary,
+                        fields: Vec::new(),
+                    };
+                    rows.push(row.clone());
+                    ring.push(row, packet, offset);
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    error = Some(e.to_string());
+                    break;
+                }
+            }
+            if rows.len() >= BATCH_SIZE
+                || (!rows.is_empty() && last_batch.elapsed() >= BATCH_INTERVAL)
+            {
+                // Packets leave the ring once they can be read back from
+                // the file.
+                if let Err(e) = writer.flush() {
+                    error = Some(format!("Failed to write capture file: {}", e));
+                    break;
+                }
+                ring.set_flushed();
+                sink(LiveCaptureEvent::Packets {
+                    capture_id,
+                    rows: std::mem::take(&mut rows),
+                });
+                last_batch = Instant::now();
+            }
+        }
+
+        if !rows.is_empty() {
+            sink(LiveCaptureEvent::Packets { capture_id, rows });
+        }
+        match writer.flush() {
+            Ok(()) => ring.set_flushed(),
+            Err(e) => {
+                error.get_or_insert(format!("Failed to write capture file: {}", e));
+            }
+        }
+        sink(LiveCaptureEvent::Stopped {
+            capture_id,
+            packet_count,
+            error,
+        });
+    });
+
+    Ok(handle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timefmt::Timestamp;
+
+    #[test]
+    fn test_event_serialization() {
+        let event = LiveCaptureEvent::Stopped {
+            capture_id: 3,
+            packet_count: 10,
+            error: None,
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["kind"], "stopped");
+        assert_eq!(json["captureId"], 3);
+        assert_eq!(json["packetCount"], 10);
+    }
+
+    #[test]
+    fn test_pause_and_resume() {
+        let handle = LiveCaptureHandle {
+            stop: Arc::default(),
+            paused: Arc::default(),
+            ring: Arc::new(LiveRing::new(
+                1,
+                1,
+                "live.pcap".to_string(),
+                1,
+                TimeDisplayMode::default(),
+                TimestampResolution::Microsecond,
+            )),
+        };
+        assert!(handle.set_paused(true));
+        assert!(!handle.set_paused(true));
+        assert!(handle.set_paused(false));
+    }
+
+    #[test]
+    fn test_start_on_missing_interface() {
+        let result = start(
+            1,
+            "kcpdump-missing0",
+            CaptureFilter::compile("").unwrap(),
+            LiveCaptureOptions::default(),
+            TimeDisplayMode::default(),
+            |_| {},
+        );
+        assert!(result.is_err());
+    }
+
+    fn row(number: usize) -> PacketRow {
+        PacketRow {
+            capture_id: 1,
+            number,
+            timestamp: Timestamp::from_micros(number as u32, 0),
+            time: String::new(),
+            stream: None,
+            summary: summary::summarize(&[]),
+            fields: Vec::new(),
+        }
+    }
+
+    fn packet(number: usize) -> PcapPacket {
+        PcapPacket {
+            header: crate::cap::PcapPacketHeader {
+                timestamp: Timestamp::from_micros(number as u32, 0),
+                incl_len: 0,
+                orig_len: 0,
+                link_type: None,
+            },
+            data: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_ring_keeps_most_recent_packets() {
+        let name = format!("kcpdump-live-ring-{}.pcap", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        let output_path = path.to_string_lossy().into_owned();
+        let mut writer = PcapWriter::new(File::create(&path).unwrap(), 1, 65535).unwrap();
+        let ring = LiveRing::new(
+            1,
+            3,
+            output_path,
+            1,
+            TimeDisplayMode::default(),
+            TimestampResolution::Microsecond,
+        );
+        for number in 1..=5 {
+            let offset = writer.position();
+            writer.write_packet(&packet(number)).unwrap();
+            ring.push(row(number), packet(number), offset);
+        }
+        // Packets stay in memory until they are in the file.
+        assert_eq!(ring.window(0, 10).unwrap().first_number, 1);
+        ring.set_flushed();
+
+        let window = ring.window(0, 10).unwrap();
+        assert_eq!(window.total, 5);
+        assert_eq!(window.first_number, 3);
+        let numbers: Vec<_> = window.rows.iter().map(|row| row.number).collect();
+        assert_eq!(numbers, vec![1, 2, 3, 4, 5]);
+        assert_eq!(window.rows[1].timestamp.sec, 2);
+
+        let numbers: Vec<_> = ring
+            .window(1, 3)
+            .unwrap()
+            .rows
+            .iter()
+            .map(|row| row.number)
+            .collect();
+        assert_eq!(numbers, vec![2, 3, 4]);
+        assert_eq!(ring.packet(2).unwrap().unwrap().header.timestamp.sec, 2);
+        assert_eq!(ring.packet(5).unwrap().unwrap().header.timestamp.sec, 5);
+        assert!(ring.packet(6).unwrap().is_none());
+        assert!(ring.packet(0).unwrap().is_none());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
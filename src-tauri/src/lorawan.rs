@@ -0,0 +1,495 @@
+use std::collections::HashMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc;
+
+use aes::Aes128;
+use aes::cipher::{BlockEncrypt, KeyInit, generic_array::GenericArray};
+use base64::Engine;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::dissect::PacketLayers;
+use crate::error::KcpdumpError;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::packetlist;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::session::LoadedCapture;
+use crate::summary::PacketSummary;
+
+/// Link type of LoRaTap captures from SDR sniffers.
+pub const LINKTYPE_LORATAP: u32 = 270;
+
+/// UDP port of the Semtech packet forwarder protocol.
+pub const PACKET_FORWARDER_PORT: u16 = 1700;
+
+const PUSH_DATA: u8 = 0x00;
+const PULL_RESP: u8 = 0x03;
+
+/// Message Type
+/// The MType field of the MAC header.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum MessageType {
+    JoinRequest,
+    JoinAccept,
+    UnconfirmedDataUp,
+    UnconfirmedDataDown,
+    ConfirmedDataUp,
+    ConfirmedDataDown,
+    Rejoin,
+    Proprietary,
+}
+
+impl From<u8> for MessageType {
+    fn from(mhdr: u8) -> Self {
+        match mhdr >> 5 {
+            0 => MessageType::JoinRequest,
+            1 => MessageType::JoinAccept,
+            2 => MessageType::UnconfirmedDataUp,
+            3 => MessageType::UnconfirmedDataDown,
+            4 => MessageType::ConfirmedDataUp,
+            5 => MessageType::ConfirmedDataDown,
+            6 => MessageType::Rejoin,
+            _ => MessageType::Proprietary,
+        }
+    }
+}
+
+impl MessageType {
+    pub fn is_uplink(self) -> bool {
+        matches!(
+            self,
+            MessageType::JoinRequest
+                | MessageType::UnconfirmedDataUp
+                | MessageType::ConfirmedDataUp
+                | MessageType::Rejoin
+        )
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            MessageType::JoinRequest => "Join Request",
+            MessageType::JoinAccept => "Join Accept",
+            MessageType::UnconfirmedDataUp => "Unconfirmed Data Up",
+            MessageType::UnconfirmedDataDown => "Unconfirmed Data Down",
+            MessageType::ConfirmedDataUp => "Confirmed Data Up",
+            MessageType::ConfirmedDataDown => "Confirmed Data Down",
+            MessageType::Rejoin => "Rejoin Request",
+            MessageType::Proprietary => "Proprietary",
+        }
+    }
+}
+
+/// Data Frame
+/// The frame header and payload of a data message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataFrame {
+    pub dev_addr: u32,
+    pub adr: bool,
+    pub ack: bool,
+    /// Lower 16 bits of the frame counter.
+    pub fcnt: u16,
+    pub fopts: Vec<u8>,
+    pub fport: Option<u8>,
+    /// FRMPayload, encrypted.
+    pub payload: Vec<u8>,
+}
+
+/// LoRaWAN Frame
+/// A LoRaWAN PHY payload: MAC header, MAC payload and MIC.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoraWanFrame {
+    pub message_type: MessageType,
+    pub major: u8,
+    /// Decoded header of data messages; other message types keep their raw MAC payload.
+    pub data: Option<DataFrame>,
+    pub mac_payload: Vec<u8>,
+    pub mic: [u8; 4],
+}
+
+impl TryFrom<&[u8]> for LoraWanFrame {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 5 {
+            return Err(KcpdumpError::malformed(
+                0,
+                "Data too short for LoRaWAN frame",
+            ));
+        }
+        let message_type = MessageType::from(data[0]);
+        let mac_payload = &data[1..data.len() - 4];
+        let mut mic = [0u8; 4];
+        mic.copy_from_slice(&data[data.len() - 4..]);
+
+        let data_frame = match message_type {
+            MessageType::UnconfirmedDataUp
+            | MessageType::UnconfirmedDataDown
+            | MessageType::ConfirmedDataUp
+            | MessageType::ConfirmedDataDown => {
+                if mac_payload.len() < 7 {
+                    return Err(KcpdumpError::malformed(1, "LoRaWAN frame header truncated"));
+                }
+                let fctrl = mac_payload[4];
+                let fopts_end = 7 + usize::from(fctrl & 0x0f);
+                let fopts = mac_payload
+                    .get(7..fopts_end)
+                    .ok_or(KcpdumpError::malformed(
+                        7,
+                        "LoRaWAN frame options truncated",
+                    ))?;
+                let rest = &mac_payload[fopts_end..];
+                Some(DataFrame {
+                    dev_addr: u32::from_le_bytes([
+                        mac_payload[0],
+                        mac_payload[1],
+                        mac_payload[2],
+                        mac_payload[3],
+                    ]),
+                    adr: fctrl & 0x80 != 0,
+                    ack: fctrl & 0x20 != 0,
+                    fcnt: u16::from_le_bytes([mac_payload[5], mac_payload[6]]),
+                    fopts: fopts.to_vec(),
+                    fport: rest.first().copied(),
+                    payload: rest.get(1..).unwrap_or_default().to_vec(),
+                })
+            }
+            _ => None,
+        };
+        Ok(LoraWanFrame {
+            message_type,
+            major: data[0] & 0x03,
+            data: data_frame,
+            mac_payload: mac_payload.to_vec(),
+            mic,
+        })
+    }
+}
+
+impl LoraWanFrame {
+    pub fn describe(&self) -> String {
+        let mut info = self.message_type.name().to_string();
+        if let Some(data) = &self.data {
+            info += &format!(" DevAddr={:08x} FCnt={}", data.dev_addr, data.fcnt);
+            if let Some(fport) = data.fport {
+                info += &format!(" FPort={}", fport);
+            }
+        }
+        info
+    }
+
+    /// Decrypts the FRMPayload with the session keys of the device. FPort 0
+    /// carries MAC commands encrypted with the network key, other ports use
+    /// the application key. The frame counter's upper 16 bits are assumed zero.
+    pub fn decrypt(&self, keys: &SessionKeys) -> Option<Vec<u8>> {
+        let data = self.data.as_ref()?;
+        let key = if data.fport? == 0 {
+            keys.nwk_s_key?
+        } else {
+            keys.app_s_key?
+        };
+        let cipher = Aes128::new(&GenericArray::from(key));
+        let direction = u8::from(!self.message_type.is_uplink());
+        Some(
+            data.payload
+                .chunks(16)
+                .enumerate()
+                .flat_map(|(index, chunk)| {
+                    let mut block = [0u8; 16];
+                    block[0] = 0x01;
+                    block[5] = direction;
+                    block[6..10].copy_from_slice(&data.dev_addr.to_le_bytes());
+                    block[10..14].copy_from_slice(&u32::from(data.fcnt).to_le_bytes());
+                    block[15] = index as u8 + 1;
+                    let mut keystream = GenericArray::from(block);
+                    cipher.encrypt_block(&mut keystream);
+                    chunk
+                        .iter()
+                        .zip(keystream)
+                        .map(|(byte, key)| byte ^ key)
+                        .collect::<Vec<_>>()
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Session Keys
+/// ABP or joined session keys of one device.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionKeys {
+    pub nwk_s_key: Option<[u8; 16]>,
+    pub app_s_key: Option<[u8; 16]>,
+}
+
+/// Session Key Config
+/// Session keys as entered by the user, in hex.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionKeyConfig {
+    pub dev_addr: String,
+    pub nwk_s_key: Option<String>,
+    pub app_s_key: Option<String>,
+}
+
+fn parse_hex<const N: usize>(text: &str, what: &str) -> Result<[u8; N], KcpdumpError> {
+    let digits: String = text.chars().filter(|c| !matches!(c, ':' | ' ')).collect();
+    let error = || KcpdumpError::InvalidInput(format!("{} must be {} bytes of hex", what, N));
+    if digits.len() != N * 2 {
+        return Err(error());
+    }
+    let mut bytes = [0u8; N];
+    for (index, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&digits[index * 2..index * 2 + 2], 16).map_err(|_| error())?;
+    }
+    Ok(bytes)
+}
+
+/// Parses the configured keys into a table indexed by device address.
+pub fn parse_session_keys(
+    configs: &[SessionKeyConfig],
+) -> Result<HashMap<u32, SessionKeys>, KcpdumpError> {
+    configs
+        .iter()
+        .map(|config| {
+            // Device addresses are written most significant byte first.
+            let dev_addr = u32::from_be_bytes(parse_hex(&config.dev_addr, "DevAddr")?);
+            let nwk_s_key = config
+                .nwk_s_key
+                .as_deref()
+                .map(|key| parse_hex(key, "NwkSKey"))
+                .transpose()?;
+            let app_s_key = config
+                .app_s_key
+                .as_deref()
+                .map(|key| parse_hex(key, "AppSKey"))
+                .transpose()?;
+            Ok((
+                dev_addr,
+                SessionKeys {
+                    nwk_s_key,
+                    app_s_key,
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Extracts the PHY payloads of a Semtech packet forwarder datagram: `rxpk`
+/// entries of PUSH_DATA uplinks and the `txpk` of a PULL_RESP downlink.
+pub fn forwarder_payloads(datagram: &[u8]) -> Vec<Vec<u8>> {
+    let json = match datagram.get(3) {
+        Some(&PUSH_DATA) => datagram.get(12..),
+        Some(&PULL_RESP) => datagram.get(4..),
+        _ => None,
+    };
+    let Some(value) = json.and_then(|json| serde_json::from_slice::<serde_json::Value>(json).ok())
+    else {
+        return Vec::new();
+    };
+    let packets = match (&value["rxpk"], &value["txpk"]) {
+        (serde_json::Value::Array(packets), _) => packets.iter().collect(),
+        (_, packet @ serde_json::Value::Object(_)) => vec![packet],
+        _ => Vec::new(),
+    };
+    packets
+        .into_iter()
+        .filter_map(|packet| packet["data"].as_str())
+        .filter_map(|data| base64::engine::general_purpose::STANDARD.decode(data).ok())
+        .collect()
+}
+
+/// Strips the LoRaTap header from a raw capture frame.
+pub fn loratap_payload(frame: &[u8]) -> Option<&[u8]> {
+    let length = u16::from_be_bytes([*frame.get(2)?, *frame.get(3)?]) as usize;
+    frame.get(length..)
+}
+
+/// Summarizes a LoRaTap frame for the packet list.
+pub fn summarize(frame: &[u8]) -> PacketSummary {
+    let parsed = loratap_payload(frame)
+        .ok_or(KcpdumpError::malformed(0, "LoRaTap header truncated"))
+        .and_then(LoraWanFrame::try_from);
+    match parsed {
+        Ok(lorawan) => PacketSummary {
+            source: String::new(),
+            destination: String::new(),
+            protocol: "LoRaWAN".to_string(),
+            length: frame.len(),
+            info: lorawan.describe(),
+            flow: None,
+        },
+        Err(e) => PacketSummary {
+            source: String::new(),
+            destination: String::new(),
+            protocol: "Malformed".to_string(),
+            length: frame.len(),
+            info: e.to_string(),
+            flow: None,
+        },
+    }
+}
+
+/// LoRaWAN Frame Row
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LoraWanFrameRow {
+    pub capture_id: u32,
+    pub number: usize,
+    /// Seconds since the epoch.
+    pub time: f64,
+    pub message_type: MessageType,
+    pub dev_addr: Option<String>,
+    pub fcnt: Option<u16>,
+    pub fport: Option<u8>,
+    pub info: String,
+    /// FRMPayload as lowercase hex, decrypted when keys for the device are known.
+    pub payload: Option<String>,
+    pub decrypted: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Lists the LoRaWAN frames of the given captures, found either in packet
+/// forwarder traffic or in LoRaTap captures.
+pub fn lorawan_frames(
+    captures: &[Arc<LoadedCapture>],
+    keys: &HashMap<u32, SessionKeys>,
+) -> Result<Vec<LoraWanFrameRow>, KcpdumpError> {
+    let link_types = packetlist::link_types(captures);
+    let mut rows = Vec::new();
+    for item in packetlist::merged_packets(captures) {
+        let (capture_id, number, packet) = item?;
+        let payloads = if link_types[&capture_id] == LINKTYPE_LORATAP {
+            loratap_payload(&packet.data)
+                .map(|payload| vec![payload.to_vec()])
+                .unwrap_or_default()
+        } else {
+            let layers = PacketLayers::decode_link(number, link_types[&capture_id], &packet);
+            match &layers.udp {
+                Some(udp)
+                    if udp.source_port == PACKET_FORWARDER_PORT
+                        || udp.dest_port == PACKET_FORWARDER_PORT =>
+                {
+                    forwarder_payloads(&udp.payload)
+                }
+                _ => Vec::new(),
+            }
+        };
+        for payload in payloads {
+            let Ok(frame) = LoraWanFrame::try_from(payload.as_slice()) else {
+                continue;
+            };
+            let data = frame.data.as_ref();
+            let plaintext = data
+                .and_then(|data| keys.get(&data.dev_addr))
+                .and_then(|keys| frame.decrypt(keys));
+            rows.push(LoraWanFrameRow {
+                capture_id,
+                number,
+                time: packet.header.timestamp.as_secs_f64(),
+                message_type: frame.message_type,
+                dev_addr: data.map(|data| format!("{:08x}", data.dev_addr)),
+                fcnt: data.map(|data| data.fcnt),
+                fport: data.and_then(|data| data.fport),
+                info: frame.describe(),
+                decrypted: plaintext.is_some(),
+                payload: plaintext
+                    .as_deref()
+                    .or(data.map(|data| data.payload.as_slice()))
+                    .map(hex),
+            });
+        }
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unconfirmed uplink from 26011bda, FCnt 1, FPort 1, payload "hi"
+    /// encrypted with the AppSKey below.
+    fn uplink() -> Vec<u8> {
+        let keys = test_keys();
+        let mut frame = vec![0x40, 0xda, 0x1b, 0x01, 0x26, 0x00, 0x01, 0x00, 0x01];
+        let mut plain = LoraWanFrame {
+            message_type: MessageType::UnconfirmedDataUp,
+            major: 0,
+            data: Some(DataFrame {
+                dev_addr: 0x2601_1bda,
+                adr: false,
+                ack: false,
+                fcnt: 1,
+                fopts: Vec::new(),
+                fport: Some(1),
+                payload: b"hi".to_vec(),
+            }),
+            mac_payload: Vec::new(),
+            mic: [0; 4],
+        };
+        // CTR mode is symmetric, so decrypting the plaintext encrypts it.
+        let encrypted = plain.decrypt(&keys).unwrap();
+        plain.data.as_mut().unwrap().payload = encrypted.clone();
+        frame.extend_from_slice(&encrypted);
+        frame.extend_from_slice(&[0xaa, 0xbb, 0xcc, 0xdd]);
+        frame
+    }
+
+    fn test_keys() -> SessionKeys {
+        parse_session_keys(&[SessionKeyConfig {
+            dev_addr: "26011BDA".to_string(),
+            nwk_s_key: Some("00112233445566778899aabbccddeeff".to_string()),
+            app_s_key: Some("2b7e151628aed2a6abf7158809cf4f3c".to_string()),
+        }])
+        .unwrap()
+        .remove(&0x2601_1bda)
+        .unwrap()
+    }
+
+    #[test]
+    fn test_data_frame() {
+        let frame = LoraWanFrame::try_from(uplink().as_slice()).unwrap();
+        assert_eq!(frame.message_type, MessageType::UnconfirmedDataUp);
+        let data = frame.data.as_ref().unwrap();
+        assert_eq!(data.dev_addr, 0x2601_1bda);
+        assert_eq!(data.fcnt, 1);
+        assert_eq!(data.fport, Some(1));
+        assert_eq!(frame.mic, [0xaa, 0xbb, 0xcc, 0xdd]);
+        assert_eq!(
+            frame.describe(),
+            "Unconfirmed Data Up DevAddr=26011bda FCnt=1 FPort=1"
+        );
+        assert_ne!(data.payload, b"hi");
+        assert_eq!(frame.decrypt(&test_keys()).unwrap(), b"hi");
+    }
+
+    #[test]
+    fn test_packet_forwarder() {
+        let phy = base64::engine::general_purpose::STANDARD.encode(uplink());
+        let mut datagram = vec![0x02, 0x12, 0x34, PUSH_DATA];
+        datagram.extend_from_slice(&[0xaa; 8]);
+        datagram.extend_from_slice(
+            format!(r#"{{"rxpk":[{{"freq":868.1,"data":"{}"}}]}}"#, phy).as_bytes(),
+        );
+        assert_eq!(forwarder_payloads(&datagram), vec![uplink()]);
+
+        let mut response = vec![0x02, 0x00, 0x00, PULL_RESP];
+        response.extend_from_slice(format!(r#"{{"txpk":{{"data":"{}"}}}}"#, phy).as_bytes());
+        assert_eq!(forwarder_payloads(&response).len(), 1);
+        assert!(forwarder_payloads(&[0x02, 0x00, 0x00, 0x04]).is_empty());
+    }
+
+    #[test]
+    fn test_loratap_summary() {
+        let mut frame = vec![0x00, 0x00, 0x00, 0x04];
+        frame.extend(uplink());
+        let summary = summarize(&frame);
+        assert_eq!(summary.protocol, "LoRaWAN");
+        assert!(summary.info.starts_with("Unconfirmed Data Up"));
+        assert_eq!(summarize(&frame[..2]).protocol, "Malformed");
+    }
+}
@@ -2,5 +2,17 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    #[cfg(feature = "server")]
+    {
+        let mut args = std::env::args().skip(1);
+        if args.next().as_deref() == Some("--serve") {
+            let address = args.next().unwrap_or_else(|| "127.0.0.1:8080".to_string());
+            if let Err(e) = kcpdump_rs_lib::server::run(&address) {
+                eprintln!("API server on {} stopped: {}", address, e);
+                std::process::exit(1);
+            }
+            return;
+        }
+    }
     kcpdump_rs_lib::run()
 }
@@ -0,0 +1,219 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use crate::cap::{Capture, PcapWriter};
+use crate::error::KcpdumpError;
+
+/// Split Mode
+/// When `split_capture` starts a new output file.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(tag = "by", content = "value", rename_all = "camelCase")]
+pub enum SplitMode {
+    /// After this many packets.
+    Packets(usize),
+    /// When a packet is this many seconds later than the first of its file.
+    Seconds(u32),
+    /// Before a file would grow beyond this many bytes, headers included.
+    /// A packet larger than the limit still gets a file of its own.
+    Bytes(u64),
+}
+
+/// Size of the pcap file header and of each record header.
+const FILE_HEADER_SIZE: u64 = 24;
+const RECORD_HEADER_SIZE: u64 = 16;
+
+/// Interleaves the packets of several captures in timestamp order into a
+/// pcap file at `output_path`, returning the number of packets written.
+/// Packets with equal timestamps keep the order of `paths`. All inputs must
+/// share one link type; only one packet per input is held in memory. The
+/// output has nanosecond timestamps if any input does.
+pub async fn merge_captures(paths: &[String], output_path: &str) -> Result<usize, KcpdumpError> {
+    if paths.is_empty() {
+        return Err(KcpdumpError::InvalidInput(
+            "No capture files to merge".to_string(),
+        ));
+    }
+    let mut inputs = Vec::new();
+    for path in paths {
+        inputs.push(Capture::from_file(path).await?);
+    }
+    let network = inputs[0].header().network;
+    if let Some(other) = inputs.iter().find(|capture| capture.header().network != network) {
+        return Err(KcpdumpError::InvalidInput(format!(
+            "Cannot merge link types {} and {}",
+            network,
+            other.header().network
+        )));
+    }
+    let snaplen = inputs
+        .iter()
+        .map(|capture| capture.header().snaplen)
+        .max()
+        .unwrap_or(65535);
+    let resolution = inputs.iter().map(Capture::resolution).max().unwrap_or_default();
+
+    let mut heads = Vec::new();
+    for capture in &mut inputs {
+        heads.push(capture.next_packet().await?);
+    }
+    let file = File::create(output_path).map_err(|e| KcpdumpError::file(output_path, e))?;
+    let mut writer = PcapWriter::with_resolution(BufWriter::new(file), network, snaplen, resolution)
+        .map_err(write_error)?;
+    let mut count = 0;
+    loop {
+        let earliest = heads
+            .iter()
+            .enumerate()
+            .filter_map(|(index, head)| {
+                let header = &head.as_ref()?.header;
+                Some((header.timestamp, index))
+            })
+            .min();
+        let Some((_, index)) = earliest else {
+            break;
+        };
+        let Some(packet) = heads[index].take() else {
+            break;
+        };
+        writer.write_packet(&packet).map_err(write_error)?;
+        count += 1;
+        heads[index] = inputs[index].next_packet().await?;
+    }
+    writer.flush().map_err(write_error)?;
+    Ok(count)
+}
+
+/// Splits a capture into numbered pcap files next to it, named after the
+/// input, e.g. `trace-00001.pcap`, and returns their paths. Packets are
+/// streamed, so only the current output file is open at a time.
+pub async fn split_capture(path: &str, mode: SplitMode) -> Result<Vec<String>, KcpdumpError> {
+    if matches!(
+        mode,
+        SplitMode::Packets(0) | SplitMode::Seconds(0) | SplitMode::Bytes(0)
+    ) {
+        return Err(KcpdumpError::InvalidInput(
+            "Split size must be greater than zero".to_string(),
+        ));
+    }
+    let mut capture = Capture::from_file(path).await?;
+    let (network, snaplen) = (capture.header().network, capture.header().snaplen);
+    let resolution = capture.resolution();
+    let input = Path::new(path);
+    let stem = input
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "capture".to_string());
+    let directory = input.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut written = Vec::new();
+    let mut writer: Option<PcapWriter<BufWriter<File>>> = None;
+    // Packets, first timestamp and bytes of the current output file.
+    let (mut packets, mut start, mut bytes) = (0usize, 0u32, 0u64);
+    while let Some(packet) = capture.next_packet().await? {
+        let record_size = RECORD_HEADER_SIZE + packet.data.len() as u64;
+        let full = match mode {
+            SplitMode::Packets(limit) => packets >= limit,
+            SplitMode::Seconds(limit) => packet.header.timestamp.sec.saturating_sub(start) >= limit,
+            SplitMode::Bytes(limit) => packets > 0 && bytes + record_size > limit,
+        };
+        if writer.is_none() || full {
+            if let Some(mut writer) = writer.take() {
+                writer.flush().map_err(write_error)?;
+            }
+            let output = directory.join(format!("{}-{:05}.pcap", stem, written.len() + 1));
+            let file = File::create(&output)
+                .map_err(|e| KcpdumpError::file(&output.to_string_lossy(), e))?;
+            writer = Some(
+                PcapWriter::with_resolution(BufWriter::new(file), network, snaplen, resolution)
+                    .map_err(write_error)?,
+            );
+            written.push(output.to_string_lossy().into_owned());
+            (packets, start, bytes) = (0, packet.header.timestamp.sec, FILE_HEADER_SIZE);
+        }
+        if let Some(writer) = writer.as_mut() {
+            writer.write_packet(&packet).map_err(write_error)?;
+        }
+        packets += 1;
+        bytes += record_size;
+    }
+    if let Some(mut writer) = writer {
+        writer.flush().map_err(write_error)?;
+    }
+    Ok(written)
+}
+
+fn write_error(error: std::io::Error) -> KcpdumpError {
+    KcpdumpError::io("Failed to write capture file", error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cap::{PcapPacket, PcapPacketHeader};
+    use crate::timefmt::Timestamp;
+
+    fn write_capture(path: &Path, times: &[(u32, u32)]) {
+        let file = File::create(path).unwrap();
+        let mut writer = PcapWriter::new(BufWriter::new(file), 1, 65535).unwrap();
+        for &(ts_sec, ts_usec) in times {
+            let data = vec![ts_sec as u8; 60];
+            writer
+                .write_packet(&PcapPacket {
+                    header: PcapPacketHeader {
+                        timestamp: Timestamp::from_micros(ts_sec, ts_usec),
+                        incl_len: 60,
+                        orig_len: 60,
+                        link_type: None,
+                    },
+                    data,
+                })
+                .unwrap();
+        }
+        writer.flush().unwrap();
+    }
+
+    async fn read_times(path: &str) -> Vec<(u32, u32)> {
+        let mut capture = Capture::from_file(path).await.unwrap();
+        let mut times = Vec::new();
+        while let Some(packet) = capture.next_packet().await.unwrap() {
+            let timestamp = packet.header.timestamp;
+            times.push((timestamp.sec, timestamp.usec()));
+        }
+        times
+    }
+
+    #[tokio::test]
+    async fn test_merge_and_split() {
+        let directory = std::env::temp_dir().join(format!("kcpdump-merge-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let first = directory.join("a.pcap");
+        let second = directory.join("b.pcap");
+        write_capture(&first, &[(1, 0), (3, 0), (3, 500)]);
+        write_capture(&second, &[(2, 0), (3, 0), (9, 0)]);
+
+        let merged = directory.join("merged.pcap").to_string_lossy().into_owned();
+        let paths = [first, second].map(|path| path.to_string_lossy().into_owned());
+        assert_eq!(merge_captures(&paths, &merged).await.unwrap(), 6);
+        assert_eq!(
+            read_times(&merged).await,
+            [(1, 0), (2, 0), (3, 0), (3, 0), (3, 500), (9, 0)]
+        );
+
+        let files = split_capture(&merged, SplitMode::Packets(4)).await.unwrap();
+        assert_eq!(files.len(), 2);
+        assert!(files[0].ends_with("merged-00001.pcap"));
+        assert_eq!(read_times(&files[1]).await, [(3, 500), (9, 0)]);
+
+        let files = split_capture(&merged, SplitMode::Seconds(2)).await.unwrap();
+        assert_eq!(read_times(&files[0]).await, [(1, 0), (2, 0)]);
+        assert_eq!(files.len(), 3);
+
+        // Room for the header and two 76-byte records per file.
+        let files = split_capture(&merged, SplitMode::Bytes(24 + 2 * 76)).await.unwrap();
+        assert_eq!(files.len(), 3);
+        assert!(split_capture(&merged, SplitMode::Bytes(0)).await.is_err());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
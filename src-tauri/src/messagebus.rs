@@ -0,0 +1,191 @@
+use std::sync::Arc;
+
+use crate::dissect::PacketLayers;
+use crate::error::KcpdumpError;
+use crate::reassembly::{StreamReassembler, TcpStream};
+use crate::session::{CaptureId, LoadedCapture};
+
+pub mod amqp;
+pub mod kafka;
+
+/// Topic Partitions
+/// A Kafka topic named by a request, with the partitions it addresses.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TopicPartitions {
+    pub topic: String,
+    pub partitions: Vec<i32>,
+}
+
+/// Parsed Operation
+/// A method or request as found by a protocol parser, located by offsets
+/// into the reassembled data of the side that sent it and of the other side.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedOperation {
+    pub from_server: bool,
+    pub offset: usize,
+    /// Start of the reply; `None` if none was seen or none is expected.
+    pub response_offset: Option<usize>,
+    pub operation: String,
+    pub channel: Option<u16>,
+    pub correlation_id: Option<i32>,
+    pub exchange: Option<String>,
+    pub routing_key: Option<String>,
+    pub queue: Option<String>,
+    pub group: Option<String>,
+    pub topics: Vec<TopicPartitions>,
+    /// Message or record batch bytes carried by the operation.
+    pub size: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// Parsed Session
+/// What a protocol parser learned from one message bus connection.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedSession {
+    pub server_version: Option<String>,
+    pub client_id: Option<String>,
+    pub user: Option<String>,
+    pub virtual_host: Option<String>,
+    pub operations: Vec<ParsedOperation>,
+}
+
+/// Message Bus Operation
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageBusOperation {
+    /// Frame carrying the start of the operation.
+    pub number: usize,
+    /// Frame carrying the start of the reply.
+    pub response_number: Option<usize>,
+    /// Seconds since the epoch.
+    pub time: f64,
+    /// Seconds from the operation to the start of its reply.
+    pub response_time: Option<f64>,
+    pub from_server: bool,
+    /// AMQP method such as `basic.publish`, or Kafka API and version such
+    /// as `Produce v9`.
+    pub operation: String,
+    pub channel: Option<u16>,
+    pub correlation_id: Option<i32>,
+    pub exchange: Option<String>,
+    pub routing_key: Option<String>,
+    pub queue: Option<String>,
+    /// Kafka consumer group.
+    pub group: Option<String>,
+    pub topics: Vec<TopicPartitions>,
+    pub size: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// Message Bus Session
+/// One client connection to an AMQP 0-9-1 broker or a Kafka broker.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageBusSession {
+    pub capture_id: CaptureId,
+    /// `amqp` or `kafka`.
+    pub protocol: String,
+    pub first_number: usize,
+    pub client: String,
+    pub server: String,
+    pub server_version: Option<String>,
+    pub client_id: Option<String>,
+    pub user: Option<String>,
+    pub virtual_host: Option<String>,
+    /// Operations of both sides in frame order.
+    pub operations: Vec<MessageBusOperation>,
+}
+
+/// Detects the message bus protocol of a stream from its content.
+fn parse_stream(stream: &TcpStream) -> Option<(&'static str, ParsedSession)> {
+    let (client, server) = (&stream.client_data.data, &stream.server_data.data);
+    if let Some(session) = amqp::parse(client, server) {
+        return Some(("amqp", session));
+    }
+    kafka::parse(client, server).map(|session| ("kafka", session))
+}
+
+fn session(capture: &LoadedCapture, stream: &TcpStream) -> Option<MessageBusSession> {
+    let (protocol, parsed) = parse_stream(stream)?;
+    let time = |number: usize| {
+        capture
+            .index
+            .get(number)
+            .map(|entry| entry.timestamp.as_secs_f64())
+    };
+    let mut operations: Vec<MessageBusOperation> = parsed
+        .operations
+        .into_iter()
+        .map(|operation| {
+            let (sender, receiver) = if operation.from_server {
+                (&stream.server_data, &stream.client_data)
+            } else {
+                (&stream.client_data, &stream.server_data)
+            };
+            let number = sender
+                .frame_at(operation.offset)
+                .unwrap_or(stream.first_number);
+            let response_number = operation
+                .response_offset
+                .and_then(|offset| receiver.frame_at(offset));
+            let sent = time(number).unwrap_or_default();
+            MessageBusOperation {
+                number,
+                response_number,
+                time: sent,
+                response_time: response_number
+                    .and_then(time)
+                    .map(|received| received - sent),
+                from_server: operation.from_server,
+                operation: operation.operation,
+                channel: operation.channel,
+                correlation_id: operation.correlation_id,
+                exchange: operation.exchange,
+                routing_key: operation.routing_key,
+                queue: operation.queue,
+                group: operation.group,
+                topics: operation.topics,
+                size: operation.size,
+                error: operation.error,
+            }
+        })
+        .collect();
+    // Each side is in stream order already; a stable sort interleaves them.
+    operations.sort_by_key(|operation| operation.number);
+    Some(MessageBusSession {
+        capture_id: capture.id,
+        protocol: protocol.to_string(),
+        first_number: stream.first_number,
+        client: format!("{}:{}", stream.client.0, stream.client.1),
+        server: format!("{}:{}", stream.server.0, stream.server.1),
+        server_version: parsed.server_version,
+        client_id: parsed.client_id,
+        user: parsed.user,
+        virtual_host: parsed.virtual_host,
+        operations,
+    })
+}
+
+/// Finds the AMQP and Kafka connections of the captures with their
+/// methods, requests and replies. Protocols are recognised by content, so
+/// brokers on non-standard ports are found as well.
+pub fn message_bus_sessions(
+    captures: &[Arc<LoadedCapture>],
+) -> Result<Vec<MessageBusSession>, KcpdumpError> {
+    let mut sessions = Vec::new();
+    for capture in captures {
+        let mut reassembler = StreamReassembler::default();
+        for (index, packet) in capture.packets().enumerate() {
+            let packet = packet?;
+            reassembler.push(&PacketLayers::decode_link(index + 1, capture.header.network, &packet));
+        }
+        sessions.extend(
+            reassembler
+                .finish()
+                .iter()
+                .filter_map(|stream| session(capture, stream)),
+        );
+    }
+    Ok(sessions)
+}
@@ -0,0 +1,553 @@
+use std::collections::{HashMap, VecDeque};
+
+use super::{ParsedOperation, ParsedSession};
+
+const PROTOCOL_HEADER: &[u8] = b"AMQP\x00\x00\x09\x01";
+
+const FRAME_METHOD: u8 = 1;
+const FRAME_HEADER: u8 = 2;
+const FRAME_END: u8 = 0xce;
+
+const REPLY_SUCCESS: u16 = 200;
+
+/// A frame: type, channel, 4-byte size, payload and frame-end octet.
+struct Frame<'a> {
+    offset: usize,
+    kind: u8,
+    channel: u16,
+    payload: &'a [u8],
+}
+
+fn frames(data: &[u8], mut offset: usize) -> Vec<Frame<'_>> {
+    let mut frames = Vec::new();
+    while let Some(header) = data.get(offset..offset + 7) {
+        let size = u32::from_be_bytes([header[3], header[4], header[5], header[6]]) as usize;
+        let end = offset + 7 + size;
+        if data.get(end) != Some(&FRAME_END) {
+            break;
+        }
+        frames.push(Frame {
+            offset,
+            kind: header[0],
+            channel: u16::from_be_bytes([header[1], header[2]]),
+            payload: &data[offset + 7..end],
+        });
+        offset = end + 1;
+    }
+    frames
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Option<&'a [u8]> {
+        let (head, tail) = self.data.split_at_checked(length)?;
+        self.data = tail;
+        Some(head)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_be_bytes(self.take(2)?.try_into().ok()?))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_be_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_be_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn short_string(&mut self) -> Option<String> {
+        let length = self.u8()?;
+        Some(String::from_utf8_lossy(self.take(usize::from(length))?).into_owned())
+    }
+
+    fn long_string(&mut self) -> Option<&'a [u8]> {
+        let length = self.u32()?;
+        self.take(length as usize)
+    }
+
+    /// Reads a field table, keeping its long string values. Fields after
+    /// one of an unknown type are dropped.
+    fn table(&mut self) -> Option<HashMap<String, String>> {
+        let mut fields = Reader {
+            data: self.long_string()?,
+        };
+        let mut strings = HashMap::new();
+        while !fields.data.is_empty() && fields.field(&mut strings).is_some() {}
+        Some(strings)
+    }
+
+    fn field(&mut self, strings: &mut HashMap<String, String>) -> Option<()> {
+        let name = self.short_string()?;
+        let size = match self.u8()? {
+            b'S' => {
+                let value = String::from_utf8_lossy(self.long_string()?).into_owned();
+                strings.insert(name, value);
+                return Some(());
+            }
+            b'A' | b'F' | b'x' => {
+                self.long_string()?;
+                return Some(());
+            }
+            b'V' => 0,
+            b't' | b'b' | b'B' => 1,
+            b's' | b'u' => 2,
+            b'D' => 5,
+            b'I' | b'i' | b'f' => 4,
+            b'l' | b'L' | b'd' | b'T' => 8,
+            _ => return None,
+        };
+        self.take(size).map(|_| ())
+    }
+}
+
+fn method_name(class: u16, method: u16) -> String {
+    let name = match (class, method) {
+        (10, 10) => "connection.start",
+        (10, 11) => "connection.start-ok",
+        (10, 20) => "connection.secure",
+        (10, 21) => "connection.secure-ok",
+        (10, 30) => "connection.tune",
+        (10, 31) => "connection.tune-ok",
+        (10, 40) => "connection.open",
+        (10, 41) => "connection.open-ok",
+        (10, 50) => "connection.close",
+        (10, 51) => "connection.close-ok",
+        (10, 60) => "connection.blocked",
+        (10, 61) => "connection.unblocked",
+        (20, 10) => "channel.open",
+        (20, 11) => "channel.open-ok",
+        (20, 20) => "channel.flow",
+        (20, 21) => "channel.flow-ok",
+        (20, 40) => "channel.close",
+        (20, 41) => "channel.close-ok",
+        (40, 10) => "exchange.declare",
+        (40, 11) => "exchange.declare-ok",
+        (40, 20) => "exchange.delete",
+        (40, 21) => "exchange.delete-ok",
+        (40, 30) => "exchange.bind",
+        (40, 31) => "exchange.bind-ok",
+        (40, 40) => "exchange.unbind",
+        (40, 51) => "exchange.unbind-ok",
+        (50, 10) => "queue.declare",
+        (50, 11) => "queue.declare-ok",
+        (50, 20) => "queue.bind",
+        (50, 21) => "queue.bind-ok",
+        (50, 30) => "queue.purge",
+        (50, 31) => "queue.purge-ok",
+        (50, 40) => "queue.delete",
+        (50, 41) => "queue.delete-ok",
+        (50, 50) => "queue.unbind",
+        (50, 51) => "queue.unbind-ok",
+        (60, 10) => "basic.qos",
+        (60, 11) => "basic.qos-ok",
+        (60, 20) => "basic.consume",
+        (60, 21) => "basic.consume-ok",
+        (60, 30) => "basic.cancel",
+        (60, 31) => "basic.cancel-ok",
+        (60, 40) => "basic.publish",
+        (60, 50) => "basic.return",
+        (60, 60) => "basic.deliver",
+        (60, 70) => "basic.get",
+        (60, 71) => "basic.get-ok",
+        (60, 72) => "basic.get-empty",
+        (60, 80) => "basic.ack",
+        (60, 90) => "basic.reject",
+        (60, 100) => "basic.recover-async",
+        (60, 110) => "basic.recover",
+        (60, 111) => "basic.recover-ok",
+        (60, 120) => "basic.nack",
+        (85, 10) => "confirm.select",
+        (85, 11) => "confirm.select-ok",
+        (90, 10) => "tx.select",
+        (90, 11) => "tx.select-ok",
+        (90, 20) => "tx.commit",
+        (90, 21) => "tx.commit-ok",
+        (90, 30) => "tx.rollback",
+        (90, 31) => "tx.rollback-ok",
+        _ => return format!("method {}.{}", class, method),
+    };
+    name.to_string()
+}
+
+/// Synchronous methods, which the peer answers with a reply method.
+fn expects_reply(class: u16, method: u16) -> bool {
+    matches!(
+        (class, method),
+        (10, 10 | 20 | 30 | 40 | 50)
+            | (20, 10 | 20 | 40)
+            | (40, 10 | 20 | 30 | 40)
+            | (50, 10 | 20 | 30 | 40 | 50)
+            | (60, 10 | 20 | 30 | 70 | 110)
+            | (85, 10)
+            | (90, 10 | 20 | 30)
+    )
+}
+
+fn is_reply(class: u16, method: u16) -> bool {
+    matches!(
+        (class, method),
+        (10, 11 | 21 | 31 | 41 | 51)
+            | (20, 11 | 21 | 41)
+            | (40, 11 | 21 | 31 | 51)
+            | (50, 11 | 21 | 31 | 41 | 51)
+            | (60, 11 | 21 | 31 | 71 | 72 | 111)
+            | (85, 11)
+            | (90, 11 | 21 | 31)
+    )
+}
+
+fn answers(request: (u16, u16), reply: (u16, u16)) -> bool {
+    request.0 == reply.0
+        && match request.1 {
+            40 if request.0 == 40 => reply.1 == 51,
+            70 if request.0 == 60 => matches!(reply.1, 71 | 72),
+            method => reply.1 == method + 1,
+        }
+}
+
+/// Methods followed by a content header and body.
+fn carries_content(class: u16, method: u16) -> bool {
+    matches!((class, method), (60, 40 | 50 | 60 | 71))
+}
+
+/// Decodes the arguments of a method into `operation`, and what they say
+/// about the connection into `session`.
+fn arguments(
+    class: u16,
+    method: u16,
+    args: &mut Reader,
+    operation: &mut ParsedOperation,
+    session: &mut ParsedSession,
+) -> Option<()> {
+    match (class, method) {
+        (10, 10) => {
+            args.take(2)?;
+            let properties = args.table()?;
+            if let Some(product) = properties.get("product") {
+                session.server_version = Some(match properties.get("version") {
+                    Some(version) => format!("{} {}", product, version),
+                    None => product.clone(),
+                });
+            }
+        }
+        (10, 11) => {
+            let properties = args.table()?;
+            session.client_id = properties
+                .get("connection_name")
+                .or_else(|| properties.get("product"))
+                .cloned();
+            let mechanism = args.short_string()?;
+            let response = args.long_string()?;
+            if mechanism == "PLAIN" {
+                // Authorisation identity, user and password, NUL-separated.
+                session.user = response
+                    .split(|&byte| byte == 0)
+                    .nth(1)
+                    .map(|user| String::from_utf8_lossy(user).into_owned());
+            }
+        }
+        (10, 40) => session.virtual_host = Some(args.short_string()?),
+        (10, 50) | (20, 40) => {
+            let code = args.u16()?;
+            let text = args.short_string()?;
+            if code != REPLY_SUCCESS {
+                operation.error = Some(format!("{} {}", code, text));
+            }
+        }
+        (40, 10 | 20) => {
+            args.u16()?;
+            operation.exchange = Some(args.short_string()?);
+        }
+        (40, 30 | 40) => {
+            // Destination, then the source that messages are routed from.
+            args.u16()?;
+            args.short_string()?;
+            operation.exchange = Some(args.short_string()?);
+            operation.routing_key = Some(args.short_string()?);
+        }
+        (50, 10 | 30 | 40) | (60, 20 | 70) => {
+            args.u16()?;
+            operation.queue = Some(args.short_string()?);
+        }
+        (50, 11) => operation.queue = Some(args.short_string()?),
+        (50, 20 | 50) => {
+            args.u16()?;
+            operation.queue = Some(args.short_string()?);
+            operation.exchange = Some(args.short_string()?);
+            operation.routing_key = Some(args.short_string()?);
+        }
+        (60, 40) => {
+            args.u16()?;
+            operation.exchange = Some(args.short_string()?);
+            operation.routing_key = Some(args.short_string()?);
+        }
+        (60, 50) => {
+            let code = args.u16()?;
+            operation.error = Some(format!("{} {}", code, args.short_string()?));
+            operation.exchange = Some(args.short_string()?);
+            operation.routing_key = Some(args.short_string()?);
+        }
+        (60, 60) => {
+            // Consumer tag, delivery tag and redelivered flag.
+            args.short_string()?;
+            args.take(9)?;
+            operation.exchange = Some(args.short_string()?);
+            operation.routing_key = Some(args.short_string()?);
+        }
+        (60, 71) => {
+            args.take(9)?;
+            operation.exchange = Some(args.short_string()?);
+            operation.routing_key = Some(args.short_string()?);
+        }
+        _ => {}
+    }
+    Some(())
+}
+
+/// A reply method, folded into the request it answers.
+struct Reply {
+    class: u16,
+    method: u16,
+    operation: ParsedOperation,
+}
+
+/// Unanswered synchronous requests by sending side and channel: operation
+/// index, class and method.
+type PendingRequests = HashMap<(bool, u16), VecDeque<(usize, u16, u16)>>;
+
+/// Parses an AMQP 0-9-1 connection: the handshake and the methods of
+/// both sides, with synchronous requests paired to their replies on the
+/// same channel. Returns `None` unless the client opens with the 0-9-1
+/// protocol header.
+pub fn parse(client: &[u8], server: &[u8]) -> Option<ParsedSession> {
+    if !client.starts_with(PROTOCOL_HEADER) {
+        return None;
+    }
+    let mut session = ParsedSession::default();
+    // A broker without 0-9-1 support answers with its own header and closes.
+    if server.starts_with(b"AMQP") {
+        return Some(session);
+    }
+
+    let mut replies: Vec<Reply> = Vec::new();
+    let mut pending = PendingRequests::new();
+    let sides = [
+        (false, frames(client, PROTOCOL_HEADER.len())),
+        (true, frames(server, 0)),
+    ];
+    for (from_server, frames) in sides {
+        // Method whose content header comes next, by channel: whether it
+        // is a reply, and its index.
+        let mut content: HashMap<u16, (bool, usize)> = HashMap::new();
+        for frame in frames {
+            let mut payload = Reader {
+                data: frame.payload,
+            };
+            match frame.kind {
+                FRAME_METHOD => {
+                    let (Some(class), Some(method)) = (payload.u16(), payload.u16()) else {
+                        continue;
+                    };
+                    let mut operation = ParsedOperation {
+                        from_server,
+                        offset: frame.offset,
+                        operation: method_name(class, method),
+                        channel: Some(frame.channel),
+                        ..Default::default()
+                    };
+                    arguments(class, method, &mut payload, &mut operation, &mut session);
+                    let reply = is_reply(class, method);
+                    let index = if reply {
+                        replies.len()
+                    } else {
+                        session.operations.len()
+                    };
+                    if carries_content(class, method) {
+                        content.insert(frame.channel, (reply, index));
+                    }
+                    if reply {
+                        replies.push(Reply {
+                            class,
+                            method,
+                            operation,
+                        });
+                    } else {
+                        if expects_reply(class, method) {
+                            pending
+                                .entry((from_server, frame.channel))
+                                .or_default()
+                                .push_back((index, class, method));
+                        }
+                        session.operations.push(operation);
+                    }
+                }
+                FRAME_HEADER => {
+                    let Some((reply, index)) = content.remove(&frame.channel) else {
+                        continue;
+                    };
+                    // Class id and weight precede the body size.
+                    payload.take(4);
+                    let operation = if reply {
+                        &mut replies[index].operation
+                    } else {
+                        &mut session.operations[index]
+                    };
+                    operation.size = payload.u64();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    for reply in replies {
+        let channel = reply.operation.channel.unwrap_or_default();
+        let Some(requests) = pending.get_mut(&(!reply.operation.from_server, channel)) else {
+            continue;
+        };
+        // Requests skipped here went unanswered, e.g. because the peer
+        // closed the channel instead.
+        while let Some((index, class, method)) = requests.pop_front() {
+            if !answers((class, method), (reply.class, reply.method)) {
+                continue;
+            }
+            let operation = &mut session.operations[index];
+            let answer = reply.operation;
+            operation.response_offset = Some(answer.offset);
+            // A server-named queue is only known from declare-ok.
+            if answer.queue.is_some() && operation.queue.as_deref().is_none_or(str::is_empty) {
+                operation.queue = answer.queue;
+            }
+            operation.exchange = operation.exchange.take().or(answer.exchange);
+            operation.routing_key = operation.routing_key.take().or(answer.routing_key);
+            operation.size = operation.size.or(answer.size);
+            break;
+        }
+    }
+    Some(session)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn method(channel: u16, class: u16, method: u16, args: &[u8]) -> Vec<u8> {
+        let payload = [&class.to_be_bytes()[..], &method.to_be_bytes()[..], args].concat();
+        let mut frame = vec![FRAME_METHOD];
+        frame.extend_from_slice(&channel.to_be_bytes());
+        frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        frame.extend(payload);
+        frame.push(FRAME_END);
+        frame
+    }
+
+    fn short(text: &str) -> Vec<u8> {
+        [&[text.len() as u8][..], text.as_bytes()].concat()
+    }
+
+    fn long(data: &[u8]) -> Vec<u8> {
+        [&(data.len() as u32).to_be_bytes()[..], data].concat()
+    }
+
+    #[test]
+    fn test_amqp_session() {
+        let properties = [
+            short("capabilities"),
+            b"F".to_vec(),
+            long(&[]),
+            short("product"),
+            b"S".to_vec(),
+            long(b"RabbitMQ"),
+            short("version"),
+            b"S".to_vec(),
+            long(b"3.13.1"),
+        ]
+        .concat();
+        let start = [vec![0, 9], long(&properties), long(b"PLAIN"), long(b"en_US")].concat();
+        let start_ok = [
+            long(&[]),
+            short("PLAIN"),
+            long(b"\0orders\0secret"),
+            short("en_US"),
+        ]
+        .concat();
+        let mut header = vec![FRAME_HEADER, 0, 1, 0, 0, 0, 12];
+        header.extend_from_slice(&[0, 60, 0, 0]);
+        header.extend_from_slice(&42u64.to_be_bytes());
+        header.push(FRAME_END);
+
+        let mut client = PROTOCOL_HEADER.to_vec();
+        client.extend(method(0, 10, 11, &start_ok));
+        client.extend(method(0, 10, 40, &[short("/shop"), short("")].concat()));
+        client.extend(method(1, 20, 10, &short("")));
+        let declare = client.len();
+        client.extend(method(1, 50, 10, &[vec![0, 0], short(""), vec![0]].concat()));
+        client.extend(method(
+            1,
+            60,
+            40,
+            &[vec![0, 0], short("orders"), short("created")].concat(),
+        ));
+        client.extend(header);
+        client.extend(method(1, 20, 41, &[]));
+
+        let mut server = method(0, 10, 10, &start);
+        server.extend(method(0, 10, 41, &short("")));
+        server.extend(method(1, 20, 11, &long(&[])));
+        let declare_ok = server.len();
+        server.extend(method(1, 50, 11, &[short("amq.gen-1"), vec![0; 8]].concat()));
+        let close = [
+            404u16.to_be_bytes().to_vec(),
+            short("NOT_FOUND - no exchange 'orders'"),
+            vec![0, 60, 0, 40],
+        ]
+        .concat();
+        server.extend(method(1, 20, 40, &close));
+
+        let session = parse(&client, &server).unwrap();
+        assert_eq!(session.server_version.as_deref(), Some("RabbitMQ 3.13.1"));
+        assert_eq!(session.user.as_deref(), Some("orders"));
+        assert_eq!(session.virtual_host.as_deref(), Some("/shop"));
+        let operations: Vec<&str> = session
+            .operations
+            .iter()
+            .map(|operation| operation.operation.as_str())
+            .collect();
+        assert_eq!(
+            operations,
+            [
+                "connection.open",
+                "channel.open",
+                "queue.declare",
+                "basic.publish",
+                "connection.start",
+                "channel.close"
+            ]
+        );
+        let declared = &session.operations[2];
+        assert_eq!(declared.offset, declare);
+        assert_eq!(declared.response_offset, Some(declare_ok));
+        assert_eq!(declared.queue.as_deref(), Some("amq.gen-1"));
+        let publish = &session.operations[3];
+        assert_eq!(publish.exchange.as_deref(), Some("orders"));
+        assert_eq!(publish.routing_key.as_deref(), Some("created"));
+        assert_eq!(publish.size, Some(42));
+        let closed = &session.operations[5];
+        assert!(closed.from_server);
+        assert!(closed.response_offset.is_some());
+        assert_eq!(
+            closed.error.as_deref(),
+            Some("404 NOT_FOUND - no exchange 'orders'")
+        );
+
+        assert!(parse(b"GET / HTTP/1.1\r\n\r\n", b"HTTP/1.1 200 OK\r\n\r\n").is_none());
+    }
+}
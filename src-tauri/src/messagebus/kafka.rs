@@ -0,0 +1,703 @@
+use std::collections::HashMap;
+
+use super::{ParsedOperation, ParsedSession, TopicPartitions};
+
+const PRODUCE: i16 = 0;
+const FETCH: i16 = 1;
+const LIST_OFFSETS: i16 = 2;
+const METADATA: i16 = 3;
+const OFFSET_COMMIT: i16 = 8;
+const OFFSET_FETCH: i16 = 9;
+const FIND_COORDINATOR: i16 = 10;
+const JOIN_GROUP: i16 = 11;
+const HEARTBEAT: i16 = 12;
+const LEAVE_GROUP: i16 = 13;
+const SYNC_GROUP: i16 = 14;
+const SASL_HANDSHAKE: i16 = 17;
+const API_VERSIONS: i16 = 18;
+const CREATE_TOPICS: i16 = 19;
+const DELETE_TOPICS: i16 = 20;
+const SASL_AUTHENTICATE: i16 = 36;
+
+/// Largest message accepted when recognising a stream as Kafka.
+const MAX_MESSAGE: usize = 64 << 20;
+
+fn api_name(api_key: i16) -> Option<&'static str> {
+    Some(match api_key {
+        PRODUCE => "Produce",
+        FETCH => "Fetch",
+        LIST_OFFSETS => "ListOffsets",
+        METADATA => "Metadata",
+        4 => "LeaderAndIsr",
+        5 => "StopReplica",
+        6 => "UpdateMetadata",
+        7 => "ControlledShutdown",
+        OFFSET_COMMIT => "OffsetCommit",
+        OFFSET_FETCH => "OffsetFetch",
+        FIND_COORDINATOR => "FindCoordinator",
+        JOIN_GROUP => "JoinGroup",
+        HEARTBEAT => "Heartbeat",
+        LEAVE_GROUP => "LeaveGroup",
+        SYNC_GROUP => "SyncGroup",
+        15 => "DescribeGroups",
+        16 => "ListGroups",
+        SASL_HANDSHAKE => "SaslHandshake",
+        API_VERSIONS => "ApiVersions",
+        CREATE_TOPICS => "CreateTopics",
+        DELETE_TOPICS => "DeleteTopics",
+        21 => "DeleteRecords",
+        22 => "InitProducerId",
+        23 => "OffsetForLeaderEpoch",
+        24 => "AddPartitionsToTxn",
+        25 => "AddOffsetsToTxn",
+        26 => "EndTxn",
+        28 => "TxnOffsetCommit",
+        29 => "DescribeAcls",
+        32 => "DescribeConfigs",
+        33 => "AlterConfigs",
+        SASL_AUTHENTICATE => "SaslAuthenticate",
+        37 => "CreatePartitions",
+        42 => "DeleteGroups",
+        47 => "OffsetDelete",
+        60 => "DescribeCluster",
+        _ => return None,
+    })
+}
+
+/// First version of an API that uses the flexible encoding: compact
+/// strings and arrays, and tagged fields. `None` if it never does.
+fn flexible_from(api_key: i16) -> Option<i16> {
+    Some(match api_key {
+        PRODUCE => 9,
+        FETCH => 12,
+        LIST_OFFSETS => 6,
+        METADATA => 9,
+        OFFSET_COMMIT => 8,
+        OFFSET_FETCH => 6,
+        FIND_COORDINATOR => 3,
+        JOIN_GROUP => 6,
+        HEARTBEAT | LEAVE_GROUP | SYNC_GROUP => 4,
+        15 => 5,
+        16 => 3,
+        API_VERSIONS => 3,
+        CREATE_TOPICS => 5,
+        DELETE_TOPICS => 4,
+        22 | SASL_AUTHENTICATE => 2,
+        _ => return None,
+    })
+}
+
+fn error_name(code: i16) -> String {
+    let name = match code {
+        -1 => "UNKNOWN_SERVER_ERROR",
+        1 => "OFFSET_OUT_OF_RANGE",
+        2 => "CORRUPT_MESSAGE",
+        3 => "UNKNOWN_TOPIC_OR_PARTITION",
+        5 => "LEADER_NOT_AVAILABLE",
+        6 => "NOT_LEADER_OR_FOLLOWER",
+        7 => "REQUEST_TIMED_OUT",
+        10 => "MESSAGE_TOO_LARGE",
+        14 => "COORDINATOR_LOAD_IN_PROGRESS",
+        15 => "COORDINATOR_NOT_AVAILABLE",
+        16 => "NOT_COORDINATOR",
+        17 => "INVALID_TOPIC_EXCEPTION",
+        19 => "NOT_ENOUGH_REPLICAS",
+        22 => "ILLEGAL_GENERATION",
+        25 => "UNKNOWN_MEMBER_ID",
+        27 => "REBALANCE_IN_PROGRESS",
+        29 => "TOPIC_AUTHORIZATION_FAILED",
+        30 => "GROUP_AUTHORIZATION_FAILED",
+        31 => "CLUSTER_AUTHORIZATION_FAILED",
+        33 => "UNSUPPORTED_SASL_MECHANISM",
+        34 => "ILLEGAL_SASL_STATE",
+        35 => "UNSUPPORTED_VERSION",
+        36 => "TOPIC_ALREADY_EXISTS",
+        37 => "INVALID_PARTITIONS",
+        41 => "NOT_CONTROLLER",
+        58 => "SASL_AUTHENTICATION_FAILED",
+        79 => "MEMBER_ID_REQUIRED",
+        _ => return format!("Error {}", code),
+    };
+    format!("{} ({})", name, code)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    /// Whether strings and arrays are compact and structures end in tagged
+    /// fields.
+    flexible: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Option<&'a [u8]> {
+        let (head, tail) = self.data.split_at_checked(length)?;
+        self.data = tail;
+        Some(head)
+    }
+
+    fn i8(&mut self) -> Option<i8> {
+        Some(self.take(1)?[0] as i8)
+    }
+
+    fn i16(&mut self) -> Option<i16> {
+        Some(i16::from_be_bytes(self.take(2)?.try_into().ok()?))
+    }
+
+    fn i32(&mut self) -> Option<i32> {
+        Some(i32::from_be_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn i64(&mut self) -> Option<i64> {
+        Some(i64::from_be_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn uvarint(&mut self) -> Option<u64> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    /// Length of a compact field, which is stored plus one so that zero
+    /// means null.
+    fn compact_length(&mut self) -> Option<Option<usize>> {
+        Some(self.uvarint()?.checked_sub(1).map(|length| length as usize))
+    }
+
+    /// Reads a nullable string; the outer `None` means truncated data.
+    fn string(&mut self) -> Option<Option<String>> {
+        let length = if self.flexible {
+            self.compact_length()?
+        } else {
+            usize::try_from(self.i16()?).ok()
+        };
+        let Some(length) = length else {
+            return Some(None);
+        };
+        let text = self.take(length)?;
+        Some(Some(String::from_utf8_lossy(text).into_owned()))
+    }
+
+    /// Skips a bytes or records field, returning its length.
+    fn bytes(&mut self) -> Option<usize> {
+        let length = if self.flexible {
+            self.compact_length()?
+        } else {
+            usize::try_from(self.i32()?).ok()
+        }
+        .unwrap_or_default();
+        self.take(length)?;
+        Some(length)
+    }
+
+    /// Element count of an array; a null array counts as empty.
+    fn array(&mut self) -> Option<usize> {
+        let count = if self.flexible {
+            self.compact_length()?
+        } else {
+            usize::try_from(self.i32()?).ok()
+        }
+        .unwrap_or_default();
+        // Every element takes at least a byte.
+        (count <= self.data.len()).then_some(count)
+    }
+
+    fn tagged_fields(&mut self) -> Option<()> {
+        if self.flexible {
+            for _ in 0..self.uvarint()? {
+                self.uvarint()?;
+                let size = self.uvarint()?;
+                self.take(usize::try_from(size).ok()?)?;
+            }
+        }
+        Some(())
+    }
+
+    fn uuid(&mut self) -> Option<String> {
+        let id = self.take(16)?;
+        Some(id.iter().map(|byte| format!("{:02x}", byte)).collect())
+    }
+
+    /// Reads a topic array whose elements start with a name (or, for
+    /// `by_id`, a topic id) followed by a partition array, using `partition`
+    /// to read each partition and its index.
+    fn topics(
+        &mut self,
+        by_id: bool,
+        mut partition: impl FnMut(&mut Self) -> Option<i32>,
+    ) -> Option<Vec<TopicPartitions>> {
+        let mut topics = Vec::new();
+        for _ in 0..self.array()? {
+            let topic = if by_id {
+                self.uuid()?
+            } else {
+                self.string()?.unwrap_or_default()
+            };
+            let mut partitions = Vec::new();
+            for _ in 0..self.array()? {
+                partitions.push(partition(self)?);
+                self.tagged_fields()?;
+            }
+            self.tagged_fields()?;
+            topics.push(TopicPartitions { topic, partitions });
+        }
+        Some(topics)
+    }
+}
+
+/// A request or response: offset of its 4-byte length, and its body.
+fn messages(data: &[u8]) -> Vec<(usize, &[u8])> {
+    let mut messages = Vec::new();
+    let mut offset = 0;
+    while let Some(length) = data.get(offset..offset + 4) {
+        let length = u32::from_be_bytes([length[0], length[1], length[2], length[3]]) as usize;
+        let end = offset + 4 + length;
+        if !(4..=MAX_MESSAGE).contains(&length) || end > data.len() {
+            break;
+        }
+        messages.push((offset, &data[offset + 4..end]));
+        offset = end;
+    }
+    messages
+}
+
+struct RequestHeader {
+    api_key: i16,
+    api_version: i16,
+    correlation_id: i32,
+    client_id: Option<String>,
+    flexible: bool,
+}
+
+/// Reads a request header, which is left with the body in `reader`.
+fn request_header(reader: &mut Reader) -> Option<RequestHeader> {
+    let api_key = reader.i16()?;
+    let api_version = reader.i16()?;
+    let correlation_id = reader.i32()?;
+    api_name(api_key)?;
+    if !(0..=20).contains(&api_version) {
+        return None;
+    }
+    // The client id is a legacy string even in flexible headers.
+    let client_id = reader.string()?;
+    if client_id
+        .as_deref()
+        .is_some_and(|id| !id.chars().all(|c| c.is_ascii_graphic() || c == ' '))
+    {
+        return None;
+    }
+    let flexible = flexible_from(api_key).is_some_and(|from| api_version >= from);
+    reader.flexible = flexible;
+    reader.tagged_fields()?;
+    Some(RequestHeader {
+        api_key,
+        api_version,
+        correlation_id,
+        client_id,
+        flexible,
+    })
+}
+
+/// Decodes the topics, partitions and group a request addresses.
+fn request_body(
+    api_key: i16,
+    version: i16,
+    body: &mut Reader,
+    operation: &mut ParsedOperation,
+) -> Option<()> {
+    match api_key {
+        PRODUCE => {
+            if version >= 3 {
+                body.string()?; // transactional id
+            }
+            body.i16()?; // acks
+            body.i32()?; // timeout
+            let mut size = 0;
+            operation.topics = body.topics(version >= 13, |body| {
+                let index = body.i32()?;
+                size += body.bytes()? as u64;
+                Some(index)
+            })?;
+            operation.size = Some(size);
+        }
+        FETCH => {
+            if version <= 14 {
+                body.i32()?; // replica id
+            }
+            body.i32()?; // max wait
+            body.i32()?; // min bytes
+            if version >= 3 {
+                body.i32()?; // max bytes
+            }
+            if version >= 4 {
+                body.i8()?; // isolation level
+            }
+            if version >= 7 {
+                body.take(8)?; // session id and epoch
+            }
+            operation.topics = body.topics(version >= 13, |body| {
+                let index = body.i32()?;
+                if version >= 9 {
+                    body.i32()?; // current leader epoch
+                }
+                body.i64()?; // fetch offset
+                if version >= 12 {
+                    body.i32()?; // last fetched epoch
+                }
+                if version >= 5 {
+                    body.i64()?; // log start offset
+                }
+                body.i32()?; // partition max bytes
+                Some(index)
+            })?;
+        }
+        LIST_OFFSETS => {
+            body.i32()?; // replica id
+            if version >= 2 {
+                body.i8()?;
+            }
+            operation.topics = body.topics(false, |body| {
+                let index = body.i32()?;
+                if version >= 4 {
+                    body.i32()?; // current leader epoch
+                }
+                body.i64()?; // timestamp
+                if version == 0 {
+                    body.i32()?; // max number of offsets
+                }
+                Some(index)
+            })?;
+        }
+        METADATA => {
+            // A null array asks for all topics.
+            for _ in 0..body.array()? {
+                if version >= 10 {
+                    body.uuid()?;
+                }
+                if let Some(topic) = body.string()? {
+                    operation.topics.push(TopicPartitions {
+                        topic,
+                        partitions: Vec::new(),
+                    });
+                }
+                body.tagged_fields()?;
+            }
+        }
+        OFFSET_COMMIT => {
+            operation.group = body.string()?;
+            if version >= 1 {
+                body.i32()?; // generation
+                body.string()?; // member id
+            }
+            if version >= 7 {
+                body.string()?; // group instance id
+            }
+            if (2..=4).contains(&version) {
+                body.i64()?; // retention time
+            }
+            operation.topics = body.topics(false, |body| {
+                let index = body.i32()?;
+                body.i64()?; // committed offset
+                if version >= 6 {
+                    body.i32()?; // leader epoch
+                }
+                if version == 1 {
+                    body.i64()?; // commit timestamp
+                }
+                body.string()?; // metadata
+                Some(index)
+            })?;
+        }
+        // Later versions batch several groups.
+        OFFSET_FETCH if version <= 7 => {
+            operation.group = body.string()?;
+            for _ in 0..body.array()? {
+                let topic = body.string()?.unwrap_or_default();
+                let mut partitions = Vec::new();
+                for _ in 0..body.array()? {
+                    partitions.push(body.i32()?);
+                }
+                body.tagged_fields()?;
+                operation.topics.push(TopicPartitions { topic, partitions });
+            }
+        }
+        FIND_COORDINATOR if version <= 3 => operation.group = body.string()?,
+        JOIN_GROUP | HEARTBEAT | LEAVE_GROUP | SYNC_GROUP => operation.group = body.string()?,
+        CREATE_TOPICS => {
+            for _ in 0..body.array()? {
+                let topic = body.string()?.unwrap_or_default();
+                let partitions = body.i32()?;
+                body.i16()?; // replication factor
+                for _ in 0..body.array()? {
+                    body.i32()?;
+                    for _ in 0..body.array()? {
+                        body.i32()?;
+                    }
+                    body.tagged_fields()?;
+                }
+                for _ in 0..body.array()? {
+                    body.string()?;
+                    body.string()?;
+                    body.tagged_fields()?;
+                }
+                body.tagged_fields()?;
+                // The partition count, or -1 for the broker default.
+                operation.topics.push(TopicPartitions {
+                    topic,
+                    partitions: (0..partitions.max(0)).collect(),
+                });
+            }
+        }
+        DELETE_TOPICS => {
+            for _ in 0..body.array()? {
+                let topic = body.string()?;
+                if version >= 6 {
+                    body.uuid()?;
+                    body.tagged_fields()?;
+                }
+                operation.topics.push(TopicPartitions {
+                    topic: topic.unwrap_or_default(),
+                    partitions: Vec::new(),
+                });
+            }
+        }
+        _ => {}
+    }
+    Some(())
+}
+
+/// The first error code a response reports, or `None` if its layout is
+/// not known. Zero means success.
+fn response_error(api_key: i16, version: i16, body: &mut Reader) -> Option<i16> {
+    match api_key {
+        API_VERSIONS | SASL_HANDSHAKE | SASL_AUTHENTICATE => body.i16(),
+        FIND_COORDINATOR if version <= 3 => {
+            if version >= 1 {
+                body.i32()?; // throttle time
+            }
+            body.i16()
+        }
+        JOIN_GROUP | HEARTBEAT | LEAVE_GROUP | SYNC_GROUP => {
+            let throttled = if api_key == JOIN_GROUP { 2 } else { 1 };
+            if version >= throttled {
+                body.i32()?;
+            }
+            body.i16()
+        }
+        PRODUCE => {
+            let mut first = 0;
+            body.topics(version >= 13, |body| {
+                let index = body.i32()?;
+                let error = body.i16()?;
+                if first == 0 {
+                    first = error;
+                }
+                body.i64()?; // base offset
+                if version >= 2 {
+                    body.i64()?; // log append time
+                }
+                if version >= 5 {
+                    body.i64()?; // log start offset
+                }
+                if version >= 8 {
+                    for _ in 0..body.array()? {
+                        body.i32()?;
+                        body.string()?;
+                        body.tagged_fields()?;
+                    }
+                    body.string()?;
+                }
+                Some(index)
+            })?;
+            Some(first)
+        }
+        METADATA if version <= 12 => {
+            if version >= 3 {
+                body.i32()?; // throttle time
+            }
+            for _ in 0..body.array()? {
+                body.i32()?; // node id
+                body.string()?; // host
+                body.i32()?; // port
+                if version >= 1 {
+                    body.string()?; // rack
+                }
+                body.tagged_fields()?;
+            }
+            if version >= 2 {
+                body.string()?; // cluster id
+            }
+            if version >= 1 {
+                body.i32()?; // controller id
+            }
+            // The topics follow, each led by its error code.
+            body.array()?;
+            body.i16()
+        }
+        _ => None,
+    }
+}
+
+/// Parses a Kafka connection: each request's API, version, correlation id
+/// and addressed topics and partitions, paired with the response carrying
+/// the same correlation id. Returns `None` unless the client's first
+/// message is a well-formed request header that the server's first
+/// response, if any, answers.
+pub fn parse(client: &[u8], server: &[u8]) -> Option<ParsedSession> {
+    let requests = messages(client);
+    let responses = messages(server);
+    let (_, first) = requests.first()?;
+    let header = request_header(&mut Reader {
+        data: first,
+        flexible: false,
+    })?;
+    if let Some((_, response)) = responses.first()
+        && response.get(..4) != Some(&header.correlation_id.to_be_bytes()[..])
+    {
+        return None;
+    }
+
+    let mut by_correlation: HashMap<i32, (usize, &[u8])> = HashMap::new();
+    for &(offset, response) in &responses {
+        let id = i32::from_be_bytes([response[0], response[1], response[2], response[3]]);
+        by_correlation.entry(id).or_insert((offset, &response[4..]));
+    }
+
+    let mut session = ParsedSession::default();
+    for (offset, request) in requests {
+        let mut body = Reader {
+            data: request,
+            flexible: false,
+        };
+        let Some(header) = request_header(&mut body) else {
+            break;
+        };
+        if session.client_id.is_none() {
+            session.client_id = header.client_id;
+        }
+        let mut operation = ParsedOperation {
+            offset,
+            operation: format!(
+                "{} v{}",
+                api_name(header.api_key).unwrap_or_default(),
+                header.api_version
+            ),
+            correlation_id: Some(header.correlation_id),
+            ..Default::default()
+        };
+        request_body(header.api_key, header.api_version, &mut body, &mut operation);
+        if let Some(&(response_offset, response)) = by_correlation.get(&header.correlation_id) {
+            operation.response_offset = Some(response_offset);
+            let mut body = Reader {
+                data: response,
+                flexible: header.flexible,
+            };
+            // ApiVersions responses keep the old header so that any client
+            // can read them.
+            if header.api_key == API_VERSIONS || body.tagged_fields().is_some() {
+                operation.error = response_error(header.api_key, header.api_version, &mut body)
+                    .filter(|&code| code != 0)
+                    .map(error_name);
+            }
+        }
+        session.operations.push(operation);
+    }
+    Some(session)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(body: &[u8]) -> Vec<u8> {
+        [&(body.len() as u32).to_be_bytes()[..], body].concat()
+    }
+
+    fn string(text: &str) -> Vec<u8> {
+        [&(text.len() as i16).to_be_bytes()[..], text.as_bytes()].concat()
+    }
+
+    fn header(api_key: i16, version: i16, correlation_id: i32) -> Vec<u8> {
+        [
+            api_key.to_be_bytes().to_vec(),
+            version.to_be_bytes().to_vec(),
+            correlation_id.to_be_bytes().to_vec(),
+            string("billing"),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn test_kafka_session() {
+        // Produce v7 of 10 record bytes to partition 2 of "orders".
+        let produce = [
+            header(PRODUCE, 7, 5),
+            vec![0xff, 0xff], // no transactional id
+            vec![0, 1, 0, 0, 0x75, 0x30],
+            vec![0, 0, 0, 1],
+            string("orders"),
+            vec![0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 10],
+            vec![0; 10],
+        ]
+        .concat();
+        // Metadata v9 (flexible) for "payments".
+        let metadata = [
+            header(METADATA, 9, 6),
+            vec![0], // no tagged fields
+            vec![2, 9],
+            b"payments".to_vec(),
+            vec![0, 0, 0, 0],
+        ]
+        .concat();
+        let mut client = message(&produce);
+        let second = client.len();
+        client.extend(message(&metadata));
+
+        let produced = [
+            vec![0, 0, 0, 5],
+            vec![0, 0, 0, 1],
+            string("orders"),
+            vec![0, 0, 0, 1, 0, 0, 0, 2, 0, 6],
+            vec![0; 24],
+            vec![0, 0, 0, 0],
+        ]
+        .concat();
+        let mut server = message(&produced);
+        server.extend(message(&[0, 0, 0, 6, 0]));
+
+        let session = parse(&client, &server).unwrap();
+        assert_eq!(session.client_id.as_deref(), Some("billing"));
+        assert_eq!(session.operations.len(), 2);
+        let produce = &session.operations[0];
+        assert_eq!(produce.operation, "Produce v7");
+        assert_eq!(produce.correlation_id, Some(5));
+        assert_eq!(
+            produce.topics,
+            [TopicPartitions {
+                topic: "orders".to_string(),
+                partitions: vec![2],
+            }]
+        );
+        assert_eq!(produce.size, Some(10));
+        assert_eq!(produce.response_offset, Some(0));
+        assert_eq!(
+            produce.error.as_deref(),
+            Some("NOT_LEADER_OR_FOLLOWER (6)")
+        );
+        let metadata = &session.operations[1];
+        assert_eq!(metadata.offset, second);
+        assert_eq!(metadata.topics[0].topic, "payments");
+        assert!(metadata.response_offset.is_some());
+
+        assert!(parse(b"GET / HTTP/1.1\r\n\r\n", b"HTTP/1.1 200 OK\r\n\r\n").is_none());
+    }
+}
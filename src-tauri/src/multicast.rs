@@ -0,0 +1,396 @@
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+
+use crate::dissect::PacketLayers;
+use crate::error::KcpdumpError;
+use crate::packetlist;
+use crate::session::LoadedCapture;
+
+/// IP protocol number of IGMP.
+pub const IP_PROTOCOL_IGMP: u8 = 2;
+
+pub const TS_PACKET_LEN: usize = 188;
+const TS_SYNC_BYTE: u8 = 0x47;
+const TS_NULL_PID: u16 = 0x1fff;
+/// RTP payload type of MPEG-TS (RFC 3551).
+const RTP_PAYLOAD_MP2T: u8 = 33;
+
+const IGMP_V1_REPORT: u8 = 0x12;
+const IGMP_V2_REPORT: u8 = 0x16;
+const IGMP_V2_LEAVE: u8 = 0x17;
+const IGMP_V3_REPORT: u8 = 0x22;
+
+/// Membership Change
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MembershipChange {
+    Join,
+    Leave,
+}
+
+/// Membership Event
+/// A host joining or leaving a group, from an IGMP report.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MembershipEvent {
+    /// Seconds since the epoch.
+    pub time: f64,
+    pub host: String,
+    pub group: String,
+    pub change: MembershipChange,
+}
+
+/// Parses the group membership changes of an IGMP message. Every IGMPv1 and
+/// v2 report counts as a join, since hosts of those versions join that way.
+pub fn membership_changes(data: &[u8]) -> Vec<(Ipv4Addr, MembershipChange)> {
+    if data.len() < 8 {
+        return Vec::new();
+    }
+    let group = Ipv4Addr::new(data[4], data[5], data[6], data[7]);
+    match data[0] {
+        IGMP_V1_REPORT | IGMP_V2_REPORT => vec![(group, MembershipChange::Join)],
+        IGMP_V2_LEAVE => vec![(group, MembershipChange::Leave)],
+        IGMP_V3_REPORT => {
+            let count = usize::from(u16::from_be_bytes([data[6], data[7]]));
+            let mut changes = Vec::new();
+            let mut rest = &data[8..];
+            for _ in 0..count {
+                let Some(record) = rest.get(..8) else {
+                    break;
+                };
+                let sources = usize::from(u16::from_be_bytes([record[2], record[3]]));
+                let length = 8 + 4 * sources + 4 * usize::from(record[1]);
+                let group = Ipv4Addr::new(record[4], record[5], record[6], record[7]);
+                // CHANGE_TO_INCLUDE with no sources leaves, IS_EXCLUDE and
+                // CHANGE_TO_EXCLUDE join; source filter changes are left out.
+                match (record[0], sources) {
+                    (3, 0) => changes.push((group, MembershipChange::Leave)),
+                    (2 | 4, _) => changes.push((group, MembershipChange::Join)),
+                    _ => {}
+                }
+                rest = rest.get(length..).unwrap_or_default();
+            }
+            changes
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Returns the MPEG-TS packets of a UDP payload, looking through an RTP
+/// header if there is one.
+pub fn ts_payload(payload: &[u8]) -> Option<(&[u8], bool)> {
+    let is_ts = |data: &[u8]| {
+        !data.is_empty()
+            && data.len().is_multiple_of(TS_PACKET_LEN)
+            && data
+                .chunks_exact(TS_PACKET_LEN)
+                .all(|packet| packet[0] == TS_SYNC_BYTE)
+    };
+    if is_ts(payload) {
+        return Some((payload, false));
+    }
+    if payload.len() >= 12 && payload[0] >> 6 == 2 && payload[1] & 0x7f == RTP_PAYLOAD_MP2T {
+        let header_len = 12 + 4 * usize::from(payload[0] & 0x0f);
+        let data = payload.get(header_len..)?;
+        if is_ts(data) {
+            return Some((data, true));
+        }
+    }
+    None
+}
+
+/// Continuity counter state of the PIDs of one stream.
+#[derive(Default)]
+struct ContinuityTracker {
+    counters: HashMap<u16, u8>,
+    packets: usize,
+    errors: usize,
+}
+
+impl ContinuityTracker {
+    fn push(&mut self, packet: &[u8]) {
+        self.packets += 1;
+        let pid = u16::from_be_bytes([packet[1], packet[2]]) & 0x1fff;
+        if pid == TS_NULL_PID {
+            return;
+        }
+        let adaptation = (packet[3] >> 4) & 0x03;
+        let counter = packet[3] & 0x0f;
+        let discontinuity = adaptation & 0x02 != 0 && packet[4] > 0 && packet[5] & 0x80 != 0;
+        // The counter only advances on packets with payload; one duplicate is allowed.
+        let has_payload = adaptation & 0x01 != 0;
+        if let Some(&last) = self.counters.get(&pid) {
+            let expected = if has_payload { (last + 1) & 0x0f } else { last };
+            if !discontinuity && counter != expected && !(has_payload && counter == last) {
+                self.errors += 1;
+            }
+        }
+        self.counters.insert(pid, counter);
+    }
+}
+
+/// Multicast Stream
+/// UDP traffic from one source to one group and port.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MulticastStream {
+    pub group: String,
+    pub port: u16,
+    pub source: String,
+    pub packets: usize,
+    pub bytes: u64,
+    pub first_seen: f64,
+    pub last_seen: f64,
+    /// Average bitrate of the IP packets, in bits per second.
+    pub bitrate: f64,
+    /// Whether the payloads are MPEG-TS, and whether they are carried in RTP.
+    pub mpeg_ts: bool,
+    pub rtp: bool,
+    pub ts_packets: usize,
+    pub pids: usize,
+    pub continuity_errors: usize,
+    /// Seconds from the first join of the group to the first packet.
+    pub join_latency: Option<f64>,
+    /// Seconds from the last leave of the group to the last packet.
+    pub leave_latency: Option<f64>,
+}
+
+/// Multicast Report
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MulticastReport {
+    pub streams: Vec<MulticastStream>,
+    pub membership: Vec<MembershipEvent>,
+}
+
+struct StreamState {
+    packets: usize,
+    bytes: u64,
+    first_seen: f64,
+    last_seen: f64,
+    rtp: bool,
+    continuity: ContinuityTracker,
+}
+
+/// Groups the multicast UDP traffic of the captures into streams and
+/// correlates them with the IGMP joins and leaves of their groups.
+pub fn multicast_report(captures: &[Arc<LoadedCapture>]) -> Result<MulticastReport, KcpdumpError> {
+    let mut streams: HashMap<(Ipv4Addr, u16, Ipv4Addr), StreamState> = HashMap::new();
+    let mut order = Vec::new();
+    let mut membership = Vec::new();
+
+    let link_types = packetlist::link_types(captures);
+    for item in packetlist::merged_packets(captures) {
+        let (capture_id, number, packet) = item?;
+        let layers = PacketLayers::decode_link(number, link_types[&capture_id], &packet);
+        let Some(ip) = &layers.ipv4 else {
+            continue;
+        };
+        let time = packet.header.timestamp.as_secs_f64();
+        let source = Ipv4Addr::from(ip.source_ip);
+        if ip.protocol == IP_PROTOCOL_IGMP {
+            for (group, change) in membership_changes(&ip.payload) {
+                membership.push(MembershipEvent {
+                    time,
+                    host: source.to_string(),
+                    group: group.to_string(),
+                    change,
+                });
+            }
+            continue;
+        }
+        let group = Ipv4Addr::from(ip.dest_ip);
+        let Some(udp) = layers.udp.as_ref().filter(|_| group.is_multicast()) else {
+            continue;
+        };
+        let key = (group, udp.dest_port, source);
+        let stream = streams.entry(key).or_insert_with(|| {
+            order.push(key);
+            StreamState {
+                packets: 0,
+                bytes: 0,
+                first_seen: time,
+                last_seen: time,
+                rtp: false,
+                continuity: ContinuityTracker::default(),
+            }
+        });
+        stream.packets += 1;
+        stream.bytes += u64::from(packet.header.orig_len);
+        stream.last_seen = time;
+        if let Some((ts, rtp)) = ts_payload(&udp.payload) {
+            stream.rtp |= rtp;
+            for ts_packet in ts.chunks_exact(TS_PACKET_LEN) {
+                stream.continuity.push(ts_packet);
+            }
+        }
+    }
+
+    let streams = order
+        .into_iter()
+        .map(|key| {
+            let (group, port, source) = key;
+            let stream = &streams[&key];
+            let group = group.to_string();
+            let duration = stream.last_seen - stream.first_seen;
+            let events = membership.iter().filter(|event| event.group == group);
+            let join = events
+                .clone()
+                .filter(|event| event.change == MembershipChange::Join)
+                .map(|event| event.time)
+                .find(|&time| time <= stream.first_seen);
+            let leave = events
+                .filter(|event| event.change == MembershipChange::Leave)
+                .map(|event| event.time)
+                .rfind(|&time| time <= stream.last_seen);
+            MulticastStream {
+                port,
+                source: source.to_string(),
+                packets: stream.packets,
+                bytes: stream.bytes,
+                first_seen: stream.first_seen,
+                last_seen: stream.last_seen,
+                bitrate: if duration > 0.0 {
+                    stream.bytes as f64 * 8.0 / duration
+                } else {
+                    0.0
+                },
+                mpeg_ts: stream.continuity.packets > 0,
+                rtp: stream.rtp,
+                ts_packets: stream.continuity.packets,
+                pids: stream.continuity.counters.len(),
+                continuity_errors: stream.continuity.errors,
+                join_latency: join.map(|time| stream.first_seen - time),
+                leave_latency: leave.map(|time| stream.last_seen - time),
+                group,
+            }
+        })
+        .collect();
+    Ok(MulticastReport {
+        streams,
+        membership,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cap::{PcapPacket, PcapPacketHeader};
+    use crate::timefmt::Timestamp;
+
+    fn ipv4_frame(time: f64, protocol: u8, dest: [u8; 4], payload: &[u8]) -> PcapPacket {
+        let mut data = vec![
+            0x01, 0x00, 0x5e, 0x01, 0x01, 0x01, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0x08, 0x00,
+            0x45, 0x00, 0x00, 0x00, 0x00, 0x01, 0x40, 0x00, 0x40, protocol, 0x00, 0x00, 10, 0, 0,
+            1,
+        ];
+        data.extend_from_slice(&dest);
+        data[16..18].copy_from_slice(&(20 + payload.len() as u16).to_be_bytes());
+        data.extend_from_slice(payload);
+        PcapPacket {
+            header: PcapPacketHeader {
+                timestamp: Timestamp::from_micros(time as u32, (time.fract() * 1e6).round() as u32),
+                incl_len: data.len() as u32,
+                orig_len: data.len() as u32,
+                link_type: None,
+            },
+            data,
+        }
+    }
+
+    /// A UDP datagram to port 5000 with two TS packets of PID 256.
+    fn ts_datagram(time: f64, counters: [u8; 2]) -> PcapPacket {
+        let mut payload = vec![0x13, 0x88, 0x13, 0x88, 0x00, 0x00, 0x00, 0x00];
+        for counter in counters {
+            let mut ts = vec![TS_SYNC_BYTE, 0x01, 0x00, 0x10 | counter];
+            ts.resize(TS_PACKET_LEN, 0xff);
+            payload.extend(ts);
+        }
+        let length = payload.len() as u16;
+        payload[4..6].copy_from_slice(&length.to_be_bytes());
+        ipv4_frame(time, 17, [239, 1, 1, 1], &payload)
+    }
+
+    #[test]
+    fn test_membership_changes() {
+        let v2_leave = [IGMP_V2_LEAVE, 0, 0, 0, 239, 1, 1, 1];
+        assert_eq!(
+            membership_changes(&v2_leave),
+            [(Ipv4Addr::new(239, 1, 1, 1), MembershipChange::Leave)]
+        );
+        // IGMPv3 report: CHANGE_TO_EXCLUDE {} for 239.1.1.1, ALLOW for 239.2.2.2.
+        let v3 = [
+            IGMP_V3_REPORT,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            2,
+            4,
+            0,
+            0,
+            0,
+            239,
+            1,
+            1,
+            1,
+            5,
+            0,
+            0,
+            1,
+            239,
+            2,
+            2,
+            2,
+            10,
+            0,
+            0,
+            9,
+        ];
+        assert_eq!(
+            membership_changes(&v3),
+            [(Ipv4Addr::new(239, 1, 1, 1), MembershipChange::Join)]
+        );
+    }
+
+    #[test]
+    fn test_multicast_report() {
+        let join = [IGMP_V2_REPORT, 0, 0, 0, 239, 1, 1, 1];
+        let leave = [IGMP_V2_LEAVE, 0, 0, 0, 239, 1, 1, 1];
+        let capture = Arc::new(LoadedCapture::from_packets(1, "iptv.pcap", 1, &[
+                ipv4_frame(10.0, IP_PROTOCOL_IGMP, [239, 1, 1, 1], &join),
+                ts_datagram(10.25, [0, 1]),
+                // Counter 3 is missing.
+                ts_datagram(11.25, [2, 4]),
+                ipv4_frame(11.5, IP_PROTOCOL_IGMP, [224, 0, 0, 2], &leave),
+                ts_datagram(12.25, [5, 6]),
+            ]));
+        let report = multicast_report(&[capture]).unwrap();
+        assert_eq!(report.membership.len(), 2);
+        assert_eq!(report.membership[0].host, "10.0.0.1");
+        assert_eq!(report.streams.len(), 1);
+        let stream = &report.streams[0];
+        assert_eq!(stream.group, "239.1.1.1");
+        assert_eq!(stream.port, 5000);
+        assert!(stream.mpeg_ts);
+        assert!(!stream.rtp);
+        assert_eq!(stream.ts_packets, 6);
+        assert_eq!(stream.pids, 1);
+        assert_eq!(stream.continuity_errors, 1);
+        assert_eq!(stream.join_latency, Some(0.25));
+        assert_eq!(stream.leave_latency, Some(0.75));
+        assert_eq!(stream.bitrate, stream.bytes as f64 * 8.0 / 2.0);
+    }
+
+    #[test]
+    fn test_rtp_ts_payload() {
+        let mut payload = vec![0x80, RTP_PAYLOAD_MP2T, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1];
+        let mut ts = vec![TS_SYNC_BYTE];
+        ts.resize(TS_PACKET_LEN, 0);
+        payload.extend_from_slice(&ts);
+        assert_eq!(ts_payload(&payload), Some((ts.as_slice(), true)));
+        assert_eq!(ts_payload(&payload[..100]), None);
+    }
+}
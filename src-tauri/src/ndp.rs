@@ -0,0 +1,561 @@
+use std::collections::HashMap;
+use std::net::Ipv6Addr;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc;
+
+use crate::dissect::PacketLayers;
+use crate::error::KcpdumpError;
+use crate::packet::MacAddress;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::packetlist;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::session::LoadedCapture;
+
+/// IPv6 next header value of ICMPv6.
+pub const ICMPV6: u8 = 58;
+
+const ROUTER_SOLICITATION: u8 = 133;
+const ROUTER_ADVERTISEMENT: u8 = 134;
+const NEIGHBOR_SOLICITATION: u8 = 135;
+const NEIGHBOR_ADVERTISEMENT: u8 = 136;
+
+/// NDP Message
+/// An ICMPv6 Neighbor Discovery message (RFC 4861) with the options used to
+/// learn link-layer addresses and prefixes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NdpMessage {
+    RouterSolicitation {
+        source_mac: Option<MacAddress>,
+    },
+    RouterAdvertisement {
+        hop_limit: u8,
+        managed: bool,
+        other: bool,
+        /// Seconds; 0 means the sender is not a default router.
+        lifetime: u16,
+        source_mac: Option<MacAddress>,
+        prefixes: Vec<(Ipv6Addr, u8)>,
+    },
+    NeighborSolicitation {
+        target: Ipv6Addr,
+        source_mac: Option<MacAddress>,
+    },
+    NeighborAdvertisement {
+        target: Ipv6Addr,
+        router: bool,
+        solicited: bool,
+        override_flag: bool,
+        target_mac: Option<MacAddress>,
+    },
+}
+
+impl TryFrom<&[u8]> for NdpMessage {
+    type Error = KcpdumpError;
+
+    /// Parses an ICMPv6 message, failing for types other than NDP.
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 8 {
+            return Err(KcpdumpError::malformed(
+                0,
+                "Data too short for ICMPv6 message",
+            ));
+        }
+        let options_offset = match data[0] {
+            ROUTER_SOLICITATION => 8,
+            ROUTER_ADVERTISEMENT => 16,
+            NEIGHBOR_SOLICITATION | NEIGHBOR_ADVERTISEMENT => 24,
+            _ => {
+                return Err(KcpdumpError::malformed(
+                    0,
+                    "Not a neighbor discovery message",
+                ));
+            }
+        };
+        if data.len() < options_offset {
+            return Err(KcpdumpError::malformed(
+                data.len(),
+                "Neighbor discovery message truncated",
+            ));
+        }
+
+        let mut source_mac = None;
+        let mut target_mac = None;
+        let mut prefixes = Vec::new();
+        let mut offset = options_offset;
+        while offset + 2 <= data.len() {
+            let length = data[offset + 1] as usize * 8;
+            if length == 0 {
+                return Err(KcpdumpError::malformed(
+                    offset + 1,
+                    "Zero length NDP option",
+                ));
+            }
+            let option = data
+                .get(offset..offset + length)
+                .ok_or(KcpdumpError::malformed(offset, "NDP option truncated"))?;
+            match (option[0], length) {
+                (1, 8) => source_mac = Some(mac(&option[2..8])),
+                (2, 8) => target_mac = Some(mac(&option[2..8])),
+                (3, 32) => prefixes.push((address(&option[16..32]), option[2])),
+                _ => {}
+            }
+            offset += length;
+        }
+
+        Ok(match data[0] {
+            ROUTER_SOLICITATION => NdpMessage::RouterSolicitation { source_mac },
+            ROUTER_ADVERTISEMENT => NdpMessage::RouterAdvertisement {
+                hop_limit: data[4],
+                managed: data[5] & 0x80 != 0,
+                other: data[5] & 0x40 != 0,
+                lifetime: u16::from_be_bytes([data[6], data[7]]),
+                source_mac,
+                prefixes,
+            },
+            NEIGHBOR_SOLICITATION => NdpMessage::NeighborSolicitation {
+                target: address(&data[8..24]),
+                source_mac,
+            },
+            _ => NdpMessage::NeighborAdvertisement {
+                target: address(&data[8..24]),
+                router: data[4] & 0x80 != 0,
+                solicited: data[4] & 0x40 != 0,
+                override_flag: data[4] & 0x20 != 0,
+                target_mac,
+            },
+        })
+    }
+}
+
+fn mac(data: &[u8]) -> MacAddress {
+    MacAddress([data[0], data[1], data[2], data[3], data[4], data[5]])
+}
+
+fn address(data: &[u8]) -> Ipv6Addr {
+    let mut octets = [0u8; 16];
+    octets.copy_from_slice(data);
+    Ipv6Addr::from(octets)
+}
+
+/// Neighbor Entry
+/// One IPv6 to MAC binding learned from neighbor discovery.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NeighborEntry {
+    pub ip: Ipv6Addr,
+    pub mac: String,
+    /// Set once the neighbor announced itself as a router.
+    pub is_router: bool,
+    pub first_number: usize,
+    pub last_number: usize,
+    /// Seconds since the epoch.
+    pub first_seen: f64,
+    pub last_seen: f64,
+    /// Neighbor discovery messages that confirmed the binding.
+    pub messages: usize,
+}
+
+/// Router Entry
+/// A router learned from its Router Advertisements.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RouterEntry {
+    pub ip: Ipv6Addr,
+    pub mac: String,
+    pub hop_limit: u8,
+    pub managed: bool,
+    pub other: bool,
+    /// Router lifetime in seconds from the last advertisement.
+    pub lifetime: u16,
+    /// Advertised prefixes, e.g. `2001:db8::/64`.
+    pub prefixes: Vec<String>,
+    pub first_seen: f64,
+    pub last_seen: f64,
+    pub advertisements: usize,
+}
+
+/// Address Conflict
+/// An IPv6 address claimed by more than one MAC address, either through
+/// bindings or by answering a duplicate address detection probe.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressConflict {
+    pub ip: Ipv6Addr,
+    /// MAC addresses in order of appearance.
+    pub macs: Vec<String>,
+    /// Frame number where the conflict was first detected.
+    pub number: usize,
+    pub time: f64,
+    /// Whether it was detected by a reply to a duplicate address detection probe.
+    pub duplicate_address_detection: bool,
+}
+
+/// Neighbor Table
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NeighborTable {
+    pub neighbors: Vec<NeighborEntry>,
+    pub routers: Vec<RouterEntry>,
+    pub conflicts: Vec<AddressConflict>,
+}
+
+/// Neighbor Table Builder
+/// Reconstructs the IPv6 neighbor and router tables from NDP traffic fed in time order.
+#[derive(Debug, Default)]
+pub struct NeighborTableBuilder {
+    table: NeighborTable,
+    bindings: HashMap<(Ipv6Addr, String), usize>,
+    routers: HashMap<Ipv6Addr, usize>,
+    conflicts: HashMap<Ipv6Addr, usize>,
+    /// Tentative addresses probed by duplicate address detection, with the prober's MAC.
+    probes: HashMap<Ipv6Addr, String>,
+}
+
+impl NeighborTableBuilder {
+    pub fn push(&mut self, layers: &PacketLayers) {
+        let (Some(eth), Some(ip)) = (&layers.ethernet, &layers.ipv6) else {
+            return;
+        };
+        if ip.next_header != ICMPV6 {
+            return;
+        }
+        let Ok(message) = NdpMessage::try_from(ip.payload.as_slice()) else {
+            return;
+        };
+        let header = &layers.packet.header;
+        let time = header.timestamp.as_secs_f64();
+        let sender = eth.header.src_mac;
+
+        match message {
+            NdpMessage::RouterSolicitation { source_mac } => {
+                if !ip.source_ip.is_unspecified() {
+                    let mac = source_mac.unwrap_or(sender);
+                    self.bind(ip.source_ip, mac, false, layers.number, time, false);
+                }
+            }
+            NdpMessage::RouterAdvertisement {
+                hop_limit,
+                managed,
+                other,
+                lifetime,
+                source_mac,
+                prefixes,
+            } => {
+                let mac = source_mac.unwrap_or(sender);
+                self.bind(ip.source_ip, mac, true, layers.number, time, false);
+                let prefixes: Vec<_> = prefixes
+                    .iter()
+                    .map(|(prefix, length)| format!("{}/{}", prefix, length))
+                    .collect();
+                match self.routers.get(&ip.source_ip) {
+                    Some(&index) => {
+                        let router = &mut self.table.routers[index];
+                        router.mac = mac.to_string();
+                        router.hop_limit = hop_limit;
+                        router.managed = managed;
+                        router.other = other;
+                        router.lifetime = lifetime;
+                        for prefix in prefixes {
+                            if !router.prefixes.contains(&prefix) {
+                                router.prefixes.push(prefix);
+                            }
+                        }
+                        router.last_seen = time;
+                        router.advertisements += 1;
+                    }
+                    None => {
+                        self.routers.insert(ip.source_ip, self.table.routers.len());
+                        self.table.routers.push(RouterEntry {
+                            ip: ip.source_ip,
+                            mac: mac.to_string(),
+                            hop_limit,
+                            managed,
+                            other,
+                            lifetime,
+                            prefixes,
+                            first_seen: time,
+                            last_seen: time,
+                            advertisements: 1,
+                        });
+                    }
+                }
+            }
+            NdpMessage::NeighborSolicitation { target, source_mac } => {
+                if ip.source_ip.is_unspecified() {
+                    self.probes.insert(target, sender.to_string());
+                } else {
+                    let mac = source_mac.unwrap_or(sender);
+                    self.bind(ip.source_ip, mac, false, layers.number, time, false);
+                }
+            }
+            NdpMessage::NeighborAdvertisement {
+                target,
+                router,
+                target_mac,
+                ..
+            } => {
+                let mac = target_mac.unwrap_or(sender);
+                let answers_probe = self
+                    .probes
+                    .get(&target)
+                    .is_some_and(|prober| *prober != mac.to_string());
+                if answers_probe && let Some(prober) = self.probes.remove(&target) {
+                    self.conflict(target, &prober, layers.number, time, true);
+                }
+                self.bind(target, mac, router, layers.number, time, answers_probe);
+            }
+        }
+    }
+
+    /// Records a binding, reporting a conflict if the address is already bound
+    /// to another MAC address.
+    fn bind(
+        &mut self,
+        ip: Ipv6Addr,
+        mac: MacAddress,
+        router: bool,
+        number: usize,
+        time: f64,
+        duplicate_address_detection: bool,
+    ) {
+        let mac = mac.to_string();
+        if let Some(&index) = self.bindings.get(&(ip, mac.clone())) {
+            let entry = &mut self.table.neighbors[index];
+            entry.is_router |= router;
+            entry.last_number = number;
+            entry.last_seen = time;
+            entry.messages += 1;
+            return;
+        }
+        let other = self
+            .table
+            .neighbors
+            .iter()
+            .find(|entry| entry.ip == ip && entry.mac != mac)
+            .map(|entry| entry.mac.clone());
+        if let Some(other) = other {
+            self.conflict(ip, &other, number, time, duplicate_address_detection);
+        }
+        if self.conflicts.contains_key(&ip) {
+            self.conflict(ip, &mac, number, time, duplicate_address_detection);
+        }
+        self.bindings
+            .insert((ip, mac.clone()), self.table.neighbors.len());
+        self.table.neighbors.push(NeighborEntry {
+            ip,
+            mac,
+            is_router: router,
+            first_number: number,
+            last_number: number,
+            first_seen: time,
+            last_seen: time,
+            messages: 1,
+        });
+    }
+
+    /// Adds `mac` to the conflict for `ip`, creating it if needed.
+    fn conflict(
+        &mut self,
+        ip: Ipv6Addr,
+        mac: &str,
+        number: usize,
+        time: f64,
+        duplicate_address_detection: bool,
+    ) {
+        let index = *self.conflicts.entry(ip).or_insert_with(|| {
+            self.table.conflicts.push(AddressConflict {
+                ip,
+                macs: Vec::new(),
+                number,
+                time,
+                duplicate_address_detection,
+            });
+            self.table.conflicts.len() - 1
+        });
+        let conflict = &mut self.table.conflicts[index];
+        if !conflict.macs.iter().any(|known| known == mac) {
+            conflict.macs.push(mac.to_string());
+        }
+    }
+
+    pub fn finish(self) -> NeighborTable {
+        self.table
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Reconstructs the neighbor table of the given captures, interleaved by time.
+pub fn neighbor_table(captures: &[Arc<LoadedCapture>]) -> Result<NeighborTable, KcpdumpError> {
+    let mut builder = NeighborTableBuilder::default();
+    let link_types = packetlist::link_types(captures);
+    for item in packetlist::merged_packets(captures) {
+        let (capture_id, number, packet) = item?;
+        builder.push(&PacketLayers::decode_link(number, link_types[&capture_id], &packet));
+    }
+    Ok(builder.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timefmt::Timestamp;
+    use crate::cap::{PcapPacket, PcapPacketHeader};
+
+    const HOST_A: [u8; 6] = [0x02, 0, 0, 0, 0, 0x0a];
+    const HOST_B: [u8; 6] = [0x02, 0, 0, 0, 0, 0x0b];
+    const ROUTER: [u8; 6] = [0x02, 0, 0, 0, 0, 0x01];
+
+    fn ip(text: &str) -> Ipv6Addr {
+        text.parse().unwrap()
+    }
+
+    fn ndp_frame(mac: [u8; 6], source: Ipv6Addr, ts_sec: u32, icmp: &[u8]) -> PcapPacket {
+        let mut data = vec![0x33, 0x33, 0, 0, 0, 1];
+        data.extend_from_slice(&mac);
+        data.extend_from_slice(&[0x86, 0xdd, 0x60, 0, 0, 0]);
+        data.extend_from_slice(&(icmp.len() as u16).to_be_bytes());
+        data.extend_from_slice(&[ICMPV6, 255]);
+        data.extend_from_slice(&source.octets());
+        data.extend_from_slice(&ip("ff02::1").octets());
+        data.extend_from_slice(icmp);
+        PcapPacket {
+            header: PcapPacketHeader {
+                timestamp: Timestamp::new(ts_sec, 0),
+                incl_len: data.len() as u32,
+                orig_len: data.len() as u32,
+                link_type: None,
+            },
+            data,
+        }
+    }
+
+    fn neighbor_message(
+        kind: u8,
+        flags: u8,
+        target: Ipv6Addr,
+        option: Option<(u8, [u8; 6])>,
+    ) -> Vec<u8> {
+        let mut data = vec![kind, 0, 0, 0, flags, 0, 0, 0];
+        data.extend_from_slice(&target.octets());
+        if let Some((code, mac)) = option {
+            data.extend_from_slice(&[code, 1]);
+            data.extend_from_slice(&mac);
+        }
+        data
+    }
+
+    fn router_advertisement() -> Vec<u8> {
+        let mut data = vec![ROUTER_ADVERTISEMENT, 0, 0, 0, 64, 0x40, 0x07, 0x08];
+        data.extend_from_slice(&[0; 8]);
+        data.extend_from_slice(&[1, 1]);
+        data.extend_from_slice(&ROUTER);
+        data.extend_from_slice(&[3, 4, 64, 0xc0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        data.extend_from_slice(&ip("2001:db8:1::").octets());
+        data
+    }
+
+    fn build(packets: &[PcapPacket]) -> NeighborTable {
+        let mut builder = NeighborTableBuilder::default();
+        for (index, packet) in packets.iter().enumerate() {
+            builder.push(&PacketLayers::decode(index + 1, packet));
+        }
+        builder.finish()
+    }
+
+    #[test]
+    fn test_router_and_neighbors() {
+        let packets = [
+            ndp_frame(ROUTER, ip("fe80::1"), 10, &router_advertisement()),
+            ndp_frame(
+                HOST_A,
+                ip("fe80::a"),
+                11,
+                &neighbor_message(NEIGHBOR_SOLICITATION, 0, ip("fe80::1"), Some((1, HOST_A))),
+            ),
+            ndp_frame(
+                ROUTER,
+                ip("fe80::1"),
+                12,
+                &neighbor_message(
+                    NEIGHBOR_ADVERTISEMENT,
+                    0xe0,
+                    ip("fe80::1"),
+                    Some((2, ROUTER)),
+                ),
+            ),
+            ndp_frame(ROUTER, ip("fe80::1"), 20, &router_advertisement()),
+        ];
+        let table = build(&packets);
+        assert!(table.conflicts.is_empty());
+        assert_eq!(table.neighbors.len(), 2);
+        let router = &table.neighbors[0];
+        assert_eq!(router.ip, ip("fe80::1"));
+        assert_eq!(router.mac, "02:00:00:00:00:01");
+        assert!(router.is_router);
+        assert_eq!(router.messages, 3);
+        assert_eq!(router.last_seen, 20.0);
+        assert_eq!(table.neighbors[1].ip, ip("fe80::a"));
+
+        assert_eq!(table.routers.len(), 1);
+        let entry = &table.routers[0];
+        assert_eq!(entry.hop_limit, 64);
+        assert!(entry.other && !entry.managed);
+        assert_eq!(entry.lifetime, 1800);
+        assert_eq!(entry.prefixes, vec!["2001:db8:1::/64"]);
+        assert_eq!(entry.advertisements, 2);
+    }
+
+    #[test]
+    fn test_duplicate_address_detection_conflict() {
+        let tentative = ip("fe80::abcd");
+        let packets = [
+            ndp_frame(
+                HOST_A,
+                ip("::"),
+                10,
+                &neighbor_message(NEIGHBOR_SOLICITATION, 0, tentative, None),
+            ),
+            ndp_frame(
+                HOST_B,
+                tentative,
+                11,
+                &neighbor_message(NEIGHBOR_ADVERTISEMENT, 0x20, tentative, Some((2, HOST_B))),
+            ),
+        ];
+        let table = build(&packets);
+        assert_eq!(table.conflicts.len(), 1);
+        let conflict = &table.conflicts[0];
+        assert_eq!(conflict.ip, tentative);
+        assert!(conflict.duplicate_address_detection);
+        assert_eq!(conflict.number, 2);
+        assert_eq!(
+            conflict.macs,
+            vec!["02:00:00:00:00:0A", "02:00:00:00:00:0B"]
+        );
+    }
+
+    #[test]
+    fn test_binding_conflict() {
+        let shared = ip("2001:db8:1::5");
+        let packets = [
+            ndp_frame(
+                HOST_A,
+                shared,
+                10,
+                &neighbor_message(NEIGHBOR_ADVERTISEMENT, 0x20, shared, Some((2, HOST_A))),
+            ),
+            ndp_frame(
+                HOST_B,
+                shared,
+                11,
+                &neighbor_message(NEIGHBOR_ADVERTISEMENT, 0x20, shared, Some((2, HOST_B))),
+            ),
+        ];
+        let table = build(&packets);
+        assert_eq!(table.neighbors.len(), 2);
+        assert_eq!(table.conflicts.len(), 1);
+        assert!(!table.conflicts[0].duplicate_address_detection);
+        assert_eq!(table.conflicts[0].macs.len(), 2);
+        assert!(NdpMessage::try_from(&[128u8, 0, 0, 0, 0, 0, 0, 0][..]).is_err());
+    }
+}
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+
+use crate::error::KcpdumpError;
+use crate::packet::MacAddress;
+
+/// Organizationally unique identifiers of vendors common on local networks,
+/// sorted by prefix. This is a small excerpt of the IEEE registry.
+const VENDORS: &[([u8; 3], &str)] = &[
+    ([0x00, 0x00, 0x0c], "Cisco"),
+    ([0x00, 0x03, 0x93], "Apple"),
+    ([0x00, 0x04, 0x4b], "NVIDIA"),
+    ([0x00, 0x05, 0x69], "VMware"),
+    ([0x00, 0x08, 0x9b], "QNAP"),
+    ([0x00, 0x09, 0x0f], "Fortinet"),
+    ([0x00, 0x0a, 0x95], "Apple"),
+    ([0x00, 0x0c, 0x29], "VMware"),
+    ([0x00, 0x0c, 0x42], "MikroTik"),
+    ([0x00, 0x0d, 0xb9], "PC Engines"),
+    ([0x00, 0x11, 0x32], "Synology"),
+    ([0x00, 0x14, 0x22], "Dell"),
+    ([0x00, 0x15, 0x5d], "Microsoft Hyper-V"),
+    ([0x00, 0x16, 0x3e], "Xen"),
+    ([0x00, 0x17, 0x88], "Philips Lighting"),
+    ([0x00, 0x17, 0xf2], "Apple"),
+    ([0x00, 0x1a, 0x11], "Google"),
+    ([0x00, 0x1b, 0x17], "Palo Alto Networks"),
+    ([0x00, 0x1b, 0x21], "Intel"),
+    ([0x00, 0x1b, 0x63], "Apple"),
+    ([0x00, 0x1c, 0x14], "VMware"),
+    ([0x00, 0x1f, 0xf3], "Apple"),
+    ([0x00, 0x25, 0x90], "Supermicro"),
+    ([0x00, 0x50, 0x56], "VMware"),
+    ([0x00, 0x50, 0xf2], "Microsoft"),
+    ([0x00, 0xe0, 0x4c], "Realtek"),
+    ([0x08, 0x00, 0x27], "VirtualBox"),
+    ([0x18, 0xb4, 0x30], "Nest Labs"),
+    ([0x24, 0x0a, 0xc4], "Espressif"),
+    ([0x28, 0xcd, 0xc1], "Raspberry Pi"),
+    ([0x30, 0xae, 0xa4], "Espressif"),
+    ([0x3c, 0x5a, 0xb4], "Google"),
+    ([0x52, 0x54, 0x00], "QEMU/KVM"),
+    ([0xb8, 0x27, 0xeb], "Raspberry Pi"),
+    ([0xdc, 0xa6, 0x32], "Raspberry Pi"),
+    ([0xe4, 0x5f, 0x01], "Raspberry Pi"),
+    ([0xf4, 0xf5, 0xd8], "Google"),
+];
+
+/// Returns the vendor of a MAC address. Unknown addresses with the locally
+/// administered bit set, such as randomized client addresses, are reported
+/// as such.
+pub fn vendor(mac: &MacAddress) -> Option<&'static str> {
+    let prefix = [mac.0[0], mac.0[1], mac.0[2]];
+    match VENDORS.binary_search_by_key(&prefix, |(oui, _)| *oui) {
+        Ok(index) => Some(VENDORS[index].1),
+        Err(_) if mac.0[0] & 0x02 != 0 => Some("Locally administered"),
+        Err(_) => None,
+    }
+}
+
+/// OUI Database
+/// Vendors loaded from a CSV file, such as the IEEE `oui.csv` registry
+/// export, that take precedence over the built-in table.
+#[derive(Debug, Clone, Default)]
+pub struct OuiDatabase {
+    path: String,
+    vendors: HashMap<[u8; 3], String>,
+}
+
+impl OuiDatabase {
+    pub fn open(path: &str) -> Result<Self, KcpdumpError> {
+        let text = std::fs::read_to_string(path).map_err(|e| KcpdumpError::file(path, e))?;
+        let mut database = OuiDatabase::parse(&text)?;
+        database.path = path.to_string();
+        Ok(database)
+    }
+
+    /// Parses CSV rows of either the IEEE export (`Registry,Assignment,
+    /// Organization Name,...`) or plain `prefix,vendor` pairs. Prefixes are
+    /// six hex digits, optionally separated by `:` or `-`. Rows without a
+    /// prefix, such as the header, are skipped.
+    pub fn parse(text: &str) -> Result<Self, KcpdumpError> {
+        let mut vendors = HashMap::new();
+        for line in text.lines() {
+            let fields = csv_fields(line);
+            let entry = match fields.as_slice() {
+                [prefix, vendor, ..] if parse_prefix(prefix).is_some() => (prefix, vendor),
+                [_, prefix, vendor, ..] => (prefix, vendor),
+                _ => continue,
+            };
+            if let (Some(prefix), vendor) = (parse_prefix(entry.0), entry.1.trim())
+                && !vendor.is_empty()
+            {
+                vendors.insert(prefix, vendor.to_string());
+            }
+        }
+        if vendors.is_empty() {
+            return Err(KcpdumpError::InvalidInput(
+                "No OUI entries found".to_string(),
+            ));
+        }
+        Ok(OuiDatabase {
+            path: String::new(),
+            vendors,
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn len(&self) -> usize {
+        self.vendors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vendors.is_empty()
+    }
+
+    /// Returns the vendor of a MAC address from this database, or else as
+    /// `vendor` does.
+    pub fn vendor(&self, mac: &MacAddress) -> Option<&str> {
+        self.vendors
+            .get(&[mac.0[0], mac.0[1], mac.0[2]])
+            .map(String::as_str)
+            .or_else(|| vendor(mac))
+    }
+}
+
+/// Splits a CSV line into fields, unquoting quoted fields.
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+fn parse_prefix(text: &str) -> Option<[u8; 3]> {
+    let digits: String = text
+        .trim()
+        .chars()
+        .filter(|c| !matches!(c, ':' | '-'))
+        .collect();
+    if digits.len() != 6 {
+        return None;
+    }
+    let value = u32::from_str_radix(&digits, 16).ok()?;
+    let [_, a, b, c] = value.to_be_bytes();
+    Some([a, b, c])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vendor() {
+        assert!(VENDORS.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(
+            vendor(&MacAddress([0xb8, 0x27, 0xeb, 0x01, 0x02, 0x03])),
+            Some("Raspberry Pi")
+        );
+        assert_eq!(
+            MacAddress([0x52, 0x54, 0x00, 0x12, 0x34, 0x56]).vendor(),
+            Some("QEMU/KVM")
+        );
+        assert_eq!(
+            vendor(&MacAddress([0xda, 0x11, 0x22, 0x33, 0x44, 0x55])),
+            Some("Locally administered")
+        );
+        assert_eq!(
+            vendor(&MacAddress([0x00, 0x11, 0x22, 0x33, 0x44, 0x55])),
+            None
+        );
+    }
+
+    #[test]
+    fn test_database() {
+        let csv = "Registry,Assignment,Organization Name,Organization Address\n\
+                   MA-L,DCA904,\"Apple, Inc.\",\"1 Infinite Loop Cupertino CA US 95014\"\n\
+                   MA-L,B827EB,Raspberry Pi Foundation,Cambridge GB\n\
+                   00:11:22,\"Example \"\"Labs\"\"\"\n";
+        let database = OuiDatabase::parse(csv).unwrap();
+        assert_eq!(database.len(), 3);
+        assert_eq!(
+            database.vendor(&MacAddress([0xdc, 0xa9, 0x04, 0x01, 0x02, 0x03])),
+            Some("Apple, Inc.")
+        );
+        // Loaded entries override the built-in table, which still serves the rest.
+        assert_eq!(
+            database.vendor(&MacAddress([0xb8, 0x27, 0xeb, 0x01, 0x02, 0x03])),
+            Some("Raspberry Pi Foundation")
+        );
+        assert_eq!(
+            database.vendor(&MacAddress([0x00, 0x11, 0x22, 0x33, 0x44, 0x55])),
+            Some("Example \"Labs\"")
+        );
+        assert_eq!(
+            database.vendor(&MacAddress([0x52, 0x54, 0x00, 0x12, 0x34, 0x56])),
+            Some("QEMU/KVM")
+        );
+        assert!(OuiDatabase::parse("Registry,Assignment\n").is_err());
+    }
+}
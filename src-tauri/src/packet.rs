@@ -1,5 +1,9 @@
 use core::fmt;
 use std::hash::Hash;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::dissect::{Dissector, FieldInfo, FieldType, FieldValue, FieldValues, PacketLayers};
+use crate::error::KcpdumpError;
 
 /// Mac Address
 /// Represents a MAC address in a human-readable format.
@@ -8,15 +12,22 @@ use std::hash::Hash;
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct MacAddress(pub [u8; 6]);
 
+impl MacAddress {
+    /// Vendor of the address from the built-in OUI table; see `oui::vendor`.
+    pub fn vendor(&self) -> Option<&'static str> {
+        crate::oui::vendor(self)
+    }
+}
+
 impl From<[u8; 6]> for MacAddress {
     fn from(bytes: [u8; 6]) -> Self {
         MacAddress(bytes)
     }
 }
 
-impl Into<[u8; 6]> for MacAddress {
-    fn into(self) -> [u8; 6] {
-        self.0
+impl From<MacAddress> for [u8; 6] {
+    fn from(mac: MacAddress) -> Self {
+        mac.0
     }
 }
 
@@ -52,9 +63,9 @@ impl From<u16> for EtherType {
     }
 }
 
-impl Into<u16> for EtherType {
-    fn into(self) -> u16 {
-        match self {
+impl From<EtherType> for u16 {
+    fn from(ether_type: EtherType) -> Self {
+        match ether_type {
             EtherType::IPv4 => 0x0800,
             EtherType::ARP => 0x0806,
             EtherType::IPv6 => 0x86DD,
@@ -63,6 +74,24 @@ impl Into<u16> for EtherType {
     }
 }
 
+/// Tag protocol identifiers of 802.1Q customer tags, 802.1ad service tags
+/// and the pre-standard QinQ outer tag.
+pub const TPID_8021Q: u16 = 0x8100;
+pub const TPID_8021AD: u16 = 0x88a8;
+pub const TPID_QINQ: u16 = 0x9100;
+
+/// VLAN Tag
+/// An 802.1Q tag: priority code point, drop eligible indicator and VLAN ID.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct VlanTag {
+    /// EtherType value that introduced the tag, e.g. 0x8100.
+    pub tpid: u16,
+    pub pcp: u8,
+    pub dei: bool,
+    pub vid: u16,
+}
+
 /// Ethernet header
 /// contains the source and destination MAC addresses, as well as the EtherType.
 #[repr(C)]
@@ -70,7 +99,22 @@ impl Into<u16> for EtherType {
 pub struct EthernetHeader {
     pub dest_mac: MacAddress,
     pub src_mac: MacAddress,
+    /// EtherType of the payload, after any VLAN tags.
     pub ether_type: EtherType,
+    /// VLAN tags from the outermost in; empty for untagged frames.
+    pub vlan_tags: Vec<VlanTag>,
+}
+
+impl EthernetHeader {
+    /// Length of the header on the wire, tags included.
+    pub fn size(&self) -> usize {
+        14 + 4 * self.vlan_tags.len()
+    }
+
+    /// IDs of the VLAN tags, outermost first.
+    pub fn vlan_ids(&self) -> Vec<u16> {
+        self.vlan_tags.iter().map(|tag| tag.vid).collect()
+    }
 }
 
 /// Ethernet Packet
@@ -83,33 +127,133 @@ pub struct EthernetPacket {
 }
 
 impl TryFrom<&[u8]> for EthernetPacket {
-    type Error = &'static str;
+    type Error = KcpdumpError;
 
     fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
         if data.len() < 14 {
-            return Err("Data too short for Ethernet packet");
+            return Err(KcpdumpError::malformed(0, "Data too short for Ethernet packet"));
         }
 
         let dest_mac = MacAddress([data[0], data[1], data[2], data[3], data[4], data[5]]);
         let src_mac = MacAddress([data[6], data[7], data[8], data[9], data[10], data[11]]);
-        let ether_type = match (data[12], data[13]) {
-            (0x08, 0x00) => EtherType::IPv4,
-            (0x08, 0x06) => EtherType::ARP,
-            (0x86, 0xDD) => EtherType::IPv6,
-            _ => EtherType::Unknown(u16::from(data[12]) << 8 | u16::from(data[13])),
-        };
+        let mut vlan_tags = Vec::new();
+        let mut offset = 12;
+        let mut type_field = u16::from_be_bytes([data[12], data[13]]);
+        while matches!(type_field, TPID_8021Q | TPID_8021AD | TPID_QINQ) {
+            let tag = data
+                .get(offset + 2..offset + 6)
+                .ok_or(KcpdumpError::malformed(offset, "Data too short for VLAN tag"))?;
+            let tci = u16::from_be_bytes([tag[0], tag[1]]);
+            vlan_tags.push(VlanTag {
+                tpid: type_field,
+                pcp: (tci >> 13) as u8,
+                dei: tci & 0x1000 != 0,
+                vid: tci & 0x0fff,
+            });
+            offset += 4;
+            type_field = u16::from_be_bytes([tag[2], tag[3]]);
+        }
 
         Ok(EthernetPacket {
             header: EthernetHeader {
                 dest_mac,
                 src_mac,
-                ether_type,
+                ether_type: EtherType::from(type_field),
+                vlan_tags,
             },
-            data: Vec::from(&data[14..]),
+            data: Vec::from(&data[offset + 2..]),
         })
     }
 }
 
+/// Ethernet Dissector
+/// Registers the Ethernet II fields with the dissector registry.
+pub struct EthernetDissector;
+
+const ETHERNET_FIELDS: &[FieldInfo] = &[
+    FieldInfo::new("eth.dst", FieldType::MacAddress, "Destination MAC address"),
+    FieldInfo::new("eth.src", FieldType::MacAddress, "Source MAC address"),
+    FieldInfo::new("eth.addr", FieldType::MacAddress, "Source or destination MAC address"),
+    FieldInfo::new("eth.type", FieldType::UInt, "EtherType"),
+];
+
+impl Dissector for EthernetDissector {
+    fn protocol(&self) -> &'static str {
+        "eth"
+    }
+
+    fn description(&self) -> &'static str {
+        "Ethernet II"
+    }
+
+    fn fields(&self) -> &'static [FieldInfo] {
+        ETHERNET_FIELDS
+    }
+
+    fn dissect(&self, layers: &PacketLayers, values: &mut FieldValues) {
+        let Some(eth) = &layers.ethernet else {
+            return;
+        };
+        let header = &eth.header;
+        values.push("eth", FieldValue::Protocol);
+        values.push("eth.dst", FieldValue::MacAddress(header.dest_mac));
+        values.push("eth.src", FieldValue::MacAddress(header.src_mac));
+        values.push("eth.addr", FieldValue::MacAddress(header.src_mac));
+        values.push("eth.addr", FieldValue::MacAddress(header.dest_mac));
+        // Like the type field on the wire, the TPID of the outer tag if tagged.
+        let ether_type = header
+            .vlan_tags
+            .first()
+            .map_or(u16::from(header.ether_type), |tag| tag.tpid);
+        values.push("eth.type", FieldValue::UInt(ether_type.into()));
+    }
+}
+
+/// VLAN Dissector
+/// Registers the 802.1Q tag fields; stacked tags each add a value.
+pub struct VlanDissector;
+
+const VLAN_FIELDS: &[FieldInfo] = &[
+    FieldInfo::new("vlan.id", FieldType::UInt, "VLAN identifier"),
+    FieldInfo::new("vlan.priority", FieldType::UInt, "Priority code point"),
+    FieldInfo::new("vlan.dei", FieldType::Bool, "Drop eligible indicator"),
+    FieldInfo::new("vlan.etype", FieldType::UInt, "EtherType following the tag"),
+];
+
+impl Dissector for VlanDissector {
+    fn protocol(&self) -> &'static str {
+        "vlan"
+    }
+
+    fn description(&self) -> &'static str {
+        "802.1Q Virtual LAN"
+    }
+
+    fn fields(&self) -> &'static [FieldInfo] {
+        VLAN_FIELDS
+    }
+
+    fn dissect(&self, layers: &PacketLayers, values: &mut FieldValues) {
+        let Some(eth) = &layers.ethernet else {
+            return;
+        };
+        let tags = &eth.header.vlan_tags;
+        if tags.is_empty() {
+            return;
+        }
+        values.push("vlan", FieldValue::Protocol);
+        for (index, tag) in tags.iter().enumerate() {
+            let next_type = tags
+                .get(index + 1)
+                .map_or(u16::from(eth.header.ether_type), |next| next.tpid);
+            values.push("vlan.id", FieldValue::UInt(tag.vid.into()));
+            values.push("vlan.priority", FieldValue::UInt(tag.pcp.into()));
+            values.push("vlan.dei", FieldValue::Bool(tag.dei));
+            values.push("vlan.etype", FieldValue::UInt(next_type.into()));
+        }
+    }
+}
+
 /// IPv4 Packet
 /// Represents an IPv4 packet with a header and payload.
 #[repr(C)]
@@ -131,11 +275,11 @@ pub struct IPv4Packet {
 }
 
 impl TryFrom<&[u8]> for IPv4Packet {
-    type Error = &'static str;
+    type Error = KcpdumpError;
 
     fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
         if data.len() < 20 {
-            return Err("Data too short for IPv4 packet");
+            return Err(KcpdumpError::malformed(0, "Data too short for IPv4 packet"));
         }
 
         let version_ihl = data[0];
@@ -143,12 +287,15 @@ impl TryFrom<&[u8]> for IPv4Packet {
         let ihl = version_ihl & 0x0F;
 
         if version != 4 {
-            return Err("Not an IPv4 packet");
+            return Err(KcpdumpError::malformed(0, "Not an IPv4 packet"));
         }
 
         let total_length = u16::from_be_bytes([data[2], data[3]]);
         if data.len() < total_length as usize {
-            return Err("Data length mismatch");
+            return Err(KcpdumpError::malformed(2, "Data length mismatch"));
+        }
+        if ihl < 5 || total_length < ihl as u16 * 4 {
+            return Err(KcpdumpError::malformed(0, "Invalid IPv4 header length"));
         }
 
         Ok(IPv4Packet {
@@ -164,7 +311,7 @@ impl TryFrom<&[u8]> for IPv4Packet {
             header_checksum: u16::from_be_bytes([data[10], data[11]]),
             source_ip: [data[12], data[13], data[14], data[15]],
             dest_ip: [data[16], data[17], data[18], data[19]],
-            payload: Vec::from(&data[(ihl as usize * 4)..]),
+            payload: Vec::from(&data[(ihl as usize * 4)..total_length as usize]),
         })
     }
 }
@@ -208,8 +355,661 @@ impl IPv4Packet {
         let checksum = !(sum as u16);
         checksum == self.header_checksum
     }
+
+    /// Validates the checksum of the TCP, UDP or ICMP message the packet
+    /// carries, over the pseudo-header for TCP and UDP. Returns `None` when
+    /// there is nothing to validate: fragments, other protocols, truncated
+    /// headers and UDP datagrams sent without a checksum.
+    pub fn validate_payload_checksum(&self) -> Option<bool> {
+        if self.flags & 0x1 != 0 || self.fragment_offset != 0 {
+            return None;
+        }
+        let source = IpAddr::from(self.source_ip);
+        let destination = IpAddr::from(self.dest_ip);
+        let payload = self.payload.as_slice();
+        match IpProtocol::from(self.protocol) {
+            IpProtocol::TCP if payload.len() >= 20 => Some(transport_checksum_valid(
+                source,
+                destination,
+                self.protocol,
+                payload,
+            )),
+            IpProtocol::UDP if payload.len() >= 8 => (payload[6..8] != [0, 0]).then(|| {
+                transport_checksum_valid(source, destination, self.protocol, payload)
+            }),
+            IpProtocol::ICMP if payload.len() >= 8 => Some(checksum_valid(0, payload)),
+            _ => None,
+        }
+    }
+}
+
+/// Adds the 16-bit big-endian words of `data` to a ones' complement sum,
+/// padding an odd final byte with zero.
+fn ones_complement_sum(mut sum: u32, data: &[u8]) -> u32 {
+    for chunk in data.chunks(2) {
+        let word = u16::from_be_bytes([chunk[0], *chunk.get(1).unwrap_or(&0)]);
+        sum = sum.wrapping_add(u32::from(word));
+    }
+    sum
+}
+
+/// Whether `data`, checksum field included, sums to all ones when added to
+/// the `initial` sum of its pseudo-header.
+fn checksum_valid(initial: u32, data: &[u8]) -> bool {
+    let mut sum = ones_complement_sum(initial, data);
+    while (sum >> 16) != 0 {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    sum == 0xFFFF
+}
+
+/// Validates the checksum of a TCP, UDP or ICMPv6 message over the IPv4 or
+/// IPv6 pseudo-header built from the addresses and protocol of the carrying
+/// packet and the length of the message.
+pub fn transport_checksum_valid(
+    source: IpAddr,
+    destination: IpAddr,
+    protocol: u8,
+    message: &[u8],
+) -> bool {
+    let address_sum = |address: IpAddr| match address {
+        IpAddr::V4(address) => ones_complement_sum(0, &address.octets()),
+        IpAddr::V6(address) => ones_complement_sum(0, &address.octets()),
+    };
+    let length = message.len() as u32;
+    let pseudo_header = address_sum(source)
+        + address_sum(destination)
+        + u32::from(protocol)
+        + (length >> 16)
+        + (length & 0xFFFF);
+    checksum_valid(pseudo_header, message)
+}
+
+/// IPv6 Packet
+/// Represents an IPv6 packet. Hop-by-hop, routing, fragment, authentication
+/// and destination options headers are skipped, so `next_header` names the
+/// upper-layer protocol.
+#[derive(Debug)]
+pub struct IPv6Packet {
+    pub version: u8,
+    pub traffic_class: u8,
+    pub flow_label: u32,
+    pub payload_length: u16,
+    pub next_header: u8,
+    pub hop_limit: u8,
+    pub source_ip: Ipv6Addr,
+    pub dest_ip: Ipv6Addr,
+    /// Next-header values of the extension headers skipped, in order.
+    pub extension_headers: Vec<u8>,
+    pub payload: Vec<u8>,
+}
+
+impl TryFrom<&[u8]> for IPv6Packet {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 40 {
+            return Err(KcpdumpError::malformed(0, "Data too short for IPv6 packet"));
+        }
+        if data[0] >> 4 != 6 {
+            return Err(KcpdumpError::malformed(0, "Not an IPv6 packet"));
+        }
+
+        let payload_length = u16::from_be_bytes([data[4], data[5]]);
+        let end = 40 + payload_length as usize;
+        if data.len() < end {
+            return Err(KcpdumpError::malformed(4, "Data length mismatch"));
+        }
+
+        let mut next_header = data[6];
+        let mut offset = 40;
+        let mut extension_headers = Vec::new();
+        while matches!(next_header, 0 | 43 | 44 | 51 | 60) {
+            let extension = data
+                .get(offset..offset + 2)
+                .filter(|_| offset + 2 <= end)
+                .ok_or(KcpdumpError::malformed(offset, "IPv6 extension header truncated"))?;
+            extension_headers.push(next_header);
+            offset += match next_header {
+                // The fragment header has a fixed size and a reserved length.
+                44 => 8,
+                // The authentication header counts 4-byte units, minus two.
+                51 => (extension[1] as usize + 2) * 4,
+                _ => (extension[1] as usize + 1) * 8,
+            };
+            next_header = extension[0];
+        }
+        if offset > end {
+            return Err(KcpdumpError::malformed(end, "IPv6 extension header truncated"));
+        }
+
+        let address = |start: usize| {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(&data[start..start + 16]);
+            Ipv6Addr::from(octets)
+        };
+        Ok(IPv6Packet {
+            version: 6,
+            traffic_class: (data[0] << 4) | (data[1] >> 4),
+            flow_label: u32::from_be_bytes([0, data[1] & 0x0F, data[2], data[3]]),
+            payload_length,
+            next_header,
+            hop_limit: data[7],
+            source_ip: address(8),
+            dest_ip: address(24),
+            extension_headers,
+            payload: Vec::from(&data[offset..end]),
+        })
+    }
+}
+
+impl IPv6Packet {
+    /// Validates the checksum of the TCP, UDP or ICMPv6 message the packet
+    /// carries, over the pseudo-header. Returns `None` for fragments, packets
+    /// with a routing header, whose final destination is not `dest_ip`, other
+    /// protocols and truncated headers. UDP checksums are mandatory over IPv6,
+    /// so a zero one is reported as invalid.
+    pub fn validate_payload_checksum(&self) -> Option<bool> {
+        if self.extension_headers.iter().any(|&header| header == 43 || header == 44) {
+            return None;
+        }
+        let minimum = match self.next_header {
+            6 => 20,
+            17 | 58 => 8,
+            _ => return None,
+        };
+        (self.payload.len() >= minimum).then(|| {
+            transport_checksum_valid(
+                IpAddr::V6(self.source_ip),
+                IpAddr::V6(self.dest_ip),
+                self.next_header,
+                &self.payload,
+            )
+        })
+    }
+}
+
+/// IP Protocol
+/// Represents the protocol field of an IPv4 header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IpProtocol {
+    ICMP,
+    TCP,
+    UDP,
+    DCCP,
+    UDPLite,
+    Unknown(u8),
+}
+
+impl From<u8> for IpProtocol {
+    fn from(value: u8) -> Self {
+        match value {
+            1 => IpProtocol::ICMP,
+            6 => IpProtocol::TCP,
+            17 => IpProtocol::UDP,
+            33 => IpProtocol::DCCP,
+            136 => IpProtocol::UDPLite,
+            _ => IpProtocol::Unknown(value),
+        }
+    }
+}
+
+impl From<IpProtocol> for u8 {
+    fn from(protocol: IpProtocol) -> Self {
+        match protocol {
+            IpProtocol::ICMP => 1,
+            IpProtocol::TCP => 6,
+            IpProtocol::UDP => 17,
+            IpProtocol::DCCP => 33,
+            IpProtocol::UDPLite => 136,
+            IpProtocol::Unknown(value) => value,
+        }
+    }
+}
+
+/// TCP Flags
+/// The control bits of a TCP header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TcpFlags(pub u16);
+
+impl TcpFlags {
+    pub const FIN: u16 = 0x001;
+    pub const SYN: u16 = 0x002;
+    pub const RST: u16 = 0x004;
+    pub const PSH: u16 = 0x008;
+    pub const ACK: u16 = 0x010;
+    pub const URG: u16 = 0x020;
+    pub const ECE: u16 = 0x040;
+    pub const CWR: u16 = 0x080;
+    pub const NS: u16 = 0x100;
+
+    pub fn contains(&self, flag: u16) -> bool {
+        self.0 & flag == flag
+    }
+
+    /// Names of the set flags in header order, e.g. `["SYN", "ACK"]`.
+    pub fn names(&self) -> Vec<&'static str> {
+        const NAMES: [(u16, &str); 9] = [
+            (TcpFlags::FIN, "FIN"),
+            (TcpFlags::SYN, "SYN"),
+            (TcpFlags::RST, "RST"),
+            (TcpFlags::PSH, "PSH"),
+            (TcpFlags::ACK, "ACK"),
+            (TcpFlags::URG, "URG"),
+            (TcpFlags::ECE, "ECE"),
+            (TcpFlags::CWR, "CWR"),
+            (TcpFlags::NS, "NS"),
+        ];
+        NAMES
+            .iter()
+            .filter(|(flag, _)| self.contains(*flag))
+            .map(|(_, name)| *name)
+            .collect()
+    }
+}
+
+/// Formats the flags the way tcpdump does, e.g. `S.` for SYN/ACK.
+impl fmt::Display for TcpFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const NAMES: [(u16, char); 8] = [
+            (TcpFlags::FIN, 'F'),
+            (TcpFlags::SYN, 'S'),
+            (TcpFlags::RST, 'R'),
+            (TcpFlags::PSH, 'P'),
+            (TcpFlags::ACK, '.'),
+            (TcpFlags::URG, 'U'),
+            (TcpFlags::ECE, 'E'),
+            (TcpFlags::CWR, 'W'),
+        ];
+        let mut empty = true;
+        for (flag, name) in NAMES {
+            if self.contains(flag) {
+                write!(f, "{}", name)?;
+                empty = false;
+            }
+        }
+        if empty {
+            write!(f, "none")?;
+        }
+        Ok(())
+    }
+}
+
+/// TCP Segment
+/// Represents a TCP header with its options and payload.
+#[derive(Debug)]
+pub struct TcpSegment {
+    pub source_port: u16,
+    pub dest_port: u16,
+    pub sequence_number: u32,
+    pub ack_number: u32,
+    pub data_offset: u8,
+    pub flags: TcpFlags,
+    pub window_size: u16,
+    pub checksum: u16,
+    pub urgent_pointer: u16,
+    pub options: Vec<u8>,
+    pub payload: Vec<u8>,
+    /// Whether the checksum matches, once validated against the carrying IP
+    /// packet; see `IPv4Packet::validate_payload_checksum`.
+    pub checksum_valid: Option<bool>,
+}
+
+impl TryFrom<&[u8]> for TcpSegment {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 20 {
+            return Err(KcpdumpError::malformed(0, "Data too short for TCP segment"));
+        }
+
+        let data_offset = data[12] >> 4;
+        let header_len = data_offset as usize * 4;
+        if header_len < 20 {
+            return Err(KcpdumpError::malformed(12, "Invalid TCP data offset"));
+        }
+        if data.len() < header_len {
+            return Err(KcpdumpError::malformed(20, "Data too short for TCP options"));
+        }
+
+        Ok(TcpSegment {
+            source_port: u16::from_be_bytes([data[0], data[1]]),
+            dest_port: u16::from_be_bytes([data[2], data[3]]),
+            sequence_number: u32::from_be_bytes([data[4], data[5], data[6], data[7]]),
+            ack_number: u32::from_be_bytes([data[8], data[9], data[10], data[11]]),
+            data_offset,
+            flags: TcpFlags(u16::from_be_bytes([data[12] & 0x01, data[13]])),
+            window_size: u16::from_be_bytes([data[14], data[15]]),
+            checksum: u16::from_be_bytes([data[16], data[17]]),
+            urgent_pointer: u16::from_be_bytes([data[18], data[19]]),
+            options: Vec::from(&data[20..header_len]),
+            payload: Vec::from(&data[header_len..]),
+            checksum_valid: None,
+        })
+    }
+}
+
+impl TcpSegment {
+    /// Decodes the header options.
+    pub fn parsed_options(&self) -> Vec<TcpOption> {
+        TcpOption::parse_all(&self.options)
+    }
+}
+
+/// TCP Option
+/// A decoded TCP header option. No-operation padding and the end of the
+/// option list are not reported.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum TcpOption {
+    #[serde(rename_all = "camelCase")]
+    MaximumSegmentSize { value: u16 },
+    #[serde(rename_all = "camelCase")]
+    WindowScale { shift: u8 },
+    SackPermitted,
+    /// Left and right edges of each selectively acknowledged block.
+    #[serde(rename_all = "camelCase")]
+    Sack { blocks: Vec<(u32, u32)> },
+    #[serde(rename_all = "camelCase")]
+    Timestamps { value: u32, echo_reply: u32 },
+    #[serde(rename_all = "camelCase")]
+    Unknown { code: u8, data: Vec<u8> },
+}
+
+impl TcpOption {
+    /// Decodes an options area. Decoding stops at the end of the option list
+    /// or at an option that runs past the end of the header.
+    pub fn parse_all(data: &[u8]) -> Vec<TcpOption> {
+        let be32 = |bytes: &[u8]| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        let mut options = Vec::new();
+        let mut rest = data;
+        while let Some((&kind, tail)) = rest.split_first() {
+            match kind {
+                0 => break,
+                1 => {
+                    rest = tail;
+                    continue;
+                }
+                _ => {}
+            }
+            let Some(&length) = tail.first() else {
+                break;
+            };
+            let Some(body) = rest.get(2..length as usize) else {
+                break;
+            };
+            options.push(match (kind, body.len()) {
+                (2, 2) => TcpOption::MaximumSegmentSize {
+                    value: u16::from_be_bytes([body[0], body[1]]),
+                },
+                (3, 1) => TcpOption::WindowScale { shift: body[0] },
+                (4, 0) => TcpOption::SackPermitted,
+                (5, len) if len > 0 && len.is_multiple_of(8) => TcpOption::Sack {
+                    blocks: body
+                        .chunks_exact(8)
+                        .map(|block| (be32(&block[0..4]), be32(&block[4..8])))
+                        .collect(),
+                },
+                (8, 8) => TcpOption::Timestamps {
+                    value: be32(&body[0..4]),
+                    echo_reply: be32(&body[4..8]),
+                },
+                _ => TcpOption::Unknown {
+                    code: kind,
+                    data: body.to_vec(),
+                },
+            });
+            rest = &rest[length as usize..];
+        }
+        options
+    }
+}
+
+/// UDP Datagram
+/// Represents a UDP header and its payload.
+#[derive(Debug)]
+pub struct UdpDatagram {
+    pub source_port: u16,
+    pub dest_port: u16,
+    pub length: u16,
+    pub checksum: u16,
+    pub payload: Vec<u8>,
+    /// Whether the checksum matches, once validated against the carrying IP
+    /// packet; see `IPv4Packet::validate_payload_checksum`.
+    pub checksum_valid: Option<bool>,
+}
+
+impl TryFrom<&[u8]> for UdpDatagram {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 8 {
+            return Err(KcpdumpError::malformed(0, "Data too short for UDP datagram"));
+        }
+
+        let length = u16::from_be_bytes([data[4], data[5]]);
+        if (length as usize) < 8 || data.len() < length as usize {
+            return Err(KcpdumpError::malformed(4, "Data length mismatch"));
+        }
+
+        Ok(UdpDatagram {
+            source_port: u16::from_be_bytes([data[0], data[1]]),
+            dest_port: u16::from_be_bytes([data[2], data[3]]),
+            length,
+            checksum: u16::from_be_bytes([data[6], data[7]]),
+            payload: Vec::from(&data[8..length as usize]),
+            checksum_valid: None,
+        })
+    }
 }
 
+/// IPv4 Dissector
+/// Registers the IPv4 header fields with the dissector registry.
+pub struct IPv4Dissector;
+
+const IPV4_FIELDS: &[FieldInfo] = &[
+    FieldInfo::new("ip.version", FieldType::UInt, "IP version"),
+    FieldInfo::new("ip.hdr_len", FieldType::UInt, "Header length in bytes"),
+    FieldInfo::new("ip.dsfield", FieldType::UInt, "Differentiated services field"),
+    FieldInfo::new("ip.len", FieldType::UInt, "Total length"),
+    FieldInfo::new("ip.id", FieldType::UInt, "Identification"),
+    FieldInfo::new("ip.flags", FieldType::UInt, "Flags"),
+    FieldInfo::new("ip.frag_offset", FieldType::UInt, "Fragment offset"),
+    FieldInfo::new("ip.ttl", FieldType::UInt, "Time to live"),
+    FieldInfo::new("ip.proto", FieldType::UInt, "Protocol"),
+    FieldInfo::new("ip.checksum", FieldType::UInt, "Header checksum"),
+    FieldInfo::new("ip.checksum_good", FieldType::Bool, "Header checksum is valid"),
+    FieldInfo::new("ip.src", FieldType::Ipv4Address, "Source address"),
+    FieldInfo::new("ip.dst", FieldType::Ipv4Address, "Destination address"),
+    FieldInfo::new("ip.addr", FieldType::Ipv4Address, "Source or destination address"),
+];
+
+impl Dissector for IPv4Dissector {
+    fn protocol(&self) -> &'static str {
+        "ip"
+    }
+
+    fn description(&self) -> &'static str {
+        "Internet Protocol Version 4"
+    }
+
+    fn fields(&self) -> &'static [FieldInfo] {
+        IPV4_FIELDS
+    }
+
+    fn dissect(&self, layers: &PacketLayers, values: &mut FieldValues) {
+        let Some(ip) = &layers.ipv4 else {
+            return;
+        };
+        let source = Ipv4Addr::from(ip.source_ip);
+        let dest = Ipv4Addr::from(ip.dest_ip);
+        values.push("ip", FieldValue::Protocol);
+        values.push("ip.version", FieldValue::UInt(ip.version.into()));
+        values.push("ip.hdr_len", FieldValue::UInt(u64::from(ip.ihl) * 4));
+        values.push("ip.dsfield", FieldValue::UInt(ip.tos.into()));
+        values.push("ip.len", FieldValue::UInt(ip.total_length.into()));
+        values.push("ip.id", FieldValue::UInt(ip.identification.into()));
+        values.push("ip.flags", FieldValue::UInt(ip.flags.into()));
+        values.push("ip.frag_offset", FieldValue::UInt(ip.fragment_offset.into()));
+        values.push("ip.ttl", FieldValue::UInt(ip.ttl.into()));
+        values.push("ip.proto", FieldValue::UInt(ip.protocol.into()));
+        values.push("ip.checksum", FieldValue::UInt(ip.header_checksum.into()));
+        values.push("ip.checksum_good", FieldValue::Bool(ip.validate_checksum()));
+        values.push("ip.src", FieldValue::Ipv4Address(source));
+        values.push("ip.dst", FieldValue::Ipv4Address(dest));
+        values.push("ip.addr", FieldValue::Ipv4Address(source));
+        values.push("ip.addr", FieldValue::Ipv4Address(dest));
+    }
+}
+
+/// TCP Dissector
+/// Registers the TCP header fields with the dissector registry.
+pub struct TcpDissector;
+
+const TCP_FIELDS: &[FieldInfo] = &[
+    FieldInfo::new("tcp.srcport", FieldType::UInt, "Source port"),
+    FieldInfo::new("tcp.dstport", FieldType::UInt, "Destination port"),
+    FieldInfo::new("tcp.port", FieldType::UInt, "Source or destination port"),
+    FieldInfo::new("tcp.seq", FieldType::UInt, "Sequence number"),
+    FieldInfo::new("tcp.ack", FieldType::UInt, "Acknowledgment number"),
+    FieldInfo::new("tcp.hdr_len", FieldType::UInt, "Header length in bytes"),
+    FieldInfo::new("tcp.flags", FieldType::UInt, "Flags"),
+    FieldInfo::new("tcp.flags.fin", FieldType::Bool, "FIN flag"),
+    FieldInfo::new("tcp.flags.syn", FieldType::Bool, "SYN flag"),
+    FieldInfo::new("tcp.flags.reset", FieldType::Bool, "RST flag"),
+    FieldInfo::new("tcp.flags.push", FieldType::Bool, "PSH flag"),
+    FieldInfo::new("tcp.flags.ack", FieldType::Bool, "ACK flag"),
+    FieldInfo::new("tcp.flags.urg", FieldType::Bool, "URG flag"),
+    FieldInfo::new("tcp.window_size", FieldType::UInt, "Window size"),
+    FieldInfo::new("tcp.checksum", FieldType::UInt, "Checksum"),
+    FieldInfo::new("tcp.checksum_good", FieldType::Bool, "Checksum is valid"),
+    FieldInfo::new("tcp.urgent_pointer", FieldType::UInt, "Urgent pointer"),
+    FieldInfo::new("tcp.options.mss_val", FieldType::UInt, "Maximum segment size"),
+    FieldInfo::new("tcp.options.wscale.shift", FieldType::UInt, "Window scale shift count"),
+    FieldInfo::new("tcp.options.sack_perm", FieldType::Bool, "SACK permitted option"),
+    FieldInfo::new("tcp.options.sack", FieldType::Bool, "SACK option"),
+    FieldInfo::new("tcp.options.timestamp.tsval", FieldType::UInt, "Timestamp value"),
+    FieldInfo::new("tcp.options.timestamp.tsecr", FieldType::UInt, "Timestamp echo reply"),
+    FieldInfo::new("tcp.len", FieldType::UInt, "Segment payload length"),
+    FieldInfo::new("tcp.payload", FieldType::Bytes, "Segment payload"),
+];
+
+impl Dissector for TcpDissector {
+    fn protocol(&self) -> &'static str {
+        "tcp"
+    }
+
+    fn description(&self) -> &'static str {
+        "Transmission Control Protocol"
+    }
+
+    fn fields(&self) -> &'static [FieldInfo] {
+        TCP_FIELDS
+    }
+
+    fn dissect(&self, layers: &PacketLayers, values: &mut FieldValues) {
+        let Some(tcp) = &layers.tcp else {
+            return;
+        };
+        let flag = |bit| FieldValue::Bool(tcp.flags.contains(bit));
+        values.push("tcp", FieldValue::Protocol);
+        values.push("tcp.srcport", FieldValue::UInt(tcp.source_port.into()));
+        values.push("tcp.dstport", FieldValue::UInt(tcp.dest_port.into()));
+        values.push("tcp.port", FieldValue::UInt(tcp.source_port.into()));
+        values.push("tcp.port", FieldValue::UInt(tcp.dest_port.into()));
+        values.push("tcp.seq", FieldValue::UInt(tcp.sequence_number.into()));
+        values.push("tcp.ack", FieldValue::UInt(tcp.ack_number.into()));
+        values.push("tcp.hdr_len", FieldValue::UInt(u64::from(tcp.data_offset) * 4));
+        values.push("tcp.flags", FieldValue::UInt(tcp.flags.0.into()));
+        values.push("tcp.flags.fin", flag(TcpFlags::FIN));
+        values.push("tcp.flags.syn", flag(TcpFlags::SYN));
+        values.push("tcp.flags.reset", flag(TcpFlags::RST));
+        values.push("tcp.flags.push", flag(TcpFlags::PSH));
+        values.push("tcp.flags.ack", flag(TcpFlags::ACK));
+        values.push("tcp.flags.urg", flag(TcpFlags::URG));
+        values.push("tcp.window_size", FieldValue::UInt(tcp.window_size.into()));
+        values.push("tcp.checksum", FieldValue::UInt(tcp.checksum.into()));
+        if let Some(valid) = tcp.checksum_valid {
+            values.push("tcp.checksum_good", FieldValue::Bool(valid));
+        }
+        values.push("tcp.urgent_pointer", FieldValue::UInt(tcp.urgent_pointer.into()));
+        for option in tcp.parsed_options() {
+            match option {
+                TcpOption::MaximumSegmentSize { value } => {
+                    values.push("tcp.options.mss_val", FieldValue::UInt(value.into()));
+                }
+                TcpOption::WindowScale { shift } => {
+                    values.push("tcp.options.wscale.shift", FieldValue::UInt(shift.into()));
+                }
+                TcpOption::SackPermitted => {
+                    values.push("tcp.options.sack_perm", FieldValue::Bool(true));
+                }
+                TcpOption::Sack { .. } => values.push("tcp.options.sack", FieldValue::Bool(true)),
+                TcpOption::Timestamps { value, echo_reply } => {
+                    values.push("tcp.options.timestamp.tsval", FieldValue::UInt(value.into()));
+                    values.push(
+                        "tcp.options.timestamp.tsecr",
+                        FieldValue::UInt(echo_reply.into()),
+                    );
+                }
+                TcpOption::Unknown { .. } => {}
+            }
+        }
+        values.push("tcp.len", FieldValue::UInt(tcp.payload.len() as u64));
+        if !tcp.payload.is_empty() {
+            values.push("tcp.payload", FieldValue::Bytes(tcp.payload.clone()));
+        }
+    }
+}
+
+/// UDP Dissector
+/// Registers the UDP header fields with the dissector registry.
+pub struct UdpDissector;
+
+const UDP_FIELDS: &[FieldInfo] = &[
+    FieldInfo::new("udp.srcport", FieldType::UInt, "Source port"),
+    FieldInfo::new("udp.dstport", FieldType::UInt, "Destination port"),
+    FieldInfo::new("udp.port", FieldType::UInt, "Source or destination port"),
+    FieldInfo::new("udp.length", FieldType::UInt, "Length of header and payload"),
+    FieldInfo::new("udp.checksum", FieldType::UInt, "Checksum"),
+    FieldInfo::new("udp.checksum_good", FieldType::Bool, "Checksum is valid"),
+    FieldInfo::new("udp.payload", FieldType::Bytes, "Datagram payload"),
+];
+
+impl Dissector for UdpDissector {
+    fn protocol(&self) -> &'static str {
+        "udp"
+    }
+
+    fn description(&self) -> &'static str {
+        "User Datagram Protocol"
+    }
+
+    fn fields(&self) -> &'static [FieldInfo] {
+        UDP_FIELDS
+    }
+
+    fn dissect(&self, layers: &PacketLayers, values: &mut FieldValues) {
+        let Some(udp) = &layers.udp else {
+            return;
+        };
+        values.push("udp", FieldValue::Protocol);
+        values.push("udp.srcport", FieldValue::UInt(udp.source_port.into()));
+        values.push("udp.dstport", FieldValue::UInt(udp.dest_port.into()));
+        values.push("udp.port", FieldValue::UInt(udp.source_port.into()));
+        values.push("udp.port", FieldValue::UInt(udp.dest_port.into()));
+        values.push("udp.length", FieldValue::UInt(udp.length.into()));
+        values.push("udp.checksum", FieldValue::UInt(udp.checksum.into()));
+        if let Some(valid) = udp.checksum_valid {
+            values.push("udp.checksum_good", FieldValue::Bool(valid));
+        }
+        if !udp.payload.is_empty() {
+            values.push("udp.payload", FieldValue::Bytes(udp.payload.clone()));
+        }
+    }
+}
+
+#[cfg(test)]
 mod tests {
     use crate::cap::Capture;
 
@@ -235,6 +1035,30 @@ mod tests {
             [0x01, 0x23, 0x45, 0x67, 0x89, 0xAC]
         );
         assert_eq!(packet.header.ether_type, EtherType::IPv4);
+        assert!(packet.header.vlan_tags.is_empty());
+    }
+
+    #[test]
+    fn test_vlan_tags() {
+        let mut data = vec![0xff; 12];
+        // Service tag VLAN 100, then customer tag VLAN 42 with priority 5 and DEI.
+        data.extend_from_slice(&[0x88, 0xa8, 0x00, 0x64, 0x81, 0x00, 0xb0, 0x2a]);
+        data.extend_from_slice(&[0x86, 0xdd, 0x60]);
+        let packet = EthernetPacket::try_from(data.as_slice()).unwrap();
+        assert_eq!(packet.header.ether_type, EtherType::IPv6);
+        assert_eq!(packet.header.vlan_ids(), [100, 42]);
+        assert_eq!(
+            packet.header.vlan_tags[1],
+            VlanTag {
+                tpid: TPID_8021Q,
+                pcp: 5,
+                dei: true,
+                vid: 42,
+            }
+        );
+        assert_eq!(packet.header.size(), 22);
+        assert_eq!(packet.data, [0x60]);
+        assert!(EthernetPacket::try_from(&data[..16]).is_err());
     }
 
     #[test]
@@ -258,7 +1082,7 @@ mod tests {
     #[test]
     fn test_ipv4_packet_checksum_valid() {
         let data: [u8; 24] = [
-            0x45, 0x00, 0x00, 0x18, 0x1c, 0x46, 0x40, 0x00, 0x40, 0x06, 0xb1, 0xe6, 0xc0, 0xa8,
+            0x45, 0x00, 0x00, 0x18, 0x1c, 0x46, 0x40, 0x00, 0x40, 0x06, 0x9c, 0x81, 0xc0, 0xa8,
             0x00, 0x01, 0xc0, 0xa8, 0x00, 0xc7, 0xde, 0xad, 0xbe, 0xef,
         ];
         let packet: IPv4Packet = (&data[..]).try_into().unwrap();
@@ -275,6 +1099,149 @@ mod tests {
         assert!(!packet.validate_checksum());
     }
 
+    #[test]
+    fn test_transport_checksums() {
+        // TCP, UDP and ICMP from 192.168.0.10 to 93.184.216.34 with valid checksums.
+        let tcp = [
+            0x45, 0x00, 0x00, 0x2b, 0x00, 0x01, 0x00, 0x00, 0x40, 0x06, 0x84, 0x3f, 0xc0, 0xa8,
+            0x00, 0x0a, 0x5d, 0xb8, 0xd8, 0x22, 0xc3, 0x50, 0x00, 0x50, 0x00, 0x00, 0x03, 0xe8,
+            0x00, 0x00, 0x00, 0x00, 0x50, 0x02, 0xff, 0xff, 0x68, 0x60, 0x00, 0x00, 0x68, 0x69,
+            0x21,
+        ];
+        let udp = [
+            0x45, 0x00, 0x00, 0x21, 0x00, 0x01, 0x00, 0x00, 0x40, 0x11, 0x84, 0x3e, 0xc0, 0xa8,
+            0x00, 0x0a, 0x5d, 0xb8, 0xd8, 0x22, 0xcf, 0x08, 0x00, 0x35, 0x00, 0x0d, 0xea, 0x20,
+            0x71, 0x75, 0x65, 0x72, 0x79,
+        ];
+        let icmp = [
+            0x45, 0x00, 0x00, 0x20, 0x00, 0x01, 0x00, 0x00, 0x40, 0x01, 0x84, 0x4f, 0xc0, 0xa8,
+            0x00, 0x0a, 0x5d, 0xb8, 0xd8, 0x22, 0x08, 0x00, 0x19, 0x2d, 0x00, 0x01, 0x00, 0x01,
+            0x70, 0x69, 0x6e, 0x67,
+        ];
+        for data in [&tcp[..], &udp[..], &icmp[..]] {
+            let packet = IPv4Packet::try_from(data).unwrap();
+            assert!(packet.validate_checksum());
+            assert_eq!(packet.validate_payload_checksum(), Some(true));
+            // Corrupting the last payload byte only breaks the transport checksum.
+            let mut corrupted = data.to_vec();
+            *corrupted.last_mut().unwrap() ^= 0xff;
+            let packet = IPv4Packet::try_from(corrupted.as_slice()).unwrap();
+            assert!(packet.validate_checksum());
+            assert_eq!(packet.validate_payload_checksum(), Some(false));
+        }
+
+        // UDP datagrams without a checksum and fragments are not validated.
+        let mut unchecked = udp;
+        unchecked[26..28].copy_from_slice(&[0, 0]);
+        let packet = IPv4Packet::try_from(&unchecked[..]).unwrap();
+        assert_eq!(packet.validate_payload_checksum(), None);
+        let mut fragment = IPv4Packet::try_from(&tcp[..]).unwrap();
+        fragment.flags = 0x1;
+        assert_eq!(fragment.validate_payload_checksum(), None);
+    }
+
+    #[test]
+    fn test_ipv6_udp_checksum() {
+        // UDP from 2001:db8::1 to 2001:db8::2.
+        let mut data = [
+            0x60, 0x00, 0x00, 0x00, 0x00, 0x0d, 0x11, 0x40, 0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x20, 0x01, 0x0d, 0xb8,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0xcf, 0x08,
+            0x00, 0x35, 0x00, 0x0d, 0x85, 0x39, 0x71, 0x75, 0x65, 0x72, 0x79,
+        ];
+        let packet = IPv6Packet::try_from(&data[..]).unwrap();
+        assert_eq!(packet.validate_payload_checksum(), Some(true));
+        // A zero UDP checksum is not allowed over IPv6.
+        data[46..48].copy_from_slice(&[0, 0]);
+        let packet = IPv6Packet::try_from(&data[..]).unwrap();
+        assert_eq!(packet.validate_payload_checksum(), Some(false));
+    }
+
+    #[test]
+    fn test_ipv6_extension_headers() {
+        let mut data = vec![0x60, 0x12, 0x34, 0x56, 0x00, 0x14, 0x00, 0x40];
+        data.extend_from_slice(&Ipv6Addr::LOCALHOST.octets());
+        data.extend_from_slice(&"2001:db8::1".parse::<Ipv6Addr>().unwrap().octets());
+        // Hop-by-hop options, then a fragment header leading to UDP.
+        data.extend_from_slice(&[44, 0, 1, 4, 0, 0, 0, 0]);
+        data.extend_from_slice(&[17, 0, 0, 0, 0, 0, 0, 1]);
+        data.extend_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+        let packet = IPv6Packet::try_from(data.as_slice()).unwrap();
+        assert_eq!(packet.version, 6);
+        assert_eq!(packet.traffic_class, 0x01);
+        assert_eq!(packet.flow_label, 0x23456);
+        assert_eq!(packet.next_header, 17);
+        assert_eq!(packet.hop_limit, 64);
+        assert_eq!(packet.extension_headers, vec![0, 44]);
+        assert_eq!(packet.dest_ip.to_string(), "2001:db8::1");
+        assert_eq!(packet.payload, vec![0xde, 0xad, 0xbe, 0xef]);
+        assert!(IPv6Packet::try_from(&data[..50]).is_err());
+    }
+
+    #[test]
+    fn test_tcp_segment() {
+        let data: [u8; 24] = [
+            0xc3, 0x50, 0x00, 0x50, 0x00, 0x00, 0x03, 0xe8, 0x00, 0x00, 0x00, 0x00, 0x50, 0x12,
+            0xff, 0xff, 0x12, 0x34, 0x00, 0x00, 0xde, 0xad, 0xbe, 0xef,
+        ];
+        let segment = TcpSegment::try_from(&data[..]).unwrap();
+        assert_eq!(segment.source_port, 50000);
+        assert_eq!(segment.dest_port, 80);
+        assert_eq!(segment.sequence_number, 1000);
+        assert!(segment.flags.contains(TcpFlags::SYN));
+        assert!(segment.flags.contains(TcpFlags::ACK));
+        assert_eq!(segment.flags.to_string(), "S.");
+        assert_eq!(segment.flags.names(), vec!["SYN", "ACK"]);
+        assert_eq!(segment.window_size, 65535);
+        assert_eq!(segment.payload, vec![0xde, 0xad, 0xbe, 0xef]);
+    }
+
+    #[test]
+    fn test_tcp_options() {
+        let options = [
+            0x02, 0x04, 0x05, 0xb4, // MSS 1460
+            0x01, // NOP
+            0x03, 0x03, 0x07, // window scale 7
+            0x04, 0x02, // SACK permitted
+            0x08, 0x0a, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02, // timestamps
+            0x05, 0x0a, 0x00, 0x00, 0x03, 0xe8, 0x00, 0x00, 0x07, 0xd0, // SACK block
+            0x00, 0x00, // end of list
+        ];
+        assert_eq!(
+            TcpOption::parse_all(&options),
+            vec![
+                TcpOption::MaximumSegmentSize { value: 1460 },
+                TcpOption::WindowScale { shift: 7 },
+                TcpOption::SackPermitted,
+                TcpOption::Timestamps {
+                    value: 1,
+                    echo_reply: 2
+                },
+                TcpOption::Sack {
+                    blocks: vec![(1000, 2000)]
+                },
+            ]
+        );
+        // An option running past the header is dropped.
+        assert_eq!(
+            TcpOption::parse_all(&[0x02, 0x04, 0x05]),
+            Vec::<TcpOption>::new()
+        );
+    }
+
+    #[test]
+    fn test_udp_datagram() {
+        let data: [u8; 12] = [
+            0xcf, 0x08, 0x00, 0x35, 0x00, 0x0c, 0x00, 0x00, 0xde, 0xad, 0xbe, 0xef,
+        ];
+        let datagram = UdpDatagram::try_from(&data[..]).unwrap();
+        assert_eq!(datagram.source_port, 53000);
+        assert_eq!(datagram.dest_port, 53);
+        assert_eq!(datagram.length, 12);
+        assert_eq!(datagram.payload, vec![0xde, 0xad, 0xbe, 0xef]);
+        assert!(UdpDatagram::try_from(&data[..10]).is_err());
+    }
+
     async fn get_ethernet_packet(eth_type: Option<EtherType>) -> EthernetPacket {
         let temp_file_path = "sample.pcap";
         // Read the pcap file
@@ -0,0 +1,531 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use crate::cap::{PcapPacket, TimestampResolution};
+use crate::dissect::{DissectorRegistry, FieldType, PacketLayers};
+use crate::error::KcpdumpError;
+use crate::filter::PacketFilter;
+use crate::flows::StreamTable;
+use crate::session::{CaptureId, LoadedCapture};
+use crate::summary::{self, PacketSummary};
+use crate::timefmt::{TimeDisplayMode, TimeFormatter, Timestamp};
+
+/// Packet Row
+/// One row of the packet list, tagged with the capture it came from so that
+/// union views over several captures stay unambiguous.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PacketRow {
+    pub capture_id: CaptureId,
+    /// Frame number within its capture, starting at 1.
+    pub number: usize,
+    pub timestamp: Timestamp,
+    pub time: String,
+    /// Conversation index in order of first appearance, independent of the filter.
+    pub stream: Option<u32>,
+    #[serde(flatten)]
+    pub summary: PacketSummary,
+    /// Values of the requested field columns, in request order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<String>,
+}
+
+/// Field Columns
+/// Display-filter fields requested as extra packet list columns. Packets are
+/// only dissected beyond the summary when at least one column is requested.
+pub struct FieldColumns<'a> {
+    registry: &'a DissectorRegistry,
+    names: Vec<String>,
+}
+
+impl<'a> FieldColumns<'a> {
+    /// Validates the field names against the registry.
+    pub fn new(registry: &'a DissectorRegistry, names: Vec<String>) -> Result<Self, KcpdumpError> {
+        if let Some(name) = names.iter().find(|name| registry.field(name).is_none()) {
+            return Err(KcpdumpError::InvalidInput(format!(
+                "Unknown field: {}",
+                name
+            )));
+        }
+        Ok(FieldColumns { registry, names })
+    }
+
+    /// Renders the column values of one packet of the given link type.
+    pub fn values(&self, number: usize, link_type: u32, packet: &PcapPacket) -> Vec<String> {
+        if self.names.is_empty() {
+            return Vec::new();
+        }
+        let values = self
+            .registry
+            .dissect(&PacketLayers::decode_link(number, link_type, packet));
+        self.names
+            .iter()
+            .map(|name| match self.registry.field(name) {
+                // Protocol columns show whether the protocol is present.
+                Some(field) if field.field_type == FieldType::Protocol => {
+                    if values.contains(name) {
+                        name.clone()
+                    } else {
+                        String::new()
+                    }
+                }
+                _ => values.display(name),
+            })
+            .collect()
+    }
+}
+
+/// Sort Column
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum SortColumn {
+    /// Capture order, i.e. the order produced by `merged_packets`.
+    #[default]
+    Number,
+    Time,
+    Source,
+    Destination,
+    Protocol,
+    Length,
+    Stream,
+}
+
+/// Packet Sort
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PacketSort {
+    pub column: SortColumn,
+    pub descending: bool,
+}
+
+/// Packet List Query
+/// Everything the packet list view asks for in one request.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PacketListQuery {
+    /// A single capture, or every open capture when `None`.
+    pub capture_id: Option<CaptureId>,
+    pub filter: PacketFilter,
+    pub sort: PacketSort,
+    pub offset: usize,
+    /// Maximum number of rows; all remaining rows when `None`.
+    pub limit: Option<usize>,
+    /// Display-filter fields to compute as extra columns.
+    pub columns: Vec<String>,
+}
+
+impl PacketListQuery {
+    /// Whether the query lists packets unfiltered in capture order, so that
+    /// a single capture can be paged straight from its index with [`page`].
+    pub fn is_capture_order(&self) -> bool {
+        self.filter == PacketFilter::default()
+            && self.sort.column == SortColumn::Number
+            && !self.sort.descending
+    }
+}
+
+/// Packet Page
+/// A window of the sorted packet list together with the total row count.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PacketPage {
+    pub total: usize,
+    pub offset: usize,
+    pub rows: Vec<PacketRow>,
+}
+
+/// Iterates over the packets of the given captures as `(capture id, frame number, packet)`,
+/// reading them from their files one at a time. A single capture keeps its file order;
+/// several captures are interleaved by the timestamps in their indexes, with ties resolved
+/// by capture id.
+pub fn merged_packets(
+    captures: &[Arc<LoadedCapture>],
+) -> impl Iterator<Item = Result<(CaptureId, usize, PcapPacket), KcpdumpError>> + '_ {
+    let mut sources: Vec<_> = captures
+        .iter()
+        .map(|capture| (capture.id, 0, capture.packets()))
+        .collect();
+    std::iter::from_fn(move || {
+        let (capture_id, number, packets) = sources
+            .iter_mut()
+            .filter_map(|source| Some((source.2.next_timestamp()?, source)))
+            .min_by_key(|(timestamp, _)| *timestamp)?
+            .1;
+        *number += 1;
+        let number = *number;
+        Some(packets.next()?.map(|packet| (*capture_id, number, packet)))
+    })
+}
+
+/// The finest timestamp resolution among the captures, to show merged
+/// packets with enough decimals for all of them.
+pub fn resolution(captures: &[Arc<LoadedCapture>]) -> TimestampResolution {
+    captures
+        .iter()
+        .map(|capture| capture.header.resolution())
+        .max()
+        .unwrap_or_default()
+}
+
+/// The link type of each capture, to decode merged packets.
+pub fn link_types(captures: &[Arc<LoadedCapture>]) -> HashMap<CaptureId, u32> {
+    captures
+        .iter()
+        .map(|capture| (capture.id, capture.header.network))
+        .collect()
+}
+
+/// Builds the packet list rows of the given captures that pass `filter`,
+/// filling in the requested field columns if any.
+pub fn build_rows(
+    captures: &[Arc<LoadedCapture>],
+    filter: &PacketFilter,
+    mode: TimeDisplayMode,
+    columns: Option<&FieldColumns>,
+) -> Result<Vec<PacketRow>, KcpdumpError> {
+    let mut formatter = TimeFormatter::with_resolution(mode, resolution(captures));
+    let mut streams = StreamTable::default();
+    let mut rows = Vec::new();
+    let link_types = link_types(captures);
+
+    for item in merged_packets(captures) {
+        let (capture_id, number, packet) = item?;
+        let timestamp = packet.header.timestamp;
+        let link_type = link_types[&capture_id];
+        let summary = summary::summarize_link(link_type, &packet.data);
+        let stream = summary.flow.map(|flow| streams.id(flow));
+        if !filter.matches_frame(timestamp, &packet.data) {
+            formatter.skip(timestamp);
+            continue;
+        }
+        rows.push(PacketRow {
+            capture_id,
+            number,
+            timestamp,
+            time: formatter.format(timestamp),
+            stream,
+            summary,
+            fields: columns
+                .map(|columns| columns.values(number, link_type, &packet))
+                .unwrap_or_default(),
+        });
+    }
+
+    Ok(rows)
+}
+
+/// Builds the rows of up to `count` packets of one capture starting at index
+/// `offset`, without a filter, filling in the requested field columns if any.
+/// Only the packets of the page are read; relative times and stream indexes
+/// come from the capture's index and stream table, so they match the full list.
+pub fn page(
+    capture: &LoadedCapture,
+    offset: usize,
+    count: usize,
+    mode: TimeDisplayMode,
+    columns: Option<&FieldColumns>,
+) -> Result<PacketPage, KcpdumpError> {
+    let mut formatter = TimeFormatter::with_resolution(mode, capture.header.resolution());
+    // Times relative to the first and previous packets start from there.
+    for number in [1, offset] {
+        if let Some(entry) = capture.index.get(number) {
+            formatter.format(entry.timestamp);
+        }
+    }
+    let rows = capture
+        .window(offset, count)?
+        .into_iter()
+        .zip(offset + 1..)
+        .map(|(packet, number)| {
+            let timestamp = packet.header.timestamp;
+            let summary = summary::summarize_link(capture.header.network, &packet.data);
+            PacketRow {
+                capture_id: capture.id,
+                number,
+                timestamp,
+                time: formatter.format(timestamp),
+                stream: summary.flow.and_then(|flow| capture.streams.get(&flow)),
+                summary,
+                fields: columns
+                    .map(|columns| columns.values(number, capture.header.network, &packet))
+                    .unwrap_or_default(),
+            }
+        })
+        .collect();
+    Ok(PacketPage {
+        total: capture.packet_count(),
+        offset,
+        rows,
+    })
+}
+
+/// Sorts rows, which must be in capture order as returned by `build_rows`, by one column.
+/// The sort is stable, so rows with equal keys keep their capture order in both
+/// directions and repeated queries page consistently.
+pub fn sort_rows(rows: &mut [PacketRow], sort: PacketSort) {
+    if sort.column == SortColumn::Number {
+        if sort.descending {
+            rows.reverse();
+        }
+        return;
+    }
+    rows.sort_by(|a, b| {
+        let ordering = compare(a, b, sort.column);
+        if sort.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+}
+
+fn compare(a: &PacketRow, b: &PacketRow, column: SortColumn) -> Ordering {
+    match column {
+        SortColumn::Number => Ordering::Equal,
+        SortColumn::Time => a.timestamp.cmp(&b.timestamp),
+        SortColumn::Source => compare_address(&a.summary.source, &b.summary.source),
+        SortColumn::Destination => compare_address(&a.summary.destination, &b.summary.destination),
+        SortColumn::Protocol => a.summary.protocol.cmp(&b.summary.protocol),
+        SortColumn::Length => a.summary.length.cmp(&b.summary.length),
+        // Rows without a stream sort after all streams.
+        SortColumn::Stream => (a.stream.is_none(), a.stream).cmp(&(b.stream.is_none(), b.stream)),
+    }
+}
+
+/// Orders IP addresses numerically and before anything else, such as MAC addresses.
+fn compare_address(a: &str, b: &str) -> Ordering {
+    match (a.parse::<IpAddr>(), b.parse::<IpAddr>()) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+/// Cuts a page out of the sorted rows. A `limit` of `None` returns every row from `offset`.
+pub fn paginate(rows: Vec<PacketRow>, offset: usize, limit: Option<usize>) -> PacketPage {
+    let total = rows.len();
+    let rows = rows
+        .into_iter()
+        .skip(offset)
+        .take(limit.unwrap_or(usize::MAX))
+        .collect();
+    PacketPage {
+        total,
+        offset,
+        rows,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cap::PcapPacketHeader;
+
+    fn capture(id: CaptureId, timestamps: &[u32]) -> Arc<LoadedCapture> {
+        let packets = timestamps
+            .iter()
+            .map(|&ts_sec| PcapPacket {
+                header: PcapPacketHeader {
+                    timestamp: Timestamp::new(ts_sec, 0),
+                    incl_len: 14,
+                    orig_len: 14,
+                    link_type: None,
+                },
+                data: vec![0; 14],
+            })
+            .collect::<Vec<_>>();
+        Arc::new(LoadedCapture::from_packets(id, "", 1, &packets))
+    }
+
+    #[test]
+    fn test_union_view_interleaves_by_time() {
+        let captures = [capture(1, &[10, 30]), capture(2, &[20, 30])];
+        let rows = build_rows(
+            &captures,
+            &PacketFilter::default(),
+            TimeDisplayMode::SinceStart,
+            None,
+        )
+        .unwrap();
+        let order: Vec<_> = rows
+            .iter()
+            .map(|row| (row.capture_id, row.number))
+            .collect();
+        assert_eq!(order, vec![(1, 1), (2, 1), (1, 2), (2, 2)]);
+        assert_eq!(rows[3].time, "20.000000");
+    }
+
+    #[test]
+    fn test_single_capture_keeps_file_order() {
+        let captures = [capture(7, &[30, 10])];
+        let rows = build_rows(
+            &captures,
+            &PacketFilter::default(),
+            TimeDisplayMode::default(),
+            None,
+        )
+        .unwrap();
+        let numbers: Vec<_> = rows.iter().map(|row| row.number).collect();
+        assert_eq!(numbers, vec![1, 2]);
+        assert!(rows.iter().all(|row| row.capture_id == 7));
+    }
+
+    #[test]
+    fn test_capture_order_query() {
+        let mut query = PacketListQuery::default();
+        assert!(query.is_capture_order());
+        query.sort.descending = true;
+        assert!(!query.is_capture_order());
+        query.sort = PacketSort {
+            column: SortColumn::Time,
+            descending: false,
+        };
+        assert!(!query.is_capture_order());
+    }
+
+    #[test]
+    fn test_page_of_one_capture() {
+        let capture = capture(3, &[10, 12, 15, 20]);
+        let window = page(&capture, 1, 2, TimeDisplayMode::DeltaDisplayed, None).unwrap();
+        assert_eq!(window.total, 4);
+        assert_eq!(window.offset, 1);
+        let numbers: Vec<_> = window.rows.iter().map(|row| row.number).collect();
+        assert_eq!(numbers, vec![2, 3]);
+        assert_eq!(window.rows[0].time, "2.000000");
+        assert!(window.rows[0].fields.is_empty());
+        let registry = DissectorRegistry::default();
+        let columns = FieldColumns::new(&registry, vec!["frame.number".to_string()]).unwrap();
+        let window = page(&capture, 2, 1, TimeDisplayMode::default(), Some(&columns)).unwrap();
+        assert_eq!(window.rows[0].fields, vec!["3"]);
+        assert!(
+            page(&capture, 10, 5, TimeDisplayMode::default(), None)
+                .unwrap()
+                .rows
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_sort_is_stable_in_both_directions() {
+        let captures = [capture(1, &[20, 10, 20, 10])];
+        let mut rows = build_rows(
+            &captures,
+            &PacketFilter::default(),
+            TimeDisplayMode::default(),
+            None,
+        )
+        .unwrap();
+
+        sort_rows(
+            &mut rows,
+            PacketSort {
+                column: SortColumn::Time,
+                descending: false,
+            },
+        );
+        let numbers: Vec<_> = rows.iter().map(|row| row.number).collect();
+        assert_eq!(numbers, vec![2, 4, 1, 3]);
+
+        let mut rows = build_rows(
+            &captures,
+            &PacketFilter::default(),
+            TimeDisplayMode::default(),
+            None,
+        )
+        .unwrap();
+        sort_rows(
+            &mut rows,
+            PacketSort {
+                column: SortColumn::Time,
+                descending: true,
+            },
+        );
+        let numbers: Vec<_> = rows.iter().map(|row| row.number).collect();
+        assert_eq!(numbers, vec![1, 3, 2, 4]);
+    }
+
+    #[test]
+    fn test_paginate() {
+        let captures = [capture(1, &[1, 2, 3, 4, 5])];
+        let rows = build_rows(
+            &captures,
+            &PacketFilter::default(),
+            TimeDisplayMode::default(),
+            None,
+        )
+        .unwrap();
+        let page = paginate(rows, 3, Some(10));
+        assert_eq!(page.total, 5);
+        let numbers: Vec<_> = page.rows.iter().map(|row| row.number).collect();
+        assert_eq!(numbers, vec![4, 5]);
+    }
+
+    #[test]
+    fn test_compare_address() {
+        assert_eq!(compare_address("10.0.0.2", "10.0.0.10"), Ordering::Less);
+        assert_eq!(
+            compare_address("10.0.0.2", "00:11:22:33:44:55"),
+            Ordering::Less
+        );
+    }
+
+    #[tokio::test]
+    async fn test_field_columns() {
+        let capture = LoadedCapture::load(1, "sample.pcap").await.unwrap();
+        let registry = DissectorRegistry::default();
+        let columns = FieldColumns::new(
+            &registry,
+            vec![
+                "tcp.window_size".to_string(),
+                "dns".to_string(),
+                "dns.qry.name".to_string(),
+            ],
+        )
+        .unwrap();
+        let rows = build_rows(
+            &[Arc::new(capture)],
+            &PacketFilter::default(),
+            TimeDisplayMode::default(),
+            Some(&columns),
+        )
+        .unwrap();
+        assert!(rows.iter().all(|row| row.fields.len() == 3));
+        let dns = rows.iter().find(|row| row.fields[1] == "dns").unwrap();
+        assert!(!dns.fields[2].is_empty());
+        assert!(dns.fields[0].is_empty());
+        assert!(rows.iter().any(|row| !row.fields[0].is_empty()));
+
+        assert!(FieldColumns::new(&registry, vec!["tcp.bogus".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_field_columns_of_raw_ip() {
+        let registry = DissectorRegistry::default();
+        let columns = FieldColumns::new(
+            &registry,
+            vec!["ip.src".to_string(), "udp.dstport".to_string()],
+        )
+        .unwrap();
+        let mut data = vec![
+            0x45, 0, 0, 28, 0, 0, 0, 0, 64, 17, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2,
+        ];
+        data.extend_from_slice(&[0x04, 0x00, 0x00, 0x35, 0, 8, 0, 0]);
+        let packet = PcapPacket {
+            header: PcapPacketHeader {
+                timestamp: Timestamp::new(1, 0),
+                incl_len: data.len() as u32,
+                orig_len: data.len() as u32,
+                link_type: None,
+            },
+            data,
+        };
+        assert_eq!(
+            columns.values(1, crate::link::LINKTYPE_RAW, &packet),
+            vec!["10.0.0.1", "53"]
+        );
+        assert_eq!(columns.values(1, 1, &packet), vec!["", ""]);
+    }
+}
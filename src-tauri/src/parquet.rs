@@ -0,0 +1,306 @@
+use std::fs::File;
+use std::sync::Arc;
+
+use ::parquet::basic::{Compression, Type as PhysicalType};
+use ::parquet::data_type::{ByteArray, ByteArrayType, DoubleType, Int64Type};
+use ::parquet::file::properties::WriterProperties;
+use ::parquet::file::writer::SerializedFileWriter;
+use ::parquet::schema::parser::parse_message_type;
+
+use crate::error::KcpdumpError;
+use crate::flows;
+use crate::packetlist;
+use crate::session::LoadedCapture;
+use crate::summary;
+
+/// Rows buffered before they are written out as a row group, which bounds
+/// memory use on large captures.
+const ROW_GROUP_ROWS: usize = 64 * 1024;
+
+/// Columns of a packets export, in the column layout of the packet list.
+pub const PACKETS_SCHEMA: &str = "message packets {
+    REQUIRED INT64 capture_id;
+    REQUIRED INT64 number;
+    REQUIRED DOUBLE time;
+    REQUIRED INT64 length;
+    REQUIRED BYTE_ARRAY protocol (UTF8);
+    REQUIRED BYTE_ARRAY source (UTF8);
+    REQUIRED BYTE_ARRAY destination (UTF8);
+    REQUIRED BYTE_ARRAY info (UTF8);
+}";
+
+/// Columns of a flows export; times are seconds since the epoch.
+pub const FLOWS_SCHEMA: &str = "message flows {
+    REQUIRED INT64 capture_id;
+    REQUIRED INT64 stream;
+    REQUIRED INT64 protocol;
+    REQUIRED BYTE_ARRAY address_a (UTF8);
+    REQUIRED INT64 port_a;
+    REQUIRED BYTE_ARRAY address_b (UTF8);
+    REQUIRED INT64 port_b;
+    REQUIRED INT64 packets;
+    REQUIRED INT64 bytes;
+    REQUIRED DOUBLE first_seen;
+    REQUIRED DOUBLE last_seen;
+}";
+
+/// Parquet Value
+/// One value of a row, matching the physical type of its column.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParquetValue {
+    Int64(i64),
+    Double(f64),
+    Utf8(String),
+}
+
+impl From<i64> for ParquetValue {
+    fn from(value: i64) -> Self {
+        ParquetValue::Int64(value)
+    }
+}
+
+impl From<f64> for ParquetValue {
+    fn from(value: f64) -> Self {
+        ParquetValue::Double(value)
+    }
+}
+
+impl From<String> for ParquetValue {
+    fn from(value: String) -> Self {
+        ParquetValue::Utf8(value)
+    }
+}
+
+/// Buffered values of one column of the current row group.
+enum ColumnBuffer {
+    Int64(Vec<i64>),
+    Double(Vec<f64>),
+    Utf8(Vec<ByteArray>),
+}
+
+impl ColumnBuffer {
+    fn accepts(&self, value: &ParquetValue) -> bool {
+        matches!(
+            (self, value),
+            (ColumnBuffer::Int64(_), ParquetValue::Int64(_))
+                | (ColumnBuffer::Double(_), ParquetValue::Double(_))
+                | (ColumnBuffer::Utf8(_), ParquetValue::Utf8(_))
+        )
+    }
+
+    fn push(&mut self, value: ParquetValue) {
+        match (self, value) {
+            (ColumnBuffer::Int64(values), ParquetValue::Int64(value)) => values.push(value),
+            (ColumnBuffer::Double(values), ParquetValue::Double(value)) => values.push(value),
+            (ColumnBuffer::Utf8(values), ParquetValue::Utf8(value)) => {
+                values.push(ByteArray::from(value.into_bytes()))
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Parquet Writer
+/// Writes the rows of a table to a Parquet file as Snappy compressed row
+/// groups of up to `ROW_GROUP_ROWS` rows.
+pub struct ParquetWriter {
+    writer: SerializedFileWriter<File>,
+    columns: Vec<ColumnBuffer>,
+    rows: usize,
+}
+
+impl ParquetWriter {
+    /// Creates the file at `path`, replacing it, for a table described in
+    /// Parquet's message type syntax. Columns are REQUIRED INT64, DOUBLE or
+    /// UTF8 BYTE_ARRAY.
+    pub fn create(path: &str, schema: &str) -> Result<Self, KcpdumpError> {
+        let schema = Arc::new(parse_message_type(schema)?);
+        let columns = schema
+            .get_fields()
+            .iter()
+            .map(|field| match field.get_physical_type() {
+                PhysicalType::INT64 => Ok(ColumnBuffer::Int64(Vec::new())),
+                PhysicalType::DOUBLE => Ok(ColumnBuffer::Double(Vec::new())),
+                PhysicalType::BYTE_ARRAY => Ok(ColumnBuffer::Utf8(Vec::new())),
+                other => Err(KcpdumpError::Other(format!(
+                    "Unsupported Parquet column type {} of {}",
+                    other,
+                    field.name()
+                ))),
+            })
+            .collect::<Result<_, _>>()?;
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let file = File::create(path).map_err(|e| KcpdumpError::file(path, e))?;
+        Ok(ParquetWriter {
+            writer: SerializedFileWriter::new(file, schema, Arc::new(properties))?,
+            columns,
+            rows: 0,
+        })
+    }
+
+    /// Appends a row with one value per column, in schema order.
+    pub fn push(&mut self, row: Vec<ParquetValue>) -> Result<(), KcpdumpError> {
+        if row.len() != self.columns.len()
+            || !self
+                .columns
+                .iter()
+                .zip(&row)
+                .all(|(column, value)| column.accepts(value))
+        {
+            return Err(KcpdumpError::Other(
+                "Parquet row does not match the table's columns".to_string(),
+            ));
+        }
+        for (column, value) in self.columns.iter_mut().zip(row) {
+            column.push(value);
+        }
+        self.rows += 1;
+        if self.rows == ROW_GROUP_ROWS {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the remaining rows and the file footer.
+    pub fn finish(mut self) -> Result<(), KcpdumpError> {
+        self.flush()?;
+        self.writer.close()?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), KcpdumpError> {
+        if self.rows == 0 {
+            return Ok(());
+        }
+        let mut row_group = self.writer.next_row_group()?;
+        for column in &mut self.columns {
+            let Some(mut writer) = row_group.next_column()? else {
+                break;
+            };
+            match column {
+                ColumnBuffer::Int64(values) => {
+                    writer
+                        .typed::<Int64Type>()
+                        .write_batch(values, None, None)?;
+                    values.clear();
+                }
+                ColumnBuffer::Double(values) => {
+                    writer
+                        .typed::<DoubleType>()
+                        .write_batch(values, None, None)?;
+                    values.clear();
+                }
+                ColumnBuffer::Utf8(values) => {
+                    writer
+                        .typed::<ByteArrayType>()
+                        .write_batch(values, None, None)?;
+                    values.clear();
+                }
+            }
+            writer.close()?;
+        }
+        row_group.close()?;
+        self.rows = 0;
+        Ok(())
+    }
+}
+
+/// Writes the packet summaries of the captures to `path` as laid out in
+/// `PACKETS_SCHEMA`, one row per packet.
+pub fn export_packets(captures: &[Arc<LoadedCapture>], path: &str) -> Result<(), KcpdumpError> {
+    let mut writer = ParquetWriter::create(path, PACKETS_SCHEMA)?;
+    let link_types = packetlist::link_types(captures);
+    for item in packetlist::merged_packets(captures) {
+        let (capture_id, number, packet) = item?;
+        let summary = summary::summarize_link(link_types[&capture_id], &packet.data);
+        writer.push(vec![
+            i64::from(capture_id).into(),
+            (number as i64).into(),
+            packet.header.timestamp.as_secs_f64().into(),
+            i64::from(packet.header.orig_len).into(),
+            summary.protocol.into(),
+            summary.source.into(),
+            summary.destination.into(),
+            summary.info.into(),
+        ])?;
+    }
+    writer.finish()
+}
+
+/// Writes the conversation records of the captures to `path` as laid out in
+/// `FLOWS_SCHEMA`, one row per flow.
+pub fn export_flows(captures: &[Arc<LoadedCapture>], path: &str) -> Result<(), KcpdumpError> {
+    let records = flows::flow_records(captures)?;
+    let mut writer = ParquetWriter::create(path, FLOWS_SCHEMA)?;
+    for record in records {
+        writer.push(vec![
+            i64::from(record.capture_id).into(),
+            i64::from(record.stream).into(),
+            i64::from(record.key.protocol).into(),
+            record.key.address_a.to_string().into(),
+            i64::from(record.key.port_a).into(),
+            record.key.address_b.to_string().into(),
+            i64::from(record.key.port_b).into(),
+            (record.packets as i64).into(),
+            (record.bytes as i64).into(),
+            record.first_seen.into(),
+            record.last_seen.into(),
+        ])?;
+    }
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::parquet::file::reader::{FileReader, SerializedFileReader};
+    use ::parquet::record::RowAccessor;
+
+    fn temp_path(name: &str) -> String {
+        let name = format!("kcpdump-{}-{}.parquet", name, std::process::id());
+        std::env::temp_dir()
+            .join(name)
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn test_row_groups() {
+        let path = temp_path("rows");
+        let schema = "message t { REQUIRED INT64 n; REQUIRED BYTE_ARRAY s (UTF8); }";
+        let mut writer = ParquetWriter::create(&path, schema).unwrap();
+        for n in 0..ROW_GROUP_ROWS + 2 {
+            writer
+                .push(vec![(n as i64).into(), format!("row {}", n).into()])
+                .unwrap();
+        }
+        assert!(
+            writer
+                .push(vec![1.0.into(), "x".to_string().into()])
+                .is_err()
+        );
+        assert!(writer.push(vec![1i64.into()]).is_err());
+        writer.finish().unwrap();
+
+        let reader = SerializedFileReader::try_from(path.as_str()).unwrap();
+        let metadata = reader.metadata();
+        assert_eq!(
+            metadata.file_metadata().num_rows(),
+            ROW_GROUP_ROWS as i64 + 2
+        );
+        assert_eq!(metadata.num_row_groups(), 2);
+        let last = reader.get_row_iter(None).unwrap().last().unwrap().unwrap();
+        assert_eq!(last.get_long(0).unwrap(), ROW_GROUP_ROWS as i64 + 1);
+        assert_eq!(last.get_string(1).unwrap(), "row 65537");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_unsupported_column_type() {
+        let path = temp_path("boolean");
+        let schema = "message t { REQUIRED BOOLEAN flag; }";
+        assert!(ParquetWriter::create(&path, schema).is_err());
+    }
+}
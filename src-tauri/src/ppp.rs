@@ -0,0 +1,282 @@
+use std::net::Ipv4Addr;
+
+use crate::error::KcpdumpError;
+use crate::packet::{IPv4Packet, IPv6Packet};
+use crate::summary::{self, PacketSummary};
+
+/// Link types of PPP and Cisco HDLC captures from serial and WAN links.
+pub const LINKTYPE_PPP: u32 = 9;
+pub const LINKTYPE_PPP_SERIAL: u32 = 50;
+pub const LINKTYPE_C_HDLC: u32 = 104;
+
+pub const PPP_IPV4: u16 = 0x0021;
+pub const PPP_IPV6: u16 = 0x0057;
+pub const PPP_IPCP: u16 = 0x8021;
+pub const PPP_LCP: u16 = 0xc021;
+
+/// Network Protocol
+/// The protocol carried by a PPP or Cisco HDLC frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkProtocol {
+    IPv4,
+    IPv6,
+    Lcp,
+    Ipcp,
+    /// Cisco Serial Line ARP keepalives.
+    Slarp,
+    Unknown(u16),
+}
+
+/// Ppp Frame
+/// A PPP or Cisco HDLC frame with its framing stripped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PppFrame {
+    pub protocol: NetworkProtocol,
+    pub payload: Vec<u8>,
+}
+
+impl PppFrame {
+    /// Decodes a frame of the given link type. PPP frames may omit the
+    /// address and control bytes and compress the protocol field to one byte.
+    pub fn decode(link_type: u32, data: &[u8]) -> Result<Self, KcpdumpError> {
+        if link_type == LINKTYPE_C_HDLC {
+            if data.len() < 4 {
+                return Err(KcpdumpError::malformed(
+                    0,
+                    "Data too short for Cisco HDLC frame",
+                ));
+            }
+            let protocol = match u16::from_be_bytes([data[2], data[3]]) {
+                0x0800 => NetworkProtocol::IPv4,
+                0x86dd => NetworkProtocol::IPv6,
+                0x8035 => NetworkProtocol::Slarp,
+                other => NetworkProtocol::Unknown(other),
+            };
+            return Ok(PppFrame {
+                protocol,
+                payload: data[4..].to_vec(),
+            });
+        }
+
+        let data = data.strip_prefix(&[0xff, 0x03]).unwrap_or(data);
+        let (protocol, header_len) = match data {
+            [first, ..] if first & 0x01 != 0 => (u16::from(*first), 1),
+            [first, second, ..] => (u16::from_be_bytes([*first, *second]), 2),
+            _ => return Err(KcpdumpError::malformed(0, "Data too short for PPP frame")),
+        };
+        let protocol = match protocol {
+            PPP_IPV4 => NetworkProtocol::IPv4,
+            PPP_IPV6 => NetworkProtocol::IPv6,
+            PPP_LCP => NetworkProtocol::Lcp,
+            PPP_IPCP => NetworkProtocol::Ipcp,
+            other => NetworkProtocol::Unknown(other),
+        };
+        Ok(PppFrame {
+            protocol,
+            payload: data[header_len..].to_vec(),
+        })
+    }
+}
+
+/// Control Packet
+/// An LCP or IPCP negotiation packet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ControlPacket {
+    pub code: u8,
+    pub identifier: u8,
+    /// Configuration options as (type, value) pairs, for the Configure codes.
+    pub options: Vec<(u8, Vec<u8>)>,
+    pub data: Vec<u8>,
+}
+
+impl TryFrom<&[u8]> for ControlPacket {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 4 {
+            return Err(KcpdumpError::malformed(
+                0,
+                "Data too short for PPP control packet",
+            ));
+        }
+        let length = u16::from_be_bytes([data[2], data[3]]) as usize;
+        let body = data
+            .get(4..length.max(4))
+            .ok_or(KcpdumpError::malformed(4, "PPP control packet truncated"))?;
+
+        let mut options = Vec::new();
+        if (1..=4).contains(&data[0]) {
+            let mut rest = body;
+            while let [option_type, option_len, ..] = *rest {
+                let option_len = usize::from(option_len);
+                if option_len < 2 || option_len > rest.len() {
+                    return Err(KcpdumpError::malformed(
+                        4 + body.len() - rest.len() + 1,
+                        "Invalid PPP configuration option length",
+                    ));
+                }
+                options.push((option_type, rest[2..option_len].to_vec()));
+                rest = &rest[option_len..];
+            }
+        }
+        Ok(ControlPacket {
+            code: data[0],
+            identifier: data[1],
+            options,
+            data: body.to_vec(),
+        })
+    }
+}
+
+impl ControlPacket {
+    pub fn code_name(&self) -> &'static str {
+        match self.code {
+            1 => "Configuration Request",
+            2 => "Configuration Ack",
+            3 => "Configuration Nak",
+            4 => "Configuration Reject",
+            5 => "Termination Request",
+            6 => "Termination Ack",
+            7 => "Code Reject",
+            8 => "Protocol Reject",
+            9 => "Echo Request",
+            10 => "Echo Reply",
+            11 => "Discard Request",
+            _ => "Unknown",
+        }
+    }
+
+    /// IP address option of an IPCP Configure packet.
+    pub fn ip_address(&self) -> Option<Ipv4Addr> {
+        self.options
+            .iter()
+            .find(|(option_type, _)| *option_type == 3)
+            .and_then(|(_, value)| <[u8; 4]>::try_from(value.as_slice()).ok())
+            .map(Ipv4Addr::from)
+    }
+}
+
+/// Summarizes a PPP or Cisco HDLC frame for the packet list.
+pub fn summarize(link_type: u32, frame: &[u8]) -> PacketSummary {
+    let mut summary = PacketSummary {
+        source: String::new(),
+        destination: String::new(),
+        protocol: if link_type == LINKTYPE_C_HDLC {
+            "CHDLC".to_string()
+        } else {
+            "PPP".to_string()
+        },
+        length: frame.len(),
+        info: String::new(),
+        flow: None,
+    };
+    let ppp = match PppFrame::decode(link_type, frame) {
+        Ok(ppp) => ppp,
+        Err(e) => {
+            summary.protocol = "Malformed".to_string();
+            summary.info = e.to_string();
+            return summary;
+        }
+    };
+
+    match ppp.protocol {
+        NetworkProtocol::IPv4 => match IPv4Packet::try_from(ppp.payload.as_slice()) {
+            Ok(ipv4_packet) => summary::summarize_ipv4(&ipv4_packet, &mut summary),
+            Err(e) => summary.info = e.to_string(),
+        },
+        NetworkProtocol::IPv6 => match IPv6Packet::try_from(ppp.payload.as_slice()) {
+            Ok(ipv6_packet) => {
+                summary.source = ipv6_packet.source_ip.to_string();
+                summary.destination = ipv6_packet.dest_ip.to_string();
+                summary.protocol = "IPv6".to_string();
+                summary.info = format!("IPv6 next header {}", ipv6_packet.next_header);
+            }
+            Err(e) => summary.info = e.to_string(),
+        },
+        NetworkProtocol::Lcp | NetworkProtocol::Ipcp => {
+            match ControlPacket::try_from(ppp.payload.as_slice()) {
+                Ok(control) => {
+                    summary.protocol = if ppp.protocol == NetworkProtocol::Lcp {
+                        "PPP LCP".to_string()
+                    } else {
+                        "PPP IPCP".to_string()
+                    };
+                    summary.info = format!("{}, id {}", control.code_name(), control.identifier);
+                    if let Some(address) = control.ip_address() {
+                        summary.info += &format!(", IP {}", address);
+                    }
+                }
+                Err(e) => summary.info = e.to_string(),
+            }
+        }
+        NetworkProtocol::Slarp => {
+            summary.protocol = "SLARP".to_string();
+            summary.info = "Serial Line ARP".to_string();
+        }
+        NetworkProtocol::Unknown(protocol) => {
+            summary.info = format!("Protocol 0x{:04x}", protocol);
+        }
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IPV4_UDP: [u8; 28] = [
+        0x45, 0x00, 0x00, 0x1c, 0x00, 0x01, 0x00, 0x00, 0x40, 0x11, 0x00, 0x00, 0x0a, 0x00, 0x00,
+        0x01, 0x0a, 0x00, 0x00, 0x02, 0x04, 0x00, 0x00, 0x35, 0x00, 0x08, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn test_ppp_framing() {
+        let mut serial = vec![0xff, 0x03, 0x00, 0x21];
+        serial.extend_from_slice(&IPV4_UDP);
+        let frame = PppFrame::decode(LINKTYPE_PPP_SERIAL, &serial).unwrap();
+        assert_eq!(frame.protocol, NetworkProtocol::IPv4);
+        assert_eq!(frame.payload, IPV4_UDP);
+
+        // Address/control field and protocol compression.
+        let mut compressed = vec![0x21];
+        compressed.extend_from_slice(&IPV4_UDP);
+        let summary = summarize(LINKTYPE_PPP, &compressed);
+        assert_eq!(summary.protocol, "UDP");
+        assert_eq!(summary.source, "10.0.0.1");
+        assert_eq!(summary.info, "1024 → 53 Len=0");
+
+        assert_eq!(summarize(LINKTYPE_PPP, &[0xff, 0x03]).protocol, "Malformed");
+    }
+
+    #[test]
+    fn test_control_packets() {
+        // LCP Configure-Request with MRU 1500 and magic number.
+        let lcp = [
+            0xff, 0x03, 0xc0, 0x21, 0x01, 0x07, 0x00, 0x0e, 0x01, 0x04, 0x05, 0xdc, 0x05, 0x06,
+            0x12, 0x34, 0x56, 0x78,
+        ];
+        let frame = PppFrame::decode(LINKTYPE_PPP, &lcp).unwrap();
+        let control = ControlPacket::try_from(frame.payload.as_slice()).unwrap();
+        assert_eq!(control.options.len(), 2);
+        assert_eq!(control.options[0], (1, vec![0x05, 0xdc]));
+        let summary = summarize(LINKTYPE_PPP, &lcp);
+        assert_eq!(summary.protocol, "PPP LCP");
+        assert_eq!(summary.info, "Configuration Request, id 7");
+
+        let ipcp = [
+            0x80, 0x21, 0x02, 0x01, 0x00, 0x0a, 0x03, 0x06, 0xc0, 0xa8, 0x01, 0x01,
+        ];
+        let summary = summarize(LINKTYPE_PPP, &ipcp);
+        assert_eq!(summary.protocol, "PPP IPCP");
+        assert_eq!(summary.info, "Configuration Ack, id 1, IP 192.168.1.1");
+    }
+
+    #[test]
+    fn test_cisco_hdlc() {
+        let mut frame = vec![0x0f, 0x00, 0x08, 0x00];
+        frame.extend_from_slice(&IPV4_UDP);
+        assert_eq!(summarize(LINKTYPE_C_HDLC, &frame).protocol, "UDP");
+        let keepalive = [0x8f, 0x00, 0x80, 0x35, 0x00, 0x00, 0x00, 0x02];
+        assert_eq!(summarize(LINKTYPE_C_HDLC, &keepalive).protocol, "SLARP");
+    }
+}
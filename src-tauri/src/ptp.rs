@@ -0,0 +1,464 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::collections::HashMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc;
+
+use crate::dissect::{Dissector, FieldInfo, FieldType, FieldValue, FieldValues, PacketLayers};
+use crate::error::KcpdumpError;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::packetlist;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::session::LoadedCapture;
+
+/// UDP ports of PTP event and general messages.
+pub const PTP_EVENT_PORT: u16 = 319;
+pub const PTP_GENERAL_PORT: u16 = 320;
+
+/// EtherType of PTP carried directly over Ethernet.
+pub const ETHERTYPE_PTP: u16 = 0x88f7;
+
+const HEADER_LEN: usize = 34;
+const FLAG_TWO_STEP: u16 = 0x0200;
+
+/// Message Type
+/// The messageType field of a PTPv2 header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageType {
+    Sync,
+    DelayReq,
+    PdelayReq,
+    PdelayResp,
+    FollowUp,
+    DelayResp,
+    PdelayRespFollowUp,
+    Announce,
+    Signaling,
+    Management,
+    Unknown(u8),
+}
+
+impl From<u8> for MessageType {
+    fn from(value: u8) -> Self {
+        match value {
+            0x0 => MessageType::Sync,
+            0x1 => MessageType::DelayReq,
+            0x2 => MessageType::PdelayReq,
+            0x3 => MessageType::PdelayResp,
+            0x8 => MessageType::FollowUp,
+            0x9 => MessageType::DelayResp,
+            0xa => MessageType::PdelayRespFollowUp,
+            0xb => MessageType::Announce,
+            0xc => MessageType::Signaling,
+            0xd => MessageType::Management,
+            other => MessageType::Unknown(other),
+        }
+    }
+}
+
+impl From<MessageType> for u8 {
+    fn from(message_type: MessageType) -> Self {
+        match message_type {
+            MessageType::Sync => 0x0,
+            MessageType::DelayReq => 0x1,
+            MessageType::PdelayReq => 0x2,
+            MessageType::PdelayResp => 0x3,
+            MessageType::FollowUp => 0x8,
+            MessageType::DelayResp => 0x9,
+            MessageType::PdelayRespFollowUp => 0xa,
+            MessageType::Announce => 0xb,
+            MessageType::Signaling => 0xc,
+            MessageType::Management => 0xd,
+            MessageType::Unknown(value) => value,
+        }
+    }
+}
+
+impl MessageType {
+    pub fn name(self) -> &'static str {
+        match self {
+            MessageType::Sync => "Sync Message",
+            MessageType::DelayReq => "Delay_Req Message",
+            MessageType::PdelayReq => "Peer_Delay_Req Message",
+            MessageType::PdelayResp => "Peer_Delay_Resp Message",
+            MessageType::FollowUp => "Follow_Up Message",
+            MessageType::DelayResp => "Delay_Resp Message",
+            MessageType::PdelayRespFollowUp => "Peer_Delay_Resp_Follow_Up Message",
+            MessageType::Announce => "Announce Message",
+            MessageType::Signaling => "Signaling Message",
+            MessageType::Management => "Management Message",
+            MessageType::Unknown(_) => "Unknown Message",
+        }
+    }
+}
+
+/// Port Identity
+/// A clock identity and the number of one of its ports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PortIdentity {
+    pub clock_identity: u64,
+    pub port_number: u16,
+}
+
+impl PortIdentity {
+    fn parse(data: &[u8]) -> Self {
+        PortIdentity {
+            clock_identity: u64::from_be_bytes(data[..8].try_into().unwrap_or_default()),
+            port_number: u16::from_be_bytes([data[8], data[9]]),
+        }
+    }
+
+    /// Clock identity in the colon separated notation used by PTP tools.
+    pub fn clock(&self) -> String {
+        let bytes = self.clock_identity.to_be_bytes();
+        let parts: Vec<_> = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+        parts.join(":")
+    }
+}
+
+/// PTP Timestamp
+/// A 48-bit seconds and 32-bit nanoseconds timestamp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PtpTimestamp {
+    pub seconds: u64,
+    pub nanoseconds: u32,
+}
+
+impl PtpTimestamp {
+    fn parse(data: &[u8]) -> Self {
+        PtpTimestamp {
+            seconds: data[..6].iter().fold(0, |n, &b| n << 8 | u64::from(b)),
+            nanoseconds: u32::from_be_bytes([data[6], data[7], data[8], data[9]]),
+        }
+    }
+
+    pub fn as_nanos(&self) -> i128 {
+        i128::from(self.seconds) * 1_000_000_000 + i128::from(self.nanoseconds)
+    }
+}
+
+/// PTP Message
+/// A PTPv2 header and the timestamps of its body.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PtpMessage {
+    pub message_type: MessageType,
+    pub version: u8,
+    pub domain: u8,
+    pub flags: u16,
+    /// Correction field in units of 2^-16 nanoseconds.
+    pub correction: i64,
+    pub source: PortIdentity,
+    pub sequence_id: u16,
+    pub log_message_interval: i8,
+    /// Origin, precise origin or receive timestamp, depending on the message type.
+    pub timestamp: Option<PtpTimestamp>,
+    /// Port whose Delay_Req or Pdelay_Req is answered.
+    pub requesting_port: Option<PortIdentity>,
+}
+
+impl TryFrom<&[u8]> for PtpMessage {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < HEADER_LEN {
+            return Err(KcpdumpError::malformed(0, "Data too short for PTP message"));
+        }
+        let version = data[1] & 0x0f;
+        if version != 2 {
+            return Err(KcpdumpError::malformed(1, "Unsupported PTP version"));
+        }
+        let message_type = MessageType::from(data[0] & 0x0f);
+        let body = &data[HEADER_LEN..];
+        let timestamp = match message_type {
+            MessageType::Unknown(_) | MessageType::Signaling | MessageType::Management => None,
+            _ => body.get(..10).map(PtpTimestamp::parse),
+        };
+        let requesting_port = match message_type {
+            MessageType::DelayResp | MessageType::PdelayResp | MessageType::PdelayRespFollowUp => {
+                body.get(10..20).map(PortIdentity::parse)
+            }
+            _ => None,
+        };
+        Ok(PtpMessage {
+            message_type,
+            version,
+            domain: data[4],
+            flags: u16::from_be_bytes([data[6], data[7]]),
+            correction: i64::from_be_bytes(data[8..16].try_into().unwrap_or_default()),
+            source: PortIdentity::parse(&data[20..30]),
+            sequence_id: u16::from_be_bytes([data[30], data[31]]),
+            log_message_interval: data[33] as i8,
+            timestamp,
+            requesting_port,
+        })
+    }
+}
+
+impl PtpMessage {
+    pub fn two_step(&self) -> bool {
+        self.flags & FLAG_TWO_STEP != 0
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn correction_nanos(&self) -> i128 {
+        i128::from(self.correction >> 16)
+    }
+
+    /// Describes the message for the packet list.
+    pub fn describe(&self) -> String {
+        format!("{} Seq={}", self.message_type.name(), self.sequence_id)
+    }
+}
+
+/// PTP Dissector
+pub struct PtpDissector;
+
+const PTP_FIELDS: &[FieldInfo] = &[
+    FieldInfo::new("ptp.v2.messagetype", FieldType::UInt, "Message type"),
+    FieldInfo::new("ptp.v2.domainnumber", FieldType::UInt, "Domain number"),
+    FieldInfo::new("ptp.v2.flags.twostep", FieldType::Bool, "Two-step clock"),
+    FieldInfo::new(
+        "ptp.v2.clockidentity",
+        FieldType::Text,
+        "Source clock identity",
+    ),
+    FieldInfo::new("ptp.v2.sourceportid", FieldType::UInt, "Source port number"),
+    FieldInfo::new("ptp.v2.sequenceid", FieldType::UInt, "Sequence ID"),
+];
+
+impl Dissector for PtpDissector {
+    fn protocol(&self) -> &'static str {
+        "ptp"
+    }
+
+    fn description(&self) -> &'static str {
+        "Precision Time Protocol (IEEE 1588)"
+    }
+
+    fn fields(&self) -> &'static [FieldInfo] {
+        PTP_FIELDS
+    }
+
+    fn dissect(&self, layers: &PacketLayers, values: &mut FieldValues) {
+        let Some(ptp) = &layers.ptp else {
+            return;
+        };
+        values.push("ptp", FieldValue::Protocol);
+        values.push(
+            "ptp.v2.messagetype",
+            FieldValue::UInt(u8::from(ptp.message_type).into()),
+        );
+        values.push("ptp.v2.domainnumber", FieldValue::UInt(ptp.domain.into()));
+        values.push("ptp.v2.flags.twostep", FieldValue::Bool(ptp.two_step()));
+        values.push("ptp.v2.clockidentity", FieldValue::Text(ptp.source.clock()));
+        values.push(
+            "ptp.v2.sourceportid",
+            FieldValue::UInt(ptp.source.port_number.into()),
+        );
+        values.push(
+            "ptp.v2.sequenceid",
+            FieldValue::UInt(ptp.sequence_id.into()),
+        );
+    }
+}
+
+/// PTP Offset Sample
+/// One end-to-end delay measurement between a master and a slave port.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PtpOffsetSample {
+    pub capture_id: u32,
+    /// Frame number of the Delay_Resp completing the measurement.
+    pub number: usize,
+    /// Seconds since the epoch.
+    pub time: f64,
+    pub domain: u8,
+    pub master: String,
+    pub slave: String,
+    pub sequence_id: u16,
+    /// Estimated offset of the slave from the master, in nanoseconds.
+    pub offset_ns: f64,
+    /// Estimated mean path delay, in nanoseconds.
+    pub path_delay_ns: f64,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// A two-step Sync waiting for its Follow_Up.
+struct PendingSync {
+    /// Capture time of the Sync, standing in for t2.
+    received: i128,
+    correction: i128,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Pairs Sync/Follow_Up with Delay_Req/Delay_Resp exchanges and estimates
+/// offset and path delay per master/slave pair. The slave's receive and send
+/// times (t2, t3) are taken from the capture timestamps, so the estimates are
+/// exact only when the capture point is next to the slave.
+pub fn ptp_offsets(captures: &[Arc<LoadedCapture>]) -> Result<Vec<PtpOffsetSample>, KcpdumpError> {
+    let mut samples = Vec::new();
+    for capture in captures {
+        let mut pending_syncs: HashMap<(PortIdentity, u16), PendingSync> = HashMap::new();
+        let mut last_sync: HashMap<(u8, PortIdentity), (i128, i128)> = HashMap::new();
+        let mut delay_requests: HashMap<(PortIdentity, u16), i128> = HashMap::new();
+
+        let single = std::slice::from_ref(capture);
+        for item in packetlist::merged_packets(single) {
+            let (capture_id, number, packet) = item?;
+            let Some(ptp) = PacketLayers::decode_link(number, capture.header.network, &packet).ptp else {
+                continue;
+            };
+            let captured = i128::from(packet.header.timestamp.as_nanos());
+            match ptp.message_type {
+                MessageType::Sync => {
+                    if ptp.two_step() {
+                        pending_syncs.insert(
+                            (ptp.source, ptp.sequence_id),
+                            PendingSync {
+                                received: captured,
+                                correction: ptp.correction_nanos(),
+                            },
+                        );
+                    } else if let Some(timestamp) = ptp.timestamp {
+                        let origin = timestamp.as_nanos() + ptp.correction_nanos();
+                        last_sync.insert((ptp.domain, ptp.source), (origin, captured));
+                    }
+                }
+                MessageType::FollowUp => {
+                    if let Some(sync) = pending_syncs.remove(&(ptp.source, ptp.sequence_id))
+                        && let Some(timestamp) = ptp.timestamp
+                    {
+                        let origin =
+                            timestamp.as_nanos() + sync.correction + ptp.correction_nanos();
+                        last_sync.insert((ptp.domain, ptp.source), (origin, sync.received));
+                    }
+                }
+                MessageType::DelayReq => {
+                    delay_requests.insert((ptp.source, ptp.sequence_id), captured);
+                }
+                MessageType::DelayResp => {
+                    let (Some(slave), Some(timestamp)) = (ptp.requesting_port, ptp.timestamp)
+                    else {
+                        continue;
+                    };
+                    let Some(sent) = delay_requests.remove(&(slave, ptp.sequence_id)) else {
+                        continue;
+                    };
+                    let Some(&(origin, received)) = last_sync.get(&(ptp.domain, ptp.source)) else {
+                        continue;
+                    };
+                    let arrived = timestamp.as_nanos() - ptp.correction_nanos();
+                    let master_to_slave = received - origin;
+                    let slave_to_master = arrived - sent;
+                    samples.push(PtpOffsetSample {
+                        capture_id,
+                        number,
+                        time: packet.header.timestamp.as_secs_f64(),
+                        domain: ptp.domain,
+                        master: ptp.source.clock(),
+                        slave: slave.clock(),
+                        sequence_id: ptp.sequence_id,
+                        offset_ns: (master_to_slave - slave_to_master) as f64 / 2.0,
+                        path_delay_ns: (master_to_slave + slave_to_master) as f64 / 2.0,
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cap::{PcapPacket, PcapPacketHeader};
+    use crate::timefmt::Timestamp;
+
+    const MASTER: u64 = 0x001b_19ff_fe00_0001;
+    const SLAVE: u64 = 0x001b_19ff_fe00_0002;
+
+    fn message(message_type: u8, clock: u64, sequence: u16, flags: u16, body: &[u8]) -> Vec<u8> {
+        let mut data = vec![message_type, 0x02];
+        data.extend_from_slice(&((HEADER_LEN + body.len()) as u16).to_be_bytes());
+        data.extend_from_slice(&[0, 0]);
+        data.extend_from_slice(&flags.to_be_bytes());
+        data.extend_from_slice(&[0; 12]);
+        data.extend_from_slice(&clock.to_be_bytes());
+        data.extend_from_slice(&1u16.to_be_bytes());
+        data.extend_from_slice(&sequence.to_be_bytes());
+        data.extend_from_slice(&[0, 0]);
+        data.extend_from_slice(body);
+        data
+    }
+
+    fn timestamp(seconds: u64, nanoseconds: u32) -> Vec<u8> {
+        let mut data = seconds.to_be_bytes()[2..].to_vec();
+        data.extend_from_slice(&nanoseconds.to_be_bytes());
+        data
+    }
+
+    /// Ethernet frame with EtherType 0x88F7 carrying `payload`.
+    fn frame(ts_sec: u32, ts_usec: u32, payload: Vec<u8>) -> PcapPacket {
+        let mut data = vec![0x01, 0x1b, 0x19, 0x00, 0x00, 0x00, 0x02, 0, 0, 0, 0, 0x01];
+        data.extend_from_slice(&ETHERTYPE_PTP.to_be_bytes());
+        data.extend(payload);
+        PcapPacket {
+            header: PcapPacketHeader {
+                timestamp: Timestamp::from_micros(ts_sec, ts_usec),
+                incl_len: data.len() as u32,
+                orig_len: data.len() as u32,
+                link_type: None,
+            },
+            data,
+        }
+    }
+
+    #[test]
+    fn test_message() {
+        let data = message(0x9, MASTER, 7, 0, &{
+            let mut body = timestamp(100, 500);
+            body.extend_from_slice(&SLAVE.to_be_bytes());
+            body.extend_from_slice(&1u16.to_be_bytes());
+            body
+        });
+        let ptp = PtpMessage::try_from(data.as_slice()).unwrap();
+        assert_eq!(ptp.message_type, MessageType::DelayResp);
+        assert_eq!(ptp.sequence_id, 7);
+        assert_eq!(ptp.source.clock(), "00:1b:19:ff:fe:00:00:01");
+        assert_eq!(ptp.timestamp.unwrap().as_nanos(), 100_000_000_500);
+        assert_eq!(ptp.requesting_port.unwrap().clock_identity, SLAVE);
+        assert_eq!(ptp.describe(), "Delay_Resp Message Seq=7");
+        assert!(PtpMessage::try_from(&data[..20]).is_err());
+    }
+
+    #[test]
+    fn test_offsets() {
+        // The slave, next to the capture point, runs 50us ahead; the path delay is 10us.
+        let mut delay_resp = timestamp(1000, 20_000);
+        delay_resp.extend_from_slice(&SLAVE.to_be_bytes());
+        delay_resp.extend_from_slice(&1u16.to_be_bytes());
+        let packets = vec![
+            frame(
+                1000,
+                0,
+                message(0x0, MASTER, 1, FLAG_TWO_STEP, &timestamp(0, 0)),
+            ),
+            frame(
+                1000,
+                1,
+                message(0x8, MASTER, 1, 0, &timestamp(999, 999_940_000)),
+            ),
+            frame(1000, 60, message(0x1, SLAVE, 5, 0, &timestamp(0, 0))),
+            frame(1000, 80, message(0x9, MASTER, 5, 0, &delay_resp)),
+        ];
+        let capture = Arc::new(LoadedCapture::from_packets(1, "ptp.pcap", 1, &packets));
+        let samples = ptp_offsets(&[capture]).unwrap();
+        assert_eq!(samples.len(), 1);
+        let sample = &samples[0];
+        assert_eq!(sample.number, 4);
+        assert_eq!(sample.slave, "00:1b:19:ff:fe:00:00:02");
+        // t2 - t1 = 60us, t4 - t3 = -40us.
+        assert_eq!(sample.offset_ns, 50_000.0);
+        assert_eq!(sample.path_delay_ns, 10_000.0);
+    }
+}
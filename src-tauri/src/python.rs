@@ -0,0 +1,221 @@
+use pyo3::exceptions::{PyFileNotFoundError, PyIOError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict, PyList};
+
+use crate::cap::{self, PcapPacket};
+use crate::dissect::{DissectorRegistry, PacketLayers, ProtocolNode};
+use crate::error::KcpdumpError;
+use crate::summary;
+
+fn to_py_err(error: KcpdumpError) -> PyErr {
+    match error {
+        KcpdumpError::FileNotFound { .. } => PyFileNotFoundError::new_err(error.to_string()),
+        KcpdumpError::Io { .. } => PyIOError::new_err(error.to_string()),
+        _ => PyValueError::new_err(error.to_string()),
+    }
+}
+
+/// Capture
+/// A capture file read packet by packet, iterated from Python as
+/// `for packet in kcpdump.Capture(path)`. Packets are dissected as they are
+/// read, the same way `kcpdump_next_packet` of the C ABI does.
+#[pyclass(name = "Capture")]
+pub struct PyCapture {
+    runtime: tokio::runtime::Runtime,
+    capture: cap::Capture,
+    registry: DissectorRegistry,
+    number: usize,
+}
+
+impl PyCapture {
+    /// Opens the capture file at `path`.
+    pub fn open(path: &str) -> Result<Self, KcpdumpError> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| KcpdumpError::io("Failed to start the runtime", e))?;
+        let capture = runtime.block_on(cap::Capture::from_file(path))?;
+        Ok(PyCapture {
+            runtime,
+            capture,
+            registry: DissectorRegistry::default(),
+            number: 0,
+        })
+    }
+
+    /// Reads and dissects the next packet, or returns `None` at the end of
+    /// the capture.
+    pub fn read_packet(&mut self) -> Result<Option<PyPacket>, KcpdumpError> {
+        let Some(packet) = self.runtime.block_on(self.capture.next_packet())? else {
+            return Ok(None);
+        };
+        self.number += 1;
+        let link_type = packet.link_type(self.capture.header().network);
+        Ok(Some(PyPacket::new(
+            self.number,
+            link_type,
+            packet,
+            &self.registry,
+        )))
+    }
+}
+
+#[pymethods]
+impl PyCapture {
+    #[new]
+    fn py_new(path: &str) -> PyResult<Self> {
+        Self::open(path).map_err(to_py_err)
+    }
+
+    /// Link type of the capture file's packets.
+    #[getter]
+    fn link_type(&self) -> u32 {
+        self.capture.header().network
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self) -> PyResult<Option<PyPacket>> {
+        self.read_packet().map_err(to_py_err)
+    }
+}
+
+/// Packet
+/// One dissected packet of a `Capture`.
+#[pyclass(name = "Packet", frozen)]
+pub struct PyPacket {
+    /// Frame number, starting at 1.
+    #[pyo3(get)]
+    pub number: usize,
+    /// Capture time in seconds since the epoch.
+    #[pyo3(get)]
+    pub time: f64,
+    /// Length on the wire, which the captured data may fall short of.
+    #[pyo3(get)]
+    pub length: u32,
+    #[pyo3(get)]
+    pub link_type: u32,
+    #[pyo3(get)]
+    pub protocol: String,
+    #[pyo3(get)]
+    pub source: String,
+    #[pyo3(get)]
+    pub destination: String,
+    #[pyo3(get)]
+    pub info: String,
+    pub data: Vec<u8>,
+    pub tree: Vec<ProtocolNode>,
+}
+
+impl PyPacket {
+    fn new(number: usize, link_type: u32, packet: PcapPacket, registry: &DissectorRegistry) -> Self {
+        let tree = registry
+            .dissect(&PacketLayers::decode_link(number, link_type, &packet))
+            .tree();
+        let summary = summary::summarize_link(link_type, &packet.data);
+        PyPacket {
+            number,
+            time: packet.header.timestamp.as_secs_f64(),
+            length: packet.header.orig_len,
+            link_type,
+            protocol: summary.protocol,
+            source: summary.source,
+            destination: summary.destination,
+            info: summary.info,
+            data: packet.data,
+            tree,
+        }
+    }
+}
+
+#[pymethods]
+impl PyPacket {
+    /// The captured bytes.
+    #[getter]
+    fn data<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.data)
+    }
+
+    /// The dissection tree as a list of `{"protocol", "fields"}` dicts, with
+    /// `fields` a list of `{"name", "value"}` dicts in dissection order.
+    #[getter]
+    fn tree<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyList>> {
+        let tree = PyList::empty(py);
+        for node in &self.tree {
+            let fields = PyList::empty(py);
+            for field in &node.fields {
+                let entry = PyDict::new(py);
+                entry.set_item("name", &field.name)?;
+                entry.set_item("value", &field.value)?;
+                fields.append(entry)?;
+            }
+            let entry = PyDict::new(py);
+            entry.set_item("protocol", &node.protocol)?;
+            entry.set_item("fields", fields)?;
+            tree.append(entry)?;
+        }
+        Ok(tree)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<Packet {} {} {}>", self.number, self.protocol, self.info)
+    }
+}
+
+/// The `kcpdump` Python module.
+#[pymodule]
+#[pyo3(name = "kcpdump")]
+fn python_module(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyCapture>()?;
+    module.add_class::<PyPacket>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cap::{PcapPacketHeader, PcapWriter};
+    use crate::link::LINKTYPE_ETHERNET;
+    use crate::timefmt::Timestamp;
+
+    #[test]
+    fn test_read_packets() {
+        let mut data = vec![0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 1, 0x08, 0x00];
+        data.extend_from_slice(&[0x45, 0, 0, 28, 0, 0, 0, 0, 64, 17, 0, 0]);
+        data.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2]);
+        data.extend_from_slice(&[0x03, 0xe8, 0x00, 0x35, 0, 8, 0, 0]);
+        let packet = PcapPacket {
+            header: PcapPacketHeader {
+                timestamp: Timestamp::new(2, 500_000_000),
+                incl_len: data.len() as u32,
+                orig_len: data.len() as u32,
+                link_type: None,
+            },
+            data,
+        };
+        let name = format!("kcpdump-python-{}.pcap", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        let mut writer =
+            PcapWriter::new(std::fs::File::create(&path).unwrap(), LINKTYPE_ETHERNET, 65535)
+                .unwrap();
+        writer.write_packet(&packet).unwrap();
+        writer.write_packet(&packet).unwrap();
+        drop(writer);
+
+        let mut capture = PyCapture::open(&path.to_string_lossy()).unwrap();
+        let first = capture.read_packet().unwrap().unwrap();
+        assert_eq!((first.number, first.time, first.length), (1, 2.5, 42));
+        assert_eq!(first.data, packet.data);
+        assert!(first.tree.iter().any(|node| node.protocol == "udp"));
+        assert_eq!(capture.read_packet().unwrap().unwrap().number, 2);
+        assert!(capture.read_packet().unwrap().is_none());
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            PyCapture::open("missing.pcap"),
+            Err(KcpdumpError::FileNotFound { .. })
+        ));
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::{BTreeMap, HashMap};
+use std::net::{IpAddr, Ipv4Addr};
+
+use crate::dissect::PacketLayers;
+use crate::flows::FlowKey;
+use crate::packet::TcpFlags;
+
+/// Stream Direction
+/// Bytes sent by one endpoint of a TCP connection, in sequence order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StreamDirection {
+    pub data: Vec<u8>,
+    /// False if a gap was found; `data` then ends at the first missing byte.
+    pub complete: bool,
+    /// Offset into `data` and frame number of each segment that contributed
+    /// new bytes, in offset order.
+    pub frames: Vec<(usize, usize)>,
+}
+
+impl StreamDirection {
+    /// Frame number of the segment that carried the byte at `offset`.
+    pub fn frame_at(&self, offset: usize) -> Option<usize> {
+        let index = self.frames.partition_point(|&(start, _)| start <= offset);
+        index.checked_sub(1).map(|index| self.frames[index].1)
+    }
+}
+
+/// TCP Stream
+/// A reassembled TCP connection. The client is the endpoint that sent the
+/// first SYN, or the first segment seen when the handshake was not captured.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TcpStream {
+    pub key: FlowKey,
+    pub client: (IpAddr, u16),
+    pub server: (IpAddr, u16),
+    /// Frame number of the first segment.
+    pub first_number: usize,
+    pub client_data: StreamDirection,
+    pub server_data: StreamDirection,
+}
+
+#[derive(Default)]
+struct DirectionState {
+    /// Sequence number of the first payload byte.
+    base: Option<u32>,
+    /// Frame number and payload by offset from `base`; the first copy of a
+    /// range wins.
+    segments: BTreeMap<u64, (usize, Vec<u8>)>,
+}
+
+impl DirectionState {
+    fn add(&mut self, number: usize, seq: u32, syn: bool, payload: &[u8]) {
+        let base = *self
+            .base
+            .get_or_insert(if syn { seq.wrapping_add(1) } else { seq });
+        if payload.is_empty() {
+            return;
+        }
+        let start = if syn { seq.wrapping_add(1) } else { seq };
+        // Segments before the base are treated as wrapped into the past and dropped.
+        let offset = start.wrapping_sub(base);
+        if offset > u32::MAX / 2 {
+            return;
+        }
+        self.segments
+            .entry(u64::from(offset))
+            .or_insert_with(|| (number, payload.to_vec()));
+    }
+
+    fn finish(self) -> StreamDirection {
+        let mut data = Vec::new();
+        let mut frames = Vec::new();
+        for (offset, (number, segment)) in self.segments {
+            let end = offset + segment.len() as u64;
+            let have = data.len() as u64;
+            if offset > have {
+                return StreamDirection {
+                    data,
+                    complete: false,
+                    frames,
+                };
+            }
+            if end > have {
+                frames.push((data.len(), number));
+                data.extend_from_slice(&segment[(have - offset) as usize..]);
+            }
+        }
+        StreamDirection {
+            data,
+            complete: true,
+            frames,
+        }
+    }
+}
+
+struct StreamState {
+    client: (IpAddr, u16),
+    server: (IpAddr, u16),
+    first_number: usize,
+    client_state: DirectionState,
+    server_state: DirectionState,
+}
+
+/// Stream Reassembler
+/// Collects TCP payloads per connection. Feed packets in capture order and
+/// call `finish` to obtain the streams in order of first appearance.
+#[derive(Default)]
+pub struct StreamReassembler {
+    streams: HashMap<FlowKey, StreamState>,
+    order: Vec<FlowKey>,
+}
+
+impl StreamReassembler {
+    pub fn push(&mut self, layers: &PacketLayers) {
+        let (Some(ip), Some(tcp)) = (&layers.ipv4, &layers.tcp) else {
+            return;
+        };
+        let source = (IpAddr::V4(Ipv4Addr::from(ip.source_ip)), tcp.source_port);
+        let destination = (IpAddr::V4(Ipv4Addr::from(ip.dest_ip)), tcp.dest_port);
+        let key = FlowKey::new(ip.protocol, source, destination);
+        let syn = tcp.flags.contains(TcpFlags::SYN);
+
+        let stream = self.streams.entry(key).or_insert_with(|| {
+            self.order.push(key);
+            // A lone SYN+ACK means the server spoke first in this capture.
+            let (client, server) = if syn && tcp.flags.contains(TcpFlags::ACK) {
+                (destination, source)
+            } else {
+                (source, destination)
+            };
+            StreamState {
+                client,
+                server,
+                first_number: layers.number,
+                client_state: DirectionState::default(),
+                server_state: DirectionState::default(),
+            }
+        });
+        let direction = if source == stream.client {
+            &mut stream.client_state
+        } else {
+            &mut stream.server_state
+        };
+        direction.add(layers.number, tcp.sequence_number, syn, &tcp.payload);
+    }
+
+    pub fn finish(mut self) -> Vec<TcpStream> {
+        self.order
+            .into_iter()
+            .filter_map(|key| {
+                let state = self.streams.remove(&key)?;
+                Some(TcpStream {
+                    key,
+                    client: state.client,
+                    server: state.server,
+                    first_number: state.first_number,
+                    client_data: state.client_state.finish(),
+                    server_data: state.server_state.finish(),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_out_of_order_and_retransmitted_segments() {
+        let mut direction = DirectionState::default();
+        direction.add(1, 99, true, b"");
+        direction.add(2, 106, false, b"world");
+        direction.add(3, 100, false, b"hello ");
+        direction.add(4, 100, false, b"hello ");
+        direction.add(5, 103, false, b"lo wor");
+        let stream = direction.finish();
+        assert!(stream.complete);
+        assert_eq!(stream.data, b"hello world");
+        assert_eq!(stream.frames, [(0, 3), (6, 5), (9, 2)]);
+        assert_eq!(stream.frame_at(7), Some(5));
+    }
+
+    #[test]
+    fn test_gap_marks_stream_incomplete() {
+        let mut direction = DirectionState::default();
+        direction.add(1, 1000, false, b"abc");
+        direction.add(2, 1010, false, b"xyz");
+        let stream = direction.finish();
+        assert!(!stream.complete);
+        assert_eq!(stream.data, b"abc");
+    }
+
+    #[tokio::test]
+    async fn test_reassemble_sample_http() {
+        let capture = crate::session::LoadedCapture::load(1, "sample.pcap")
+            .await
+            .unwrap();
+        let mut reassembler = StreamReassembler::default();
+        for (index, packet) in capture.packets().enumerate() {
+            let packet = packet.unwrap();
+            reassembler.push(&PacketLayers::decode(index + 1, &packet));
+        }
+        let streams = reassembler.finish();
+        let http = streams.iter().find(|stream| stream.server.1 == 80).unwrap();
+        assert!(http.client_data.data.starts_with(b"GET /index.html"));
+        assert!(http.server_data.data.starts_with(b"HTTP/1.1 200 OK"));
+    }
+}
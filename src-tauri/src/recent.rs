@@ -0,0 +1,202 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use tokio::io;
+
+use crate::filter::PacketFilter;
+
+/// Number of captures remembered in the recent list.
+const MAX_RECENT: usize = 20;
+
+/// Column Layout
+/// One column of the packet list as arranged by the user.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnLayout {
+    pub id: String,
+    pub width: Option<u32>,
+    #[serde(default = "default_visible")]
+    pub visible: bool,
+}
+
+fn default_visible() -> bool {
+    true
+}
+
+/// Bookmark
+/// A marked packet with an optional note.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Bookmark {
+    pub number: usize,
+    #[serde(default)]
+    pub note: String,
+}
+
+/// View State
+/// Everything needed to resume the analysis of a capture.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ViewState {
+    pub display_filter: Option<PacketFilter>,
+    pub columns: Vec<ColumnLayout>,
+    pub bookmarks: Vec<Bookmark>,
+}
+
+/// Recent Capture
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentCapture {
+    pub path: String,
+    /// Seconds since the Unix epoch.
+    pub last_opened: i64,
+    #[serde(default)]
+    pub state: ViewState,
+}
+
+/// Recent Captures
+/// The most recently opened captures, newest first, persisted as JSON.
+#[derive(Debug)]
+pub struct RecentCaptures {
+    file: PathBuf,
+    entries: Mutex<Vec<RecentCapture>>,
+}
+
+impl RecentCaptures {
+    /// Loads the store from `file`. A missing or unreadable file yields an empty list.
+    pub fn load(file: PathBuf) -> Self {
+        let entries = fs::read(&file)
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+        RecentCaptures {
+            file,
+            entries: Mutex::new(entries),
+        }
+    }
+
+    pub fn list(&self) -> Vec<RecentCapture> {
+        self.entries.lock().unwrap().clone()
+    }
+
+    pub fn get(&self, path: &str) -> Option<RecentCapture> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|entry| entry.path == path)
+            .cloned()
+    }
+
+    /// Moves `path` to the front of the list, keeping its saved state.
+    pub fn touch(&self, path: &str) -> io::Result<()> {
+        self.update(path, |_| {})
+    }
+
+    pub fn save_state(&self, path: &str, state: ViewState) -> io::Result<()> {
+        self.update(path, |entry| entry.state = state)
+    }
+
+    pub fn remove(&self, path: &str) -> io::Result<bool> {
+        let mut entries = self.entries.lock().unwrap();
+        let count = entries.len();
+        entries.retain(|entry| entry.path != path);
+        let removed = entries.len() != count;
+        if removed {
+            self.persist(&entries)?;
+        }
+        Ok(removed)
+    }
+
+    fn update(&self, path: &str, apply: impl FnOnce(&mut RecentCapture)) -> io::Result<()> {
+        let mut entries = self.entries.lock().unwrap();
+        let mut entry = match entries.iter().position(|entry| entry.path == path) {
+            Some(index) => entries.remove(index),
+            None => RecentCapture {
+                path: path.to_string(),
+                last_opened: 0,
+                state: ViewState::default(),
+            },
+        };
+        entry.last_opened = chrono::Utc::now().timestamp();
+        apply(&mut entry);
+        entries.insert(0, entry);
+        entries.truncate(MAX_RECENT);
+        self.persist(&entries)
+    }
+
+    /// Writes the list through a temporary file so a crash never leaves a truncated store.
+    fn persist(&self, entries: &[RecentCapture]) -> io::Result<()> {
+        if let Some(dir) = self.file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let temp = self.file.with_extension("json.tmp");
+        fs::write(&temp, serde_json::to_vec_pretty(entries)?)?;
+        fs::rename(&temp, &self.file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_store(name: &str) -> PathBuf {
+        let file = std::env::temp_dir().join(format!("kcpdump-{}-{}.json", name, std::process::id()));
+        let _ = fs::remove_file(&file);
+        file
+    }
+
+    #[test]
+    fn test_recent_order_and_persistence() {
+        let file = temp_store("recent-order");
+        let recent = RecentCaptures::load(file.clone());
+        recent.touch("a.pcap").unwrap();
+        recent.touch("b.pcap").unwrap();
+        recent.touch("a.pcap").unwrap();
+        let paths: Vec<_> = recent.list().into_iter().map(|entry| entry.path).collect();
+        assert_eq!(paths, vec!["a.pcap", "b.pcap"]);
+
+        let reloaded = RecentCaptures::load(file.clone());
+        assert_eq!(reloaded.list(), recent.list());
+        assert!(reloaded.remove("b.pcap").unwrap());
+        assert!(!reloaded.remove("b.pcap").unwrap());
+        fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn test_view_state_survives_touch() {
+        let file = temp_store("recent-state");
+        let recent = RecentCaptures::load(file.clone());
+        let state = ViewState {
+            display_filter: Some(PacketFilter {
+                ip_address: "10.0.0.1".to_string(),
+                ..Default::default()
+            }),
+            columns: vec![ColumnLayout {
+                id: "time".to_string(),
+                width: Some(120),
+                visible: true,
+            }],
+            bookmarks: vec![Bookmark {
+                number: 42,
+                note: "retransmission".to_string(),
+            }],
+        };
+        recent.save_state("a.pcap", state.clone()).unwrap();
+        recent.touch("a.pcap").unwrap();
+        assert_eq!(RecentCaptures::load(file.clone()).get("a.pcap").unwrap().state, state);
+        fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn test_list_is_bounded() {
+        let file = temp_store("recent-bounded");
+        let recent = RecentCaptures::load(file.clone());
+        for i in 0..MAX_RECENT + 5 {
+            recent.touch(&format!("{}.pcap", i)).unwrap();
+        }
+        assert_eq!(recent.list().len(), MAX_RECENT);
+        fs::remove_file(file).unwrap();
+    }
+}
@@ -0,0 +1,305 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Arc;
+
+use crate::dissect::{DissectorRegistry, FieldValue, PacketLayers};
+use crate::error::KcpdumpError;
+use crate::expert::{self, ExpertSummary};
+use crate::packetlist;
+use crate::session::LoadedCapture;
+use crate::stats::{self, CaptureSummary, RankedEntry};
+
+/// Number of bars in the traffic chart.
+const TRAFFIC_BUCKETS: usize = 60;
+const CHART_WIDTH: f64 = 720.0;
+const CHART_HEIGHT: f64 = 160.0;
+
+const STYLE: &str = "body{font-family:system-ui,sans-serif;margin:2em auto;max-width:800px;color:#222}\
+h1{font-size:1.6em}h2{font-size:1.2em;margin-top:2em;border-bottom:1px solid #ccc}\
+table{border-collapse:collapse;width:100%}th,td{text-align:left;padding:4px 8px;border-bottom:1px solid #eee}\
+td.n{text-align:right}.error{color:#b00}.warning{color:#b60}\
+@media print{body{margin:0}h2{break-after:avoid}}";
+
+/// Hierarchy Row
+/// One protocol of the protocol hierarchy, below the protocols it was
+/// decoded from.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HierarchyRow {
+    pub protocol: String,
+    pub depth: usize,
+    pub packets: usize,
+    pub bytes: u64,
+}
+
+/// Counts packets and bytes per protocol stack prefix, as in Wireshark's
+/// protocol hierarchy statistics. Rows are ordered depth first.
+pub fn protocol_hierarchy(
+    captures: &[Arc<LoadedCapture>],
+    registry: &DissectorRegistry,
+) -> Result<Vec<HierarchyRow>, KcpdumpError> {
+    let mut counts: BTreeMap<Vec<&'static str>, (usize, u64)> = BTreeMap::new();
+    let link_types = packetlist::link_types(captures);
+    for item in packetlist::merged_packets(captures) {
+        let (capture_id, number, packet) = item?;
+        let values = registry.dissect(&PacketLayers::decode_link(number, link_types[&capture_id], &packet));
+        let mut stack = Vec::new();
+        for (name, value) in values.iter() {
+            if *value != FieldValue::Protocol {
+                continue;
+            }
+            stack.push(*name);
+            let entry = counts.entry(stack.clone()).or_default();
+            entry.0 += 1;
+            entry.1 += u64::from(packet.header.orig_len);
+        }
+    }
+    Ok(counts
+        .into_iter()
+        .map(|(stack, (packets, bytes))| HierarchyRow {
+            protocol: stack.last().unwrap_or(&"").to_string(),
+            depth: stack.len() - 1,
+            packets,
+            bytes,
+        })
+        .collect())
+}
+
+/// Packets per equal time slice between the first and the last packet.
+fn traffic_buckets(captures: &[Arc<LoadedCapture>]) -> (f64, Vec<usize>) {
+    let times: Vec<f64> = captures
+        .iter()
+        .flat_map(|capture| capture.index.entries())
+        .map(|entry| entry.timestamp.as_secs_f64())
+        .collect();
+    let (Some(first), Some(last)) = (
+        times.iter().copied().reduce(f64::min),
+        times.iter().copied().reduce(f64::max),
+    ) else {
+        return (0.0, Vec::new());
+    };
+    let width = ((last - first) / TRAFFIC_BUCKETS as f64).max(1e-6);
+    let mut buckets = vec![0; TRAFFIC_BUCKETS];
+    for time in times {
+        let index = (((time - first) / width) as usize).min(TRAFFIC_BUCKETS - 1);
+        buckets[index] += 1;
+    }
+    (width, buckets)
+}
+
+/// Escapes text for HTML element content and attribute values.
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Formats a byte count with a binary unit.
+fn format_size(bytes: u64) -> String {
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    for unit in ["KiB", "MiB"] {
+        if value < 1024.0 {
+            return format!("{:.1} {}", value, unit);
+        }
+        value /= 1024.0;
+    }
+    format!("{:.1} GiB", value)
+}
+
+/// Renders a bar chart as inline SVG.
+fn bar_chart(title: &str, bars: &[(String, f64)]) -> String {
+    let max = bars.iter().map(|(_, value)| *value).fold(0.0, f64::max);
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" role=\"img\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\"><title>{}</title>",
+        escape(title),
+        w = CHART_WIDTH,
+        h = CHART_HEIGHT,
+    );
+    if max > 0.0 {
+        let step = CHART_WIDTH / bars.len() as f64;
+        for (index, (label, value)) in bars.iter().enumerate() {
+            let height = value / max * (CHART_HEIGHT - 10.0);
+            let _ = write!(
+                svg,
+                "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"#4a7bd0\"><title>{}: {}</title></rect>",
+                index as f64 * step,
+                CHART_HEIGHT - height,
+                (step - 1.0).max(1.0),
+                height,
+                escape(label),
+                value
+            );
+        }
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+fn ranked_table(out: &mut String, heading: &str, entries: &[RankedEntry]) {
+    let _ = write!(
+        out,
+        "<table><tr><th>{}</th><th>Packets</th><th>Bytes</th></tr>",
+        heading
+    );
+    for entry in entries {
+        let _ = write!(
+            out,
+            "<tr><td>{}</td><td class=\"n\">{}</td><td class=\"n\">{}</td></tr>",
+            escape(&entry.name),
+            entry.packets,
+            format_size(entry.bytes)
+        );
+    }
+    out.push_str("</table>");
+}
+
+/// Renders a standalone HTML report of the captures: summary figures,
+/// traffic and protocol charts, the protocol hierarchy, top talkers and
+/// expert findings. The page has print styles, so browsers can save it as
+/// PDF.
+pub fn render_html(
+    captures: &[Arc<LoadedCapture>],
+    registry: &DissectorRegistry,
+    title: &str,
+) -> Result<String, KcpdumpError> {
+    let summary: CaptureSummary = stats::capture_summary(captures)?;
+    let expert: ExpertSummary = expert::summarize_captures(captures)?;
+    let hierarchy = protocol_hierarchy(captures, registry)?;
+    let (bucket_width, buckets) = traffic_buckets(captures);
+
+    let mut out = String::new();
+    let _ = write!(
+        out,
+        "<!DOCTYPE html><html lang=\"en\"><head><meta charset=\"utf-8\"><title>{0}</title><style>{1}</style></head><body><h1>{0}</h1>",
+        escape(title),
+        STYLE
+    );
+    let files: Vec<String> = captures
+        .iter()
+        .map(|capture| escape(&capture.path))
+        .collect();
+    let _ = write!(
+        out,
+        "<p>{}</p><h2>Summary</h2><table>\
+         <tr><th>Packets</th><td class=\"n\">{}</td></tr>\
+         <tr><th>Bytes</th><td class=\"n\">{}</td></tr>\
+         <tr><th>Duration</th><td class=\"n\">{:.3} s</td></tr>\
+         <tr><th>Average rate</th><td class=\"n\">{:.1} packets/s, {:.0} bit/s</td></tr></table>",
+        files.join(", "),
+        summary.packets,
+        format_size(summary.bytes),
+        summary.duration,
+        summary.avg_pps,
+        summary.avg_bps
+    );
+
+    out.push_str("<h2>Traffic</h2>");
+    let bars: Vec<(String, f64)> = buckets
+        .iter()
+        .enumerate()
+        .map(|(index, count)| {
+            (
+                format!("+{:.3} s", index as f64 * bucket_width),
+                *count as f64,
+            )
+        })
+        .collect();
+    out.push_str(&bar_chart("Packets over time", &bars));
+
+    out.push_str("<h2>Protocol hierarchy</h2><table><tr><th>Protocol</th><th>Packets</th><th>% Packets</th><th>Bytes</th></tr>");
+    for row in &hierarchy {
+        let _ = write!(
+            out,
+            "<tr><td style=\"padding-left:{}em\">{}</td><td class=\"n\">{}</td><td class=\"n\">{:.1}</td><td class=\"n\">{}</td></tr>",
+            row.depth + 1,
+            escape(&row.protocol),
+            row.packets,
+            row.packets as f64 * 100.0 / summary.packets.max(1) as f64,
+            format_size(row.bytes)
+        );
+    }
+    out.push_str("</table>");
+
+    out.push_str("<h2>Top protocols</h2>");
+    let bars: Vec<(String, f64)> = summary
+        .top_protocols
+        .iter()
+        .map(|entry| (entry.name.clone(), entry.packets as f64))
+        .collect();
+    out.push_str(&bar_chart("Packets per protocol", &bars));
+    ranked_table(&mut out, "Protocol", &summary.top_protocols);
+
+    out.push_str("<h2>Top talkers</h2>");
+    ranked_table(&mut out, "Address", &summary.top_talkers);
+
+    out.push_str("<h2>Expert findings</h2>");
+    if expert.entries.is_empty() {
+        out.push_str("<p>No findings.</p>");
+    } else {
+        out.push_str(
+            "<table><tr><th>Severity</th><th>Protocol</th><th>Message</th><th>Count</th></tr>",
+        );
+        for entry in &expert.entries {
+            let severity = format!("{:?}", entry.severity);
+            let _ = write!(
+                out,
+                "<tr class=\"{}\"><td>{}</td><td>{}</td><td>{}</td><td class=\"n\">{}</td></tr>",
+                severity.to_lowercase(),
+                severity,
+                escape(&entry.protocol),
+                escape(&entry.message),
+                entry.count
+            );
+        }
+        out.push_str("</table>");
+    }
+    out.push_str("</body></html>\n");
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_render_report() {
+        let capture = Arc::new(LoadedCapture::load(1, "sample.pcap").await.unwrap());
+        let captures = std::slice::from_ref(&capture);
+        let registry = DissectorRegistry::default();
+
+        let hierarchy = protocol_hierarchy(captures, &registry).unwrap();
+        assert_eq!(hierarchy[0].protocol, "frame");
+        assert_eq!(hierarchy[0].depth, 0);
+        assert_eq!(hierarchy[0].packets, 14);
+        assert!(
+            hierarchy
+                .iter()
+                .any(|row| row.protocol == "dns" && row.depth > 1)
+        );
+
+        let html = render_html(captures, &registry, "Report <sample>").unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>Report &lt;sample&gt;</title>"));
+        assert_eq!(html.matches("<svg").count(), 2);
+        assert!(html.contains("Protocol hierarchy"));
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(escape("a&\"b"), "a&amp;&quot;b");
+    }
+}
@@ -0,0 +1,372 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tokio::net::UdpSocket;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::dns::{DnsMessage, DnsRecordData};
+use crate::error::KcpdumpError;
+
+const DNS_PORT: u16 = 53;
+const PTR: u16 = 12;
+
+/// Time to wait for each name server before trying the next.
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Reverse lookups in flight at once per batch.
+const MAX_CONCURRENT_LOOKUPS: usize = 32;
+
+#[cfg(windows)]
+const HOSTS_PATH: &str = r"C:\Windows\System32\drivers\etc\hosts";
+#[cfg(not(windows))]
+const HOSTS_PATH: &str = "/etc/hosts";
+
+const RESOLV_CONF_PATH: &str = "/etc/resolv.conf";
+
+/// Name Resolution Mode
+/// Where addresses are looked up when results ask for host names.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum NameResolutionMode {
+    /// Addresses are left unresolved.
+    #[default]
+    Off,
+    /// Only the hosts file is consulted; nothing is sent on the network.
+    Hosts,
+    /// The hosts file, then reverse DNS queries to the system name servers.
+    Live,
+}
+
+/// Name Resolver
+/// Resolves addresses to host names from a hosts file and, in live mode,
+/// PTR queries, caching the answers for the session. The system hosts file
+/// and name servers are read on first use.
+#[derive(Debug, Default)]
+pub struct NameResolver {
+    hosts: Mutex<Option<Arc<HashMap<IpAddr, String>>>>,
+    nameservers: Mutex<Option<Arc<Vec<SocketAddr>>>>,
+    /// Answers of past reverse lookups, including addresses without a name.
+    cache: Mutex<HashMap<IpAddr, Option<String>>>,
+}
+
+impl NameResolver {
+    /// Replaces the system hosts file with the one at `path` and forgets
+    /// cached answers. Returns the number of addresses it names.
+    pub fn load_hosts(&self, path: &str) -> Result<usize, KcpdumpError> {
+        let text = std::fs::read_to_string(path).map_err(|e| KcpdumpError::file(path, e))?;
+        let hosts = parse_hosts(&text);
+        let entries = hosts.len();
+        *self.hosts.lock().unwrap() = Some(Arc::new(hosts));
+        self.cache.lock().unwrap().clear();
+        Ok(entries)
+    }
+
+    /// Replaces the system name servers used in live mode.
+    pub fn set_nameservers(&self, nameservers: Vec<SocketAddr>) {
+        *self.nameservers.lock().unwrap() = Some(Arc::new(nameservers));
+        self.cache.lock().unwrap().clear();
+    }
+
+    fn hosts(&self) -> Arc<HashMap<IpAddr, String>> {
+        self.hosts
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| {
+                let text = std::fs::read_to_string(HOSTS_PATH).unwrap_or_default();
+                Arc::new(parse_hosts(&text))
+            })
+            .clone()
+    }
+
+    fn nameservers(&self) -> Arc<Vec<SocketAddr>> {
+        self.nameservers
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| {
+                let text = std::fs::read_to_string(RESOLV_CONF_PATH).unwrap_or_default();
+                Arc::new(parse_resolv_conf(&text))
+            })
+            .clone()
+    }
+
+    /// Starts a batch of lookups in `mode`.
+    pub fn batch(self: &Arc<Self>, mode: NameResolutionMode) -> NameBatch {
+        let (hosts, nameservers) = match mode {
+            NameResolutionMode::Off => (Arc::default(), Arc::default()),
+            NameResolutionMode::Hosts => (self.hosts(), Arc::default()),
+            NameResolutionMode::Live => (self.hosts(), self.nameservers()),
+        };
+        NameBatch {
+            resolver: Arc::clone(self),
+            mode,
+            hosts,
+            nameservers,
+            names: HashMap::new(),
+            lookups: JoinSet::new(),
+            permits: Arc::new(Semaphore::new(MAX_CONCURRENT_LOOKUPS)),
+        }
+    }
+}
+
+/// Name Batch
+/// Addresses seen while iterating packets. Reverse lookups start in the
+/// background as soon as an address is added, so adding never waits on the
+/// network; `finish` collects the answers.
+pub struct NameBatch {
+    resolver: Arc<NameResolver>,
+    mode: NameResolutionMode,
+    hosts: Arc<HashMap<IpAddr, String>>,
+    nameservers: Arc<Vec<SocketAddr>>,
+    /// Every address added, with its name once known.
+    names: HashMap<IpAddr, Option<String>>,
+    lookups: JoinSet<(IpAddr, Option<String>)>,
+    permits: Arc<Semaphore>,
+}
+
+impl NameBatch {
+    pub fn add(&mut self, address: IpAddr) {
+        if self.mode == NameResolutionMode::Off || self.names.contains_key(&address) {
+            return;
+        }
+        let known = match self.hosts.get(&address) {
+            Some(name) => Some(Some(name.clone())),
+            None => self.resolver.cache.lock().unwrap().get(&address).cloned(),
+        };
+        if let Some(name) = known {
+            self.names.insert(address, name);
+            return;
+        }
+        self.names.insert(address, None);
+        if self.nameservers.is_empty() {
+            return;
+        }
+        let nameservers = Arc::clone(&self.nameservers);
+        let permits = Arc::clone(&self.permits);
+        self.lookups.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            (address, lookup_ptr(address, &nameservers).await)
+        });
+    }
+
+    /// Waits for the pending lookups and returns the names found, by address.
+    pub async fn finish(mut self) -> HashMap<IpAddr, String> {
+        while let Some(result) = self.lookups.join_next().await {
+            if let Ok((address, name)) = result {
+                self.resolver
+                    .cache
+                    .lock()
+                    .unwrap()
+                    .insert(address, name.clone());
+                self.names.insert(address, name);
+            }
+        }
+        self.names
+            .into_iter()
+            .filter_map(|(address, name)| Some((address, name?)))
+            .collect()
+    }
+}
+
+/// Parses hosts file lines of an address followed by its names; the first
+/// name of the first line for an address is its canonical name.
+pub fn parse_hosts(text: &str) -> HashMap<IpAddr, String> {
+    let mut hosts = HashMap::new();
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut fields = line.split_whitespace();
+        if let (Some(address), Some(name)) = (fields.next(), fields.next())
+            && let Ok(address) = address.parse()
+        {
+            hosts.entry(address).or_insert_with(|| name.to_string());
+        }
+    }
+    hosts
+}
+
+/// Name servers of `nameserver` lines in resolv.conf format.
+fn parse_resolv_conf(text: &str) -> Vec<SocketAddr> {
+    text.lines()
+        .filter_map(|line| line.trim().strip_prefix("nameserver"))
+        .filter_map(|address| address.trim().parse::<IpAddr>().ok())
+        .map(|address| SocketAddr::new(address, DNS_PORT))
+        .collect()
+}
+
+/// The `in-addr.arpa` or `ip6.arpa` name of an address.
+fn ptr_name(address: IpAddr) -> String {
+    match address {
+        IpAddr::V4(address) => {
+            let [a, b, c, d] = address.octets();
+            format!("{}.{}.{}.{}.in-addr.arpa", d, c, b, a)
+        }
+        IpAddr::V6(address) => {
+            let mut name = String::new();
+            for byte in address.octets().iter().rev() {
+                name.push_str(&format!("{:x}.{:x}.", byte & 0x0f, byte >> 4));
+            }
+            name.push_str("ip6.arpa");
+            name
+        }
+    }
+}
+
+/// A recursive PTR query for `address`.
+fn ptr_query(id: u16, address: IpAddr) -> Vec<u8> {
+    let mut query = Vec::with_capacity(96);
+    query.extend_from_slice(&id.to_be_bytes());
+    // Recursion desired, one question.
+    query.extend_from_slice(&[0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+    for label in ptr_name(address).split('.') {
+        query.push(label.len() as u8);
+        query.extend_from_slice(label.as_bytes());
+    }
+    query.push(0);
+    query.extend_from_slice(&PTR.to_be_bytes());
+    query.extend_from_slice(&1u16.to_be_bytes());
+    query
+}
+
+/// Asks each name server in turn for the PTR record of `address`.
+async fn lookup_ptr(address: IpAddr, nameservers: &[SocketAddr]) -> Option<String> {
+    let id = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.subsec_nanos() as u16);
+    let query = ptr_query(id, address);
+    for server in nameservers {
+        let local: SocketAddr = match server {
+            SocketAddr::V4(_) => ([0, 0, 0, 0], 0).into(),
+            SocketAddr::V6(_) => ([0u16; 8], 0).into(),
+        };
+        let Ok(socket) = UdpSocket::bind(local).await else {
+            continue;
+        };
+        if socket.connect(server).await.is_err() || socket.send(&query).await.is_err() {
+            continue;
+        }
+        let mut buf = [0u8; 512];
+        let Ok(Ok(len)) = tokio::time::timeout(LOOKUP_TIMEOUT, socket.recv(&mut buf)).await else {
+            continue;
+        };
+        let Ok(response) = DnsMessage::try_from(&buf[..len]) else {
+            continue;
+        };
+        if response.id != id || !response.is_response() {
+            continue;
+        }
+        // An answer without a PTR record is final; other errors are not.
+        if response.rcode() == 0 || response.rcode() == 3 {
+            return response.answers.into_iter().find_map(|answer| match answer.data {
+                DnsRecordData::Name(name) if answer.record_type == PTR => {
+                    Some(name.trim_end_matches('.').to_string())
+                }
+                _ => None,
+            });
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv6Addr;
+
+    #[test]
+    fn test_parse_hosts() {
+        let hosts = parse_hosts(
+            "# comment\n127.0.0.1 localhost\n192.168.0.10\tnas nas.lan # storage\n\
+             192.168.0.10 other\n::1 ip6-localhost\nbogus line\n",
+        );
+        assert_eq!(hosts.len(), 3);
+        assert_eq!(hosts[&IpAddr::from([192, 168, 0, 10])], "nas");
+        assert_eq!(hosts[&IpAddr::V6(Ipv6Addr::LOCALHOST)], "ip6-localhost");
+        assert_eq!(
+            parse_resolv_conf("search lan\nnameserver 192.168.0.1\nnameserver ::1\n"),
+            vec![
+                SocketAddr::new(IpAddr::from([192, 168, 0, 1]), 53),
+                SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), 53),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ptr_query() {
+        assert_eq!(
+            ptr_name(IpAddr::from([192, 0, 2, 1])),
+            "1.2.0.192.in-addr.arpa"
+        );
+        let name = ptr_name(IpAddr::V6("2001:db8::1".parse().unwrap()));
+        assert!(name.starts_with("1.0.0.0.0.0.0.0"));
+        assert!(name.ends_with("8.b.d.0.1.0.0.2.ip6.arpa"));
+
+        let query = ptr_query(0x1234, IpAddr::from([192, 0, 2, 1]));
+        let message = DnsMessage::try_from(query.as_slice()).unwrap();
+        assert_eq!(message.id, 0x1234);
+        assert!(message.recursion_desired());
+        assert_eq!(message.questions[0].name, "1.2.0.192.in-addr.arpa");
+        assert_eq!(message.questions[0].record_type, PTR);
+    }
+
+    /// Answers one PTR query with `name`.
+    async fn fake_name_server(name: &'static str) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = socket.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            let (len, peer) = socket.recv_from(&mut buf).await.unwrap();
+            let mut response = buf[..len].to_vec();
+            response[2..4].copy_from_slice(&[0x81, 0x80]);
+            response[6..8].copy_from_slice(&1u16.to_be_bytes());
+            let mut data = Vec::new();
+            for label in name.split('.') {
+                data.push(label.len() as u8);
+                data.extend_from_slice(label.as_bytes());
+            }
+            data.push(0);
+            response.extend_from_slice(&[0xc0, 0x0c, 0x00, 0x0c, 0x00, 0x01, 0, 0, 0x0e, 0x10]);
+            response.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            response.extend_from_slice(&data);
+            socket.send_to(&response, peer).await.unwrap();
+        });
+        address
+    }
+
+    #[tokio::test]
+    async fn test_batch() {
+        let path = std::env::temp_dir().join(format!("kcpdump-hosts-{}", std::process::id()));
+        std::fs::write(&path, "192.168.0.10 nas\n").unwrap();
+        let resolver = Arc::new(NameResolver::default());
+        assert_eq!(resolver.load_hosts(&path.to_string_lossy()).unwrap(), 1);
+        std::fs::remove_file(&path).unwrap();
+        let nas = IpAddr::from([192, 168, 0, 10]);
+        let web = IpAddr::from([192, 0, 2, 1]);
+
+        let mut batch = resolver.batch(NameResolutionMode::Off);
+        batch.add(nas);
+        assert!(batch.finish().await.is_empty());
+
+        resolver.set_nameservers(vec![fake_name_server("www.example.com.").await]);
+        let mut batch = resolver.batch(NameResolutionMode::Hosts);
+        batch.add(nas);
+        batch.add(web);
+        let names = batch.finish().await;
+        assert_eq!(names.len(), 1);
+        assert_eq!(names[&nas], "nas");
+
+        let mut batch = resolver.batch(NameResolutionMode::Live);
+        batch.add(nas);
+        batch.add(web);
+        batch.add(web);
+        let names = batch.finish().await;
+        assert_eq!(names[&nas], "nas");
+        assert_eq!(names[&web], "www.example.com");
+
+        // The answer is cached; the fake server only answers once.
+        let mut batch = resolver.batch(NameResolutionMode::Live);
+        batch.add(web);
+        assert_eq!(batch.finish().await[&web], "www.example.com");
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufWriter;
+
+use crate::cap::PcapWriter;
+use crate::error::KcpdumpError;
+use crate::session::{CaptureId, LoadedCapture};
+use crate::summary;
+use crate::timefmt::Timestamp;
+
+/// Sampling
+/// Selects a representative subset of a capture, to profile very large
+/// captures quickly or to share part of one.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Sampling {
+    /// Keeps packets 1, 1 + n, 1 + 2n, ...
+    EveryNth { n: usize },
+    /// Keeps the first packet of every flow in each interval of `seconds`,
+    /// counted from the first packet of the capture. Packets outside any flow
+    /// share one bucket.
+    PerFlowInterval { seconds: f64 },
+}
+
+impl Sampling {
+    fn validate(&self) -> Result<(), KcpdumpError> {
+        match *self {
+            Sampling::EveryNth { n: 0 } => Err(KcpdumpError::InvalidInput(
+                "n must be at least 1".to_string(),
+            )),
+            Sampling::PerFlowInterval { seconds } if !(seconds > 0.0 && seconds.is_finite()) => {
+                Err(KcpdumpError::InvalidInput(
+                    "The interval must be a positive number of seconds".to_string(),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Short description used to name sampled captures.
+    pub fn describe(&self) -> String {
+        match self {
+            Sampling::EveryNth { n } => format!("1 in {} packets", n),
+            Sampling::PerFlowInterval { seconds } => format!("1 per flow per {} s", seconds),
+        }
+    }
+}
+
+/// Returns the numbers of the packets of `capture` kept by `sampling`,
+/// starting at 1 and in capture order.
+pub fn sample_packets(
+    capture: &LoadedCapture,
+    sampling: &Sampling,
+) -> Result<Vec<usize>, KcpdumpError> {
+    sampling.validate()?;
+    let numbers = match *sampling {
+        Sampling::EveryNth { n } => (1..=capture.packet_count()).step_by(n).collect(),
+        Sampling::PerFlowInterval { seconds } => {
+            let time = |timestamp: Timestamp| timestamp.as_secs_f64();
+            let start = capture
+                .index
+                .get(1)
+                .map_or(0.0, |entry| time(entry.timestamp));
+            let mut last_bucket = HashMap::new();
+            let mut numbers = Vec::new();
+            for (index, packet) in capture.packets().enumerate() {
+                let packet = packet?;
+                let bucket = ((time(packet.header.timestamp) - start) / seconds).floor() as i64;
+                let flow = summary::summarize_link(capture.header.network, &packet.data).flow;
+                if last_bucket.insert(flow, bucket) != Some(bucket) {
+                    numbers.push(index + 1);
+                }
+            }
+            numbers
+        }
+    };
+    Ok(numbers)
+}
+
+/// Builds a new capture of the sampled packets of `capture`, read from the
+/// same file, so that every analysis can run on the subset.
+pub fn sampled_capture(
+    id: CaptureId,
+    capture: &LoadedCapture,
+    sampling: &Sampling,
+) -> Result<LoadedCapture, KcpdumpError> {
+    let mut sampled = capture.subset(&sample_packets(capture, sampling)?)?;
+    sampled.id = id;
+    sampled.path = format!("{} ({})", capture.path, sampling.describe());
+    Ok(sampled)
+}
+
+/// Writes the sampled packets of `capture` to a libpcap file at `path`, one
+/// packet at a time, replacing the file.
+pub fn export_sampled_pcap(
+    capture: &LoadedCapture,
+    sampling: &Sampling,
+    path: &str,
+) -> Result<(), KcpdumpError> {
+    let numbers = sample_packets(capture, sampling)?;
+    let header = &capture.header;
+    let write_error = |e: std::io::Error| KcpdumpError::io("Failed to write capture file", e);
+    let file = File::create(path).map_err(|e| KcpdumpError::file(path, e))?;
+    let mut writer = PcapWriter::with_resolution(
+        BufWriter::new(file),
+        header.network,
+        header.snaplen,
+        header.resolution(),
+    )
+    .map_err(write_error)?;
+    for number in numbers {
+        if let Some(packet) = capture.packet(number)? {
+            writer.write_packet(&packet).map_err(write_error)?;
+        }
+    }
+    writer.flush().map_err(write_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cap;
+
+    #[tokio::test]
+    async fn test_sampling() {
+        let capture = LoadedCapture::load(1, "sample.pcap").await.unwrap();
+        let every_third = sample_packets(&capture, &Sampling::EveryNth { n: 3 }).unwrap();
+        assert_eq!(every_third.len(), 5);
+        assert_eq!(every_third[1], 4);
+        assert_eq!(
+            sample_packets(&capture, &Sampling::EveryNth { n: 1 })
+                .unwrap()
+                .len(),
+            14
+        );
+        assert!(sample_packets(&capture, &Sampling::EveryNth { n: 0 }).is_err());
+
+        // One packet per flow when the interval covers the whole capture.
+        let per_flow =
+            sample_packets(&capture, &Sampling::PerFlowInterval { seconds: 3600.0 }).unwrap();
+        let flows: std::collections::HashSet<_> = capture
+            .packets()
+            .map(|packet| summary::summarize_link(capture.header.network, &packet.unwrap().data).flow)
+            .collect();
+        assert_eq!(per_flow.len(), flows.len());
+
+        let sampled = sampled_capture(2, &capture, &Sampling::EveryNth { n: 2 }).unwrap();
+        assert_eq!(sampled.packet_count(), 7);
+        let third = sampled.packet(3).unwrap().unwrap();
+        assert_eq!(third.data, capture.packet(5).unwrap().unwrap().data);
+        assert!(sampled.path.ends_with("(1 in 2 packets)"));
+        let path =
+            std::env::temp_dir().join(format!("kcpdump-sampled-{}.pcap", std::process::id()));
+        let path = path.to_string_lossy();
+        export_sampled_pcap(&capture, &Sampling::EveryNth { n: 2 }, &path).unwrap();
+        let (_, packets) = cap::parse_pcap_bytes(&std::fs::read(&*path).unwrap()).unwrap();
+        std::fs::remove_file(&*path).unwrap();
+        assert_eq!(packets.len(), 7);
+    }
+}
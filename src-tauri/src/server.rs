@@ -0,0 +1,392 @@
+use std::sync::Arc;
+
+use axum::body::Bytes;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{DefaultBodyLimit, Path, Query, State};
+use axum::http::{StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post};
+use axum::{Json, Router};
+use tokio::io;
+use tokio::net::TcpListener;
+use tokio::sync::broadcast;
+
+use crate::dissect::DissectorRegistry;
+use crate::error::KcpdumpError;
+use crate::flows::{self, FlowRecord};
+use crate::packetlist::{self, FieldColumns, PacketListQuery, PacketPage};
+use crate::session::{CaptureId, CaptureInfo, LoadedCapture, Session};
+use crate::stats::{self, CaptureSummary};
+
+/// Largest request body accepted, which bounds uploaded captures.
+const MAX_BODY_LEN: usize = 1 << 30;
+/// Events buffered for each `/events` client; a client further behind
+/// misses the oldest ones.
+const EVENT_BACKLOG: usize = 1024;
+
+/// Server Event
+/// A change to the open captures, pushed to `/events` clients.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ServerEvent {
+    #[serde(rename_all = "camelCase")]
+    CaptureOpened { capture: CaptureInfo },
+    #[serde(rename_all = "camelCase")]
+    CaptureClosed { capture_id: CaptureId },
+}
+
+/// Api Error
+/// A failed request, answered with its status and `{ "error": message }`.
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn bad_request(message: String) -> Self {
+        ApiError {
+            status: StatusCode::BAD_REQUEST,
+            message,
+        }
+    }
+}
+
+impl From<KcpdumpError> for ApiError {
+    fn from(error: KcpdumpError) -> Self {
+        let status = match error {
+            KcpdumpError::CaptureNotFound { .. } | KcpdumpError::PacketNotFound { .. } => {
+                StatusCode::NOT_FOUND
+            }
+            KcpdumpError::Io { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        };
+        ApiError {
+            status,
+            message: error.to_string(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = Json(serde_json::json!({ "error": self.message }));
+        (self.status, body).into_response()
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+type ServerState = State<Arc<ApiServer>>;
+
+#[derive(serde::Deserialize, Debug, Default)]
+struct CaptureParams {
+    /// A single capture, or all captures if absent.
+    capture: Option<CaptureId>,
+}
+
+#[derive(serde::Deserialize, Debug, Default)]
+struct OpenParams {
+    path: Option<String>,
+    name: Option<String>,
+}
+
+/// Api Server
+/// Serves the analysis engine over HTTP without the GUI. Captures are
+/// referenced by path or uploaded as libpcap bodies, then queried as JSON:
+///
+/// - `GET /captures`, `POST /captures?path=...` or `POST /captures` with a
+///   pcap body, `DELETE /captures/{id}`
+/// - `GET /summary`, `GET /flows`, optionally with `?capture={id}`
+/// - `POST /packets` with a packet list query body
+/// - `GET /events`, a WebSocket pushing a `ServerEvent` whenever a capture
+///   is opened or closed, and the `CaptureLoadEvent`s of captures opened by
+///   path while they load, one JSON text message each
+pub struct ApiServer {
+    session: Session,
+    registry: DissectorRegistry,
+    events: broadcast::Sender<String>,
+}
+
+impl Default for ApiServer {
+    fn default() -> Self {
+        ApiServer {
+            session: Session::default(),
+            registry: DissectorRegistry::default(),
+            events: broadcast::channel(EVENT_BACKLOG).0,
+        }
+    }
+}
+
+impl ApiServer {
+    /// The routes of the API, sharing this server's session.
+    pub fn router(self: Arc<Self>) -> Router {
+        Router::new()
+            .route("/captures", get(list_captures).post(open_capture))
+            .route("/captures/{id}", delete(close_capture))
+            .route("/summary", get(summary))
+            .route("/flows", get(flow_records))
+            .route("/packets", post(packet_list))
+            .route("/events", get(events))
+            .fallback(not_found)
+            .layer(DefaultBodyLimit::max(MAX_BODY_LEN))
+            .with_state(self)
+    }
+
+    /// Accepts connections on `address` until the listener fails.
+    pub async fn serve(self: Arc<Self>, address: &str) -> io::Result<()> {
+        let listener = TcpListener::bind(address).await?;
+        axum::serve(listener, self.router()).await
+    }
+
+    /// Sends an event to every connected `/events` client.
+    fn publish<T: serde::Serialize>(&self, event: &T) {
+        if let Ok(text) = serde_json::to_string(event) {
+            // Fails only when no client is listening.
+            let _ = self.events.send(text);
+        }
+    }
+}
+
+async fn list_captures(State(server): ServerState) -> Json<Vec<CaptureInfo>> {
+    let captures = server.session.captures();
+    Json(captures.iter().map(|capture| capture.info()).collect())
+}
+
+async fn open_capture(
+    State(server): ServerState,
+    Query(params): Query<OpenParams>,
+    body: Bytes,
+) -> ApiResult<CaptureInfo> {
+    let id = server.session.next_capture_id();
+    let capture = match params.path {
+        Some(path) => {
+            let mode = server.session.settings().time_display_mode;
+            LoadedCapture::load_with_progress(id, &path, mode, None, |event| server.publish(&event))
+                .await
+                .map_err(KcpdumpError::from)?
+        }
+        None => {
+            let name = params.name.as_deref().unwrap_or("upload.pcap");
+            LoadedCapture::load_bytes(id, name, &body)
+                .await
+                .map_err(KcpdumpError::from)?
+        }
+    };
+    let info = server.session.insert_capture(capture);
+    server.publish(&ServerEvent::CaptureOpened {
+        capture: info.clone(),
+    });
+    Ok(Json(info))
+}
+
+async fn close_capture(State(server): ServerState, Path(id): Path<CaptureId>) -> Json<bool> {
+    let removed = server.session.remove_capture(id);
+    if removed {
+        server.publish(&ServerEvent::CaptureClosed { capture_id: id });
+    }
+    Json(removed)
+}
+
+async fn summary(
+    State(server): ServerState,
+    Query(params): Query<CaptureParams>,
+) -> ApiResult<CaptureSummary> {
+    let captures = server.session.select(params.capture)?;
+    spawn_query(move || stats::capture_summary(&captures)).await
+}
+
+async fn flow_records(
+    State(server): ServerState,
+    Query(params): Query<CaptureParams>,
+) -> ApiResult<Vec<FlowRecord>> {
+    let captures = server.session.select(params.capture)?;
+    spawn_query(move || flows::flow_records(&captures)).await
+}
+
+async fn packet_list(State(server): ServerState, body: Bytes) -> ApiResult<PacketPage> {
+    let query: PacketListQuery = if body.is_empty() {
+        PacketListQuery::default()
+    } else {
+        serde_json::from_slice(&body).map_err(|e| ApiError::bad_request(e.to_string()))?
+    };
+    let mode = server.session.settings().time_display_mode;
+    if let Some(capture) = query.capture_id.and_then(|id| server.session.capture(id))
+        && query.is_capture_order()
+    {
+        let count = query.limit.unwrap_or(usize::MAX);
+        return spawn_query(move || {
+            let columns = FieldColumns::new(&server.registry, query.columns)?;
+            packetlist::page(&capture, query.offset, count, mode, Some(&columns))
+        })
+        .await;
+    }
+    let captures = server.session.select(query.capture_id)?;
+    spawn_query(move || {
+        let columns = FieldColumns::new(&server.registry, query.columns.clone())?;
+        let mut rows = packetlist::build_rows(&captures, &query.filter, mode, Some(&columns))?;
+        packetlist::sort_rows(&mut rows, query.sort);
+        Ok(packetlist::paginate(rows, query.offset, query.limit))
+    })
+    .await
+}
+
+/// Runs a query on the blocking pool. Captures are read from disk on demand,
+/// so a scan on an async worker would stall the other requests and the
+/// `/events` clients sharing it.
+async fn spawn_query<T: Send + 'static>(
+    query: impl FnOnce() -> Result<T, KcpdumpError> + Send + 'static,
+) -> ApiResult<T> {
+    let result = tokio::task::spawn_blocking(query)
+        .await
+        .map_err(|e| ApiError {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: format!("Query failed: {}", e),
+        })?;
+    Ok(Json(result?))
+}
+
+async fn events(upgrade: WebSocketUpgrade, State(server): ServerState) -> Response {
+    // Subscribing before the upgrade completes means a client sees every
+    // event after its handshake.
+    let events = server.events.subscribe();
+    upgrade.on_upgrade(move |socket| push_events(socket, events))
+}
+
+/// Forwards events to one client until it disconnects. Clients only listen;
+/// anything they send is ignored.
+async fn push_events(mut socket: WebSocket, mut events: broadcast::Receiver<String>) {
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(text) => {
+                    if socket.send(Message::Text(text.into())).await.is_err() {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            message = socket.recv() => {
+                if !matches!(message, Some(Ok(_))) {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+async fn not_found(uri: Uri) -> ApiError {
+    ApiError {
+        status: StatusCode::NOT_FOUND,
+        message: format!("No route for {}", uri.path()),
+    }
+}
+
+/// Runs the API server on `address` until it fails, for the `--serve`
+/// command line mode. Returns the error that stopped it, such as the
+/// address being in use.
+pub fn run(address: &str) -> io::Result<()> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(Arc::new(ApiServer::default()).serve(address))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use futures_util::StreamExt;
+    use tower::ServiceExt;
+
+    /// A libpcap file without packets.
+    const EMPTY_PCAP: [u8; 24] = [
+        0xd4, 0xc3, 0xb2, 0xa1, 0x02, 0x00, 0x04, 0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 0, 0,
+        0x01, 0, 0, 0,
+    ];
+
+    async fn call(
+        server: &Arc<ApiServer>,
+        method: &str,
+        uri: &str,
+        body: &[u8],
+    ) -> (StatusCode, serde_json::Value) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::from(body.to_vec()))
+            .unwrap();
+        let response = server.clone().router().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn test_routes() {
+        let server = Arc::new(ApiServer::default());
+        let (status, _) = call(&server, "POST", "/captures?path=sample.pcap", b"").await;
+        assert_eq!(status, StatusCode::OK);
+        let data = tokio::fs::read("sample.pcap").await.unwrap();
+        let (_, uploaded) = call(&server, "POST", "/captures", &data).await;
+        assert_eq!(uploaded["path"], "upload.pcap");
+
+        let (_, captures) = call(&server, "GET", "/captures", b"").await;
+        assert_eq!(captures[1]["packetCount"], 14);
+        let (status, _) = call(&server, "GET", "/summary?capture=1", b"").await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, flows) = call(&server, "GET", "/flows", b"").await;
+        assert!(flows.is_array());
+        let body = br#"{"captureId": 2, "limit": 2}"#;
+        let (_, page) = call(&server, "POST", "/packets", body).await;
+        assert_eq!(page["total"], 14);
+        assert_eq!(page["rows"].as_array().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let server = Arc::new(ApiServer::default());
+        let (status, body) = call(&server, "GET", "/summary?capture=1", b"").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"], "No open capture with id 1");
+        let (status, _) = call(&server, "GET", "/summary?capture=x", b"").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = call(&server, "POST", "/packets", b"{").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = call(&server, "PUT", "/flows", b"").await;
+        assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+        let (status, body) = call(&server, "GET", "/nowhere", b"").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"], "No route for /nowhere");
+    }
+
+    #[tokio::test]
+    async fn test_events() {
+        let server = Arc::new(ApiServer::default());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let router = server.clone().router();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        let url = format!("ws://{}/events", address);
+        let (mut socket, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        let mut next_event = async || {
+            let message = socket.next().await.unwrap().unwrap();
+            serde_json::from_str::<ServerEvent>(message.to_text().unwrap()).unwrap()
+        };
+
+        let (status, _) = call(&server, "POST", "/captures?name=empty.pcap", &EMPTY_PCAP).await;
+        assert_eq!(status, StatusCode::OK);
+        let ServerEvent::CaptureOpened { capture } = next_event().await else {
+            panic!("expected an opened capture");
+        };
+        assert_eq!((capture.id, capture.path.as_str()), (1, "empty.pcap"));
+
+        let (_, removed) = call(&server, "DELETE", "/captures/1", b"").await;
+        assert_eq!(removed, true);
+        assert_eq!(
+            next_event().await,
+            ServerEvent::CaptureClosed { capture_id: 1 }
+        );
+    }
+}
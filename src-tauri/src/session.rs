@@ -0,0 +1,629 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tokio::io;
+
+use crate::asn::AsnDatabase;
+use crate::bpf::CaptureFilter;
+use crate::can::DbcDatabase;
+use crate::cap::{
+    BlockingCapture, Capture, IndexEntry, PacketIndex, PcapHeader, PcapPacket, TimestampResolution,
+};
+use crate::error::KcpdumpError;
+use crate::flows::StreamTable;
+use crate::geoip::GeoIpDatabase;
+use crate::live::{LiveCaptureHandle, LiveRing};
+use crate::lorawan::SessionKeys;
+use crate::oui::OuiDatabase;
+use crate::packetlist::PacketRow;
+use crate::resolve::{NameResolutionMode, NameResolver};
+use crate::summary;
+use crate::timefmt::{TimeDisplayMode, TimeFormatter, Timestamp};
+use crate::watch::{DirectoryWatchHandle, WatchId};
+
+pub type CaptureId = u32;
+
+/// Packets read between two load events.
+const LOAD_BATCH_SIZE: usize = 1000;
+
+/// Session Settings
+/// User preferences that affect how analysis results are rendered.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionSettings {
+    pub time_display_mode: TimeDisplayMode,
+    pub name_resolution: NameResolutionMode,
+}
+
+/// Loaded Capture
+/// A capture file open in the session. Only its packet index is held in
+/// memory; packets are read from the file when they are needed.
+#[derive(Debug)]
+pub struct LoadedCapture {
+    pub id: CaptureId,
+    pub path: String,
+    pub header: PcapHeader,
+    /// Where each packet is in the file, in packet order. Packets rejected
+    /// by the capture filter have no entry.
+    pub index: PacketIndex,
+    /// Stream ids of the conversations in the capture.
+    pub streams: StreamTable,
+    /// The file read, which differs from `path` for uploaded captures.
+    file: String,
+    /// Whether `file` was written for this capture and goes with it.
+    temporary: bool,
+    /// Reads single packets and pages, kept open between requests.
+    reader: Mutex<Option<BlockingCapture>>,
+}
+
+impl LoadedCapture {
+    /// Indexes every packet of a capture file.
+    pub async fn load(id: CaptureId, path: &str) -> io::Result<Self> {
+        let capture =
+            Self::load_with_progress(id, path, TimeDisplayMode::default(), None, |_| {}).await?;
+        Ok(capture)
+    }
+
+    /// Indexes every packet of a capture file like `load`, passing the
+    /// packet list rows of each batch to `sink` as soon as it is read so
+    /// that large files can be shown while they load. Packets rejected by
+    /// `filter` are left out of the index.
+    pub async fn load_with_progress<F>(
+        id: CaptureId,
+        path: &str,
+        mode: TimeDisplayMode,
+        filter: Option<CaptureFilter>,
+        mut sink: F,
+    ) -> io::Result<Self>
+    where
+        F: FnMut(CaptureLoadEvent),
+    {
+        let size = tokio::fs::metadata(path).await?.len();
+        let mut capture = Capture::from_file(path).await?;
+        capture.set_capture_filter(filter);
+        let header = capture.header().clone();
+        let mut formatter = TimeFormatter::with_resolution(mode, capture.resolution());
+        let mut streams = StreamTable::default();
+        let mut index = PacketIndex::default();
+        let mut rows = Vec::new();
+        loop {
+            let packet = capture.next_packet().await?;
+            if let Some(packet) = &packet {
+                let timestamp = packet.header.timestamp;
+                index.push(IndexEntry {
+                    offset: capture.packet_offset(),
+                    timestamp,
+                });
+                let summary = summary::summarize_link(header.network, &packet.data);
+                rows.push(PacketRow {
+                    capture_id: id,
+                    number: index.len(),
+                    timestamp,
+                    time: formatter.format(timestamp),
+                    stream: summary.flow.map(|flow| streams.id(flow)),
+                    summary,
+                    fields: Vec::new(),
+                });
+            }
+            if rows.len() >= LOAD_BATCH_SIZE || (packet.is_none() && !rows.is_empty()) {
+                let position = capture.position();
+                sink(CaptureLoadEvent::Packets {
+                    capture_id: id,
+                    offset: rows[0].number - 1,
+                    rows: std::mem::take(&mut rows),
+                    progress: if size == 0 {
+                        100.0
+                    } else {
+                        (position as f64 / size as f64 * 100.0).min(100.0)
+                    },
+                });
+            }
+            if packet.is_none() {
+                break;
+            }
+        }
+        sink(CaptureLoadEvent::Loaded {
+            capture_id: id,
+            packet_count: index.len(),
+        });
+        let reader = BlockingCapture::from_capture(capture, path)?;
+        Ok(LoadedCapture {
+            id,
+            path: path.to_string(),
+            header,
+            index,
+            streams,
+            file: path.to_string(),
+            temporary: false,
+            reader: Mutex::new(Some(reader)),
+        })
+    }
+
+    /// Loads a capture file received as bytes, such as an upload, shown
+    /// as `name`. The bytes are kept in a temporary file for as long as the
+    /// capture is open.
+    pub async fn load_bytes(id: CaptureId, name: &str, data: &[u8]) -> io::Result<Self> {
+        static UPLOADS: AtomicU32 = AtomicU32::new(0);
+        let file = std::env::temp_dir().join(format!(
+            "kcpdump-{}-{}.cap",
+            std::process::id(),
+            UPLOADS.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = file.to_string_lossy().into_owned();
+        tokio::fs::write(&file, data).await?;
+        match Self::load(id, &file).await {
+            Ok(mut capture) => {
+                capture.path = name.to_string();
+                capture.temporary = true;
+                Ok(capture)
+            }
+            Err(e) => {
+                let _ = tokio::fs::remove_file(&file).await;
+                Err(e)
+            }
+        }
+    }
+
+    /// A capture of the packets at `numbers` of this one, starting at 1 and
+    /// in ascending order, read from the same file.
+    pub fn subset(&self, numbers: &[usize]) -> Result<Self, KcpdumpError> {
+        let mut reader = BlockingCapture::from_file(&self.file)?;
+        let mut index = PacketIndex::default();
+        // Streams are numbered again from the packets kept.
+        let mut streams = StreamTable::default();
+        for &number in numbers {
+            let entry = self.index.get(number).ok_or(KcpdumpError::PacketNotFound {
+                capture_id: self.id,
+                number,
+            })?;
+            let packet = read_entry(&mut reader, entry)?;
+            if let Some(flow) = summary::summarize_link(self.header.network, &packet.data).flow {
+                streams.id(flow);
+            }
+            index.push(*entry);
+        }
+        Ok(LoadedCapture {
+            id: self.id,
+            path: self.path.clone(),
+            header: self.header.clone(),
+            index,
+            streams,
+            file: self.file.clone(),
+            temporary: false,
+            reader: Mutex::new(Some(reader)),
+        })
+    }
+
+    pub fn packet_count(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Reads packet `number`, starting at 1, from the file.
+    pub fn packet(&self, number: usize) -> Result<Option<PcapPacket>, KcpdumpError> {
+        match number.checked_sub(1) {
+            Some(offset) => Ok(self.window(offset, 1)?.pop()),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads up to `count` packets following the first `offset` ones.
+    pub fn window(&self, offset: usize, count: usize) -> Result<Vec<PcapPacket>, KcpdumpError> {
+        let entries = self.index.entries().iter().skip(offset).take(count);
+        let mut reader = self.reader.lock().unwrap();
+        let reader = match &mut *reader {
+            Some(reader) => reader,
+            empty => empty.insert(BlockingCapture::from_file(&self.file)?),
+        };
+        entries.map(|entry| read_entry(reader, entry)).collect()
+    }
+
+    /// Iterates over the packets in order, reading them from the file one
+    /// at a time.
+    pub fn packets(&self) -> Packets<'_> {
+        Packets {
+            file: &self.file,
+            entries: self.index.entries().iter(),
+            reader: None,
+        }
+    }
+
+    pub fn info(&self) -> CaptureInfo {
+        CaptureInfo {
+            id: self.id,
+            path: self.path.clone(),
+            link_type: self.header.network,
+            resolution: self.header.resolution(),
+            packet_count: self.packet_count(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl LoadedCapture {
+    /// Writes `packets` to a temporary pcap file and loads it, for tests
+    /// of both plain and async code.
+    pub(crate) fn from_packets(
+        id: CaptureId,
+        path: &str,
+        network: u32,
+        packets: &[PcapPacket],
+    ) -> LoadedCapture {
+        let mut writer = crate::cap::PcapWriter::new(Vec::new(), network, 65535).unwrap();
+        for packet in packets {
+            writer.write_packet(packet).unwrap();
+        }
+        let data = writer.into_inner();
+        let path = path.to_string();
+        std::thread::spawn(move || {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(LoadedCapture::load_bytes(id, &path, &data))
+                .unwrap()
+        })
+        .join()
+        .unwrap()
+    }
+}
+
+impl Drop for LoadedCapture {
+    fn drop(&mut self) {
+        if self.temporary {
+            let _ = std::fs::remove_file(&self.file);
+        }
+    }
+}
+
+fn read_entry(
+    reader: &mut BlockingCapture,
+    entry: &IndexEntry,
+) -> Result<PcapPacket, KcpdumpError> {
+    reader
+        .read_at(entry.offset)?
+        .ok_or_else(|| KcpdumpError::malformed(entry.offset as usize, "Indexed packet missing"))
+}
+
+/// Packets
+/// Iterator over the packets of a `LoadedCapture`, which opens the file
+/// when the first packet is read.
+pub struct Packets<'a> {
+    file: &'a str,
+    entries: std::slice::Iter<'a, IndexEntry>,
+    reader: Option<BlockingCapture>,
+}
+
+impl Packets<'_> {
+    /// Timestamp of the next packet, known from the index without reading it.
+    pub fn next_timestamp(&self) -> Option<Timestamp> {
+        self.entries.as_slice().first().map(|entry| entry.timestamp)
+    }
+}
+
+impl Iterator for Packets<'_> {
+    type Item = Result<PcapPacket, KcpdumpError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = self.entries.next()?;
+        let reader = match &mut self.reader {
+            Some(reader) => reader,
+            empty => match BlockingCapture::from_file(self.file) {
+                Ok(reader) => empty.insert(reader),
+                Err(e) => {
+                    self.entries = [].iter();
+                    return Some(Err(e));
+                }
+            },
+        };
+        Some(read_entry(reader, entry))
+    }
+}
+
+/// Capture Load Event
+/// Emitted to the frontend while a capture file is read into the session.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum CaptureLoadEvent {
+    #[serde(rename_all = "camelCase")]
+    Packets {
+        capture_id: CaptureId,
+        /// Index of the first row in the capture, starting at 0.
+        offset: usize,
+        rows: Vec<PacketRow>,
+        /// Percentage of the file read so far.
+        progress: f64,
+    },
+    #[serde(rename_all = "camelCase")]
+    Loaded {
+        capture_id: CaptureId,
+        packet_count: usize,
+    },
+}
+
+/// Capture Info
+/// Describes an open capture to the frontend.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureInfo {
+    pub id: CaptureId,
+    pub path: String,
+    pub link_type: u32,
+    pub resolution: TimestampResolution,
+    pub packet_count: usize,
+}
+
+/// Session
+/// Application state shared by all Tauri commands: settings, the workspace
+/// of captures that are currently open, running live captures, directory watches
+/// and the GeoIP, ASN and OUI databases and the name resolver used for
+/// enrichment.
+#[derive(Debug, Default)]
+pub struct Session {
+    settings: Mutex<SessionSettings>,
+    captures: Mutex<BTreeMap<CaptureId, Arc<LoadedCapture>>>,
+    live_captures: Mutex<HashMap<CaptureId, LiveCaptureHandle>>,
+    watches: Mutex<HashMap<WatchId, DirectoryWatchHandle>>,
+    geoip: Mutex<Option<Arc<GeoIpDatabase>>>,
+    asn: Mutex<Option<Arc<AsnDatabase>>>,
+    dbc: Mutex<Option<Arc<DbcDatabase>>>,
+    oui: Mutex<Option<Arc<OuiDatabase>>>,
+    resolver: Arc<NameResolver>,
+    zigbee_network_key: Mutex<Option<[u8; 16]>>,
+    lorawan_keys: Mutex<Arc<HashMap<u32, SessionKeys>>>,
+    next_capture_id: AtomicU32,
+    next_watch_id: AtomicU32,
+}
+
+impl Session {
+    pub fn settings(&self) -> SessionSettings {
+        *self.settings.lock().unwrap()
+    }
+
+    pub fn set_time_display_mode(&self, mode: TimeDisplayMode) {
+        self.settings.lock().unwrap().time_display_mode = mode;
+    }
+
+    pub fn set_name_resolution(&self, mode: NameResolutionMode) {
+        self.settings.lock().unwrap().name_resolution = mode;
+    }
+
+    pub fn resolver(&self) -> Arc<NameResolver> {
+        Arc::clone(&self.resolver)
+    }
+
+    /// Reserves an id for a capture that is about to be loaded.
+    pub fn next_capture_id(&self) -> CaptureId {
+        self.next_capture_id.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    pub fn insert_capture(&self, capture: LoadedCapture) -> CaptureInfo {
+        let info = capture.info();
+        self.captures
+            .lock()
+            .unwrap()
+            .insert(capture.id, Arc::new(capture));
+        info
+    }
+
+    pub fn remove_capture(&self, id: CaptureId) -> bool {
+        self.captures.lock().unwrap().remove(&id).is_some()
+    }
+
+    pub fn capture(&self, id: CaptureId) -> Option<Arc<LoadedCapture>> {
+        self.captures.lock().unwrap().get(&id).cloned()
+    }
+
+    /// All open captures, ordered by id.
+    pub fn captures(&self) -> Vec<Arc<LoadedCapture>> {
+        self.captures.lock().unwrap().values().cloned().collect()
+    }
+
+    pub fn insert_live_capture(&self, id: CaptureId, handle: LiveCaptureHandle) {
+        self.live_captures.lock().unwrap().insert(id, handle);
+    }
+
+    /// Stops a running live capture, keeping its recent packets available until
+    /// it is closed. Returns false if no such capture runs.
+    pub fn stop_live_capture(&self, id: CaptureId) -> bool {
+        self.live_captures
+            .lock()
+            .unwrap()
+            .get(&id)
+            .is_some_and(|handle| handle.stop())
+    }
+
+    /// Pauses or resumes a running live capture. Returns false if no such
+    /// capture runs or it was already in that state.
+    pub fn pause_live_capture(&self, id: CaptureId, paused: bool) -> bool {
+        self.live_captures
+            .lock()
+            .unwrap()
+            .get(&id)
+            .is_some_and(|handle| handle.set_paused(paused))
+    }
+
+    /// Stops a live capture if needed and releases its recent packets.
+    pub fn remove_live_capture(&self, id: CaptureId) -> bool {
+        self.live_captures.lock().unwrap().remove(&id).is_some()
+    }
+
+    pub fn live_ring(&self, id: CaptureId) -> Option<Arc<LiveRing>> {
+        self.live_captures
+            .lock()
+            .unwrap()
+            .get(&id)
+            .map(|handle| handle.ring())
+    }
+
+    pub fn next_watch_id(&self) -> WatchId {
+        self.next_watch_id.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    pub fn insert_watch(&self, id: WatchId, handle: DirectoryWatchHandle) {
+        self.watches.lock().unwrap().insert(id, handle);
+    }
+
+    /// Stops a directory watch. Returns false if no such watch runs.
+    pub fn stop_watch(&self, id: WatchId) -> bool {
+        self.watches.lock().unwrap().remove(&id).is_some()
+    }
+
+    pub fn set_geoip(&self, database: Option<GeoIpDatabase>) {
+        *self.geoip.lock().unwrap() = database.map(Arc::new);
+    }
+
+    pub fn geoip(&self) -> Option<Arc<GeoIpDatabase>> {
+        self.geoip.lock().unwrap().clone()
+    }
+
+    pub fn set_asn(&self, database: Option<AsnDatabase>) {
+        *self.asn.lock().unwrap() = database.map(Arc::new);
+    }
+
+    pub fn asn(&self) -> Option<Arc<AsnDatabase>> {
+        self.asn.lock().unwrap().clone()
+    }
+
+    pub fn set_dbc(&self, database: DbcDatabase) {
+        *self.dbc.lock().unwrap() = Some(Arc::new(database));
+    }
+
+    pub fn dbc(&self) -> Option<Arc<DbcDatabase>> {
+        self.dbc.lock().unwrap().clone()
+    }
+
+    pub fn set_oui(&self, database: OuiDatabase) {
+        *self.oui.lock().unwrap() = Some(Arc::new(database));
+    }
+
+    pub fn oui(&self) -> Option<Arc<OuiDatabase>> {
+        self.oui.lock().unwrap().clone()
+    }
+
+    pub fn set_zigbee_network_key(&self, key: Option<[u8; 16]>) {
+        *self.zigbee_network_key.lock().unwrap() = key;
+    }
+
+    pub fn zigbee_network_key(&self) -> Option<[u8; 16]> {
+        *self.zigbee_network_key.lock().unwrap()
+    }
+
+    pub fn set_lorawan_keys(&self, keys: HashMap<u32, SessionKeys>) {
+        *self.lorawan_keys.lock().unwrap() = Arc::new(keys);
+    }
+
+    pub fn lorawan_keys(&self) -> Arc<HashMap<u32, SessionKeys>> {
+        self.lorawan_keys.lock().unwrap().clone()
+    }
+
+    /// Resolves a query target: a single capture, or every open capture when `id` is `None`.
+    pub fn select(&self, id: Option<CaptureId>) -> Result<Vec<Arc<LoadedCapture>>, KcpdumpError> {
+        match id {
+            Some(id) => self
+                .capture(id)
+                .map(|capture| vec![capture])
+                .ok_or(KcpdumpError::CaptureNotFound { id }),
+            None => Ok(self.captures()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timefmt::Timestamp;
+
+    fn empty_capture(id: CaptureId) -> LoadedCapture {
+        LoadedCapture::from_packets(id, &format!("capture-{}.pcap", id), 1, &[])
+    }
+
+    #[tokio::test]
+    async fn test_load_with_progress() {
+        use crate::cap::{PcapPacketHeader, PcapWriter};
+
+        let path = std::env::temp_dir().join(format!("kcpdump-load-{}.pcap", std::process::id()));
+        let mut writer = PcapWriter::new(std::fs::File::create(&path).unwrap(), 1, 65535).unwrap();
+        for ts_sec in 0..(LOAD_BATCH_SIZE as u32 + 5) {
+            let packet = PcapPacket {
+                header: PcapPacketHeader {
+                    timestamp: Timestamp::new(ts_sec, 0),
+                    incl_len: 14,
+                    orig_len: 14,
+                    link_type: None,
+                },
+                data: vec![0; 14],
+            };
+            writer.write_packet(&packet).unwrap();
+        }
+        drop(writer);
+
+        let mut events = Vec::new();
+        let path = path.to_string_lossy().into_owned();
+        let capture =
+            LoadedCapture::load_with_progress(4, &path, TimeDisplayMode::default(), None, |event| {
+                events.push(event)
+            })
+            .await
+            .unwrap();
+        assert_eq!(capture.packet_count(), LOAD_BATCH_SIZE + 5);
+        assert_eq!(
+            capture.packet(3).unwrap().unwrap().header.timestamp,
+            Timestamp::new(2, 0)
+        );
+        drop(capture);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(events.len(), 3);
+        let CaptureLoadEvent::Packets {
+            offset,
+            rows,
+            progress,
+            ..
+        } = &events[1]
+        else {
+            panic!("expected packets");
+        };
+        assert_eq!(*offset, LOAD_BATCH_SIZE);
+        assert_eq!(rows.len(), 5);
+        assert_eq!(rows[0].number, LOAD_BATCH_SIZE + 1);
+        assert_eq!(*progress, 100.0);
+        assert!(matches!(
+            events[2],
+            CaptureLoadEvent::Loaded {
+                capture_id: 4,
+                packet_count,
+            } if packet_count == LOAD_BATCH_SIZE + 5
+        ));
+    }
+
+    #[test]
+    fn test_time_display_mode_setting() {
+        let session = Session::default();
+        assert_eq!(session.settings().time_display_mode, TimeDisplayMode::Absolute);
+        session.set_time_display_mode(TimeDisplayMode::DeltaDisplayed);
+        assert_eq!(
+            session.settings().time_display_mode,
+            TimeDisplayMode::DeltaDisplayed
+        );
+    }
+
+    #[test]
+    fn test_workspace_captures() {
+        let session = Session::default();
+        let first = session.next_capture_id();
+        let second = session.next_capture_id();
+        assert_ne!(first, second);
+        session.insert_capture(empty_capture(second));
+        session.insert_capture(empty_capture(first));
+
+        let ids: Vec<_> = session.captures().iter().map(|c| c.id).collect();
+        assert_eq!(ids, vec![first, second]);
+        assert_eq!(session.select(Some(second)).unwrap().len(), 1);
+        assert_eq!(session.select(None).unwrap().len(), 2);
+
+        assert!(session.remove_capture(first));
+        assert!(!session.remove_capture(first));
+        assert!(session.select(Some(first)).is_err());
+    }
+}
@@ -0,0 +1,185 @@
+use std::fmt::Write;
+
+use base64::Engine;
+
+use crate::error::KcpdumpError;
+
+/// Bytes per line in the array formats.
+const BYTES_PER_LINE: usize = 12;
+/// Bytes per line of a hex dump, split into two groups of eight.
+const HEX_DUMP_LINE: usize = 16;
+
+/// Snippet Format
+/// Source code representations available for exporting packet bytes.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SnippetFormat {
+    /// `static const unsigned char name[N] = { 0x.., ... };`
+    C,
+    /// `const NAME: &[u8] = &[0x.., ...];`
+    Rust,
+    /// `name = b"\x..\x.."`
+    Python,
+    Base64,
+}
+
+/// Byte Range
+/// Selects part of a packet, e.g. the bytes of a single field.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ByteRange {
+    pub offset: usize,
+    pub length: usize,
+}
+
+impl ByteRange {
+    pub fn slice<'a>(&self, data: &'a [u8]) -> Result<&'a [u8], KcpdumpError> {
+        self.offset
+            .checked_add(self.length)
+            .and_then(|end| data.get(self.offset..end))
+            .ok_or_else(|| {
+                KcpdumpError::InvalidInput("Byte range exceeds packet length".to_string())
+            })
+    }
+}
+
+/// Renders `bytes` as a snippet named `name` in the requested format.
+pub fn format_bytes(bytes: &[u8], format: SnippetFormat, name: &str) -> String {
+    match format {
+        SnippetFormat::C => {
+            let mut out = format!("static const unsigned char {}[{}] = {{\n", name, bytes.len());
+            write_hex_lines(&mut out, bytes);
+            out.push_str("};\n");
+            out
+        }
+        SnippetFormat::Rust => {
+            let mut out = format!("const {}: &[u8] = &[\n", name.to_uppercase());
+            write_hex_lines(&mut out, bytes);
+            out.push_str("];\n");
+            out
+        }
+        SnippetFormat::Python => {
+            let mut out = format!("{} = (\n", name);
+            for chunk in bytes.chunks(BYTES_PER_LINE * 2) {
+                out.push_str("    b\"");
+                for byte in chunk {
+                    write!(out, "\\x{:02x}", byte).unwrap();
+                }
+                out.push_str("\"\n");
+            }
+            if bytes.is_empty() {
+                out.push_str("    b\"\"\n");
+            }
+            out.push_str(")\n");
+            out
+        }
+        SnippetFormat::Base64 => base64::engine::general_purpose::STANDARD.encode(bytes),
+    }
+}
+
+/// Renders `bytes` as a classic hex dump: the offset of each line, sixteen
+/// bytes in hex and their printable ASCII characters, like Wireshark's
+/// bytes pane.
+///
+/// ```text
+/// 0000  45 00 00 1c 00 01 00 00  40 11 f9 cc 0a 00 00 01   E.......@.......
+/// ```
+pub fn hex_dump(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for (line, chunk) in bytes.chunks(HEX_DUMP_LINE).enumerate() {
+        write!(out, "{:04x} ", line * HEX_DUMP_LINE).unwrap();
+        for index in 0..HEX_DUMP_LINE {
+            if index == HEX_DUMP_LINE / 2 {
+                out.push(' ');
+            }
+            match chunk.get(index) {
+                Some(byte) => write!(out, " {:02x}", byte).unwrap(),
+                None => out.push_str("   "),
+            }
+        }
+        out.push_str("   ");
+        out.extend(chunk.iter().map(|&byte| {
+            if byte.is_ascii_graphic() || byte == b' ' {
+                byte as char
+            } else {
+                '.'
+            }
+        }));
+        out.push('\n');
+    }
+    out
+}
+
+fn write_hex_lines(out: &mut String, bytes: &[u8]) {
+    for chunk in bytes.chunks(BYTES_PER_LINE) {
+        out.push_str("    ");
+        let line: Vec<_> = chunk.iter().map(|byte| format!("0x{:02x},", byte)).collect();
+        out.push_str(&line.join(" "));
+        out.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BYTES: [u8; 4] = [0x45, 0x00, 0x00, 0x1c];
+
+    #[test]
+    fn test_c_array() {
+        assert_eq!(
+            format_bytes(&BYTES, SnippetFormat::C, "pkt1"),
+            "static const unsigned char pkt1[4] = {\n    0x45, 0x00, 0x00, 0x1c,\n};\n"
+        );
+    }
+
+    #[test]
+    fn test_rust_slice() {
+        assert_eq!(
+            format_bytes(&BYTES, SnippetFormat::Rust, "pkt1"),
+            "const PKT1: &[u8] = &[\n    0x45, 0x00, 0x00, 0x1c,\n];\n"
+        );
+    }
+
+    #[test]
+    fn test_python_bytes() {
+        assert_eq!(
+            format_bytes(&BYTES, SnippetFormat::Python, "pkt1"),
+            "pkt1 = (\n    b\"\\x45\\x00\\x00\\x1c\"\n)\n"
+        );
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(format_bytes(&BYTES, SnippetFormat::Base64, "pkt1"), "RQAAHA==");
+    }
+
+    #[test]
+    fn test_hex_dump() {
+        let bytes: Vec<u8> = (0x41..0x53).collect();
+        assert_eq!(
+            hex_dump(&bytes),
+            format!(
+                "0000  41 42 43 44 45 46 47 48  49 4a 4b 4c 4d 4e 4f 50   ABCDEFGHIJKLMNOP\n\
+                 0010  51 52{}QR\n",
+                " ".repeat(46)
+            )
+        );
+        assert_eq!(hex_dump(&BYTES), format!("0000  45 00 00 1c{}E...\n", " ".repeat(40)));
+        assert_eq!(hex_dump(&[]), "");
+    }
+
+    #[test]
+    fn test_byte_range() {
+        let range = ByteRange {
+            offset: 2,
+            length: 2,
+        };
+        assert_eq!(range.slice(&BYTES).unwrap(), &[0x00, 0x1c]);
+        let range = ByteRange {
+            offset: 3,
+            length: 2,
+        };
+        assert!(range.slice(&BYTES).is_err());
+    }
+}
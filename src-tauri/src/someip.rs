@@ -0,0 +1,615 @@
+use std::net::Ipv4Addr;
+
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::dissect::{Dissector, FieldInfo, FieldType, FieldValue, FieldValues, PacketLayers};
+use crate::error::KcpdumpError;
+use crate::packet::UdpDatagram;
+
+/// UDP port of SOME/IP service discovery.
+pub const SOMEIP_SD_PORT: u16 = 30490;
+
+/// Message ID (service 0xffff, method 0x8100) of service discovery messages.
+const SD_SERVICE_ID: u16 = 0xffff;
+const SD_METHOD_ID: u16 = 0x8100;
+
+const HEADER_LEN: usize = 16;
+const SD_ENTRY_LEN: usize = 16;
+
+/// Flag of message types sent with SOME/IP-TP segmentation.
+const MESSAGE_TYPE_TP: u8 = 0x20;
+
+/// Name of a message type, with `-TP` appended for segmented messages.
+pub fn message_type_name(message_type: u8) -> String {
+    let name = match message_type & !MESSAGE_TYPE_TP {
+        0x00 => "REQUEST",
+        0x01 => "REQUEST_NO_RETURN",
+        0x02 => "NOTIFICATION",
+        0x80 => "RESPONSE",
+        0x81 => "ERROR",
+        _ => return format!("Unknown (0x{:02x})", message_type),
+    };
+    if message_type & MESSAGE_TYPE_TP != 0 {
+        format!("{}-TP", name)
+    } else {
+        name.to_string()
+    }
+}
+
+pub fn return_code_name(return_code: u8) -> String {
+    match return_code {
+        0x00 => "E_OK",
+        0x01 => "E_NOT_OK",
+        0x02 => "E_UNKNOWN_SERVICE",
+        0x03 => "E_UNKNOWN_METHOD",
+        0x04 => "E_NOT_READY",
+        0x05 => "E_NOT_REACHABLE",
+        0x06 => "E_TIMEOUT",
+        0x07 => "E_WRONG_PROTOCOL_VERSION",
+        0x08 => "E_WRONG_INTERFACE_VERSION",
+        0x09 => "E_MALFORMED_MESSAGE",
+        0x0a => "E_WRONG_MESSAGE_TYPE",
+        other => return format!("Unknown (0x{:02x})", other),
+    }
+    .to_string()
+}
+
+/// SD Entry
+/// A service or eventgroup entry of a service discovery message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SdEntry {
+    pub entry_type: u8,
+    /// Index of the first option of the first and second option runs.
+    pub first_option: u8,
+    pub second_option: u8,
+    /// Lengths of the two option runs.
+    pub option_counts: (u8, u8),
+    pub service_id: u16,
+    pub instance_id: u16,
+    pub major_version: u8,
+    /// Lifetime in seconds; 0 stops an offer or subscription.
+    pub ttl: u32,
+    /// Minor version of service entries.
+    pub minor_version: Option<u32>,
+    /// Eventgroup of eventgroup entries.
+    pub eventgroup_id: Option<u16>,
+}
+
+impl SdEntry {
+    pub fn is_eventgroup(&self) -> bool {
+        self.entry_type >= 0x04
+    }
+
+    pub fn name(&self) -> String {
+        let stop = self.ttl == 0;
+        match (self.entry_type, stop) {
+            (0x00, _) => "FindService",
+            (0x01, false) => "OfferService",
+            (0x01, true) => "StopOfferService",
+            (0x06, false) => "SubscribeEventgroup",
+            (0x06, true) => "StopSubscribeEventgroup",
+            (0x07, false) => "SubscribeEventgroupAck",
+            (0x07, true) => "SubscribeEventgroupNack",
+            (other, _) => return format!("Unknown entry (0x{:02x})", other),
+        }
+        .to_string()
+    }
+}
+
+/// SD Option
+/// An option of a service discovery message. Endpoint options carry an
+/// address, transport protocol and port.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SdOption {
+    pub option_type: u8,
+    pub endpoint: Option<(Ipv4Addr, u8, u16)>,
+    pub data: Vec<u8>,
+}
+
+impl SdOption {
+    pub fn name(&self) -> &'static str {
+        match self.option_type {
+            0x01 => "Configuration",
+            0x02 => "Load Balancing",
+            0x04 => "IPv4 Endpoint",
+            0x06 => "IPv6 Endpoint",
+            0x14 => "IPv4 Multicast",
+            0x16 => "IPv6 Multicast",
+            0x24 => "IPv4 SD Endpoint",
+            0x26 => "IPv6 SD Endpoint",
+            _ => "Unknown",
+        }
+    }
+}
+
+/// Service Discovery
+/// The body of a SOME/IP-SD message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceDiscovery {
+    pub flags: u8,
+    pub entries: Vec<SdEntry>,
+    pub options: Vec<SdOption>,
+}
+
+impl ServiceDiscovery {
+    pub fn reboot(&self) -> bool {
+        self.flags & 0x80 != 0
+    }
+
+    pub fn unicast(&self) -> bool {
+        self.flags & 0x40 != 0
+    }
+}
+
+impl TryFrom<&[u8]> for ServiceDiscovery {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 8 {
+            return Err(KcpdumpError::malformed(
+                0,
+                "Data too short for SOME/IP-SD message",
+            ));
+        }
+        let entries_len = BigEndian::read_u32(&data[4..8]) as usize;
+        let entries_data = data.get(8..8 + entries_len).ok_or(KcpdumpError::malformed(
+            8,
+            "SOME/IP-SD entries array exceeds message",
+        ))?;
+        if !entries_len.is_multiple_of(SD_ENTRY_LEN) {
+            return Err(KcpdumpError::malformed(
+                0,
+                "SOME/IP-SD entries array is not a multiple of 16 bytes",
+            ));
+        }
+        let entries = entries_data
+            .chunks_exact(SD_ENTRY_LEN)
+            .map(|entry| {
+                let entry_type = entry[0];
+                let eventgroup = entry_type >= 0x04;
+                SdEntry {
+                    entry_type,
+                    first_option: entry[1],
+                    second_option: entry[2],
+                    option_counts: (entry[3] >> 4, entry[3] & 0x0f),
+                    service_id: BigEndian::read_u16(&entry[4..6]),
+                    instance_id: BigEndian::read_u16(&entry[6..8]),
+                    major_version: entry[8],
+                    ttl: BigEndian::read_u24(&entry[9..12]),
+                    minor_version: (!eventgroup).then(|| BigEndian::read_u32(&entry[12..16])),
+                    eventgroup_id: eventgroup.then(|| BigEndian::read_u16(&entry[14..16])),
+                }
+            })
+            .collect();
+
+        let rest = &data[8 + entries_len..];
+        if rest.len() < 4 {
+            return Err(KcpdumpError::malformed(
+                8 + entries_len,
+                "SOME/IP-SD options array length missing",
+            ));
+        }
+        let options_len = BigEndian::read_u32(&rest[..4]) as usize;
+        let mut options_data = rest.get(4..4 + options_len).ok_or(KcpdumpError::malformed(
+            4,
+            "SOME/IP-SD options array exceeds message",
+        ))?;
+        let mut options = Vec::new();
+        while !options_data.is_empty() {
+            if options_data.len() < 3 {
+                return Err(KcpdumpError::malformed(
+                    12 + entries_len + options_len - options_data.len(),
+                    "Truncated SOME/IP-SD option",
+                ));
+            }
+            // The length counts the bytes after the type field.
+            let length = usize::from(BigEndian::read_u16(&options_data[..2]));
+            let option_type = options_data[2];
+            let body = options_data
+                .get(3..3 + length)
+                .ok_or(KcpdumpError::malformed(
+                    3,
+                    "SOME/IP-SD option exceeds options array",
+                ))?;
+            let endpoint = match option_type {
+                0x04 | 0x14 | 0x24 if body.len() >= 9 => Some((
+                    Ipv4Addr::new(body[1], body[2], body[3], body[4]),
+                    body[6],
+                    BigEndian::read_u16(&body[7..9]),
+                )),
+                _ => None,
+            };
+            options.push(SdOption {
+                option_type,
+                endpoint,
+                data: body.get(1..).unwrap_or_default().to_vec(),
+            });
+            options_data = &options_data[3 + length..];
+        }
+
+        Ok(ServiceDiscovery {
+            flags: data[0],
+            entries,
+            options,
+        })
+    }
+}
+
+/// SOME/IP Message
+/// A SOME/IP header and its payload. Service discovery messages have their
+/// body decoded as well.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SomeIpMessage {
+    pub service_id: u16,
+    pub method_id: u16,
+    /// Bytes from the request ID to the end of the payload.
+    pub length: u32,
+    pub client_id: u16,
+    pub session_id: u16,
+    pub protocol_version: u8,
+    pub interface_version: u8,
+    pub message_type: u8,
+    pub return_code: u8,
+    pub payload: Vec<u8>,
+    pub sd: Option<ServiceDiscovery>,
+}
+
+impl TryFrom<&[u8]> for SomeIpMessage {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < HEADER_LEN {
+            return Err(KcpdumpError::malformed(
+                0,
+                "Data too short for SOME/IP message",
+            ));
+        }
+        let length = BigEndian::read_u32(&data[4..8]);
+        if length < 8 {
+            return Err(KcpdumpError::malformed(
+                0,
+                "SOME/IP length shorter than the header",
+            ));
+        }
+        let payload = data
+            .get(HEADER_LEN..8 + length as usize)
+            .ok_or(KcpdumpError::malformed(
+                HEADER_LEN,
+                "SOME/IP length exceeds datagram",
+            ))?;
+        let service_id = BigEndian::read_u16(&data[0..2]);
+        let method_id = BigEndian::read_u16(&data[2..4]);
+        let sd = if service_id == SD_SERVICE_ID && method_id == SD_METHOD_ID {
+            Some(ServiceDiscovery::try_from(payload)?)
+        } else {
+            None
+        };
+        Ok(SomeIpMessage {
+            service_id,
+            method_id,
+            length,
+            client_id: BigEndian::read_u16(&data[8..10]),
+            session_id: BigEndian::read_u16(&data[10..12]),
+            protocol_version: data[12],
+            interface_version: data[13],
+            message_type: data[14],
+            return_code: data[15],
+            payload: payload.to_vec(),
+            sd,
+        })
+    }
+}
+
+impl SomeIpMessage {
+    /// Decodes a datagram on the service discovery port, or a datagram on
+    /// another port that holds exactly one well-formed SOME/IP message.
+    pub fn detect(datagram: &UdpDatagram) -> Option<Self> {
+        let message = SomeIpMessage::try_from(datagram.payload.as_slice()).ok()?;
+        if datagram.source_port == SOMEIP_SD_PORT || datagram.dest_port == SOMEIP_SD_PORT {
+            return Some(message);
+        }
+        let exact = message.length as usize + 8 == datagram.payload.len();
+        let known_type = !message_type_name(message.message_type).starts_with("Unknown");
+        (exact && message.protocol_version == 1 && known_type).then_some(message)
+    }
+
+    /// Whether the method ID names an event rather than a method.
+    pub fn is_event(&self) -> bool {
+        self.method_id & 0x8000 != 0
+    }
+
+    pub fn describe(&self) -> String {
+        if let Some(sd) = &self.sd {
+            let entries: Vec<String> = sd
+                .entries
+                .iter()
+                .map(|entry| format!("{} 0x{:04x}", entry.name(), entry.service_id))
+                .collect();
+            return format!("SOME/IP-SD {}", entries.join(", "));
+        }
+        let mut info = format!(
+            "{} Service=0x{:04x} {}=0x{:04x} Client=0x{:04x} Session={}",
+            message_type_name(self.message_type),
+            self.service_id,
+            if self.is_event() { "Event" } else { "Method" },
+            self.method_id,
+            self.client_id,
+            self.session_id
+        );
+        if self.message_type & !MESSAGE_TYPE_TP >= 0x80 {
+            info += &format!(" {}", return_code_name(self.return_code));
+        }
+        info
+    }
+}
+
+/// SOME/IP Dissector
+pub struct SomeIpDissector;
+
+const SOMEIP_FIELDS: &[FieldInfo] = &[
+    FieldInfo::new("someip.serviceid", FieldType::UInt, "Service ID"),
+    FieldInfo::new("someip.methodid", FieldType::UInt, "Method or event ID"),
+    FieldInfo::new("someip.length", FieldType::UInt, "Length"),
+    FieldInfo::new("someip.clientid", FieldType::UInt, "Client ID"),
+    FieldInfo::new("someip.sessionid", FieldType::UInt, "Session ID"),
+    FieldInfo::new("someip.protoversion", FieldType::UInt, "Protocol version"),
+    FieldInfo::new(
+        "someip.interfaceversion",
+        FieldType::UInt,
+        "Interface version",
+    ),
+    FieldInfo::new("someip.messagetype", FieldType::UInt, "Message type"),
+    FieldInfo::new("someip.returncode", FieldType::UInt, "Return code"),
+    FieldInfo::new("someip.payload", FieldType::Bytes, "Payload"),
+];
+
+impl Dissector for SomeIpDissector {
+    fn protocol(&self) -> &'static str {
+        "someip"
+    }
+
+    fn description(&self) -> &'static str {
+        "Scalable service-Oriented MiddlewarE over IP"
+    }
+
+    fn fields(&self) -> &'static [FieldInfo] {
+        SOMEIP_FIELDS
+    }
+
+    fn dissect(&self, layers: &PacketLayers, values: &mut FieldValues) {
+        let Some(message) = &layers.someip else {
+            return;
+        };
+        values.push("someip", FieldValue::Protocol);
+        values.push(
+            "someip.serviceid",
+            FieldValue::UInt(message.service_id.into()),
+        );
+        values.push(
+            "someip.methodid",
+            FieldValue::UInt(message.method_id.into()),
+        );
+        values.push("someip.length", FieldValue::UInt(message.length.into()));
+        values.push(
+            "someip.clientid",
+            FieldValue::UInt(message.client_id.into()),
+        );
+        values.push(
+            "someip.sessionid",
+            FieldValue::UInt(message.session_id.into()),
+        );
+        values.push(
+            "someip.protoversion",
+            FieldValue::UInt(message.protocol_version.into()),
+        );
+        values.push(
+            "someip.interfaceversion",
+            FieldValue::UInt(message.interface_version.into()),
+        );
+        values.push(
+            "someip.messagetype",
+            FieldValue::UInt(message.message_type.into()),
+        );
+        values.push(
+            "someip.returncode",
+            FieldValue::UInt(message.return_code.into()),
+        );
+        if message.sd.is_none() {
+            values.push("someip.payload", FieldValue::Bytes(message.payload.clone()));
+        }
+    }
+}
+
+/// SOME/IP-SD Dissector
+/// Fields of service discovery entries and options, one value per entry or
+/// option.
+pub struct SomeIpSdDissector;
+
+const SOMEIPSD_FIELDS: &[FieldInfo] = &[
+    FieldInfo::new("someipsd.flags.reboot", FieldType::Bool, "Reboot flag"),
+    FieldInfo::new("someipsd.flags.unicast", FieldType::Bool, "Unicast flag"),
+    FieldInfo::new("someipsd.entry.type", FieldType::UInt, "Entry type"),
+    FieldInfo::new(
+        "someipsd.entry.serviceid",
+        FieldType::UInt,
+        "Entry service ID",
+    ),
+    FieldInfo::new(
+        "someipsd.entry.instanceid",
+        FieldType::UInt,
+        "Entry instance ID",
+    ),
+    FieldInfo::new(
+        "someipsd.entry.majorver",
+        FieldType::UInt,
+        "Entry major version",
+    ),
+    FieldInfo::new(
+        "someipsd.entry.minorver",
+        FieldType::UInt,
+        "Entry minor version",
+    ),
+    FieldInfo::new(
+        "someipsd.entry.ttl",
+        FieldType::UInt,
+        "Entry TTL in seconds",
+    ),
+    FieldInfo::new(
+        "someipsd.entry.eventgroupid",
+        FieldType::UInt,
+        "Entry eventgroup ID",
+    ),
+    FieldInfo::new("someipsd.option.type", FieldType::UInt, "Option type"),
+    FieldInfo::new(
+        "someipsd.option.ipv4",
+        FieldType::Ipv4Address,
+        "Endpoint option address",
+    ),
+    FieldInfo::new(
+        "someipsd.option.proto",
+        FieldType::UInt,
+        "Endpoint option transport protocol",
+    ),
+    FieldInfo::new(
+        "someipsd.option.port",
+        FieldType::UInt,
+        "Endpoint option port",
+    ),
+];
+
+impl Dissector for SomeIpSdDissector {
+    fn protocol(&self) -> &'static str {
+        "someipsd"
+    }
+
+    fn description(&self) -> &'static str {
+        "SOME/IP Service Discovery"
+    }
+
+    fn fields(&self) -> &'static [FieldInfo] {
+        SOMEIPSD_FIELDS
+    }
+
+    fn dissect(&self, layers: &PacketLayers, values: &mut FieldValues) {
+        let Some(sd) = layers
+            .someip
+            .as_ref()
+            .and_then(|message| message.sd.as_ref())
+        else {
+            return;
+        };
+        values.push("someipsd", FieldValue::Protocol);
+        values.push("someipsd.flags.reboot", FieldValue::Bool(sd.reboot()));
+        values.push("someipsd.flags.unicast", FieldValue::Bool(sd.unicast()));
+        for entry in &sd.entries {
+            values.push(
+                "someipsd.entry.type",
+                FieldValue::UInt(entry.entry_type.into()),
+            );
+            values.push(
+                "someipsd.entry.serviceid",
+                FieldValue::UInt(entry.service_id.into()),
+            );
+            values.push(
+                "someipsd.entry.instanceid",
+                FieldValue::UInt(entry.instance_id.into()),
+            );
+            values.push(
+                "someipsd.entry.majorver",
+                FieldValue::UInt(entry.major_version.into()),
+            );
+            if let Some(minor) = entry.minor_version {
+                values.push("someipsd.entry.minorver", FieldValue::UInt(minor.into()));
+            }
+            values.push("someipsd.entry.ttl", FieldValue::UInt(entry.ttl.into()));
+            if let Some(eventgroup) = entry.eventgroup_id {
+                values.push(
+                    "someipsd.entry.eventgroupid",
+                    FieldValue::UInt(eventgroup.into()),
+                );
+            }
+        }
+        for option in &sd.options {
+            values.push(
+                "someipsd.option.type",
+                FieldValue::UInt(option.option_type.into()),
+            );
+            if let Some((address, protocol, port)) = option.endpoint {
+                values.push("someipsd.option.ipv4", FieldValue::Ipv4Address(address));
+                values.push("someipsd.option.proto", FieldValue::UInt(protocol.into()));
+                values.push("someipsd.option.port", FieldValue::UInt(port.into()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offer_service() -> Vec<u8> {
+        let mut body = vec![0xc0, 0, 0, 0];
+        body.extend_from_slice(&16u32.to_be_bytes());
+        // OfferService of 0x1234/0x0001 v1.0 with one option, TTL 3.
+        body.extend_from_slice(&[0x01, 0x00, 0x00, 0x10, 0x12, 0x34, 0x00, 0x01]);
+        body.extend_from_slice(&[0x01, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00]);
+        body.extend_from_slice(&12u32.to_be_bytes());
+        // IPv4 endpoint option: 192.168.1.2, UDP (17), port 30501.
+        body.extend_from_slice(&[0x00, 0x09, 0x04, 0x00, 192, 168, 1, 2, 0x00, 17, 0x77, 0x25]);
+
+        let mut message = vec![0xff, 0xff, 0x81, 0x00];
+        message.extend_from_slice(&(8 + body.len() as u32).to_be_bytes());
+        message.extend_from_slice(&[0x00, 0x00, 0x00, 0x01, 0x01, 0x01, 0x02, 0x00]);
+        message.extend_from_slice(&body);
+        message
+    }
+
+    #[test]
+    fn test_service_discovery() {
+        let message = SomeIpMessage::try_from(offer_service().as_slice()).unwrap();
+        let sd = message.sd.as_ref().unwrap();
+        assert!(sd.reboot() && sd.unicast());
+        assert_eq!(sd.entries.len(), 1);
+        let entry = &sd.entries[0];
+        assert_eq!(entry.name(), "OfferService");
+        assert_eq!(
+            (entry.service_id, entry.instance_id, entry.ttl),
+            (0x1234, 1, 3)
+        );
+        assert_eq!(entry.minor_version, Some(0));
+        assert_eq!(sd.options[0].name(), "IPv4 Endpoint");
+        assert_eq!(
+            sd.options[0].endpoint,
+            Some((Ipv4Addr::new(192, 168, 1, 2), 17, 30501))
+        );
+        assert_eq!(message.describe(), "SOME/IP-SD OfferService 0x1234");
+    }
+
+    #[test]
+    fn test_request_and_error() {
+        let mut data = vec![0x12, 0x34, 0x00, 0x05, 0, 0, 0, 10, 0x00, 0x07, 0x00, 0x2a];
+        data.extend_from_slice(&[0x01, 0x02, 0x81, 0x03, 0xab, 0xcd]);
+        let datagram = UdpDatagram {
+            source_port: 30501,
+            dest_port: 40000,
+            length: 8 + data.len() as u16,
+            checksum: 0,
+            payload: data.clone(),
+            checksum_valid: None,
+        };
+        let message = SomeIpMessage::detect(&datagram).unwrap();
+        assert_eq!(message.payload, [0xab, 0xcd]);
+        assert!(!message.is_event());
+        assert_eq!(
+            message.describe(),
+            "ERROR Service=0x1234 Method=0x0005 Client=0x0007 Session=42 E_UNKNOWN_METHOD"
+        );
+        assert_eq!(message_type_name(0x22), "NOTIFICATION-TP");
+
+        // Trailing bytes make the heuristic reject a datagram off the SD port.
+        let mut padded = datagram;
+        padded.payload.push(0);
+        assert!(SomeIpMessage::detect(&padded).is_none());
+        assert!(SomeIpMessage::try_from(&data[..12]).is_err());
+    }
+}
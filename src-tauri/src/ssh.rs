@@ -0,0 +1,291 @@
+use std::sync::Arc;
+
+use crate::dissect::PacketLayers;
+use crate::error::KcpdumpError;
+use crate::flows::FlowKey;
+use crate::reassembly::{StreamReassembler, TcpStream};
+use crate::session::LoadedCapture;
+
+/// SSH message number of key exchange initialisation.
+pub const SSH_MSG_KEXINIT: u8 = 20;
+
+/// Kex Init
+/// The algorithm name-lists of an SSH_MSG_KEXINIT message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KexInit {
+    pub kex_algorithms: String,
+    pub server_host_key_algorithms: String,
+    pub encryption_client_to_server: String,
+    pub encryption_server_to_client: String,
+    pub mac_client_to_server: String,
+    pub mac_server_to_client: String,
+    pub compression_client_to_server: String,
+    pub compression_server_to_client: String,
+}
+
+impl TryFrom<&[u8]> for KexInit {
+    type Error = KcpdumpError;
+
+    /// Parses the payload of an SSH binary packet, starting at the message number.
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.first() != Some(&SSH_MSG_KEXINIT) {
+            return Err(KcpdumpError::malformed(0, "Not an SSH KEXINIT message"));
+        }
+        // Message number and the 16 byte cookie.
+        let mut rest = data
+            .get(17..)
+            .ok_or(KcpdumpError::malformed(17, "SSH KEXINIT truncated"))?;
+        let mut name_list = || -> Result<String, Self::Error> {
+            let length = rest.get(..4).ok_or(KcpdumpError::malformed(
+                data.len() - rest.len(),
+                "SSH name-list truncated",
+            ))?;
+            let length = u32::from_be_bytes([length[0], length[1], length[2], length[3]]) as usize;
+            let list = rest
+                .get(4..4 + length)
+                .ok_or(KcpdumpError::malformed(4, "SSH name-list truncated"))?;
+            rest = &rest[4 + length..];
+            Ok(String::from_utf8_lossy(list).into_owned())
+        };
+        Ok(KexInit {
+            kex_algorithms: name_list()?,
+            server_host_key_algorithms: name_list()?,
+            encryption_client_to_server: name_list()?,
+            encryption_server_to_client: name_list()?,
+            mac_client_to_server: name_list()?,
+            mac_server_to_client: name_list()?,
+            compression_client_to_server: name_list()?,
+            compression_server_to_client: name_list()?,
+        })
+    }
+}
+
+impl KexInit {
+    /// The string a client's HASSH is the MD5 of.
+    pub fn hassh_algorithms(&self) -> String {
+        format!(
+            "{};{};{};{}",
+            self.kex_algorithms,
+            self.encryption_client_to_server,
+            self.mac_client_to_server,
+            self.compression_client_to_server
+        )
+    }
+
+    /// The string a server's HASSHServer is the MD5 of.
+    pub fn hassh_server_algorithms(&self) -> String {
+        format!(
+            "{};{};{};{}",
+            self.kex_algorithms,
+            self.encryption_server_to_client,
+            self.mac_server_to_client,
+            self.compression_server_to_client
+        )
+    }
+}
+
+/// Splits the start of one direction of an SSH connection into the
+/// identification string and the KEXINIT that follows it.
+pub fn parse_handshake(mut data: &[u8]) -> (Option<String>, Option<KexInit>) {
+    // Servers may send other lines before the identification string.
+    let banner = loop {
+        let Some(end) = data.iter().position(|&byte| byte == b'\n') else {
+            return (None, None);
+        };
+        let line = &data[..end];
+        data = &data[end + 1..];
+        if line.starts_with(b"SSH-") {
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            break String::from_utf8_lossy(line).into_owned();
+        }
+    };
+    // The first binary packet: length, padding length, payload, padding.
+    let kex_init = data.get(..5).and_then(|header| {
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let payload_len = length.checked_sub(usize::from(header[4]) + 1)?;
+        let payload = data.get(5..5 + payload_len)?;
+        KexInit::try_from(payload).ok()
+    });
+    (Some(banner), kex_init)
+}
+
+/// Ssh Fingerprint
+/// HASSH fingerprints of both ends of an SSH connection.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SshFingerprint {
+    pub source: FlowKey,
+    /// Frame number of the first packet of the connection.
+    pub first_number: usize,
+    pub client: String,
+    pub server: String,
+    /// Identification strings, e.g. `SSH-2.0-OpenSSH_9.6`.
+    pub client_banner: Option<String>,
+    pub server_banner: Option<String>,
+    pub hassh: Option<String>,
+    pub hassh_algorithms: Option<String>,
+    pub hassh_server: Option<String>,
+    pub hassh_server_algorithms: Option<String>,
+}
+
+fn fingerprint(stream: &TcpStream) -> Option<SshFingerprint> {
+    let (client_banner, client_kex) = parse_handshake(&stream.client_data.data);
+    let (server_banner, server_kex) = parse_handshake(&stream.server_data.data);
+    if client_banner.is_none() && server_banner.is_none() {
+        return None;
+    }
+    let hassh_algorithms = client_kex.as_ref().map(KexInit::hassh_algorithms);
+    let hassh_server_algorithms = server_kex.as_ref().map(KexInit::hassh_server_algorithms);
+    Some(SshFingerprint {
+        source: stream.key,
+        first_number: stream.first_number,
+        client: format!("{}:{}", stream.client.0, stream.client.1),
+        server: format!("{}:{}", stream.server.0, stream.server.1),
+        client_banner,
+        server_banner,
+        hassh: hassh_algorithms.as_deref().map(md5_hex),
+        hassh_algorithms,
+        hassh_server: hassh_server_algorithms.as_deref().map(md5_hex),
+        hassh_server_algorithms,
+    })
+}
+
+/// Computes the HASSH fingerprints of every SSH connection of the captures.
+pub fn ssh_fingerprints(
+    captures: &[Arc<LoadedCapture>],
+) -> Result<Vec<SshFingerprint>, KcpdumpError> {
+    let mut fingerprints = Vec::new();
+    for capture in captures {
+        let mut reassembler = StreamReassembler::default();
+        for (index, packet) in capture.packets().enumerate() {
+            let packet = packet?;
+            reassembler.push(&PacketLayers::decode_link(index + 1, capture.header.network, &packet));
+        }
+        fingerprints.extend(reassembler.finish().iter().filter_map(fingerprint));
+    }
+    Ok(fingerprints)
+}
+
+pub(crate) fn md5_hex(text: &str) -> String {
+    md5(text.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// MD5 (RFC 1321), which HASSH is defined over.
+fn md5(data: &[u8]) -> [u8; 16] {
+    const SHIFTS: [u32; 16] = [7, 12, 17, 22, 5, 9, 14, 20, 4, 11, 16, 23, 6, 10, 15, 21];
+    let constants: Vec<u32> = (0..64)
+        .map(|i| ((i as f64 + 1.0).sin().abs() * 4294967296.0) as u32)
+        .collect();
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64).wrapping_mul(8)).to_le_bytes());
+
+    let mut state: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+    for block in message.chunks_exact(64) {
+        let words: Vec<u32> = block
+            .chunks_exact(4)
+            .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+            .collect();
+        let [mut a, mut b, mut c, mut d] = state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let rotated = a
+                .wrapping_add(f)
+                .wrapping_add(constants[i])
+                .wrapping_add(words[g])
+                .rotate_left(SHIFTS[i / 16 * 4 + i % 4]);
+            (a, d, c) = (d, c, b);
+            b = b.wrapping_add(rotated);
+        }
+        for (value, add) in state.iter_mut().zip([a, b, c, d]) {
+            *value = value.wrapping_add(add);
+        }
+    }
+    let mut digest = [0u8; 16];
+    for (chunk, value) in digest.chunks_exact_mut(4).zip(state) {
+        chunk.copy_from_slice(&value.to_le_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kex_init(lists: [&str; 10]) -> Vec<u8> {
+        let mut payload = vec![SSH_MSG_KEXINIT];
+        payload.extend_from_slice(&[0xab; 16]);
+        for list in lists {
+            payload.extend_from_slice(&(list.len() as u32).to_be_bytes());
+            payload.extend_from_slice(list.as_bytes());
+        }
+        payload.extend_from_slice(&[0, 0, 0, 0, 0]);
+        let padding = 4;
+        let mut packet = ((payload.len() + padding + 1) as u32)
+            .to_be_bytes()
+            .to_vec();
+        packet.push(padding as u8);
+        packet.extend_from_slice(&payload);
+        packet.extend_from_slice(&[0; 4]);
+        packet
+    }
+
+    #[test]
+    fn test_md5() {
+        assert_eq!(md5_hex(""), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(md5_hex("abc"), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(
+            md5_hex(
+                "12345678901234567890123456789012345678901234567890123456789012345678901234567890"
+            ),
+            "57edf4a22be3c955ac49da2e2107b67a"
+        );
+    }
+
+    #[test]
+    fn test_handshake() {
+        let mut data = b"SSH-2.0-OpenSSH_9.6\r\n".to_vec();
+        data.extend(kex_init([
+            "curve25519-sha256,diffie-hellman-group14-sha256",
+            "ssh-ed25519",
+            "chacha20-poly1305@openssh.com,aes128-ctr",
+            "aes256-ctr",
+            "hmac-sha2-256",
+            "hmac-sha2-512",
+            "none",
+            "zlib@openssh.com",
+            "",
+            "",
+        ]));
+        let (banner, kex) = parse_handshake(&data);
+        assert_eq!(banner.as_deref(), Some("SSH-2.0-OpenSSH_9.6"));
+        let kex = kex.unwrap();
+        assert_eq!(
+            kex.hassh_algorithms(),
+            "curve25519-sha256,diffie-hellman-group14-sha256;\
+             chacha20-poly1305@openssh.com,aes128-ctr;hmac-sha2-256;none"
+        );
+        assert_eq!(
+            kex.hassh_server_algorithms(),
+            "curve25519-sha256,diffie-hellman-group14-sha256;aes256-ctr;hmac-sha2-512;zlib@openssh.com"
+        );
+
+        // Only the banner arrived.
+        let (banner, kex) = parse_handshake(b"Welcome\r\nSSH-2.0-dropbear\r\n");
+        assert_eq!(banner.as_deref(), Some("SSH-2.0-dropbear"));
+        assert_eq!(kex, None);
+        assert_eq!(parse_handshake(b"GET / HTTP/1.1\r\n"), (None, None));
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::{BTreeMap, HashMap};
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
+
+use crate::cap::Capture;
+use crate::dissect::PacketLayers;
+use crate::error::KcpdumpError;
+use crate::expert::{ExpertAnalyzer, ExpertSeverity};
+use crate::packetlist;
+use crate::session::LoadedCapture;
+use crate::summary;
+use crate::timefmt::Timestamp;
+
+/// Number of entries in the top-N lists.
+const TOP_N: usize = 5;
+
+/// Ranked Entry
+/// One line of a top-N table.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RankedEntry {
+    pub name: String,
+    pub packets: usize,
+    pub bytes: u64,
+}
+
+/// Capture Summary
+/// Headline statistics for a dashboard view.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureSummary {
+    pub packets: usize,
+    pub bytes: u64,
+    /// Seconds between the first and the last packet.
+    pub duration: f64,
+    pub avg_pps: f64,
+    pub avg_bps: f64,
+    /// Highest decoded protocols by packet count.
+    pub top_protocols: Vec<RankedEntry>,
+    /// IP endpoints by bytes sent and received.
+    pub top_talkers: Vec<RankedEntry>,
+    /// Expert info item counts per severity.
+    pub alerts: BTreeMap<ExpertSeverity, usize>,
+}
+
+#[derive(Default)]
+struct Counter {
+    packets: usize,
+    bytes: u64,
+}
+
+impl Counter {
+    fn add(&mut self, bytes: u64) {
+        self.packets += 1;
+        self.bytes += bytes;
+    }
+}
+
+/// Computes the summary of the given captures in a single pass.
+pub fn capture_summary(captures: &[Arc<LoadedCapture>]) -> Result<CaptureSummary, KcpdumpError> {
+    let mut packets = 0;
+    let mut bytes = 0u64;
+    let mut first = None;
+    let mut last = None;
+    let mut protocols: HashMap<String, Counter> = HashMap::new();
+    let mut talkers: HashMap<IpAddr, Counter> = HashMap::new();
+    let mut alerts = BTreeMap::new();
+    // Sequence analysis is per capture, so each capture gets its own analyzer.
+    let mut analyzers: HashMap<u32, ExpertAnalyzer> = HashMap::new();
+    let link_types = packetlist::link_types(captures);
+
+    for item in packetlist::merged_packets(captures) {
+        let (capture_id, number, packet) = item?;
+        let length = u64::from(packet.header.orig_len);
+        let micros = packet.header.timestamp.as_micros();
+        packets += 1;
+        bytes += length;
+        first = Some(first.map_or(micros, |first: i64| first.min(micros)));
+        last = Some(last.map_or(micros, |last: i64| last.max(micros)));
+
+        let protocol = summary::summarize_link(link_types[&capture_id], &packet.data).protocol;
+        protocols.entry(protocol).or_default().add(length);
+
+        let layers = PacketLayers::decode_link(number, link_types[&capture_id], &packet);
+        if let Some(ip) = &layers.ipv4 {
+            for address in [ip.source_ip, ip.dest_ip] {
+                talkers
+                    .entry(IpAddr::V4(Ipv4Addr::from(address)))
+                    .or_default()
+                    .add(length);
+            }
+        }
+        for item in analyzers.entry(capture_id).or_default().analyze(&layers) {
+            *alerts.entry(item.severity).or_default() += 1;
+        }
+    }
+
+    let duration = match (first, last) {
+        (Some(first), Some(last)) => (last - first) as f64 / 1_000_000.0,
+        _ => 0.0,
+    };
+    let (avg_pps, avg_bps) = if duration > 0.0 {
+        (packets as f64 / duration, bytes as f64 * 8.0 / duration)
+    } else {
+        (0.0, 0.0)
+    };
+
+    Ok(CaptureSummary {
+        packets,
+        bytes,
+        duration,
+        avg_pps,
+        avg_bps,
+        top_protocols: top(protocols, |entry| entry.packets as u64),
+        top_talkers: top(talkers, |entry| entry.bytes),
+        alerts,
+    })
+}
+
+/// Capture File Info
+/// File-level facts about a capture, read from its record headers alone.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureFileInfo {
+    pub version_major: u16,
+    pub version_minor: u16,
+    pub link_type: u32,
+    pub snaplen: u32,
+    pub packets: usize,
+    /// Sum of the original packet lengths.
+    pub bytes: u64,
+    /// Earliest and latest packet times, which differ from the first and
+    /// last records when packets are out of order.
+    pub first_timestamp: Option<Timestamp>,
+    pub last_timestamp: Option<Timestamp>,
+    /// Seconds between the first and the last packet.
+    pub duration: f64,
+    pub avg_pps: f64,
+}
+
+/// Reads the file header and every record header of a capture in one pass.
+/// Packet data is skipped rather than decoded, so large files are read with
+/// a constant amount of memory.
+pub async fn capture_file_info(path: &str) -> Result<CaptureFileInfo, KcpdumpError> {
+    let mut capture = Capture::from_file(path).await?;
+    let mut packets = 0;
+    let mut bytes = 0u64;
+    let mut first: Option<Timestamp> = None;
+    let mut last: Option<Timestamp> = None;
+    while let Some(header) = capture.next_header().await? {
+        packets += 1;
+        bytes += u64::from(header.orig_len);
+        first = Some(first.map_or(header.timestamp, |first| first.min(header.timestamp)));
+        last = Some(last.map_or(header.timestamp, |last| last.max(header.timestamp)));
+    }
+
+    let duration = match (first, last) {
+        (Some(first), Some(last)) => (last.as_nanos() - first.as_nanos()) as f64 / 1e9,
+        _ => 0.0,
+    };
+    let avg_pps = if duration > 0.0 {
+        packets as f64 / duration
+    } else {
+        0.0
+    };
+    let header = capture.header();
+    Ok(CaptureFileInfo {
+        version_major: header.version_major,
+        version_minor: header.version_minor,
+        link_type: header.network,
+        snaplen: header.snaplen,
+        packets,
+        bytes,
+        first_timestamp: first,
+        last_timestamp: last,
+        duration,
+        avg_pps,
+    })
+}
+
+/// Ranks counters by `key`, descending, with ties broken by name.
+fn top<K: ToString>(
+    counters: HashMap<K, Counter>,
+    key: impl Fn(&RankedEntry) -> u64,
+) -> Vec<RankedEntry> {
+    let mut entries: Vec<_> = counters
+        .into_iter()
+        .map(|(name, counter)| RankedEntry {
+            name: name.to_string(),
+            packets: counter.packets,
+            bytes: counter.bytes,
+        })
+        .collect();
+    entries.sort_by(|a, b| key(b).cmp(&key(a)).then_with(|| a.name.cmp(&b.name)));
+    entries.truncate(TOP_N);
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cap::{PcapPacket, PcapPacketHeader, PcapWriter};
+
+    #[tokio::test]
+    async fn test_capture_summary() {
+        let capture = Arc::new(LoadedCapture::load(1, "sample.pcap").await.unwrap());
+        let summary = capture_summary(std::slice::from_ref(&capture)).unwrap();
+        assert_eq!(summary.packets, capture.packet_count());
+        assert!(summary.bytes > 0);
+        assert!(summary.top_protocols.len() <= TOP_N);
+        assert!(
+            summary
+                .top_protocols
+                .iter()
+                .any(|entry| entry.name == "TCP")
+        );
+        assert!(!summary.top_talkers.is_empty());
+        assert!(
+            summary
+                .top_talkers
+                .windows(2)
+                .all(|pair| pair[0].bytes >= pair[1].bytes)
+        );
+        assert!(
+            summary
+                .alerts
+                .get(&ExpertSeverity::Chat)
+                .is_some_and(|&count| count > 0)
+        );
+        if summary.duration > 0.0 {
+            assert!(summary.avg_pps > 0.0);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_capture_file_info() {
+        let temp_file_path = "test_file_info.pcap";
+        let mut writer = PcapWriter::new(Vec::new(), 1, 65535).unwrap();
+        // Out of order: the earliest packet is the second record.
+        for (sec, length) in [(1_700_000_002, 60u32), (1_700_000_000, 1500), (1_700_000_004, 40)] {
+            writer
+                .write_packet(&PcapPacket {
+                    header: PcapPacketHeader {
+                        timestamp: Timestamp::from_micros(sec, 500_000),
+                        incl_len: length.min(64),
+                        orig_len: length,
+                        link_type: None,
+                    },
+                    data: vec![0; length.min(64) as usize],
+                })
+                .unwrap();
+        }
+        tokio::fs::write(temp_file_path, writer.into_inner()).await.unwrap();
+
+        let info = capture_file_info(temp_file_path).await.unwrap();
+        tokio::fs::remove_file(temp_file_path).await.unwrap();
+        assert_eq!((info.version_major, info.version_minor), (2, 4));
+        assert_eq!((info.link_type, info.snaplen), (1, 65535));
+        assert_eq!(info.packets, 3);
+        assert_eq!(info.bytes, 1600);
+        assert_eq!(info.first_timestamp, Some(Timestamp::from_micros(1_700_000_000, 500_000)));
+        assert_eq!(info.last_timestamp, Some(Timestamp::from_micros(1_700_000_004, 500_000)));
+        assert_eq!(info.duration, 4.0);
+        assert_eq!(info.avg_pps, 0.75);
+    }
+
+    #[test]
+    fn test_empty_summary() {
+        let summary = capture_summary(&[]).unwrap();
+        assert_eq!(summary.packets, 0);
+        assert_eq!(summary.duration, 0.0);
+        assert!(summary.top_protocols.is_empty());
+    }
+}
@@ -0,0 +1,285 @@
+use std::net::Ipv4Addr;
+
+use crate::bluetooth::{self, LINKTYPE_BLUETOOTH_HCI_H4_WITH_PHDR};
+use crate::can::{self, LINKTYPE_CAN_SOCKETCAN};
+use crate::capwap::{CAPWAP_CONTROL_PORT, CAPWAP_DATA_PORT, CapwapPacket, ControlMessage};
+use crate::dccp::DccpPacket;
+use crate::erspan::{ErspanPacket, IP_PROTOCOL_GRE};
+use crate::flows::FlowKey;
+use crate::icmp::IcmpMessage;
+use crate::ieee802154::{self, LINKTYPE_IEEE802_15_4_NOFCS, LINKTYPE_IEEE802_15_4_WITHFCS};
+use crate::link::{self, LINKTYPE_ETHERNET, LinkTypeError};
+use crate::lorawan::{self, LINKTYPE_LORATAP};
+use crate::packet::{
+    EtherType, EthernetPacket, IPv4Packet, IpProtocol, TcpFlags, TcpSegment, UdpDatagram,
+};
+use crate::ppp::{self, LINKTYPE_C_HDLC, LINKTYPE_PPP, LINKTYPE_PPP_SERIAL};
+use crate::ptp::{ETHERTYPE_PTP, PTP_EVENT_PORT, PTP_GENERAL_PORT, PtpMessage};
+use crate::someip::SomeIpMessage;
+use crate::tzsp::{TZSP_PORT, TzspPacket};
+use crate::udplite::UdpLiteDatagram;
+use crate::usb::{self, LINKTYPE_USBPCAP};
+use crate::wlan::{self, LINKTYPE_IEEE802_11, LINKTYPE_IEEE802_11_RADIOTAP};
+
+/// Packet Summary
+/// The protocol-dependent columns of one row in a packet list:
+/// highest decoded layer, its endpoints and a one-line description.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PacketSummary {
+    pub source: String,
+    pub destination: String,
+    pub protocol: String,
+    pub length: usize,
+    pub info: String,
+    pub flow: Option<FlowKey>,
+}
+
+/// Summarizes an Ethernet frame.
+pub fn summarize(frame: &[u8]) -> PacketSummary {
+    let Ok(eth_packet) = EthernetPacket::try_from(frame) else {
+        return PacketSummary {
+            source: String::new(),
+            destination: String::new(),
+            protocol: "Malformed".to_string(),
+            length: frame.len(),
+            info: "Frame too short for Ethernet".to_string(),
+            flow: None,
+        };
+    };
+
+    let mut summary = PacketSummary {
+        source: eth_packet.header.src_mac.to_string(),
+        destination: eth_packet.header.dest_mac.to_string(),
+        protocol: format!("{:?}", eth_packet.header.ether_type),
+        length: frame.len(),
+        info: String::new(),
+        flow: None,
+    };
+
+    match eth_packet.header.ether_type {
+        EtherType::IPv4 => match IPv4Packet::try_from(eth_packet.data.as_slice()) {
+            Ok(ipv4_packet) => summarize_ipv4(&ipv4_packet, &mut summary),
+            Err(e) => summary.info = e.to_string(),
+        },
+        EtherType::Unknown(ETHERTYPE_PTP) => {
+            match PtpMessage::try_from(eth_packet.data.as_slice()) {
+                Ok(message) => {
+                    summary.protocol = "PTPv2".to_string();
+                    summary.info = message.describe();
+                }
+                Err(e) => summary.info = e.to_string(),
+            }
+        }
+        EtherType::Unknown(value) => {
+            summary.protocol = format!("0x{:04x}", value);
+            summary.info = format!("Ethernet II, type 0x{:04x}", value);
+        }
+        ether_type => summary.info = format!("{:?} packet", ether_type),
+    }
+
+    summary
+}
+
+/// Summarizes a frame of the given link type. Frames of link types without
+/// a decoder are reported as unsupported.
+pub fn summarize_link(link_type: u32, frame: &[u8]) -> PacketSummary {
+    match link_type {
+        LINKTYPE_ETHERNET => summarize(frame),
+        link_type if link::is_supported(link_type) => link::summarize(link_type, frame),
+        LINKTYPE_BLUETOOTH_HCI_H4_WITH_PHDR => bluetooth::summarize(frame),
+        LINKTYPE_USBPCAP => usb::summarize(frame),
+        LINKTYPE_CAN_SOCKETCAN => can::summarize(frame),
+        LINKTYPE_IEEE802_15_4_WITHFCS | LINKTYPE_IEEE802_15_4_NOFCS => {
+            ieee802154::summarize(link_type, frame)
+        }
+        LINKTYPE_LORATAP => lorawan::summarize(frame),
+        LINKTYPE_IEEE802_11 | LINKTYPE_IEEE802_11_RADIOTAP => wlan::summarize(link_type, frame),
+        LINKTYPE_PPP | LINKTYPE_PPP_SERIAL | LINKTYPE_C_HDLC => ppp::summarize(link_type, frame),
+        _ => PacketSummary {
+            source: String::new(),
+            destination: String::new(),
+            protocol: format!("DLT {}", link_type),
+            length: frame.len(),
+            info: LinkTypeError::Unsupported(link_type).to_string(),
+            flow: None,
+        },
+    }
+}
+
+pub(crate) fn summarize_ipv4(ipv4_packet: &IPv4Packet, summary: &mut PacketSummary) {
+    summary.source = Ipv4Addr::from(ipv4_packet.source_ip).to_string();
+    summary.destination = Ipv4Addr::from(ipv4_packet.dest_ip).to_string();
+    summary.protocol = "IPv4".to_string();
+    summary.flow = Some(FlowKey::from_ipv4(ipv4_packet));
+
+    let payload = ipv4_packet.payload.as_slice();
+    if ipv4_packet.protocol == IP_PROTOCOL_GRE
+        && let Ok(erspan) = ErspanPacket::from_gre(payload)
+    {
+        *summary = PacketSummary {
+            length: summary.length,
+            ..summarize(&erspan.frame)
+        };
+        return;
+    }
+    summary.info = match IpProtocol::from(ipv4_packet.protocol) {
+        IpProtocol::TCP => match TcpSegment::try_from(payload) {
+            Ok(segment) => {
+                summary.protocol = "TCP".to_string();
+                tcp_info(&segment)
+            }
+            Err(e) => e.to_string(),
+        },
+        IpProtocol::UDP => match UdpDatagram::try_from(payload) {
+            Ok(datagram) => {
+                if let Some(inner) = tunnel_summary(&datagram) {
+                    *summary = PacketSummary {
+                        length: summary.length,
+                        ..inner
+                    };
+                    return;
+                }
+                if let Some((protocol, info)) = application_info(&datagram) {
+                    summary.protocol = protocol.to_string();
+                    info
+                } else {
+                    summary.protocol = "UDP".to_string();
+                    format!(
+                        "{} → {} Len={}",
+                        datagram.source_port,
+                        datagram.dest_port,
+                        datagram.payload.len()
+                    )
+                }
+            }
+            Err(e) => e.to_string(),
+        },
+        IpProtocol::UDPLite => match UdpLiteDatagram::try_from(payload) {
+            Ok(datagram) => {
+                summary.protocol = "UDPlite".to_string();
+                format!(
+                    "{} → {} Len={} Coverage={}",
+                    datagram.source_port,
+                    datagram.dest_port,
+                    datagram.payload.len(),
+                    datagram.covered_len()
+                )
+            }
+            Err(e) => e.to_string(),
+        },
+        IpProtocol::DCCP => match DccpPacket::try_from(payload) {
+            Ok(packet) => {
+                summary.protocol = "DCCP".to_string();
+                packet.describe()
+            }
+            Err(e) => e.to_string(),
+        },
+        IpProtocol::ICMP => {
+            summary.protocol = "ICMP".to_string();
+            icmp_info(payload)
+        }
+        IpProtocol::Unknown(protocol) => format!("IPv4 protocol {}", protocol),
+    };
+}
+
+/// Summarizes the frame streamed in a TZSP or tunneled in a CAPWAP data
+/// channel datagram instead of the datagram itself.
+fn tunnel_summary(datagram: &UdpDatagram) -> Option<PacketSummary> {
+    let ports = [datagram.source_port, datagram.dest_port];
+    let payload = datagram.payload.as_slice();
+    if ports.contains(&TZSP_PORT) {
+        let tzsp = TzspPacket::try_from(payload).ok()?;
+        return Some(summarize_link(tzsp.link_type()?, &tzsp.frame));
+    }
+    if ports.contains(&CAPWAP_DATA_PORT) {
+        let capwap = CapwapPacket::try_from(payload).ok()?;
+        return Some(summarize_link(capwap.link_type()?, &capwap.payload));
+    }
+    None
+}
+
+/// Protocol and description of the application messages shown in place of
+/// the UDP header: CAPWAP control, PTP and SOME/IP.
+fn application_info(datagram: &UdpDatagram) -> Option<(&'static str, String)> {
+    let ports = [datagram.source_port, datagram.dest_port];
+    let payload = datagram.payload.as_slice();
+    if ports.contains(&CAPWAP_CONTROL_PORT) {
+        let capwap = CapwapPacket::try_from(payload).ok()?;
+        if capwap.dtls {
+            return Some((
+                "CAPWAP-Control",
+                "DTLS encrypted control message".to_string(),
+            ));
+        }
+        let message = ControlMessage::try_from(capwap.payload.as_slice()).ok()?;
+        return Some(("CAPWAP-Control", message.name()));
+    }
+    if [PTP_EVENT_PORT, PTP_GENERAL_PORT].contains(&datagram.dest_port) {
+        let message = PtpMessage::try_from(payload).ok()?;
+        return Some(("PTPv2", message.describe()));
+    }
+    let message = SomeIpMessage::detect(datagram)?;
+    let protocol = if message.sd.is_some() {
+        "SOME/IP-SD"
+    } else {
+        "SOME/IP"
+    };
+    Some((protocol, message.describe()))
+}
+
+fn tcp_info(segment: &TcpSegment) -> String {
+    let mut info = format!(
+        "{} → {} [{}] Seq={}",
+        segment.source_port,
+        segment.dest_port,
+        segment.flags.names().join(", "),
+        segment.sequence_number
+    );
+    if segment.flags.contains(TcpFlags::ACK) {
+        info += &format!(" Ack={}", segment.ack_number);
+    }
+    info += &format!(" Win={} Len={}", segment.window_size, segment.payload.len());
+    info
+}
+
+fn icmp_info(data: &[u8]) -> String {
+    match IcmpMessage::try_from(data) {
+        Ok(message) => message.describe(),
+        Err(_) => "Truncated ICMP message".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize_tcp() {
+        let frame: [u8; 54] = [
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0x08, 0x00,
+            0x45, 0x00, 0x00, 0x28, 0x00, 0x01, 0x40, 0x00, 0x40, 0x06, 0x00, 0x00, 0xc0, 0xa8,
+            0x00, 0x0a, 0x5d, 0xb8, 0xd8, 0x22, 0xc3, 0x50, 0x00, 0x50, 0x00, 0x00, 0x03, 0xe8,
+            0x00, 0x00, 0x00, 0x00, 0x50, 0x02, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let summary = summarize(&frame);
+        assert_eq!(summary.source, "192.168.0.10");
+        assert_eq!(summary.destination, "93.184.216.34");
+        assert_eq!(summary.protocol, "TCP");
+        assert_eq!(summary.length, 54);
+        assert_eq!(summary.info, "50000 → 80 [SYN] Seq=1000 Win=65535 Len=0");
+        assert!(summary.flow.is_some());
+    }
+
+    #[test]
+    fn test_summarize_non_ip() {
+        let frame: [u8; 14] = [
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0x88, 0xcc,
+        ];
+        let summary = summarize(&frame);
+        assert_eq!(summary.source, "66:77:88:99:AA:BB");
+        assert_eq!(summary.protocol, "0x88cc");
+        assert!(summary.flow.is_none());
+        assert_eq!(summarize(&frame[..10]).protocol, "Malformed");
+    }
+}
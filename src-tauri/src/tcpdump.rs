@@ -0,0 +1,194 @@
+use std::net::Ipv4Addr;
+
+use tokio::io;
+
+use crate::cap::Capture;
+use crate::dccp::DccpPacket;
+use crate::filter::PacketFilter;
+use crate::packet::{
+    EtherType, EthernetPacket, IPv4Packet, IpProtocol, TcpFlags, TcpSegment, UdpDatagram,
+};
+use crate::timefmt::{TimeDisplayMode, TimeFormatter};
+use crate::udplite::UdpLiteDatagram;
+
+/// Formats one Ethernet frame as a classic tcpdump summary line, without the timestamp.
+pub fn format_frame(frame: &[u8]) -> String {
+    let Ok(eth_packet) = EthernetPacket::try_from(frame) else {
+        return "[|ether]".to_string();
+    };
+    match eth_packet.header.ether_type {
+        EtherType::IPv4 => match IPv4Packet::try_from(eth_packet.data.as_slice()) {
+            Ok(ipv4_packet) => format_ipv4(&ipv4_packet),
+            Err(_) => "IP [|ip]".to_string(),
+        },
+        EtherType::IPv6 => format!("IP6, length {}", eth_packet.data.len()),
+        EtherType::ARP => format!("ARP, length {}", eth_packet.data.len()),
+        EtherType::Unknown(value) => {
+            format!("ethertype 0x{:04x}, length {}", value, eth_packet.data.len())
+        }
+    }
+}
+
+fn format_ipv4(ipv4_packet: &IPv4Packet) -> String {
+    let src = Ipv4Addr::from(ipv4_packet.source_ip);
+    let dst = Ipv4Addr::from(ipv4_packet.dest_ip);
+    let payload = ipv4_packet.payload.as_slice();
+    match IpProtocol::from(ipv4_packet.protocol) {
+        IpProtocol::TCP => match TcpSegment::try_from(payload) {
+            Ok(segment) => format!(
+                "IP {}.{} > {}.{}: {}",
+                src,
+                segment.source_port,
+                dst,
+                segment.dest_port,
+                format_tcp(&segment)
+            ),
+            Err(_) => format!("IP {} > {}: tcp [|tcp]", src, dst),
+        },
+        IpProtocol::UDP => match UdpDatagram::try_from(payload) {
+            Ok(datagram) => format!(
+                "IP {}.{} > {}.{}: UDP, length {}",
+                src,
+                datagram.source_port,
+                dst,
+                datagram.dest_port,
+                datagram.payload.len()
+            ),
+            Err(_) => format!("IP {} > {}: udp [|udp]", src, dst),
+        },
+        IpProtocol::UDPLite => match UdpLiteDatagram::try_from(payload) {
+            Ok(datagram) => format!(
+                "IP {}.{} > {}.{}: UDPLITE, length {}",
+                src,
+                datagram.source_port,
+                dst,
+                datagram.dest_port,
+                datagram.payload.len()
+            ),
+            Err(_) => format!("IP {} > {}: udplite [|udplite]", src, dst),
+        },
+        IpProtocol::DCCP => match DccpPacket::try_from(payload) {
+            Ok(packet) => format!(
+                "IP {}.{} > {}.{}: DCCP {:?} (seq={})",
+                src, packet.source_port, dst, packet.dest_port, packet.packet_type, packet.sequence
+            ),
+            Err(_) => format!("IP {} > {}: dccp [|dccp]", src, dst),
+        },
+        IpProtocol::ICMP => format!("IP {} > {}: {}", src, dst, format_icmp(payload)),
+        IpProtocol::Unknown(protocol) => {
+            format!("IP {} > {}: ip-proto-{} {}", src, dst, protocol, payload.len())
+        }
+    }
+}
+
+fn format_tcp(segment: &TcpSegment) -> String {
+    let mut line = format!("Flags [{}]", segment.flags);
+    let length = segment.payload.len() as u32;
+    if length > 0 {
+        line += &format!(
+            ", seq {}:{}",
+            segment.sequence_number,
+            segment.sequence_number.wrapping_add(length)
+        );
+    } else if segment.flags.0 & (TcpFlags::SYN | TcpFlags::FIN | TcpFlags::RST) != 0 {
+        line += &format!(", seq {}", segment.sequence_number);
+    }
+    if segment.flags.contains(TcpFlags::ACK) {
+        line += &format!(", ack {}", segment.ack_number);
+    }
+    line += &format!(", win {}, length {}", segment.window_size, length);
+    line
+}
+
+fn format_icmp(data: &[u8]) -> String {
+    if data.len() < 8 {
+        return "ICMP [|icmp]".to_string();
+    }
+    let id = u16::from_be_bytes([data[4], data[5]]);
+    let seq = u16::from_be_bytes([data[6], data[7]]);
+    match (data[0], data[1]) {
+        (8, 0) => format!("ICMP echo request, id {}, seq {}, length {}", id, seq, data.len()),
+        (0, 0) => format!("ICMP echo reply, id {}, seq {}, length {}", id, seq, data.len()),
+        (icmp_type, code) => format!("ICMP type-#{} code {}, length {}", icmp_type, code, data.len()),
+    }
+}
+
+/// Renders every packet of a capture that passes `filter` as tcpdump lines,
+/// prefixed with the timestamp in the requested display mode.
+pub async fn export_lines(
+    file_path: &str,
+    filter: &PacketFilter,
+    mode: TimeDisplayMode,
+) -> io::Result<Vec<String>> {
+    let mut capture = Capture::from_file(file_path).await?;
+    let mut formatter = TimeFormatter::with_resolution(mode, capture.resolution());
+    let mut lines = Vec::new();
+
+    while let Some(raw_packet) = capture.next_packet().await? {
+        let timestamp = raw_packet.header.timestamp;
+        if !filter.matches_frame(timestamp, &raw_packet.data) {
+            formatter.skip(timestamp);
+            continue;
+        }
+        let time = formatter.format(timestamp);
+        lines.push(format!("{} {}", time, format_frame(&raw_packet.data)));
+    }
+
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ipv4_frame(protocol: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0x08, 0x00,
+        ];
+        let total_length = (20 + payload.len()) as u16;
+        frame.extend_from_slice(&[0x45, 0x00]);
+        frame.extend_from_slice(&total_length.to_be_bytes());
+        frame.extend_from_slice(&[0x00, 0x01, 0x40, 0x00, 0x40, protocol, 0x00, 0x00]);
+        frame.extend_from_slice(&[192, 168, 0, 10, 93, 184, 216, 34]);
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    #[test]
+    fn test_format_tcp_syn() {
+        let tcp: [u8; 20] = [
+            0xc3, 0x50, 0x00, 0x50, 0x00, 0x00, 0x03, 0xe8, 0x00, 0x00, 0x00, 0x00, 0x50, 0x02,
+            0xff, 0xff, 0x00, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(
+            format_frame(&ipv4_frame(6, &tcp)),
+            "IP 192.168.0.10.50000 > 93.184.216.34.80: Flags [S], seq 1000, win 65535, length 0"
+        );
+    }
+
+    #[test]
+    fn test_format_tcp_data() {
+        let tcp: [u8; 22] = [
+            0xc3, 0x50, 0x00, 0x50, 0x00, 0x00, 0x03, 0xe9, 0x00, 0x00, 0x13, 0x89, 0x50, 0x18,
+            0x01, 0x00, 0x00, 0x00, 0x00, 0x00, b'h', b'i',
+        ];
+        assert_eq!(
+            format_frame(&ipv4_frame(6, &tcp)),
+            "IP 192.168.0.10.50000 > 93.184.216.34.80: Flags [P.], seq 1001:1003, ack 5001, win 256, length 2"
+        );
+    }
+
+    #[test]
+    fn test_format_udp_and_icmp() {
+        let udp: [u8; 10] = [0xcf, 0x08, 0x00, 0x35, 0x00, 0x0a, 0x00, 0x00, 0x01, 0x02];
+        assert_eq!(
+            format_frame(&ipv4_frame(17, &udp)),
+            "IP 192.168.0.10.53000 > 93.184.216.34.53: UDP, length 2"
+        );
+        let icmp: [u8; 8] = [0x08, 0x00, 0x00, 0x00, 0x00, 0x42, 0x00, 0x01];
+        assert_eq!(
+            format_frame(&ipv4_frame(1, &icmp)),
+            "IP 192.168.0.10 > 93.184.216.34: ICMP echo request, id 66, seq 1, length 8"
+        );
+    }
+}
@@ -0,0 +1,232 @@
+use std::fs::File;
+use std::io::BufWriter;
+
+use crate::cap::{PcapPacket, PcapPacketHeader, PcapWriter};
+use crate::error::KcpdumpError;
+use crate::timefmt::Timestamp;
+
+/// Hex Import Options
+/// Link type and fake timestamps for packets imported from a hex dump.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HexImportOptions {
+    pub link_type: u32,
+    /// Timestamp of the first packet in milliseconds since the Unix epoch.
+    pub start_time: i64,
+    /// Gap between consecutive packets in microseconds.
+    pub interval_usec: u32,
+}
+
+impl Default for HexImportOptions {
+    fn default() -> Self {
+        HexImportOptions {
+            link_type: 1,
+            start_time: 0,
+            interval_usec: 1000,
+        }
+    }
+}
+
+/// Splits a hex dump into packets.
+///
+/// Every line that carries data starts with a hexadecimal offset, as produced by
+/// `od -Ax -tx1`, `tcpdump -xx` (`0x0010:` and 4-digit groups) or Wireshark's
+/// "Copy as Hex Dump". An offset of zero starts a new packet; lines without an
+/// offset, such as tcpdump's summary lines, are ignored. A trailing ASCII column
+/// is recognised by the wider gap in front of it, and any bytes misread from it
+/// are dropped again when the next line's offset shows the real packet length.
+pub fn parse_hex_dump(text: &str) -> Result<Vec<Vec<u8>>, KcpdumpError> {
+    let mut packets = Vec::new();
+    let mut current: Vec<u8> = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let Some((offset, rest)) = split_offset(line) else {
+            continue;
+        };
+        if offset == 0 && !current.is_empty() {
+            packets.push(std::mem::take(&mut current));
+        }
+        if offset < current.len() {
+            current.truncate(offset);
+        } else if offset > current.len() {
+            return Err(KcpdumpError::InvalidInput(format!(
+                "Line {}: offset {:#x} does not follow previous data ({:#x} bytes)",
+                index + 1,
+                offset,
+                current.len()
+            )));
+        }
+        parse_bytes(rest, &mut current);
+    }
+    if !current.is_empty() {
+        packets.push(current);
+    }
+    if packets.is_empty() {
+        return Err(KcpdumpError::InvalidInput("No hex data found".to_string()));
+    }
+    Ok(packets)
+}
+
+/// Returns the offset at the start of `line` and the text following it.
+fn split_offset(line: &str) -> Option<(usize, &str)> {
+    let line = line.trim_start();
+    let end = line.find(char::is_whitespace)?;
+    let token = line[..end].trim_end_matches(':');
+    let digits = token
+        .strip_prefix("0x")
+        .or_else(|| token.strip_prefix("0X"))
+        .unwrap_or(token);
+    if digits.len() < 2 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let offset = usize::from_str_radix(digits, 16).ok()?;
+    Some((offset, line[end..].trim_start()))
+}
+
+/// Appends the hex byte groups of one line, stopping at the ASCII column.
+fn parse_bytes(rest: &str, data: &mut Vec<u8>) {
+    let mut remaining = rest;
+    loop {
+        let end = remaining.find(char::is_whitespace).unwrap_or(remaining.len());
+        let token = &remaining[..end];
+        if token.is_empty()
+            || !token.len().is_multiple_of(2)
+            || token.len() > 8
+            || !token.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            return;
+        }
+        data.extend(
+            (0..token.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&token[i..i + 2], 16).unwrap()),
+        );
+        let after = &remaining[end..];
+        let next = after.trim_start();
+        if after.len() - next.len() >= 3 {
+            return;
+        }
+        remaining = next;
+    }
+}
+
+/// Turns raw frames into pcap packets with evenly spaced fake timestamps.
+pub fn to_packets(frames: Vec<Vec<u8>>, options: &HexImportOptions) -> Vec<PcapPacket> {
+    let start_usec = options.start_time * 1000;
+    frames
+        .into_iter()
+        .enumerate()
+        .map(|(index, data)| {
+            let ts = start_usec + index as i64 * i64::from(options.interval_usec);
+            PcapPacket {
+                header: PcapPacketHeader {
+                    timestamp: Timestamp::from_nanos(ts * 1000),
+                    incl_len: data.len() as u32,
+                    orig_len: data.len() as u32,
+                    link_type: None,
+                },
+                data,
+            }
+        })
+        .collect()
+}
+
+/// Converts a hex dump into a pcap file at `output_path`, returning the number of packets.
+pub fn import_hex_dump(
+    text: &str,
+    output_path: &str,
+    options: &HexImportOptions,
+) -> Result<usize, KcpdumpError> {
+    let packets = to_packets(parse_hex_dump(text)?, options);
+    let file = File::create(output_path).map_err(|e| KcpdumpError::file(output_path, e))?;
+    let write = || -> std::io::Result<()> {
+        let mut writer = PcapWriter::new(BufWriter::new(file), options.link_type, 65535)?;
+        for packet in &packets {
+            writer.write_packet(packet)?;
+        }
+        writer.flush()
+    };
+    write().map_err(|e| KcpdumpError::io("Failed to write capture file", e))?;
+    Ok(packets.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cap::Capture;
+
+    #[test]
+    fn test_wireshark_hex_dump() {
+        let text = "\
+0000   ff ff ff ff ff ff 00 11 22 33 44 55 08 06 00 01   ........\"3DU....
+0010   08 00 06 04 00 01                                 ......
+";
+        let packets = parse_hex_dump(text).unwrap();
+        assert_eq!(packets.len(), 1);
+        assert_eq!(packets[0].len(), 22);
+        assert_eq!(&packets[0][12..14], &[0x08, 0x06]);
+    }
+
+    #[test]
+    fn test_tcpdump_hex_dump() {
+        let text = "\
+12:00:00.000000 IP 10.0.0.1 > 10.0.0.2: ICMP echo request
+\t0x0000:  0011 2233 4455 6677 8899 aabb 0800 4500
+\t0x0010:  001c
+12:00:00.000100 IP 10.0.0.2 > 10.0.0.1: ICMP echo reply
+\t0x0000:  6677 8899 aabb 0011 2233 4455 0800
+";
+        let packets = parse_hex_dump(text).unwrap();
+        assert_eq!(packets.len(), 2);
+        assert_eq!(packets[0].len(), 18);
+        assert_eq!(packets[1].len(), 14);
+        assert_eq!(packets[1][0], 0x66);
+    }
+
+    #[test]
+    fn test_od_hex_dump_and_ascii_overrun() {
+        // The ASCII column "ab cd" looks like hex; the next offset trims it away.
+        let text = "\
+000000 01 02 03 04   ab cd
+000004 05 06
+";
+        let packets = parse_hex_dump(text).unwrap();
+        assert_eq!(packets, vec![vec![1, 2, 3, 4, 5, 6]]);
+    }
+
+    #[test]
+    fn test_offset_gap_is_rejected() {
+        let text = "0000 01 02\n0010 03 04\n";
+        assert!(parse_hex_dump(text).is_err());
+        assert!(parse_hex_dump("no hex here").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_import_writes_pcap() {
+        let path = std::env::temp_dir().join(format!("kcpdump-import-{}.pcap", std::process::id()));
+        let path = path.to_str().unwrap();
+        let options = HexImportOptions {
+            link_type: 101,
+            start_time: 1_700_000_000_000,
+            interval_usec: 500_000,
+        };
+        let count = import_hex_dump("0000 45 00\n0000 45 01\n0000 45 02\n", path, &options).unwrap();
+        assert_eq!(count, 3);
+
+        let mut capture = Capture::from_file(path).await.unwrap();
+        assert_eq!(capture.header().network, 101);
+        let mut stamps = Vec::new();
+        while let Some(packet) = capture.next_packet().await.unwrap() {
+            stamps.push(packet.header.timestamp);
+        }
+        assert_eq!(
+            stamps,
+            vec![
+                Timestamp::from_micros(1_700_000_000, 0),
+                Timestamp::from_micros(1_700_000_000, 500_000),
+                Timestamp::from_micros(1_700_000_001, 0)
+            ]
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+}
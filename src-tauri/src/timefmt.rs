@@ -0,0 +1,271 @@
+use std::fmt;
+
+use chrono::{DateTime, Local, SecondsFormat, Utc};
+
+use crate::cap::TimestampResolution;
+
+/// Timestamp
+/// A packet time as whole seconds since the Unix epoch and nanoseconds
+/// within the second, precise enough for nanosecond captures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Timestamp {
+    pub sec: u32,
+    pub nsec: u32,
+}
+
+impl Timestamp {
+    /// A timestamp from seconds and nanoseconds; excess nanoseconds carry
+    /// into the seconds.
+    pub fn new(sec: u32, nsec: u32) -> Self {
+        Timestamp {
+            sec: sec.wrapping_add(nsec / 1_000_000_000),
+            nsec: nsec % 1_000_000_000,
+        }
+    }
+
+    /// A timestamp from seconds and microseconds.
+    pub fn from_micros(sec: u32, usec: u32) -> Self {
+        Self::new(sec.wrapping_add(usec / 1_000_000), usec % 1_000_000 * 1000)
+    }
+
+    /// A timestamp from nanoseconds since the epoch. Times before the epoch
+    /// or past what `sec` holds are clamped to its range.
+    pub fn from_nanos(nanos: i64) -> Self {
+        let sec = nanos.div_euclid(1_000_000_000);
+        match u32::try_from(sec) {
+            Ok(sec) => Timestamp {
+                sec,
+                nsec: nanos.rem_euclid(1_000_000_000) as u32,
+            },
+            Err(_) if sec < 0 => Timestamp::default(),
+            Err(_) => Timestamp {
+                sec: u32::MAX,
+                nsec: 999_999_999,
+            },
+        }
+    }
+
+    /// Microseconds within the second, truncated.
+    pub fn usec(&self) -> u32 {
+        self.nsec / 1000
+    }
+
+    pub fn as_micros(&self) -> i64 {
+        i64::from(self.sec) * 1_000_000 + i64::from(self.usec())
+    }
+
+    pub fn as_nanos(&self) -> i64 {
+        i64::from(self.sec) * 1_000_000_000 + i64::from(self.nsec)
+    }
+
+    pub fn as_secs_f64(&self) -> f64 {
+        f64::from(self.sec) + f64::from(self.nsec) / 1e9
+    }
+
+    /// ISO-8601 UTC time with nanoseconds, e.g. `2023-11-14T22:13:20.010000000Z`.
+    pub fn to_iso8601(&self) -> String {
+        DateTime::<Utc>::from_timestamp(i64::from(self.sec), self.nsec)
+            .map(|time| time.to_rfc3339_opts(SecondsFormat::Nanos, true))
+            .unwrap_or_else(|| format!("{}.{:09}", self.sec, self.nsec))
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_iso8601())
+    }
+}
+
+/// Time Display Mode
+/// Selects how the time column of summary rows is rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TimeDisplayMode {
+    /// Wall-clock time in the local timezone, including the UTC offset.
+    #[default]
+    Absolute,
+    /// Wall-clock time in UTC.
+    AbsoluteUtc,
+    /// Seconds elapsed since the first packet of the capture.
+    SinceStart,
+    /// Seconds elapsed since the previous captured packet.
+    DeltaPrevious,
+    /// Seconds elapsed since the previous displayed packet.
+    DeltaDisplayed,
+}
+
+/// Time Formatter
+/// Renders packet timestamps according to a `TimeDisplayMode`, with as many
+/// decimals as the timestamp resolution of the capture.
+/// Every captured packet must be passed through the formatter in capture order,
+/// either with `format` (displayed rows) or `skip` (rows hidden by a filter),
+/// so that relative modes stay anchored to the right packets.
+#[derive(Debug)]
+pub struct TimeFormatter {
+    mode: TimeDisplayMode,
+    resolution: TimestampResolution,
+    first: Option<i64>,
+    previous: Option<i64>,
+    previous_displayed: Option<i64>,
+}
+
+impl TimeFormatter {
+    /// A formatter for microsecond captures.
+    pub fn new(mode: TimeDisplayMode) -> Self {
+        Self::with_resolution(mode, TimestampResolution::Microsecond)
+    }
+
+    /// Like `new`, with times shown at `resolution`.
+    pub fn with_resolution(mode: TimeDisplayMode, resolution: TimestampResolution) -> Self {
+        TimeFormatter {
+            mode,
+            resolution,
+            first: None,
+            previous: None,
+            previous_displayed: None,
+        }
+    }
+
+    pub fn mode(&self) -> TimeDisplayMode {
+        self.mode
+    }
+
+    /// Formats the timestamp of a displayed packet.
+    pub fn format(&mut self, timestamp: Timestamp) -> String {
+        let nanos = timestamp.as_nanos();
+        let first = *self.first.get_or_insert(nanos);
+        let resolution = self.resolution;
+        let text = match self.mode {
+            TimeDisplayMode::Absolute => format_absolute(timestamp, false, resolution),
+            TimeDisplayMode::AbsoluteUtc => format_absolute(timestamp, true, resolution),
+            TimeDisplayMode::SinceStart => format_seconds(nanos - first, resolution),
+            TimeDisplayMode::DeltaPrevious => {
+                format_seconds(nanos - self.previous.unwrap_or(nanos), resolution)
+            }
+            TimeDisplayMode::DeltaDisplayed => {
+                format_seconds(nanos - self.previous_displayed.unwrap_or(nanos), resolution)
+            }
+        };
+        self.previous = Some(nanos);
+        self.previous_displayed = Some(nanos);
+        text
+    }
+
+    /// Records a packet that is not displayed.
+    pub fn skip(&mut self, timestamp: Timestamp) {
+        let nanos = timestamp.as_nanos();
+        self.first.get_or_insert(nanos);
+        self.previous = Some(nanos);
+    }
+}
+
+fn format_seconds(nanos: i64, resolution: TimestampResolution) -> String {
+    let sign = if nanos < 0 { "-" } else { "" };
+    let nanos = nanos.unsigned_abs();
+    let fraction = resolution.fraction(Timestamp::new(0, (nanos % 1_000_000_000) as u32));
+    format!(
+        "{}{}.{:0width$}",
+        sign,
+        nanos / 1_000_000_000,
+        fraction,
+        width = resolution.digits()
+    )
+}
+
+fn format_absolute(timestamp: Timestamp, utc: bool, resolution: TimestampResolution) -> String {
+    let Some(time) = DateTime::<Utc>::from_timestamp(i64::from(timestamp.sec), timestamp.nsec)
+    else {
+        return format!(
+            "{}.{:0width$}",
+            timestamp.sec,
+            resolution.fraction(timestamp),
+            width = resolution.digits()
+        );
+    };
+    let seconds = match resolution {
+        TimestampResolution::Microsecond => "%Y-%m-%d %H:%M:%S%.6f",
+        TimestampResolution::Nanosecond => "%Y-%m-%d %H:%M:%S%.9f",
+    };
+    if utc {
+        time.format(&format!("{} UTC", seconds)).to_string()
+    } else {
+        time.with_timezone(&Local)
+            .format(&format!("{} %:z", seconds))
+            .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_absolute_utc() {
+        let mut formatter = TimeFormatter::new(TimeDisplayMode::AbsoluteUtc);
+        assert_eq!(
+            formatter.format(Timestamp::from_micros(1_700_000_000, 10_000)),
+            "2023-11-14 22:13:20.010000 UTC"
+        );
+    }
+
+    #[test]
+    fn test_since_start() {
+        let mut formatter = TimeFormatter::new(TimeDisplayMode::SinceStart);
+        formatter.skip(Timestamp::from_micros(100, 500_000));
+        assert_eq!(formatter.format(Timestamp::from_micros(101, 0)), "0.500000");
+        assert_eq!(formatter.format(Timestamp::from_micros(102, 750_000)), "2.250000");
+    }
+
+    #[test]
+    fn test_delta_previous_and_displayed() {
+        let mut previous = TimeFormatter::new(TimeDisplayMode::DeltaPrevious);
+        let mut displayed = TimeFormatter::new(TimeDisplayMode::DeltaDisplayed);
+        for formatter in [&mut previous, &mut displayed] {
+            assert_eq!(formatter.format(Timestamp::from_micros(10, 0)), "0.000000");
+            formatter.skip(Timestamp::from_micros(11, 0));
+        }
+        assert_eq!(previous.format(Timestamp::from_micros(11, 250_000)), "0.250000");
+        assert_eq!(displayed.format(Timestamp::from_micros(11, 250_000)), "1.250000");
+    }
+
+    #[test]
+    fn test_timestamp() {
+        let timestamp = Timestamp::new(1_700_000_000, 10_000_123);
+        assert_eq!(timestamp.to_iso8601(), "2023-11-14T22:13:20.010000123Z");
+        assert_eq!(timestamp.usec(), 10_000);
+        assert_eq!(Timestamp::new(1, 1_500_000_000), Timestamp::new(2, 500_000_000));
+        assert_eq!(Timestamp::from_nanos(timestamp.as_nanos()), timestamp);
+        assert!(Timestamp::from_micros(1, 999_999) < Timestamp::new(2, 0));
+    }
+
+    #[test]
+    fn test_negative_delta() {
+        let mut formatter = TimeFormatter::new(TimeDisplayMode::DeltaPrevious);
+        formatter.format(Timestamp::from_micros(10, 500_000));
+        assert_eq!(formatter.format(Timestamp::from_micros(10, 0)), "-0.500000");
+    }
+
+    #[test]
+    fn test_nanosecond_resolution() {
+        let mut formatter =
+            TimeFormatter::with_resolution(TimeDisplayMode::AbsoluteUtc, TimestampResolution::Nanosecond);
+        assert_eq!(
+            formatter.format(Timestamp::new(1_700_000_000, 10_000_123)),
+            "2023-11-14 22:13:20.010000123 UTC"
+        );
+        let mut formatter =
+            TimeFormatter::with_resolution(TimeDisplayMode::DeltaPrevious, TimestampResolution::Nanosecond);
+        formatter.format(Timestamp::new(10, 999_999_999));
+        assert_eq!(formatter.format(Timestamp::new(11, 1)), "0.000000002");
+        assert_eq!(formatter.format(Timestamp::new(11, 0)), "-0.000000001");
+    }
+
+    #[test]
+    fn test_from_nanos_out_of_range() {
+        assert_eq!(Timestamp::from_nanos(-1), Timestamp::default());
+        assert_eq!(Timestamp::from_nanos(-1_500_000_000), Timestamp::default());
+        assert_eq!(Timestamp::from_nanos(i64::MAX), Timestamp::new(u32::MAX, 999_999_999));
+    }
+}
@@ -0,0 +1,270 @@
+use std::net::{IpAddr, Ipv4Addr};
+
+use crate::dissect::PacketLayers;
+use crate::error::KcpdumpError;
+use crate::expert::{self, ExpertAnalyzer};
+use crate::flows::FlowKey;
+use crate::packet::TcpFlags;
+use crate::session::LoadedCapture;
+
+/// Number of buckets used when the caller does not ask for a specific count.
+pub const DEFAULT_BUCKETS: usize = 100;
+
+/// Retransmissions closer together than this belong to the same burst.
+const BURST_GAP_USEC: i64 = 1_000_000;
+
+/// Smallest number of retransmissions reported as a burst.
+const MIN_BURST: usize = 3;
+
+/// Timeline Bucket
+/// Traffic of a conversation during one time slice, split by direction.
+/// "A" and "B" are the endpoints of the conversation key.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TimelineBucket {
+    /// Seconds from the first packet of the conversation.
+    pub start: f64,
+    pub packets: usize,
+    pub bytes: u64,
+    pub packets_a_to_b: usize,
+    pub bytes_a_to_b: u64,
+    pub packets_b_to_a: usize,
+    pub bytes_b_to_a: u64,
+}
+
+/// Timeline Marker Kind
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum TimelineMarkerKind {
+    Syn,
+    SynAck,
+    Fin,
+    Reset,
+    RetransmissionBurst,
+}
+
+/// Timeline Marker
+/// A key event drawn on top of the buckets.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TimelineMarker {
+    pub kind: TimelineMarkerKind,
+    /// Frame number of the packet the marker points at; the first one for bursts.
+    pub number: usize,
+    /// Seconds from the first packet of the conversation.
+    pub time: f64,
+    pub label: String,
+}
+
+/// Conversation Timeline
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversationTimeline {
+    /// Capture timestamp of the first packet, in seconds since the epoch.
+    pub start_time: f64,
+    /// Seconds between the first and the last packet.
+    pub duration: f64,
+    /// Length of each bucket in seconds.
+    pub bucket_duration: f64,
+    pub buckets: Vec<TimelineBucket>,
+    pub markers: Vec<TimelineMarker>,
+}
+
+struct Burst {
+    number: usize,
+    first: i64,
+    last: i64,
+    count: usize,
+}
+
+/// Builds the activity timeline of `conversation`, split into `bucket_count`
+/// equally long buckets.
+pub fn conversation_timeline(
+    capture: &LoadedCapture,
+    conversation: FlowKey,
+    bucket_count: usize,
+) -> Result<ConversationTimeline, KcpdumpError> {
+    // (number, micros, length, a to b) of every packet in the conversation.
+    let mut packets = Vec::new();
+    let mut markers = Vec::new();
+    let mut analyzer = ExpertAnalyzer::default();
+    let mut bursts: Vec<Burst> = Vec::new();
+
+    for (index, packet) in capture.packets().enumerate() {
+        let packet = packet?;
+        let layers = PacketLayers::decode_link(index + 1, capture.header.network, &packet);
+        let Some(ip) = &layers.ipv4 else {
+            continue;
+        };
+        if FlowKey::from_ipv4(ip) != conversation {
+            continue;
+        }
+        let micros = packet.header.timestamp.as_micros();
+        let source = IpAddr::V4(Ipv4Addr::from(ip.source_ip));
+        let source_port = layers.tcp.as_ref().map_or_else(
+            || layers.udp.as_ref().map_or(0, |udp| udp.source_port),
+            |tcp| tcp.source_port,
+        );
+        let a_to_b = (source, source_port) == (conversation.address_a, conversation.port_a);
+        packets.push((
+            layers.number,
+            micros,
+            u64::from(packet.header.orig_len),
+            a_to_b,
+        ));
+
+        if let Some(tcp) = &layers.tcp {
+            let flags = tcp.flags;
+            let kind = if flags.contains(TcpFlags::RST) {
+                Some((TimelineMarkerKind::Reset, "RST"))
+            } else if flags.contains(TcpFlags::SYN) && flags.contains(TcpFlags::ACK) {
+                Some((TimelineMarkerKind::SynAck, "SYN, ACK"))
+            } else if flags.contains(TcpFlags::SYN) {
+                Some((TimelineMarkerKind::Syn, "SYN"))
+            } else if flags.contains(TcpFlags::FIN) {
+                Some((TimelineMarkerKind::Fin, "FIN"))
+            } else {
+                None
+            };
+            if let Some((kind, label)) = kind {
+                markers.push((kind, layers.number, micros, label.to_string()));
+            }
+        }
+
+        let retransmission = analyzer
+            .analyze(&layers)
+            .iter()
+            .any(|item| item.message == expert::RETRANSMISSION);
+        if retransmission {
+            match bursts.last_mut() {
+                Some(burst) if micros - burst.last <= BURST_GAP_USEC => {
+                    burst.last = micros;
+                    burst.count += 1;
+                }
+                _ => bursts.push(Burst {
+                    number: layers.number,
+                    first: micros,
+                    last: micros,
+                    count: 1,
+                }),
+            }
+        }
+    }
+
+    let Some(first) = packets.iter().map(|packet| packet.1).min() else {
+        return Ok(ConversationTimeline::default());
+    };
+    let last = packets.iter().map(|packet| packet.1).max().unwrap_or(first);
+    let span = last - first;
+    let bucket_count = bucket_count.max(1);
+    // At least one microsecond per bucket so that every packet has a bucket.
+    let bucket_usec = (span / bucket_count as i64 + 1).max(1);
+
+    let mut buckets: Vec<_> = (0..bucket_count)
+        .map(|index| TimelineBucket {
+            start: (index as i64 * bucket_usec) as f64 / 1_000_000.0,
+            ..Default::default()
+        })
+        .collect();
+    for (_, micros, length, a_to_b) in packets {
+        let index = (((micros - first) / bucket_usec) as usize).min(bucket_count - 1);
+        let bucket = &mut buckets[index];
+        bucket.packets += 1;
+        bucket.bytes += length;
+        if a_to_b {
+            bucket.packets_a_to_b += 1;
+            bucket.bytes_a_to_b += length;
+        } else {
+            bucket.packets_b_to_a += 1;
+            bucket.bytes_b_to_a += length;
+        }
+    }
+
+    let seconds = |micros: i64| (micros - first) as f64 / 1_000_000.0;
+    let mut markers: Vec<_> = markers
+        .into_iter()
+        .map(|(kind, number, micros, label)| TimelineMarker {
+            kind,
+            number,
+            time: seconds(micros),
+            label,
+        })
+        .collect();
+    markers.extend(
+        bursts
+            .into_iter()
+            .filter(|burst| burst.count >= MIN_BURST)
+            .map(|burst| TimelineMarker {
+                kind: TimelineMarkerKind::RetransmissionBurst,
+                number: burst.number,
+                time: seconds(burst.first),
+                label: format!(
+                    "{} retransmissions in {:.3} s",
+                    burst.count,
+                    (burst.last - burst.first) as f64 / 1_000_000.0
+                ),
+            }),
+    );
+    markers.sort_by(|a, b| a.time.total_cmp(&b.time).then(a.number.cmp(&b.number)));
+
+    Ok(ConversationTimeline {
+        start_time: first as f64 / 1_000_000.0,
+        duration: seconds(last),
+        bucket_duration: bucket_usec as f64 / 1_000_000.0,
+        buckets,
+        markers,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_sample_http_conversation() {
+        let capture = LoadedCapture::load(1, "sample.pcap").await.unwrap();
+        let conversation = capture
+            .packets()
+            .map(Result::unwrap)
+            .enumerate()
+            .filter_map(|(index, packet)| PacketLayers::decode(index + 1, &packet).ipv4)
+            .filter(|ip| ip.protocol == 6)
+            .map(|ip| FlowKey::from_ipv4(&ip))
+            .next()
+            .unwrap();
+        let timeline = conversation_timeline(&capture, conversation, 10).unwrap();
+        assert_eq!(timeline.buckets.len(), 10);
+        let packets: usize = timeline.buckets.iter().map(|bucket| bucket.packets).sum();
+        let a_to_b: usize = timeline
+            .buckets
+            .iter()
+            .map(|bucket| bucket.packets_a_to_b)
+            .sum();
+        assert!(packets > 0);
+        assert!(a_to_b > 0 && a_to_b < packets);
+        assert!(timeline.buckets[0].packets > 0);
+        let kinds: Vec<_> = timeline.markers.iter().map(|marker| marker.kind).collect();
+        assert_eq!(kinds.first(), Some(&TimelineMarkerKind::Syn));
+        assert!(kinds.contains(&TimelineMarkerKind::SynAck));
+        assert!(kinds.contains(&TimelineMarkerKind::Fin));
+        assert!(
+            timeline
+                .markers
+                .windows(2)
+                .all(|pair| pair[0].time <= pair[1].time)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_conversation_is_empty() {
+        let capture = LoadedCapture::load(1, "sample.pcap").await.unwrap();
+        let unknown = FlowKey::new(
+            6,
+            (IpAddr::V4(Ipv4Addr::new(203, 0, 113, 1)), 1),
+            (IpAddr::V4(Ipv4Addr::new(203, 0, 113, 2)), 2),
+        );
+        let timeline = conversation_timeline(&capture, unknown, 10).unwrap();
+        assert!(timeline.buckets.is_empty());
+        assert!(timeline.markers.is_empty());
+    }
+}
@@ -0,0 +1,732 @@
+use crate::error::KcpdumpError;
+use crate::flows::FlowKey;
+use crate::reassembly::TcpStream;
+use crate::ssh::md5_hex;
+
+/// TLS extension types.
+pub const EXTENSION_SERVER_NAME: u16 = 0;
+pub const EXTENSION_SUPPORTED_GROUPS: u16 = 10;
+pub const EXTENSION_EC_POINT_FORMATS: u16 = 11;
+pub const EXTENSION_ALPN: u16 = 16;
+pub const EXTENSION_SUPPORTED_VERSIONS: u16 = 43;
+
+/// TLS record content type of handshake messages.
+const CONTENT_HANDSHAKE: u8 = 22;
+
+/// TLS handshake message types.
+pub const HANDSHAKE_CLIENT_HELLO: u8 = 1;
+pub const HANDSHAKE_SERVER_HELLO: u8 = 2;
+pub const HANDSHAKE_CERTIFICATE: u8 = 11;
+
+/// Client Hello
+/// The parts of a TLS ClientHello needed to identify the service a
+/// connection is for. Only a ClientHello that fits in the first segment is
+/// parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientHello {
+    /// Legacy version field, e.g. 0x0303 for TLS 1.2 and 1.3.
+    pub version: u16,
+    pub cipher_suites: Vec<u16>,
+    /// Extensions as (type, data) pairs, in order.
+    pub extensions: Vec<(u16, Vec<u8>)>,
+}
+
+impl TryFrom<&[u8]> for ClientHello {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        // Record header, then the handshake header.
+        if data.len() < 9 || data[0] != 0x16 || data[1] != 0x03 {
+            return Err(KcpdumpError::malformed(0, "Not a TLS handshake record"));
+        }
+        if data[5] != HANDSHAKE_CLIENT_HELLO {
+            return Err(KcpdumpError::malformed(0, "Not a TLS ClientHello"));
+        }
+        Self::parse(&data[9..])
+    }
+}
+
+impl ClientHello {
+    /// Parses the body of a ClientHello handshake message.
+    pub fn parse(body: &[u8]) -> Result<Self, KcpdumpError> {
+        let mut reader = Reader(body);
+        let version = reader.u16()?;
+        reader.take(32)?;
+        let session_id_len = usize::from(reader.u8()?);
+        reader.take(session_id_len)?;
+        let suites_len = usize::from(reader.u16()?);
+        let cipher_suites = reader
+            .take(suites_len)?
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect();
+        let compression_len = usize::from(reader.u8()?);
+        reader.take(compression_len)?;
+        let mut extensions = Vec::new();
+        if !reader.0.is_empty() {
+            let extensions_len = usize::from(reader.u16()?);
+            let mut list = Reader(reader.take(extensions_len)?);
+            while !list.0.is_empty() {
+                let extension_type = list.u16()?;
+                let length = usize::from(list.u16()?);
+                extensions.push((extension_type, list.take(length)?.to_vec()));
+            }
+        }
+        Ok(ClientHello {
+            version,
+            cipher_suites,
+            extensions,
+        })
+    }
+
+    fn extension(&self, extension_type: u16) -> Option<&[u8]> {
+        self.extensions
+            .iter()
+            .find(|(kind, _)| *kind == extension_type)
+            .map(|(_, data)| data.as_slice())
+    }
+
+    /// Host name from the server_name extension.
+    pub fn server_name(&self) -> Option<String> {
+        let mut reader = Reader(self.extension(EXTENSION_SERVER_NAME)?);
+        let list_len = usize::from(reader.u16().ok()?);
+        let mut list = Reader(reader.take(list_len).ok()?);
+        while !list.0.is_empty() {
+            let name_type = list.u8().ok()?;
+            let length = usize::from(list.u16().ok()?);
+            let name = list.take(length).ok()?;
+            if name_type == 0 {
+                return String::from_utf8(name.to_vec()).ok();
+            }
+        }
+        None
+    }
+
+    /// Protocols offered in the ALPN extension, e.g. `h2` and `http/1.1`.
+    pub fn alpn(&self) -> Vec<String> {
+        let mut protocols = Vec::new();
+        let Some(data) = self.extension(EXTENSION_ALPN) else {
+            return protocols;
+        };
+        let mut reader = Reader(data);
+        let Ok(list_len) = reader.u16() else {
+            return protocols;
+        };
+        let Ok(list) = reader.take(usize::from(list_len)) else {
+            return protocols;
+        };
+        let mut list = Reader(list);
+        while let Ok(length) = list.u8() {
+            let Ok(protocol) = list.take(usize::from(length)) else {
+                break;
+            };
+            protocols.push(String::from_utf8_lossy(protocol).into_owned());
+        }
+        protocols
+    }
+
+    /// Named groups offered in the supported_groups extension.
+    pub fn supported_groups(&self) -> Vec<u16> {
+        let Some(data) = self.extension(EXTENSION_SUPPORTED_GROUPS) else {
+            return Vec::new();
+        };
+        let mut reader = Reader(data);
+        let Ok(length) = reader.u16() else {
+            return Vec::new();
+        };
+        reader
+            .take(usize::from(length))
+            .unwrap_or_default()
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect()
+    }
+
+    /// Formats offered in the ec_point_formats extension.
+    pub fn ec_point_formats(&self) -> Vec<u8> {
+        let Some(data) = self.extension(EXTENSION_EC_POINT_FORMATS) else {
+            return Vec::new();
+        };
+        let mut reader = Reader(data);
+        let Ok(length) = reader.u8() else {
+            return Vec::new();
+        };
+        reader.take(usize::from(length)).unwrap_or_default().to_vec()
+    }
+
+    /// Versions offered, from the supported_versions extension when present
+    /// and the legacy version field otherwise. GREASE values are left out.
+    pub fn offered_versions(&self) -> Vec<u16> {
+        let Some(data) = self.extension(EXTENSION_SUPPORTED_VERSIONS) else {
+            return vec![self.version];
+        };
+        let mut reader = Reader(data);
+        let Ok(length) = reader.u8() else {
+            return vec![self.version];
+        };
+        reader
+            .take(usize::from(length))
+            .unwrap_or_default()
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .filter(|&version| !is_grease(version))
+            .collect()
+    }
+
+    /// The JA3 input: version, cipher suites, extension types, groups and
+    /// point formats, with GREASE values removed.
+    pub fn ja3_string(&self) -> String {
+        let extension_types: Vec<u16> = self.extensions.iter().map(|(kind, _)| *kind).collect();
+        let formats: Vec<u16> = self.ec_point_formats().into_iter().map(u16::from).collect();
+        format!(
+            "{},{},{},{},{}",
+            self.version,
+            join_values(&self.cipher_suites),
+            join_values(&extension_types),
+            join_values(&self.supported_groups()),
+            join_values(&formats)
+        )
+    }
+
+    /// JA3 fingerprint: the MD5 of `ja3_string`.
+    pub fn ja3(&self) -> String {
+        md5_hex(&self.ja3_string())
+    }
+}
+
+/// Server Hello
+/// The parameters a server selected for a TLS connection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerHello {
+    /// Legacy version field; TLS 1.3 keeps 0x0303 here.
+    pub version: u16,
+    pub cipher_suite: u16,
+    /// Extensions as (type, data) pairs, in order.
+    pub extensions: Vec<(u16, Vec<u8>)>,
+}
+
+impl ServerHello {
+    /// Parses the body of a ServerHello handshake message.
+    pub fn parse(body: &[u8]) -> Result<Self, KcpdumpError> {
+        let mut reader = Reader(body);
+        let version = reader.u16()?;
+        reader.take(32)?;
+        let session_id_len = usize::from(reader.u8()?);
+        reader.take(session_id_len)?;
+        let cipher_suite = reader.u16()?;
+        reader.u8()?;
+        let mut extensions = Vec::new();
+        if !reader.0.is_empty() {
+            let extensions_len = usize::from(reader.u16()?);
+            let mut list = Reader(reader.take(extensions_len)?);
+            while !list.0.is_empty() {
+                let extension_type = list.u16()?;
+                let length = usize::from(list.u16()?);
+                extensions.push((extension_type, list.take(length)?.to_vec()));
+            }
+        }
+        Ok(ServerHello {
+            version,
+            cipher_suite,
+            extensions,
+        })
+    }
+
+    /// Negotiated version, taking the supported_versions extension into account.
+    pub fn selected_version(&self) -> u16 {
+        self.extensions
+            .iter()
+            .find(|(kind, _)| *kind == EXTENSION_SUPPORTED_VERSIONS)
+            .and_then(|(_, data)| Reader(data).u16().ok())
+            .unwrap_or(self.version)
+    }
+
+    /// The JA3S input: version, cipher suite and extension types.
+    pub fn ja3s_string(&self) -> String {
+        let extension_types: Vec<u16> = self.extensions.iter().map(|(kind, _)| *kind).collect();
+        format!(
+            "{},{},{}",
+            self.version,
+            self.cipher_suite,
+            join_values(&extension_types)
+        )
+    }
+
+    /// JA3S fingerprint: the MD5 of `ja3s_string`.
+    pub fn ja3s(&self) -> String {
+        md5_hex(&self.ja3s_string())
+    }
+}
+
+/// GREASE values (RFC 8701) are random placeholders that JA3 ignores.
+fn is_grease(value: u16) -> bool {
+    value & 0x0f0f == 0x0a0a && (value >> 8) == (value & 0xff)
+}
+
+fn join_values(values: &[u16]) -> String {
+    values
+        .iter()
+        .filter(|&&value| !is_grease(value))
+        .map(u16::to_string)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Name of a protocol version, e.g. `TLS 1.2` for 0x0303.
+pub fn version_name(version: u16) -> String {
+    match version {
+        0x0300 => "SSL 3.0".to_string(),
+        0x0301 => "TLS 1.0".to_string(),
+        0x0302 => "TLS 1.1".to_string(),
+        0x0303 => "TLS 1.2".to_string(),
+        0x0304 => "TLS 1.3".to_string(),
+        _ => format!("0x{:04x}", version),
+    }
+}
+
+/// Collects the plaintext handshake messages at the start of one direction of
+/// a stream as (type, body) pairs. Messages may span records; reading stops
+/// at the first record that is not a handshake, such as ChangeCipherSpec
+/// after which the handshake is encrypted.
+pub fn handshake_messages(mut data: &[u8]) -> Vec<(u8, Vec<u8>)> {
+    let mut handshake = Vec::new();
+    while data.len() >= 5 && data[1] == 0x03 {
+        let length = usize::from(u16::from_be_bytes([data[3], data[4]]));
+        if data[0] != CONTENT_HANDSHAKE || data.len() < 5 + length {
+            break;
+        }
+        handshake.extend_from_slice(&data[5..5 + length]);
+        data = &data[5 + length..];
+    }
+    let mut messages = Vec::new();
+    let mut reader = Reader(&handshake);
+    while let (Ok(kind), Ok(length)) = (reader.u8(), reader.u24()) {
+        let Ok(body) = reader.take(length) else {
+            break;
+        };
+        messages.push((kind, body.to_vec()));
+    }
+    messages
+}
+
+/// Certificate
+/// Identity and validity of one certificate a server presented.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Certificate {
+    /// Distinguished name, e.g. `CN=example.com, O=Example`.
+    pub subject: String,
+    pub issuer: String,
+    /// Serial number in hexadecimal.
+    pub serial: String,
+    /// Validity bounds in ISO 8601, e.g. `2024-01-31T12:00:00Z`.
+    pub not_before: Option<String>,
+    pub not_after: Option<String>,
+    /// Size of the DER encoding in bytes.
+    pub size: usize,
+}
+
+impl Certificate {
+    /// Reads the fields of interest from a DER-encoded X.509 certificate.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let (_, certificate, _) = der(data)?;
+        let (_, tbs, _) = der(certificate)?;
+        let (tag, mut serial, mut rest) = der(tbs)?;
+        if tag == 0xa0 {
+            // Explicit version, present from v2 on.
+            (_, serial, rest) = der(rest)?;
+        }
+        let (_, _, rest) = der(rest)?;
+        let (_, issuer, rest) = der(rest)?;
+        let (_, validity, rest) = der(rest)?;
+        let (_, subject, _) = der(rest)?;
+        let (before_tag, not_before, rest) = der(validity)?;
+        let (after_tag, not_after, _) = der(rest)?;
+        Some(Certificate {
+            subject: distinguished_name(subject),
+            issuer: distinguished_name(issuer),
+            serial: serial.iter().map(|byte| format!("{:02x}", byte)).collect(),
+            not_before: der_time(before_tag, not_before),
+            not_after: der_time(after_tag, not_after),
+            size: data.len(),
+        })
+    }
+}
+
+/// Splits the body of a Certificate handshake message into DER certificates.
+pub fn certificates(body: &[u8]) -> Vec<Vec<u8>> {
+    let mut reader = Reader(body);
+    let Ok(list) = reader.u24().and_then(|length| reader.take(length)) else {
+        return Vec::new();
+    };
+    let mut list = Reader(list);
+    let mut certificates = Vec::new();
+    while let Ok(certificate) = list.u24().and_then(|length| list.take(length)) {
+        certificates.push(certificate.to_vec());
+    }
+    certificates
+}
+
+/// Reads one DER element, returning its tag, contents and the bytes after it.
+fn der(data: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = data.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (length, rest) = if first & 0x80 == 0 {
+        (usize::from(first), rest)
+    } else {
+        let count = usize::from(first & 0x7f);
+        if count == 0 || count > 4 || rest.len() < count {
+            return None;
+        }
+        let length = rest[..count]
+            .iter()
+            .fold(0, |length, &byte| (length << 8) | usize::from(byte));
+        (length, &rest[count..])
+    };
+    (rest.len() >= length).then(|| (tag, &rest[..length], &rest[length..]))
+}
+
+/// Formats the common attributes of an X.501 Name.
+fn distinguished_name(mut data: &[u8]) -> String {
+    let mut parts = Vec::new();
+    while let Some((_, set, rest)) = der(data) {
+        data = rest;
+        let mut attributes = set;
+        while let Some((_, attribute, rest)) = der(attributes) {
+            attributes = rest;
+            let Some((_, oid, value)) = der(attribute) else {
+                continue;
+            };
+            let Some((_, value, _)) = der(value) else {
+                continue;
+            };
+            let key = match oid {
+                [0x55, 0x04, 0x03] => "CN",
+                [0x55, 0x04, 0x06] => "C",
+                [0x55, 0x04, 0x07] => "L",
+                [0x55, 0x04, 0x08] => "ST",
+                [0x55, 0x04, 0x0a] => "O",
+                [0x55, 0x04, 0x0b] => "OU",
+                _ => continue,
+            };
+            parts.push(format!("{}={}", key, String::from_utf8_lossy(value)));
+        }
+    }
+    parts.join(", ")
+}
+
+/// Converts a UTCTime or GeneralizedTime to ISO 8601.
+fn der_time(tag: u8, value: &[u8]) -> Option<String> {
+    let digits = std::str::from_utf8(value).ok()?.strip_suffix('Z')?;
+    let (year, rest) = match tag {
+        0x17 => {
+            let year: u32 = digits.get(..2)?.parse().ok()?;
+            (if year >= 50 { 1900 + year } else { 2000 + year }, &digits[2..])
+        }
+        0x18 => (digits.get(..4)?.parse().ok()?, &digits[4..]),
+        _ => return None,
+    };
+    if rest.len() != 10 || !rest.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    Some(format!(
+        "{:04}-{}-{}T{}:{}:{}Z",
+        year,
+        &rest[0..2],
+        &rest[2..4],
+        &rest[4..6],
+        &rest[6..8],
+        &rest[8..10]
+    ))
+}
+
+/// Tls Connection
+/// Handshake metadata of one TLS connection, readable without decrypting it.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TlsConnection {
+    pub stream: FlowKey,
+    /// Frame number of the first packet of the connection.
+    pub first_number: usize,
+    pub client: String,
+    pub server: String,
+    pub server_name: Option<String>,
+    pub alpn: Vec<String>,
+    /// Versions the client offered, by name.
+    pub offered_versions: Vec<String>,
+    pub cipher_suites: Vec<u16>,
+    pub selected_version: Option<String>,
+    pub selected_cipher_suite: Option<u16>,
+    pub ja3: Option<String>,
+    pub ja3_string: Option<String>,
+    pub ja3s: Option<String>,
+    pub ja3s_string: Option<String>,
+    /// Certificates sent in the clear; TLS 1.3 encrypts them.
+    pub certificates: Vec<Certificate>,
+}
+
+/// Extracts the handshake metadata of a stream, if it starts a TLS handshake.
+pub fn connection(stream: &TcpStream) -> Option<TlsConnection> {
+    let client_messages = handshake_messages(&stream.client_data.data);
+    let server_messages = handshake_messages(&stream.server_data.data);
+    let client_hello = client_messages
+        .iter()
+        .find(|(kind, _)| *kind == HANDSHAKE_CLIENT_HELLO)
+        .and_then(|(_, body)| ClientHello::parse(body).ok());
+    let server_hello = server_messages
+        .iter()
+        .find(|(kind, _)| *kind == HANDSHAKE_SERVER_HELLO)
+        .and_then(|(_, body)| ServerHello::parse(body).ok());
+    if client_hello.is_none() && server_hello.is_none() {
+        return None;
+    }
+    let certificates = server_messages
+        .iter()
+        .filter(|(kind, _)| *kind == HANDSHAKE_CERTIFICATE)
+        .flat_map(|(_, body)| certificates(body))
+        .filter_map(|data| Certificate::parse(&data))
+        .collect();
+    Some(TlsConnection {
+        stream: stream.key,
+        first_number: stream.first_number,
+        client: format!("{}:{}", stream.client.0, stream.client.1),
+        server: format!("{}:{}", stream.server.0, stream.server.1),
+        server_name: client_hello.as_ref().and_then(ClientHello::server_name),
+        alpn: client_hello.as_ref().map(ClientHello::alpn).unwrap_or_default(),
+        offered_versions: client_hello
+            .as_ref()
+            .map(|hello| hello.offered_versions().into_iter().map(version_name).collect())
+            .unwrap_or_default(),
+        cipher_suites: client_hello
+            .as_ref()
+            .map(|hello| hello.cipher_suites.clone())
+            .unwrap_or_default(),
+        selected_version: server_hello
+            .as_ref()
+            .map(|hello| version_name(hello.selected_version())),
+        selected_cipher_suite: server_hello.as_ref().map(|hello| hello.cipher_suite),
+        ja3: client_hello.as_ref().map(ClientHello::ja3),
+        ja3_string: client_hello.as_ref().map(ClientHello::ja3_string),
+        ja3s: server_hello.as_ref().map(ServerHello::ja3s),
+        ja3s_string: server_hello.as_ref().map(ServerHello::ja3s_string),
+        certificates,
+    })
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], KcpdumpError> {
+        if self.0.len() < length {
+            return Err(KcpdumpError::malformed(0, "TLS handshake truncated"));
+        }
+        let (head, rest) = self.0.split_at(length);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, KcpdumpError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, KcpdumpError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u24(&mut self) -> Result<usize, KcpdumpError> {
+        let bytes = self.take(3)?;
+        Ok((usize::from(bytes[0]) << 16) | (usize::from(bytes[1]) << 8) | usize::from(bytes[2]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a ClientHello record offering `h2` to `server_name`.
+    fn client_hello(server_name: &str) -> Vec<u8> {
+        let name = server_name.as_bytes();
+        let mut extensions = Vec::new();
+        extensions.extend_from_slice(&[0x00, 0x00]);
+        extensions.extend_from_slice(&(name.len() as u16 + 5).to_be_bytes());
+        extensions.extend_from_slice(&(name.len() as u16 + 3).to_be_bytes());
+        extensions.push(0);
+        extensions.extend_from_slice(&(name.len() as u16).to_be_bytes());
+        extensions.extend_from_slice(name);
+        extensions.extend_from_slice(&[0x00, 0x10, 0x00, 0x05, 0x00, 0x03, 0x02, b'h', b'2']);
+
+        let mut body = vec![0x03, 0x03];
+        body.extend_from_slice(&[0u8; 32]);
+        body.push(0);
+        body.extend_from_slice(&[0x00, 0x04, 0x13, 0x01, 0xc0, 0x2f]);
+        body.extend_from_slice(&[0x01, 0x00]);
+        body.extend_from_slice(&(extensions.len() as u16).to_be_bytes());
+        body.extend_from_slice(&extensions);
+
+        let mut handshake = vec![0x01, 0x00];
+        handshake.extend_from_slice(&(body.len() as u16).to_be_bytes());
+        handshake.extend_from_slice(&body);
+        let mut record = vec![0x16, 0x03, 0x01];
+        record.extend_from_slice(&(handshake.len() as u16).to_be_bytes());
+        record.extend_from_slice(&handshake);
+        record
+    }
+
+    #[test]
+    fn test_client_hello() {
+        let data = client_hello("dns.google");
+        let hello = ClientHello::try_from(data.as_slice()).unwrap();
+        assert_eq!(hello.version, 0x0303);
+        assert_eq!(hello.cipher_suites, [0x1301, 0xc02f]);
+        assert_eq!(hello.server_name().as_deref(), Some("dns.google"));
+        assert_eq!(hello.alpn(), ["h2"]);
+        assert!(ClientHello::try_from(&data[..50]).is_err());
+        assert!(ClientHello::try_from(&b"GET / HTTP/1.1\r\n"[..]).is_err());
+    }
+
+    /// Wraps contents in a DER element.
+    fn tlv(tag: u8, contents: &[u8]) -> Vec<u8> {
+        let mut element = vec![tag];
+        match contents.len() {
+            length @ 0..=127 => element.push(length as u8),
+            length => {
+                element.push(0x82);
+                element.extend_from_slice(&(length as u16).to_be_bytes());
+            }
+        }
+        element.extend_from_slice(contents);
+        element
+    }
+
+    fn common_name(name: &str) -> Vec<u8> {
+        let attribute = [tlv(0x06, &[0x55, 0x04, 0x03]), tlv(0x0c, name.as_bytes())].concat();
+        tlv(0x30, &tlv(0x31, &tlv(0x30, &attribute)))
+    }
+
+    fn handshake(kind: u8, body: &[u8]) -> Vec<u8> {
+        let mut message = vec![kind];
+        message.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..]);
+        message.extend_from_slice(body);
+        message
+    }
+
+    fn record(content_type: u8, fragment: &[u8]) -> Vec<u8> {
+        let mut record = vec![content_type, 0x03, 0x03];
+        record.extend_from_slice(&(fragment.len() as u16).to_be_bytes());
+        record.extend_from_slice(fragment);
+        record
+    }
+
+    #[test]
+    fn test_ja3_ignores_grease() {
+        let mut body = vec![0x03, 0x03];
+        body.extend_from_slice(&[0u8; 32]);
+        body.push(0);
+        body.extend_from_slice(&[0x00, 0x08, 0x0a, 0x0a, 0x13, 0x01, 0xc0, 0x2f, 0x00, 0x2f]);
+        body.extend_from_slice(&[0x01, 0x00]);
+        let extensions = [
+            &[0x0a, 0x0a, 0x00, 0x00][..],
+            &[0x00, 0x0a, 0x00, 0x08, 0x00, 0x06, 0x1a, 0x1a, 0x00, 0x1d, 0x00, 0x17],
+            &[0x00, 0x0b, 0x00, 0x02, 0x01, 0x00],
+            &[0x00, 0x2b, 0x00, 0x07, 0x06, 0x2a, 0x2a, 0x03, 0x04, 0x03, 0x03],
+        ]
+        .concat();
+        body.extend_from_slice(&(extensions.len() as u16).to_be_bytes());
+        body.extend_from_slice(&extensions);
+
+        let hello = ClientHello::parse(&body).unwrap();
+        assert_eq!(hello.supported_groups(), [0x1a1a, 29, 23]);
+        assert_eq!(hello.ec_point_formats(), [0]);
+        assert_eq!(hello.offered_versions(), [0x0304, 0x0303]);
+        assert_eq!(hello.ja3_string(), "771,4865-49199-47,10-11-43,29-23,0");
+        assert_eq!(hello.ja3(), "74cae17a8a8f522117e40dea846fec89");
+
+        let data = client_hello("dns.google");
+        let hello = ClientHello::try_from(data.as_slice()).unwrap();
+        assert_eq!(hello.ja3_string(), "771,4865-49199,0-16,,");
+        assert_eq!(hello.offered_versions(), [0x0303]);
+    }
+
+    #[test]
+    fn test_connection() {
+        use crate::reassembly::StreamDirection;
+        use std::net::{IpAddr, Ipv4Addr};
+
+        let mut server_hello = vec![0x03, 0x03];
+        server_hello.extend_from_slice(&[0u8; 32]);
+        server_hello.push(0);
+        server_hello.extend_from_slice(&[0x13, 0x01, 0x00]);
+        server_hello.extend_from_slice(&[0x00, 0x06, 0x00, 0x2b, 0x00, 0x02, 0x03, 0x04]);
+
+        let validity = [tlv(0x17, b"240101000000Z"), tlv(0x18, b"20341231235959Z")].concat();
+        let tbs = [
+            tlv(0xa0, &tlv(0x02, &[2])),
+            tlv(0x02, &[0x01, 0x23]),
+            tlv(0x30, &tlv(0x06, &[0x2a, 0x86, 0x48])),
+            common_name("Example CA"),
+            tlv(0x30, &validity),
+            common_name("example.com"),
+            tlv(0x30, &[]),
+        ]
+        .concat();
+        let der = tlv(0x30, &[tlv(0x30, &tbs), tlv(0x30, &[]), tlv(0x03, &[0])].concat());
+        let mut entry = (der.len() as u32).to_be_bytes()[1..].to_vec();
+        entry.extend_from_slice(&der);
+        let mut certificate = (entry.len() as u32).to_be_bytes()[1..].to_vec();
+        certificate.extend_from_slice(&entry);
+
+        // The ClientHello is split over two records.
+        let hello = client_hello("example.com");
+        let message = &hello[5..];
+        let mut client_data = record(0x16, &message[..20]);
+        client_data.extend(record(0x16, &message[20..]));
+        let mut server_data = record(
+            0x16,
+            &[
+                handshake(HANDSHAKE_SERVER_HELLO, &server_hello),
+                handshake(HANDSHAKE_CERTIFICATE, &certificate),
+            ]
+            .concat(),
+        );
+        server_data.extend(record(0x14, &[1]));
+        server_data.extend(record(0x17, &[0xde, 0xad]));
+
+        let client = (IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 49152);
+        let server = (IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)), 443);
+        let stream = TcpStream {
+            key: FlowKey::new(6, client, server),
+            client,
+            server,
+            first_number: 1,
+            client_data: StreamDirection {
+                data: client_data,
+                complete: true,
+                frames: vec![(0, 4)],
+            },
+            server_data: StreamDirection {
+                data: server_data,
+                complete: true,
+                frames: vec![(0, 5)],
+            },
+        };
+        let tls = connection(&stream).unwrap();
+        assert_eq!(tls.server_name.as_deref(), Some("example.com"));
+        assert_eq!(tls.alpn, ["h2"]);
+        assert_eq!(tls.offered_versions, ["TLS 1.2"]);
+        assert_eq!(tls.cipher_suites, [0x1301, 0xc02f]);
+        assert_eq!(tls.selected_version.as_deref(), Some("TLS 1.3"));
+        assert_eq!(tls.selected_cipher_suite, Some(0x1301));
+        assert_eq!(tls.ja3.as_deref(), Some("49cbdc1ae4567ba59ab722a6eed4184d"));
+        assert_eq!(tls.ja3s_string.as_deref(), Some("771,4865,43"));
+        assert_eq!(tls.ja3s.as_deref(), Some("cce84e7a8b742462e40afb585a3e3ccc"));
+        assert_eq!(tls.certificates.len(), 1);
+        let certificate = &tls.certificates[0];
+        assert_eq!(certificate.subject, "CN=example.com");
+        assert_eq!(certificate.issuer, "CN=Example CA");
+        assert_eq!(certificate.serial, "0123");
+        assert_eq!(certificate.not_before.as_deref(), Some("2024-01-01T00:00:00Z"));
+        assert_eq!(certificate.not_after.as_deref(), Some("2034-12-31T23:59:59Z"));
+        assert_eq!(certificate.size, der.len());
+    }
+}
@@ -0,0 +1,195 @@
+use crate::dissect::{Dissector, FieldInfo, FieldType, FieldValue, FieldValues, PacketLayers};
+use crate::error::KcpdumpError;
+
+/// UDP port MikroTik and other sniffers stream TZSP packets to.
+pub const TZSP_PORT: u16 = 37008;
+
+pub const TYPE_RECEIVED: u8 = 0;
+pub const TYPE_TRANSMIT: u8 = 1;
+
+pub const ENCAP_ETHERNET: u16 = 1;
+
+const TAG_PADDING: u8 = 0;
+const TAG_END: u8 = 1;
+
+/// Tzsp Packet
+/// A TaZmen Sniffer Protocol packet wrapping a captured frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TzspPacket {
+    pub version: u8,
+    pub packet_type: u8,
+    pub encapsulation: u16,
+    /// Tagged fields as (tag, value) pairs, without padding and end tags.
+    pub tags: Vec<(u8, Vec<u8>)>,
+    pub frame: Vec<u8>,
+}
+
+impl TryFrom<&[u8]> for TzspPacket {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 4 {
+            return Err(KcpdumpError::malformed(0, "Data too short for TZSP packet"));
+        }
+        if data[0] != 1 {
+            return Err(KcpdumpError::malformed(0, "Unsupported TZSP version"));
+        }
+        let mut tags = Vec::new();
+        let mut offset = 4;
+        loop {
+            match data.get(offset) {
+                None => {
+                    return Err(KcpdumpError::malformed(
+                        offset,
+                        "TZSP tagged fields truncated",
+                    ));
+                }
+                Some(&TAG_PADDING) => offset += 1,
+                Some(&TAG_END) => {
+                    offset += 1;
+                    break;
+                }
+                Some(&tag) => {
+                    let length = usize::from(
+                        *data
+                            .get(offset + 1)
+                            .ok_or(KcpdumpError::malformed(offset + 1, "TZSP tag truncated"))?,
+                    );
+                    let value = data
+                        .get(offset + 2..offset + 2 + length)
+                        .ok_or(KcpdumpError::malformed(offset + 2, "TZSP tag truncated"))?;
+                    tags.push((tag, value.to_vec()));
+                    offset += 2 + length;
+                }
+            }
+        }
+        Ok(TzspPacket {
+            version: data[0],
+            packet_type: data[1],
+            encapsulation: u16::from_be_bytes([data[2], data[3]]),
+            tags,
+            frame: data[offset..].to_vec(),
+        })
+    }
+}
+
+impl TzspPacket {
+    /// Link type of the encapsulated frame, if it is one this application decodes.
+    pub fn link_type(&self) -> Option<u32> {
+        if !matches!(self.packet_type, TYPE_RECEIVED | TYPE_TRANSMIT) {
+            return None;
+        }
+        match self.encapsulation {
+            ENCAP_ETHERNET => Some(1),
+            4 => Some(crate::ppp::LINKTYPE_PPP),
+            18 => Some(crate::wlan::LINKTYPE_IEEE802_11),
+            _ => None,
+        }
+    }
+}
+
+/// Tzsp Dissector
+pub struct TzspDissector;
+
+const TZSP_FIELDS: &[FieldInfo] = &[
+    FieldInfo::new("tzsp.version", FieldType::UInt, "Version"),
+    FieldInfo::new("tzsp.type", FieldType::UInt, "Packet type"),
+    FieldInfo::new("tzsp.encap", FieldType::UInt, "Encapsulated protocol"),
+];
+
+impl Dissector for TzspDissector {
+    fn protocol(&self) -> &'static str {
+        "tzsp"
+    }
+
+    fn description(&self) -> &'static str {
+        "TaZmen Sniffer Protocol"
+    }
+
+    fn fields(&self) -> &'static [FieldInfo] {
+        TZSP_FIELDS
+    }
+
+    fn dissect(&self, layers: &PacketLayers, values: &mut FieldValues) {
+        let Some(tzsp) = &layers.tzsp else {
+            return;
+        };
+        values.push("tzsp", FieldValue::Protocol);
+        values.push("tzsp.version", FieldValue::UInt(tzsp.version.into()));
+        values.push("tzsp.type", FieldValue::UInt(tzsp.packet_type.into()));
+        values.push("tzsp.encap", FieldValue::UInt(tzsp.encapsulation.into()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timefmt::Timestamp;
+
+    /// Ethernet/IPv4/UDP frame to the TZSP port carrying `payload`.
+    fn streamed(payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb,
+        ];
+        frame.extend_from_slice(&[0x08, 0x00]);
+        let total = (28 + payload.len()) as u16;
+        frame.extend_from_slice(&[0x45, 0x00]);
+        frame.extend_from_slice(&total.to_be_bytes());
+        frame.extend_from_slice(&[0x00, 0x01, 0x00, 0x00, 0x40, 0x11, 0x00, 0x00]);
+        frame.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2]);
+        frame.extend_from_slice(&40000u16.to_be_bytes());
+        frame.extend_from_slice(&TZSP_PORT.to_be_bytes());
+        frame.extend_from_slice(&(total - 20).to_be_bytes());
+        frame.extend_from_slice(&[0x00, 0x00]);
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    #[test]
+    fn test_tzsp_packet() {
+        let mut data = vec![0x01, 0x00, 0x00, 0x01, 0x00, 0x0a, 0x01, 0xd8, 0x01];
+        data.extend_from_slice(&[0xaa; 14]);
+        let tzsp = TzspPacket::try_from(data.as_slice()).unwrap();
+        assert_eq!(tzsp.encapsulation, ENCAP_ETHERNET);
+        assert_eq!(tzsp.tags, vec![(10, vec![0xd8])]);
+        assert_eq!(tzsp.frame, [0xaa; 14]);
+        assert_eq!(tzsp.link_type(), Some(1));
+
+        assert!(TzspPacket::try_from(&data[..6]).is_err());
+        assert!(TzspPacket::try_from(&[0x02, 0x00, 0x00, 0x01, 0x01][..]).is_err());
+    }
+
+    #[test]
+    fn test_inner_frame() {
+        let inner: [u8; 54] = [
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0x08, 0x00,
+            0x45, 0x00, 0x00, 0x28, 0x00, 0x01, 0x40, 0x00, 0x40, 0x06, 0x00, 0x00, 0xc0, 0xa8,
+            0x00, 0x0a, 0x5d, 0xb8, 0xd8, 0x22, 0xc3, 0x50, 0x00, 0x50, 0x00, 0x00, 0x03, 0xe8,
+            0x00, 0x00, 0x00, 0x00, 0x50, 0x02, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let mut payload = vec![0x01, 0x00, 0x00, 0x01, 0x01];
+        payload.extend_from_slice(&inner);
+        let frame = streamed(&payload);
+
+        let summary = crate::summary::summarize(&frame);
+        assert_eq!(summary.source, "192.168.0.10");
+        assert_eq!(summary.protocol, "TCP");
+        assert_eq!(summary.length, frame.len());
+
+        let packet = crate::cap::PcapPacket {
+            header: crate::cap::PcapPacketHeader {
+                timestamp: Timestamp::from_micros(0, 0),
+                incl_len: frame.len() as u32,
+                orig_len: frame.len() as u32,
+                link_type: None,
+            },
+            data: frame,
+        };
+        let layers = PacketLayers::decode(1, &packet);
+        assert_eq!(layers.tzsp.as_ref().unwrap().encapsulation, ENCAP_ETHERNET);
+        assert_eq!(layers.tcp.as_ref().unwrap().dest_port, 80);
+        assert!(layers.udp.is_none());
+        let values = crate::dissect::DissectorRegistry::default().dissect(&layers);
+        assert!(values.contains("tzsp"));
+    }
+}
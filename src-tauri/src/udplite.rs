@@ -0,0 +1,139 @@
+use crate::dissect::{Dissector, FieldInfo, FieldType, FieldValue, FieldValues, PacketLayers};
+use crate::error::KcpdumpError;
+
+/// UDP-Lite Datagram
+/// Represents a UDP-Lite header and its payload. The datagram extends to the
+/// end of the IP payload; the length field of UDP is replaced by the number
+/// of bytes covered by the checksum.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UdpLiteDatagram {
+    pub source_port: u16,
+    pub dest_port: u16,
+    /// Checksum coverage in bytes, 0 meaning the whole datagram.
+    pub coverage: u16,
+    pub checksum: u16,
+    pub payload: Vec<u8>,
+}
+
+impl TryFrom<&[u8]> for UdpLiteDatagram {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 8 {
+            return Err(KcpdumpError::malformed(
+                0,
+                "Data too short for UDP-Lite datagram",
+            ));
+        }
+        let coverage = u16::from_be_bytes([data[4], data[5]]);
+        if coverage != 0 && (coverage < 8 || coverage as usize > data.len()) {
+            return Err(KcpdumpError::malformed(
+                4,
+                "Invalid UDP-Lite checksum coverage",
+            ));
+        }
+        Ok(UdpLiteDatagram {
+            source_port: u16::from_be_bytes([data[0], data[1]]),
+            dest_port: u16::from_be_bytes([data[2], data[3]]),
+            coverage,
+            checksum: u16::from_be_bytes([data[6], data[7]]),
+            payload: data[8..].to_vec(),
+        })
+    }
+}
+
+impl UdpLiteDatagram {
+    /// Number of bytes, header included, protected by the checksum.
+    pub fn covered_len(&self) -> usize {
+        match self.coverage {
+            0 => self.payload.len() + 8,
+            coverage => coverage as usize,
+        }
+    }
+}
+
+/// UDP-Lite Dissector
+pub struct UdpLiteDissector;
+
+const UDPLITE_FIELDS: &[FieldInfo] = &[
+    FieldInfo::new("udplite.srcport", FieldType::UInt, "Source port"),
+    FieldInfo::new("udplite.dstport", FieldType::UInt, "Destination port"),
+    FieldInfo::new(
+        "udplite.port",
+        FieldType::UInt,
+        "Source or destination port",
+    ),
+    FieldInfo::new(
+        "udplite.checksum_coverage",
+        FieldType::UInt,
+        "Checksum coverage",
+    ),
+    FieldInfo::new("udplite.checksum", FieldType::UInt, "Checksum"),
+];
+
+impl Dissector for UdpLiteDissector {
+    fn protocol(&self) -> &'static str {
+        "udplite"
+    }
+
+    fn description(&self) -> &'static str {
+        "Lightweight User Datagram Protocol"
+    }
+
+    fn fields(&self) -> &'static [FieldInfo] {
+        UDPLITE_FIELDS
+    }
+
+    fn dissect(&self, layers: &PacketLayers, values: &mut FieldValues) {
+        let Some(udplite) = &layers.udplite else {
+            return;
+        };
+        values.push("udplite", FieldValue::Protocol);
+        values.push(
+            "udplite.srcport",
+            FieldValue::UInt(udplite.source_port.into()),
+        );
+        values.push(
+            "udplite.dstport",
+            FieldValue::UInt(udplite.dest_port.into()),
+        );
+        values.push("udplite.port", FieldValue::UInt(udplite.source_port.into()));
+        values.push("udplite.port", FieldValue::UInt(udplite.dest_port.into()));
+        values.push(
+            "udplite.checksum_coverage",
+            FieldValue::UInt(udplite.coverage.into()),
+        );
+        values.push(
+            "udplite.checksum",
+            FieldValue::UInt(udplite.checksum.into()),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_udplite_datagram() {
+        let data = [
+            0x13, 0x88, 0x13, 0x89, 0x00, 0x0a, 0x12, 0x34, 0xde, 0xad, 0xbe, 0xef,
+        ];
+        let datagram = UdpLiteDatagram::try_from(&data[..]).unwrap();
+        assert_eq!(datagram.source_port, 5000);
+        assert_eq!(datagram.dest_port, 5001);
+        assert_eq!(datagram.coverage, 10);
+        assert_eq!(datagram.covered_len(), 10);
+        assert_eq!(datagram.payload, [0xde, 0xad, 0xbe, 0xef]);
+
+        let mut full = data;
+        full[5] = 0;
+        assert_eq!(
+            UdpLiteDatagram::try_from(&full[..]).unwrap().covered_len(),
+            12
+        );
+        full[5] = 4;
+        assert!(UdpLiteDatagram::try_from(&full[..]).is_err());
+        assert!(UdpLiteDatagram::try_from(&data[..6]).is_err());
+    }
+}
@@ -0,0 +1,285 @@
+use crate::error::KcpdumpError;
+use crate::summary::PacketSummary;
+
+/// Link type of USBPcap captures from Windows.
+pub const LINKTYPE_USBPCAP: u32 = 249;
+
+pub const TRANSFER_ISOCHRONOUS: u8 = 0;
+pub const TRANSFER_INTERRUPT: u8 = 1;
+pub const TRANSFER_CONTROL: u8 = 2;
+pub const TRANSFER_BULK: u8 = 3;
+
+const BASE_HEADER_LEN: usize = 27;
+
+/// Stage of a control transfer, carried in the byte after the base header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlStage {
+    Setup,
+    Data,
+    Status,
+    Complete,
+    Unknown(u8),
+}
+
+impl From<u8> for ControlStage {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => ControlStage::Setup,
+            1 => ControlStage::Data,
+            2 => ControlStage::Status,
+            3 => ControlStage::Complete,
+            other => ControlStage::Unknown(other),
+        }
+    }
+}
+
+/// Setup Packet
+/// The 8-byte request that starts a control transfer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetupPacket {
+    pub request_type: u8,
+    pub request: u8,
+    pub value: u16,
+    pub index: u16,
+    pub length: u16,
+}
+
+impl TryFrom<&[u8]> for SetupPacket {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 8 {
+            return Err(KcpdumpError::malformed(
+                0,
+                "Data too short for USB setup packet",
+            ));
+        }
+        Ok(SetupPacket {
+            request_type: data[0],
+            request: data[1],
+            value: u16::from_le_bytes([data[2], data[3]]),
+            index: u16::from_le_bytes([data[4], data[5]]),
+            length: u16::from_le_bytes([data[6], data[7]]),
+        })
+    }
+}
+
+impl SetupPacket {
+    /// Describes standard requests the way USB analyzers do, e.g.
+    /// `GET DESCRIPTOR Request DEVICE`.
+    pub fn describe(&self) -> String {
+        // Only standard requests (type bits 0) have well-known meanings.
+        if self.request_type & 0x60 != 0 {
+            let kind = if self.request_type & 0x60 == 0x20 {
+                "Class"
+            } else {
+                "Vendor"
+            };
+            return format!("{} request 0x{:02x}", kind, self.request);
+        }
+        let name = match self.request {
+            0x00 => "GET STATUS",
+            0x01 => "CLEAR FEATURE",
+            0x03 => "SET FEATURE",
+            0x05 => "SET ADDRESS",
+            0x06 => "GET DESCRIPTOR",
+            0x07 => "SET DESCRIPTOR",
+            0x08 => "GET CONFIGURATION",
+            0x09 => "SET CONFIGURATION",
+            0x0a => "GET INTERFACE",
+            0x0b => "SET INTERFACE",
+            other => return format!("Standard request 0x{:02x}", other),
+        };
+        if matches!(self.request, 0x06 | 0x07) {
+            let descriptor = match self.value >> 8 {
+                1 => "DEVICE".to_string(),
+                2 => "CONFIGURATION".to_string(),
+                3 => "STRING".to_string(),
+                4 => "INTERFACE".to_string(),
+                5 => "ENDPOINT".to_string(),
+                6 => "DEVICE QUALIFIER".to_string(),
+                0x0f => "BOS".to_string(),
+                0x22 => "HID REPORT".to_string(),
+                other => format!("0x{:02x}", other),
+            };
+            return format!("{} Request {}", name, descriptor);
+        }
+        if self.request == 0x05 {
+            return format!("{} Request {}", name, self.value);
+        }
+        format!("{} Request", name)
+    }
+}
+
+/// USBPcap Packet
+/// A USB request block captured by USBPcap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsbPcapPacket {
+    pub irp_id: u64,
+    pub status: u32,
+    pub function: u16,
+    /// The URB travelled from the device to the host.
+    pub from_device: bool,
+    pub bus: u16,
+    pub device: u16,
+    /// Endpoint address, including the direction bit 0x80.
+    pub endpoint: u8,
+    pub transfer_type: u8,
+    /// Control stage, for control transfers.
+    pub stage: Option<ControlStage>,
+    pub data: Vec<u8>,
+}
+
+impl TryFrom<&[u8]> for UsbPcapPacket {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < BASE_HEADER_LEN {
+            return Err(KcpdumpError::malformed(
+                0,
+                "Data too short for USBPcap header",
+            ));
+        }
+        let header_len = u16::from_le_bytes([data[0], data[1]]) as usize;
+        if header_len < BASE_HEADER_LEN || header_len > data.len() {
+            return Err(KcpdumpError::malformed(0, "Invalid USBPcap header length"));
+        }
+        let transfer_type = data[22];
+        let stage = (transfer_type == TRANSFER_CONTROL && header_len > BASE_HEADER_LEN)
+            .then(|| ControlStage::from(data[BASE_HEADER_LEN]));
+        let data_length = u32::from_le_bytes([data[23], data[24], data[25], data[26]]) as usize;
+        let payload = &data[header_len..];
+        Ok(UsbPcapPacket {
+            irp_id: u64::from_le_bytes(data[2..10].try_into().unwrap_or_default()),
+            status: u32::from_le_bytes([data[10], data[11], data[12], data[13]]),
+            function: u16::from_le_bytes([data[14], data[15]]),
+            from_device: data[16] & 0x01 != 0,
+            bus: u16::from_le_bytes([data[17], data[18]]),
+            device: u16::from_le_bytes([data[19], data[20]]),
+            endpoint: data[21],
+            transfer_type,
+            stage,
+            data: payload[..payload.len().min(data_length)].to_vec(),
+        })
+    }
+}
+
+impl UsbPcapPacket {
+    /// Setup packet of the setup stage of a control transfer.
+    pub fn setup(&self) -> Option<SetupPacket> {
+        if self.stage != Some(ControlStage::Setup) {
+            return None;
+        }
+        SetupPacket::try_from(self.data.as_slice()).ok()
+    }
+
+    /// Device address in the `bus.device.endpoint` notation.
+    pub fn address(&self) -> String {
+        format!("{}.{}.{}", self.bus, self.device, self.endpoint & 0x0f)
+    }
+}
+
+/// Summarizes a USBPcap packet for the packet list.
+pub fn summarize(frame: &[u8]) -> PacketSummary {
+    let packet = match UsbPcapPacket::try_from(frame) {
+        Ok(packet) => packet,
+        Err(e) => {
+            return PacketSummary {
+                source: String::new(),
+                destination: String::new(),
+                protocol: "Malformed".to_string(),
+                length: frame.len(),
+                info: e.to_string(),
+                flow: None,
+            };
+        }
+    };
+
+    let (source, destination) = if packet.from_device {
+        (packet.address(), "host".to_string())
+    } else {
+        ("host".to_string(), packet.address())
+    };
+    let direction = if packet.from_device { "in" } else { "out" };
+    let info = match (packet.transfer_type, packet.setup()) {
+        (_, Some(setup)) => setup.describe(),
+        (TRANSFER_CONTROL, _) => match packet.stage {
+            Some(ControlStage::Complete) if packet.from_device => {
+                format!("Control response Len={}", packet.data.len())
+            }
+            Some(stage) => format!("Control {:?} stage Len={}", stage, packet.data.len()),
+            None => format!("Control transfer Len={}", packet.data.len()),
+        },
+        (transfer_type, _) => {
+            let kind = match transfer_type {
+                TRANSFER_ISOCHRONOUS => "ISOCHRONOUS",
+                TRANSFER_INTERRUPT => "INTERRUPT",
+                TRANSFER_BULK => "BULK",
+                _ => "UNKNOWN",
+            };
+            format!("URB_{} {} Len={}", kind, direction, packet.data.len())
+        }
+    };
+
+    PacketSummary {
+        source,
+        destination,
+        protocol: "USB".to_string(),
+        length: frame.len(),
+        info,
+        flow: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usbpcap(
+        from_device: bool,
+        endpoint: u8,
+        transfer_type: u8,
+        stage: Option<u8>,
+        payload: &[u8],
+    ) -> Vec<u8> {
+        let header_len = BASE_HEADER_LEN + usize::from(stage.is_some());
+        let mut data = (header_len as u16).to_le_bytes().to_vec();
+        data.extend_from_slice(&0x1234u64.to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&0x0008u16.to_le_bytes());
+        data.push(u8::from(from_device));
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&3u16.to_le_bytes());
+        data.push(endpoint);
+        data.push(transfer_type);
+        data.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        data.extend(stage);
+        data.extend_from_slice(payload);
+        data
+    }
+
+    #[test]
+    fn test_control_setup() {
+        let setup = [0x80, 0x06, 0x00, 0x01, 0x00, 0x00, 0x12, 0x00];
+        let frame = usbpcap(false, 0x80, TRANSFER_CONTROL, Some(0), &setup);
+        let packet = UsbPcapPacket::try_from(frame.as_slice()).unwrap();
+        assert_eq!((packet.bus, packet.device), (1, 3));
+        assert_eq!(packet.stage, Some(ControlStage::Setup));
+        let request = packet.setup().unwrap();
+        assert_eq!(request.length, 18);
+        let summary = summarize(&frame);
+        assert_eq!(summary.source, "host");
+        assert_eq!(summary.destination, "1.3.0");
+        assert_eq!(summary.protocol, "USB");
+        assert_eq!(summary.info, "GET DESCRIPTOR Request DEVICE");
+    }
+
+    #[test]
+    fn test_bulk_and_malformed() {
+        let frame = usbpcap(true, 0x81, TRANSFER_BULK, None, &[0xaa; 64]);
+        let summary = summarize(&frame);
+        assert_eq!(summary.source, "1.3.1");
+        assert_eq!(summary.info, "URB_BULK in Len=64");
+        assert_eq!(summarize(&frame[..10]).protocol, "Malformed");
+    }
+}
@@ -0,0 +1,740 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
+
+use crate::cap::TimestampResolution;
+use crate::dissect::PacketLayers;
+use crate::error::KcpdumpError;
+use crate::flowgraph::{FlowEvent, FlowGraph};
+use crate::flows::FlowKey;
+use crate::packetlist;
+use crate::session::LoadedCapture;
+use crate::timefmt::{TimeDisplayMode, TimeFormatter};
+
+pub const SIP_PORT: u16 = 5060;
+
+/// SIP Message
+/// A SIP request or response carried in a single UDP datagram.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SipMessage {
+    pub start_line: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl TryFrom<&[u8]> for SipMessage {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        let text = std::str::from_utf8(data).map_err(|e| {
+            KcpdumpError::malformed(e.valid_up_to(), "SIP message is not valid UTF-8")
+        })?;
+        let (head, body) = text.split_once("\r\n\r\n").ok_or(KcpdumpError::malformed(
+            0,
+            "SIP message has no end of headers",
+        ))?;
+        let mut lines = head.split("\r\n");
+        let start_line = lines.next().unwrap_or_default().to_string();
+        let is_response = start_line.starts_with("SIP/2.0 ");
+        if !is_response && !start_line.ends_with(" SIP/2.0") {
+            return Err(KcpdumpError::malformed(0, "Not a SIP start line"));
+        }
+        let headers = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+            .collect();
+        Ok(SipMessage {
+            start_line,
+            headers,
+            body: body.to_string(),
+        })
+    }
+}
+
+impl SipMessage {
+    /// Case-insensitive header lookup that also accepts the compact form.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        let compact = match name.to_ascii_lowercase().as_str() {
+            "call-id" => "i",
+            "from" => "f",
+            "to" => "t",
+            "content-type" => "c",
+            "content-length" => "l",
+            _ => "",
+        };
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name) || key.eq_ignore_ascii_case(compact))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Method of a request, e.g. `INVITE`.
+    pub fn method(&self) -> Option<&str> {
+        if self.start_line.starts_with("SIP/2.0 ") {
+            return None;
+        }
+        self.start_line.split_whitespace().next()
+    }
+
+    /// Status code of a response, e.g. 180.
+    pub fn status(&self) -> Option<u16> {
+        self.start_line
+            .strip_prefix("SIP/2.0 ")?
+            .split_whitespace()
+            .next()?
+            .parse()
+            .ok()
+    }
+
+    /// Method named in the CSeq header, which tells what a response answers.
+    pub fn cseq_method(&self) -> Option<&str> {
+        self.header("CSeq")?.split_whitespace().nth(1)
+    }
+
+    /// Short label for ladder diagrams: the method, or the status line of a response.
+    pub fn label(&self) -> String {
+        match self.method() {
+            Some(method) => method.to_string(),
+            None => self.start_line.trim_start_matches("SIP/2.0 ").to_string(),
+        }
+    }
+
+    /// Audio endpoint announced in an SDP body (`c=` address and `m=audio` port).
+    pub fn sdp_audio(&self) -> Option<(IpAddr, u16)> {
+        if !self
+            .header("Content-Type")
+            .is_some_and(|value| value.eq_ignore_ascii_case("application/sdp"))
+        {
+            return None;
+        }
+        let mut address = None;
+        let mut port = None;
+        for line in self.body.lines() {
+            if let Some(connection) = line.strip_prefix("c=IN IP4 ") {
+                address = connection.trim().parse::<Ipv4Addr>().ok().map(IpAddr::V4);
+            } else if let Some(media) = line.strip_prefix("m=audio ") {
+                port = media.split_whitespace().next()?.parse().ok();
+            }
+        }
+        Some((address?, port?))
+    }
+}
+
+/// Extracts the URI from a From or To header value,
+/// e.g. `"Alice" <sip:alice@example.com>;tag=1` gives `sip:alice@example.com`.
+fn party(value: &str) -> String {
+    match value.split_once('<') {
+        Some((_, rest)) => rest.split('>').next().unwrap_or_default().to_string(),
+        None => value
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+    }
+}
+
+/// Call State
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CallState {
+    /// INVITE sent, no provisional response yet.
+    Calling,
+    Ringing,
+    /// The INVITE was answered and no BYE was seen.
+    InCall,
+    /// Ended with a BYE.
+    Completed,
+    /// The INVITE got a final error response.
+    Rejected,
+    Cancelled,
+}
+
+/// RTP Stream Stats
+/// Sequence and timing statistics of one RTP stream (one SSRC in one direction).
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RtpStreamStats {
+    pub ssrc: u32,
+    pub source: String,
+    pub destination: String,
+    pub payload_type: u8,
+    pub codec: Option<String>,
+    pub first_number: usize,
+    pub packets: usize,
+    /// Packets expected from the sequence numbers but not received.
+    pub lost: u64,
+    pub lost_percent: f64,
+    /// Largest gap between two consecutive packets, in milliseconds.
+    pub max_delta_ms: f64,
+    /// Interarrival jitter as defined in RFC 3550, in milliseconds.
+    pub mean_jitter_ms: f64,
+    pub max_jitter_ms: f64,
+}
+
+/// Call Quality
+/// Worst-case figures over all RTP streams of a call.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CallQuality {
+    pub streams: usize,
+    pub packets: usize,
+    pub lost_percent: f64,
+    pub max_jitter_ms: f64,
+}
+
+/// VoIP Call
+/// A SIP dialog started by an INVITE.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct VoipCall {
+    pub call_id: String,
+    pub from: String,
+    pub to: String,
+    pub start_time: String,
+    pub end_time: String,
+    /// Seconds between the first and the last SIP message.
+    pub duration: f64,
+    pub state: CallState,
+    pub sip_packets: usize,
+    /// None when no RTP was seen for the call.
+    pub quality: Option<CallQuality>,
+}
+
+/// VoIP Call Detail
+/// The SIP ladder and RTP streams of one call.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct VoipCallDetail {
+    pub call: VoipCall,
+    pub ladder: FlowGraph,
+    pub streams: Vec<RtpStreamStats>,
+}
+
+/// Returns the codec name and clock rate of a static RTP payload type.
+/// Dynamic types are assumed to use an 8 kHz clock.
+pub fn payload_type_info(payload_type: u8) -> (Option<&'static str>, u32) {
+    match payload_type {
+        0 => (Some("PCMU"), 8000),
+        3 => (Some("GSM"), 8000),
+        4 => (Some("G723"), 8000),
+        8 => (Some("PCMA"), 8000),
+        9 => (Some("G722"), 8000),
+        18 => (Some("G729"), 8000),
+        _ => (None, 8000),
+    }
+}
+
+/// RTP Header
+/// The fixed part of an RTP header.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RtpHeader {
+    pub payload_type: u8,
+    pub sequence: u16,
+    pub timestamp: u32,
+    pub ssrc: u32,
+}
+
+impl TryFrom<&[u8]> for RtpHeader {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 12 {
+            return Err(KcpdumpError::malformed(0, "Data too short for RTP header"));
+        }
+        if data[0] >> 6 != 2 {
+            return Err(KcpdumpError::malformed(0, "Unsupported RTP version"));
+        }
+        Ok(RtpHeader {
+            payload_type: data[1] & 0x7f,
+            sequence: u16::from_be_bytes([data[2], data[3]]),
+            timestamp: u32::from_be_bytes([data[4], data[5], data[6], data[7]]),
+            ssrc: u32::from_be_bytes([data[8], data[9], data[10], data[11]]),
+        })
+    }
+}
+
+struct RtpStreamState {
+    stats: RtpStreamStats,
+    clock_rate: f64,
+    base_sequence: u32,
+    /// Highest sequence number seen, extended with the wrap-around count.
+    max_sequence: u32,
+    last_arrival: i64,
+    last_timestamp: u32,
+    /// Jitter in timestamp units.
+    jitter: f64,
+    jitter_sum: f64,
+}
+
+impl RtpStreamState {
+    fn new(
+        number: usize,
+        micros: i64,
+        header: RtpHeader,
+        source: String,
+        destination: String,
+    ) -> Self {
+        let (codec, clock_rate) = payload_type_info(header.payload_type);
+        RtpStreamState {
+            stats: RtpStreamStats {
+                ssrc: header.ssrc,
+                source,
+                destination,
+                payload_type: header.payload_type,
+                codec: codec.map(str::to_string),
+                first_number: number,
+                packets: 1,
+                lost: 0,
+                lost_percent: 0.0,
+                max_delta_ms: 0.0,
+                mean_jitter_ms: 0.0,
+                max_jitter_ms: 0.0,
+            },
+            clock_rate: f64::from(clock_rate),
+            base_sequence: u32::from(header.sequence),
+            max_sequence: u32::from(header.sequence),
+            last_arrival: micros,
+            last_timestamp: header.timestamp,
+            jitter: 0.0,
+            jitter_sum: 0.0,
+        }
+    }
+
+    fn push(&mut self, micros: i64, header: RtpHeader) {
+        self.stats.packets += 1;
+
+        let low = self.max_sequence as u16;
+        let delta = header.sequence.wrapping_sub(low);
+        if delta != 0 && delta < 0x8000 {
+            self.max_sequence += u32::from(delta);
+        }
+
+        let arrival_delta = micros - self.last_arrival;
+        self.stats.max_delta_ms = self.stats.max_delta_ms.max(arrival_delta as f64 / 1000.0);
+        let arrival_units = arrival_delta as f64 * self.clock_rate / 1_000_000.0;
+        let timestamp_units = f64::from(header.timestamp.wrapping_sub(self.last_timestamp) as i32);
+        let difference = (arrival_units - timestamp_units).abs();
+        self.jitter += (difference - self.jitter) / 16.0;
+        let jitter_ms = self.jitter * 1000.0 / self.clock_rate;
+        self.stats.max_jitter_ms = self.stats.max_jitter_ms.max(jitter_ms);
+        self.jitter_sum += jitter_ms;
+
+        self.last_arrival = micros;
+        self.last_timestamp = header.timestamp;
+    }
+
+    fn finish(mut self) -> RtpStreamStats {
+        let expected = u64::from(self.max_sequence - self.base_sequence) + 1;
+        self.stats.lost = expected.saturating_sub(self.stats.packets as u64);
+        self.stats.lost_percent = self.stats.lost as f64 * 100.0 / expected as f64;
+        if self.stats.packets > 1 {
+            self.stats.mean_jitter_ms = self.jitter_sum / (self.stats.packets - 1) as f64;
+        }
+        self.stats
+    }
+}
+
+struct CallRecord {
+    call: VoipCall,
+    first: i64,
+    ladder: FlowGraph,
+    streams: Vec<RtpStreamState>,
+}
+
+/// VoIP Analyzer
+/// Follows SIP dialogs and the RTP streams negotiated in their SDP bodies.
+/// Feed packets in time order and call `finish`.
+pub struct VoipAnalyzer {
+    formatter: TimeFormatter,
+    calls: Vec<CallRecord>,
+    by_call_id: HashMap<String, usize>,
+    /// Media endpoints announced in SDP, mapped to their call.
+    media: HashMap<(IpAddr, u16), usize>,
+    /// RTP streams by SSRC and flow, as (call, stream) indices.
+    rtp: HashMap<(u32, FlowKey), (usize, usize)>,
+}
+
+impl VoipAnalyzer {
+    /// An analyzer showing times in `mode` at `resolution`.
+    pub fn new(mode: TimeDisplayMode, resolution: TimestampResolution) -> Self {
+        VoipAnalyzer {
+            formatter: TimeFormatter::with_resolution(mode, resolution),
+            calls: Vec::new(),
+            by_call_id: HashMap::new(),
+            media: HashMap::new(),
+            rtp: HashMap::new(),
+        }
+    }
+
+    pub fn push(&mut self, layers: &PacketLayers) {
+        let header = &layers.packet.header;
+        let timestamp = header.timestamp;
+        let (Some(ip), Some(udp)) = (&layers.ipv4, &layers.udp) else {
+            self.formatter.skip(timestamp);
+            return;
+        };
+        let micros = timestamp.as_micros();
+        let source = (IpAddr::V4(Ipv4Addr::from(ip.source_ip)), udp.source_port);
+        let destination = (IpAddr::V4(Ipv4Addr::from(ip.dest_ip)), udp.dest_port);
+
+        if (udp.source_port == SIP_PORT || udp.dest_port == SIP_PORT)
+            && let Ok(message) = SipMessage::try_from(udp.payload.as_slice())
+            && let Some(index) = self.call_index(&message)
+        {
+            let event = FlowEvent {
+                frame_number: layers.number,
+                timestamp,
+                time: self.formatter.format(timestamp),
+                source: source.0.to_string(),
+                destination: destination.0.to_string(),
+                protocol: "SIP".to_string(),
+                label: message.label(),
+                flow: Some(FlowKey::new(ip.protocol, source, destination)),
+            };
+            self.push_sip(index, micros, event, &message);
+            if let Some(endpoint) = message.sdp_audio() {
+                self.media.insert(endpoint, index);
+            }
+            return;
+        }
+        self.formatter.skip(timestamp);
+
+        let Some(&call) = self.media.get(&destination) else {
+            return;
+        };
+        let Ok(rtp) = RtpHeader::try_from(udp.payload.as_slice()) else {
+            return;
+        };
+        let flow = FlowKey::new(ip.protocol, source, destination);
+        match self.rtp.get(&(rtp.ssrc, flow)) {
+            Some(&(call, stream)) => self.calls[call].streams[stream].push(micros, rtp),
+            None => {
+                let streams = &mut self.calls[call].streams;
+                self.rtp.insert((rtp.ssrc, flow), (call, streams.len()));
+                streams.push(RtpStreamState::new(
+                    layers.number,
+                    micros,
+                    rtp,
+                    format!("{}:{}", source.0, source.1),
+                    format!("{}:{}", destination.0, destination.1),
+                ));
+            }
+        }
+    }
+
+    /// Finds the call a message belongs to. Only an INVITE starts a new call.
+    fn call_index(&mut self, message: &SipMessage) -> Option<usize> {
+        let call_id = message.header("Call-ID")?;
+        if let Some(&index) = self.by_call_id.get(call_id) {
+            return Some(index);
+        }
+        if message.method() != Some("INVITE") {
+            return None;
+        }
+        let index = self.calls.len();
+        self.by_call_id.insert(call_id.to_string(), index);
+        self.calls.push(CallRecord {
+            call: VoipCall {
+                call_id: call_id.to_string(),
+                from: message.header("From").map(party).unwrap_or_default(),
+                to: message.header("To").map(party).unwrap_or_default(),
+                start_time: String::new(),
+                end_time: String::new(),
+                duration: 0.0,
+                state: CallState::Calling,
+                sip_packets: 0,
+                quality: None,
+            },
+            first: 0,
+            ladder: FlowGraph::default(),
+            streams: Vec::new(),
+        });
+        Some(index)
+    }
+
+    /// Updates the call state and appends the message to the ladder.
+    fn push_sip(&mut self, index: usize, micros: i64, event: FlowEvent, message: &SipMessage) {
+        let record = &mut self.calls[index];
+        let call = &mut record.call;
+        if call.sip_packets == 0 {
+            record.first = micros;
+            call.start_time = event.time.clone();
+        }
+        call.sip_packets += 1;
+        call.end_time = event.time.clone();
+        call.duration = (micros - record.first) as f64 / 1_000_000.0;
+
+        call.state = match (message.method(), message.status(), message.cseq_method()) {
+            (Some("BYE"), _, _) => CallState::Completed,
+            (Some("CANCEL"), _, _) if call.state != CallState::InCall => CallState::Cancelled,
+            (None, Some(180 | 183), Some("INVITE")) if call.state == CallState::Calling => {
+                CallState::Ringing
+            }
+            (None, Some(200..=299), Some("INVITE")) if call.state != CallState::Completed => {
+                CallState::InCall
+            }
+            (None, Some(487), Some("INVITE")) => CallState::Cancelled,
+            (None, Some(300..), Some("INVITE"))
+                if matches!(call.state, CallState::Calling | CallState::Ringing) =>
+            {
+                CallState::Rejected
+            }
+            _ => call.state,
+        };
+
+        record.ladder.push(event);
+    }
+
+    pub fn finish(self) -> Vec<VoipCallDetail> {
+        self.calls
+            .into_iter()
+            .map(|record| {
+                let streams: Vec<_> = record
+                    .streams
+                    .into_iter()
+                    .map(RtpStreamState::finish)
+                    .collect();
+                let mut call = record.call;
+                call.quality = (!streams.is_empty()).then(|| CallQuality {
+                    streams: streams.len(),
+                    packets: streams.iter().map(|stream| stream.packets).sum(),
+                    lost_percent: streams
+                        .iter()
+                        .map(|stream| stream.lost_percent)
+                        .fold(0.0, f64::max),
+                    max_jitter_ms: streams
+                        .iter()
+                        .map(|stream| stream.max_jitter_ms)
+                        .fold(0.0, f64::max),
+                });
+                VoipCallDetail {
+                    call,
+                    ladder: record.ladder,
+                    streams,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Analyzes the given captures, interleaved by time, and returns every call
+/// with its ladder and RTP streams.
+pub fn analyze_calls(
+    captures: &[Arc<LoadedCapture>],
+    mode: TimeDisplayMode,
+) -> Result<Vec<VoipCallDetail>, KcpdumpError> {
+    let mut analyzer = VoipAnalyzer::new(mode, packetlist::resolution(captures));
+    let link_types = packetlist::link_types(captures);
+    for item in packetlist::merged_packets(captures) {
+        let (capture_id, number, packet) = item?;
+        analyzer.push(&PacketLayers::decode_link(number, link_types[&capture_id], &packet));
+    }
+    Ok(analyzer.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timefmt::Timestamp;
+    use crate::cap::{PcapPacket, PcapPacketHeader};
+
+    /// A UDP datagram between 10.0.0.1 and 10.0.0.2 at `millis` after the epoch.
+    fn udp_frame(forward: bool, ports: (u16, u16), millis: u32, payload: &[u8]) -> PcapPacket {
+        let (source, destination) = if forward {
+            ([10, 0, 0, 1], [10, 0, 0, 2])
+        } else {
+            ([10, 0, 0, 2], [10, 0, 0, 1])
+        };
+        let mut data = vec![
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0x08, 0x00,
+            0x45, 0x00, 0x00, 0x00, 0x00, 0x01, 0x40, 0x00, 0x40, 0x11, 0x00, 0x00,
+        ];
+        data.extend_from_slice(&source);
+        data.extend_from_slice(&destination);
+        data.extend_from_slice(&ports.0.to_be_bytes());
+        data.extend_from_slice(&ports.1.to_be_bytes());
+        data.extend_from_slice(&(8 + payload.len() as u16).to_be_bytes());
+        data.extend_from_slice(&[0, 0]);
+        data.extend_from_slice(payload);
+        let total = (data.len() - 14) as u16;
+        data[16..18].copy_from_slice(&total.to_be_bytes());
+        PcapPacket {
+            header: PcapPacketHeader {
+                timestamp: Timestamp::from_micros(millis / 1000, millis % 1000 * 1000),
+                incl_len: data.len() as u32,
+                orig_len: data.len() as u32,
+                link_type: None,
+            },
+            data,
+        }
+    }
+
+    fn sip(start_line: &str, cseq: &str, sdp_port: Option<u16>) -> Vec<u8> {
+        let body = sdp_port
+            .map(|port| format!("v=0\r\nc=IN IP4 10.0.0.2\r\nm=audio {} RTP/AVP 0\r\n", port))
+            .unwrap_or_default();
+        let content_type = if body.is_empty() {
+            ""
+        } else {
+            "Content-Type: application/sdp\r\n"
+        };
+        format!(
+            "{}\r\nCall-ID: abc@10.0.0.1\r\nFrom: \"Alice\" <sip:alice@example.com>;tag=1\r\nt: <sip:bob@example.com>\r\nCSeq: {}\r\n{}Content-Length: {}\r\n\r\n{}",
+            start_line,
+            cseq,
+            content_type,
+            body.len(),
+            body
+        )
+        .into_bytes()
+    }
+
+    fn rtp(sequence: u16, timestamp: u32) -> Vec<u8> {
+        let mut data = vec![0x80, 0x00];
+        data.extend_from_slice(&sequence.to_be_bytes());
+        data.extend_from_slice(&timestamp.to_be_bytes());
+        data.extend_from_slice(&0x1234u32.to_be_bytes());
+        data.extend_from_slice(&[0xff; 160]);
+        data
+    }
+
+    #[test]
+    fn test_parse_sip_message() {
+        let message =
+            SipMessage::try_from(sip("SIP/2.0 200 OK", "1 INVITE", Some(4000)).as_slice()).unwrap();
+        assert_eq!(message.status(), Some(200));
+        assert_eq!(message.method(), None);
+        assert_eq!(message.cseq_method(), Some("INVITE"));
+        assert_eq!(message.label(), "200 OK");
+        assert_eq!(message.header("To"), Some("<sip:bob@example.com>"));
+        assert_eq!(
+            message.sdp_audio(),
+            Some((IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)), 4000))
+        );
+        assert_eq!(
+            party("\"Alice\" <sip:alice@example.com>;tag=1"),
+            "sip:alice@example.com"
+        );
+        assert!(SipMessage::try_from(b"GET / HTTP/1.1\r\n\r\n".as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_call_with_rtp_stream() {
+        let ports = (SIP_PORT, SIP_PORT);
+        let mut packets = vec![
+            udp_frame(
+                true,
+                ports,
+                0,
+                &sip("INVITE sip:bob@example.com SIP/2.0", "1 INVITE", None),
+            ),
+            udp_frame(
+                false,
+                ports,
+                100,
+                &sip("SIP/2.0 180 Ringing", "1 INVITE", None),
+            ),
+            udp_frame(
+                false,
+                ports,
+                1000,
+                &sip("SIP/2.0 200 OK", "1 INVITE", Some(4000)),
+            ),
+            udp_frame(
+                true,
+                ports,
+                1010,
+                &sip("ACK sip:bob@example.com SIP/2.0", "1 ACK", None),
+            ),
+        ];
+        // 20 ms packets with sequence number 3 missing and the last one 10 ms late.
+        for sequence in [1u16, 2, 4, 5] {
+            let late = if sequence == 5 { 10 } else { 0 };
+            let millis = 1100 + u32::from(sequence) * 20 + late;
+            packets.push(udp_frame(
+                true,
+                (5000, 4000),
+                millis,
+                &rtp(sequence, u32::from(sequence) * 160),
+            ));
+        }
+        packets.push(udp_frame(
+            true,
+            ports,
+            5000,
+            &sip("BYE sip:bob@example.com SIP/2.0", "2 BYE", None),
+        ));
+
+        let mut analyzer = VoipAnalyzer::new(TimeDisplayMode::SinceStart, TimestampResolution::Microsecond);
+        for (index, packet) in packets.iter().enumerate() {
+            analyzer.push(&PacketLayers::decode(index + 1, packet));
+        }
+        let calls = analyzer.finish();
+        assert_eq!(calls.len(), 1);
+        let detail = &calls[0];
+        assert_eq!(detail.call.from, "sip:alice@example.com");
+        assert_eq!(detail.call.to, "sip:bob@example.com");
+        assert_eq!(detail.call.state, CallState::Completed);
+        assert_eq!(detail.call.sip_packets, 5);
+        assert_eq!(detail.call.duration, 5.0);
+        assert_eq!(detail.call.end_time, "5.000000");
+        assert_eq!(detail.ladder.nodes, vec!["10.0.0.1", "10.0.0.2"]);
+        let labels: Vec<_> = detail
+            .ladder
+            .events
+            .iter()
+            .map(|event| event.label.as_str())
+            .collect();
+        assert_eq!(
+            labels,
+            vec!["INVITE", "180 Ringing", "200 OK", "ACK", "BYE"]
+        );
+
+        assert_eq!(detail.streams.len(), 1);
+        let stream = &detail.streams[0];
+        assert_eq!(stream.codec.as_deref(), Some("PCMU"));
+        assert_eq!(stream.packets, 4);
+        assert_eq!(stream.lost, 1);
+        assert_eq!(stream.lost_percent, 20.0);
+        assert_eq!(stream.max_delta_ms, 40.0);
+        assert!(stream.max_jitter_ms > 0.0);
+        let quality = detail.call.quality.as_ref().unwrap();
+        assert_eq!(quality.packets, 4);
+        assert_eq!(quality.lost_percent, 20.0);
+    }
+
+    #[test]
+    fn test_rejected_call_and_register_ignored() {
+        let ports = (SIP_PORT, SIP_PORT);
+        let packets = [
+            udp_frame(
+                true,
+                ports,
+                0,
+                &sip("REGISTER sip:example.com SIP/2.0", "1 REGISTER", None),
+            ),
+            udp_frame(
+                true,
+                ports,
+                10,
+                &sip("INVITE sip:bob@example.com SIP/2.0", "2 INVITE", None),
+            ),
+            udp_frame(
+                false,
+                ports,
+                20,
+                &sip("SIP/2.0 486 Busy Here", "2 INVITE", None),
+            ),
+        ];
+        let mut analyzer = VoipAnalyzer::new(TimeDisplayMode::SinceStart, TimestampResolution::Microsecond);
+        for (index, packet) in packets.iter().enumerate() {
+            analyzer.push(&PacketLayers::decode(index + 1, packet));
+        }
+        let calls = analyzer.finish();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].call.state, CallState::Rejected);
+        assert_eq!(calls[0].call.sip_packets, 2);
+        assert!(calls[0].call.quality.is_none());
+    }
+}
@@ -0,0 +1,364 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use tokio::io;
+
+use crate::cap::Capture;
+use crate::dissect::{DissectorRegistry, FieldValue, PacketLayers};
+use crate::filter::PacketFilter;
+use crate::summary;
+
+pub type WatchId = u32;
+
+/// Analysis Profile
+/// What to run on every capture file that appears in a watched directory.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AnalysisProfile {
+    /// Only packets passing this filter are counted.
+    pub filter: PacketFilter,
+    pub alerts: Vec<AlertRule>,
+    /// File extensions to pick up, without the dot.
+    pub extensions: Vec<String>,
+    /// Also analyze files that already exist when the watch starts.
+    pub include_existing: bool,
+    pub poll_interval_ms: u64,
+}
+
+impl Default for AnalysisProfile {
+    fn default() -> Self {
+        AnalysisProfile {
+            filter: PacketFilter::default(),
+            alerts: Vec::new(),
+            extensions: vec!["pcap".to_string(), "cap".to_string()],
+            include_existing: false,
+            poll_interval_ms: 2000,
+        }
+    }
+}
+
+/// Alert Rule
+/// Raises an alert when at least `threshold` packets carry `field`.
+/// Boolean fields only count when true, e.g. `tcp.flags.reset`.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertRule {
+    pub name: String,
+    pub field: String,
+    pub threshold: usize,
+}
+
+/// Batch Stats
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchStats {
+    pub packets: usize,
+    pub bytes: u64,
+    pub first_ts_sec: Option<u32>,
+    pub last_ts_sec: Option<u32>,
+    /// Packet count per highest decoded protocol.
+    pub protocols: BTreeMap<String, usize>,
+}
+
+/// Alert Hit
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertHit {
+    pub name: String,
+    pub count: usize,
+    pub first_number: usize,
+}
+
+/// Batch Report
+/// The result of running an analysis profile on one capture file.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchReport {
+    pub path: String,
+    pub stats: BatchStats,
+    pub alerts: Vec<AlertHit>,
+}
+
+/// Watch Event
+/// Emitted to the frontend for every file processed by a directory watch.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum WatchEvent {
+    #[serde(rename_all = "camelCase")]
+    Analyzed {
+        watch_id: WatchId,
+        report: BatchReport,
+    },
+    #[serde(rename_all = "camelCase")]
+    Failed {
+        watch_id: WatchId,
+        path: String,
+        error: String,
+    },
+}
+
+/// Runs `profile` over one capture file.
+pub async fn analyze_file(
+    path: &str,
+    profile: &AnalysisProfile,
+    registry: &DissectorRegistry,
+) -> io::Result<BatchReport> {
+    let mut capture = Capture::from_file(path).await?;
+    let mut stats = BatchStats::default();
+    let mut hits: Vec<Option<AlertHit>> = vec![None; profile.alerts.len()];
+    let mut number = 0;
+
+    while let Some(packet) = capture.next_packet().await? {
+        number += 1;
+        let header = &packet.header;
+        if !profile.filter.matches_frame(header.timestamp, &packet.data) {
+            continue;
+        }
+        stats.packets += 1;
+        stats.bytes += u64::from(header.orig_len);
+        stats.first_ts_sec.get_or_insert(header.timestamp.sec);
+        stats.last_ts_sec = Some(header.timestamp.sec);
+        let link_type = capture.header().network;
+        let protocol = summary::summarize_link(link_type, &packet.data).protocol;
+        *stats.protocols.entry(protocol).or_default() += 1;
+
+        if profile.alerts.is_empty() {
+            continue;
+        }
+        let values = registry.dissect(&PacketLayers::decode_link(number, link_type, &packet));
+        for (rule, hit) in profile.alerts.iter().zip(hits.iter_mut()) {
+            let matched = values
+                .get(&rule.field)
+                .any(|value| *value != FieldValue::Bool(false));
+            if matched {
+                hit.get_or_insert(AlertHit {
+                    name: rule.name.clone(),
+                    count: 0,
+                    first_number: number,
+                })
+                .count += 1;
+            }
+        }
+    }
+
+    let alerts = profile
+        .alerts
+        .iter()
+        .zip(hits)
+        .filter_map(|(rule, hit)| hit.filter(|hit| hit.count >= rule.threshold.max(1)))
+        .collect();
+    Ok(BatchReport {
+        path: path.to_string(),
+        stats,
+        alerts,
+    })
+}
+
+/// Directory Scanner
+/// Finds capture files that appeared in a directory. A file is reported once
+/// its size stayed the same across two polls, so files that are still being
+/// written by a rotating capture are picked up only after they are closed.
+#[derive(Debug)]
+pub struct DirectoryScanner {
+    directory: PathBuf,
+    extensions: Vec<String>,
+    seen: HashSet<PathBuf>,
+    pending: HashMap<PathBuf, u64>,
+}
+
+impl DirectoryScanner {
+    /// Creates a scanner. Unless `include_existing` is set, files already in
+    /// the directory are ignored.
+    pub fn new(
+        directory: &Path,
+        extensions: &[String],
+        include_existing: bool,
+    ) -> io::Result<Self> {
+        let mut scanner = DirectoryScanner {
+            directory: directory.to_path_buf(),
+            extensions: extensions.iter().map(|e| e.to_lowercase()).collect(),
+            seen: HashSet::new(),
+            pending: HashMap::new(),
+        };
+        if !include_existing {
+            scanner.seen = scanner.list()?.into_iter().map(|(path, _)| path).collect();
+        }
+        Ok(scanner)
+    }
+
+    /// Returns the files that became ready since the last poll, sorted by name.
+    pub fn poll(&mut self) -> io::Result<Vec<PathBuf>> {
+        let mut ready = Vec::new();
+        let mut pending = HashMap::new();
+        for (path, size) in self.list()? {
+            if self.seen.contains(&path) {
+                continue;
+            }
+            if size > 0 && self.pending.get(&path) == Some(&size) {
+                self.seen.insert(path.clone());
+                ready.push(path);
+            } else {
+                pending.insert(path, size);
+            }
+        }
+        self.pending = pending;
+        ready.sort();
+        Ok(ready)
+    }
+
+    fn list(&self) -> io::Result<Vec<(PathBuf, u64)>> {
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(&self.directory)? {
+            let entry = entry?;
+            let path = entry.path();
+            let extension = path
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(|e| e.to_lowercase());
+            if !extension.is_some_and(|e| self.extensions.contains(&e)) {
+                continue;
+            }
+            let metadata = entry.metadata()?;
+            if metadata.is_file() {
+                files.push((path, metadata.len()));
+            }
+        }
+        Ok(files)
+    }
+}
+
+/// Directory Watch Handle
+/// Stops the watch when asked or dropped.
+#[derive(Debug)]
+pub struct DirectoryWatchHandle {
+    stop: Arc<AtomicBool>,
+}
+
+impl DirectoryWatchHandle {
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for DirectoryWatchHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Watches `directory` and runs `profile` on every new capture file, passing
+/// the results to `sink`.
+pub fn start<F>(
+    watch_id: WatchId,
+    directory: &Path,
+    profile: AnalysisProfile,
+    mut sink: F,
+) -> io::Result<DirectoryWatchHandle>
+where
+    F: FnMut(WatchEvent) + Send + 'static,
+{
+    let mut scanner =
+        DirectoryScanner::new(directory, &profile.extensions, profile.include_existing)?;
+    let stop = Arc::new(AtomicBool::new(false));
+    let handle = DirectoryWatchHandle { stop: stop.clone() };
+
+    tauri::async_runtime::spawn(async move {
+        let registry = DissectorRegistry::default();
+        let mut interval =
+            tokio::time::interval(Duration::from_millis(profile.poll_interval_ms.max(100)));
+        while !stop.load(Ordering::Relaxed) {
+            interval.tick().await;
+            let ready = match scanner.poll() {
+                Ok(ready) => ready,
+                Err(e) => {
+                    sink(WatchEvent::Failed {
+                        watch_id,
+                        path: scanner.directory.to_string_lossy().into_owned(),
+                        error: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+            for path in ready {
+                let path = path.to_string_lossy().into_owned();
+                let event = match analyze_file(&path, &profile, &registry).await {
+                    Ok(report) => WatchEvent::Analyzed { watch_id, report },
+                    Err(e) => WatchEvent::Failed {
+                        watch_id,
+                        path,
+                        error: e.to_string(),
+                    },
+                };
+                sink(event);
+            }
+        }
+    });
+
+    Ok(handle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_analyze_file_with_alerts() {
+        let profile = AnalysisProfile {
+            alerts: vec![
+                AlertRule {
+                    name: "DNS traffic".to_string(),
+                    field: "dns".to_string(),
+                    threshold: 1,
+                },
+                AlertRule {
+                    name: "SYN seen".to_string(),
+                    field: "tcp.flags.syn".to_string(),
+                    threshold: 1,
+                },
+                AlertRule {
+                    name: "Many resets".to_string(),
+                    field: "tcp.flags.reset".to_string(),
+                    threshold: 100,
+                },
+            ],
+            ..Default::default()
+        };
+        let report = analyze_file("sample.pcap", &profile, &DissectorRegistry::default())
+            .await
+            .unwrap();
+        assert!(report.stats.packets > 0);
+        assert_eq!(
+            report.stats.protocols.values().sum::<usize>(),
+            report.stats.packets
+        );
+        let names: Vec<_> = report.alerts.iter().map(|hit| hit.name.as_str()).collect();
+        assert_eq!(names, vec!["DNS traffic", "SYN seen"]);
+    }
+
+    #[test]
+    fn test_scanner_waits_for_stable_size() {
+        let directory = std::env::temp_dir().join(format!("kcpdump-watch-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("old.pcap"), b"old").unwrap();
+
+        let extensions = AnalysisProfile::default().extensions;
+        let mut scanner = DirectoryScanner::new(&directory, &extensions, false).unwrap();
+        std::fs::write(directory.join("new.pcap"), b"partial").unwrap();
+        std::fs::write(directory.join("notes.txt"), b"ignored").unwrap();
+        assert!(scanner.poll().unwrap().is_empty());
+
+        std::fs::write(directory.join("new.pcap"), b"partial and done").unwrap();
+        assert!(scanner.poll().unwrap().is_empty());
+        assert_eq!(scanner.poll().unwrap(), vec![directory.join("new.pcap")]);
+        assert!(scanner.poll().unwrap().is_empty());
+
+        let mut scanner = DirectoryScanner::new(&directory, &extensions, true).unwrap();
+        scanner.poll().unwrap();
+        assert_eq!(scanner.poll().unwrap().len(), 2);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,754 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+use crate::error::KcpdumpError;
+use crate::packet::{IPv4Packet, MacAddress};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::session::LoadedCapture;
+use crate::summary::{self, PacketSummary};
+
+/// Link types of 802.11 captures.
+pub const LINKTYPE_IEEE802_11: u32 = 105;
+pub const LINKTYPE_IEEE802_11_RADIOTAP: u32 = 127;
+
+/// LLC/SNAP header announcing an EAPOL payload (EtherType 0x888E).
+const EAPOL_SNAP: [u8; 8] = [0xaa, 0xaa, 0x03, 0x00, 0x00, 0x00, 0x88, 0x8e];
+/// LLC/SNAP header announcing an IPv4 payload.
+const IPV4_SNAP: [u8; 8] = [0xaa, 0xaa, 0x03, 0x00, 0x00, 0x00, 0x08, 0x00];
+
+const KEY_INFO_PAIRWISE: u16 = 0x0008;
+const KEY_INFO_INSTALL: u16 = 0x0040;
+const KEY_INFO_ACK: u16 = 0x0080;
+const KEY_INFO_MIC: u16 = 0x0100;
+const KEY_INFO_SECURE: u16 = 0x0200;
+
+/// Offset and length of the MIC within an EAPOL-Key frame.
+const MIC_OFFSET: usize = 81;
+const MIC_LENGTH: usize = 16;
+
+/// Wlan Frame
+/// The parts of an 802.11 frame needed to follow WPA handshakes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WlanFrame {
+    /// 0 management, 1 control, 2 data.
+    pub frame_type: u8,
+    pub subtype: u8,
+    pub to_ds: bool,
+    pub from_ds: bool,
+    pub protected: bool,
+    pub addr1: MacAddress,
+    pub addr2: MacAddress,
+    pub addr3: MacAddress,
+    /// Frame body after the MAC header.
+    pub body: Vec<u8>,
+}
+
+impl TryFrom<&[u8]> for WlanFrame {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 24 {
+            return Err(KcpdumpError::malformed(
+                0,
+                "Data too short for 802.11 frame",
+            ));
+        }
+        let control = data[0];
+        let flags = data[1];
+        let frame_type = (control >> 2) & 0x03;
+        let subtype = control >> 4;
+        let to_ds = flags & 0x01 != 0;
+        let from_ds = flags & 0x02 != 0;
+
+        let mut header_length = 24;
+        if frame_type == 2 {
+            if to_ds && from_ds {
+                header_length += 6;
+            }
+            if subtype & 0x08 != 0 {
+                header_length += 2;
+                // HT control field of QoS data frames with the order bit set.
+                if flags & 0x80 != 0 {
+                    header_length += 4;
+                }
+            }
+        }
+        let body = data.get(header_length..).ok_or(KcpdumpError::malformed(
+            header_length,
+            "802.11 header truncated",
+        ))?;
+
+        let mac = |offset: usize| {
+            MacAddress([
+                data[offset],
+                data[offset + 1],
+                data[offset + 2],
+                data[offset + 3],
+                data[offset + 4],
+                data[offset + 5],
+            ])
+        };
+        Ok(WlanFrame {
+            frame_type,
+            subtype,
+            to_ds,
+            from_ds,
+            protected: flags & 0x40 != 0,
+            addr1: mac(4),
+            addr2: mac(10),
+            addr3: mac(16),
+            body: body.to_vec(),
+        })
+    }
+}
+
+impl WlanFrame {
+    /// Decodes a captured frame of the given link type, removing the radiotap header if present.
+    pub fn decode(link_type: u32, data: &[u8]) -> Option<Self> {
+        let frame = match link_type {
+            LINKTYPE_IEEE802_11 => data,
+            LINKTYPE_IEEE802_11_RADIOTAP => {
+                let length = u16::from_le_bytes([*data.get(2)?, *data.get(3)?]) as usize;
+                data.get(length..)?
+            }
+            _ => return None,
+        };
+        WlanFrame::try_from(frame).ok()
+    }
+
+    /// SSID of a beacon or probe response.
+    pub fn ssid(&self) -> Option<Vec<u8>> {
+        if self.frame_type != 0 || !matches!(self.subtype, 5 | 8) {
+            return None;
+        }
+        // Tagged parameters follow the timestamp, beacon interval and capabilities.
+        let mut offset = 12;
+        while let (Some(&tag), Some(&length)) = (self.body.get(offset), self.body.get(offset + 1)) {
+            let value = self.body.get(offset + 2..offset + 2 + length as usize)?;
+            if tag == 0 {
+                return Some(value.to_vec());
+            }
+            offset += 2 + length as usize;
+        }
+        None
+    }
+
+    /// Access point address, for frames between a station and an access point.
+    pub fn bssid(&self) -> Option<MacAddress> {
+        match (self.to_ds, self.from_ds) {
+            (false, false) => Some(self.addr3),
+            (false, true) => Some(self.addr2),
+            (true, false) => Some(self.addr1),
+            (true, true) => None,
+        }
+    }
+
+    /// Station address of a data frame exchanged with an access point.
+    pub fn station(&self) -> Option<MacAddress> {
+        match (self.to_ds, self.from_ds) {
+            (false, true) => Some(self.addr1),
+            (true, false) => Some(self.addr2),
+            _ => None,
+        }
+    }
+
+    /// Information elements of a management frame, as (element ID, value) pairs.
+    pub fn elements(&self) -> Vec<(u8, &[u8])> {
+        // Fixed fields preceding the elements, per management subtype.
+        let offset = match (self.frame_type, self.subtype) {
+            (0, 0) => 4,
+            (0, 1) | (0, 3) => 6,
+            (0, 2) => 10,
+            (0, 4) => 0,
+            (0, 5) | (0, 8) => 12,
+            _ => return Vec::new(),
+        };
+        let mut elements = Vec::new();
+        let mut rest = self.body.get(offset..).unwrap_or_default();
+        while let [id, length, ..] = *rest {
+            let Some(value) = rest.get(2..2 + length as usize) else {
+                break;
+            };
+            elements.push((id, value));
+            rest = &rest[2 + length as usize..];
+        }
+        elements
+    }
+
+    /// Capability information of a beacon, probe response or association frame.
+    pub fn capabilities(&self) -> Option<u16> {
+        let offset = match (self.frame_type, self.subtype) {
+            (0, 0..=3) => 0,
+            (0, 5) | (0, 8) => 10,
+            _ => return None,
+        };
+        let bytes = self.body.get(offset..offset + 2)?;
+        Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// EAPOL frame carried in an unprotected data frame.
+    pub fn eapol(&self) -> Option<&[u8]> {
+        if self.frame_type != 2 || self.protected {
+            return None;
+        }
+        let payload = self.body.strip_prefix(EAPOL_SNAP.as_slice())?;
+        let length = u16::from_be_bytes([*payload.get(2)?, *payload.get(3)?]) as usize;
+        payload.get(..4 + length)
+    }
+}
+
+fn frame_name(frame_type: u8, subtype: u8) -> &'static str {
+    match (frame_type, subtype) {
+        (0, 0) => "Association Request",
+        (0, 1) => "Association Response",
+        (0, 2) => "Reassociation Request",
+        (0, 3) => "Reassociation Response",
+        (0, 4) => "Probe Request",
+        (0, 5) => "Probe Response",
+        (0, 8) => "Beacon frame",
+        (0, 10) => "Disassociate",
+        (0, 11) => "Authentication",
+        (0, 12) => "Deauthentication",
+        (0, 13) => "Action",
+        (1, 8) => "802.11 Block Ack Req",
+        (1, 9) => "802.11 Block Ack",
+        (1, 11) => "Request-to-send",
+        (1, 12) => "Clear-to-send",
+        (1, 13) => "Acknowledgement",
+        (2, 0) => "Data",
+        (2, 4) => "Null function (No data)",
+        (2, 8) => "QoS Data",
+        (2, 12) => "QoS Null function (No data)",
+        _ => "Unrecognized frame",
+    }
+}
+
+/// Summarizes an 802.11 frame for the packet list. Unprotected data frames
+/// carrying IPv4 are shown by their IP contents.
+pub fn summarize(link_type: u32, frame: &[u8]) -> PacketSummary {
+    let Some(wlan) = WlanFrame::decode(link_type, frame) else {
+        return PacketSummary {
+            source: String::new(),
+            destination: String::new(),
+            protocol: "Malformed".to_string(),
+            length: frame.len(),
+            info: "Invalid 802.11 frame".to_string(),
+            flow: None,
+        };
+    };
+    let mut summary = PacketSummary {
+        source: wlan.addr2.to_string(),
+        destination: wlan.addr1.to_string(),
+        protocol: "802.11".to_string(),
+        length: frame.len(),
+        info: frame_name(wlan.frame_type, wlan.subtype).to_string(),
+        flow: None,
+    };
+    if let Some(ssid) = wlan.ssid() {
+        summary.info += &format!(", SSID={}", String::from_utf8_lossy(&ssid));
+    }
+    if wlan.eapol().is_some() {
+        summary.protocol = "EAPOL".to_string();
+    }
+    if wlan.frame_type == 2
+        && !wlan.protected
+        && let Some(payload) = wlan.body.strip_prefix(IPV4_SNAP.as_slice())
+        && let Ok(ipv4_packet) = IPv4Packet::try_from(payload)
+    {
+        summary::summarize_ipv4(&ipv4_packet, &mut summary);
+    }
+    summary
+}
+
+/// Radiotap Info
+/// The radio measurements of a radiotap header used for site surveys.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RadiotapInfo {
+    pub signal_dbm: Option<i8>,
+    pub frequency: Option<u16>,
+}
+
+impl TryFrom<&[u8]> for RadiotapInfo {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 8 {
+            return Err(KcpdumpError::malformed(
+                0,
+                "Data too short for radiotap header",
+            ));
+        }
+        let length = u16::from_le_bytes([data[2], data[3]]) as usize;
+        let header = data.get(..length).ok_or(KcpdumpError::malformed(
+            data.len(),
+            "Radiotap header truncated",
+        ))?;
+        let present = u32::from_le_bytes([data[4], data[5], data[6], data[7]]);
+        // Skip extended presence bitmaps.
+        let mut offset = 8;
+        let mut word = present;
+        while word & 0x8000_0000 != 0 {
+            let bytes = header
+                .get(offset..offset + 4)
+                .ok_or(KcpdumpError::malformed(offset, "Radiotap header truncated"))?;
+            word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            offset += 4;
+        }
+
+        // (alignment, size) of the fields up to the antenna signal.
+        const FIELDS: [(usize, usize); 6] = [(8, 8), (1, 1), (1, 1), (2, 4), (1, 2), (1, 1)];
+        let mut info = RadiotapInfo::default();
+        for (bit, (align, size)) in FIELDS.iter().enumerate() {
+            if present & (1 << bit) == 0 {
+                continue;
+            }
+            offset = offset.next_multiple_of(*align);
+            let Some(field) = header.get(offset..offset + size) else {
+                break;
+            };
+            match bit {
+                3 => info.frequency = Some(u16::from_le_bytes([field[0], field[1]])),
+                5 => info.signal_dbm = Some(field[0] as i8),
+                _ => {}
+            }
+            offset += size;
+        }
+        Ok(info)
+    }
+}
+
+/// Rsn Info
+/// The cipher and key management suites of an RSN element.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RsnInfo {
+    pub group_cipher: u8,
+    pub pairwise_ciphers: Vec<u8>,
+    pub akm_suites: Vec<u8>,
+}
+
+impl TryFrom<&[u8]> for RsnInfo {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 8 {
+            return Err(KcpdumpError::malformed(0, "RSN element too short"));
+        }
+        let suites = |offset: usize| -> Result<(Vec<u8>, usize), Self::Error> {
+            let count = data
+                .get(offset..offset + 2)
+                .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as usize)
+                .ok_or(KcpdumpError::malformed(offset, "RSN element truncated"))?;
+            let end = offset + 2 + count * 4;
+            let list = data
+                .get(offset + 2..end)
+                .ok_or(KcpdumpError::malformed(offset + 2, "RSN element truncated"))?;
+            Ok((list.chunks(4).map(|suite| suite[3]).collect(), end))
+        };
+        let (pairwise_ciphers, offset) = suites(6)?;
+        let (akm_suites, _) = suites(offset)?;
+        Ok(RsnInfo {
+            group_cipher: data[5],
+            pairwise_ciphers,
+            akm_suites,
+        })
+    }
+}
+
+impl RsnInfo {
+    /// Describes the security of a network, e.g. `WPA2-PSK CCMP`.
+    pub fn describe(&self) -> String {
+        let akm: Vec<_> = self
+            .akm_suites
+            .iter()
+            .map(|suite| match suite {
+                1 => "WPA2-802.1X".to_string(),
+                2 => "WPA2-PSK".to_string(),
+                5 => "WPA2-802.1X-SHA256".to_string(),
+                6 => "WPA2-PSK-SHA256".to_string(),
+                8 => "WPA3-SAE".to_string(),
+                18 => "OWE".to_string(),
+                other => format!("AKM {}", other),
+            })
+            .collect();
+        let ciphers: Vec<_> = self
+            .pairwise_ciphers
+            .iter()
+            .map(|suite| match suite {
+                1 => "WEP-40".to_string(),
+                2 => "TKIP".to_string(),
+                4 => "CCMP".to_string(),
+                5 => "WEP-104".to_string(),
+                8 => "GCMP".to_string(),
+                9 => "GCMP-256".to_string(),
+                10 => "CCMP-256".to_string(),
+                other => format!("Cipher {}", other),
+            })
+            .collect();
+        format!("{} {}", akm.join("/"), ciphers.join("/"))
+    }
+}
+
+/// EAPOL Key
+/// An EAPOL-Key frame of a WPA/WPA2 4-way handshake.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EapolKey {
+    pub key_info: u16,
+    pub replay_counter: u64,
+    pub nonce: [u8; 32],
+    pub mic: [u8; MIC_LENGTH],
+    /// The whole EAPOL frame, as used to verify the MIC.
+    pub frame: Vec<u8>,
+}
+
+impl TryFrom<&[u8]> for EapolKey {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 99 {
+            return Err(KcpdumpError::malformed(
+                0,
+                "Data too short for EAPOL-Key frame",
+            ));
+        }
+        if data[1] != 3 {
+            return Err(KcpdumpError::malformed(0, "Not an EAPOL-Key frame"));
+        }
+        let mut nonce = [0u8; 32];
+        nonce.copy_from_slice(&data[17..49]);
+        let mut mic = [0u8; MIC_LENGTH];
+        mic.copy_from_slice(&data[MIC_OFFSET..MIC_OFFSET + MIC_LENGTH]);
+        Ok(EapolKey {
+            key_info: u16::from_be_bytes([data[5], data[6]]),
+            replay_counter: u64::from_be_bytes(data[9..17].try_into().unwrap_or_default()),
+            nonce,
+            mic,
+            frame: data.to_vec(),
+        })
+    }
+}
+
+impl EapolKey {
+    /// Position in the 4-way handshake, 1 to 4, or `None` for group key messages.
+    pub fn message(&self) -> Option<u8> {
+        let info = self.key_info;
+        if info & KEY_INFO_PAIRWISE == 0 {
+            return None;
+        }
+        let ack = info & KEY_INFO_ACK != 0;
+        let mic = info & KEY_INFO_MIC != 0;
+        match (ack, mic) {
+            (true, false) => Some(1),
+            (true, true) if info & KEY_INFO_INSTALL != 0 => Some(3),
+            (false, true) if info & KEY_INFO_SECURE == 0 || self.nonce != [0; 32] => Some(2),
+            (false, true) => Some(4),
+            _ => None,
+        }
+    }
+}
+
+/// WPA Handshake
+/// EAPOL-Key messages exchanged between one access point and one client.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WpaHandshake {
+    pub ssid: Option<String>,
+    pub bssid: String,
+    pub client: String,
+    /// Frame number of the first message.
+    pub first_number: usize,
+    /// Handshake messages seen, e.g. `[1, 2, 3, 4]`.
+    pub messages: Vec<u8>,
+    /// All four messages were seen with matching replay counters.
+    pub complete: bool,
+    /// A hashcat line can be produced: M2 plus the ANonce from M1 or M3 and the SSID.
+    pub crackable: bool,
+}
+
+#[derive(Default)]
+struct HandshakeState {
+    first_number: usize,
+    messages: [Option<EapolKey>; 4],
+}
+
+impl HandshakeState {
+    fn replay(&self, message: usize) -> Option<u64> {
+        self.messages[message - 1]
+            .as_ref()
+            .map(|key| key.replay_counter)
+    }
+
+    fn complete(&self) -> bool {
+        self.messages.iter().all(Option::is_some)
+            && self.replay(1) == self.replay(2)
+            && self.replay(3) == self.replay(4)
+    }
+
+    /// The message pair used for cracking: ANonce source and the hashcat pair code.
+    fn challenge(&self) -> Option<(&EapolKey, &EapolKey, u8)> {
+        let m2 = self.messages[1].as_ref()?;
+        match (&self.messages[0], &self.messages[2]) {
+            (Some(m1), _) if m1.replay_counter == m2.replay_counter => Some((m1, m2, 0x00)),
+            (_, Some(m3)) if m3.replay_counter == m2.replay_counter + 1 => Some((m3, m2, 0x02)),
+            (Some(m1), _) => Some((m1, m2, 0x80)),
+            (_, Some(m3)) => Some((m3, m2, 0x82)),
+            _ => None,
+        }
+    }
+}
+
+/// Handshake Tracker
+/// Collects SSIDs from beacons and EAPOL-Key messages from data frames fed in capture order.
+#[derive(Default)]
+pub struct HandshakeTracker {
+    ssids: HashMap<[u8; 6], Vec<u8>>,
+    handshakes: Vec<([u8; 6], [u8; 6], HandshakeState)>,
+    /// Latest handshake per access point and client.
+    current: HashMap<([u8; 6], [u8; 6]), usize>,
+}
+
+impl HandshakeTracker {
+    pub fn push(&mut self, number: usize, frame: &WlanFrame) {
+        if let Some(ssid) = frame.ssid() {
+            self.ssids.insert(frame.addr3.0, ssid);
+            return;
+        }
+        let (Some(bssid), Some(client), Some(eapol)) =
+            (frame.bssid(), frame.station(), frame.eapol())
+        else {
+            return;
+        };
+        let Ok(key) = EapolKey::try_from(eapol) else {
+            return;
+        };
+        let Some(message) = key.message() else {
+            return;
+        };
+        let pair = (bssid.0, client.0);
+        let index = match self.current.get(&pair) {
+            // A new M1 after later messages starts another handshake.
+            Some(&index) if message != 1 || self.handshakes[index].2.messages[1].is_none() => index,
+            _ => {
+                self.handshakes.push((
+                    bssid.0,
+                    client.0,
+                    HandshakeState {
+                        first_number: number,
+                        ..Default::default()
+                    },
+                ));
+                self.current.insert(pair, self.handshakes.len() - 1);
+                self.handshakes.len() - 1
+            }
+        };
+        let slot = &mut self.handshakes[index].2.messages[message as usize - 1];
+        // Keep the first copy of retransmitted messages.
+        if slot.is_none() {
+            *slot = Some(key);
+        }
+    }
+
+    pub fn handshakes(&self) -> Vec<WpaHandshake> {
+        self.handshakes
+            .iter()
+            .map(|(bssid, client, state)| {
+                let ssid = self.ssids.get(bssid);
+                WpaHandshake {
+                    ssid: ssid.map(|ssid| String::from_utf8_lossy(ssid).into_owned()),
+                    bssid: MacAddress(*bssid).to_string(),
+                    client: MacAddress(*client).to_string(),
+                    first_number: state.first_number,
+                    messages: (1..=4)
+                        .filter(|message| state.messages[*message as usize - 1].is_some())
+                        .collect(),
+                    complete: state.complete(),
+                    crackable: ssid.is_some() && state.challenge().is_some(),
+                }
+            })
+            .collect()
+    }
+
+    /// Formats the crackable handshakes as hashcat mode 22000 `WPA*02` lines.
+    pub fn hashcat_lines(&self, only_complete: bool) -> Vec<String> {
+        self.handshakes
+            .iter()
+            .filter(|(_, _, state)| !only_complete || state.complete())
+            .filter_map(|(bssid, client, state)| {
+                let ssid = self.ssids.get(bssid)?;
+                let (anonce, m2, pair) = state.challenge()?;
+                let mut eapol = m2.frame.clone();
+                eapol[MIC_OFFSET..MIC_OFFSET + MIC_LENGTH].fill(0);
+                Some(format!(
+                    "WPA*02*{}*{}*{}*{}*{}*{}*{:02x}",
+                    hex(&m2.mic),
+                    hex(bssid),
+                    hex(client),
+                    hex(ssid),
+                    hex(&anonce.nonce),
+                    hex(&eapol),
+                    pair
+                ))
+            })
+            .collect()
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut text, byte| {
+        let _ = write!(text, "{:02x}", byte);
+        text
+    })
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Runs the handshake tracker over an 802.11 capture.
+/// Captures with other link types yield no handshakes.
+pub fn track_handshakes(capture: &LoadedCapture) -> Result<HandshakeTracker, KcpdumpError> {
+    let mut tracker = HandshakeTracker::default();
+    for (index, packet) in capture.packets().enumerate() {
+        let packet = packet?;
+        if let Some(frame) = WlanFrame::decode(capture.header.network, &packet.data) {
+            tracker.push(index + 1, &frame);
+        }
+    }
+    Ok(tracker)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AP: [u8; 6] = [0x02, 0, 0, 0, 0, 0xaa];
+    const CLIENT: [u8; 6] = [0x02, 0, 0, 0, 0, 0xcc];
+
+    fn beacon(ssid: &[u8]) -> Vec<u8> {
+        let mut data = vec![0x80, 0x00, 0, 0];
+        data.extend_from_slice(&[0xff; 6]);
+        data.extend_from_slice(&AP);
+        data.extend_from_slice(&AP);
+        data.extend_from_slice(&[0, 0]);
+        data.extend_from_slice(&[0; 12]);
+        data.extend_from_slice(&[0, ssid.len() as u8]);
+        data.extend_from_slice(ssid);
+        data
+    }
+
+    fn eapol_frame(from_ap: bool, key_info: u16, replay: u64, nonce: u8, mic: u8) -> Vec<u8> {
+        // QoS data frame so the header length handling is exercised.
+        let mut data = vec![0x88, if from_ap { 0x02 } else { 0x01 }, 0, 0];
+        let (addr1, addr2) = if from_ap { (CLIENT, AP) } else { (AP, CLIENT) };
+        data.extend_from_slice(&addr1);
+        data.extend_from_slice(&addr2);
+        data.extend_from_slice(&AP);
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        data.extend_from_slice(&EAPOL_SNAP);
+        let mut eapol = vec![0x02, 0x03, 0x00, 95, 0x02];
+        eapol.extend_from_slice(&key_info.to_be_bytes());
+        eapol.extend_from_slice(&[0x00, 0x10]);
+        eapol.extend_from_slice(&replay.to_be_bytes());
+        eapol.extend_from_slice(&[nonce; 32]);
+        eapol.extend_from_slice(&[0; 32]);
+        eapol.extend_from_slice(&[mic; MIC_LENGTH]);
+        eapol.extend_from_slice(&[0, 0]);
+        data.extend_from_slice(&eapol);
+        data
+    }
+
+    #[test]
+    fn test_summarize() {
+        let summary = summarize(LINKTYPE_IEEE802_11, &beacon(b"HomeNet"));
+        assert_eq!(summary.protocol, "802.11");
+        assert_eq!(summary.source, "02:00:00:00:00:AA");
+        assert_eq!(summary.info, "Beacon frame, SSID=HomeNet");
+
+        // Data frame from the client carrying an ICMP echo request.
+        let mut data = vec![0x08, 0x01, 0, 0];
+        data.extend_from_slice(&AP);
+        data.extend_from_slice(&CLIENT);
+        data.extend_from_slice(&AP);
+        data.extend_from_slice(&[0, 0]);
+        data.extend_from_slice(&IPV4_SNAP);
+        data.extend_from_slice(&[
+            0x45, 0x00, 0x00, 0x1c, 0x00, 0x01, 0x00, 0x00, 0x40, 0x01, 0x00, 0x00, 0xc0, 0xa8,
+            0x01, 0x0a, 0xc0, 0xa8, 0x01, 0x01, 0x08, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x02,
+        ]);
+        let summary = summarize(LINKTYPE_IEEE802_11, &data);
+        assert_eq!(summary.protocol, "ICMP");
+        assert_eq!(summary.source, "192.168.1.10");
+        assert_eq!(
+            summarize(LINKTYPE_IEEE802_11, &data[..10]).protocol,
+            "Malformed"
+        );
+    }
+
+    fn handshake_frames() -> Vec<Vec<u8>> {
+        vec![
+            beacon(b"HomeNet"),
+            eapol_frame(true, 0x008a, 1, 0x11, 0),
+            eapol_frame(false, 0x010a, 1, 0x22, 0x33),
+            eapol_frame(true, 0x13ca, 2, 0x11, 0x44),
+            eapol_frame(false, 0x030a, 2, 0x00, 0x55),
+        ]
+    }
+
+    fn track(frames: &[Vec<u8>]) -> HandshakeTracker {
+        let mut tracker = HandshakeTracker::default();
+        for (index, data) in frames.iter().enumerate() {
+            let frame = WlanFrame::decode(LINKTYPE_IEEE802_11, data).unwrap();
+            tracker.push(index + 1, &frame);
+        }
+        tracker
+    }
+
+    #[test]
+    fn test_complete_handshake() {
+        let tracker = track(&handshake_frames());
+        let handshakes = tracker.handshakes();
+        assert_eq!(handshakes.len(), 1);
+        let handshake = &handshakes[0];
+        assert_eq!(handshake.ssid.as_deref(), Some("HomeNet"));
+        assert_eq!(handshake.bssid, "02:00:00:00:00:AA");
+        assert_eq!(handshake.client, "02:00:00:00:00:CC");
+        assert_eq!(handshake.first_number, 2);
+        assert_eq!(handshake.messages, vec![1, 2, 3, 4]);
+        assert!(handshake.complete);
+        assert!(handshake.crackable);
+    }
+
+    #[test]
+    fn test_hashcat_line() {
+        let tracker = track(&handshake_frames());
+        let lines = tracker.hashcat_lines(true);
+        assert_eq!(lines.len(), 1);
+        let fields: Vec<_> = lines[0].split('*').collect();
+        assert_eq!(fields[0], "WPA");
+        assert_eq!(fields[1], "02");
+        assert_eq!(fields[2], "33".repeat(16));
+        assert_eq!(fields[3], "0200000000aa");
+        assert_eq!(fields[4], "0200000000cc");
+        assert_eq!(fields[5], hex(b"HomeNet"));
+        assert_eq!(fields[6], "11".repeat(32));
+        assert!(fields[7].starts_with("0203005f02010a"));
+        assert!(!fields[7].contains(&"33".repeat(16)));
+        assert_eq!(fields[8], "00");
+    }
+
+    #[test]
+    fn test_partial_handshake_without_ssid() {
+        let frames = handshake_frames();
+        let tracker = track(&frames[1..3]);
+        let handshakes = tracker.handshakes();
+        assert_eq!(handshakes[0].messages, vec![1, 2]);
+        assert!(!handshakes[0].complete);
+        assert!(!handshakes[0].crackable);
+        assert!(tracker.hashcat_lines(false).is_empty());
+    }
+
+    #[test]
+    fn test_radiotap_and_other_link_types() {
+        let mut data = vec![0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00];
+        data.extend_from_slice(&beacon(b"x"));
+        let frame = WlanFrame::decode(LINKTYPE_IEEE802_11_RADIOTAP, &data).unwrap();
+        assert_eq!(frame.ssid(), Some(b"x".to_vec()));
+        assert!(WlanFrame::decode(1, &data).is_none());
+    }
+}
@@ -0,0 +1,389 @@
+use std::collections::BTreeMap;
+
+use crate::error::KcpdumpError;
+use crate::packet::MacAddress;
+use crate::session::LoadedCapture;
+use crate::wlan::{LINKTYPE_IEEE802_11_RADIOTAP, RadiotapInfo, RsnInfo, WlanFrame};
+
+const ELEMENT_SSID: u8 = 0;
+const ELEMENT_RATES: u8 = 1;
+const ELEMENT_DS_PARAMETER: u8 = 3;
+const ELEMENT_RSN: u8 = 48;
+const ELEMENT_EXTENDED_RATES: u8 = 50;
+const ELEMENT_VENDOR: u8 = 221;
+
+/// Vendor element of Microsoft's WPA (version 1) information.
+const WPA_OUI_TYPE: [u8; 4] = [0x00, 0x50, 0xf2, 0x01];
+
+const CAPABILITY_PRIVACY: u16 = 0x0010;
+
+/// Signal Sample
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SignalSample {
+    /// Seconds since the epoch.
+    pub time: f64,
+    pub dbm: i8,
+}
+
+/// Access Point
+/// A BSS seen in beacons or probe responses.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessPoint {
+    pub bssid: String,
+    pub ssid: Option<String>,
+    pub channel: Option<u8>,
+    /// Supported rates in Mbit/s.
+    pub rates: Vec<f32>,
+    /// e.g. `WPA2-PSK CCMP`, `WPA`, `WEP` or `Open`.
+    pub security: String,
+    pub beacons: usize,
+    pub first_seen: f64,
+    pub last_seen: f64,
+    /// Signal strength of the frames it transmitted, from radiotap headers.
+    pub signal: Vec<SignalSample>,
+    /// Clients associated with it.
+    pub clients: Vec<String>,
+}
+
+/// Wlan Client
+/// A station seen probing, associating or exchanging data.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WlanClient {
+    pub address: String,
+    /// Access point the client is associated with, last seen.
+    pub bssid: Option<String>,
+    /// SSIDs the client asked for in directed probe requests.
+    pub probed_ssids: Vec<String>,
+    pub frames: usize,
+    pub first_seen: f64,
+    pub last_seen: f64,
+    pub signal: Vec<SignalSample>,
+}
+
+/// Wlan Inventory
+/// Access points and clients of a monitor-mode capture.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WlanInventory {
+    pub access_points: Vec<AccessPoint>,
+    pub clients: Vec<WlanClient>,
+}
+
+fn is_unicast(address: MacAddress) -> bool {
+    address.0[0] & 0x01 == 0
+}
+
+fn security(frame: &WlanFrame) -> String {
+    let elements = frame.elements();
+    if let Some(rsn) = elements
+        .iter()
+        .find(|(id, _)| *id == ELEMENT_RSN)
+        .and_then(|(_, value)| RsnInfo::try_from(*value).ok())
+    {
+        return rsn.describe();
+    }
+    if elements
+        .iter()
+        .any(|(id, value)| *id == ELEMENT_VENDOR && value.starts_with(&WPA_OUI_TYPE))
+    {
+        return "WPA".to_string();
+    }
+    match frame.capabilities() {
+        Some(capabilities) if capabilities & CAPABILITY_PRIVACY != 0 => "WEP".to_string(),
+        _ => "Open".to_string(),
+    }
+}
+
+impl AccessPoint {
+    fn new(bssid: MacAddress, time: f64) -> Self {
+        AccessPoint {
+            bssid: bssid.to_string(),
+            ssid: None,
+            channel: None,
+            rates: Vec::new(),
+            security: "Open".to_string(),
+            beacons: 0,
+            first_seen: time,
+            last_seen: time,
+            signal: Vec::new(),
+            clients: Vec::new(),
+        }
+    }
+
+    /// Takes the network description from a beacon or probe response.
+    fn update(&mut self, frame: &WlanFrame) {
+        let mut rates = Vec::new();
+        for (id, value) in frame.elements() {
+            match id {
+                ELEMENT_SSID if !value.is_empty() && value.iter().any(|&b| b != 0) => {
+                    self.ssid = Some(String::from_utf8_lossy(value).into_owned());
+                }
+                ELEMENT_DS_PARAMETER => self.channel = value.first().copied(),
+                ELEMENT_RATES | ELEMENT_EXTENDED_RATES => {
+                    rates.extend(value.iter().map(|rate| f32::from(rate & 0x7f) / 2.0));
+                }
+                _ => {}
+            }
+        }
+        if !rates.is_empty() {
+            self.rates = rates;
+        }
+        self.security = security(frame);
+    }
+}
+
+impl WlanClient {
+    fn new(address: MacAddress, time: f64) -> Self {
+        WlanClient {
+            address: address.to_string(),
+            bssid: None,
+            probed_ssids: Vec::new(),
+            frames: 0,
+            first_seen: time,
+            last_seen: time,
+            signal: Vec::new(),
+        }
+    }
+}
+
+/// Inventory Builder
+/// Collects access points and clients from 802.11 frames fed in capture order.
+#[derive(Default)]
+pub struct InventoryBuilder {
+    access_points: BTreeMap<[u8; 6], AccessPoint>,
+    clients: BTreeMap<[u8; 6], WlanClient>,
+}
+
+impl InventoryBuilder {
+    fn access_point(&mut self, bssid: MacAddress, time: f64) -> &mut AccessPoint {
+        let access_point = self
+            .access_points
+            .entry(bssid.0)
+            .or_insert_with(|| AccessPoint::new(bssid, time));
+        access_point.last_seen = time;
+        access_point
+    }
+
+    fn client(&mut self, address: MacAddress, time: f64) -> &mut WlanClient {
+        let client = self
+            .clients
+            .entry(address.0)
+            .or_insert_with(|| WlanClient::new(address, time));
+        client.last_seen = time;
+        client.frames += 1;
+        client
+    }
+
+    fn associate(&mut self, client: MacAddress, bssid: MacAddress, time: f64) {
+        if is_unicast(client) && client != bssid && !self.access_points.contains_key(&client.0) {
+            self.client(client, time).bssid = Some(bssid.to_string());
+        }
+    }
+
+    pub fn push(&mut self, time: f64, frame: &WlanFrame, signal_dbm: Option<i8>) {
+        match (frame.frame_type, frame.subtype) {
+            // Beacon and probe response.
+            (0, 8) | (0, 5) => {
+                let access_point = self.access_point(frame.addr3, time);
+                access_point.update(frame);
+                if frame.subtype == 8 {
+                    access_point.beacons += 1;
+                }
+            }
+            // Probe request.
+            (0, 4) if is_unicast(frame.addr2) => {
+                let ssid = frame
+                    .elements()
+                    .into_iter()
+                    .find(|(id, value)| *id == ELEMENT_SSID && !value.is_empty())
+                    .map(|(_, value)| String::from_utf8_lossy(value).into_owned());
+                let client = self.client(frame.addr2, time);
+                if let Some(ssid) = ssid
+                    && !client.probed_ssids.contains(&ssid)
+                {
+                    client.probed_ssids.push(ssid);
+                }
+            }
+            // Association and reassociation requests.
+            (0, 0) | (0, 2) => self.associate(frame.addr2, frame.addr1, time),
+            // Data frames between a station and an access point.
+            (2, _) => {
+                if let (Some(station), Some(bssid)) = (frame.station(), frame.bssid()) {
+                    self.associate(station, bssid, time);
+                }
+            }
+            _ => {}
+        }
+
+        if let Some(dbm) = signal_dbm {
+            let sample = SignalSample { time, dbm };
+            if let Some(access_point) = self.access_points.get_mut(&frame.addr2.0) {
+                access_point.signal.push(sample);
+            } else if let Some(client) = self.clients.get_mut(&frame.addr2.0) {
+                client.signal.push(sample);
+            }
+        }
+    }
+
+    pub fn finish(mut self) -> WlanInventory {
+        for client in self.clients.values() {
+            let Some(bssid) = &client.bssid else {
+                continue;
+            };
+            if let Some(access_point) = self
+                .access_points
+                .values_mut()
+                .find(|access_point| &access_point.bssid == bssid)
+            {
+                access_point.clients.push(client.address.clone());
+            }
+        }
+        WlanInventory {
+            access_points: self.access_points.into_values().collect(),
+            clients: self.clients.into_values().collect(),
+        }
+    }
+}
+
+/// Builds the access point and client inventory of an 802.11 capture.
+/// Signal strength is only available in radiotap captures.
+pub fn wlan_inventory(capture: &LoadedCapture) -> Result<WlanInventory, KcpdumpError> {
+    let mut builder = InventoryBuilder::default();
+    for packet in capture.packets() {
+        let packet = packet?;
+        let Some(frame) = WlanFrame::decode(capture.header.network, &packet.data) else {
+            continue;
+        };
+        let signal_dbm = if capture.header.network == LINKTYPE_IEEE802_11_RADIOTAP {
+            RadiotapInfo::try_from(packet.data.as_slice())
+                .ok()
+                .and_then(|info| info.signal_dbm)
+        } else {
+            None
+        };
+        let time = packet.header.timestamp.as_secs_f64();
+        builder.push(time, &frame, signal_dbm);
+    }
+    Ok(builder.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cap::{PcapPacket, PcapPacketHeader};
+    use crate::timefmt::Timestamp;
+
+    const AP: [u8; 6] = [0x02, 0, 0, 0, 0, 0xaa];
+    const CLIENT: [u8; 6] = [0x02, 0, 0, 0, 0, 0xcc];
+
+    fn management(subtype: u8, addr1: [u8; 6], addr2: [u8; 6], body: &[u8]) -> Vec<u8> {
+        let mut data = vec![subtype << 4, 0x00, 0, 0];
+        data.extend_from_slice(&addr1);
+        data.extend_from_slice(&addr2);
+        data.extend_from_slice(&AP);
+        data.extend_from_slice(&[0, 0]);
+        data.extend_from_slice(body);
+        data
+    }
+
+    fn beacon() -> Vec<u8> {
+        let mut body = vec![0; 8];
+        body.extend_from_slice(&[0x64, 0x00, 0x11, 0x04]);
+        body.extend_from_slice(&[ELEMENT_SSID, 7]);
+        body.extend_from_slice(b"HomeNet");
+        body.extend_from_slice(&[ELEMENT_RATES, 4, 0x82, 0x84, 0x8b, 0x96]);
+        body.extend_from_slice(&[ELEMENT_DS_PARAMETER, 1, 6]);
+        body.extend_from_slice(&[
+            ELEMENT_RSN,
+            20,
+            0x01,
+            0x00,
+            0x00,
+            0x0f,
+            0xac,
+            0x04,
+            0x01,
+            0x00,
+            0x00,
+            0x0f,
+            0xac,
+            0x04,
+            0x01,
+            0x00,
+            0x00,
+            0x0f,
+            0xac,
+            0x02,
+            0x00,
+            0x00,
+        ]);
+        management(8, [0xff; 6], AP, &body)
+    }
+
+    /// Radiotap header with flags and antenna signal.
+    fn radiotap(dbm: i8, frame: Vec<u8>) -> Vec<u8> {
+        let mut data = vec![
+            0x00, 0x00, 0x0a, 0x00, 0x22, 0x00, 0x00, 0x00, 0x00, dbm as u8,
+        ];
+        data.extend(frame);
+        data
+    }
+
+    fn packet(ts_sec: u32, data: Vec<u8>) -> PcapPacket {
+        PcapPacket {
+            header: PcapPacketHeader {
+                timestamp: Timestamp::new(ts_sec, 0),
+                incl_len: data.len() as u32,
+                orig_len: data.len() as u32,
+                link_type: None,
+            },
+            data,
+        }
+    }
+
+    #[test]
+    fn test_radiotap() {
+        let data = radiotap(-42, beacon());
+        let info = RadiotapInfo::try_from(data.as_slice()).unwrap();
+        assert_eq!(info.signal_dbm, Some(-42));
+        assert_eq!(info.frequency, None);
+    }
+
+    #[test]
+    fn test_inventory() {
+        let mut probe = vec![ELEMENT_SSID, 7];
+        probe.extend_from_slice(b"HomeNet");
+        let mut data = vec![0x08, 0x01, 0, 0];
+        data.extend_from_slice(&AP);
+        data.extend_from_slice(&CLIENT);
+        data.extend_from_slice(&AP);
+        data.extend_from_slice(&[0, 0]);
+        let capture = LoadedCapture::from_packets(1, "survey.pcap", LINKTYPE_IEEE802_11_RADIOTAP, &[
+                packet(10, radiotap(-40, beacon())),
+                packet(11, radiotap(-60, management(4, [0xff; 6], CLIENT, &probe))),
+                packet(12, radiotap(-45, beacon())),
+                packet(13, radiotap(-58, data)),
+            ]);
+        let inventory = wlan_inventory(&capture).unwrap();
+        assert_eq!(inventory.access_points.len(), 1);
+        let access_point = &inventory.access_points[0];
+        assert_eq!(access_point.ssid.as_deref(), Some("HomeNet"));
+        assert_eq!(access_point.channel, Some(6));
+        assert_eq!(access_point.rates, [1.0, 2.0, 5.5, 11.0]);
+        assert_eq!(access_point.security, "WPA2-PSK CCMP");
+        assert_eq!(access_point.beacons, 2);
+        assert_eq!(access_point.signal.len(), 2);
+        assert_eq!(access_point.signal[1].dbm, -45);
+        assert_eq!(access_point.clients, ["02:00:00:00:00:CC"]);
+
+        let client = &inventory.clients[0];
+        assert_eq!(client.probed_ssids, ["HomeNet"]);
+        assert_eq!(client.bssid.as_deref(), Some("02:00:00:00:00:AA"));
+        assert_eq!(client.frames, 2);
+        assert_eq!(client.first_seen, 11.0);
+        assert_eq!(client.signal.len(), 2);
+    }
+}
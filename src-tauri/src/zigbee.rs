@@ -0,0 +1,537 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc;
+
+use aes::Aes128;
+use aes::cipher::{BlockEncrypt, KeyInit, generic_array::GenericArray};
+
+use crate::error::KcpdumpError;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::ieee802154::{self, LINKTYPE_IEEE802_15_4_NOFCS, LINKTYPE_IEEE802_15_4_WITHFCS};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::packetlist;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::session::LoadedCapture;
+
+/// Zigbee PRO network layer protocol version.
+const NWK_VERSION: u8 = 2;
+
+/// Security level used on air; the level bits are sent as zero and must be
+/// restored before verifying the MIC.
+const SECURITY_LEVEL_ENC_MIC_32: u8 = 5;
+const MIC_LENGTH: usize = 4;
+
+/// Security Header
+/// The auxiliary header in front of a secured NWK payload.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SecurityHeader {
+    pub control: u8,
+    pub frame_counter: u32,
+    /// Extended source address, present when the extended nonce bit is set.
+    pub source: Option<u64>,
+    /// Sequence number of the network key used.
+    pub key_sequence: Option<u8>,
+}
+
+impl SecurityHeader {
+    fn parse(data: &[u8]) -> Result<(Self, usize), KcpdumpError> {
+        let truncated = "Zigbee security header truncated";
+        let control = *data.first().ok_or(KcpdumpError::malformed(0, truncated))?;
+        let counter = data
+            .get(1..5)
+            .ok_or(KcpdumpError::malformed(1, truncated))?;
+        let mut offset = 5;
+        let source = if control & 0x20 != 0 {
+            let field = data
+                .get(offset..offset + 8)
+                .ok_or(KcpdumpError::malformed(offset, truncated))?;
+            offset += 8;
+            Some(u64::from_le_bytes(field.try_into().unwrap_or_default()))
+        } else {
+            None
+        };
+        let key_sequence = if (control >> 3) & 0x03 == 1 {
+            let field = *data
+                .get(offset)
+                .ok_or(KcpdumpError::malformed(offset, truncated))?;
+            offset += 1;
+            Some(field)
+        } else {
+            None
+        };
+        Ok((
+            SecurityHeader {
+                control,
+                frame_counter: u32::from_le_bytes([counter[0], counter[1], counter[2], counter[3]]),
+                source,
+                key_sequence,
+            },
+            offset,
+        ))
+    }
+}
+
+/// NWK Frame
+/// A Zigbee network layer frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NwkFrame {
+    /// 0 data, 1 command.
+    pub frame_type: u8,
+    pub dest: u16,
+    pub source: u16,
+    pub radius: u8,
+    pub sequence: u8,
+    pub dest_ieee: Option<u64>,
+    pub source_ieee: Option<u64>,
+    pub security: Option<SecurityHeader>,
+    /// Header bytes including the security header, authenticated by the MIC.
+    pub header: Vec<u8>,
+    /// Payload; still encrypted and followed by the MIC when secured.
+    pub payload: Vec<u8>,
+}
+
+impl TryFrom<&[u8]> for NwkFrame {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 8 {
+            return Err(KcpdumpError::malformed(
+                0,
+                "Data too short for Zigbee NWK header",
+            ));
+        }
+        let control = u16::from_le_bytes([data[0], data[1]]);
+        if (control >> 2) & 0x0f != u16::from(NWK_VERSION) {
+            return Err(KcpdumpError::malformed(
+                0,
+                "Unsupported Zigbee NWK protocol version",
+            ));
+        }
+        let truncated = "Zigbee NWK header truncated";
+        let mut offset = 8;
+        let mut ieee = |present: bool| -> Result<Option<u64>, Self::Error> {
+            if !present {
+                return Ok(None);
+            }
+            let field = data
+                .get(offset..offset + 8)
+                .ok_or(KcpdumpError::malformed(offset, truncated))?;
+            offset += 8;
+            Ok(Some(u64::from_le_bytes(
+                field.try_into().unwrap_or_default(),
+            )))
+        };
+        let dest_ieee = ieee(control & 0x0800 != 0)?;
+        let source_ieee = ieee(control & 0x1000 != 0)?;
+        if control & 0x0100 != 0 {
+            // Multicast control.
+            offset += 1;
+        }
+        if control & 0x0400 != 0 {
+            let relays = *data
+                .get(offset)
+                .ok_or(KcpdumpError::malformed(offset, truncated))?
+                as usize;
+            offset += 2 + 2 * relays;
+        }
+        if offset > data.len() {
+            return Err(KcpdumpError::malformed(data.len(), truncated));
+        }
+        let security = if control & 0x0200 != 0 {
+            let (header, length) = SecurityHeader::parse(&data[offset..])?;
+            offset += length;
+            Some(header)
+        } else {
+            None
+        };
+        Ok(NwkFrame {
+            frame_type: (control & 0x03) as u8,
+            dest: u16::from_le_bytes([data[2], data[3]]),
+            source: u16::from_le_bytes([data[4], data[5]]),
+            radius: data[6],
+            sequence: data[7],
+            dest_ieee,
+            source_ieee,
+            security,
+            header: data[..offset].to_vec(),
+            payload: data[offset..].to_vec(),
+        })
+    }
+}
+
+impl NwkFrame {
+    pub fn describe(&self) -> String {
+        let kind = if self.frame_type == 1 {
+            "Command"
+        } else {
+            "Data"
+        };
+        let mut info = format!(
+            "{}, Dst: 0x{:04x}, Src: 0x{:04x}",
+            kind, self.dest, self.source
+        );
+        if self.security.is_some() {
+            info += ", Secured";
+        }
+        info
+    }
+
+    /// Decrypts a secured payload with the network key. Returns `None` if the
+    /// frame is not secured, the nonce cannot be built or the MIC does not match.
+    pub fn decrypt(&self, key: &[u8; 16]) -> Option<Vec<u8>> {
+        let security = self.security.as_ref()?;
+        let source = security.source.or(self.source_ieee)?;
+        let control = (security.control & !0x07) | SECURITY_LEVEL_ENC_MIC_32;
+        let mut nonce = [0u8; 13];
+        nonce[..8].copy_from_slice(&source.to_le_bytes());
+        nonce[8..12].copy_from_slice(&security.frame_counter.to_le_bytes());
+        nonce[12] = control;
+
+        let mut header = self.header.clone();
+        let control_offset = header.len() - self.payload_security_length();
+        header[control_offset] = control;
+        let split = self.payload.len().checked_sub(MIC_LENGTH)?;
+        let (ciphertext, mic) = self.payload.split_at(split);
+        ccm_star_decrypt(key, &nonce, &header, ciphertext, mic)
+    }
+
+    fn payload_security_length(&self) -> usize {
+        self.security.as_ref().map_or(0, |security| {
+            5 + if security.source.is_some() { 8 } else { 0 }
+                + usize::from(security.key_sequence.is_some())
+        })
+    }
+}
+
+/// APS Frame
+/// A Zigbee application support sublayer frame.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ApsFrame {
+    /// 0 data, 1 command, 2 acknowledgement.
+    pub frame_type: u8,
+    /// 0 unicast, 2 broadcast, 3 group.
+    pub delivery_mode: u8,
+    pub secured: bool,
+    pub dest_endpoint: Option<u8>,
+    pub group: Option<u16>,
+    pub cluster: Option<u16>,
+    pub profile: Option<u16>,
+    pub source_endpoint: Option<u8>,
+    pub counter: u8,
+    #[serde(skip)]
+    pub payload: Vec<u8>,
+}
+
+impl TryFrom<&[u8]> for ApsFrame {
+    type Error = KcpdumpError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        let truncated = "Zigbee APS header truncated";
+        let control = *data.first().ok_or(KcpdumpError::malformed(0, truncated))?;
+        let frame_type = control & 0x03;
+        let delivery_mode = (control >> 2) & 0x03;
+        let mut offset = 1;
+        let mut take = |length: usize| -> Result<&[u8], Self::Error> {
+            let field = data
+                .get(offset..offset + length)
+                .ok_or(KcpdumpError::malformed(offset, truncated))?;
+            offset += length;
+            Ok(field)
+        };
+        let word = |field: &[u8]| u16::from_le_bytes([field[0], field[1]]);
+
+        let (mut dest_endpoint, mut group, mut cluster, mut profile, mut source_endpoint) =
+            (None, None, None, None, None);
+        // Data frames and acknowledgements of data frames carry addressing.
+        let addressed = frame_type == 0 || (frame_type == 2 && control & 0x10 == 0);
+        if addressed {
+            if delivery_mode == 3 {
+                group = Some(word(take(2)?));
+            } else {
+                dest_endpoint = Some(take(1)?[0]);
+            }
+            cluster = Some(word(take(2)?));
+            profile = Some(word(take(2)?));
+            source_endpoint = Some(take(1)?[0]);
+        }
+        let counter = take(1)?[0];
+        Ok(ApsFrame {
+            frame_type,
+            delivery_mode,
+            secured: control & 0x20 != 0,
+            dest_endpoint,
+            group,
+            cluster,
+            profile,
+            source_endpoint,
+            counter,
+            payload: data[offset..].to_vec(),
+        })
+    }
+}
+
+fn encrypt_block(cipher: &Aes128, block: &mut [u8; 16]) {
+    let mut buffer = GenericArray::from(*block);
+    cipher.encrypt_block(&mut buffer);
+    block.copy_from_slice(&buffer);
+}
+
+/// Counter mode keystream block `index` for a 13-byte nonce (L = 2).
+fn ctr_block(cipher: &Aes128, nonce: &[u8; 13], index: u16) -> [u8; 16] {
+    let mut block = [0u8; 16];
+    block[0] = 0x01;
+    block[1..14].copy_from_slice(nonce);
+    block[14..].copy_from_slice(&index.to_be_bytes());
+    encrypt_block(cipher, &mut block);
+    block
+}
+
+fn ctr_xor(cipher: &Aes128, nonce: &[u8; 13], data: &[u8]) -> Vec<u8> {
+    data.chunks(16)
+        .enumerate()
+        .flat_map(|(index, chunk)| {
+            let keystream = ctr_block(cipher, nonce, index as u16 + 1);
+            chunk
+                .iter()
+                .zip(keystream)
+                .map(|(byte, key)| byte ^ key)
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// CBC-MAC over the CCM* authentication blocks, truncated to the MIC length.
+fn cbc_mac(cipher: &Aes128, nonce: &[u8; 13], header: &[u8], message: &[u8]) -> [u8; MIC_LENGTH] {
+    let mut first = [0u8; 16];
+    first[0] = 0x40 | (((MIC_LENGTH as u8 - 2) / 2) << 3) | 0x01;
+    first[1..14].copy_from_slice(nonce);
+    first[14..].copy_from_slice(&(message.len() as u16).to_be_bytes());
+
+    let mut authenticated = (header.len() as u16).to_be_bytes().to_vec();
+    authenticated.extend_from_slice(header);
+    authenticated.resize(authenticated.len().div_ceil(16) * 16, 0);
+    let mut padded_message = message.to_vec();
+    padded_message.resize(message.len().div_ceil(16) * 16, 0);
+    authenticated.extend_from_slice(&padded_message);
+
+    let mut state = first;
+    encrypt_block(cipher, &mut state);
+    for block in authenticated.chunks(16) {
+        for (byte, input) in state.iter_mut().zip(block) {
+            *byte ^= input;
+        }
+        encrypt_block(cipher, &mut state);
+    }
+    let mut mic = [0u8; MIC_LENGTH];
+    mic.copy_from_slice(&state[..MIC_LENGTH]);
+    mic
+}
+
+fn ccm_star_decrypt(
+    key: &[u8; 16],
+    nonce: &[u8; 13],
+    header: &[u8],
+    ciphertext: &[u8],
+    encrypted_mic: &[u8],
+) -> Option<Vec<u8>> {
+    let cipher = Aes128::new(&GenericArray::from(*key));
+    let plaintext = ctr_xor(&cipher, nonce, ciphertext);
+    let first = ctr_block(&cipher, nonce, 0);
+    let mic: Vec<_> = encrypted_mic
+        .iter()
+        .zip(first)
+        .map(|(b, k)| b ^ k)
+        .collect();
+    (cbc_mac(&cipher, nonce, header, &plaintext)[..] == mic[..]).then_some(plaintext)
+}
+
+/// Parses a network key given as 32 hex digits, optionally separated by colons or spaces.
+pub fn parse_network_key(text: &str) -> Result<[u8; 16], KcpdumpError> {
+    let digits: String = text
+        .chars()
+        .filter(|c| !matches!(c, ':' | ' ' | '-'))
+        .collect();
+    if digits.len() != 32 {
+        return Err(KcpdumpError::InvalidInput(
+            "Network key must be 16 bytes of hex".to_string(),
+        ));
+    }
+    let mut key = [0u8; 16];
+    for (index, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&digits[index * 2..index * 2 + 2], 16).map_err(|_| {
+            KcpdumpError::InvalidInput("Network key must be 16 bytes of hex".to_string())
+        })?;
+    }
+    Ok(key)
+}
+
+/// Zigbee Frame Row
+/// A Zigbee NWK frame with its APS header, decrypted when a network key is known.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ZigbeeFrameRow {
+    pub capture_id: u32,
+    pub number: usize,
+    /// Seconds since the epoch.
+    pub time: f64,
+    pub pan_id: Option<u16>,
+    pub source: u16,
+    pub destination: u16,
+    pub source_ieee: Option<String>,
+    pub info: String,
+    pub secured: bool,
+    /// The payload was decrypted and its MIC verified.
+    pub decrypted: bool,
+    pub aps: Option<ApsFrame>,
+    /// Decrypted (or plain) payload above the NWK layer, as lowercase hex.
+    pub payload: Option<String>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Lists the Zigbee frames of the 802.15.4 captures among `captures`.
+pub fn zigbee_frames(
+    captures: &[Arc<LoadedCapture>],
+    network_key: Option<&[u8; 16]>,
+) -> Result<Vec<ZigbeeFrameRow>, KcpdumpError> {
+    let captures: Vec<_> = captures
+        .iter()
+        .filter(|capture| {
+            matches!(
+                capture.header.network,
+                LINKTYPE_IEEE802_15_4_WITHFCS | LINKTYPE_IEEE802_15_4_NOFCS
+            )
+        })
+        .cloned()
+        .collect();
+    let link_types = packetlist::link_types(&captures);
+    packetlist::merged_packets(&captures)
+        .filter_map(|item| {
+            item.map(|(capture_id, number, packet)| {
+                let mac =
+                    ieee802154::MacFrame::decode(link_types[&capture_id], &packet.data).ok()?;
+                let nwk = mac.nwk()?;
+                let secured = nwk.security.is_some();
+                let plaintext = if secured {
+                    network_key.and_then(|key| nwk.decrypt(key))
+                } else {
+                    Some(nwk.payload.clone())
+                };
+                let aps = plaintext
+                    .as_deref()
+                    .filter(|_| nwk.frame_type == 0)
+                    .and_then(|payload| ApsFrame::try_from(payload).ok());
+                let source_ieee = nwk
+                    .security
+                    .as_ref()
+                    .and_then(|security| security.source)
+                    .or(nwk.source_ieee)
+                    .map(|address| ieee802154::MacAddress::Extended(address).to_string());
+                Some(ZigbeeFrameRow {
+                    capture_id,
+                    number,
+                    time: packet.header.timestamp.as_secs_f64(),
+                    pan_id: mac.dest_pan,
+                    source: nwk.source,
+                    destination: nwk.dest,
+                    source_ieee,
+                    info: nwk.describe(),
+                    secured,
+                    decrypted: secured && plaintext.is_some(),
+                    aps,
+                    payload: plaintext.map(|payload| {
+                        payload.iter().map(|byte| format!("{:02x}", byte)).collect()
+                    }),
+                })
+            })
+            .transpose()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 16] = [
+        0x01, 0x03, 0x05, 0x07, 0x09, 0x0b, 0x0d, 0x0f, 0x00, 0x02, 0x04, 0x06, 0x08, 0x0a, 0x0c,
+        0x0d,
+    ];
+
+    fn ccm_star_encrypt(nonce: &[u8; 13], header: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        let cipher = Aes128::new(&GenericArray::from(KEY));
+        let mic = cbc_mac(&cipher, nonce, header, plaintext);
+        let mut output = ctr_xor(&cipher, nonce, plaintext);
+        let first = ctr_block(&cipher, nonce, 0);
+        output.extend(mic.iter().zip(first).map(|(b, k)| b ^ k));
+        output
+    }
+
+    /// A secured NWK data frame from 0x1234 to the coordinator carrying an
+    /// APS frame for the On/Off cluster of the Home Automation profile.
+    fn secured_frame() -> Vec<u8> {
+        let source = 0x0011_2233_4455_6677u64;
+        let mut header = vec![0x08, 0x02, 0x00, 0x00, 0x34, 0x12, 0x1e, 0x2a];
+        // Level 0 on air, network key, extended nonce.
+        header.push(0x28);
+        header.extend_from_slice(&7u32.to_le_bytes());
+        header.extend_from_slice(&source.to_le_bytes());
+        header.push(0x00);
+        let aps = [
+            0x40, 0x0b, 0x06, 0x00, 0x04, 0x01, 0x01, 0x33, 0x18, 0x01, 0x0b,
+        ];
+
+        let mut nonce = [0u8; 13];
+        nonce[..8].copy_from_slice(&source.to_le_bytes());
+        nonce[8..12].copy_from_slice(&7u32.to_le_bytes());
+        nonce[12] = 0x28 | SECURITY_LEVEL_ENC_MIC_32;
+        let mut authenticated = header.clone();
+        authenticated[8] = nonce[12];
+        let mut frame = header;
+        frame.extend(ccm_star_encrypt(&nonce, &authenticated, &aps));
+        frame
+    }
+
+    #[test]
+    fn test_nwk_decryption() {
+        let frame = secured_frame();
+        let nwk = NwkFrame::try_from(frame.as_slice()).unwrap();
+        assert_eq!(nwk.source, 0x1234);
+        assert_eq!(nwk.dest, 0x0000);
+        assert_eq!(nwk.describe(), "Data, Dst: 0x0000, Src: 0x1234, Secured");
+        let security = nwk.security.as_ref().unwrap();
+        assert_eq!(security.frame_counter, 7);
+        assert_eq!(security.key_sequence, Some(0));
+
+        let plaintext = nwk.decrypt(&KEY).unwrap();
+        let aps = ApsFrame::try_from(plaintext.as_slice()).unwrap();
+        assert_eq!(aps.dest_endpoint, Some(0x0b));
+        assert_eq!(aps.cluster, Some(0x0006));
+        assert_eq!(aps.profile, Some(0x0104));
+        assert_eq!(aps.source_endpoint, Some(0x01));
+        assert_eq!(aps.counter, 0x33);
+        assert_eq!(aps.payload, [0x18, 0x01, 0x0b]);
+
+        let mut wrong = KEY;
+        wrong[0] ^= 0xff;
+        assert!(nwk.decrypt(&wrong).is_none());
+    }
+
+    #[test]
+    fn test_zigbee_in_mac_frame() {
+        let mut data = vec![0x41, 0x88, 0x05, 0x62, 0x1a, 0x00, 0x00, 0x34, 0x12];
+        data.extend(secured_frame());
+        data.extend_from_slice(&[0, 0]);
+        let summary = ieee802154::summarize(LINKTYPE_IEEE802_15_4_WITHFCS, &data);
+        assert_eq!(summary.protocol, "ZigBee");
+        assert_eq!(summary.source, "0x1234");
+        assert_eq!(summary.info, "Data, Dst: 0x0000, Src: 0x1234, Secured");
+    }
+
+    #[test]
+    fn test_parse_network_key() {
+        let key = parse_network_key("01:03:05:07:09:0b:0d:0f:00:02:04:06:08:0a:0c:0d").unwrap();
+        assert_eq!(key, KEY);
+        assert!(parse_network_key("0102").is_err());
+        assert!(parse_network_key(&"zz".repeat(16)).is_err());
+    }
+}